@@ -0,0 +1,130 @@
+// ABOUTME: Per-turn token budget — asks before another round-trip once a turn has used [agent] per_turn_token_budget.
+// ABOUTME: The question goes through the ask_user prompt; its answer continues, stops asking, or ends the turn.
+
+/// Answers offered when a turn crosses its budget.
+pub const CONTINUE: &str = "continue";
+pub const CONTINUE_UNASKED: &str = "continue without asking again this turn";
+pub const STOP: &str = "stop";
+
+/// Stands in for the tool calls left unrun when the user stops the turn.
+pub const STOPPED_RESULT: &str = "Not run: the user stopped the turn at its token budget";
+
+/// The `tool_call_id` the budget question is asked under.
+pub const BUDGET_QUESTION_ID: &str = "token-budget";
+
+/// What the user chose when asked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetChoice {
+    Continue,
+    /// Continue, and don't ask again for the rest of the turn.
+    ContinueUnasked,
+    Stop,
+}
+
+impl BudgetChoice {
+    /// Read an answer to the budget question. Anything but a continue
+    /// stops, including no answer at all.
+    pub fn from_answer(answer: &str) -> Self {
+        match answer.trim().to_lowercase().as_str() {
+            CONTINUE_UNASKED => BudgetChoice::ContinueUnasked,
+            CONTINUE => BudgetChoice::Continue,
+            _ => BudgetChoice::Stop,
+        }
+    }
+}
+
+/// Tokens used so far in one turn, against an optional budget.
+#[derive(Debug, Clone, Copy)]
+pub struct TurnBudget {
+    limit: Option<u64>,
+    used: u64,
+}
+
+impl TurnBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    /// Count a response's tokens: input, output, and cache reads and writes.
+    pub fn record(&mut self, tokens: u64) {
+        self.used += tokens;
+    }
+
+    /// Whether to ask before the next round-trip.
+    pub fn exceeded(&self) -> bool {
+        self.limit.is_some_and(|limit| self.used >= limit)
+    }
+
+    /// Don't ask again this turn.
+    pub fn stop_asking(&mut self) {
+        self.limit = None;
+    }
+
+    pub fn question(&self) -> String {
+        format!("This turn has used {} tokens. Continue?", approx(self.used))
+    }
+
+    pub fn options() -> Vec<String> {
+        vec![
+            CONTINUE.to_string(),
+            CONTINUE_UNASKED.to_string(),
+            STOP.to_string(),
+        ]
+    }
+
+    /// The note shown when the user stops the turn.
+    pub fn stopped_notice(&self) -> String {
+        format!(
+            "Stopped the turn after {} tokens ([agent] per_turn_token_budget)",
+            approx(self.used)
+        )
+    }
+}
+
+/// "~85k" for 85,123 tokens.
+fn approx(tokens: u64) -> String {
+    if tokens >= 1_000 {
+        format!("~{}k", (tokens + 500) / 1_000)
+    } else {
+        format!("~{}", tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_the_budget_asks_until_told_not_to() {
+        let mut budget = TurnBudget::new(Some(1_000));
+        budget.record(600);
+        assert!(!budget.exceeded());
+        budget.record(84_600);
+        assert!(budget.exceeded());
+        assert_eq!(
+            budget.question(),
+            "This turn has used ~85k tokens. Continue?"
+        );
+        budget.stop_asking();
+        assert!(!budget.exceeded());
+
+        assert!(!TurnBudget::new(None).exceeded());
+    }
+
+    #[test]
+    fn answers_map_to_choices() {
+        assert_eq!(
+            BudgetChoice::from_answer("continue"),
+            BudgetChoice::Continue
+        );
+        assert_eq!(
+            BudgetChoice::from_answer(CONTINUE_UNASKED),
+            BudgetChoice::ContinueUnasked
+        );
+        assert_eq!(BudgetChoice::from_answer("stop"), BudgetChoice::Stop);
+        assert_eq!(
+            BudgetChoice::from_answer("[No response received]"),
+            BudgetChoice::Stop
+        );
+    }
+}
@@ -0,0 +1,485 @@
+// ABOUTME: Library entry point — AgentBuilder wires client, tools, approvals, and the loop without a TUI.
+// ABOUTME: The resulting Agent streams AgentEvents per message and takes approval/question answers.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures::Stream;
+use tokio::sync::{Mutex, mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use mux::prelude::*;
+
+use crate::agent::compaction::SummaryDecision;
+use crate::agent::effort::Reasoning;
+use crate::agent::fallback::Fallbacks;
+use crate::agent::pins::Pins;
+use crate::agent::shutdown::ShutdownFlag;
+use crate::agent::timeouts::RequestTimeouts;
+use crate::agent::{AgentLoopParams, create_client, run_agent_loop};
+use crate::approval::{ApprovalDecision, ApprovalEngine};
+use crate::config::{Config, SessionFormat};
+use crate::prompt::{
+    SystemPromptParams, build_system_prompt, hostname, load_context_files, load_skill_files,
+};
+use crate::session::AutoSave;
+use crate::tools::ask_user::AskUserTool;
+use crate::tools::catalog::register_enabled;
+use crate::tools::roster::McpRoster;
+use crate::tui::state::{AgentEvent, UserEvent};
+
+/// Builds an [`Agent`] from a [`Config`], the way `App::run` does for the TUI:
+/// built-in tools, the approval engine, the LLM client, and the system prompt.
+pub struct AgentBuilder {
+    config: Config,
+    workspace_dir: PathBuf,
+    client: Option<Arc<dyn LlmClient>>,
+    approvals_path: PathBuf,
+    system_prompt: Option<String>,
+    initial_messages: Vec<Message>,
+}
+
+impl AgentBuilder {
+    /// Start from a config, using the current directory as the workspace.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            workspace_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            client: None,
+            approvals_path: Config::approvals_path(),
+            system_prompt: None,
+            initial_messages: Vec::new(),
+        }
+    }
+
+    /// Directory the tools run in and context files are loaded from.
+    pub fn with_workspace(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.workspace_dir = dir.into();
+        self
+    }
+
+    /// Use this client instead of creating one from `[llm]`.
+    pub fn with_client(mut self, client: Arc<dyn LlmClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Read and persist the allowlist here instead of the default approvals.json.
+    pub fn with_approvals_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.approvals_path = path.into();
+        self
+    }
+
+    /// Use a fixed system prompt instead of building one from the workspace.
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Continue an earlier conversation.
+    pub fn with_history(mut self, messages: Vec<Message>) -> Self {
+        self.initial_messages = messages;
+        self
+    }
+
+    /// Register tools, create the engine and client, and start the agent loop.
+    pub async fn build(self) -> anyhow::Result<Agent> {
+        let client = match self.client {
+            Some(client) => client,
+            None => create_client(&self.config.llm)?,
+        };
+
+        let registry = Registry::new();
+        let tools_config = &self.config.tools;
+        register_enabled(&registry, tools_config, BashTool).await;
+        register_enabled(&registry, tools_config, ReadFileTool).await;
+        register_enabled(&registry, tools_config, WriteFileTool).await;
+        register_enabled(&registry, tools_config, ListFilesTool).await;
+        register_enabled(&registry, tools_config, SearchTool).await;
+        register_enabled(&registry, tools_config, AskUserTool).await;
+
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(
+                self.approvals_path,
+                self.config.permissions.bypass_approvals,
+            )?
+            .with_max_command_length(self.config.approval.max_command_length)
+            .with_redaction(self.config.privacy.redact_params)
+            .with_workspace(&self.workspace_dir),
+        );
+
+        let workspace_dir = self.workspace_dir.to_string_lossy().to_string();
+        let tool_defs = registry.to_definitions().await;
+        let prompt_params = SystemPromptParams {
+            tool_names: tool_defs.iter().map(|d| d.name.clone()).collect(),
+            tool_summaries: tool_defs
+                .iter()
+                .map(|d| (d.name.clone(), d.description.clone()))
+                .collect(),
+            tool_origins: HashMap::new(),
+            mcp_descriptions: HashMap::new(),
+            workspace_dir: workspace_dir.clone(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            shell: std::env::var("SHELL").unwrap_or_default(),
+            hostname: hostname(),
+            privacy: self.config.privacy.clone(),
+            model: self.config.llm.model.clone(),
+            context_files: load_context_files(&workspace_dir),
+            skill_files: load_skill_files(&workspace_dir, &self.config.skills).files,
+        };
+        let system_prompt = self
+            .system_prompt
+            .unwrap_or_else(|| build_system_prompt(&prompt_params));
+
+        let (user_tx, user_rx) = mpsc::channel::<UserEvent>(16);
+        let (agent_tx, agent_rx) = mpsc::channel::<AgentEvent>(64);
+        let shutdown = ShutdownFlag::default();
+
+        let handle = tokio::spawn(run_agent_loop(
+            AgentLoopParams {
+                client,
+                registry,
+                engine,
+                model: self.config.llm.model.clone(),
+                max_tokens: self.config.llm.max_tokens,
+                approval_timeout_seconds: self.config.approval.timeout_seconds,
+                system_prompt,
+                prompt_params,
+                initial_messages: self.initial_messages,
+                // No logger and no session.json: embedders own persistence.
+                session_logger: None,
+                workspace_dir: self.workspace_dir,
+                compaction_config: self.config.compaction.clone(),
+                existing_created_at: None,
+                streaming: self.config.llm.streaming,
+                tools_config: self.config.tools.clone(),
+                session_format: SessionFormat::Jsonl,
+                reasoning: Reasoning::from_config(&self.config.llm),
+                autosave: AutoSave::disabled(),
+                observe: false,
+                timeouts: RequestTimeouts::from_config(&self.config.llm),
+                mcp: McpRoster::default(),
+                mcp_arrivals: None,
+                shutdown: shutdown.clone(),
+                pins: Pins::default(),
+                token_budget: None,
+                fallbacks: Fallbacks::from_config(&self.config.llm),
+                pending_tool_calls: self.config.sessions.pending_tool_calls,
+                context_note: None,
+            },
+            user_rx,
+            agent_tx,
+        ));
+
+        Ok(Agent {
+            user_tx,
+            events: Arc::new(Mutex::new(agent_rx)),
+            pending: Arc::new(std::sync::Mutex::new(Pending::default())),
+            shutdown,
+            handle,
+        })
+    }
+}
+
+/// Responders for the approval, question, or summary review the current turn
+/// is waiting on.
+#[derive(Default)]
+struct Pending {
+    approval: Option<oneshot::Sender<ApprovalDecision>>,
+    question: Option<oneshot::Sender<String>>,
+    review: Option<oneshot::Sender<SummaryDecision>>,
+}
+
+/// A running agent loop with no TUI attached.
+pub struct Agent {
+    user_tx: mpsc::Sender<UserEvent>,
+    events: Arc<Mutex<mpsc::Receiver<AgentEvent>>>,
+    pending: Arc<std::sync::Mutex<Pending>>,
+    shutdown: ShutdownFlag,
+    handle: JoinHandle<()>,
+}
+
+impl Agent {
+    /// Send a user message and stream the turn's events, ending after `Done`.
+    ///
+    /// When the turn needs an approval or an answer, the stream yields the
+    /// event and waits; reply with [`Agent::answer_approval`] or
+    /// [`Agent::answer_question`] (or through the event's own responder).
+    pub fn send(
+        &self,
+        message: impl Into<String>,
+    ) -> impl Stream<Item = AgentEvent> + Send + 'static {
+        let state = TurnStream {
+            unsent: Some(message.into()),
+            user_tx: self.user_tx.clone(),
+            events: Arc::clone(&self.events),
+            pending: Arc::clone(&self.pending),
+        };
+        futures::stream::unfold(Some(state), |state| async move {
+            let mut state = state?;
+            if let Some(message) = state.unsent.take() {
+                state.user_tx.send(UserEvent::Message(message)).await.ok()?;
+            }
+            let event = state.events.lock().await.recv().await?;
+            let done = matches!(event, AgentEvent::Done);
+            let event = hold_responders(event, &state.pending);
+            Some((event, (!done).then_some(state)))
+        })
+    }
+
+    /// Answer the tool call approval the current turn is waiting on.
+    pub fn answer_approval(&self, decision: ApprovalDecision) -> anyhow::Result<()> {
+        let responder = self
+            .pending
+            .lock()
+            .expect("pending lock poisoned")
+            .approval
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("no approval is pending"))?;
+        responder
+            .send(decision)
+            .map_err(|_| anyhow::anyhow!("the turn is no longer waiting for approval"))
+    }
+
+    /// Answer the ask_user question the current turn is waiting on.
+    pub fn answer_question(&self, answer: impl Into<String>) -> anyhow::Result<()> {
+        let responder = self
+            .pending
+            .lock()
+            .expect("pending lock poisoned")
+            .question
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("no question is pending"))?;
+        responder
+            .send(answer.into())
+            .map_err(|_| anyhow::anyhow!("the turn is no longer waiting for an answer"))
+    }
+
+    /// Answer the compaction summary review (`[compaction] review = "ask"`)
+    /// the current turn is waiting on.
+    pub fn answer_summary_review(&self, decision: SummaryDecision) -> anyhow::Result<()> {
+        let responder = self
+            .pending
+            .lock()
+            .expect("pending lock poisoned")
+            .review
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("no summary review is pending"))?;
+        responder
+            .send(decision)
+            .map_err(|_| anyhow::anyhow!("the turn is no longer waiting for a review"))
+    }
+
+    /// Abort the turn in progress.
+    pub async fn cancel(&self) {
+        let _ = self.user_tx.send(UserEvent::Cancel).await;
+    }
+
+    /// Stop the agent loop and wait for it to exit.
+    pub async fn shutdown(self) {
+        self.shutdown.request();
+        let _ = self.user_tx.send(UserEvent::Quit).await;
+        let _ = self.handle.await;
+    }
+}
+
+/// State carried between items of a `send` stream.
+struct TurnStream {
+    unsent: Option<String>,
+    user_tx: mpsc::Sender<UserEvent>,
+    events: Arc<Mutex<mpsc::Receiver<AgentEvent>>>,
+    pending: Arc<std::sync::Mutex<Pending>>,
+}
+
+/// Park the loop's responder in `pending` so the `answer_*` methods can use
+/// it, and hand the caller a stand-in that forwards to the same slot.
+/// Whichever answers first wins.
+fn hold_responders(event: AgentEvent, pending: &Arc<std::sync::Mutex<Pending>>) -> AgentEvent {
+    match event {
+        AgentEvent::ToolCallNeedsApproval {
+            tool_call_id,
+            description,
+            pattern,
+            similar,
+            scopes,
+            tool_name,
+            responder,
+        } => {
+            pending.lock().expect("pending lock poisoned").approval = Some(responder);
+            let (tx, rx) = oneshot::channel();
+            let pending = Arc::clone(pending);
+            tokio::spawn(async move {
+                if let Ok(decision) = rx.await
+                    && let Some(responder) = pending
+                        .lock()
+                        .expect("pending lock poisoned")
+                        .approval
+                        .take()
+                {
+                    let _ = responder.send(decision);
+                }
+            });
+            AgentEvent::ToolCallNeedsApproval {
+                tool_call_id,
+                description,
+                pattern,
+                similar,
+                scopes,
+                tool_name,
+                responder: tx,
+            }
+        }
+        AgentEvent::AskUser {
+            question,
+            tool_call_id,
+            options,
+            style,
+            responder,
+        } => {
+            pending.lock().expect("pending lock poisoned").question = Some(responder);
+            let (tx, rx) = oneshot::channel();
+            let pending = Arc::clone(pending);
+            tokio::spawn(async move {
+                if let Ok(answer) = rx.await
+                    && let Some(responder) = pending
+                        .lock()
+                        .expect("pending lock poisoned")
+                        .question
+                        .take()
+                {
+                    let _ = responder.send(answer);
+                }
+            });
+            AgentEvent::AskUser {
+                question,
+                tool_call_id,
+                options,
+                style,
+                responder: tx,
+            }
+        }
+        AgentEvent::CompactionSummaryReady { summary, responder } => {
+            pending.lock().expect("pending lock poisoned").review = Some(responder);
+            let (tx, rx) = oneshot::channel();
+            let pending = Arc::clone(pending);
+            tokio::spawn(async move {
+                if let Ok(decision) = rx.await
+                    && let Some(responder) =
+                        pending.lock().expect("pending lock poisoned").review.take()
+                {
+                    let _ = responder.send(decision);
+                }
+            });
+            AgentEvent::CompactionSummaryReady {
+                summary,
+                responder: tx,
+            }
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StreamingMode;
+    use crate::testing::{MockLlmClient, ScriptedResponse};
+    use futures::StreamExt;
+
+    async fn agent(client: Arc<MockLlmClient>, dir: &tempfile::TempDir) -> Agent {
+        let mut config = Config::default();
+        config.llm.streaming = StreamingMode::Off;
+        config.compaction.enabled = false;
+        AgentBuilder::new(config)
+            .with_workspace(dir.path())
+            .with_approvals_path(dir.path().join("approvals.json"))
+            .with_system_prompt("test")
+            .with_client(client)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn text_turn_streams_deltas_and_ends_with_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new().text("Hello there"),
+        ]));
+        let agent = agent(client, &dir).await;
+
+        let events: Vec<AgentEvent> = agent.send("hi").collect().await;
+        let text: String = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::TextDelta(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello there");
+        assert!(matches!(events.last(), Some(AgentEvent::Done)));
+        agent.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn approval_is_answered_through_the_handle() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new().tool_use(
+                "c1",
+                "bash",
+                serde_json::json!({"command": "rm -rf scratch"}),
+            ),
+            ScriptedResponse::new().text("ok, skipped"),
+        ]));
+        let agent = agent(client.clone(), &dir).await;
+
+        let mut events = Box::pin(agent.send("clean up"));
+        let mut denied = false;
+        while let Some(event) = events.next().await {
+            match event {
+                AgentEvent::ToolCallNeedsApproval { .. } => {
+                    agent.answer_approval(ApprovalDecision::Deny).unwrap();
+                }
+                AgentEvent::ToolCallDenied { .. } => denied = true,
+                _ => {}
+            }
+        }
+        assert!(denied);
+        assert!(agent.answer_approval(ApprovalDecision::Deny).is_err());
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 2);
+        let second = serde_json::to_string(&requests[1].messages).unwrap();
+        assert!(second.contains("Denied by user"));
+        agent.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn question_is_answered_through_the_handle() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new().tool_use(
+                "q1",
+                "ask_user",
+                serde_json::json!({"question": "Which color?"}),
+            ),
+            ScriptedResponse::new().text("Blue it is"),
+        ]));
+        let agent = agent(client.clone(), &dir).await;
+
+        let mut events = Box::pin(agent.send("paint it"));
+        while let Some(event) = events.next().await {
+            if let AgentEvent::AskUser { question, .. } = event {
+                assert_eq!(question, "Which color?");
+                agent.answer_question("blue").unwrap();
+            }
+        }
+
+        let second = serde_json::to_string(&client.requests()[1].messages).unwrap();
+        assert!(second.contains("blue"));
+        agent.shutdown().await;
+    }
+}
@@ -1,17 +1,42 @@
 // ABOUTME: Conversation compaction — summarizes older messages when context limits approach.
 // ABOUTME: Reduces conversation history using LLM summarization to stay within token budgets.
 
+use std::collections::BTreeSet;
 use std::sync::Arc;
 
+use futures::StreamExt;
 use mux::prelude::*;
 
-use crate::config::CompactionConfig;
+use crate::agent::pins::{Pins, message_text};
+use crate::config::{CompactionConfig, CompactionRetain, OnResume};
 
 pub const SUMMARY_PREFIX: &str = "Another language model started to solve this problem and produced a summary of its thinking process:";
 
 /// Default token budget for retained user messages after compaction.
 pub const DEFAULT_USER_MESSAGE_BUDGET_TOKENS: usize = 20_000;
 
+/// The user's answer when reviewing a compaction summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SummaryDecision {
+    /// Use the summary as written.
+    Accept,
+    /// Use this edited summary instead.
+    Edit(String),
+    /// Keep the full history for now; compaction is retried at the next check.
+    Skip,
+}
+
+impl SummaryDecision {
+    /// Stable name for the event stream.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SummaryDecision::Accept => "accept",
+            SummaryDecision::Edit(_) => "edit",
+            SummaryDecision::Skip => "skip",
+        }
+    }
+}
+
 /// Fraction of the context window that triggers automatic compaction.
 const COMPACTION_THRESHOLD_RATIO: f64 = 0.9;
 
@@ -71,6 +96,166 @@ pub fn needs_compaction(messages: &[Message], model: &str, config: &CompactionCo
     current_tokens > limit
 }
 
+/// The most history `model` should be sent at once: its compaction limit,
+/// which leaves the rest of the window for the prompt, tools, and reply.
+pub fn history_budget(model: &str, config: &CompactionConfig) -> usize {
+    auto_compact_limit(
+        context_window_for_model(model),
+        config.threshold_token_limit,
+    ) as usize
+}
+
+/// Index of the earliest message from which the rest of the history fits in
+/// `budget` tokens and that starts a turn: a user message that doesn't carry
+/// tool results, so no result is split from its call. `messages.len()` when
+/// not even the last turn fits.
+pub fn recent_turns_start(messages: &[Message], budget: usize) -> usize {
+    let mut start = messages.len();
+    let mut tokens = 0;
+    for (i, msg) in messages.iter().enumerate().rev() {
+        tokens += approx_messages_tokens(std::slice::from_ref(msg));
+        if tokens > budget {
+            break;
+        }
+        let carries_results = msg
+            .content
+            .iter()
+            .any(|block| matches!(block, ContentBlock::ToolResult { .. }));
+        if matches!(msg.role, Role::User) && !carries_results {
+            start = i;
+        }
+    }
+    start
+}
+
+/// How a resumed history was made to fit the current model's window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeFit {
+    /// It fits as it is.
+    Fits,
+    /// The agent loop summarizes it before the first message.
+    Compact,
+    /// Older turns were dropped.
+    Truncated { kept: usize, total: usize },
+}
+
+impl ResumeFit {
+    /// A line for the startup message, or None when nothing was done.
+    pub fn describe(&self, model: &str) -> Option<String> {
+        match self {
+            ResumeFit::Fits => None,
+            ResumeFit::Compact => Some(format!(
+                "Resumed session is too large for {}; compacting it before you continue",
+                model
+            )),
+            ResumeFit::Truncated { kept, total } => Some(format!(
+                "Resumed session is too large for {}; kept the last {} of {} messages",
+                model, kept, total
+            )),
+        }
+    }
+}
+
+/// Apply `[compaction] on_resume` to a resumed history that may exceed the
+/// current model's budget, e.g. after switching to a smaller model. Errors
+/// with `on_resume = "error"`. With compaction off, `"compact"` truncates
+/// instead, since the loop won't summarize. Truncating keeps the messages in `pins` ahead
+/// of the recent turns, while they fit, and moves the pins to match.
+pub fn fit_resumed_history(
+    messages: Vec<Message>,
+    model: &str,
+    config: &CompactionConfig,
+    pins: &Pins,
+) -> anyhow::Result<(Vec<Message>, ResumeFit)> {
+    let budget = history_budget(model, config);
+    let tokens = approx_messages_tokens(&messages);
+    if tokens <= budget {
+        return Ok((messages, ResumeFit::Fits));
+    }
+    match config.on_resume {
+        OnResume::Compact if config.enabled => Ok((messages, ResumeFit::Compact)),
+        OnResume::Compact | OnResume::Truncate => {
+            let total = messages.len();
+            let pinned = pins.indexes();
+            // Pins among the recent turns need no room of their own.
+            let window = recent_turns_start(&messages, budget);
+            let pinned_tokens: usize = pinned
+                .iter()
+                .filter(|&&p| p < window)
+                .flat_map(|&p| pinned_span(&messages, p))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|i| approx_token_count(&message_text(&messages[i])))
+                .sum();
+            // Pins that would crowd out every recent turn aren't kept.
+            let reserved = if pinned_tokens < budget {
+                pinned_tokens
+            } else {
+                0
+            };
+            let start = recent_turns_start(&messages, budget - reserved);
+            let mut kept = Vec::new();
+            let mut kept_pins = Vec::new();
+            if reserved > 0 {
+                let older: BTreeSet<usize> = pinned
+                    .iter()
+                    .filter(|&&p| p < start)
+                    .flat_map(|&p| pinned_span(&messages, p))
+                    .collect();
+                for i in older {
+                    if pinned.contains(&i) {
+                        kept_pins.push(kept.len());
+                    }
+                    // Only the text: a reply's tool calls lose their results here.
+                    let text = message_text(&messages[i]);
+                    kept.push(match messages[i].role {
+                        Role::Assistant => Message::assistant(text),
+                        _ => Message::user(text),
+                    });
+                }
+            }
+            let offset = kept.len();
+            kept_pins.extend(
+                pinned
+                    .iter()
+                    .filter(|&&p| p >= start)
+                    .map(|p| offset + p - start),
+            );
+            kept.extend_from_slice(&messages[start..]);
+            pins.set(kept_pins);
+            let fit = ResumeFit::Truncated {
+                kept: kept.len(),
+                total,
+            };
+            Ok((kept, fit))
+        }
+        OnResume::Error => anyhow::bail!(
+            "The saved session is ~{} tokens, more than {} can take (~{}). \
+             Start with --fresh, or set [compaction] on_resume to \"compact\" or \"truncate\".",
+            tokens,
+            model,
+            budget
+        ),
+    }
+}
+
+/// The messages a pin at `index` keeps in a truncated history: a typed
+/// message alone, or a reply with the user message before it, so the
+/// history still starts with the user. Empty when there's nothing to keep.
+fn pinned_span(messages: &[Message], index: usize) -> Vec<usize> {
+    let Some((role, _)) = messages.get(index).and_then(dialogue_turn) else {
+        return Vec::new();
+    };
+    if role == Role::User {
+        return vec![index];
+    }
+    messages[..index]
+        .iter()
+        .rposition(|m| dialogue_turn(m).is_some_and(|(role, _)| role == Role::User))
+        .map(|question| vec![question, index])
+        .unwrap_or_default()
+}
+
 /// Extract text content from user messages, skipping summary messages.
 pub fn collect_user_messages(messages: &[Message]) -> Vec<String> {
     messages
@@ -100,54 +285,206 @@ pub fn collect_user_messages(messages: &[Message]) -> Vec<String> {
         .collect()
 }
 
+/// Extract the text of user and assistant messages, in order, for
+/// `retain = "both"`. Summary messages and messages without text (tool
+/// calls and results) are skipped.
+pub fn collect_dialogue(messages: &[Message]) -> Vec<(Role, String)> {
+    messages.iter().filter_map(dialogue_turn).collect()
+}
+
+/// The turns compaction keeps for `retain`, and which of them (by position
+/// in the returned list) are the messages at the `pinned` indexes. A pinned
+/// reply is kept even when `retain` only keeps user messages.
+pub fn collect_turns(
+    messages: &[Message],
+    retain: CompactionRetain,
+    pinned: &[usize],
+) -> (Vec<(Role, String)>, Vec<usize>) {
+    let mut turns = Vec::new();
+    let mut pinned_turns = Vec::new();
+    for (i, msg) in messages.iter().enumerate() {
+        let Some((role, text)) = dialogue_turn(msg) else {
+            continue;
+        };
+        let is_pinned = pinned.contains(&i);
+        if role == Role::Assistant && retain == CompactionRetain::User && !is_pinned {
+            continue;
+        }
+        if is_pinned {
+            pinned_turns.push(turns.len());
+        }
+        turns.push((role, text));
+    }
+    (turns, pinned_turns)
+}
+
+/// The role and text of a user or assistant message, or None for a summary
+/// or a message without text.
+fn dialogue_turn(msg: &Message) -> Option<(Role, String)> {
+    let texts: Vec<&str> = msg
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } if !text.is_empty() => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    if texts.is_empty() {
+        return None;
+    }
+    let combined = texts.join("\n");
+    if msg.role == Role::Assistant {
+        Some((Role::Assistant, combined))
+    } else if msg.role == Role::User && !combined.starts_with(SUMMARY_PREFIX) {
+        Some((Role::User, combined))
+    } else {
+        None
+    }
+}
+
+/// A compacted history and where its pins ended up.
+#[derive(Debug, Clone)]
+pub struct Compacted {
+    pub messages: Vec<Message>,
+    /// Indexes in `messages` of the pinned turns that were kept.
+    pub pinned: Vec<usize>,
+}
+
 /// Build compacted conversation history from a summary and recent user messages.
 ///
 /// Selects user messages backward from the most recent, within the given token budget.
 /// If a message exceeds the remaining budget, it is truncated with a marker.
+/// The messages at the `pinned` positions are kept verbatim ahead of that,
+/// as long as the pins fit in the budget together.
 /// Returns messages in chronological order: selected user messages, then summary message.
 pub fn build_compacted_history(
     user_messages: &[String],
     summary_text: &str,
     max_user_tokens: usize,
-) -> Vec<Message> {
-    let mut selected: Vec<Message> = Vec::new();
-    let mut remaining_budget = max_user_tokens;
+    pinned: &[usize],
+) -> Compacted {
+    let turns: Vec<(Role, String)> = user_messages
+        .iter()
+        .map(|text| (Role::User, text.clone()))
+        .collect();
+    build_compacted_dialogue(&turns, summary_text, max_user_tokens, pinned)
+}
 
-    // Walk backward through user messages, selecting within budget.
-    for text in user_messages.iter().rev() {
+/// Like [`build_compacted_history`], but keeps the recent dialogue from
+/// [`collect_dialogue`]: user and assistant messages interleaved, the oldest
+/// trimmed first to fit `max_tokens`. The kept history never starts with an
+/// assistant message, since providers expect the user to speak first, so a
+/// pinned reply is kept along with the user message before it.
+pub fn build_compacted_dialogue(
+    turns: &[(Role, String)],
+    summary_text: &str,
+    max_tokens: usize,
+    pinned: &[usize],
+) -> Compacted {
+    let mut kept: Vec<Option<String>> = vec![None; turns.len()];
+    let mut is_pin = vec![false; turns.len()];
+    let mut remaining_budget = max_tokens;
+
+    // Pinned messages first, newest first, whole or not at all.
+    for &i in pinned.iter().rev() {
+        let Some((role, text)) = turns.get(i) else {
+            continue;
+        };
+        if kept[i].is_some() {
+            // Already kept as the question of a later pinned reply.
+            is_pin[i] = true;
+            continue;
+        }
+        let question = if *role == Role::Assistant {
+            turns[..i]
+                .iter()
+                .rposition(|(role, _)| *role == Role::User)
+                .filter(|&q| kept[q].is_none())
+        } else {
+            None
+        };
+        let tokens =
+            approx_token_count(text) + question.map_or(0, |q| approx_token_count(&turns[q].1));
+        if tokens > remaining_budget {
+            continue;
+        }
+        remaining_budget -= tokens;
+        kept[i] = Some(text.clone());
+        is_pin[i] = true;
+        if let Some(q) = question {
+            kept[q] = Some(turns[q].1.clone());
+        }
+    }
+
+    // Then walk backward through the rest, selecting within budget.
+    for (i, (_, text)) in turns.iter().enumerate().rev() {
+        if kept[i].is_some() {
+            continue;
+        }
         let tokens = approx_token_count(text);
-        if tokens <= remaining_budget {
-            selected.push(Message::user(text.clone()));
+        let text = if tokens <= remaining_budget {
             remaining_budget -= tokens;
+            text.clone()
         } else if remaining_budget > 0 {
             // Truncate this message to fit within remaining budget.
             let char_limit = remaining_budget * 4;
             let truncated: String = text.chars().take(char_limit).collect();
             let omitted = tokens.saturating_sub(remaining_budget);
-            let truncated_msg = format!("{}...{} tokens truncated...", truncated, omitted);
-            selected.push(Message::user(truncated_msg));
             remaining_budget = 0;
-        }
-        // If remaining_budget is 0, skip older messages.
+            format!("{}...{} tokens truncated...", truncated, omitted)
+        } else {
+            // The budget is spent; skip older messages.
+            continue;
+        };
+        kept[i] = Some(text);
     }
 
-    // Reverse to restore chronological order.
-    selected.reverse();
+    let mut selected: Vec<(Message, bool)> = turns
+        .iter()
+        .zip(kept)
+        .zip(is_pin)
+        .filter_map(|(((role, _), text), pin)| {
+            let message = match role {
+                Role::Assistant => Message::assistant(text?),
+                _ => Message::user(text?),
+            };
+            Some((message, pin))
+        })
+        .collect();
+    let leading_answers = selected
+        .iter()
+        .take_while(|(m, _)| m.role == Role::Assistant)
+        .count();
+    selected.drain(..leading_answers);
+
+    let pinned = selected
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, pin))| *pin)
+        .map(|(i, _)| i)
+        .collect();
+    let mut messages: Vec<Message> = selected.into_iter().map(|(m, _)| m).collect();
 
     // Append the summary as a user message with the SUMMARY_PREFIX.
     let summary_content = format!("{}\n\n{}", SUMMARY_PREFIX, summary_text);
-    selected.push(Message::user(summary_content));
+    messages.push(Message::user(summary_content));
 
-    selected
+    Compacted { messages, pinned }
 }
 
+/// Summary tokens between progress reports while a summary streams in.
+pub const PROGRESS_STEP_TOKENS: usize = 50;
+
 /// Run compaction: send the full conversation to the LLM with a summarization prompt
-/// and return the summary text.
+/// and return the summary text. The summary is streamed, and `on_progress` is
+/// called with its approximate size every [`PROGRESS_STEP_TOKENS`] tokens; a
+/// provider that can't stream is asked again without streaming.
 pub async fn run_compaction(
     client: &Arc<dyn LlmClient>,
     model: &str,
     max_tokens: u32,
     messages: &[Message],
+    on_progress: impl FnMut(usize),
 ) -> anyhow::Result<String> {
     // Build a request with the full conversation plus the summarization prompt.
     let mut compaction_messages: Vec<Message> = messages.to_vec();
@@ -157,8 +494,44 @@ pub async fn run_compaction(
         .max_tokens(max_tokens)
         .messages(compaction_messages);
 
-    let response = client.create_message(&request).await?;
-    Ok(response.text())
+    match collect_summary(client.create_message_stream(&request), on_progress).await? {
+        Some(summary) => Ok(summary),
+        None => Ok(client.create_message(&request).await?.text()),
+    }
+}
+
+/// Accumulate a streamed summary's text, reporting its approximate token
+/// count each time it grows by another [`PROGRESS_STEP_TOKENS`]. None when
+/// the stream failed before sending anything; an error after that fails.
+pub async fn collect_summary<S, E>(
+    stream: S,
+    mut on_progress: impl FnMut(usize),
+) -> anyhow::Result<Option<String>>
+where
+    S: futures::Stream<Item = Result<StreamEvent, E>>,
+    E: Into<anyhow::Error>,
+{
+    let mut stream = std::pin::pin!(stream);
+    let mut summary = String::new();
+    let mut received_any = false;
+    let mut reported = 0;
+    while let Some(event) = stream.next().await {
+        let event = match event {
+            Ok(event) => event,
+            Err(_) if !received_any => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        received_any = true;
+        if let StreamEvent::ContentBlockDelta { text, .. } = event {
+            summary.push_str(&text);
+            let tokens = approx_token_count(&summary);
+            if tokens >= reported + PROGRESS_STEP_TOKENS {
+                reported = tokens - tokens % PROGRESS_STEP_TOKENS;
+                on_progress(tokens);
+            }
+        }
+    }
+    Ok(Some(summary))
 }
 
 #[cfg(test)]
@@ -285,7 +658,7 @@ mod tests {
         // Budget of 10 tokens = 40 bytes. "recent message" = 14 bytes = 3 tokens,
         // "middle message" = 14 bytes = 3 tokens, "old message" = 11 bytes = 2 tokens.
         // Total = 8 tokens, fits in budget.
-        let result = build_compacted_history(&user_messages, "summary", 10);
+        let result = build_compacted_history(&user_messages, "summary", 10, &[]).messages;
 
         // Should have all 3 user messages + 1 summary = 4 messages.
         assert_eq!(result.len(), 4);
@@ -306,7 +679,7 @@ mod tests {
             "y".repeat(40),  // 10 tokens
         ];
         // Budget = 15 tokens. "y" (10 tokens) fits. "x" (50 tokens) has 5 token budget remaining.
-        let result = build_compacted_history(&user_messages, "summary text", 15);
+        let result = build_compacted_history(&user_messages, "summary text", 15, &[]).messages;
 
         // Should have: truncated "x" message, "y" message, summary = 3 messages.
         assert_eq!(result.len(), 3);
@@ -322,7 +695,7 @@ mod tests {
     #[test]
     fn build_compacted_history_appends_summary_with_prefix() {
         let user_messages = vec!["question".to_string()];
-        let result = build_compacted_history(&user_messages, "my summary", 100);
+        let result = build_compacted_history(&user_messages, "my summary", 100, &[]).messages;
 
         // Last message is the summary.
         let last = result.last().unwrap();
@@ -334,4 +707,316 @@ mod tests {
             panic!("expected text block in summary message");
         }
     }
+
+    fn dialogue_texts(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| {
+                let ContentBlock::Text { text } = &m.content[0] else {
+                    panic!("expected text");
+                };
+                let who = if m.role == Role::Assistant { "A" } else { "U" };
+                format!("{}:{}", who, text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn retain_both_keeps_answers_that_user_drops() {
+        let messages = vec![
+            Message::user("q1 ".repeat(8)),
+            Message::assistant("a1 ".repeat(8)),
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "c1".to_string(),
+                    name: "bash".to_string(),
+                    input: serde_json::json!({}),
+                }],
+            },
+            Message::tool_results(vec![ContentBlock::tool_result("c1", "ok")]),
+            Message::user("q2 ".repeat(8)),
+            Message::assistant("a2 ".repeat(8)),
+        ];
+        // Each text is 24 bytes, 6 tokens; a 12-token budget holds two of them.
+        let user =
+            build_compacted_history(&collect_user_messages(&messages), "s", 12, &[]).messages;
+        let both = build_compacted_dialogue(&collect_dialogue(&messages), "s", 12, &[]).messages;
+
+        let (q1, q2, a2) = ("q1 ".repeat(8), "q2 ".repeat(8), "a2 ".repeat(8));
+        let user = dialogue_texts(&user);
+        let both = dialogue_texts(&both);
+        assert_eq!(user[..2], [format!("U:{}", q1), format!("U:{}", q2)]);
+        assert_eq!(both[..2], [format!("U:{}", q2), format!("A:{}", a2)]);
+        assert!(both[2].starts_with(&format!("U:{}", SUMMARY_PREFIX)));
+        assert_eq!(both.len(), 3);
+    }
+
+    #[test]
+    fn retain_both_never_starts_with_an_answer() {
+        let turns = vec![
+            (Role::User, "question".to_string()),
+            (Role::Assistant, "x".repeat(400)),
+            (Role::User, "y".repeat(40)),
+        ];
+        // The budget runs out inside the long answer, which would lead.
+        let kept = build_compacted_dialogue(&turns, "s", 20, &[]).messages;
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].role, Role::User);
+        assert_eq!(dialogue_texts(&kept)[0], format!("U:{}", "y".repeat(40)));
+    }
+
+    /// Three turns of 40 tokens each, against a 50-token budget that only
+    /// has room for the last one.
+    fn oversized_session() -> (Vec<Message>, CompactionConfig) {
+        let messages = (0..3)
+            .flat_map(|i| {
+                [
+                    Message::user(format!("{}{}", i, "u".repeat(79))),
+                    Message::assistant("a".repeat(80)),
+                ]
+            })
+            .collect();
+        let config = CompactionConfig {
+            threshold_token_limit: Some(50),
+            ..Default::default()
+        };
+        (messages, config)
+    }
+
+    #[test]
+    fn recent_turns_never_split_a_result_from_its_call() {
+        let messages = vec![
+            Message::user("q".repeat(40)),
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "c1".to_string(),
+                    name: "bash".to_string(),
+                    input: serde_json::json!({}),
+                }],
+            },
+            Message::tool_results(vec![ContentBlock::tool_result("c1", &"r".repeat(160))]),
+            Message::assistant("done"),
+        ];
+        assert_eq!(recent_turns_start(&messages, 100), 0);
+        // The tool result fits but the turn's question doesn't, so nothing
+        // can be kept without orphaning the result.
+        assert_eq!(recent_turns_start(&messages, 50), messages.len());
+    }
+
+    #[test]
+    fn resumed_history_within_budget_is_untouched() {
+        let (messages, _) = oversized_session();
+        let (kept, fit) = fit_resumed_history(
+            messages.clone(),
+            "claude-sonnet-4-5",
+            &CompactionConfig::default(),
+            &Pins::default(),
+        )
+        .unwrap();
+        assert_eq!(fit, ResumeFit::Fits);
+        assert_eq!(kept.len(), messages.len());
+        assert_eq!(fit.describe("claude-sonnet-4-5"), None);
+    }
+
+    #[test]
+    fn oversized_resume_compacts_by_default() {
+        let (messages, config) = oversized_session();
+        let (kept, fit) =
+            fit_resumed_history(messages, "llama3", &config, &Pins::default()).unwrap();
+        assert_eq!(fit, ResumeFit::Compact);
+        // The loop summarizes it; nothing is dropped up front.
+        assert_eq!(kept.len(), 6);
+        assert!(fit.describe("llama3").unwrap().contains("compacting"));
+    }
+
+    #[test]
+    fn oversized_resume_truncates_to_the_recent_turns() {
+        let (messages, mut config) = oversized_session();
+        config.on_resume = OnResume::Truncate;
+        let (kept, fit) =
+            fit_resumed_history(messages, "llama3", &config, &Pins::default()).unwrap();
+        assert_eq!(fit, ResumeFit::Truncated { kept: 2, total: 6 });
+        assert!(
+            matches!(&kept[0].content[0], ContentBlock::Text { text } if text.starts_with('2'))
+        );
+        assert_eq!(
+            fit.describe("llama3").unwrap(),
+            "Resumed session is too large for llama3; kept the last 2 of 6 messages"
+        );
+    }
+
+    #[test]
+    fn oversized_resume_truncates_when_compaction_is_off() {
+        let (messages, mut config) = oversized_session();
+        config.enabled = false;
+        let (kept, fit) =
+            fit_resumed_history(messages, "llama3", &config, &Pins::default()).unwrap();
+        assert_eq!(fit, ResumeFit::Truncated { kept: 2, total: 6 });
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn oversized_resume_errors_when_asked_to() {
+        let (messages, mut config) = oversized_session();
+        config.on_resume = OnResume::Error;
+        let err = fit_resumed_history(messages, "llama3", &config, &Pins::default()).unwrap_err();
+        assert!(err.to_string().contains("--fresh"));
+    }
+
+    #[test]
+    fn a_pinned_old_message_survives_compaction() {
+        let user_messages = vec![
+            "the API must stay backwards compatible".to_string(), // 9 tokens
+            "x".repeat(200),                                      // 50 tokens
+            "y".repeat(40),                                       // 10 tokens
+        ];
+        // Unpinned, a 15-token budget keeps "y" and a cut of "x" only.
+        let unpinned = build_compacted_history(&user_messages, "s", 15, &[]).messages;
+        assert_eq!(unpinned.len(), 3);
+        assert!(!dialogue_texts(&unpinned)[0].contains("backwards"));
+
+        let kept = build_compacted_history(&user_messages, "s", 15, &[0]);
+        let texts = dialogue_texts(&kept.messages);
+        assert_eq!(texts[0], "U:the API must stay backwards compatible");
+        // The pin spends the budget first; what's left goes to the newest.
+        assert!(texts[1].starts_with("U:yyyyyy") && texts[1].contains("truncated"));
+        assert_eq!(kept.messages.len(), 3);
+        assert_eq!(kept.pinned, [0]);
+
+        // Pins that don't fit the budget at all are selected like the rest.
+        let kept = build_compacted_history(&user_messages, "s", 5, &[0]);
+        assert!(!dialogue_texts(&kept.messages)[0].contains("backwards"));
+        assert!(kept.pinned.is_empty());
+    }
+
+    #[test]
+    fn a_pinned_reply_survives_compaction_with_its_question() {
+        let messages = vec![
+            Message::user("which port?"),
+            Message::assistant("Use port 8443 for the admin API."),
+            Message::user("x".repeat(200)),
+            Message::assistant("z".repeat(200)),
+            Message::user("y".repeat(40)),
+        ];
+        // `retain = "user"` drops replies, but not a pinned one.
+        let (turns, pinned) = collect_turns(&messages, CompactionRetain::User, &[1]);
+        assert_eq!(turns.len(), 4);
+        assert_eq!(pinned, [1]);
+
+        let kept = build_compacted_dialogue(&turns, "s", 25, &pinned);
+        let texts = dialogue_texts(&kept.messages);
+        assert_eq!(texts[0], "U:which port?");
+        assert_eq!(texts[1], "A:Use port 8443 for the admin API.");
+        assert_eq!(kept.pinned, [1]);
+    }
+
+    #[test]
+    fn a_pinned_message_survives_resume_truncation() {
+        let (mut messages, mut config) = oversized_session();
+        config.on_resume = OnResume::Truncate;
+        messages.insert(0, Message::user("keep me"));
+        let pins = Pins::new(vec![0, 6]);
+        let (kept, fit) = fit_resumed_history(messages, "llama3", &config, &pins).unwrap();
+        assert_eq!(fit, ResumeFit::Truncated { kept: 3, total: 7 });
+        assert_eq!(dialogue_texts(&kept)[0], "U:keep me");
+        assert!(dialogue_texts(&kept)[1].starts_with("U:2"));
+        // Both pins moved with their messages.
+        assert_eq!(pins.indexes(), [0, 2]);
+    }
+
+    #[test]
+    fn a_pinned_reply_keeps_its_text_and_question_through_resume_truncation() {
+        let (mut messages, mut config) = oversized_session();
+        config.on_resume = OnResume::Truncate;
+        messages.splice(
+            0..0,
+            [
+                Message::user("why?"),
+                Message {
+                    role: Role::Assistant,
+                    content: vec![
+                        ContentBlock::text("because"),
+                        ContentBlock::ToolUse {
+                            id: "c1".to_string(),
+                            name: "bash".to_string(),
+                            input: serde_json::json!({}),
+                        },
+                    ],
+                },
+                Message::tool_results(vec![ContentBlock::tool_result("c1", "ok")]),
+            ],
+        );
+        let pins = Pins::new(vec![1]);
+        let (kept, _) = fit_resumed_history(messages, "llama3", &config, &pins).unwrap();
+        assert_eq!(dialogue_texts(&kept)[..2], ["U:why?", "A:because"]);
+        assert_eq!(kept[1].content.len(), 1);
+        assert_eq!(pins.indexes(), [1]);
+    }
+
+    fn deltas(parts: &[&str]) -> Vec<Result<StreamEvent, anyhow::Error>> {
+        let mut events = vec![Ok(StreamEvent::ContentBlockStart {
+            index: 0,
+            block: ContentBlock::text(""),
+        })];
+        events.extend(parts.iter().map(|text| {
+            Ok(StreamEvent::ContentBlockDelta {
+                index: 0,
+                text: text.to_string(),
+            })
+        }));
+        events
+    }
+
+    #[tokio::test]
+    async fn streamed_summary_accumulates_into_the_final_text() {
+        // 120 bytes per part = 30 tokens, so progress lands at 60, 120, and 150.
+        let part = "x".repeat(120);
+        let mut events = deltas(&[&part, &part, &part, &part, &part]);
+        events.push(Ok(StreamEvent::ContentBlockStop { index: 0 }));
+        events.push(Ok(StreamEvent::MessageStop));
+        let mut progress = Vec::new();
+        let summary = collect_summary(futures::stream::iter(events), |t| progress.push(t))
+            .await
+            .unwrap();
+        assert_eq!(summary, Some(part.repeat(5)));
+        assert_eq!(progress, [60, 120, 150]);
+    }
+
+    #[tokio::test]
+    async fn a_stream_that_never_starts_falls_back() {
+        let events: Vec<Result<StreamEvent, anyhow::Error>> =
+            vec![Err(anyhow::anyhow!("streaming not supported"))];
+        let summary = collect_summary(futures::stream::iter(events), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(summary, None);
+    }
+
+    #[tokio::test]
+    async fn a_stream_that_breaks_midway_fails() {
+        let mut events = deltas(&["Goal: ship"]);
+        events.push(Err(anyhow::anyhow!("connection reset")));
+        let err = collect_summary(futures::stream::iter(events), |_| {})
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("connection reset"));
+    }
+
+    #[tokio::test]
+    async fn run_compaction_uses_the_streamed_summary() {
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+        let mock = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new()
+                .text("Goal: ")
+                .text("fix the parser."),
+        ]));
+        let client: Arc<dyn LlmClient> = mock.clone();
+        let summary = run_compaction(&client, "mock-model", 1024, &[Message::user("hi")], |_| {})
+            .await
+            .unwrap();
+        assert_eq!(summary, "Goal: fix the parser.");
+        assert_eq!(mock.requests().len(), 1);
+    }
 }
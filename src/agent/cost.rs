@@ -0,0 +1,190 @@
+// ABOUTME: Cost estimation — per-model token prices and running usage totals, cache tokens included.
+// ABOUTME: Cache reads are billed at a discount and cache writes at a premium over plain input.
+
+/// Prices in US dollars per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    pub cache_read: f64,
+    pub cache_write: f64,
+}
+
+impl ModelPricing {
+    /// Anthropic-style pricing: cache reads at 10% of input, writes at 125%.
+    const fn anthropic(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read: input * 0.1,
+            cache_write: input * 1.25,
+        }
+    }
+
+    /// Providers that cache automatically: no write premium, discounted reads.
+    const fn auto_cached(input: f64, output: f64, cache_read: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read,
+            cache_write: input,
+        }
+    }
+}
+
+/// Published list prices for a model, or `None` for local and unknown
+/// models, which get no cost estimate.
+pub fn pricing_for_model(model: &str) -> Option<ModelPricing> {
+    if model.contains("claude") {
+        if model.contains("haiku") {
+            Some(ModelPricing::anthropic(1.0, 5.0))
+        } else if model.contains("opus-4-5") || model.contains("opus-4-6") {
+            Some(ModelPricing::anthropic(5.0, 25.0))
+        } else if model.contains("opus") {
+            Some(ModelPricing::anthropic(15.0, 75.0))
+        } else {
+            Some(ModelPricing::anthropic(3.0, 15.0))
+        }
+    } else if model.contains("gpt-5") {
+        Some(ModelPricing::auto_cached(1.25, 10.0, 0.125))
+    } else if model.contains("gpt-4o") {
+        Some(ModelPricing::auto_cached(2.5, 10.0, 1.25))
+    } else if model.contains("gemini") {
+        Some(ModelPricing::auto_cached(1.25, 10.0, 0.3125))
+    } else {
+        None
+    }
+}
+
+/// Token counts from one response, or summed over a session. `input` is
+/// the uncached input only; cache reads and writes are counted separately.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_read += other.cache_read;
+        self.cache_write += other.cache_write;
+    }
+
+    /// Every token in the prompt: uncached input plus cache reads and writes.
+    pub fn prompt_tokens(&self) -> u64 {
+        self.input + self.cache_read + self.cache_write
+    }
+
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens() + self.output
+    }
+
+    /// Estimated cost in US dollars, each kind of token at its own rate.
+    pub fn cost(&self, pricing: &ModelPricing) -> f64 {
+        let per_token = |count: u64, price: f64| count as f64 * price / 1_000_000.0;
+        per_token(self.input, pricing.input)
+            + per_token(self.output, pricing.output)
+            + per_token(self.cache_read, pricing.cache_read)
+            + per_token(self.cache_write, pricing.cache_write)
+    }
+}
+
+/// Format a dollar amount for the status bar: cents for most sessions,
+/// more precision when it's under a cent.
+pub fn format_cost(dollars: f64) -> String {
+    if dollars > 0.0 && dollars < 0.01 {
+        format!("${:.4}", dollars)
+    } else {
+        format!("${:.2}", dollars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn plain_input_and_output_cost() {
+        let pricing = pricing_for_model("claude-sonnet-4-5-20250929").unwrap();
+        let usage = TokenUsage {
+            input: 1_000_000,
+            output: 100_000,
+            ..TokenUsage::default()
+        };
+        assert_close(usage.cost(&pricing), 3.0 + 1.5);
+    }
+
+    #[test]
+    fn cache_tokens_cost_their_own_rates() {
+        let pricing = pricing_for_model("claude-sonnet-4-5-20250929").unwrap();
+        let reads = TokenUsage {
+            cache_read: 1_000_000,
+            ..TokenUsage::default()
+        };
+        assert_close(reads.cost(&pricing), 0.30);
+        let writes = TokenUsage {
+            cache_write: 1_000_000,
+            ..TokenUsage::default()
+        };
+        assert_close(writes.cost(&pricing), 3.75);
+
+        let mixed = TokenUsage {
+            input: 2_000,
+            output: 500,
+            cache_read: 50_000,
+            cache_write: 10_000,
+        };
+        let expected = (2_000.0 * 3.0 + 500.0 * 15.0 + 50_000.0 * 0.3 + 10_000.0 * 3.75) / 1e6;
+        assert_close(mixed.cost(&pricing), expected);
+    }
+
+    #[test]
+    fn auto_cached_providers_discount_reads_without_a_write_premium() {
+        let pricing = pricing_for_model("gpt-5.2").unwrap();
+        let usage = TokenUsage {
+            cache_read: 1_000_000,
+            cache_write: 1_000_000,
+            ..TokenUsage::default()
+        };
+        assert_close(usage.cost(&pricing), 0.125 + 1.25);
+    }
+
+    #[test]
+    fn local_models_have_no_pricing() {
+        assert_eq!(pricing_for_model("llama3"), None);
+    }
+
+    #[test]
+    fn totals_add_up() {
+        let mut total = TokenUsage::default();
+        total.add(TokenUsage {
+            input: 10,
+            output: 5,
+            cache_read: 100,
+            cache_write: 20,
+        });
+        total.add(TokenUsage {
+            input: 1,
+            output: 1,
+            cache_read: 130,
+            cache_write: 0,
+        });
+        assert_eq!(total.prompt_tokens(), 261);
+        assert_eq!(total.total(), 267);
+    }
+
+    #[test]
+    fn cost_formatting() {
+        assert_eq!(format_cost(0.0), "$0.00");
+        assert_eq!(format_cost(0.0042), "$0.0042");
+        assert_eq!(format_cost(1.234), "$1.23");
+    }
+}
@@ -0,0 +1,181 @@
+// ABOUTME: Reasoning effort — maps [llm] reasoning_effort / thinking_budget_tokens onto requests.
+// ABOUTME: Only providers and models that accept the knob get it; everything else is left untouched.
+
+use mux::prelude::*;
+
+use crate::config::{Effort, LlmConfig};
+
+/// Smallest thinking budget Anthropic accepts.
+const MIN_THINKING_BUDGET: u32 = 1024;
+
+/// The provider-specific form an effort setting takes on a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffortSetting {
+    /// OpenAI-style `reasoning_effort`.
+    ReasoningEffort(Effort),
+    /// Anthropic extended thinking with this token budget.
+    ThinkingBudget(u32),
+}
+
+/// The session's reasoning settings. `effort` can change mid-session via /effort.
+#[derive(Debug, Clone, Default)]
+pub struct Reasoning {
+    pub provider: String,
+    pub effort: Option<Effort>,
+    pub thinking_budget_tokens: u32,
+}
+
+impl Reasoning {
+    pub fn from_config(config: &LlmConfig) -> Self {
+        Self {
+            provider: config.provider.clone(),
+            effort: config.reasoning_effort,
+            thinking_budget_tokens: config.thinking_budget_tokens,
+        }
+    }
+
+    /// Switch effort for /effort. Turning it off also drops the configured
+    /// thinking budget, which would otherwise keep thinking on.
+    pub fn set_effort(&mut self, effort: Option<Effort>) {
+        self.effort = effort;
+        if effort.is_none() {
+            self.thinking_budget_tokens = 0;
+        }
+    }
+
+    /// What to add to a request for `model`, or None when nothing is
+    /// configured or the model doesn't take it.
+    pub fn setting(&self, model: &str, max_tokens: u32) -> Option<EffortSetting> {
+        match self.provider.as_str() {
+            "openai" | "openrouter" if is_openai_reasoning_model(model) => {
+                self.effort.map(EffortSetting::ReasoningEffort)
+            }
+            "anthropic" if is_anthropic_thinking_model(model) => {
+                let budget = match (self.thinking_budget_tokens, self.effort) {
+                    (0, Some(effort)) => default_budget(effort),
+                    (0, None) => return None,
+                    (budget, _) => budget,
+                };
+                // The budget has to leave room for the answer itself.
+                let budget = budget.min(max_tokens.saturating_sub(1));
+                (budget >= MIN_THINKING_BUDGET).then_some(EffortSetting::ThinkingBudget(budget))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Thinking budget used for an effort level when none is configured.
+fn default_budget(effort: Effort) -> u32 {
+    match effort {
+        Effort::Low => 1024,
+        Effort::Medium => 4096,
+        Effort::High => 16384,
+    }
+}
+
+/// o-series and GPT-5 models take `reasoning_effort`.
+fn is_openai_reasoning_model(model: &str) -> bool {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    ["o1", "o3", "o4", "gpt-5"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Claude 3.7 and the Claude 4 families support extended thinking.
+fn is_anthropic_thinking_model(model: &str) -> bool {
+    model.contains("claude-3-7")
+        || ["claude-sonnet-4", "claude-opus-4", "claude-haiku-4"]
+            .iter()
+            .any(|family| model.contains(family))
+}
+
+/// Add the effort setting, if any, to a request.
+pub fn apply(request: Request, setting: Option<EffortSetting>) -> Request {
+    match setting {
+        Some(EffortSetting::ReasoningEffort(effort)) => request.reasoning_effort(effort.as_str()),
+        Some(EffortSetting::ThinkingBudget(budget)) => request.thinking_budget(budget),
+        None => request,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasoning(provider: &str, effort: Option<Effort>, budget: u32) -> Reasoning {
+        Reasoning {
+            provider: provider.to_string(),
+            effort,
+            thinking_budget_tokens: budget,
+        }
+    }
+
+    #[test]
+    fn openai_reasoning_models_get_reasoning_effort() {
+        let r = reasoning("openai", Some(Effort::High), 0);
+        assert_eq!(
+            r.setting("gpt-5.2", 4096),
+            Some(EffortSetting::ReasoningEffort(Effort::High))
+        );
+        assert_eq!(
+            reasoning("openrouter", Some(Effort::Low), 0).setting("openai/o3-mini", 4096),
+            Some(EffortSetting::ReasoningEffort(Effort::Low))
+        );
+        assert_eq!(r.setting("gpt-4o", 4096), None);
+    }
+
+    #[test]
+    fn anthropic_thinking_budget_comes_from_effort_or_config() {
+        let from_effort = reasoning("anthropic", Some(Effort::Medium), 0);
+        assert_eq!(
+            from_effort.setting("claude-sonnet-4-5-20250929", 8192),
+            Some(EffortSetting::ThinkingBudget(4096))
+        );
+        let explicit = reasoning("anthropic", None, 2000);
+        assert_eq!(
+            explicit.setting("claude-opus-4-1", 8192),
+            Some(EffortSetting::ThinkingBudget(2000))
+        );
+        assert_eq!(from_effort.setting("claude-3-5-haiku-latest", 8192), None);
+    }
+
+    #[test]
+    fn effort_off_drops_the_configured_budget() {
+        let mut r = reasoning("anthropic", Some(Effort::Low), 2000);
+        r.set_effort(Some(Effort::High));
+        assert_eq!(
+            r.setting("claude-opus-4-1", 8192),
+            Some(EffortSetting::ThinkingBudget(2000))
+        );
+        r.set_effort(None);
+        assert_eq!(r.setting("claude-opus-4-1", 8192), None);
+    }
+
+    #[test]
+    fn thinking_budget_is_capped_below_max_tokens() {
+        let r = reasoning("anthropic", Some(Effort::High), 0);
+        assert_eq!(
+            r.setting("claude-sonnet-4-5", 4096),
+            Some(EffortSetting::ThinkingBudget(4095))
+        );
+        assert_eq!(r.setting("claude-sonnet-4-5", 1000), None);
+    }
+
+    #[test]
+    fn unsupported_providers_and_unset_effort_send_nothing() {
+        assert_eq!(
+            reasoning("ollama", Some(Effort::High), 0).setting("o3", 4096),
+            None
+        );
+        assert_eq!(
+            reasoning("gemini", Some(Effort::High), 0).setting("gemini-2.5-pro", 4096),
+            None
+        );
+        assert_eq!(reasoning("openai", None, 0).setting("o3", 4096), None);
+        assert_eq!(
+            reasoning("anthropic", None, 0).setting("claude-opus-4-1", 8192),
+            None
+        );
+    }
+}
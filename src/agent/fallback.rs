@@ -0,0 +1,150 @@
+// ABOUTME: Fallback models — the providers a turn moves to, in order, when the configured one fails.
+// ABOUTME: Clients are created the first time they're needed and kept; every turn starts on the primary.
+
+use std::sync::Arc;
+
+use mux::prelude::*;
+
+use crate::agent::compaction;
+use crate::agent::provider::create_client;
+use crate::config::{CompactionConfig, FallbackModel, LlmConfig};
+
+/// Creates the client for a fallback.
+pub type Connect = Box<dyn Fn(&FallbackModel) -> anyhow::Result<Arc<dyn LlmClient>> + Send + Sync>;
+
+/// The `[llm] fallbacks` chain, with a client cached for each one tried.
+pub struct Fallbacks {
+    models: Vec<FallbackModel>,
+    clients: Vec<Option<Arc<dyn LlmClient>>>,
+    connect: Connect,
+}
+
+impl Default for Fallbacks {
+    fn default() -> Self {
+        Self::new(
+            Vec::new(),
+            Box::new(|_| anyhow::bail!("no fallbacks configured")),
+        )
+    }
+}
+
+impl Fallbacks {
+    pub fn new(models: Vec<FallbackModel>, connect: Connect) -> Self {
+        let clients = vec![None; models.len()];
+        Self {
+            models,
+            clients,
+            connect,
+        }
+    }
+
+    /// The chain `[llm] fallbacks` configures, each client built from `llm`
+    /// with the fallback's provider and model.
+    pub fn from_config(llm: &LlmConfig) -> Self {
+        let llm = llm.clone();
+        Self::new(
+            llm.fallbacks.clone(),
+            Box::new(move |fallback| create_client(&llm.for_fallback(fallback))),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn model(&self, index: usize) -> &FallbackModel {
+        &self.models[index]
+    }
+
+    /// The client for fallback `index`, created on first use. A failure
+    /// (a missing API key, say) isn't cached, so a later turn tries again.
+    pub fn client(&mut self, index: usize) -> anyhow::Result<Arc<dyn LlmClient>> {
+        if let Some(client) = &self.clients[index] {
+            return Ok(Arc::clone(client));
+        }
+        let client = (self.connect)(&self.models[index])?;
+        self.clients[index] = Some(Arc::clone(&client));
+        Ok(client)
+    }
+}
+
+/// The part of `messages` to send a fallback `model`: all of it when it
+/// fits the model's window, else the most recent whole turns that do. A
+/// history whose last turn alone is too big is sent as-is for the provider
+/// to reject.
+pub fn fitting_history<'a>(
+    messages: &'a [Message],
+    model: &str,
+    config: &CompactionConfig,
+) -> &'a [Message] {
+    let budget = compaction::history_budget(model, config);
+    if compaction::approx_messages_tokens(messages) <= budget {
+        return messages;
+    }
+    match compaction::recent_turns_start(messages, budget) {
+        start if start < messages.len() => &messages[start..],
+        _ => messages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fallback(provider: &str, model: &str) -> FallbackModel {
+        FallbackModel {
+            provider: provider.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    #[test]
+    fn clients_are_created_once_and_failures_retried() {
+        let connects = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connects);
+        let mut fallbacks = Fallbacks::new(
+            vec![
+                fallback("openai", "gpt-5.2"),
+                fallback("gemini", "gemini-2.5-pro"),
+            ],
+            Box::new(move |f| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if f.provider == "gemini" {
+                    anyhow::bail!("GEMINI_API_KEY not set");
+                }
+                Ok(Arc::new(MockLlmClient::new(Vec::new())) as Arc<dyn LlmClient>)
+            }),
+        );
+
+        let first = fallbacks.client(0).unwrap();
+        let again = fallbacks.client(0).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(fallbacks.client(1).is_err());
+        assert!(fallbacks.client(1).is_err());
+        assert_eq!(connects.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn a_smaller_window_gets_only_the_turns_that_fit() {
+        let config = CompactionConfig {
+            threshold_token_limit: Some(50),
+            ..CompactionConfig::default()
+        };
+        let long = "word ".repeat(100);
+        let messages = vec![
+            Message::user(&long),
+            Message::assistant("ok"),
+            Message::user("and now?"),
+        ];
+        assert_eq!(fitting_history(&messages, "gpt-5.2", &config).len(), 1);
+
+        let roomy = CompactionConfig::default();
+        assert_eq!(fitting_history(&messages, "gpt-5.2", &roomy).len(), 3);
+    }
+}
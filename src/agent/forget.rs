@@ -0,0 +1,131 @@
+// ABOUTME: /forget — blanks the text of a message the user typed, in place, so it leaves the context.
+// ABOUTME: Message structure is kept, so tool_use/tool_result pairs around it stay valid for the provider.
+
+use mux::prelude::*;
+
+use crate::agent::compaction::SUMMARY_PREFIX;
+
+/// What a forgotten message's text is replaced with.
+pub const REDACTED: &str = "[redacted by user]";
+
+/// Whether `message` is one the user typed, rather than tool results or a
+/// compaction summary. The chat counts the same messages, so "/forget 2"
+/// means the same one on both sides.
+fn is_typed_by_user(message: &Message) -> bool {
+    message.role == Role::User
+        && message.content.iter().any(|b| match b {
+            ContentBlock::Text { text } => !text.starts_with(SUMMARY_PREFIX),
+            _ => false,
+        })
+}
+
+/// Index of the `nth` most recent message the user typed (1 is the last),
+/// or None when there aren't that many.
+pub fn nth_typed_message(messages: &[Message], nth: usize) -> Option<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, m)| is_typed_by_user(m))
+        .nth(nth.checked_sub(1)?)
+        .map(|(i, _)| i)
+}
+
+/// Replace the text of the `nth` most recent message the user typed (1 is
+/// the last) with [`REDACTED`], leaving any other blocks alone. Returns the
+/// message as it was and as it is now, or None when there aren't that many.
+pub fn forget_user_message(messages: &mut [Message], nth: usize) -> Option<(Message, Message)> {
+    let index = nth_typed_message(messages, nth)?;
+    let original = messages[index].clone();
+    for block in &mut messages[index].content {
+        if let ContentBlock::Text { text } = block {
+            *text = REDACTED.to_string();
+        }
+    }
+    Some((original, messages[index].clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<Message> {
+        vec![
+            Message::user("my token is sk-live-123, list the files"),
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "c1".to_string(),
+                    name: "list_files".to_string(),
+                    input: serde_json::json!({ "path": "." }),
+                }],
+            },
+            Message::tool_results(vec![ContentBlock::tool_result("c1", "a.txt")]),
+            Message::assistant("There is one file."),
+            Message::user("thanks"),
+        ]
+    }
+
+    fn json(messages: &[Message]) -> serde_json::Value {
+        serde_json::to_value(messages).unwrap()
+    }
+
+    fn text_of(message: &Message) -> String {
+        message
+            .content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn counts_back_over_typed_messages_only() {
+        let mut messages = history();
+        let (original, redacted) = forget_user_message(&mut messages, 2).unwrap();
+        assert!(text_of(&original).contains("sk-live-123"));
+        assert_eq!(text_of(&messages[0]), REDACTED);
+        assert_eq!(json(&[redacted]), json(&messages[..1]));
+        // The tool result message in between isn't counted or touched.
+        assert_eq!(json(&messages[2..4]), json(&history()[2..4]));
+        assert_eq!(text_of(&messages[4]), "thanks");
+    }
+
+    #[test]
+    fn tool_pairing_survives_redaction_next_to_a_call() {
+        let mut messages = history();
+        forget_user_message(&mut messages, 2).unwrap();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(json(&messages[1..2]), json(&history()[1..2]));
+        let ContentBlock::ToolResult { tool_use_id, .. } = &messages[2].content[0] else {
+            panic!("tool result moved");
+        };
+        assert_eq!(tool_use_id, "c1");
+    }
+
+    #[test]
+    fn a_compaction_summary_is_not_counted() {
+        let mut messages = vec![
+            Message::user("remember the password hunter2"),
+            Message::user(format!("{}\n\nThe user shared a password.", SUMMARY_PREFIX)),
+            Message::assistant("Noted."),
+            Message::user("thanks"),
+        ];
+        assert_eq!(nth_typed_message(&messages, 2), Some(0));
+        forget_user_message(&mut messages, 2).unwrap();
+        assert_eq!(text_of(&messages[0]), REDACTED);
+        assert!(text_of(&messages[1]).starts_with(SUMMARY_PREFIX));
+    }
+
+    #[test]
+    fn nothing_is_forgotten_past_the_start() {
+        let mut messages = history();
+        assert!(forget_user_message(&mut messages, 3).is_none());
+        assert!(forget_user_message(&mut messages, 0).is_none());
+        assert_eq!(json(&messages), json(&history()));
+        assert!(forget_user_message(&mut messages, 1).is_some());
+        assert_eq!(text_of(&messages[4]), REDACTED);
+    }
+}
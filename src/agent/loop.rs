@@ -1,22 +1,43 @@
 // ABOUTME: Streaming agent loop — drives conversation between user, LLM, and tools.
 // ABOUTME: Handles streaming responses, tool call approval/execution, and message history.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use futures::StreamExt;
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::{Mutex, mpsc, oneshot};
 
 use mux::prelude::*;
 
-use crate::agent::compaction;
+use crate::agent::budget::{BUDGET_QUESTION_ID, BudgetChoice, STOPPED_RESULT, TurnBudget};
+use crate::agent::compaction::{self, SummaryDecision};
+use crate::agent::effort::{self, Reasoning};
+use crate::agent::fallback::{Fallbacks, fitting_history};
+use crate::agent::forget::forget_user_message;
+use crate::agent::pins::{Pins, pin_target};
+use crate::agent::refusal::Declined;
+use crate::agent::shutdown::ShutdownFlag;
+use crate::agent::timeouts::{self, RequestTimeouts};
+use crate::agent::tool_cache::ToolCache;
 use crate::approval::{ApprovalDecision, ApprovalEngine, EngineOutcome, ToolCallInfo};
-use crate::config::CompactionConfig;
-use crate::session::SessionLogger;
+use crate::config::{
+    CompactionConfig, CompactionReview, Config, FallbackModel, OnResume, PendingToolCalls,
+    SessionFormat, StreamingMode, ToolsConfig,
+};
+use crate::prompt::{
+    SkillEntry, SystemPromptParams, build_system_prompt, load_context_files, load_skill_files,
+};
 use crate::session::persistence::{SessionState, save_session};
-use crate::tools::ask_user::ASK_USER_TOOL_NAME;
+use crate::session::staleness::head_commit;
+use crate::session::{AutoSave, SessionLogger};
+use crate::tools::ask_user::{ASK_USER_TOOL_NAME, CONFIRM_YES, QuestionStyle};
+use crate::tools::catalog::{DISABLED_TOOL_ERROR, ToolOrigin, is_read_only_tool};
+use crate::tools::mcp::McpArrival;
+use crate::tools::open_in_editor::OPEN_IN_EDITOR_TOOL_NAME;
+use crate::tools::roster::McpRoster;
+use crate::tools::summary::{describe_tool_call, summarize_params_within};
 use crate::tui::state::{AgentEvent, UserEvent};
 
 /// Metadata tracked for a tool call being assembled from streaming events.
@@ -26,6 +47,16 @@ struct PendingToolCall {
     json_buf: String,
 }
 
+/// How a conversation turn ended.
+enum TurnOutcome {
+    /// The turn ran to completion (successfully or with an error).
+    Finished(anyhow::Result<()>),
+    /// The user cancelled the turn mid-flight.
+    Cancelled,
+    /// The user quit (or the TUI went away) mid-turn.
+    Quit,
+}
+
 /// Bundled parameters for the agent loop, replacing individual function arguments.
 pub struct AgentLoopParams {
     pub client: Arc<dyn LlmClient>,
@@ -35,13 +66,59 @@ pub struct AgentLoopParams {
     pub max_tokens: u32,
     pub approval_timeout_seconds: u64,
     pub system_prompt: String,
+    /// Inputs the system prompt was built from, kept so /reload can rebuild it.
+    pub prompt_params: SystemPromptParams,
     pub initial_messages: Vec<Message>,
     pub session_logger: Option<Arc<Mutex<SessionLogger>>>,
     pub workspace_dir: PathBuf,
     pub compaction_config: CompactionConfig,
     pub existing_created_at: Option<String>,
+    pub streaming: StreamingMode,
+    pub tools_config: ToolsConfig,
+    /// Which on-disk formats the session is saved in.
+    pub session_format: SessionFormat,
+    /// Reasoning effort / thinking budget sent to models that support it.
+    pub reasoning: Reasoning,
+    /// When a long turn saves `session.json` before it finishes.
+    pub autosave: AutoSave,
+    /// Start in observe mode: tools are offered but every call is refused.
+    pub observe: bool,
+    /// How long to wait on the provider before ending the turn.
+    pub timeouts: RequestTimeouts,
+    /// MCP servers and their tools, re-synced between turns.
+    pub mcp: McpRoster,
+    /// Servers still starting in the background (`[mcp] lazy`), added to
+    /// `mcp` between turns as they come up.
+    pub mcp_arrivals: Option<mpsc::Receiver<McpArrival>>,
+    /// Set by the app once it starts quitting.
+    pub shutdown: ShutdownFlag,
+    /// Messages pinned with /pin, restored from the saved session.
+    pub pins: Pins,
+    /// Tokens a turn may use before the user is asked whether to go on.
+    pub token_budget: Option<u64>,
+    /// Where a turn goes when the provider fails (`[llm] fallbacks`).
+    pub fallbacks: Fallbacks,
+    /// What to do with tool calls the resumed history never answered.
+    pub pending_tool_calls: PendingToolCalls,
+    /// Added to the system prompt of the first turn only, e.g. a warning
+    /// that a stale session's workspace may have changed.
+    pub context_note: Option<String>,
 }
 
+/// Tool result for every call made in observe mode.
+pub const OBSERVE_RESULT: &str = "Tool execution is disabled (observe mode). Nothing was run; \
+     explain what you would do and what you expect it to show instead.";
+
+/// Result for a tool call an interrupted session never ran, under
+/// `pending_tool_calls = "fail"`.
+pub const INTERRUPTED_RESULT: &str = "Not run: the session was interrupted before this tool ran";
+
+/// Result for a pending call the user chose not to run on resume.
+pub const DECLINED_PENDING_RESULT: &str = "Not run: the session was interrupted before this tool ran, and the user declined to run it on resume";
+
+/// The `tool_call_id` the resume question is asked under.
+pub const PENDING_QUESTION_ID: &str = "pending-tool-calls";
+
 /// Log a message via the session logger, if one is configured.
 async fn maybe_log_message(logger: &Option<Arc<Mutex<SessionLogger>>>, msg: &Message) {
     if let Some(logger) = logger {
@@ -52,6 +129,34 @@ async fn maybe_log_message(logger: &Option<Arc<Mutex<SessionLogger>>>, msg: &Mes
     }
 }
 
+/// Log an assistant message, with the fallback that wrote it, if any.
+async fn maybe_log_answer(
+    logger: &Option<Arc<Mutex<SessionLogger>>>,
+    msg: &Message,
+    answered_by: Option<&str>,
+) {
+    if let Some(logger) = logger {
+        let mut guard = logger.lock().await;
+        if let Err(e) = guard.log_answer(msg, answered_by) {
+            eprintln!("Warning: failed to log session message: {}", e);
+        }
+    }
+}
+
+/// Log a tool results message with the reason each call was approved.
+async fn maybe_log_tool_results(
+    logger: &Option<Arc<Mutex<SessionLogger>>>,
+    msg: &Message,
+    approvals: &BTreeMap<String, String>,
+) {
+    if let Some(logger) = logger {
+        let mut guard = logger.lock().await;
+        if let Err(e) = guard.log_tool_results(msg, approvals) {
+            eprintln!("Warning: failed to log session message: {}", e);
+        }
+    }
+}
+
 /// Run the agent loop, processing user messages and streaming LLM responses.
 ///
 /// This function runs until the user sends a Quit event or the channel closes.
@@ -64,107 +169,630 @@ pub async fn run_agent_loop(
     agent_tx: mpsc::Sender<AgentEvent>,
 ) {
     let mut messages: Vec<Message> = params.initial_messages;
+    // Auto mode may downgrade this to Off for the rest of the session.
+    let mut streaming = params.streaming;
+    let mut system_prompt = params.system_prompt;
+    let mut prompt_params = params.prompt_params;
+    let mut reasoning = params.reasoning;
+    let mut autosave = params.autosave;
+    let mut observe = params.observe;
+    let mut mcp = params.mcp;
+    let mut mcp_arrivals = params.mcp_arrivals;
+    let mut fallbacks = params.fallbacks;
+    // Servers' tool lists are re-read between turns, never mid-turn.
+    let mut resync = resync_interval(&mcp);
     let created_at = params
         .existing_created_at
         .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+    let pins = params.pins.clone();
+    let mut context_note = params.context_note;
+
+    // A session that exited mid-turn leaves its last tool calls unanswered;
+    // settle them before anything is sent to the provider.
+    if settle_pending_tool_calls(
+        params.pending_tool_calls,
+        &mut messages,
+        &params.registry,
+        &params.engine,
+        params.approval_timeout_seconds,
+        &params.tools_config,
+        observe,
+        &params.session_logger,
+        &agent_tx,
+        &params.shutdown,
+    )
+    .await
+    {
+        let _ = agent_tx.send(AgentEvent::Done).await;
+    }
+
+    // HEAD is saved with the session; git runs once per turn, off the
+    // runtime's threads, rather than on every save.
+    let head = std::sync::Mutex::new(read_head(&params.workspace_dir).await);
+    let save_progress = |messages: &[Message]| {
+        save_session_state(
+            params.session_format,
+            &params.workspace_dir,
+            &params.model,
+            &created_at,
+            messages,
+            &pins.indexes(),
+            head.lock().unwrap().clone(),
+        )
+    };
+
+    // A resumed history too large for this model is summarized before the
+    // first message; the TUI blocks input from CompactionStarted until
+    // ResumeCompacted.
+    let budget = compaction::history_budget(&params.model, &params.compaction_config);
+    if params.compaction_config.enabled
+        && params.compaction_config.on_resume == OnResume::Compact
+        && compaction::approx_messages_tokens(&messages) > budget
+    {
+        compact_and_report(
+            &params.client,
+            &params.model,
+            params.max_tokens,
+            &params.compaction_config,
+            &params.session_logger,
+            params.session_format,
+            &mut messages,
+            &pins,
+            &agent_tx,
+            Some(budget),
+        )
+        .await;
+        let _ = agent_tx.send(AgentEvent::ResumeCompacted).await;
+    }
 
     loop {
-        // Wait for a user event.
-        let event = match user_rx.recv().await {
-            Some(e) => e,
-            None => break, // Channel closed.
+        // Wait for a user event, re-syncing MCP tools when it's time and
+        // adding servers that finished starting in the background.
+        let event = tokio::select! {
+            event = user_rx.recv() => match event {
+                Some(e) => e,
+                None => break, // Channel closed.
+            },
+            arrival = next_arrival(&mut mcp_arrivals) => {
+                match arrival {
+                    Some(arrival) => {
+                        admit_mcp_server(
+                            arrival,
+                            &mut mcp,
+                            &params.registry,
+                            &params.tools_config,
+                            &mut prompt_params,
+                            &mut system_prompt,
+                            &agent_tx,
+                        )
+                        .await;
+                        if resync.is_none() {
+                            resync = resync_interval(&mcp);
+                        }
+                    }
+                    None => mcp_arrivals = None,
+                }
+                continue;
+            }
+            _ = next_resync(&mut resync) => {
+                sync_mcp_tools(
+                    &mut mcp,
+                    &params.registry,
+                    &params.tools_config,
+                    &mut prompt_params,
+                    &mut system_prompt,
+                    &agent_tx,
+                )
+                .await;
+                continue;
+            }
         };
 
         match event {
             UserEvent::Quit => break,
+            // Nothing is in flight between turns, so there is nothing to cancel.
+            UserEvent::Cancel => continue,
+            UserEvent::Reload => {
+                match reload_prompt(&mut prompt_params) {
+                    Ok(skills) => {
+                        system_prompt = build_system_prompt(&prompt_params);
+                        let _ = agent_tx.send(AgentEvent::Reloaded { skills }).await;
+                    }
+                    Err(e) => {
+                        let _ = agent_tx
+                            .send(AgentEvent::Error(format!("Reload failed: {}", e)))
+                            .await;
+                    }
+                }
+                continue;
+            }
+            UserEvent::SetEffort(effort) => {
+                reasoning.set_effort(effort);
+                let notice = match effort {
+                    None => "Reasoning effort cleared".to_string(),
+                    Some(e)
+                        if reasoning
+                            .setting(&params.model, params.max_tokens)
+                            .is_none() =>
+                    {
+                        format!(
+                            "Reasoning effort set to {} ({} doesn't support it, so it is ignored)",
+                            e.as_str(),
+                            params.model
+                        )
+                    }
+                    Some(e) => format!("Reasoning effort set to {}", e.as_str()),
+                };
+                let _ = agent_tx.send(AgentEvent::Notice(notice)).await;
+                continue;
+            }
+            UserEvent::SetObserve(enabled) => {
+                observe = enabled;
+                let notice = if enabled {
+                    "Observe mode on: tool calls are refused and nothing runs"
+                } else {
+                    "Observe mode off: tool calls run again"
+                };
+                let _ = agent_tx.send(AgentEvent::Notice(notice.to_string())).await;
+                continue;
+            }
+            UserEvent::Forget { nth } => {
+                let notice = match forget_user_message(&mut messages, nth) {
+                    Some((original, redacted)) => {
+                        let mut rewritten = 0;
+                        if let Some(logger) = &params.session_logger {
+                            match logger.lock().await.log_redaction(&original, &redacted) {
+                                Ok(n) => rewritten = n,
+                                Err(e) => {
+                                    let _ = agent_tx
+                                        .send(AgentEvent::Error(format!(
+                                            "Failed to redact the session log: {}",
+                                            e
+                                        )))
+                                        .await;
+                                }
+                            }
+                        }
+                        // A pin on the forgotten message has nothing left to keep.
+                        pins.prune(&messages);
+                        save_progress(&messages);
+                        let _ = agent_tx.send(AgentEvent::Forgotten { nth }).await;
+                        format!(
+                            "\u{1f9f9} Forgot that message ({} log entr{} rewritten)",
+                            rewritten,
+                            if rewritten == 1 { "y" } else { "ies" }
+                        )
+                    }
+                    None => "Nothing to forget".to_string(),
+                };
+                let _ = agent_tx.send(AgentEvent::Notice(notice)).await;
+                continue;
+            }
+            UserEvent::Pin { nth, reply } => {
+                let notice = match pins.toggle(&messages, nth, reply) {
+                    Some(change) => {
+                        // The log is the only record without session.json,
+                        // and the write-ahead log for it otherwise.
+                        let pinned = pins.indexes();
+                        if let Some(logger) = &params.session_logger
+                            && let Some(target) = pin_target(&messages, nth, reply)
+                            && let Err(e) = logger.lock().await.log_pins(&messages[target], &pinned)
+                        {
+                            eprintln!("Warning: failed to log pins: {}", e);
+                        }
+                        save_progress(&messages);
+                        let _ = agent_tx
+                            .send(AgentEvent::Pinned {
+                                nth,
+                                reply,
+                                pinned: change.is_pinned(),
+                            })
+                            .await;
+                        change.to_string()
+                    }
+                    None => "Nothing to pin".to_string(),
+                };
+                let _ = agent_tx.send(AgentEvent::Notice(notice)).await;
+                continue;
+            }
             UserEvent::Message(text) => {
                 let user_msg = Message::user(&text);
                 maybe_log_message(&params.session_logger, &user_msg).await;
                 messages.push(user_msg);
-
-                // Enter the LLM conversation loop. After each round of tool calls,
-                // we re-send the updated conversation to the LLM.
-                if let Err(e) = conversation_turn(
-                    &params.client,
-                    &params.registry,
-                    &params.engine,
-                    &params.model,
-                    params.max_tokens,
-                    params.approval_timeout_seconds,
-                    &params.system_prompt,
-                    &mut messages,
-                    &agent_tx,
-                    &params.session_logger,
-                )
-                .await
+            }
+            UserEvent::Regenerate { hint } => {
+                let Some(discarded) = discard_last_answer(&mut messages, hint.as_deref()) else {
+                    let _ = agent_tx
+                        .send(AgentEvent::Notice("Nothing to regenerate".to_string()))
+                        .await;
+                    let _ = agent_tx.send(AgentEvent::Done).await;
+                    continue;
+                };
+                // A pin on the discarded answer doesn't carry over to the new one.
+                pins.prune(&messages);
+                if let Some(logger) = &params.session_logger
+                    && let Some(user_msg) = messages.last()
+                    && let Err(e) = logger.lock().await.log_regeneration(user_msg, discarded)
                 {
-                    let _ = agent_tx.send(AgentEvent::Error(e.to_string())).await;
+                    eprintln!("Warning: failed to log regeneration: {}", e);
                 }
+            }
+        }
 
-                // Check if compaction is needed before signaling Done, so the
-                // TUI keeps streaming=true and blocks user input during compaction.
-                if compaction::needs_compaction(
-                    &messages,
-                    &params.model,
-                    &params.compaction_config,
-                ) {
-                    let _ = agent_tx.send(AgentEvent::CompactionStarted).await;
-                    let old_count = messages.len();
-
-                    match compaction::run_compaction(
-                        &params.client,
-                        &params.model,
-                        params.max_tokens,
-                        &messages,
-                    )
-                    .await
-                    {
-                        Ok(summary_text) => {
-                            let user_messages = compaction::collect_user_messages(&messages);
-                            let compacted = compaction::build_compacted_history(
-                                &user_messages,
-                                &summary_text,
-                                params.compaction_config.user_message_budget_tokens,
-                            );
-                            let new_count = compacted.len();
-                            messages = compacted;
-                            let _ = agent_tx
-                                .send(AgentEvent::CompactionDone {
-                                    old_count,
-                                    new_count,
-                                })
-                                .await;
-                        }
-                        Err(e) => {
-                            let _ = agent_tx
-                                .send(AgentEvent::Error(format!("Compaction failed: {}", e)))
-                                .await;
-                        }
-                    }
+        // Enter the LLM conversation loop. After each round of tool calls,
+        // we re-send the updated conversation to the LLM. The turn races
+        // against the user channel so Ctrl+C (Cancel) or Quit can abort it.
+        autosave.start_turn(Instant::now());
+        let turn_prompt = with_context_note(&system_prompt, context_note.take());
+        let outcome = {
+            let turn = conversation_turn(
+                &params.client,
+                &params.registry,
+                &params.engine,
+                &params.model,
+                params.max_tokens,
+                params.approval_timeout_seconds,
+                &params.tools_config,
+                &mcp,
+                &reasoning,
+                &turn_prompt,
+                &mut messages,
+                &agent_tx,
+                &params.session_logger,
+                &mut streaming,
+                &params.timeouts,
+                &mut autosave,
+                &save_progress,
+                observe,
+                params.token_budget,
+                &mut fallbacks,
+                &params.compaction_config,
+                &params.shutdown,
+            );
+            tokio::pin!(turn);
+            loop {
+                tokio::select! {
+                    result = &mut turn => break match result {
+                        // Shutdown closed a prompt mid-turn: end it like a quit.
+                        Err(e) if e.is::<TurnAborted>() => TurnOutcome::Quit,
+                        result => TurnOutcome::Finished(result),
+                    },
+                    event = user_rx.recv() => match event {
+                        Some(UserEvent::Cancel) => break TurnOutcome::Cancelled,
+                        Some(UserEvent::Quit) | None => break TurnOutcome::Quit,
+                        // The TUI queues messages and refuses /reload and /regen
+                        // while a turn is running.
+                        Some(
+                            UserEvent::Message(_)
+                            | UserEvent::Reload
+                            | UserEvent::Regenerate { .. }
+                            | UserEvent::SetEffort(_)
+                            | UserEvent::SetObserve(_)
+                            | UserEvent::Forget { .. }
+                            | UserEvent::Pin { .. },
+                        ) => {}
+                    },
                 }
+            }
+        };
 
-                let _ = agent_tx.send(AgentEvent::Done).await;
-
-                // Save session state after each complete turn.
-                save_session(
-                    &params.workspace_dir,
-                    &SessionState {
-                        workspace_dir: params.workspace_dir.to_string_lossy().to_string(),
-                        model: params.model.clone(),
-                        created_at: created_at.clone(),
-                        updated_at: chrono::Utc::now().to_rfc3339(),
-                        messages: messages.clone(),
-                        total_tokens: 0,
-                    },
-                )
-                .ok();
+        match outcome {
+            TurnOutcome::Finished(Ok(())) => {}
+            TurnOutcome::Finished(Err(e)) => {
+                let _ = agent_tx.send(AgentEvent::Error(e.to_string())).await;
+            }
+            TurnOutcome::Cancelled | TurnOutcome::Quit => {
+                if let Some(closing) = close_dangling_tool_calls(&messages, "Cancelled by user") {
+                    maybe_log_message(&params.session_logger, &closing).await;
+                    messages.push(closing);
+                }
+            }
+        }
+
+        let current_head = read_head(&params.workspace_dir).await;
+        *head.lock().unwrap() = current_head;
+        if matches!(outcome, TurnOutcome::Quit) {
+            save_progress(&messages);
+            break;
+        }
+
+        if matches!(outcome, TurnOutcome::Cancelled) {
+            let _ = agent_tx.send(AgentEvent::TurnCancelled).await;
+        }
+
+        // Check if compaction is needed before signaling Done, so the
+        // TUI keeps streaming=true and blocks user input during compaction.
+        if !matches!(outcome, TurnOutcome::Cancelled)
+            && compaction::needs_compaction(&messages, &params.model, &params.compaction_config)
+        {
+            compact_and_report(
+                &params.client,
+                &params.model,
+                params.max_tokens,
+                &params.compaction_config,
+                &params.session_logger,
+                params.session_format,
+                &mut messages,
+                &pins,
+                &agent_tx,
+                None,
+            )
+            .await;
+        }
+
+        let _ = agent_tx.send(AgentEvent::Done).await;
+
+        // Save session state after each complete turn.
+        save_progress(&messages);
+    }
+}
+
+/// Answer the tool calls a resumed history's last reply never got results
+/// for: with `Ask`, the user decides whether they run now (still through
+/// the approval engine); with `Fail`, or when the user says no, they're
+/// closed with errors. Returns whether there were any.
+#[allow(clippy::too_many_arguments)]
+async fn settle_pending_tool_calls(
+    mode: PendingToolCalls,
+    messages: &mut Vec<Message>,
+    registry: &Registry,
+    engine: &Arc<ApprovalEngine>,
+    approval_timeout_seconds: u64,
+    tools_config: &ToolsConfig,
+    observe: bool,
+    session_logger: &Option<Arc<Mutex<SessionLogger>>>,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    shutdown: &ShutdownFlag,
+) -> bool {
+    let Some(closing) = close_dangling_tool_calls(messages, INTERRUPTED_RESULT) else {
+        return false;
+    };
+    let pending: Vec<String> = messages[messages.len() - 1]
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { name, input, .. } => {
+                Some(describe_tool_call(name, &engine.display_params(input)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let run = mode == PendingToolCalls::Ask && {
+        let (tx, rx) = oneshot::channel();
+        let question = match pending.as_slice() {
+            [call] => format!(
+                "A pending tool call from your previous session: {} \u{2014} run it now?",
+                call
+            ),
+            calls => format!(
+                "{} pending tool calls from your previous session: {} \u{2014} run them now?",
+                calls.len(),
+                calls.join(", ")
+            ),
+        };
+        let _ = agent_tx
+            .send(AgentEvent::AskUser {
+                question,
+                tool_call_id: PENDING_QUESTION_ID.to_string(),
+                options: Vec::new(),
+                style: QuestionStyle::Confirm { default_yes: false },
+                responder: tx,
+            })
+            .await;
+        rx.await.is_ok_and(|answer| answer == CONFIRM_YES)
+    };
+
+    let (results, approvals) = if run {
+        let blocks = messages[messages.len() - 1].content.clone();
+        let mut cache = ToolCache::new(&tools_config.cache_reads);
+        let executed = execute_tool_calls(
+            &blocks,
+            registry,
+            engine,
+            approval_timeout_seconds,
+            tools_config,
+            observe,
+            &mut cache,
+            agent_tx,
+            shutdown,
+        )
+        .await;
+        (Message::tool_results(executed.results), executed.approvals)
+    } else {
+        let closing = match mode {
+            PendingToolCalls::Ask => close_dangling_tool_calls(messages, DECLINED_PENDING_RESULT)
+                .expect("the last message has tool calls"),
+            PendingToolCalls::Fail => closing,
+        };
+        let _ = agent_tx
+            .send(AgentEvent::Notice(format!(
+                "Not running {} tool call{} left pending by the previous session: {}",
+                pending.len(),
+                if pending.len() == 1 { "" } else { "s" },
+                pending.join(", ")
+            )))
+            .await;
+        (closing, BTreeMap::new())
+    };
+    maybe_log_tool_results(session_logger, &results, &approvals).await;
+    messages.push(results);
+    true
+}
+
+/// Compact the history and tell the TUI how it went, from CompactionStarted
+/// to CompactionDone, a skip notice, or an error.
+#[allow(clippy::too_many_arguments)]
+async fn compact_and_report(
+    client: &Arc<dyn LlmClient>,
+    model: &str,
+    max_tokens: u32,
+    config: &CompactionConfig,
+    session_logger: &Option<Arc<Mutex<SessionLogger>>>,
+    session_format: SessionFormat,
+    messages: &mut Vec<Message>,
+    pins: &Pins,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    fit_budget: Option<usize>,
+) {
+    let _ = agent_tx.send(AgentEvent::CompactionStarted).await;
+    let old_count = messages.len();
+
+    let summarizer = Summarizer {
+        client,
+        model,
+        max_tokens,
+    };
+    match compact_history(&summarizer, config, messages, pins, agent_tx, fit_budget).await {
+        Ok(None) => {
+            let _ = agent_tx
+                .send(AgentEvent::Notice(
+                    "Compaction skipped; history unchanged".to_string(),
+                ))
+                .await;
+        }
+        Ok(Some(new_count)) => {
+            // Without session.json the log is the only record, so
+            // it has to carry the compacted history forward.
+            if !session_format.writes_json()
+                && let Some(logger) = session_logger
+            {
+                let _ = logger.lock().await.log_history(messages, &pins.indexes());
+            }
+            let _ = agent_tx
+                .send(AgentEvent::CompactionDone {
+                    old_count,
+                    new_count,
+                })
+                .await;
+        }
+        Err(e) => {
+            let _ = agent_tx
+                .send(AgentEvent::Error(format!("Compaction failed: {}", e)))
+                .await;
+        }
+    }
+}
+
+/// Summarize the conversation and replace it with the compacted history. With
+/// `[compaction] review = "ask"` the user sees the summary first and can
+/// accept, edit, or skip it. Returns the new message count, or None when the
+/// user skipped and the history was left untouched.
+///
+/// With `fit_budget`, for a history larger than the model's window, only the
+/// recent turns that fit are summarized and the retained user messages get at
+/// most half the budget. The messages in `pins` are kept first, and the pins
+/// move to where they ended up.
+async fn compact_history(
+    summarizer: &Summarizer<'_>,
+    config: &CompactionConfig,
+    messages: &mut Vec<Message>,
+    pins: &Pins,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    fit_budget: Option<usize>,
+) -> anyhow::Result<Option<usize>> {
+    let Summarizer {
+        client,
+        model,
+        max_tokens,
+    } = *summarizer;
+    let (start, user_budget) = match fit_budget {
+        Some(budget) => {
+            let start = compaction::recent_turns_start(messages, budget);
+            if start == messages.len() {
+                anyhow::bail!("even the last turn is too large for {}", model);
+            }
+            (start, config.user_message_budget_tokens.min(budget / 2))
+        }
+        None => (0, config.user_message_budget_tokens),
+    };
+    // Progress is best-effort: a report is dropped rather than waited on.
+    let report = |tokens| {
+        let _ = agent_tx.try_send(AgentEvent::CompactionProgress { tokens });
+    };
+    let summary =
+        compaction::run_compaction(client, model, max_tokens, &messages[start..], report).await?;
+    let summary = match config.review {
+        CompactionReview::Auto => summary,
+        CompactionReview::Ask => {
+            let (tx, rx) = oneshot::channel();
+            let _ = agent_tx
+                .send(AgentEvent::CompactionSummaryReady {
+                    summary: summary.clone(),
+                    responder: tx,
+                })
+                .await;
+            // A prompt dropped unanswered counts as a skip.
+            match rx.await.unwrap_or(SummaryDecision::Skip) {
+                SummaryDecision::Accept => summary,
+                SummaryDecision::Edit(edited) => edited,
+                SummaryDecision::Skip => return Ok(None),
             }
         }
+    };
+    let (turns, pinned) = compaction::collect_turns(messages, config.retain, &pins.indexes());
+    let compacted = compaction::build_compacted_dialogue(&turns, &summary, user_budget, &pinned);
+    *messages = compacted.messages;
+    pins.set(compacted.pinned);
+    Ok(Some(messages.len()))
+}
+
+/// The model compaction asks for the summary.
+#[derive(Clone, Copy)]
+struct Summarizer<'a> {
+    client: &'a Arc<dyn LlmClient>,
+    model: &'a str,
+    max_tokens: u32,
+}
+
+/// The workspace's HEAD commit, read on a blocking thread.
+async fn read_head(workspace_dir: &Path) -> Option<String> {
+    let workspace_dir = workspace_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || head_commit(&workspace_dir))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Rewrite `session.json` with the current conversation, unless the session
+/// format is JSONL-only. Only the agent loop task calls this, one save at a
+/// time, and each save is a tmp-file write plus rename, so a crash mid-save
+/// leaves the previous file intact. `head_commit` is HEAD as last read.
+fn save_session_state(
+    format: SessionFormat,
+    workspace_dir: &Path,
+    model: &str,
+    created_at: &str,
+    messages: &[Message],
+    pins: &[usize],
+    head_commit: Option<String>,
+) {
+    if !format.writes_json() {
+        return;
     }
+    save_session(
+        workspace_dir,
+        &SessionState {
+            workspace_dir: workspace_dir.to_string_lossy().to_string(),
+            model: model.to_string(),
+            created_at: created_at.to_string(),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            messages: messages.to_vec(),
+            total_tokens: 0,
+            pins: pins.to_vec(),
+            head_commit,
+        },
+    )
+    .ok();
 }
 
 /// Execute one full conversation turn: stream LLM response, handle tool calls,
-/// and loop back if the LLM stopped due to tool use.
+/// and loop back if the LLM stopped due to tool use. When `autosave` says so,
+/// the history is saved with `save` after a batch of tool results. In
+/// `observe` mode every tool call is refused. Once the turn has used
+/// `token_budget` tokens the user is asked before each further tool round.
+/// Fails with [`TurnAborted`] when an approval prompt closed because the
+/// session is shutting down.
 #[allow(clippy::too_many_arguments)]
 async fn conversation_turn(
     client: &Arc<dyn LlmClient>,
@@ -173,21 +801,73 @@ async fn conversation_turn(
     model: &str,
     max_tokens: u32,
     approval_timeout_seconds: u64,
+    tools_config: &ToolsConfig,
+    mcp: &McpRoster,
+    reasoning: &Reasoning,
     system_prompt: &str,
     messages: &mut Vec<Message>,
     agent_tx: &mpsc::Sender<AgentEvent>,
     session_logger: &Option<Arc<Mutex<SessionLogger>>>,
+    streaming: &mut StreamingMode,
+    timeouts: &RequestTimeouts,
+    autosave: &mut AutoSave,
+    save: &(dyn Fn(&[Message]) + Sync),
+    observe: bool,
+    token_budget: Option<u64>,
+    fallbacks: &mut Fallbacks,
+    compaction_config: &CompactionConfig,
+    shutdown: &ShutdownFlag,
 ) -> anyhow::Result<()> {
+    // Idempotent reads repeated within this turn reuse the first result.
+    let mut cache = ToolCache::new(&tools_config.cache_reads);
+    let mut budget = TurnBudget::new(token_budget);
+    // The configured model answers until it fails; then each fallback in
+    // turn, for the rest of this turn.
+    let mut client = Arc::clone(client);
+    let mut model = model.to_string();
+    let mut answered_by: Option<String> = None;
+    let mut next_fallback = 0;
     loop {
-        let tool_defs = registry.to_definitions().await;
-
-        let request = Request::new(model)
-            .system(system_prompt)
-            .max_tokens(max_tokens)
-            .messages(messages.iter().cloned())
-            .tools(tool_defs);
-
-        let (assistant_blocks, stop_reason) = stream_response(client, &request, agent_tx).await?;
+        let mut tool_defs = registry.to_definitions().await;
+        tool_defs.retain(|d| !tools_config.is_disabled(&d.name) && !mcp.is_withdrawn(&d.name));
+
+        let reply = loop {
+            // A fallback may have a smaller window than the history was
+            // sized for.
+            let history = match answered_by {
+                Some(_) => fitting_history(messages, &model, compaction_config),
+                None => &messages[..],
+            };
+            let request = Request::new(&model)
+                .system(system_prompt)
+                .max_tokens(max_tokens)
+                .messages(history.iter().cloned())
+                .tools(tool_defs.clone());
+            let request = effort::apply(request, reasoning.setting(&model, max_tokens));
+
+            match fetch_response(&client, &request, agent_tx, streaming, timeouts).await {
+                Ok(reply) => break reply,
+                // Part of the reply is already on screen; starting it over
+                // elsewhere would show it twice.
+                Err(e) if e.is::<StreamBrokeOff>() => return Err(e),
+                Err(e) => {
+                    let Some((fallback, fallback_client)) =
+                        fall_back(fallbacks, &mut next_fallback, &e, agent_tx).await
+                    else {
+                        return Err(e);
+                    };
+                    client = fallback_client;
+                    model = fallback.model.clone();
+                    answered_by = Some(fallback.to_string());
+                }
+            }
+        };
+        let Reply {
+            blocks: assistant_blocks,
+            stop_reason,
+            tokens,
+        } = reply;
+        budget.record(tokens);
 
         // Record the assistant's response in conversation history.
         if !assistant_blocks.is_empty() {
@@ -195,25 +875,65 @@ async fn conversation_turn(
                 role: Role::Assistant,
                 content: assistant_blocks.clone(),
             };
-            maybe_log_message(session_logger, &assistant_msg).await;
+            maybe_log_answer(session_logger, &assistant_msg, answered_by.as_deref()).await;
             messages.push(assistant_msg);
         }
 
+        // A refusal or filtered reply would otherwise end the turn blank.
+        if let Some(declined) = Declined::of(stop_reason.as_ref(), &assistant_blocks) {
+            let _ = agent_tx
+                .send(AgentEvent::Notice(declined.to_string()))
+                .await;
+            break;
+        }
+
         // If the LLM stopped because of tool use, execute tools and continue.
         if stop_reason == Some(StopReason::ToolUse) {
-            let tool_results = execute_tool_calls(
+            // Over budget: ask before running the tools, whose results would
+            // cost another round-trip.
+            if budget.exceeded() {
+                match ask_to_continue(&budget, agent_tx).await {
+                    BudgetChoice::Continue => {}
+                    BudgetChoice::ContinueUnasked => budget.stop_asking(),
+                    BudgetChoice::Stop => {
+                        if let Some(closing) = close_dangling_tool_calls(messages, STOPPED_RESULT) {
+                            maybe_log_message(session_logger, &closing).await;
+                            messages.push(closing);
+                        }
+                        let _ = agent_tx
+                            .send(AgentEvent::Notice(budget.stopped_notice()))
+                            .await;
+                        break;
+                    }
+                }
+            }
+
+            let executed = execute_tool_calls(
                 &assistant_blocks,
                 registry,
                 engine,
                 approval_timeout_seconds,
+                tools_config,
+                observe,
+                &mut cache,
                 agent_tx,
+                shutdown,
             )
             .await;
 
-            if !tool_results.is_empty() {
-                let tool_msg = Message::tool_results(tool_results);
-                maybe_log_message(session_logger, &tool_msg).await;
+            if !executed.results.is_empty() {
+                let count = executed.results.len();
+                let tool_msg = Message::tool_results(executed.results);
+                maybe_log_tool_results(session_logger, &tool_msg, &executed.approvals).await;
                 messages.push(tool_msg);
+                if autosave.record_results(count, Instant::now()) {
+                    save(messages);
+                }
+            }
+
+            // Quitting: the model is not asked again.
+            if executed.aborted {
+                return Err(TurnAborted.into());
             }
 
             // Loop back to send updated conversation to LLM.
@@ -227,69 +947,357 @@ async fn conversation_turn(
     Ok(())
 }
 
-/// Stream a single LLM response, forwarding text deltas and accumulating
-/// content blocks (text + tool use). Returns the assembled content blocks
-/// and the stop reason.
-async fn stream_response(
-    client: &Arc<dyn LlmClient>,
-    request: &Request,
+/// Move a failed turn to the next fallback whose client can be created,
+/// telling the user why; fallbacks that can't be reached are reported and
+/// skipped. None once the chain is used up.
+async fn fall_back(
+    fallbacks: &mut Fallbacks,
+    next: &mut usize,
+    error: &anyhow::Error,
     agent_tx: &mpsc::Sender<AgentEvent>,
-) -> anyhow::Result<(Vec<ContentBlock>, Option<StopReason>)> {
-    let mut stream = client.create_message_stream(request);
-
-    let mut blocks: Vec<ContentBlock> = Vec::new();
-    let mut pending_tools: HashMap<usize, PendingToolCall> = HashMap::new();
-    let mut stop_reason: Option<StopReason> = None;
-    let mut current_text = String::new();
-
-    while let Some(event_result) = stream.next().await {
-        let event = match event_result {
-            Ok(e) => e,
+) -> Option<(FallbackModel, Arc<dyn LlmClient>)> {
+    while *next < fallbacks.len() {
+        let index = *next;
+        *next += 1;
+        let fallback = fallbacks.model(index).clone();
+        match fallbacks.client(index) {
+            Ok(client) => {
+                let _ = agent_tx
+                    .send(AgentEvent::Notice(format!(
+                        "{}; falling back to {}",
+                        error, fallback
+                    )))
+                    .await;
+                return Some((fallback, client));
+            }
             Err(e) => {
                 let _ = agent_tx
-                    .send(AgentEvent::Error(format!("Stream error: {}", e)))
+                    .send(AgentEvent::Notice(format!(
+                        "Skipping fallback {}: {}",
+                        fallback, e
+                    )))
                     .await;
-                return Err(e.into());
             }
-        };
+        }
+    }
+    None
+}
 
-        match event {
-            StreamEvent::MessageStart { .. } => {}
+/// Ask the user whether a turn over its token budget should go on, through
+/// the ask_user prompt. A prompt closed without an answer stops the turn.
+async fn ask_to_continue(budget: &TurnBudget, agent_tx: &mpsc::Sender<AgentEvent>) -> BudgetChoice {
+    let (tx, rx) = oneshot::channel();
+    let _ = agent_tx
+        .send(AgentEvent::AskUser {
+            question: budget.question(),
+            tool_call_id: BUDGET_QUESTION_ID.to_string(),
+            options: TurnBudget::options(),
+            style: QuestionStyle::Open,
+            responder: tx,
+        })
+        .await;
+    match rx.await {
+        Ok(answer) => BudgetChoice::from_answer(&answer),
+        Err(_) => BudgetChoice::Stop,
+    }
+}
 
-            StreamEvent::ContentBlockStart { index, block } => {
-                match &block {
-                    ContentBlock::ToolUse { id, name, .. } => {
-                        // Finalize any accumulated text before tool blocks.
-                        if !current_text.is_empty() {
-                            blocks.push(ContentBlock::text(&current_text));
-                            let _ = agent_tx.send(AgentEvent::TextDone).await;
-                            current_text.clear();
-                        }
-                        pending_tools.insert(
-                            index,
-                            PendingToolCall {
-                                id: id.clone(),
-                                name: name.clone(),
-                                json_buf: String::new(),
-                            },
-                        );
-                    }
-                    ContentBlock::Text { .. } => {
-                        // Text block start — nothing special to do here.
-                    }
-                    _ => {}
-                }
-            }
+/// The turn ended because the session is shutting down, not because of an
+/// error worth reporting.
+#[derive(Debug)]
+struct TurnAborted;
 
-            StreamEvent::ContentBlockDelta { index: _, text } => {
-                current_text.push_str(&text);
-                let _ = agent_tx.send(AgentEvent::TextDelta(text)).await;
-            }
+impl std::fmt::Display for TurnAborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "turn aborted: shutting down")
+    }
+}
 
-            StreamEvent::InputJsonDelta {
-                index,
-                partial_json,
-            } => {
+impl std::error::Error for TurnAborted {}
+
+/// Tool result for the calls left when a turn is aborted by shutdown.
+const SHUTDOWN_RESULT: &str = "Cancelled: soloclaw is shutting down";
+
+/// One LLM response: the blocks to record in history, why it stopped, and
+/// the tokens its usage reports added up to.
+struct Reply {
+    blocks: Vec<ContentBlock>,
+    stop_reason: Option<StopReason>,
+    tokens: u64,
+}
+
+/// The provider's stream failed before yielding a single event. In
+/// `streaming = "auto"` mode this triggers the non-streaming fallback.
+#[derive(Debug)]
+struct StreamStartError(String);
+
+impl std::fmt::Display for StreamStartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stream error: {}", self.0)
+    }
+}
+
+impl std::error::Error for StreamStartError {}
+
+/// The provider's stream failed after part of the reply was shown.
+#[derive(Debug)]
+struct StreamBrokeOff(anyhow::Error);
+
+impl std::fmt::Display for StreamBrokeOff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for StreamBrokeOff {}
+
+/// `error`, marked as breaking off a reply when part of it was `shown`.
+fn broke_off(error: anyhow::Error, shown: bool) -> anyhow::Error {
+    if shown {
+        StreamBrokeOff(error).into()
+    } else {
+        error
+    }
+}
+
+/// Whether a stream that failed before its first event failed because the
+/// provider (or a proxy in front of it) can't stream, rather than for a
+/// reason a non-streaming request would hit too: bad credentials, rate
+/// limits, the network. The provider's error is all there is to go on.
+fn streaming_unsupported(error: &str) -> bool {
+    let error = error.to_lowercase();
+    let about_streaming = [
+        "streaming",
+        "event-stream",
+        "\"stream\"",
+        "'stream'",
+        "`stream`",
+    ]
+    .iter()
+    .any(|word| error.contains(word));
+    let unsupported = [
+        "not supported",
+        "unsupported",
+        "not implemented",
+        "does not support",
+        "doesn't support",
+        "not enabled",
+        "not available",
+        "not allowed",
+    ]
+    .iter()
+    .any(|phrase| error.contains(phrase));
+    about_streaming && unsupported
+}
+
+/// Get one LLM response according to the streaming mode. In auto mode a
+/// stream the provider says it can't serve switches the session to
+/// non-streaming requests for good and retries the request that way; any
+/// other failure is reported as it is.
+async fn fetch_response(
+    client: &Arc<dyn LlmClient>,
+    request: &Request,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    streaming: &mut StreamingMode,
+    timeouts: &RequestTimeouts,
+) -> anyhow::Result<Reply> {
+    match *streaming {
+        StreamingMode::Off => complete_response(client, request, agent_tx, timeouts).await,
+        StreamingMode::On => stream_response(client, request, agent_tx, timeouts).await,
+        StreamingMode::Auto => match stream_response(client, request, agent_tx, timeouts).await {
+            Err(e)
+                if e.downcast_ref::<StreamStartError>()
+                    .is_some_and(|start| streaming_unsupported(&start.0)) =>
+            {
+                *streaming = StreamingMode::Off;
+                let _ = agent_tx
+                    .send(AgentEvent::Notice(format!(
+                        "{}; switching to non-streaming requests for this session",
+                        e
+                    )))
+                    .await;
+                complete_response(client, request, agent_tx, timeouts).await
+            }
+            other => other,
+        },
+    }
+}
+
+/// Make a single non-streaming request and replay the response through the
+/// same events the streaming path emits, so the TUI can't tell the difference.
+async fn complete_response(
+    client: &Arc<dyn LlmClient>,
+    request: &Request,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    timeouts: &RequestTimeouts,
+) -> anyhow::Result<Reply> {
+    let response = timeouts::within(timeouts.request, client.create_message(request)).await??;
+    let blocks = emit_response_blocks(&response.content, agent_tx).await;
+
+    if let Some(event) = usage_event(&response.usage) {
+        let _ = agent_tx.send(event).await;
+    }
+
+    Ok(Reply {
+        blocks,
+        stop_reason: Some(response.stop_reason),
+        tokens: usage_tokens(&response.usage),
+    })
+}
+
+/// Every token a usage report counts: input, output, and cache reads and writes.
+fn usage_tokens(usage: &Usage) -> u64 {
+    u64::from(usage.input_tokens)
+        + u64::from(usage.output_tokens)
+        + u64::from(usage.cache_read_tokens)
+        + u64::from(usage.cache_write_tokens)
+}
+
+/// The Usage event for a response, or `None` if the provider reported no
+/// tokens at all. Cache reads and writes are passed through separately
+/// because they are priced differently from plain input.
+fn usage_event(usage: &Usage) -> Option<AgentEvent> {
+    (usage_tokens(usage) > 0).then(|| AgentEvent::Usage {
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_read_tokens: usage.cache_read_tokens,
+        cache_write_tokens: usage.cache_write_tokens,
+    })
+}
+
+/// Forward a complete response's content as TextDelta/TextDone events and
+/// return the blocks to record in history. Text is sent line by line for
+/// smoother rendering, and adjacent text blocks are merged exactly as the
+/// streaming path merges them.
+async fn emit_response_blocks(
+    content: &[ContentBlock],
+    agent_tx: &mpsc::Sender<AgentEvent>,
+) -> Vec<ContentBlock> {
+    let mut blocks: Vec<ContentBlock> = Vec::new();
+    let mut current_text = String::new();
+
+    for block in content {
+        match block {
+            ContentBlock::Text { text } => {
+                for chunk in text.split_inclusive('\n') {
+                    let _ = agent_tx
+                        .send(AgentEvent::TextDelta(chunk.to_string()))
+                        .await;
+                }
+                current_text.push_str(text);
+            }
+            ContentBlock::ToolUse { .. } => {
+                if !current_text.is_empty() {
+                    blocks.push(ContentBlock::text(&current_text));
+                    let _ = agent_tx.send(AgentEvent::TextDone).await;
+                    current_text.clear();
+                }
+                blocks.push(block.clone());
+            }
+            _ => {}
+        }
+    }
+
+    if !current_text.is_empty() {
+        blocks.push(ContentBlock::text(&current_text));
+        let _ = agent_tx.send(AgentEvent::TextDone).await;
+    }
+
+    blocks
+}
+
+/// Stream a single LLM response, forwarding text deltas and accumulating
+/// content blocks (text + tool use). Returns the assembled content blocks
+/// and the stop reason.
+async fn stream_response(
+    client: &Arc<dyn LlmClient>,
+    request: &Request,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    timeouts: &RequestTimeouts,
+) -> anyhow::Result<Reply> {
+    consume_stream(client.create_message_stream(request), agent_tx, timeouts).await
+}
+
+/// Drive a stream of provider events to completion (see `stream_response`),
+/// giving up with a `RequestTimeout` when the provider goes quiet.
+async fn consume_stream<S, E>(
+    stream: S,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+    timeouts: &RequestTimeouts,
+) -> anyhow::Result<Reply>
+where
+    S: futures::Stream<Item = Result<StreamEvent, E>>,
+    E: Into<anyhow::Error> + std::fmt::Display,
+{
+    let mut stream = std::pin::pin!(stream);
+
+    let mut blocks: Vec<ContentBlock> = Vec::new();
+    let mut pending_tools: HashMap<usize, PendingToolCall> = HashMap::new();
+    let mut stop_reason: Option<StopReason> = None;
+    let mut tokens: u64 = 0;
+    let mut current_text = String::new();
+    let mut received_any = false;
+    // Whether any of the reply's text has gone out to the TUI.
+    let mut shown = false;
+
+    while let Some(event_result) = timeouts::next_within(&mut stream, timeouts, received_any)
+        .await
+        .map_err(|e| broke_off(e.into(), shown))?
+    {
+        let event = match event_result {
+            Ok(e) => e,
+            Err(e) if !received_any => {
+                return Err(StreamStartError(e.to_string()).into());
+            }
+            // Nothing shown yet: the caller may still retry elsewhere.
+            Err(e) if !shown => return Err(e.into()),
+            Err(e) => {
+                let _ = agent_tx
+                    .send(AgentEvent::Error(format!("Stream error: {}", e)))
+                    .await;
+                return Err(broke_off(e.into(), shown));
+            }
+        };
+        received_any = true;
+
+        match event {
+            StreamEvent::MessageStart { .. } => {}
+
+            StreamEvent::ContentBlockStart { index, block } => {
+                match &block {
+                    ContentBlock::ToolUse { id, name, .. } => {
+                        // Finalize any accumulated text before tool blocks.
+                        if !current_text.is_empty() {
+                            blocks.push(ContentBlock::text(&current_text));
+                            let _ = agent_tx.send(AgentEvent::TextDone).await;
+                            current_text.clear();
+                        }
+                        pending_tools.insert(
+                            index,
+                            PendingToolCall {
+                                id: id.clone(),
+                                name: name.clone(),
+                                json_buf: String::new(),
+                            },
+                        );
+                    }
+                    ContentBlock::Text { .. } => {
+                        // Text block start — nothing special to do here.
+                    }
+                    _ => {}
+                }
+            }
+
+            StreamEvent::ContentBlockDelta { index: _, text } => {
+                current_text.push_str(&text);
+                shown = true;
+                let _ = agent_tx.send(AgentEvent::TextDelta(text)).await;
+            }
+
+            StreamEvent::InputJsonDelta {
+                index,
+                partial_json,
+            } => {
                 if let Some(tool) = pending_tools.get_mut(&index) {
                     tool.json_buf.push_str(&partial_json);
                 }
@@ -316,14 +1324,9 @@ async fn stream_response(
                 if let Some(reason) = sr {
                     stop_reason = Some(reason);
                 }
-                let total = usage.input_tokens + usage.output_tokens;
-                if total > 0 {
-                    let _ = agent_tx
-                        .send(AgentEvent::Usage {
-                            input_tokens: usage.input_tokens,
-                            output_tokens: usage.output_tokens,
-                        })
-                        .await;
+                tokens += usage_tokens(&usage);
+                if let Some(event) = usage_event(&usage) {
+                    let _ = agent_tx.send(event).await;
                 }
             }
 
@@ -344,19 +1347,46 @@ async fn stream_response(
         let _ = agent_tx.send(AgentEvent::TextDone).await;
     }
 
-    Ok((blocks, stop_reason))
+    Ok(Reply {
+        blocks,
+        stop_reason,
+        tokens,
+    })
+}
+
+/// What running a response's tool calls produced.
+struct ExecutedTools {
+    /// Tool result blocks to send back to the LLM.
+    results: Vec<ContentBlock>,
+    /// Why each approved call ran, keyed by tool call id: an auto-approval
+    /// reason, or "user" when the user allowed it.
+    approvals: BTreeMap<String, String>,
+    /// An approval prompt closed because the session is shutting down; the
+    /// calls from there on were cancelled rather than run or denied.
+    aborted: bool,
 }
 
 /// Execute all tool calls from the assistant's content blocks, routing through
-/// the approval engine. Returns tool result content blocks to send back to the LLM.
+/// the approval engine. In `observe` mode none of them run. An approval
+/// prompt that closes unanswered is a denial, unless the session is shutting
+/// down (`shutdown`, or the TUI is gone), which aborts the rest of the calls.
+#[allow(clippy::too_many_arguments)]
 async fn execute_tool_calls(
     assistant_blocks: &[ContentBlock],
     registry: &Registry,
     engine: &Arc<ApprovalEngine>,
     approval_timeout_seconds: u64,
+    tools_config: &ToolsConfig,
+    observe: bool,
+    cache: &mut ToolCache,
     agent_tx: &mpsc::Sender<AgentEvent>,
-) -> Vec<ContentBlock> {
+    shutdown: &ShutdownFlag,
+) -> ExecutedTools {
     let mut results = Vec::new();
+    let mut approvals = BTreeMap::new();
+    let mut aborted = false;
+    // Decisions made in this turn, so repeated identical calls ask only once.
+    let mut decisions: HashMap<(String, String), ApprovalDecision> = HashMap::new();
 
     for block in assistant_blocks {
         let (id, name, input) = match block {
@@ -364,6 +1394,44 @@ async fn execute_tool_calls(
             _ => continue,
         };
 
+        // Every call still needs a result, so the history stays valid.
+        if aborted {
+            results.push(ContentBlock::tool_error(id, SHUTDOWN_RESULT.to_string()));
+            continue;
+        }
+
+        // A tool `[tools] disabled` hides was never offered, and in observe
+        // mode nothing runs; refuse before the approval engine or registry.
+        let refusal = if tools_config.is_disabled(name) {
+            Some((DISABLED_TOOL_ERROR, DISABLED_TOOL_ERROR))
+        } else if observe {
+            Some(("observe mode", OBSERVE_RESULT))
+        } else {
+            None
+        };
+        if let Some((reason, result)) = refusal {
+            let _ = agent_tx
+                .send(AgentEvent::ToolCallStarted {
+                    tool_call_id: id.clone(),
+                    tool_name: name.clone(),
+                    params_summary: summarize_params_within(
+                        name,
+                        &engine.display_params(input),
+                        tools_config.summary_max_chars,
+                    ),
+                })
+                .await;
+            let _ = agent_tx
+                .send(AgentEvent::ToolCallDenied {
+                    tool_call_id: id.clone(),
+                    tool_name: name.clone(),
+                    reason: reason.to_string(),
+                })
+                .await;
+            results.push(ContentBlock::tool_error(id, result));
+            continue;
+        }
+
         // Intercept ask_user tool calls — bypass approval engine entirely.
         if name == ASK_USER_TOOL_NAME {
             let question = input
@@ -381,6 +1449,7 @@ async fn execute_tool_calls(
                         .collect()
                 })
                 .unwrap_or_default();
+            let style = QuestionStyle::from_input(input);
 
             let (tx, rx) = oneshot::channel();
             let _ = agent_tx
@@ -388,6 +1457,7 @@ async fn execute_tool_calls(
                     question,
                     tool_call_id: id.clone(),
                     options,
+                    style,
                     responder: tx,
                 })
                 .await;
@@ -402,9 +1472,14 @@ async fn execute_tool_calls(
             continue;
         }
 
-        let params_summary = summarize_params(input);
+        let params_summary = summarize_params_within(
+            name,
+            &engine.display_params(input),
+            tools_config.summary_max_chars,
+        );
         let _ = agent_tx
             .send(AgentEvent::ToolCallStarted {
+                tool_call_id: id.clone(),
                 tool_name: name.clone(),
                 params_summary,
             })
@@ -418,21 +1493,25 @@ async fn execute_tool_calls(
         let outcome = engine.check(&info);
 
         match outcome {
-            EngineOutcome::Allowed => {
+            EngineOutcome::Allowed { reason } => {
+                approvals.insert(id.clone(), reason.to_string());
                 let _ = agent_tx
                     .send(AgentEvent::ToolCallApproved {
+                        tool_call_id: id.clone(),
                         tool_name: name.clone(),
+                        reason: Some(reason),
                     })
                     .await;
 
-                let result = execute_single_tool(registry, name, input).await;
-                send_tool_result(agent_tx, name, &result).await;
+                let result =
+                    run_approved(registry, id, name, input, tools_config, cache, agent_tx).await;
                 results.push(tool_result_to_block(id, &result));
             }
 
             EngineOutcome::Denied { reason } => {
                 let _ = agent_tx
                     .send(AgentEvent::ToolCallDenied {
+                        tool_call_id: id.clone(),
                         tool_name: name.clone(),
                         reason: reason.clone(),
                     })
@@ -443,51 +1522,101 @@ async fn execute_tool_calls(
             EngineOutcome::NeedsApproval {
                 description,
                 pattern,
+                similar,
+                scopes,
             } => {
-                let (tx, rx) = oneshot::channel();
-                let _ = agent_tx
-                    .send(AgentEvent::ToolCallNeedsApproval {
-                        description,
-                        pattern: pattern.clone(),
-                        tool_name: name.clone(),
-                        responder: tx,
-                    })
-                    .await;
+                let memo_key = approval_memo_key(name, input);
+                let decision = if let Some(&earlier) = decisions.get(&memo_key) {
+                    let _ = agent_tx
+                        .send(AgentEvent::Notice(format!(
+                            "{}: auto-applied your earlier decision",
+                            description.lines().next().unwrap_or_default()
+                        )))
+                        .await;
+                    earlier
+                } else {
+                    let (tx, rx) = oneshot::channel();
+                    let _ = agent_tx
+                        .send(AgentEvent::ToolCallNeedsApproval {
+                            tool_call_id: id.clone(),
+                            description,
+                            pattern: pattern.clone(),
+                            similar,
+                            scopes: scopes.clone(),
+                            tool_name: name.clone(),
+                            responder: tx,
+                        })
+                        .await;
 
-                // Wait for user decision with timeout.
-                let decision =
-                    match tokio::time::timeout(Duration::from_secs(approval_timeout_seconds), rx)
-                        .await
+                    // Wait for user decision with timeout.
+                    let decision = match tokio::time::timeout(
+                        Duration::from_secs(approval_timeout_seconds),
+                        rx,
+                    )
+                    .await
                     {
                         Ok(Ok(decision)) => decision,
+                        Ok(Err(_)) if shutdown.is_requested() || agent_tx.is_closed() => {
+                            // The prompt went away with the TUI: stop here
+                            // rather than hand the model a denial to react to.
+                            aborted = true;
+                            results.push(ContentBlock::tool_error(id, SHUTDOWN_RESULT.to_string()));
+                            continue;
+                        }
                         Ok(Err(_)) => {
                             // Oneshot channel dropped — treat as deny.
                             ApprovalDecision::Deny
                         }
                         Err(_) => {
-                            // Timeout — treat as deny.
-                            ApprovalDecision::Deny
+                            // Timeout — tell the TUI to drop the dead prompt, then deny.
+                            let _ = agent_tx
+                                .send(AgentEvent::ApprovalExpired {
+                                    tool_call_id: id.clone(),
+                                    tool_name: name.clone(),
+                                })
+                                .await;
+                            results.push(ContentBlock::tool_error(
+                                id,
+                                "Denied: approval timed out".to_string(),
+                            ));
+                            continue;
                         }
                     };
 
-                // Record the decision in the engine for AllowAlways persistence.
-                engine.resolve(name, pattern.as_deref(), decision);
+                    // Record the decision in the engine for AllowAlways persistence;
+                    // a chosen directory scope is saved in place of the tool name.
+                    let pattern = match decision {
+                        ApprovalDecision::AllowScope(i) => scopes.get(i).map(|s| s.pattern.clone()),
+                        _ => pattern,
+                    };
+                    engine.resolve(name, pattern.as_deref(), decision);
+                    decisions.insert(memo_key, decision);
+                    decision
+                };
 
                 match decision {
-                    ApprovalDecision::AllowOnce | ApprovalDecision::AllowAlways => {
+                    ApprovalDecision::AllowOnce
+                    | ApprovalDecision::AllowAlways
+                    | ApprovalDecision::AllowTool
+                    | ApprovalDecision::AllowScope(_) => {
+                        approvals.insert(id.clone(), "user".to_string());
                         let _ = agent_tx
                             .send(AgentEvent::ToolCallApproved {
+                                tool_call_id: id.clone(),
                                 tool_name: name.clone(),
+                                reason: None,
                             })
                             .await;
 
-                        let result = execute_single_tool(registry, name, input).await;
-                        send_tool_result(agent_tx, name, &result).await;
+                        let result =
+                            run_approved(registry, id, name, input, tools_config, cache, agent_tx)
+                                .await;
                         results.push(tool_result_to_block(id, &result));
                     }
                     ApprovalDecision::Deny => {
                         let _ = agent_tx
                             .send(AgentEvent::ToolCallDenied {
+                                tool_call_id: id.clone(),
                                 tool_name: name.clone(),
                                 reason: "denied by user".to_string(),
                             })
@@ -499,7 +1628,213 @@ async fn execute_tool_calls(
         }
     }
 
-    results
+    ExecutedTools {
+        results,
+        approvals,
+        aborted,
+    }
+}
+
+/// Wait for the next MCP resync; never resolves when resyncing is off.
+async fn next_resync(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// `system_prompt` with `note` appended, when there is one.
+fn with_context_note(system_prompt: &str, note: Option<String>) -> Cow<'_, str> {
+    match note {
+        Some(note) => Cow::Owned(format!("{}\n\n{}", system_prompt, note)),
+        None => Cow::Borrowed(system_prompt),
+    }
+}
+
+/// The MCP resync timer, first firing one period from now; None when
+/// resyncing is off or there are no servers yet.
+fn resync_interval(mcp: &McpRoster) -> Option<tokio::time::Interval> {
+    mcp.resync_every().map(|every| {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval
+    })
+}
+
+/// Wait for the next server started in the background; never resolves when
+/// none are starting. None once they all have.
+async fn next_arrival(arrivals: &mut Option<mpsc::Receiver<McpArrival>>) -> Option<McpArrival> {
+    match arrivals {
+        Some(arrivals) => arrivals.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Add a server that came up in the background: register its tools, rebuild
+/// the prompt's tool list, and tell the TUI. A server that failed to start
+/// is reported as an error.
+async fn admit_mcp_server(
+    arrival: McpArrival,
+    mcp: &mut McpRoster,
+    registry: &Registry,
+    tools_config: &ToolsConfig,
+    prompt_params: &mut SystemPromptParams,
+    system_prompt: &mut String,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+) {
+    let server = match arrival.result {
+        Ok(server) => server,
+        Err(e) => {
+            let _ = agent_tx
+                .send(AgentEvent::Error(format!(
+                    "MCP server '{}' {}",
+                    arrival.name, e
+                )))
+                .await;
+            return;
+        }
+    };
+    let delta = mcp.admit(server, registry, tools_config).await;
+    let tool_count = rebuild_tool_list(mcp, registry, tools_config, prompt_params).await;
+    *system_prompt = build_system_prompt(prompt_params);
+    let _ = agent_tx
+        .send(AgentEvent::McpConnected {
+            server: arrival.name,
+            tools: delta.added.len(),
+            tool_count,
+        })
+        .await;
+}
+
+/// Re-list the MCP servers' tools. When any changed, the prompt's tool list
+/// is rebuilt and each change is noted in the chat.
+async fn sync_mcp_tools(
+    mcp: &mut McpRoster,
+    registry: &Registry,
+    tools_config: &ToolsConfig,
+    prompt_params: &mut SystemPromptParams,
+    system_prompt: &mut String,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+) {
+    let deltas = mcp.resync(registry, tools_config).await;
+    if deltas.is_empty() {
+        return;
+    }
+    rebuild_tool_list(mcp, registry, tools_config, prompt_params).await;
+    *system_prompt = build_system_prompt(prompt_params);
+    for delta in deltas {
+        let _ = agent_tx.send(AgentEvent::Notice(delta.to_string())).await;
+    }
+}
+
+/// Point the prompt's tool list at the tools currently offered, returning
+/// how many there are.
+async fn rebuild_tool_list(
+    mcp: &McpRoster,
+    registry: &Registry,
+    tools_config: &ToolsConfig,
+    prompt_params: &mut SystemPromptParams,
+) -> usize {
+    let mut tool_defs = registry.to_definitions().await;
+    tool_defs.retain(|d| !tools_config.is_disabled(&d.name) && !mcp.is_withdrawn(&d.name));
+    prompt_params.tool_names = tool_defs.iter().map(|d| d.name.clone()).collect();
+    prompt_params.tool_summaries = tool_defs
+        .iter()
+        .map(|d| (d.name.clone(), d.description.clone()))
+        .collect();
+    prompt_params.tool_origins = tool_defs
+        .iter()
+        .map(|d| {
+            let origin = match mcp.server_of(&d.name) {
+                Some(server) => ToolOrigin::Mcp(server.to_string()),
+                None => ToolOrigin::BuiltIn,
+            };
+            (d.name.clone(), origin)
+        })
+        .collect();
+    tool_defs.len()
+}
+
+/// Re-read config, context files, and skills into `prompt_params`, returning
+/// the new skill report. Tools and model are left as they were.
+fn reload_prompt(prompt_params: &mut SystemPromptParams) -> anyhow::Result<Vec<SkillEntry>> {
+    let config = Config::load()?;
+    let skills = load_skill_files(&prompt_params.workspace_dir, &config.skills);
+    prompt_params.context_files = load_context_files(&prompt_params.workspace_dir);
+    prompt_params.skill_files = skills.files;
+    Ok(skills.entries)
+}
+
+/// Key identifying "the same call" for per-turn approval reuse: the tool name
+/// plus its bash command exactly as written, or its full input otherwise.
+/// Whitespace can matter to the shell (inside quotes, say), so commands that
+/// differ only in spacing are asked about separately.
+fn approval_memo_key(tool_name: &str, input: &serde_json::Value) -> (String, String) {
+    let detail = match input.get("command").and_then(|v| v.as_str()) {
+        Some(command) if tool_name == "bash" => command.to_string(),
+        _ => input.to_string(),
+    };
+    (tool_name.to_string(), detail)
+}
+
+/// Build a tool-results message for any tool calls in the final assistant
+/// message that never received a result (e.g. because the turn was cancelled
+/// mid-execution). Providers reject histories with unanswered tool_use blocks,
+/// so these are closed out with error results.
+fn close_dangling_tool_calls(messages: &[Message], reason: &str) -> Option<Message> {
+    let last = messages.last()?;
+    if last.role != Role::Assistant {
+        return None;
+    }
+    let results: Vec<ContentBlock> = last
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::ToolUse { id, .. } => {
+                Some(ContentBlock::tool_error(id, reason.to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    if results.is_empty() {
+        None
+    } else {
+        Some(Message::tool_results(results))
+    }
+}
+
+/// Where to cut history to regenerate the last answer: just after the last
+/// message the user typed (tool-result messages don't count). None unless an
+/// assistant reply follows it.
+fn regeneration_point(messages: &[Message]) -> Option<usize> {
+    let user = messages.iter().rposition(|m| {
+        m.role == Role::User
+            && m.content
+                .iter()
+                .any(|b| matches!(b, ContentBlock::Text { .. }))
+    })?;
+    messages[user + 1..]
+        .iter()
+        .any(|m| m.role == Role::Assistant)
+        .then_some(user + 1)
+}
+
+/// Drop the last answer, including its tool calls and results, so the user
+/// message before it can be answered again. A hint is appended to that
+/// message to steer the new answer. Returns how many messages were dropped.
+fn discard_last_answer(messages: &mut Vec<Message>, hint: Option<&str>) -> Option<usize> {
+    let cut = regeneration_point(messages)?;
+    let discarded = messages.len() - cut;
+    messages.truncate(cut);
+    if let Some(hint) = hint.filter(|h| !h.trim().is_empty()) {
+        messages[cut - 1].content.push(ContentBlock::text(format!(
+            "(Regenerating the previous answer: {})",
+            hint.trim()
+        )));
+    }
+    Some(discarded)
 }
 
 /// Execute a single tool by looking it up in the registry and calling its execute method.
@@ -521,14 +1856,116 @@ async fn execute_single_tool(
     }
 }
 
+/// Execute a tool, retrying read-only tools that fail up to the configured
+/// number of extra attempts. Anything that might write runs exactly once.
+async fn execute_with_retry(
+    registry: &Registry,
+    name: &str,
+    input: &serde_json::Value,
+    tools_config: &ToolsConfig,
+) -> ToolResult {
+    let mut result = execute_single_tool(registry, name, input).await;
+    if !is_read_only_tool(name) {
+        return result;
+    }
+    for _ in 0..tools_config.read_retries {
+        if !result.is_error {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(tools_config.retry_delay_ms)).await;
+        result = execute_single_tool(registry, name, input).await;
+    }
+    result
+}
+
+/// Run a tool call that passed approval. open_in_editor is handed to the
+/// TUI, which runs the user's editor and answers with the file as saved;
+/// everything else goes through the cache to the registry.
+async fn run_approved(
+    registry: &Registry,
+    tool_call_id: &str,
+    name: &str,
+    input: &serde_json::Value,
+    tools_config: &ToolsConfig,
+    cache: &mut ToolCache,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+) -> ToolResult {
+    if name != OPEN_IN_EDITOR_TOOL_NAME {
+        return execute_cached(
+            registry,
+            tool_call_id,
+            name,
+            input,
+            tools_config,
+            cache,
+            agent_tx,
+        )
+        .await;
+    }
+    let path = input
+        .get("path")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let (tx, rx) = oneshot::channel();
+    let _ = agent_tx
+        .send(AgentEvent::OpenInEditor {
+            tool_call_id: tool_call_id.to_string(),
+            path: path.clone(),
+            responder: tx,
+        })
+        .await;
+    let result = match rx.await {
+        Ok(Ok(contents)) => ToolResult::text(format!(
+            "The user closed the editor. {} now reads:\n\n{}",
+            path, contents
+        )),
+        Ok(Err(e)) => ToolResult::error(format!("Editing {} failed: {}", path, e)),
+        Err(_) => ToolResult::error("No editor is available in this session"),
+    };
+    send_tool_result(agent_tx, tool_call_id, name, &result).await;
+    result
+}
+
+/// Execute a tool through the per-turn cache and report the result to the TUI.
+/// A cache hit skips execution; the TUI sees it marked "(cached)".
+async fn execute_cached(
+    registry: &Registry,
+    tool_call_id: &str,
+    name: &str,
+    input: &serde_json::Value,
+    tools_config: &ToolsConfig,
+    cache: &mut ToolCache,
+    agent_tx: &mpsc::Sender<AgentEvent>,
+) -> ToolResult {
+    if let Some(content) = cache.get(name, input) {
+        let result = ToolResult::text(content);
+        let _ = agent_tx
+            .send(AgentEvent::ToolResult {
+                tool_call_id: tool_call_id.to_string(),
+                tool_name: name.to_string(),
+                content: format!("(cached) {}", content),
+                is_error: false,
+            })
+            .await;
+        return result;
+    }
+    let result = execute_with_retry(registry, name, input, tools_config).await;
+    cache.record(name, input, &result);
+    send_tool_result(agent_tx, tool_call_id, name, &result).await;
+    result
+}
+
 /// Send a tool result event to the TUI.
 async fn send_tool_result(
     agent_tx: &mpsc::Sender<AgentEvent>,
+    tool_call_id: &str,
     tool_name: &str,
     result: &ToolResult,
 ) {
     let _ = agent_tx
         .send(AgentEvent::ToolResult {
+            tool_call_id: tool_call_id.to_string(),
             tool_name: tool_name.to_string(),
             content: result.content.clone(),
             is_error: result.is_error,
@@ -545,36 +1982,10 @@ fn tool_result_to_block(tool_use_id: &str, result: &ToolResult) -> ContentBlock
     }
 }
 
-/// Summarize tool parameters for display, truncating to 80 characters.
-fn summarize_params(params: &serde_json::Value) -> String {
-    let s = params.to_string();
-    let truncated: String = s.chars().take(80).collect();
-    if truncated.len() < s.len() {
-        format!("{}...", truncated)
-    } else {
-        s
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn summarize_short_params() {
-        let params = serde_json::json!({"command": "ls"});
-        let summary = summarize_params(&params);
-        assert_eq!(summary, r#"{"command":"ls"}"#);
-    }
-
-    #[test]
-    fn summarize_long_params_truncates() {
-        let long = "x".repeat(200);
-        let params = serde_json::json!({"command": long});
-        let summary = summarize_params(&params);
-        assert!(summary.len() <= 84); // 80 + "..."
-        assert!(summary.ends_with("..."));
-    }
+    use crate::approval::ApprovalReason;
 
     #[test]
     fn tool_result_to_block_success() {
@@ -612,6 +2023,1406 @@ mod tests {
         }
     }
 
+    /// Drain every event currently queued on the channel.
+    fn drain(rx: &mut mpsc::Receiver<AgentEvent>) -> Vec<AgentEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    fn streamed_text(events: &[AgentEvent]) -> String {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::TextDelta(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn text_done_count(events: &[AgentEvent]) -> usize {
+        events
+            .iter()
+            .filter(|e| matches!(e, AgentEvent::TextDone))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn non_streaming_path_matches_streaming_history() {
+        let tool_input = serde_json::json!({"command": "ls"});
+
+        // Streaming: text in two deltas, then a tool call assembled from JSON fragments.
+        let events: Vec<Result<StreamEvent, anyhow::Error>> = vec![
+            Ok(StreamEvent::ContentBlockStart {
+                index: 0,
+                block: ContentBlock::text(""),
+            }),
+            Ok(StreamEvent::ContentBlockDelta {
+                index: 0,
+                text: "Listing\n".to_string(),
+            }),
+            Ok(StreamEvent::ContentBlockDelta {
+                index: 0,
+                text: "files now".to_string(),
+            }),
+            Ok(StreamEvent::ContentBlockStop { index: 0 }),
+            Ok(StreamEvent::ContentBlockStart {
+                index: 1,
+                block: ContentBlock::ToolUse {
+                    id: "call-1".to_string(),
+                    name: "bash".to_string(),
+                    input: serde_json::json!({}),
+                },
+            }),
+            Ok(StreamEvent::InputJsonDelta {
+                index: 1,
+                partial_json: "{\"command\":".to_string(),
+            }),
+            Ok(StreamEvent::InputJsonDelta {
+                index: 1,
+                partial_json: "\"ls\"}".to_string(),
+            }),
+            Ok(StreamEvent::ContentBlockStop { index: 1 }),
+            Ok(StreamEvent::MessageStop),
+        ];
+        let (tx, mut rx) = mpsc::channel(64);
+        let streamed_blocks =
+            consume_stream(futures::stream::iter(events), &tx, &RequestTimeouts::none())
+                .await
+                .unwrap()
+                .blocks;
+        let streamed_events = drain(&mut rx);
+
+        // Non-streaming: the same content as a complete response.
+        let content = vec![
+            ContentBlock::text("Listing\nfiles now"),
+            ContentBlock::ToolUse {
+                id: "call-1".to_string(),
+                name: "bash".to_string(),
+                input: tool_input,
+            },
+        ];
+        let complete_blocks = emit_response_blocks(&content, &tx).await;
+        let complete_events = drain(&mut rx);
+
+        assert_eq!(
+            serde_json::to_value(&streamed_blocks).unwrap(),
+            serde_json::to_value(&complete_blocks).unwrap()
+        );
+        assert_eq!(streamed_text(&streamed_events), "Listing\nfiles now");
+        assert_eq!(streamed_text(&complete_events), "Listing\nfiles now");
+        assert_eq!(text_done_count(&streamed_events), 1);
+        assert_eq!(text_done_count(&complete_events), 1);
+    }
+
+    #[tokio::test]
+    async fn emit_response_blocks_chunks_by_line_and_merges_text() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let content = vec![ContentBlock::text("a\nb\n"), ContentBlock::text("c")];
+
+        let blocks = emit_response_blocks(&content, &tx).await;
+        let events = drain(&mut rx);
+
+        assert_eq!(
+            serde_json::to_value(&blocks).unwrap(),
+            serde_json::to_value(vec![ContentBlock::text("a\nb\nc")]).unwrap()
+        );
+        let deltas: Vec<String> = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::TextDelta(t) => Some(t.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deltas, vec!["a\n", "b\n", "c"]);
+    }
+
+    #[tokio::test]
+    async fn stream_failing_immediately_is_a_start_error() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let events: Vec<Result<StreamEvent, anyhow::Error>> =
+            vec![Err(anyhow::anyhow!("streaming not supported"))];
+
+        let err = consume_stream(futures::stream::iter(events), &tx, &RequestTimeouts::none())
+            .await
+            .unwrap_err();
+
+        assert!(err.downcast_ref::<StreamStartError>().is_some());
+        assert!(err.to_string().contains("streaming not supported"));
+        // No error event: the caller decides whether to fall back or report it.
+        assert!(drain(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn only_unsupported_streaming_counts_as_unsupported() {
+        assert!(streaming_unsupported(
+            "Streaming is not supported for this model"
+        ));
+        assert!(streaming_unsupported(
+            "400: 'stream' is an unsupported parameter"
+        ));
+        assert!(streaming_unsupported(
+            "proxy doesn't support text/event-stream"
+        ));
+        assert!(!streaming_unsupported(
+            "401 Unauthorized: invalid x-api-key"
+        ));
+        assert!(!streaming_unsupported(
+            "429 Too Many Requests: rate limit exceeded"
+        ));
+        assert!(!streaming_unsupported(
+            "error sending request: connection refused"
+        ));
+        assert!(!streaming_unsupported("stream error: model not supported"));
+    }
+
+    #[tokio::test]
+    async fn auto_streaming_only_falls_back_when_the_provider_cannot_stream() {
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+
+        for (error, falls_back) in [
+            ("streaming is not supported by this endpoint", true),
+            ("401 Unauthorized", false),
+            ("429 Too Many Requests", false),
+            ("connection refused", false),
+        ] {
+            let client: Arc<dyn LlmClient> = Arc::new(MockLlmClient::new(vec![
+                ScriptedResponse::new().stream_error(error),
+                ScriptedResponse::new().text("hello"),
+            ]));
+            let (tx, _rx) = mpsc::channel(64);
+            let mut streaming = StreamingMode::Auto;
+            let result = fetch_response(
+                &client,
+                &Request::new("mock-model"),
+                &tx,
+                &mut streaming,
+                &RequestTimeouts::none(),
+            )
+            .await;
+            assert_eq!(result.is_ok(), falls_back, "{}", error);
+            let expected = if falls_back {
+                StreamingMode::Off
+            } else {
+                StreamingMode::Auto
+            };
+            assert_eq!(streaming, expected, "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_failing_midway_is_not_a_start_error() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let events: Vec<Result<StreamEvent, anyhow::Error>> = vec![
+            Ok(StreamEvent::ContentBlockDelta {
+                index: 0,
+                text: "partial".to_string(),
+            }),
+            Err(anyhow::anyhow!("connection reset")),
+        ];
+
+        let err = consume_stream(futures::stream::iter(events), &tx, &RequestTimeouts::none())
+            .await
+            .unwrap_err();
+
+        assert!(err.downcast_ref::<StreamStartError>().is_none());
+        assert!(
+            drain(&mut rx)
+                .iter()
+                .any(|e| matches!(e, AgentEvent::Error(m) if m.contains("connection reset")))
+        );
+    }
+
+    fn bash_call(id: &str, command: &str) -> ContentBlock {
+        ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: "bash".to_string(),
+            input: serde_json::json!({ "command": command }),
+        }
+    }
+
+    #[test]
+    fn approval_memo_key_uses_the_exact_bash_command() {
+        let a = approval_memo_key("bash", &serde_json::json!({"command": "rm -rf /tmp/x"}));
+        let b = approval_memo_key("bash", &serde_json::json!({"command": "rm -rf /tmp/x"}));
+        let c = approval_memo_key("bash", &serde_json::json!({"command": "rm  -rf /tmp/x"}));
+        let d = approval_memo_key("bash", &serde_json::json!({"command": "echo 'a  b'"}));
+        let e = approval_memo_key("bash", &serde_json::json!({"command": "echo 'a b'"}));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(d, e);
+        assert_ne!(
+            approval_memo_key("write_file", &serde_json::json!({"path": "a"})),
+            approval_memo_key("write_file", &serde_json::json!({"path": "b"}))
+        );
+    }
+
+    #[tokio::test]
+    async fn identical_calls_in_a_turn_prompt_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(ApprovalEngine::new(dir.path().join("approvals.json")).unwrap());
+        let registry = Registry::new();
+        let blocks = vec![
+            bash_call("c1", "rm -rf /tmp/scratch"),
+            bash_call("c2", "rm -rf /tmp/scratch"),
+            bash_call("c3", "rm -rf /tmp/scratch"),
+            bash_call("c4", "rm -rf /tmp/other"),
+            bash_call("c5", "rm  -rf /tmp/scratch"),
+        ];
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let responder = tokio::spawn(async move {
+            let (mut prompts, mut reused) = (0, 0);
+            while let Some(event) = agent_rx.recv().await {
+                match event {
+                    AgentEvent::ToolCallNeedsApproval { responder, .. } => {
+                        prompts += 1;
+                        let _ = responder.send(ApprovalDecision::AllowOnce);
+                    }
+                    AgentEvent::Notice(msg) if msg.contains("auto-applied") => reused += 1,
+                    _ => {}
+                }
+            }
+            (prompts, reused)
+        });
+
+        let results = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &ToolsConfig::default(),
+            false,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            &ShutdownFlag::default(),
+        )
+        .await
+        .results;
+        drop(agent_tx);
+        let (prompts, reused) = responder.await.unwrap();
+
+        // Exact repeats are reused; the respaced command is asked anew.
+        assert_eq!(prompts, 3);
+        assert_eq!(reused, 2);
+        assert_eq!(results.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn approvals_record_why_each_call_ran() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(ApprovalEngine::new(dir.path().join("approvals.json")).unwrap());
+        let registry = Registry::new();
+        let blocks = vec![
+            bash_call("c1", "ls"),
+            bash_call("c2", "rm -rf /tmp/scratch"),
+        ];
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let responder = tokio::spawn(async move {
+            let mut reasons = Vec::new();
+            while let Some(event) = agent_rx.recv().await {
+                match event {
+                    AgentEvent::ToolCallNeedsApproval { responder, .. } => {
+                        let _ = responder.send(ApprovalDecision::AllowOnce);
+                    }
+                    AgentEvent::ToolCallApproved {
+                        tool_call_id,
+                        reason,
+                        ..
+                    } => reasons.push((tool_call_id, reason)),
+                    _ => {}
+                }
+            }
+            reasons
+        });
+
+        let executed = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &ToolsConfig::default(),
+            false,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            &ShutdownFlag::default(),
+        )
+        .await;
+        drop(agent_tx);
+
+        assert_eq!(
+            responder.await.unwrap(),
+            vec![
+                ("c1".to_string(), Some(ApprovalReason::SafeBins)),
+                ("c2".to_string(), None),
+            ]
+        );
+        let approvals: Vec<(&str, &str)> = executed
+            .approvals
+            .iter()
+            .map(|(id, reason)| (id.as_str(), reason.as_str()))
+            .collect();
+        assert_eq!(approvals, [("c1", "safe-bin"), ("c2", "user")]);
+    }
+
+    #[tokio::test]
+    async fn denial_is_reused_for_identical_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(ApprovalEngine::new(dir.path().join("approvals.json")).unwrap());
+        let registry = Registry::new();
+        let blocks = vec![
+            bash_call("c1", "rm -rf /tmp/scratch"),
+            bash_call("c2", "rm -rf /tmp/scratch"),
+        ];
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let responder = tokio::spawn(async move {
+            let mut denied = 0;
+            while let Some(event) = agent_rx.recv().await {
+                match event {
+                    AgentEvent::ToolCallNeedsApproval { responder, .. } => {
+                        let _ = responder.send(ApprovalDecision::Deny);
+                    }
+                    AgentEvent::ToolCallDenied { .. } => denied += 1,
+                    _ => {}
+                }
+            }
+            denied
+        });
+
+        let results = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &ToolsConfig::default(),
+            false,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            &ShutdownFlag::default(),
+        )
+        .await
+        .results;
+        drop(agent_tx);
+
+        assert_eq!(responder.await.unwrap(), 2);
+        let rendered = serde_json::to_string(&results).unwrap();
+        assert_eq!(rendered.matches("Denied by user").count(), 2);
+    }
+
+    /// Run one call that needs approval, answering with `decision` (None
+    /// never answers), and return the events that followed the prompt.
+    async fn answer_one_approval(
+        decision: Option<ApprovalDecision>,
+        approval_timeout_seconds: u64,
+        shutdown: &ShutdownFlag,
+    ) -> Vec<AgentEvent> {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(ApprovalEngine::new(dir.path().join("approvals.json")).unwrap());
+        let registry = Registry::new();
+        let blocks = vec![bash_call("c1", "rm -rf /tmp/scratch")];
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let responder = tokio::spawn(async move {
+            let mut held = Vec::new();
+            let mut after = Vec::new();
+            while let Some(event) = agent_rx.recv().await {
+                match event {
+                    AgentEvent::ToolCallNeedsApproval { responder, .. } => match decision {
+                        Some(decision) => {
+                            let _ = responder.send(decision);
+                        }
+                        None => held.push(responder),
+                    },
+                    other => after.push(other),
+                }
+            }
+            after
+        });
+
+        execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            approval_timeout_seconds,
+            &ToolsConfig::default(),
+            false,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            shutdown,
+        )
+        .await;
+        drop(agent_tx);
+        responder.await.unwrap()
+    }
+
+    fn expired_and_denied(events: &[AgentEvent]) -> (bool, bool) {
+        let expired = events
+            .iter()
+            .any(|e| matches!(e, AgentEvent::ApprovalExpired { .. }));
+        let denied = events
+            .iter()
+            .any(|e| matches!(e, AgentEvent::ToolCallDenied { .. }));
+        (expired, denied)
+    }
+
+    #[tokio::test]
+    async fn an_unanswered_approval_expires_instead_of_being_denied() {
+        let events = answer_one_approval(None, 0, &ShutdownFlag::default()).await;
+        let (expired, denied) = expired_and_denied(&events);
+        assert!(expired);
+        assert!(!denied);
+    }
+
+    #[tokio::test]
+    async fn a_user_deny_is_a_denial_not_an_expiry() {
+        let events =
+            answer_one_approval(Some(ApprovalDecision::Deny), 60, &ShutdownFlag::default()).await;
+        let (expired, denied) = expired_and_denied(&events);
+        assert!(!expired);
+        assert!(denied);
+    }
+
+    /// Run two approval-needing calls whose prompts are dropped unanswered,
+    /// with `shutdown` set or not.
+    async fn drop_approval_prompts(shutdown: &ShutdownFlag) -> (ExecutedTools, Vec<AgentEvent>) {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(ApprovalEngine::new(dir.path().join("approvals.json")).unwrap());
+        let registry = Registry::new();
+        let blocks = vec![
+            bash_call("c1", "rm -rf /tmp/scratch"),
+            bash_call("c2", "rm -rf /tmp/other"),
+        ];
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let responder = tokio::spawn(async move {
+            let mut events = Vec::new();
+            while let Some(event) = agent_rx.recv().await {
+                // Prompts are dropped unanswered, as by a TUI that went away.
+                if !matches!(event, AgentEvent::ToolCallNeedsApproval { .. }) {
+                    events.push(event);
+                }
+            }
+            events
+        });
+
+        let executed = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &ToolsConfig::default(),
+            false,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            shutdown,
+        )
+        .await;
+        drop(agent_tx);
+        (executed, responder.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn a_prompt_dropped_during_shutdown_aborts_the_turn() {
+        let shutdown = ShutdownFlag::default();
+        shutdown.request();
+        let (executed, events) = drop_approval_prompts(&shutdown).await;
+        assert!(executed.aborted);
+        let (expired, denied) = expired_and_denied(&events);
+        assert!(!expired && !denied);
+        // Both calls are closed out, and the second is never asked about.
+        assert_eq!(executed.results.len(), 2);
+        for block in &executed.results {
+            let ContentBlock::ToolResult {
+                content, is_error, ..
+            } = block
+            else {
+                panic!("expected a tool result");
+            };
+            assert!(*is_error);
+            assert_eq!(content, SHUTDOWN_RESULT);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_prompt_dropped_otherwise_is_still_a_denial() {
+        let (executed, events) = drop_approval_prompts(&ShutdownFlag::default()).await;
+        assert!(!executed.aborted);
+        let denials = events
+            .iter()
+            .filter(|e| matches!(e, AgentEvent::ToolCallDenied { .. }))
+            .count();
+        assert_eq!(denials, 2);
+    }
+
+    #[tokio::test]
+    async fn a_timed_out_prompt_is_denied_even_while_shutting_down() {
+        // Only a dropped prompt means the TUI went away; an expiry doesn't.
+        let shutdown = ShutdownFlag::default();
+        shutdown.request();
+        let events = answer_one_approval(None, 0, &shutdown).await;
+        let (expired, _) = expired_and_denied(&events);
+        assert!(expired);
+    }
+
+    #[tokio::test]
+    async fn hallucinated_call_to_a_disabled_tool_is_refused() {
+        let (registry, calls) = flaky_registry("write_file", 0).await;
+        let tools_config = ToolsConfig {
+            disabled: vec!["write_file".to_string()],
+            ..no_delay(0)
+        };
+        let blocks = vec![ContentBlock::ToolUse {
+            id: "c1".to_string(),
+            name: "write_file".to_string(),
+            input: serde_json::json!({"path": "a.txt", "content": "x"}),
+        }];
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let collector = tokio::spawn(async move {
+            let mut reasons = Vec::new();
+            while let Some(event) = agent_rx.recv().await {
+                if let AgentEvent::ToolCallDenied { reason, .. } = event {
+                    reasons.push(reason);
+                }
+            }
+            reasons
+        });
+
+        let results = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &tools_config,
+            false,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            &ShutdownFlag::default(),
+        )
+        .await
+        .results;
+        drop(agent_tx);
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(collector.await.unwrap(), [DISABLED_TOOL_ERROR]);
+        let rendered = serde_json::to_string(&results).unwrap();
+        assert!(rendered.contains(DISABLED_TOOL_ERROR));
+        assert!(rendered.contains("c1"));
+    }
+
+    #[tokio::test]
+    async fn observe_mode_refuses_every_tool_call() {
+        let (registry, calls) = flaky_registry("read_file", 0).await;
+        let blocks = vec![
+            ContentBlock::ToolUse {
+                id: "c1".to_string(),
+                name: "read_file".to_string(),
+                input: serde_json::json!({"path": "a.txt"}),
+            },
+            bash_call("c2", "ls"),
+            ContentBlock::ToolUse {
+                id: "c3".to_string(),
+                name: ASK_USER_TOOL_NAME.to_string(),
+                input: serde_json::json!({"question": "which file?"}),
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let results = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &ToolsConfig::default(),
+            true,
+            &mut ToolCache::new(&[]),
+            &agent_tx,
+            &ShutdownFlag::default(),
+        )
+        .await
+        .results;
+        drop(agent_tx);
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(results.len(), 3);
+        for result in &results {
+            let rendered = serde_json::to_string(result).unwrap();
+            assert!(rendered.contains("observe mode"), "{}", rendered);
+        }
+        let events = drain(&mut agent_rx);
+        assert!(
+            !events
+                .iter()
+                .any(|e| matches!(e, AgentEvent::AskUser { .. }))
+        );
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| matches!(e, AgentEvent::ToolCallDenied { .. }))
+                .count(),
+            3
+        );
+    }
+
+    /// Tool that fails its first `failures` calls, then succeeds.
+    struct FlakyTool {
+        name: &'static str,
+        failures: usize,
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Tool for FlakyTool {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "fails, then succeeds"
+        }
+
+        fn schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object"})
+        }
+
+        fn requires_approval(&self, _params: &serde_json::Value) -> bool {
+            false
+        }
+
+        async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+            let n = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if n < self.failures {
+                Ok(ToolResult::error("file is locked"))
+            } else {
+                Ok(ToolResult::text("contents"))
+            }
+        }
+    }
+
+    async fn flaky_registry(
+        name: &'static str,
+        failures: usize,
+    ) -> (Registry, Arc<std::sync::atomic::AtomicUsize>) {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let registry = Registry::new();
+        registry
+            .register(FlakyTool {
+                name,
+                failures,
+                calls: calls.clone(),
+            })
+            .await;
+        (registry, calls)
+    }
+
+    fn no_delay(read_retries: u32) -> ToolsConfig {
+        ToolsConfig {
+            read_retries,
+            retry_delay_ms: 0,
+            cache_reads: Vec::new(),
+            disabled: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_read_tool_is_retried_until_success() {
+        let (registry, calls) = flaky_registry("read_file", 1).await;
+        let input = serde_json::json!({"path": "a.txt"});
+
+        let result = execute_with_retry(&registry, "read_file", &input, &no_delay(2)).await;
+
+        assert!(!result.is_error);
+        assert_eq!(result.content, "contents");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn read_retries_are_bounded() {
+        let (registry, calls) = flaky_registry("search", 10).await;
+        let input = serde_json::json!({"pattern": "x"});
+
+        let result = execute_with_retry(&registry, "search", &input, &no_delay(2)).await;
+
+        assert!(result.is_error);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn failing_write_tool_is_not_retried() {
+        let (registry, calls) = flaky_registry("write_file", 1).await;
+        let input = serde_json::json!({"path": "a.txt", "content": "x"});
+
+        let result = execute_with_retry(&registry, "write_file", &input, &no_delay(2)).await;
+
+        assert!(result.is_error);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn repeated_read_in_a_turn_executes_once() {
+        let (registry, calls) = flaky_registry("read_file", 0).await;
+        let tools_config = ToolsConfig {
+            cache_reads: vec!["read_file".to_string()],
+            ..no_delay(0)
+        };
+        let read = |id: &str| ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: "read_file".to_string(),
+            input: serde_json::json!({"path": "a.txt"}),
+        };
+        let blocks = vec![read("c1"), read("c2")];
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let collector = tokio::spawn(async move {
+            let mut contents = Vec::new();
+            while let Some(event) = agent_rx.recv().await {
+                if let AgentEvent::ToolResult { content, .. } = event {
+                    contents.push(content);
+                }
+            }
+            contents
+        });
+
+        let mut cache = ToolCache::new(&tools_config.cache_reads);
+        let results = execute_tool_calls(
+            &blocks,
+            &registry,
+            &engine,
+            60,
+            &tools_config,
+            false,
+            &mut cache,
+            &agent_tx,
+            &ShutdownFlag::default(),
+        )
+        .await
+        .results;
+        drop(agent_tx);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(
+            collector.await.unwrap(),
+            vec!["contents", "(cached) contents"]
+        );
+    }
+
+    #[tokio::test]
+    async fn scripted_turn_runs_tool_and_sends_result_back() {
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+
+        let mock = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new()
+                .text("Reading it.")
+                .tool_use("c1", "read_file", serde_json::json!({"path": "a.txt"}))
+                .usage(100, 20),
+            ScriptedResponse::new()
+                .text("It says ")
+                .text("contents.")
+                .usage(130, 10),
+        ]));
+        let client: Arc<dyn LlmClient> = mock.clone();
+        let (registry, calls) = flaky_registry("read_file", 0).await;
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+        let mut messages = vec![Message::user("what's in a.txt?")];
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let saved = std::sync::Mutex::new(Vec::new());
+        let save = |messages: &[Message]| saved.lock().unwrap().push(messages.len());
+
+        conversation_turn(
+            &client,
+            &registry,
+            &engine,
+            "mock-model",
+            1024,
+            60,
+            &ToolsConfig::default(),
+            &McpRoster::default(),
+            &Reasoning::default(),
+            "system",
+            &mut messages,
+            &agent_tx,
+            &None,
+            &mut StreamingMode::On,
+            &RequestTimeouts::none(),
+            &mut AutoSave::new(1, 0),
+            &save,
+            false,
+            None,
+            &mut Fallbacks::default(),
+            &CompactionConfig::default(),
+            &ShutdownFlag::default(),
+        )
+        .await
+        .unwrap();
+        let events = drain(&mut agent_rx);
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(messages.len(), 4);
+        // Saved mid-turn once the tool result was in, before the final answer.
+        assert_eq!(*saved.lock().unwrap(), [3]);
+        assert_eq!(streamed_text(&events), "Reading it.It says contents.");
+        let usage_events = events
+            .iter()
+            .filter(|e| matches!(e, AgentEvent::Usage { .. }))
+            .count();
+        assert_eq!(usage_events, 2);
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        let followup = serde_json::to_string(&requests[1].messages).unwrap();
+        assert!(followup.contains("c1"));
+        assert!(followup.contains("contents"));
+        assert_eq!(mock.remaining(), 0);
+    }
+
+    /// The request one plain turn sends for `model` with `reasoning`, in
+    /// its Debug form.
+    async fn request_sent(model: &str, reasoning: &Reasoning) -> String {
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+
+        let mock = Arc::new(MockLlmClient::new(vec![ScriptedResponse::new().text("ok")]));
+        let client: Arc<dyn LlmClient> = mock.clone();
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+        let mut messages = vec![Message::user("think hard")];
+        let (agent_tx, _agent_rx) = mpsc::channel(64);
+        conversation_turn(
+            &client,
+            &Registry::new(),
+            &engine,
+            model,
+            32_000,
+            60,
+            &ToolsConfig::default(),
+            &McpRoster::default(),
+            reasoning,
+            "system",
+            &mut messages,
+            &agent_tx,
+            &None,
+            &mut StreamingMode::On,
+            &RequestTimeouts::none(),
+            &mut AutoSave::disabled(),
+            &|_: &[Message]| {},
+            false,
+            None,
+            &mut Fallbacks::default(),
+            &CompactionConfig::default(),
+            &ShutdownFlag::default(),
+        )
+        .await
+        .unwrap();
+        format!("{:?}", mock.requests()[0])
+    }
+
+    #[tokio::test]
+    async fn requests_carry_the_effort_and_thinking_budget() {
+        use crate::config::Effort;
+
+        let mut reasoning = Reasoning {
+            provider: "anthropic".to_string(),
+            effort: Some(Effort::High),
+            thinking_budget_tokens: 12_345,
+        };
+        let sent = request_sent("claude-sonnet-4-5", &reasoning).await;
+        assert!(sent.contains("12345"), "{}", sent);
+
+        // /effort off drops the configured budget along with the level.
+        reasoning.set_effort(None);
+        let sent = request_sent("claude-sonnet-4-5", &reasoning).await;
+        assert!(!sent.contains("12345"), "{}", sent);
+
+        let openai = Reasoning {
+            provider: "openai".to_string(),
+            effort: Some(Effort::High),
+            thinking_budget_tokens: 0,
+        };
+        let sent = request_sent("gpt-5.2", &openai).await;
+        assert!(sent.to_lowercase().contains("high"), "{}", sent);
+    }
+
+    /// What a turn over its token budget did.
+    struct BudgetRun {
+        messages: Vec<Message>,
+        questions: Vec<String>,
+        notices: Vec<String>,
+        requests: usize,
+        tool_calls: usize,
+    }
+
+    /// Run a turn of two tool rounds and a final answer, 120 tokens per
+    /// response, against a 100-token budget, answering the budget question
+    /// with `answers` in order.
+    async fn run_over_budget(answers: &[&str]) -> BudgetRun {
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+
+        let mock = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new()
+                .tool_use("c1", "read_file", serde_json::json!({"path": "a.txt"}))
+                .usage(100, 20),
+            ScriptedResponse::new()
+                .tool_use("c2", "read_file", serde_json::json!({"path": "b.txt"}))
+                .usage(100, 20),
+            ScriptedResponse::new().text("Both read.").usage(100, 20),
+        ]));
+        let client: Arc<dyn LlmClient> = mock.clone();
+        let (registry, calls) = flaky_registry("read_file", 0).await;
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+        let mut messages = vec![Message::user("read a.txt and b.txt")];
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+        let mut answers: std::collections::VecDeque<String> =
+            answers.iter().map(|a| a.to_string()).collect();
+        let answerer = tokio::spawn(async move {
+            let (mut questions, mut notices) = (Vec::new(), Vec::new());
+            while let Some(event) = agent_rx.recv().await {
+                match event {
+                    AgentEvent::AskUser {
+                        question,
+                        tool_call_id,
+                        options,
+                        responder,
+                        ..
+                    } => {
+                        assert_eq!(tool_call_id, BUDGET_QUESTION_ID);
+                        assert_eq!(options, TurnBudget::options());
+                        questions.push(question);
+                        let _ = responder.send(answers.pop_front().unwrap_or_default());
+                    }
+                    AgentEvent::Notice(notice) => notices.push(notice),
+                    _ => {}
+                }
+            }
+            (questions, notices)
+        });
+
+        conversation_turn(
+            &client,
+            &registry,
+            &engine,
+            "mock-model",
+            1024,
+            60,
+            &ToolsConfig::default(),
+            &McpRoster::default(),
+            &Reasoning::default(),
+            "system",
+            &mut messages,
+            &agent_tx,
+            &None,
+            &mut StreamingMode::On,
+            &RequestTimeouts::none(),
+            &mut AutoSave::disabled(),
+            &|_: &[Message]| {},
+            false,
+            Some(100),
+            &mut Fallbacks::default(),
+            &CompactionConfig::default(),
+            &ShutdownFlag::default(),
+        )
+        .await
+        .unwrap();
+        drop(agent_tx);
+        let (questions, notices) = answerer.await.unwrap();
+
+        BudgetRun {
+            messages,
+            questions,
+            notices,
+            requests: mock.requests().len(),
+            tool_calls: calls.load(std::sync::atomic::Ordering::SeqCst),
+        }
+    }
+
+    #[tokio::test]
+    async fn stopping_at_the_budget_answers_the_pending_tool_call() {
+        let run = run_over_budget(&["stop"]).await;
+
+        // Asked once the first response crossed the budget, before its tool ran.
+        assert_eq!(run.questions, ["This turn has used ~120 tokens. Continue?"]);
+        assert_eq!(run.requests, 1);
+        assert_eq!(run.tool_calls, 0);
+        assert_eq!(run.messages.len(), 3);
+        match &run.messages[2].content[0] {
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                assert_eq!(tool_use_id, "c1");
+                assert_eq!(content, STOPPED_RESULT);
+                assert!(is_error);
+            }
+            other => panic!("expected a tool result, got {:?}", other),
+        }
+        assert_eq!(
+            run.notices,
+            ["Stopped the turn after ~120 tokens ([agent] per_turn_token_budget)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn continuing_past_the_budget_asks_again_next_round() {
+        let run = run_over_budget(&["continue", "stop"]).await;
+
+        assert_eq!(
+            run.questions,
+            [
+                "This turn has used ~120 tokens. Continue?",
+                "This turn has used ~240 tokens. Continue?"
+            ]
+        );
+        assert_eq!(run.requests, 2);
+        assert_eq!(run.tool_calls, 1);
+        assert_eq!(run.notices.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn continuing_without_asking_finishes_the_turn() {
+        let run = run_over_budget(&[crate::agent::budget::CONTINUE_UNASKED]).await;
+
+        assert_eq!(run.questions.len(), 1);
+        assert_eq!(run.requests, 3);
+        assert_eq!(run.tool_calls, 2);
+        assert_eq!(run.messages.len(), 6);
+        assert!(run.notices.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_failed_primary_falls_back_to_the_next_provider() {
+        use crate::config::FallbackModel;
+        use crate::session::log::read_log_entries;
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+
+        let primary = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new().stream_error("overloaded"),
+        ]));
+        let client: Arc<dyn LlmClient> = primary.clone();
+        let backup = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new().text("Answered anyway."),
+        ]));
+        let backup_client: Arc<dyn LlmClient> = backup.clone();
+        let mut fallbacks = Fallbacks::new(
+            vec![
+                FallbackModel {
+                    provider: "gemini".into(),
+                    model: "gemini-2.5-pro".into(),
+                },
+                FallbackModel {
+                    provider: "openai".into(),
+                    model: "gpt-5.2".into(),
+                },
+            ],
+            Box::new(move |f| {
+                if f.provider == "gemini" {
+                    anyhow::bail!("GEMINI_API_KEY not set");
+                }
+                Ok(Arc::clone(&backup_client))
+            }),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let logger = SessionLogger::new_in_dir(dir.path()).unwrap();
+        let log_path = logger.log_path().to_path_buf();
+        let logger = Some(Arc::new(Mutex::new(logger)));
+        let engine = Arc::new(
+            ApprovalEngine::new_with_bypass(dir.path().join("approvals.json"), true).unwrap(),
+        );
+        let mut messages = vec![Message::user("hello")];
+        let (agent_tx, mut agent_rx) = mpsc::channel(64);
+
+        conversation_turn(
+            &client,
+            &Registry::new(),
+            &engine,
+            "mock-model",
+            1024,
+            60,
+            &ToolsConfig::default(),
+            &McpRoster::default(),
+            &Reasoning::default(),
+            "system",
+            &mut messages,
+            &agent_tx,
+            &logger,
+            &mut StreamingMode::On,
+            &RequestTimeouts::none(),
+            &mut AutoSave::disabled(),
+            &|_: &[Message]| {},
+            false,
+            None,
+            &mut fallbacks,
+            &CompactionConfig::default(),
+            &ShutdownFlag::default(),
+        )
+        .await
+        .unwrap();
+        drop(agent_tx);
+
+        let mut notices = Vec::new();
+        while let Some(event) = agent_rx.recv().await {
+            match event {
+                AgentEvent::Notice(notice) => notices.push(notice),
+                AgentEvent::Error(e) => panic!("unexpected error: {}", e),
+                _ => {}
+            }
+        }
+        assert_eq!(notices.len(), 2);
+        assert_eq!(
+            notices[0],
+            "Skipping fallback gemini/gemini-2.5-pro: GEMINI_API_KEY not set"
+        );
+        assert!(notices[1].contains("overloaded"));
+        assert!(notices[1].ends_with("; falling back to openai/gpt-5.2"));
+
+        assert_eq!(backup.requests().len(), 1);
+        assert_eq!(messages.len(), 2);
+        let entries = read_log_entries(&log_path).unwrap();
+        let answer = entries.last().unwrap();
+        assert_eq!(answer.answered_by.as_deref(), Some("openai/gpt-5.2"));
+    }
+
+    /// Run compact_history against a mock summarizer, answering the review
+    /// prompt (if one comes) with `decision`. Returns the resulting history,
+    /// the result, and whether the user was prompted.
+    async fn compact_with(
+        review: CompactionReview,
+        decision: SummaryDecision,
+    ) -> (Vec<Message>, Option<usize>, bool) {
+        use crate::testing::{MockLlmClient, ScriptedResponse};
+
+        let client: Arc<dyn LlmClient> = Arc::new(MockLlmClient::new(vec![
+            ScriptedResponse::new().text("the summary"),
+        ]));
+        let config = CompactionConfig {
+            review,
+            ..CompactionConfig::default()
+        };
+        let mut messages = vec![Message::user("fix the bug"), Message::assistant("fixed")];
+        let (agent_tx, mut agent_rx) = mpsc::channel(8);
+        let answerer = tokio::spawn(async move {
+            let mut prompted = false;
+            while let Some(event) = agent_rx.recv().await {
+                if let AgentEvent::CompactionSummaryReady { summary, responder } = event {
+                    assert_eq!(summary, "the summary");
+                    prompted = true;
+                    let _ = responder.send(decision.clone());
+                }
+            }
+            prompted
+        });
+        let summarizer = Summarizer {
+            client: &client,
+            model: "mock-model",
+            max_tokens: 1024,
+        };
+        let result = compact_history(
+            &summarizer,
+            &config,
+            &mut messages,
+            &Pins::default(),
+            &agent_tx,
+            None,
+        )
+        .await
+        .unwrap();
+        drop(agent_tx);
+        (messages, result, answerer.await.unwrap())
+    }
+
+    fn history_text(messages: &[Message]) -> String {
+        serde_json::to_string(messages).unwrap()
+    }
+
+    #[tokio::test]
+    async fn auto_review_compacts_without_asking() {
+        let (messages, result, prompted) =
+            compact_with(CompactionReview::Auto, SummaryDecision::Skip).await;
+        assert!(!prompted);
+        assert_eq!(result, Some(2));
+        assert!(history_text(&messages).contains("the summary"));
+        assert!(!history_text(&messages).contains("fixed"));
+    }
+
+    #[tokio::test]
+    async fn accepted_summary_replaces_history() {
+        let (messages, result, prompted) =
+            compact_with(CompactionReview::Ask, SummaryDecision::Accept).await;
+        assert!(prompted);
+        assert_eq!(result, Some(2));
+        assert!(history_text(&messages).contains("the summary"));
+    }
+
+    #[tokio::test]
+    async fn edited_summary_replaces_history() {
+        let (messages, result, _) = compact_with(
+            CompactionReview::Ask,
+            SummaryDecision::Edit("the summary, plus: keep the API stable".to_string()),
+        )
+        .await;
+        assert_eq!(result, Some(2));
+        assert!(history_text(&messages).contains("keep the API stable"));
+    }
+
+    #[tokio::test]
+    async fn skipped_summary_leaves_history_untouched() {
+        let (messages, result, prompted) =
+            compact_with(CompactionReview::Ask, SummaryDecision::Skip).await;
+        assert!(prompted);
+        assert_eq!(result, None);
+        assert_eq!(messages.len(), 2);
+        assert!(!history_text(&messages).contains("the summary"));
+    }
+
+    #[test]
+    fn close_dangling_tool_calls_answers_every_tool_use() {
+        let messages = vec![
+            Message::user("run two things"),
+            Message {
+                role: Role::Assistant,
+                content: vec![
+                    ContentBlock::text("On it."),
+                    ContentBlock::ToolUse {
+                        id: "call-1".to_string(),
+                        name: "bash".to_string(),
+                        input: serde_json::json!({"command": "sleep 100"}),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "call-2".to_string(),
+                        name: "read_file".to_string(),
+                        input: serde_json::json!({"path": "a.txt"}),
+                    },
+                ],
+            },
+        ];
+
+        let closing = close_dangling_tool_calls(&messages, "Cancelled by user").unwrap();
+        assert_eq!(closing.role, Role::User);
+        let ids: Vec<&str> = closing
+            .content
+            .iter()
+            .map(|b| match b {
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    is_error,
+                    ..
+                } => {
+                    assert!(is_error);
+                    tool_use_id.as_str()
+                }
+                other => panic!("expected ToolResult, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(ids, vec!["call-1", "call-2"]);
+    }
+
+    #[test]
+    fn close_dangling_tool_calls_ignores_complete_history() {
+        let text_only = vec![
+            Message::user("hi"),
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::text("hello")],
+            },
+        ];
+        assert!(close_dangling_tool_calls(&text_only, "x").is_none());
+
+        let user_last = vec![Message::user("hi")];
+        assert!(close_dangling_tool_calls(&user_last, "x").is_none());
+        assert!(close_dangling_tool_calls(&[], "x").is_none());
+    }
+
+    fn assistant(text: &str) -> Message {
+        Message {
+            role: Role::Assistant,
+            content: vec![ContentBlock::text(text)],
+        }
+    }
+
+    #[test]
+    fn regeneration_discards_tool_calls_and_results_of_the_last_turn() {
+        let mut messages = vec![
+            Message::user("first"),
+            assistant("one"),
+            Message::user("read a.txt"),
+            Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "c1".to_string(),
+                    name: "read_file".to_string(),
+                    input: serde_json::json!({"path": "a.txt"}),
+                }],
+            },
+            Message::tool_results(vec![ContentBlock::tool_result("c1", "contents")]),
+            assistant("it says contents"),
+        ];
+
+        assert_eq!(discard_last_answer(&mut messages, None), Some(3));
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].role, Role::User);
+        assert_eq!(messages[2].content.len(), 1);
+    }
+
+    #[test]
+    fn regeneration_needs_an_answer_after_the_user_message() {
+        let mut pending = vec![
+            Message::user("first"),
+            assistant("one"),
+            Message::user("second"),
+        ];
+        assert_eq!(discard_last_answer(&mut pending, None), None);
+        assert_eq!(pending.len(), 3);
+        assert_eq!(discard_last_answer(&mut Vec::new(), None), None);
+    }
+
+    #[test]
+    fn regeneration_hint_is_appended_to_the_user_message() {
+        let mut messages = vec![Message::user("explain"), assistant("a long answer")];
+        assert_eq!(
+            discard_last_answer(&mut messages, Some("make it shorter")),
+            Some(1)
+        );
+        let rendered = serde_json::to_string(&messages[0]).unwrap();
+        assert!(rendered.contains("explain"));
+        assert!(rendered.contains("make it shorter"));
+    }
+
+    #[test]
+    fn context_note_is_appended_to_the_system_prompt() {
+        assert_eq!(
+            with_context_note("You are helpful.", None),
+            "You are helpful."
+        );
+        assert_eq!(
+            with_context_note("You are helpful.", Some("Note: resumed.".to_string())),
+            "You are helpful.\n\nNote: resumed."
+        );
+    }
+
     #[test]
     fn agent_loop_params_is_constructible() {
         // Compile-time test: verify AgentLoopParams struct can be referenced
@@ -626,11 +3437,19 @@ mod tests {
             let _: &u32 = &p.max_tokens;
             let _: &u64 = &p.approval_timeout_seconds;
             let _: &String = &p.system_prompt;
+            let _: &SystemPromptParams = &p.prompt_params;
             let _: &Vec<Message> = &p.initial_messages;
             let _: &Option<Arc<Mutex<SessionLogger>>> = &p.session_logger;
             let _: &PathBuf = &p.workspace_dir;
             let _: &CompactionConfig = &p.compaction_config;
             let _: &Option<String> = &p.existing_created_at;
+            let _: &StreamingMode = &p.streaming;
+            let _: &ToolsConfig = &p.tools_config;
+            let _: &SessionFormat = &p.session_format;
+            let _: &Reasoning = &p.reasoning;
+            let _: &AutoSave = &p.autosave;
+            let _: bool = p.observe;
+            let _: &RequestTimeouts = &p.timeouts;
         }
     }
 }
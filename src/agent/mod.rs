@@ -1,9 +1,21 @@
 // ABOUTME: Agent module — LLM provider factory and streaming agent loop.
 // ABOUTME: Manages conversation history and tool call dispatch.
 
+pub mod budget;
+pub mod builder;
 pub mod compaction;
+pub mod cost;
+pub mod effort;
+pub mod fallback;
+pub mod forget;
 pub mod r#loop;
+pub mod pins;
 pub mod provider;
+pub mod refusal;
+pub mod shutdown;
+pub mod timeouts;
+pub mod tool_cache;
 
+pub use builder::{Agent, AgentBuilder};
 pub use r#loop::{AgentLoopParams, run_agent_loop};
 pub use provider::*;
@@ -0,0 +1,225 @@
+// ABOUTME: /pin — messages the user marked to be kept word for word through compaction.
+// ABOUTME: Pins are kept by index in the history, and moved along whenever compaction or truncation rebuilds it.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+use mux::prelude::*;
+
+use crate::agent::forget::{REDACTED, nth_typed_message};
+
+/// Characters of a pinned message quoted back in the notice.
+const PREVIEW_CHARS: usize = 60;
+
+/// What a /pin did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinChange {
+    Pinned(String),
+    /// The message was already pinned, so the pin came off.
+    Unpinned(String),
+}
+
+impl PinChange {
+    pub fn is_pinned(&self) -> bool {
+        matches!(self, PinChange::Pinned(_))
+    }
+}
+
+impl std::fmt::Display for PinChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinChange::Pinned(text) => write!(
+                f,
+                "\u{1f4cc} Pinned \"{}\"; it's kept word for word when the history is compacted",
+                preview(text)
+            ),
+            PinChange::Unpinned(text) => write!(f, "Unpinned \"{}\"", preview(text)),
+        }
+    }
+}
+
+/// The first line of `text`, cut to [`PREVIEW_CHARS`].
+fn preview(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("");
+    if line.chars().count() > PREVIEW_CHARS || line.len() < text.len() {
+        let cut: String = line.chars().take(PREVIEW_CHARS).collect();
+        format!("{}\u{2026}", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+/// The text of `message`, as compaction keeps it.
+pub fn message_text(message: &Message) -> String {
+    let texts: Vec<&str> = message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    texts.join("\n")
+}
+
+/// Index of the `nth` most recent assistant message with text (1 is the
+/// last), or None when there aren't that many. The chat shows one reply per
+/// such message, so "/pin reply 2" means the same one on both sides.
+fn nth_reply(messages: &[Message], nth: usize) -> Option<usize> {
+    messages
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, m)| {
+            m.role == Role::Assistant
+                && m.content
+                    .iter()
+                    .any(|b| matches!(b, ContentBlock::Text { text } if !text.is_empty()))
+        })
+        .nth(nth.checked_sub(1)?)
+        .map(|(i, _)| i)
+}
+
+/// Index of the message "/pin [reply] nth" means: the `nth` most recent
+/// message the user typed (1 is the last), or with `reply` the `nth` most
+/// recent reply. None when there aren't that many.
+pub fn pin_target(messages: &[Message], nth: usize, reply: bool) -> Option<usize> {
+    if reply {
+        nth_reply(messages, nth)
+    } else {
+        nth_typed_message(messages, nth)
+    }
+}
+
+/// The pins in `indexes` that still point at a message worth keeping: not
+/// past the end of `messages` (/regen cut it) and not redacted by /forget.
+pub fn live_pins(messages: &[Message], indexes: &[usize]) -> Vec<usize> {
+    indexes
+        .iter()
+        .copied()
+        .filter(|&i| messages.get(i).is_some_and(|m| message_text(m) != REDACTED))
+        .collect()
+}
+
+/// Indexes in the history of the pinned messages, shared by the agent loop
+/// and its saves.
+#[derive(Debug, Clone, Default)]
+pub struct Pins(Arc<Mutex<BTreeSet<usize>>>);
+
+impl Pins {
+    /// Pins restored from a saved session.
+    pub fn new(indexes: Vec<usize>) -> Self {
+        Self(Arc::new(Mutex::new(indexes.into_iter().collect())))
+    }
+
+    /// The pinned indexes, in order.
+    pub fn indexes(&self) -> Vec<usize> {
+        self.0.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Replace the pins, after the history they index was rebuilt.
+    pub fn set(&self, indexes: Vec<usize>) {
+        *self.0.lock().unwrap() = indexes.into_iter().collect();
+    }
+
+    /// Pin the message [`pin_target`] picks, or unpin it if it already is.
+    /// None when there is no such message.
+    pub fn toggle(&self, messages: &[Message], nth: usize, reply: bool) -> Option<PinChange> {
+        let index = pin_target(messages, nth, reply)?;
+        let text = message_text(&messages[index]);
+        let mut pins = self.0.lock().unwrap();
+        if pins.remove(&index) {
+            Some(PinChange::Unpinned(text))
+        } else {
+            pins.insert(index);
+            Some(PinChange::Pinned(text))
+        }
+    }
+
+    /// Drop the pins [`live_pins`] wouldn't keep.
+    pub fn prune(&self, messages: &[Message]) {
+        let live = live_pins(messages, &self.indexes());
+        self.set(live);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::forget::forget_user_message;
+
+    fn history() -> Vec<Message> {
+        vec![
+            Message::user("the API must stay backwards compatible"),
+            Message::assistant("Noted."),
+            Message::user("now add the endpoint"),
+        ]
+    }
+
+    #[test]
+    fn pin_toggles_and_counts_back_over_typed_messages() {
+        let pins = Pins::default();
+        let messages = history();
+        let change = pins.toggle(&messages, 2, false).unwrap();
+        assert_eq!(
+            change,
+            PinChange::Pinned("the API must stay backwards compatible".to_string())
+        );
+        assert!(change.to_string().starts_with("\u{1f4cc} Pinned \"the API"));
+        assert_eq!(pins.indexes(), [0]);
+
+        assert!(matches!(
+            pins.toggle(&messages, 2, false),
+            Some(PinChange::Unpinned(_))
+        ));
+        assert!(pins.indexes().is_empty());
+        assert!(pins.toggle(&messages, 3, false).is_none());
+    }
+
+    #[test]
+    fn replies_can_be_pinned_too() {
+        let pins = Pins::default();
+        let messages = history();
+        assert_eq!(
+            pins.toggle(&messages, 1, true),
+            Some(PinChange::Pinned("Noted.".to_string()))
+        );
+        assert_eq!(pins.indexes(), [1]);
+        assert!(pins.toggle(&messages, 2, true).is_none());
+    }
+
+    #[test]
+    fn identical_messages_are_pinned_separately() {
+        let pins = Pins::default();
+        let messages = vec![
+            Message::user("continue"),
+            Message::assistant("ok"),
+            Message::user("continue"),
+        ];
+        pins.toggle(&messages, 1, false).unwrap();
+        assert_eq!(pins.indexes(), [2]);
+    }
+
+    #[test]
+    fn forgetting_or_regenerating_a_pinned_message_drops_the_pin() {
+        let pins = Pins::default();
+        let mut messages = history();
+        pins.toggle(&messages, 1, false).unwrap();
+        pins.toggle(&messages, 2, false).unwrap();
+        forget_user_message(&mut messages, 2).unwrap();
+        pins.prune(&messages);
+        assert_eq!(pins.indexes(), [2]);
+
+        messages.truncate(2);
+        pins.prune(&messages);
+        assert!(pins.indexes().is_empty());
+    }
+
+    #[test]
+    fn long_messages_are_previewed() {
+        let long = "x".repeat(100);
+        assert_eq!(preview(&long).chars().count(), PREVIEW_CHARS + 1);
+        assert_eq!(preview("first\nsecond"), "first\u{2026}");
+        assert_eq!(preview("short"), "short");
+    }
+}
@@ -9,8 +9,20 @@ use mux::llm::{
 
 use crate::config::LlmConfig;
 
+/// Attach each configured header to a client via its `with_header` builder.
+macro_rules! with_headers {
+    ($client:expr, $headers:expr) => {{
+        let mut client = $client;
+        for (name, value) in $headers {
+            client = client.with_header(name, value);
+        }
+        client
+    }};
+}
+
 /// Create an LLM client based on the provider name in config.
 pub fn create_client(config: &LlmConfig) -> anyhow::Result<Arc<dyn LlmClient>> {
+    let headers = config.provider_headers();
     match config.provider.as_str() {
         "anthropic" => {
             let mut client = AnthropicClient::from_env()?;
@@ -22,21 +34,21 @@ pub fn create_client(config: &LlmConfig) -> anyhow::Result<Arc<dyn LlmClient>> {
             {
                 client = client.with_base_url(url);
             }
-            Ok(Arc::new(client))
+            Ok(Arc::new(with_headers!(client, &headers)))
         }
         "openai" => {
             let mut client = OpenAIClient::from_env()?;
             if let Some(url) = config.openai.base_url.as_deref().filter(|s| !s.is_empty()) {
                 client = client.with_base_url(url);
             }
-            Ok(Arc::new(client))
+            Ok(Arc::new(with_headers!(client, &headers)))
         }
         "gemini" => {
             let mut client = GeminiClient::from_env()?;
             if let Some(url) = config.gemini.base_url.as_deref().filter(|s| !s.is_empty()) {
                 client = client.with_base_url(url);
             }
-            Ok(Arc::new(client))
+            Ok(Arc::new(with_headers!(client, &headers)))
         }
         "openrouter" => {
             if let Some(url) = config
@@ -46,16 +58,16 @@ pub fn create_client(config: &LlmConfig) -> anyhow::Result<Arc<dyn LlmClient>> {
                 .filter(|s| !s.is_empty())
             {
                 let client = OpenAIClient::openrouter_from_env()?.with_base_url(url);
-                Ok(Arc::new(client))
+                Ok(Arc::new(with_headers!(client, &headers)))
             } else {
                 let client = OpenRouterClient::from_env()?;
-                Ok(Arc::new(client))
+                Ok(Arc::new(with_headers!(client, &headers)))
             }
         }
         "ollama" => {
             let base_url = format!("{}/v1", config.ollama.base_url.trim_end_matches('/'));
             let client = OllamaClient::with_base_url(&base_url, &config.model);
-            Ok(Arc::new(client))
+            Ok(Arc::new(with_headers!(client, &headers)))
         }
         other => anyhow::bail!(
             "Unknown LLM provider: '{}'. Expected: anthropic, openai, gemini, openrouter, ollama",
@@ -79,4 +91,19 @@ mod tests {
         let err = result.err().unwrap();
         assert!(err.to_string().contains("fakeprovider"));
     }
+
+    #[test]
+    fn ollama_client_accepts_configured_headers() {
+        // Ollama needs no API key, so the full builder path runs offline.
+        let mut config = LlmConfig {
+            provider: "ollama".to_string(),
+            ..Default::default()
+        };
+        config
+            .ollama
+            .headers
+            .insert("X-Proxy-Auth".to_string(), "token".to_string());
+        assert_eq!(config.provider_headers().len(), 1);
+        assert!(create_client(&config).is_ok());
+    }
 }
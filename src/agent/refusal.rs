@@ -0,0 +1,80 @@
+// ABOUTME: Replies the provider ended without an answer — a refusal, a content filter, or nothing at all.
+// ABOUTME: Maps mux's stop reasons onto what the loop tells the user about the blank reply.
+
+use mux::prelude::*;
+
+/// Why the model stopped without answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Declined {
+    /// The model itself declined the request.
+    Refusal,
+    /// The provider's content filter cut the reply off.
+    ContentFilter,
+    /// The turn ended normally but with no text and no tool calls.
+    Empty,
+}
+
+impl Declined {
+    /// What a response that stopped for `stop_reason` with `blocks` means,
+    /// or None when it's an ordinary answer (or was cut off by max tokens).
+    pub fn of(stop_reason: Option<&StopReason>, blocks: &[ContentBlock]) -> Option<Self> {
+        match stop_reason {
+            Some(StopReason::Refusal) => Some(Declined::Refusal),
+            Some(StopReason::ContentFilter) => Some(Declined::ContentFilter),
+            Some(StopReason::EndTurn) | None => blocks.is_empty().then_some(Declined::Empty),
+            Some(StopReason::ToolUse | StopReason::MaxTokens | StopReason::StopSequence) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Declined {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Declined::Refusal => write!(
+                f,
+                "The model declined to answer this request. Rephrasing it, or explaining what it's for, may help"
+            ),
+            Declined::ContentFilter => write!(
+                f,
+                "The provider's content filter stopped the reply. Rephrasing the request may help"
+            ),
+            Declined::Empty => write!(f, "The model ended its turn without replying"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refusal_and_filter_stops_are_declined_even_with_partial_text() {
+        let partial = [ContentBlock::text("I can")];
+        assert_eq!(
+            Declined::of(Some(&StopReason::Refusal), &partial),
+            Some(Declined::Refusal)
+        );
+        assert_eq!(
+            Declined::of(Some(&StopReason::ContentFilter), &[]),
+            Some(Declined::ContentFilter)
+        );
+        assert_eq!(
+            Declined::of(Some(&StopReason::ContentFilter), &partial),
+            Some(Declined::ContentFilter)
+        );
+        assert_eq!(Declined::of(Some(&StopReason::MaxTokens), &partial), None);
+    }
+
+    #[test]
+    fn only_blank_end_turns_count_as_empty() {
+        assert_eq!(
+            Declined::of(Some(&StopReason::EndTurn), &[]),
+            Some(Declined::Empty)
+        );
+        assert_eq!(
+            Declined::of(Some(&StopReason::EndTurn), &[ContentBlock::text("hi")]),
+            None
+        );
+        assert_eq!(Declined::of(Some(&StopReason::ToolUse), &[]), None);
+    }
+}
@@ -0,0 +1,20 @@
+// ABOUTME: Shutdown flag shared by the app and the agent loop, set once soloclaw starts quitting.
+// ABOUTME: Lets the loop tell a prompt dropped by a closing TUI apart from a user's answer.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when the session is shutting down. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownFlag(Arc<AtomicBool>);
+
+impl ShutdownFlag {
+    /// Mark the session as shutting down.
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
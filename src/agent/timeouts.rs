@@ -0,0 +1,187 @@
+// ABOUTME: Provider request timeouts — a limit on the whole request and on gaps between stream events.
+// ABOUTME: A stalled connection ends the turn with a RequestTimeout error instead of hanging it forever.
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+
+use crate::config::LlmConfig;
+
+/// How long to wait on the provider. None waits forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestTimeouts {
+    /// A non-streaming request, or a stream's first event.
+    pub request: Option<Duration>,
+    /// The gap between one stream event and the next.
+    pub stream_idle: Option<Duration>,
+}
+
+impl RequestTimeouts {
+    /// The limits from `[llm]`; 0 disables either one.
+    pub fn from_config(config: &LlmConfig) -> Self {
+        let seconds = |s: u64| (s > 0).then(|| Duration::from_secs(s));
+        Self {
+            request: seconds(config.request_timeout_seconds),
+            stream_idle: seconds(config.stream_idle_timeout_seconds),
+        }
+    }
+
+    /// Never time out.
+    pub fn none() -> Self {
+        Self::default()
+    }
+}
+
+/// The provider went quiet for longer than a limit allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTimeout {
+    pub waited: Duration,
+    /// Timed out between stream events rather than waiting for a response.
+    pub idle: bool,
+}
+
+impl std::fmt::Display for RequestTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.idle {
+            write!(
+                f,
+                "Provider stream stalled: no data for {}s (stream_idle_timeout_seconds)",
+                self.waited.as_secs()
+            )
+        } else {
+            write!(
+                f,
+                "Provider didn't respond within {}s (request_timeout_seconds)",
+                self.waited.as_secs()
+            )
+        }
+    }
+}
+
+impl std::error::Error for RequestTimeout {}
+
+/// Await `request`, giving up after `limit`.
+pub async fn within<T>(
+    limit: Option<Duration>,
+    request: impl Future<Output = T>,
+) -> Result<T, RequestTimeout> {
+    let Some(limit) = limit else {
+        return Ok(request.await);
+    };
+    tokio::select! {
+        output = request => Ok(output),
+        _ = tokio::time::sleep(limit) => Err(RequestTimeout { waited: limit, idle: false }),
+    }
+}
+
+/// The next event from `stream`, racing it against a sleep that starts over
+/// with each call: `timeouts.request` before the first event (`started` is
+/// false), `timeouts.stream_idle` after it.
+pub async fn next_within<S>(
+    stream: &mut S,
+    timeouts: &RequestTimeouts,
+    started: bool,
+) -> Result<Option<S::Item>, RequestTimeout>
+where
+    S: Stream + Unpin,
+{
+    let limit = if started {
+        timeouts.stream_idle
+    } else {
+        timeouts.request
+    };
+    within(limit, stream.next())
+        .await
+        .map_err(|timeout| RequestTimeout {
+            idle: started,
+            ..timeout
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    fn timeouts(request_ms: u64, idle_ms: u64) -> RequestTimeouts {
+        RequestTimeouts {
+            request: Some(Duration::from_millis(request_ms)),
+            stream_idle: Some(Duration::from_millis(idle_ms)),
+        }
+    }
+
+    #[tokio::test]
+    async fn events_inside_the_idle_window_keep_the_stream_alive() {
+        let (tx, rx) = mpsc::channel(4);
+        let mut stream = ReceiverStream::new(rx);
+        tokio::spawn(async move {
+            for n in 0..4 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                tx.send(n).await.unwrap();
+            }
+        });
+
+        // Each gap is 20ms; the whole stream takes longer than one 60ms window.
+        let limits = timeouts(500, 60);
+        let mut seen = Vec::new();
+        while let Some(n) = next_within(&mut stream, &limits, !seen.is_empty())
+            .await
+            .unwrap()
+        {
+            seen.push(n);
+        }
+        assert_eq!(seen, [0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn a_stall_after_the_first_event_is_an_idle_timeout() {
+        let (tx, rx) = mpsc::channel(4);
+        let mut stream = ReceiverStream::new(rx);
+        tx.send(1).await.unwrap();
+
+        let limits = timeouts(500, 30);
+        assert_eq!(next_within(&mut stream, &limits, false).await, Ok(Some(1)));
+        let err = next_within(&mut stream, &limits, true).await.unwrap_err();
+        assert!(err.idle);
+        assert_eq!(err.waited, Duration::from_millis(30));
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn no_first_event_is_a_request_timeout() {
+        let (_tx, rx) = mpsc::channel::<u8>(1);
+        let mut stream = ReceiverStream::new(rx);
+
+        let err = next_within(&mut stream, &timeouts(30, 500), false)
+            .await
+            .unwrap_err();
+        assert!(!err.idle);
+        assert!(err.to_string().contains("request_timeout_seconds"));
+    }
+
+    #[tokio::test]
+    async fn no_limit_waits_for_the_stream() {
+        let (tx, rx) = mpsc::channel(1);
+        let mut stream = ReceiverStream::new(rx);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            tx.send(7).await.unwrap();
+        });
+        let next = next_within(&mut stream, &RequestTimeouts::none(), true).await;
+        assert_eq!(next, Ok(Some(7)));
+    }
+
+    #[test]
+    fn zero_disables_a_limit() {
+        let config = LlmConfig {
+            request_timeout_seconds: 0,
+            stream_idle_timeout_seconds: 45,
+            ..LlmConfig::default()
+        };
+        let limits = RequestTimeouts::from_config(&config);
+        assert_eq!(limits.request, None);
+        assert_eq!(limits.stream_idle, Some(Duration::from_secs(45)));
+    }
+}
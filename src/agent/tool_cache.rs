@@ -0,0 +1,295 @@
+// ABOUTME: Per-turn cache of idempotent tool results (read_file, list_files, search, ...).
+// ABOUTME: Repeated identical reads within a turn reuse the earlier result; writes invalidate it.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use mux::prelude::*;
+use serde_json::Value;
+
+use crate::approval::analyze_command;
+use crate::session::changes::normalize_path;
+use crate::tools::catalog::is_read_only_tool;
+
+/// Results of cacheable tool calls made during one conversation turn, keyed
+/// by tool name and canonicalized parameters. Dropped when the turn ends.
+pub struct ToolCache {
+    cacheable: HashSet<String>,
+    entries: HashMap<(String, String), String>,
+}
+
+impl ToolCache {
+    /// A cache for the tools named in `[tools] cache_reads` that only read:
+    /// the read-only built-ins, and bash for safe commands. Anything else
+    /// listed is ignored; reusing its result would skip what it does.
+    pub fn new(cacheable: &[String]) -> Self {
+        Self {
+            cacheable: cacheable
+                .iter()
+                .filter(|name| is_read_only_tool(name) || *name == "bash")
+                .cloned()
+                .collect(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The cache key for a call, or None when the call must not be cached.
+    /// Bash is only cached when listed and the command analyzes as safe.
+    fn key(&self, name: &str, input: &Value) -> Option<(String, String)> {
+        if !self.cacheable.contains(name) {
+            return None;
+        }
+        if name == "bash" && !is_safe_bash(input) {
+            return None;
+        }
+        Some((name.to_string(), canonical_json(input)))
+    }
+
+    /// The content of an earlier identical call in this turn.
+    pub fn get(&self, name: &str, input: &Value) -> Option<&str> {
+        let key = self.key(name, input)?;
+        self.entries.get(&key).map(String::as_str)
+    }
+
+    /// Record an executed call: cache successful cacheable results, and drop
+    /// entries the call may have made stale.
+    pub fn record(&mut self, name: &str, input: &Value, result: &ToolResult) {
+        if let Some(key) = self.key(name, input) {
+            if !result.is_error {
+                self.entries.insert(key, result.content.clone());
+            }
+            return;
+        }
+        if name == "write_file" {
+            if let Some(path) = input.get("path").and_then(|v| v.as_str()) {
+                self.invalidate_path(path);
+            }
+        } else if !is_read_only_tool(name) && !(name == "bash" && is_safe_bash(input)) {
+            // Anything else that can write (unsafe bash, MCP tools) might
+            // have touched any file.
+            self.entries.clear();
+        }
+    }
+
+    /// Forget results that may include `path`: reads of the path itself,
+    /// listings and searches of a directory containing it, and every cached
+    /// bash command, since its arguments can't be attributed to paths.
+    /// Paths are compared resolved against the workspace, so `./a.rs`,
+    /// `src/../a.rs`, and the absolute path are the same file.
+    pub fn invalidate_path(&mut self, path: &str) {
+        let written = normalize_path(Path::new(path));
+        self.entries.retain(|(tool, params), _| {
+            if tool == "bash" {
+                return false;
+            }
+            let cached_path = serde_json::from_str::<Value>(params)
+                .ok()
+                .and_then(|v| v.get("path").and_then(|p| p.as_str()).map(str::to_string));
+            match cached_path {
+                Some(cached) => !written.starts_with(normalize_path(Path::new(&cached))),
+                // Searches without a path cover the whole workspace.
+                None => false,
+            }
+        });
+    }
+
+    /// Number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn is_safe_bash(input: &Value) -> bool {
+    input
+        .get("command")
+        .and_then(|v| v.as_str())
+        .is_some_and(|command| analyze_command(command).safe)
+}
+
+/// Serialize with object keys sorted at every level, so parameter order
+/// doesn't affect the cache key.
+fn canonical_json(value: &Value) -> String {
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Value::Object(
+                    keys.into_iter()
+                        .map(|k| (k.clone(), sorted(&map[k])))
+                        .collect(),
+                )
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    sorted(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn reads() -> Vec<String> {
+        ["read_file", "list_files", "search"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn hit_after_recording_and_miss_for_other_params() {
+        let mut cache = ToolCache::new(&reads());
+        let input = json!({"path": "src/main.rs"});
+        assert!(cache.get("read_file", &input).is_none());
+
+        cache.record("read_file", &input, &ToolResult::text("fn main() {}"));
+        assert_eq!(cache.get("read_file", &input), Some("fn main() {}"));
+        assert!(
+            cache
+                .get("read_file", &json!({"path": "src/lib.rs"}))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn key_ignores_parameter_order() {
+        let mut cache = ToolCache::new(&reads());
+        cache.record(
+            "search",
+            &json!({"pattern": "todo", "path": "src"}),
+            &ToolResult::text("3 matches"),
+        );
+        assert_eq!(
+            cache.get("search", &json!({"path": "src", "pattern": "todo"})),
+            Some("3 matches")
+        );
+    }
+
+    #[test]
+    fn errors_and_unlisted_tools_are_not_cached() {
+        let mut cache = ToolCache::new(&["read_file".to_string()]);
+        let input = json!({"path": "missing.txt"});
+        cache.record("read_file", &input, &ToolResult::error("not found"));
+        assert!(cache.get("read_file", &input).is_none());
+
+        let listing = json!({"path": "."});
+        cache.record("list_files", &listing, &ToolResult::text("a\nb"));
+        assert!(cache.get("list_files", &listing).is_none());
+    }
+
+    #[test]
+    fn write_invalidates_the_path_and_its_directories() {
+        let mut cache = ToolCache::new(&reads());
+        cache.record(
+            "read_file",
+            &json!({"path": "src/a.rs"}),
+            &ToolResult::text("a"),
+        );
+        cache.record(
+            "read_file",
+            &json!({"path": "src/b.rs"}),
+            &ToolResult::text("b"),
+        );
+        cache.record(
+            "list_files",
+            &json!({"path": "src"}),
+            &ToolResult::text("a.rs b.rs"),
+        );
+        cache.record(
+            "search",
+            &json!({"pattern": "x"}),
+            &ToolResult::text("none"),
+        );
+
+        cache.record(
+            "write_file",
+            &json!({"path": "src/a.rs", "content": "new"}),
+            &ToolResult::text("ok"),
+        );
+
+        assert!(
+            cache
+                .get("read_file", &json!({"path": "src/a.rs"}))
+                .is_none()
+        );
+        assert!(cache.get("list_files", &json!({"path": "src"})).is_none());
+        assert!(cache.get("search", &json!({"pattern": "x"})).is_none());
+        assert_eq!(
+            cache.get("read_file", &json!({"path": "src/b.rs"})),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn writes_invalidate_the_same_file_however_it_is_spelled() {
+        let mut cache = ToolCache::new(&reads());
+        cache.record(
+            "read_file",
+            &json!({"path": "src/a.rs"}),
+            &ToolResult::text("a"),
+        );
+        cache.record(
+            "list_files",
+            &json!({"path": "."}),
+            &ToolResult::text("src"),
+        );
+        cache.record(
+            "read_file",
+            &json!({"path": "src/b.rs"}),
+            &ToolResult::text("b"),
+        );
+
+        cache.invalidate_path("./src/../src/a.rs");
+        assert!(
+            cache
+                .get("read_file", &json!({"path": "src/a.rs"}))
+                .is_none()
+        );
+        assert!(cache.get("list_files", &json!({"path": "."})).is_none());
+        assert_eq!(
+            cache.get("read_file", &json!({"path": "src/b.rs"})),
+            Some("b")
+        );
+
+        let absolute = std::env::current_dir().unwrap().join("src/b.rs");
+        cache.invalidate_path(absolute.to_str().unwrap());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn only_read_only_tools_are_cached() {
+        let listed = vec!["write_file".to_string(), "deploy".to_string()];
+        let mut cache = ToolCache::new(&listed);
+        let input = json!({"path": "a.txt", "content": "x"});
+        cache.record("write_file", &input, &ToolResult::text("ok"));
+        assert!(cache.get("write_file", &input).is_none());
+        cache.record("deploy", &json!({}), &ToolResult::text("done"));
+        assert!(cache.get("deploy", &json!({})).is_none());
+    }
+
+    #[test]
+    fn bash_is_cached_only_when_listed_and_safe() {
+        let safe = json!({"command": "cat README.md"});
+        let unsafe_cmd = json!({"command": "rm -rf build"});
+
+        let mut cache = ToolCache::new(&reads());
+        cache.record("bash", &safe, &ToolResult::text("# readme"));
+        assert!(cache.get("bash", &safe).is_none(), "bash not listed");
+
+        let mut listed = reads();
+        listed.push("bash".to_string());
+        let mut cache = ToolCache::new(&listed);
+        cache.record("bash", &safe, &ToolResult::text("# readme"));
+        assert_eq!(cache.get("bash", &safe), Some("# readme"));
+        cache.record("bash", &unsafe_cmd, &ToolResult::text(""));
+        assert!(cache.get("bash", &unsafe_cmd).is_none());
+        assert!(cache.is_empty(), "an unsafe command clears the cache");
+    }
+}
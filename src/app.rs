@@ -1,7 +1,9 @@
 // ABOUTME: App orchestrator — wires together LLM client, tools, approval, TUI, and agent loop.
 // ABOUTME: Sets up subsystems then runs the boba TUI event loop.
 
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use tokio::sync::{mpsc, Mutex};
@@ -13,92 +15,390 @@ use boba::{MouseMode, ProgramOptions};
 use crate::agent;
 use crate::agent::AgentLoopParams;
 use crate::agent::compaction;
-use crate::approval::ApprovalEngine;
-use crate::tools::ask_user::AskUserTool;
-use crate::config::{Config, load_mcp_configs};
+use crate::agent::cost::{TokenUsage, format_cost};
+use crate::agent::effort::Reasoning;
+use crate::agent::fallback::Fallbacks;
+use crate::agent::pins::Pins;
+use crate::agent::shutdown::ShutdownFlag;
+use crate::agent::timeouts::RequestTimeouts;
+use crate::approval::{ApprovalEngine, SecurityLevel};
+use crate::config::{
+    BroadWorkspace, Config, McpConflictPolicy, Provenance, SessionFormat, Source, load_mcp_configs,
+    load_mcp_descriptions, migrate_state_files,
+};
+use crate::events::{self, EventRecord};
+use crate::exit::{ExitStatus, SessionTally};
 use crate::prompt::{
-    SystemPromptParams, build_system_prompt, load_context_files, load_skill_files,
+    SystemPromptParams, build_system_prompt, hostname, load_context_files, load_skill_files,
 };
-use crate::session::SessionLogger;
+use crate::redact::redact_params;
 use crate::session::persistence;
+use crate::session::staleness::run_git;
+use crate::session::{
+    AutoSave, ChangeJournal, FsyncPolicy, ResumeCheck, ResumeFallback, SessionLogger, check_resume,
+    check_staleness, prune_sessions, stale_threshold, workspace_hash,
+};
+use crate::tools::ask_user::AskUserTool;
+use crate::tools::catalog::{FILE_TOOLS, ToolInfo, ToolOrigin, register_enabled};
+use crate::tools::conflicts::{Placement, ToolConflict, place_tools};
+use crate::tools::journaled::Journaled;
+use crate::tools::mcp::{McpServer, McpTool, start_in_background, start_servers};
+use crate::tools::open_in_editor::OpenInEditorTool;
+use crate::tools::roster::McpRoster;
+use crate::tools::summary::describe_tool_call_within;
+use crate::tui::consent::Consents;
+use crate::tui::keyboard;
 use crate::tui::model::{ClawApp, Flags};
+use crate::tui::onboarding::{Onboarding, is_first_run};
 use crate::tui::state::{ChatMessage, ChatMessageKind, ToolCallStatus, UserEvent};
+use crate::tui::widgets::chat::is_thinking;
+use crate::tui::widgets::status::format_tokens;
 
 /// Top-level application that orchestrates all subsystems.
 pub struct App {
     config: Config,
     fresh: bool,
+    mcp: bool,
+    json_events: Option<String>,
+    provenance: Provenance,
+    initial_prompt: Option<String>,
+    observe: bool,
 }
 
 impl App {
     /// Create a new app with the given configuration.
     pub fn new(config: Config, fresh: bool) -> Self {
-        Self { config, fresh }
+        Self {
+            config,
+            fresh,
+            mcp: true,
+            json_events: None,
+            provenance: Provenance::default(),
+            initial_prompt: None,
+            observe: false,
+        }
+    }
+
+    /// Record where the effective settings came from, for the startup message.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Start in observe mode, where every tool call is refused (`--observe`).
+    pub fn with_observe(mut self, observe: bool) -> Self {
+        self.observe = observe;
+        self
+    }
+
+    /// Mirror events as JSON lines to a file or FIFO (`-` for stderr).
+    pub fn with_json_events(mut self, target: Option<String>) -> Self {
+        self.json_events = target;
+        self
+    }
+
+    /// Send `prompt` as the first message once the TUI is up.
+    pub fn with_initial_prompt(mut self, prompt: Option<String>) -> Self {
+        self.initial_prompt = prompt;
+        self
+    }
+
+    /// Enable or disable connecting to MCP servers from .mcp.json.
+    pub fn with_mcp(mut self, enabled: bool) -> Self {
+        self.mcp = enabled;
+        self
     }
 
     /// Run the application: set up subsystems, launch the agent loop, and drive the TUI.
-    pub async fn run(self) -> anyhow::Result<()> {
+    /// Returns how the session ended, for the process exit code.
+    pub async fn run(self) -> anyhow::Result<ExitStatus> {
         // Load local .env if present, then XDG secrets.
         let _ = dotenvy::dotenv();
         let _ = dotenvy::from_path(Config::secrets_env_path());
 
+        // Logs, usage records, and locks moved from the data dir to the state dir.
+        match migrate_state_files(&Config::data_dir(), &Config::state_dir()) {
+            Ok(moved) => {
+                for (from, to) in moved {
+                    eprintln!("Moved {} to {}", from.display(), to.display());
+                }
+            }
+            Err(e) => eprintln!("Warning: failed to migrate state files: {}", e),
+        }
+
+        let workspace_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+
+        // Checked before this run writes a session or approvals.json. A
+        // `--prompt` run goes straight to work instead.
+        let onboarding = (self.initial_prompt.is_none()
+            && is_first_run(
+                &Config::onboarded_marker_path(),
+                &Config::approvals_path(),
+                &Config::sessions_dir(),
+            ))
+        .then(|| {
+            Onboarding::new(
+                Config::onboarded_marker_path(),
+                Config::resolved_config_path(),
+            )
+        });
+
+        // Started from ~ or /, the "workspace" is everything: don't load
+        // context files from it and ask before any file tool touches it.
+        let home = dirs::home_dir().map(|h| h.canonicalize().unwrap_or(h));
+        let broad_workspace = self.config.workspace.guard(
+            &workspace_path
+                .canonicalize()
+                .unwrap_or_else(|_| workspace_path.clone()),
+            home.as_deref(),
+        );
+
+        // Try to load an existing session for this workspace (unless --fresh).
+        let mut loaded_session = if !self.fresh {
+            persistence::load_session(&workspace_path, self.config.sessions.format)
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+
+        // The JSONL log is written ahead of session.json, so after a crash it
+        // may hold messages the snapshot never got.
+        let mut recovery_notice: Option<String> = None;
+        if !self.fresh && self.config.sessions.format == SessionFormat::Both {
+            match persistence::recover_session(&workspace_path, &mut loaded_session) {
+                Ok(recovery) => recovery_notice = recovery.describe(),
+                Err(e) => eprintln!("Warning: failed to replay the session log: {}", e),
+            }
+        }
+
+        // Make sure the saved session can actually continue with the current
+        // provider setup before the first request fails in a confusing way.
+        let mut resume_notice: Option<String> = None;
+        if let Some(session) = &loaded_session {
+            let check = check_resume(&session.model, &self.config.llm, |var| {
+                std::env::var(var).is_ok_and(|v| !v.is_empty())
+            });
+            match check {
+                ResumeCheck::Resume => {}
+                ResumeCheck::MissingCredentials { .. } => {
+                    let why = check.describe().unwrap_or_default();
+                    // Ask on a terminal; with no one to ask, go on without
+                    // resuming.
+                    let fallback =
+                        if self.initial_prompt.is_none() && std::io::stdin().is_terminal() {
+                            eprintln!("{}", why);
+                            eprint!("{}", ResumeFallback::QUESTION);
+                            let mut answer = String::new();
+                            std::io::stdin().read_line(&mut answer)?;
+                            ResumeFallback::from_answer(&answer)
+                        } else {
+                            ResumeFallback::Fresh
+                        };
+                    if fallback == ResumeFallback::Quit {
+                        anyhow::bail!(why);
+                    }
+                    loaded_session = None;
+                    resume_notice = Some(format!("{} Started a fresh session instead.", why));
+                }
+                ResumeCheck::ModelSwitch { .. } => resume_notice = check.describe(),
+            }
+        }
+
+        // A session idle for days may remember files that have since changed.
+        // With the warning off, git isn't asked either.
+        let stale_after = stale_threshold(self.config.sessions.stale_after_hours);
+        let staleness =
+            loaded_session
+                .as_ref()
+                .zip(stale_after)
+                .and_then(|(session, threshold)| {
+                    check_staleness(
+                        &session.updated_at,
+                        session.head_commit.as_deref(),
+                        chrono::Utc::now(),
+                        Some(threshold),
+                        &|args: &[&str]| run_git(&workspace_path, args),
+                    )
+                });
+        let context_note = staleness
+            .as_ref()
+            .filter(|_| self.config.sessions.stale_context_note)
+            .map(|stale| stale.context_note());
+
         // Create LLM client.
         let client = agent::create_client(&self.config.llm)?;
 
-        // Create tool registry and register built-in tools.
+        // Create tool registry and register built-in tools, minus any that
+        // `[tools] disabled` hides.
         let registry = Registry::new();
-        registry.register(BashTool).await;
-        registry.register(ReadFileTool).await;
-        registry.register(WriteFileTool).await;
-        registry.register(ListFilesTool).await;
-        registry.register(SearchTool).await;
-        registry.register(AskUserTool).await;
-
-        // Connect MCP servers.
-        let mcp_configs = load_mcp_configs()?;
-        let mut mcp_clients: Vec<Arc<McpClient>> = Vec::new();
-        for mcp_config in mcp_configs {
-            let name = mcp_config.name.clone();
-            match McpClient::connect(mcp_config).await {
-                Ok(mut mcp_client) => {
-                    if let Err(e) = mcp_client.initialize().await {
-                        eprintln!("Warning: failed to initialize MCP server '{}': {}", name, e);
-                        continue;
-                    }
-                    let mcp_client = Arc::new(mcp_client);
-                    if let Err(e) = registry.merge_mcp(mcp_client.clone(), Some(&name)).await {
-                        eprintln!("Warning: failed to merge MCP tools from '{}': {}", name, e);
+        let tools_config = &self.config.tools;
+        register_enabled(&registry, tools_config, BashTool).await;
+        register_enabled(&registry, tools_config, ReadFileTool).await;
+        let journal = Arc::new(std::sync::Mutex::new(open_change_journal(
+            &workspace_path,
+            loaded_session.is_none(),
+        )));
+        register_enabled(
+            &registry,
+            tools_config,
+            Journaled::new(WriteFileTool, Arc::clone(&journal)),
+        )
+        .await;
+        register_enabled(&registry, tools_config, ListFilesTool).await;
+        register_enabled(&registry, tools_config, SearchTool).await;
+        register_enabled(&registry, tools_config, AskUserTool).await;
+        register_enabled(&registry, tools_config, OpenInEditorTool).await;
+
+        // Connect MCP servers, remembering which server contributed each tool.
+        let mut known_tools: HashSet<String> = registry
+            .to_definitions()
+            .await
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        let mut mcp_tool_origins: HashMap<String, String> = HashMap::new();
+        let (mcp_configs, mcp_descriptions) = if self.mcp {
+            (load_mcp_configs()?, load_mcp_descriptions()?)
+        } else {
+            (Vec::new(), HashMap::new())
+        };
+        // Server handles own the clients, which are replaced if a server has
+        // to be reconnected mid-session.
+        let mut mcp_servers: Vec<Arc<McpServer>> = Vec::new();
+        // Which server each MCP tool came from, re-synced while idle.
+        let mut mcp_roster = McpRoster::new(self.config.mcp.on_conflict)
+            .with_resync_every(self.config.mcp.resync_every());
+        // Per-server outcomes, reported in the TUI's startup message since
+        // stderr is hidden once the alternate screen takes over.
+        let mut mcp_statuses: Vec<McpServerStatus> = Vec::new();
+        // Tools whose names were already taken, resolved per [mcp] on_conflict.
+        let conflict_policy = self.config.mcp.on_conflict;
+        let mut conflict_notes: Vec<String> = Vec::new();
+        let mut conflicts: Vec<ToolConflict> = Vec::new();
+        // Servers start concurrently. With [mcp] lazy they're left starting
+        // in the background and the agent loop adds each as it comes up.
+        let mcp_timeout = self.config.mcp.connect_timeout();
+        let mut mcp_starting = 0;
+        let mut mcp_arrivals = None;
+        let mut background_start = None;
+        let started = if self.config.mcp.lazy && !mcp_configs.is_empty() {
+            mcp_starting = mcp_configs.len();
+            let (arrivals, background) = start_in_background(mcp_configs, mcp_timeout);
+            mcp_arrivals = Some(arrivals);
+            background_start = Some(background);
+            Vec::new()
+        } else {
+            start_servers(mcp_configs, mcp_timeout).await
+        };
+        for (name, result) in started {
+            // Each server's tools load into its own registry first, so a name
+            // that is already taken can't replace the earlier tool.
+            match result {
+                Ok(server) => {
+                    let server = Arc::new(server);
+                    mcp_servers.push(server.clone());
+                    mcp_roster.add_server(server.clone());
+                    // Disabled MCP tools are never registered, offered, or counted.
+                    let mut offered: Vec<String> = server
+                        .tool_names()
+                        .await
+                        .into_iter()
+                        .filter(|tool| !tools_config.is_disabled(tool))
+                        .collect();
+                    offered.sort();
+                    let (placements, server_conflicts) =
+                        place_tools(&name, &offered, &mut known_tools, conflict_policy);
+                    let mut tool_count = 0;
+                    for (tool, placement) in placements {
+                        let Placement::As(registered) = placement else {
+                            conflict_notes.push(format!("skipped {}: {}", name, tool));
+                            continue;
+                        };
+                        let Some(mcp_tool) = McpTool::new(&registered, &tool, server.clone()).await
+                        else {
+                            continue;
+                        };
+                        if registered != tool {
+                            conflict_notes.push(format!(
+                                "renamed {}: {} \u{2192} {}",
+                                name, tool, registered
+                            ));
+                        }
+                        registry.register(mcp_tool).await;
+                        mcp_roster.record(&registered, &name, &tool);
+                        mcp_tool_origins.insert(registered, name.clone());
+                        tool_count += 1;
                     }
-                    mcp_clients.push(mcp_client);
+                    conflicts.extend(server_conflicts);
+                    mcp_statuses.push(McpServerStatus {
+                        name: name.clone(),
+                        result: Ok(tool_count),
+                    });
                 }
                 Err(e) => {
-                    eprintln!("Warning: failed to connect MCP server '{}': {}", name, e);
+                    mcp_statuses.push(McpServerStatus::failed(&name, e.stage, e.error));
                 }
             }
         }
+        if conflict_policy == McpConflictPolicy::Error && !conflicts.is_empty() {
+            for server in &mcp_servers {
+                server.shutdown().await;
+            }
+            let listed: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+            anyhow::bail!(
+                "MCP tool names conflict with tools already registered ([mcp] on_conflict = \"error\"): {}",
+                listed.join(", ")
+            );
+        }
 
         // Create approval engine.
         let approvals_path = Config::approvals_path();
-        let engine = Arc::new(ApprovalEngine::new_with_bypass(
+        let mut engine = ApprovalEngine::new_with_bypass(
             approvals_path,
             self.config.permissions.bypass_approvals,
-        )?);
+        )?
+        .with_max_command_length(self.config.approval.max_command_length)
+        .with_redaction(self.config.privacy.redact_params)
+        .with_workspace(&workspace_path);
+        if let Some(level) = chosen_security(&self.config, &self.provenance) {
+            engine = engine.with_default_security(level);
+        }
+        if broad_workspace.is_some() {
+            engine = engine.with_restricted_tools(FILE_TOOLS);
+        }
+        let engine = Arc::new(engine);
 
         // Create channels for agent <-> TUI communication.
-        let (user_tx, user_rx) = mpsc::channel::<UserEvent>(16);
-        let (agent_tx, agent_rx) = mpsc::channel::<crate::tui::state::AgentEvent>(64);
+        let (user_tx, mut user_rx) = mpsc::channel::<UserEvent>(16);
+        let (agent_tx, mut agent_rx) = mpsc::channel::<crate::tui::state::AgentEvent>(64);
+
+        // With --json-events, tee both directions through the event stream.
+        let event_sink = self.json_events.as_deref().map(events::spawn_sink);
+        if let Some(sink) = &event_sink {
+            let (tee_user_tx, tee_user_rx) = mpsc::channel::<UserEvent>(16);
+            events::spawn_user_tee(user_rx, tee_user_tx, sink.clone());
+            user_rx = tee_user_rx;
+            let (tee_agent_tx, tee_agent_rx) = mpsc::channel::<crate::tui::state::AgentEvent>(64);
+            events::spawn_agent_tee(agent_rx, tee_agent_tx, sink.clone());
+            agent_rx = tee_agent_rx;
+        }
 
         let model = self.config.llm.model.clone();
         let max_tokens = self.config.llm.max_tokens;
         let approval_timeout_seconds = self.config.approval.timeout_seconds;
-        let tool_count = registry.count().await;
 
         // Gather runtime info and build the system prompt.
-        let workspace_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
         let workspace_dir = workspace_path.to_string_lossy().to_string();
 
-        let context_files = load_context_files(&workspace_dir);
-        let skill_files = load_skill_files(&workspace_dir, &self.config.skills);
+        let context_files = if broad_workspace.is_some() {
+            Vec::new()
+        } else {
+            load_context_files(&workspace_dir)
+        };
+        let skill_load = load_skill_files(&workspace_dir, &self.config.skills);
+        let skill_entries = skill_load.entries;
+        let skill_files = skill_load.files;
 
         // Collect context file names for the startup message shown in the TUI.
         let context_file_names: Vec<String> =
@@ -106,62 +406,156 @@ impl App {
         let skill_file_names: Vec<String> =
             skill_files.iter().map(|f| f.name.clone()).collect();
 
-        // Collect tool names and summaries from the registry.
-        let tool_defs = registry.to_definitions().await;
+        // Collect tool names and summaries from the registry. MCP tools can't
+        // be unregistered, so disabled ones are filtered out here as well.
+        let mut tool_defs = registry.to_definitions().await;
+        tool_defs.retain(|d| !tools_config.is_disabled(&d.name));
+        let tool_count = tool_defs.len();
         let tool_names: Vec<String> = tool_defs.iter().map(|d| d.name.clone()).collect();
-        let tool_summaries: std::collections::HashMap<String, String> = tool_defs
+        let tool_summaries: HashMap<String, String> = tool_defs
             .iter()
             .map(|d| (d.name.clone(), d.description.clone()))
             .collect();
 
-        let system_prompt = build_system_prompt(&SystemPromptParams {
+        // Snapshot of the tool catalog for the TUI's /tools command.
+        let tool_infos: Vec<ToolInfo> = tool_defs
+            .iter()
+            .map(|d| ToolInfo {
+                name: d.name.clone(),
+                description: d.description.clone(),
+                origin: match mcp_tool_origins.get(&d.name) {
+                    Some(server) => ToolOrigin::Mcp(server.clone()),
+                    None => ToolOrigin::BuiltIn,
+                },
+                security: engine.tool_security(&d.name).security,
+            })
+            .collect();
+
+        let tool_origins: HashMap<String, ToolOrigin> = tool_infos
+            .iter()
+            .map(|info| (info.name.clone(), info.origin.clone()))
+            .collect();
+        let prompt_params = SystemPromptParams {
             tool_names,
             tool_summaries,
+            tool_origins,
+            mcp_descriptions,
             workspace_dir,
             os: std::env::consts::OS.to_string(),
             arch: std::env::consts::ARCH.to_string(),
             shell: std::env::var("SHELL").unwrap_or_default(),
+            hostname: hostname(),
+            privacy: self.config.privacy.clone(),
             model: model.clone(),
             context_files,
             skill_files,
-        });
-
-        // Create session logger for conversation persistence.
-        let session_logger = match SessionLogger::new(&workspace_path) {
-            Ok(logger) => Some(Arc::new(Mutex::new(logger))),
-            Err(e) => {
-                eprintln!("Warning: failed to create session logger: {}", e);
-                None
-            }
         };
+        let system_prompt = build_system_prompt(&prompt_params);
 
-        // Try to load an existing session for this workspace (unless --fresh).
-        let loaded_session = if !self.fresh {
-            persistence::load_session(&workspace_path).ok().flatten()
-        } else {
-            None
-        };
+        // Apply session retention limits, never touching this workspace's session.
+        match prune_sessions(
+            &Config::sessions_dir(),
+            &self.config.sessions,
+            Some(&workspace_hash(&workspace_path)),
+            false,
+        ) {
+            Ok(report) if !report.removed.is_empty() => {
+                for line in report.describe() {
+                    eprintln!("Session cleanup: {}", line);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: failed to prune old sessions: {}", e),
+        }
 
         let initial_messages = loaded_session
             .as_ref()
             .map(|s| s.messages.clone())
             .unwrap_or_default();
+        let pins = Pins::new(
+            loaded_session
+                .as_ref()
+                .map(|s| s.pins.clone())
+                .unwrap_or_default(),
+        );
+        // A session saved under a larger model may not fit this one.
+        let (initial_messages, resume_fit) = compaction::fit_resumed_history(
+            initial_messages,
+            &model,
+            &self.config.compaction,
+            &pins,
+        )?;
+
+        // Create session logger for conversation persistence.
+        let session_format = self.config.sessions.format;
+        let session_logger = if session_format.writes_jsonl() {
+            match SessionLogger::new(&workspace_path) {
+                Ok(logger) => {
+                    let mut logger = logger
+                        .with_max_bytes(self.config.sessions.max_log_bytes)
+                        .with_fsync(FsyncPolicy::from_every(
+                            self.config.sessions.fsync_every_writes,
+                        ))
+                        // A JSONL-only log is all there is to resume from,
+                        // so it keeps the real parameters to replay.
+                        .with_redaction(
+                            self.config.privacy.redact_params && session_format.writes_json(),
+                        )
+                        .with_model(&model);
+                    // A JSONL-only session resumes from the newest log, so that
+                    // log has to start with the conversation being resumed.
+                    if !session_format.writes_json()
+                        && !initial_messages.is_empty()
+                        && let Err(e) = logger.log_history(&initial_messages, &pins.indexes())
+                    {
+                        eprintln!("Warning: failed to log resumed session: {}", e);
+                    }
+                    Some(Arc::new(Mutex::new(logger)))
+                }
+                Err(e) => {
+                    eprintln!("Warning: failed to create session logger: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Set before the TUI's prompts close on quit, so the loop ends the
+        // turn instead of treating them as denials.
+        let shutdown = ShutdownFlag::default();
 
         // Spawn the agent loop in a background task.
         let agent_handle = tokio::spawn(agent::run_agent_loop(
             AgentLoopParams {
                 client,
                 registry,
-                engine,
+                engine: Arc::clone(&engine),
                 model: model.clone(),
                 max_tokens,
                 approval_timeout_seconds,
                 system_prompt,
+                prompt_params,
                 initial_messages,
                 session_logger,
                 workspace_dir: workspace_path.clone(),
                 compaction_config: self.config.compaction.clone(),
                 existing_created_at: loaded_session.as_ref().map(|s| s.created_at.clone()),
+                streaming: self.config.llm.streaming,
+                tools_config: self.config.tools.clone(),
+                session_format,
+                reasoning: Reasoning::from_config(&self.config.llm),
+                autosave: AutoSave::from_config(&self.config.sessions),
+                observe: self.observe,
+                timeouts: RequestTimeouts::from_config(&self.config.llm),
+                mcp: mcp_roster,
+                mcp_arrivals,
+                shutdown: shutdown.clone(),
+                pins,
+                token_budget: self.config.agent.per_turn_token_budget,
+                fallbacks: Fallbacks::from_config(&self.config.llm),
+                pending_tool_calls: self.config.sessions.pending_tool_calls,
+                context_note,
             },
             user_rx,
             agent_tx,
@@ -172,23 +566,99 @@ impl App {
 
         // Build session replay messages for the TUI.
         let replay_messages = if let Some(ref session) = loaded_session {
-            replay_session_messages(session)
+            replay_session_messages(
+                session,
+                self.config.privacy.redact_params,
+                self.config.tools.summary_max_chars,
+            )
         } else {
             vec![]
         };
 
         // Build startup message.
-        let startup_message = build_startup_message(&context_file_names, &skill_file_names);
+        let mut startup_message =
+            build_startup_message(&context_file_names, &skill_file_names, &mcp_statuses);
+        if let Some(summary) = self.provenance.summary() {
+            startup_message.push_str(&format!("\nConfig: {}", summary));
+        }
+        if mcp_starting > 0 {
+            let noun = if mcp_starting == 1 {
+                "server"
+            } else {
+                "servers"
+            };
+            startup_message.push_str(&format!(
+                "\n\u{1f50c} Connecting to {} MCP {} in the background\u{2026}",
+                mcp_starting, noun
+            ));
+        }
+        if !conflict_notes.is_empty() {
+            startup_message.push_str(&format!(
+                "\n\u{26a0}\u{fe0f} MCP tool name conflicts: {}",
+                conflict_notes.join(", ")
+            ));
+        }
+        if let Some(notice) = recovery_notice {
+            startup_message.push_str(&format!("\n{}", notice));
+        }
+        if let Some(notice) = resume_notice {
+            startup_message.push_str(&format!("\n\u{26a0}\u{fe0f} {}", notice));
+        }
+        if let Some(stale) = &staleness {
+            startup_message.push_str(&format!("\n\u{26a0}\u{fe0f} {}", stale.describe()));
+        }
+        if let Some(note) = resume_fit.describe(&model) {
+            startup_message.push_str(&format!("\n\u{2702}\u{fe0f} {}", note));
+        }
+        if let Some(broad) = broad_workspace {
+            startup_message.push_str(&format!("\n{}", broad_workspace_warning(broad)));
+        }
+        if self.observe {
+            startup_message.push_str(&format!("\n{}", OBSERVE_NOTICE));
+        }
+        // Mark the workspace as seen either way, so the hint only ever shows
+        // on the very first run here.
+        let first_run = take_first_run(&Config::seen_workspaces_dir(), &workspace_path);
+        if first_run
+            && onboarding.is_none()
+            && broad_workspace.is_none()
+            && context_file_names.is_empty()
+            && skill_file_names.is_empty()
+        {
+            startup_message.push_str(&format!("\n{}", FIRST_RUN_HINT));
+        }
+
+        let context_window = compaction::context_window_for_model(&model);
+        let compaction_limit = self.config.compaction.enabled.then(|| {
+            compaction::auto_compact_limit(
+                context_window,
+                self.config.compaction.threshold_token_limit,
+            )
+        });
 
         let flags = Flags {
             user_tx,
             agent_rx,
             model_name: model.clone(),
             tool_count,
-            context_window: compaction::context_window_for_model(&model),
+            context_window,
+            compaction_limit,
             workspace_dir: workspace_path.to_string_lossy().to_string(),
             replay_messages,
             startup_message,
+            tui_config: self.config.tui.clone(),
+            tools: tool_infos,
+            skills: skill_entries,
+            snippets: self.config.snippets.clone(),
+            engine: Some(Arc::clone(&engine)),
+            initial_prompt: self.initial_prompt,
+            keyboard_enhancement: !self.config.tui.enter_submits && keyboard::supported(),
+            onboarding,
+            observe: self.observe,
+            privacy: self.config.privacy.clone(),
+            consents: Consents::load(&Config::consents_path()),
+            changes: Some(journal),
+            shutdown: shutdown.clone(),
         };
 
         let options = ProgramOptions {
@@ -203,33 +673,68 @@ impl App {
 
         // Run the boba TUI — blocks until quit.
         let result = boba::run_with::<ClawApp>(flags, options).await;
+        keyboard::disable();
 
         // Print farewell screen.
         if let Ok(ref app) = result {
             print_exit_screen(app);
         }
 
-        // Signal agent to quit and wait for it.
+        // The model set the shutdown flag when the user quit; boba can also
+        // return on its own (an error, a panic), so set it here too. Then
+        // tell the agent to quit and wait for it.
+        shutdown.request();
         let _ = user_tx_for_quit.send(UserEvent::Quit).await;
         drop(user_tx_for_quit);
         let _ = agent_handle.await;
 
         // Shutdown MCP clients.
-        for mcp_client in &mcp_clients {
-            let _ = mcp_client.shutdown().await;
+        for server in &mcp_servers {
+            server.shutdown().await;
+        }
+        if let Some(background) = background_start {
+            background.shutdown().await;
+        }
+
+        // The summary is the stream's last line, after the agent's final events.
+        let (tally, total_tokens) = match &result {
+            Ok(app) => (app.tally.clone(), app.total_tokens),
+            Err(_) => (SessionTally::default(), 0),
+        };
+        let status = tally.status(result.is_err());
+        if let Some(sink) = &event_sink {
+            sink.emit(&EventRecord::ExitSummary {
+                status,
+                turns: tally.turns,
+                tool_calls_run: tally.tool_calls_run,
+                tool_calls_denied: tally.tool_calls_denied,
+                total_tokens,
+            });
+            sink.close();
+        } else if result.is_ok()
+            && let Some(reason) = status.reason()
+        {
+            // Nothing else says why the exit code isn't 0.
+            eprintln!("Exit status {}: {}", status.code(), reason);
         }
 
         match result {
-            Ok(_) => Ok(()),
+            Ok(_) => Ok(status),
             Err(e) => Err(anyhow::anyhow!("TUI error: {}", e)),
         }
     }
 }
 
-/// Replay loaded session messages into ChatMessage format for the TUI.
-fn replay_session_messages(session: &persistence::SessionState) -> Vec<ChatMessage> {
+/// Replay loaded session messages into ChatMessage format for the TUI, with
+/// tool-call summaries cut to `summary_chars` as they were live.
+fn replay_session_messages(
+    session: &persistence::SessionState,
+    redact: bool,
+    summary_chars: usize,
+) -> Vec<ChatMessage> {
     let mut messages = Vec::new();
-    for msg in &session.messages {
+    for (i, msg) in session.messages.iter().enumerate() {
+        let pinned = session.pins.contains(&i);
         match msg.role {
             Role::User => {
                 for block in &msg.content {
@@ -237,16 +742,20 @@ fn replay_session_messages(session: &persistence::SessionState) -> Vec<ChatMessa
                         ContentBlock::Text { text } => {
                             if !text.is_empty() {
                                 messages.push(ChatMessage {
-                                    kind: ChatMessageKind::User,
-                                    content: text.clone(),
+                                    pinned,
+                                    ..ChatMessage::new(ChatMessageKind::User, text.clone())
                                 });
                             }
                         }
-                        ContentBlock::ToolResult { content, is_error, .. } => {
-                            messages.push(ChatMessage {
-                                kind: ChatMessageKind::ToolResult { is_error: *is_error },
-                                content: content.clone(),
-                            });
+                        ContentBlock::ToolResult {
+                            content, is_error, ..
+                        } => {
+                            messages.push(ChatMessage::new(
+                                ChatMessageKind::ToolResult {
+                                    is_error: *is_error,
+                                },
+                                content.clone(),
+                            ));
                         }
                         _ => {}
                     }
@@ -258,27 +767,30 @@ fn replay_session_messages(session: &persistence::SessionState) -> Vec<ChatMessa
                         ContentBlock::Text { text } => {
                             if !text.is_empty() {
                                 messages.push(ChatMessage {
-                                    kind: ChatMessageKind::Assistant,
-                                    content: text.clone(),
+                                    pinned,
+                                    ..ChatMessage::new(ChatMessageKind::Assistant, text.clone())
                                 });
                             }
                         }
-                        ContentBlock::ToolUse { name, input, .. } => {
-                            let params_summary = input.to_string();
-                            let char_count = params_summary.chars().count();
-                            let display = if char_count > 80 {
-                                let truncated: String = params_summary.chars().take(80).collect();
-                                format!("{}({}...)", name, truncated)
+                        ContentBlock::ToolUse { id, name, input } => {
+                            let display = if redact {
+                                describe_tool_call_within(
+                                    name,
+                                    &redact_params(input),
+                                    summary_chars,
+                                )
                             } else {
-                                format!("{}({})", name, params_summary)
+                                describe_tool_call_within(name, input, summary_chars)
                             };
-                            messages.push(ChatMessage {
-                                kind: ChatMessageKind::ToolCall {
+                            messages.push(ChatMessage::new(
+                                ChatMessageKind::ToolCall {
+                                    tool_call_id: id.clone(),
                                     tool_name: name.clone(),
                                     status: ToolCallStatus::Allowed,
+                                    approval_reason: None,
                                 },
-                                content: display,
-                            });
+                                display,
+                            ));
                         }
                         _ => {}
                     }
@@ -290,7 +802,11 @@ fn replay_session_messages(session: &persistence::SessionState) -> Vec<ChatMessa
 }
 
 /// Build the startup system message showing loaded context and skill files.
-fn build_startup_message(context_file_names: &[String], skill_file_names: &[String]) -> String {
+fn build_startup_message(
+    context_file_names: &[String],
+    skill_file_names: &[String],
+    mcp_statuses: &[McpServerStatus],
+) -> String {
     let mut parts: Vec<String> = Vec::new();
     if context_file_names.is_empty() {
         parts.push("No context files found".to_string());
@@ -300,7 +816,148 @@ fn build_startup_message(context_file_names: &[String], skill_file_names: &[Stri
     if !skill_file_names.is_empty() {
         parts.push(format!("Skills: {}", skill_file_names.join(", ")));
     }
-    parts.join(" | ")
+    let connected: Vec<String> = mcp_statuses
+        .iter()
+        .filter_map(|s| match &s.result {
+            Ok(1) => Some(format!("{} (1 tool)", s.name)),
+            Ok(n) => Some(format!("{} ({} tools)", s.name, n)),
+            Err(_) => None,
+        })
+        .collect();
+    if !connected.is_empty() {
+        parts.push(format!("MCP: {}", connected.join(", ")));
+    }
+
+    let mut message = parts.join(" | ");
+    for status in mcp_statuses {
+        if let Err(e) = &status.result {
+            message.push_str(&format!(
+                "\n\u{26a0}\u{fe0f} MCP server '{}' {}",
+                status.name, e
+            ));
+        }
+    }
+    message
+}
+
+/// Shown on the first run in a workspace that has no context files or skills.
+const FIRST_RUN_HINT: &str = "\u{1f44b} New here? Add an AGENTS.md to this directory to tell the agent \
+     about your project and how you like to work, or run `claw setup` to configure providers \
+     and approvals.";
+
+/// Shown at startup with `--observe`.
+const OBSERVE_NOTICE: &str = "\u{1f441}\u{fe0f} Observe mode: the model sees its tools, but every call \
+     is refused and nothing runs. /observe turns it off.";
+
+/// Resolve a `--prompt` argument: `-` reads the prompt from `stdin`.
+/// Surrounding whitespace is trimmed; an empty prompt means none.
+pub fn read_prompt_arg(arg: &str, mut stdin: impl std::io::Read) -> anyhow::Result<Option<String>> {
+    let text = if arg == "-" {
+        let mut buf = String::new();
+        stdin
+            .read_to_string(&mut buf)
+            .map_err(|e| anyhow::anyhow!("failed to read the prompt from stdin: {}", e))?;
+        buf
+    } else {
+        arg.to_string()
+    };
+    let text = text.trim();
+    Ok((!text.is_empty()).then(|| text.to_string()))
+}
+
+/// The security level to hand the approval engine at startup:
+/// `approval.security`, when the config file or `--security` set it. Left
+/// unset, approvals.json's own defaults stand.
+fn chosen_security(config: &Config, provenance: &Provenance) -> Option<SecurityLevel> {
+    provenance
+        .source("security")
+        .filter(|source| *source != Source::Default)
+        .and_then(|_| SecurityLevel::parse(&config.approval.security))
+}
+
+/// The startup warning for a workspace that is far broader than a project.
+fn broad_workspace_warning(broad: BroadWorkspace) -> String {
+    format!(
+        "\u{26a0}\u{fe0f} This workspace is {}. Context files were not loaded and file tools \
+         ask before every call. Start soloclaw from a project directory, or pass --allow-home \
+         (or set [workspace] allow_home = true) to run here with normal defaults.",
+        broad.describe()
+    )
+}
+
+/// This workspace's change journal: the saved one on resume, a new one with
+/// `fresh` (no session was resumed, so an old journal belongs to a session
+/// that's gone). An unreadable journal is replaced rather than stopping
+/// startup.
+fn open_change_journal(workspace: &Path, fresh: bool) -> ChangeJournal {
+    let dir = Config::sessions_dir().join(workspace_hash(workspace));
+    ChangeJournal::open(&dir, fresh).unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: failed to load the change journal, starting a new one: {}",
+            e
+        );
+        ChangeJournal::open(&dir, true).expect("a fresh journal needs no reads")
+    })
+}
+
+/// Whether this is the first run in `workspace`, recording it under
+/// `markers_dir` so later calls return false. If the marker can't be
+/// written the run still counts as first, at worst repeating the hint.
+fn take_first_run(markers_dir: &Path, workspace: &Path) -> bool {
+    let marker = markers_dir.join(workspace_hash(workspace));
+    if marker.exists() {
+        return false;
+    }
+    if let Err(e) = std::fs::create_dir_all(markers_dir)
+        .and_then(|()| std::fs::write(&marker, workspace.to_string_lossy().as_bytes()))
+    {
+        eprintln!("Warning: failed to record first run: {}", e);
+    }
+    true
+}
+
+/// How starting one MCP server went: its tool count, or what failed.
+struct McpServerStatus {
+    name: String,
+    result: Result<usize, String>,
+}
+
+impl McpServerStatus {
+    fn failed(name: &str, stage: &str, error: impl std::fmt::Display) -> Self {
+        Self {
+            name: name.to_string(),
+            result: Err(format!("{}: {}", stage, error)),
+        }
+    }
+}
+
+/// One line of session token totals, with cache reads and writes broken
+/// out and the estimated cost when the model's pricing is known.
+fn usage_summary(usage: &TokenUsage, cost: Option<f64>) -> String {
+    let mut summary = format!(
+        "{} tokens ({} in, {} out",
+        format_tokens(usage.total()),
+        format_tokens(usage.input),
+        format_tokens(usage.output)
+    );
+    if usage.cache_read > 0 || usage.cache_write > 0 {
+        summary.push_str(&format!(
+            ", {} cache read, {} cache write",
+            format_tokens(usage.cache_read),
+            format_tokens(usage.cache_write)
+        ));
+    }
+    summary.push(')');
+    if let Some(cost) = cost {
+        summary.push_str(&format!(", about {}", format_cost(cost)));
+    }
+    summary
+}
+
+/// Messages that count as exchanged: not a "thinking…" placeholder left by
+/// quitting before the reply's first token.
+fn exchanged_messages(messages: &[ChatMessage]) -> usize {
+    messages.iter().filter(|m| !is_thinking(m)).count()
 }
 
 /// Print a farewell screen after the TUI exits.
@@ -311,7 +968,7 @@ fn print_exit_screen(app: &ClawApp) {
     } else {
         format!("{}m {:02}s", elapsed_secs / 60, elapsed_secs % 60)
     };
-    let msg_count = app.messages.len();
+    let msg_count = exchanged_messages(&app.messages);
 
     let farewells: &[(&str, &str)] = &[
         ("You showed up for AI today, and that's pretty cool.", "Until next time \u{2014} keep building awesome things!"),
@@ -339,7 +996,237 @@ fn print_exit_screen(app: &ClawApp) {
     println!();
     println!("  \u{2728} {line1}");
     println!("  \u{1f550} Session lasted {elapsed} with {msg_count} messages exchanged.");
+    if app.total_tokens > 0 {
+        println!(
+            "  \u{1f4ca} {}",
+            usage_summary(&app.usage, app.session_cost())
+        );
+    }
     println!();
     println!("  \u{1f49c} {line2}");
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prompt_arg_is_used_as_is_or_read_from_stdin() {
+        let no_stdin: &[u8] = b"";
+        assert_eq!(
+            read_prompt_arg("fix the failing tests", no_stdin)
+                .unwrap()
+                .as_deref(),
+            Some("fix the failing tests")
+        );
+        let piped: &[u8] = b"explain src/main.rs\n";
+        assert_eq!(
+            read_prompt_arg("-", piped).unwrap().as_deref(),
+            Some("explain src/main.rs")
+        );
+        assert_eq!(read_prompt_arg("-", no_stdin).unwrap(), None);
+        assert_eq!(read_prompt_arg("   ", no_stdin).unwrap(), None);
+    }
+
+    #[test]
+    fn replayed_messages_are_all_part_of_the_conversation() {
+        let mut session = persistence::new_session_state(Path::new("/tmp/ws"), "test-model");
+        session.messages = vec![
+            Message::user("list files"),
+            Message {
+                role: Role::Assistant,
+                content: vec![
+                    ContentBlock::Text {
+                        text: "Listing.".into(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "c1".into(),
+                        name: "list_files".into(),
+                        input: serde_json::json!({"path": "."}),
+                    },
+                ],
+            },
+            Message::tool_results(vec![ContentBlock::tool_result("c1", "a.txt")]),
+        ];
+        let replayed = replay_session_messages(&session, false, 200);
+        assert_eq!(replayed.len(), 4);
+        assert_eq!(crate::tui::state::conversation(&replayed).count(), 4);
+    }
+
+    #[test]
+    fn replayed_replies_never_look_like_a_thinking_placeholder() {
+        let mut session = persistence::new_session_state(Path::new("/tmp/ws"), "test-model");
+        // A reply that went straight to a tool call has an empty text block.
+        session.messages = vec![
+            Message::user("list files"),
+            Message {
+                role: Role::Assistant,
+                content: vec![
+                    ContentBlock::Text {
+                        text: String::new(),
+                    },
+                    ContentBlock::ToolUse {
+                        id: "c1".into(),
+                        name: "list_files".into(),
+                        input: serde_json::json!({"path": "."}),
+                    },
+                ],
+            },
+        ];
+        let replayed = replay_session_messages(&session, false, 200);
+        assert_eq!(replayed.len(), 2);
+        assert!(!replayed.iter().any(is_thinking));
+    }
+
+    #[test]
+    fn exit_screen_does_not_count_a_thinking_placeholder() {
+        let messages = vec![
+            ChatMessage::new(ChatMessageKind::User, "hi".to_string()),
+            ChatMessage::new(ChatMessageKind::Assistant, "Hello.".to_string()),
+            ChatMessage::new(ChatMessageKind::User, "and now?".to_string()),
+            ChatMessage::new(ChatMessageKind::Assistant, String::new()),
+        ];
+        assert_eq!(exchanged_messages(&messages), 3);
+        assert_eq!(exchanged_messages(&messages[..3]), 3);
+    }
+
+    #[test]
+    fn replayed_pins_are_marked() {
+        let mut session = persistence::new_session_state(Path::new("/tmp/ws"), "test-model");
+        session.messages = vec![Message::user("which port?"), Message::assistant("8443")];
+        session.pins = vec![1];
+        let replayed = replay_session_messages(&session, false, 200);
+        let pinned: Vec<bool> = replayed.iter().map(|m| m.pinned).collect();
+        assert_eq!(pinned, [false, true]);
+    }
+
+    #[test]
+    fn replayed_tool_calls_are_cut_by_characters() {
+        let call = |command: String| Message {
+            role: Role::Assistant,
+            content: vec![ContentBlock::ToolUse {
+                id: "c1".into(),
+                name: "bash".into(),
+                input: serde_json::json!({ "command": command }),
+            }],
+        };
+        let mut session = persistence::new_session_state(Path::new("/tmp/ws"), "test-model");
+        // Eight three-byte characters: 24 bytes, but within an 8-character limit.
+        session.messages = vec![call("ファイルを一覧表示".chars().take(8).collect())];
+        let replayed = replay_session_messages(&session, false, 8);
+        assert_eq!(replayed[0].content, "bash(ファイルを一覧表)");
+
+        session.messages = vec![call("ファイルを一覧表示".to_string())];
+        let replayed = replay_session_messages(&session, false, 8);
+        assert_eq!(replayed[0].content, "bash(ファイルを一覧表...)");
+    }
+
+    #[test]
+    fn usage_summary_breaks_out_cache_tokens_and_cost() {
+        let plain = TokenUsage {
+            input: 1_200,
+            output: 300,
+            ..TokenUsage::default()
+        };
+        assert_eq!(
+            usage_summary(&plain, None),
+            "1.5k tokens (1.2k in, 300 out)"
+        );
+
+        let cached = TokenUsage {
+            input: 1_000,
+            output: 500,
+            cache_read: 20_000,
+            cache_write: 4_000,
+        };
+        assert_eq!(
+            usage_summary(&cached, Some(0.0315)),
+            "25.5k tokens (1.0k in, 500 out, 20.0k cache read, 4.0k cache write), about $0.03"
+        );
+    }
+
+    #[test]
+    fn startup_message_lists_connected_and_failed_mcp_servers() {
+        let statuses = vec![
+            McpServerStatus {
+                name: "github".to_string(),
+                result: Ok(3),
+            },
+            McpServerStatus::failed("slow", "failed to connect", "timed out"),
+            McpServerStatus {
+                name: "notes".to_string(),
+                result: Ok(1),
+            },
+            McpServerStatus::failed("broken", "failed to initialize", "bad handshake"),
+        ];
+        let message = build_startup_message(&["AGENTS.md".to_string()], &[], &statuses);
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Context: AGENTS.md | MCP: github (3 tools), notes (1 tool)"
+        );
+        assert_eq!(
+            lines[1],
+            "\u{26a0}\u{fe0f} MCP server 'slow' failed to connect: timed out"
+        );
+        assert_eq!(
+            lines[2],
+            "\u{26a0}\u{fe0f} MCP server 'broken' failed to initialize: bad handshake"
+        );
+    }
+
+    #[test]
+    fn startup_message_without_mcp_servers_is_unchanged() {
+        let message = build_startup_message(&[], &["peekaboo".to_string()], &[]);
+        assert_eq!(message, "No context files found | Skills: peekaboo");
+    }
+
+    #[test]
+    fn first_run_is_detected_once_per_workspace() {
+        let markers = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+
+        assert!(take_first_run(markers.path(), workspace.path()));
+        assert!(!take_first_run(markers.path(), workspace.path()));
+        assert!(!take_first_run(markers.path(), workspace.path()));
+        assert!(take_first_run(markers.path(), other.path()));
+    }
+
+    #[test]
+    fn first_run_marker_dir_is_created() {
+        let tmp = tempfile::tempdir().unwrap();
+        let markers = tmp.path().join("state").join("seen-workspaces");
+        assert!(take_first_run(&markers, tmp.path()));
+        assert!(markers.is_dir());
+        assert!(!take_first_run(&markers, tmp.path()));
+    }
+
+    #[test]
+    fn security_overrides_approvals_json_only_when_chosen() {
+        let (config, provenance) =
+            Config::parse_with_provenance("[approval]\nask = \"on-miss\"\n").unwrap();
+        assert_eq!(chosen_security(&config, &provenance), None);
+
+        let (config, provenance) =
+            Config::parse_with_provenance("[approval]\nsecurity = \"full\"\n").unwrap();
+        assert_eq!(
+            chosen_security(&config, &provenance),
+            Some(SecurityLevel::Full)
+        );
+
+        let mut provenance = Provenance::default();
+        let mut config = Config::default();
+        crate::config::CliOverrides {
+            security: Some("deny".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut provenance, |_| false)
+        .unwrap();
+        assert_eq!(
+            chosen_security(&config, &provenance),
+            Some(SecurityLevel::Deny)
+        );
+    }
+}
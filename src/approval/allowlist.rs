@@ -8,7 +8,10 @@ use chrono::{DateTime, Utc};
 use glob::Pattern;
 use serde::{Deserialize, Serialize};
 
-use super::types::ToolSecurity;
+use super::types::{AskMode, SecurityLevel, ToolSecurity};
+
+/// Minimum pattern similarity for an allowlist entry to count as related.
+const SIMILARITY_THRESHOLD: f64 = 0.5;
 
 /// A single allowlist entry recording a permitted pattern and usage metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +28,14 @@ pub struct AllowlistEntry {
     pub last_used_command: Option<String>,
 }
 
+/// One allowlist entry flattened out of its tool, for listing and editing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowlistRow {
+    pub tool: String,
+    pub pattern: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
 /// Per-tool security configuration paired with its allowlist entries.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolApprovalConfig {
@@ -34,6 +45,30 @@ pub struct ToolApprovalConfig {
     /// Allowlisted patterns for this tool.
     #[serde(default)]
     pub allowlist: Vec<AllowlistEntry>,
+    /// Glob patterns that deny a call outright, whatever the allowlist and
+    /// security level say.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denylist: Vec<String>,
+}
+
+/// Security applied to tools without their own config, plus denylist
+/// patterns that hold for every tool.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalDefaults {
+    /// Flattened so security/ask/ask_fallback appear inline.
+    #[serde(flatten)]
+    pub security: ToolSecurity,
+    /// Patterns denied for every tool: matched against tool names, bash
+    /// executables, and write targets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denylist: Vec<String>,
+}
+
+/// One denylist pattern, with the tool it applies to (None for all tools).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenylistRow {
+    pub tool: Option<String>,
+    pub pattern: String,
 }
 
 /// Top-level approvals file that persists to JSON.
@@ -41,8 +76,9 @@ pub struct ToolApprovalConfig {
 pub struct ApprovalsFile {
     /// Schema version for forward compatibility.
     pub version: u32,
-    /// Default security applied when no tool-specific config exists.
-    pub defaults: ToolSecurity,
+    /// Default security applied when no tool-specific config exists, and
+    /// the global denylist.
+    pub defaults: ApprovalDefaults,
     /// Per-tool overrides keyed by tool name (supports "*" wildcard).
     #[serde(default)]
     pub tools: HashMap<String, ToolApprovalConfig>,
@@ -52,7 +88,7 @@ impl Default for ApprovalsFile {
     fn default() -> Self {
         Self {
             version: 1,
-            defaults: ToolSecurity::default(),
+            defaults: ApprovalDefaults::default(),
             tools: HashMap::new(),
         }
     }
@@ -89,21 +125,28 @@ impl ApprovalsFile {
         if let Some(wildcard) = self.tools.get("*") {
             return &wildcard.security;
         }
-        &self.defaults
+        &self.defaults.security
     }
 
     /// Check if a pattern matches any allowlist entry for the given tool.
     ///
     /// Each stored entry pattern is compiled as a glob and tested against the input.
     pub fn is_allowed(&self, tool_name: &str, pattern: &str) -> bool {
-        let Some(config) = self.tools.get(tool_name) else {
-            return false;
-        };
-        config.allowlist.iter().any(|entry| {
-            Pattern::new(&entry.pattern)
-                .map(|p| p.matches(pattern))
-                .unwrap_or(false)
-        })
+        self.matching_entry(tool_name, pattern).is_some()
+    }
+
+    /// The first allowlist entry for the tool whose glob matches `pattern`.
+    pub fn matching_entry(&self, tool_name: &str, pattern: &str) -> Option<&str> {
+        let config = self.tools.get(tool_name)?;
+        config
+            .allowlist
+            .iter()
+            .find(|entry| {
+                Pattern::new(&entry.pattern)
+                    .map(|p| p.matches(pattern))
+                    .unwrap_or(false)
+            })
+            .map(|entry| entry.pattern.as_str())
     }
 
     /// Add a pattern to a tool's allowlist, skipping if the exact pattern already exists.
@@ -114,8 +157,9 @@ impl ApprovalsFile {
             self.tools
                 .entry(tool_name.to_string())
                 .or_insert_with(|| ToolApprovalConfig {
-                    security: self.defaults.clone(),
+                    security: self.defaults.security.clone(),
                     allowlist: Vec::new(),
+                    denylist: Vec::new(),
                 });
 
         // Skip duplicates.
@@ -130,6 +174,190 @@ impl ApprovalsFile {
             last_used_command: None,
         });
     }
+
+    /// Set a tool's security level, creating its config from the settings it
+    /// currently inherits if it has none. A `full` level also turns asking
+    /// off, so every call of the tool is allowed without a prompt.
+    pub fn set_security(&mut self, tool_name: &str, level: SecurityLevel) {
+        let inherited = self.tool_security(tool_name).clone();
+        let config =
+            self.tools
+                .entry(tool_name.to_string())
+                .or_insert_with(|| ToolApprovalConfig {
+                    security: inherited,
+                    allowlist: Vec::new(),
+                    denylist: Vec::new(),
+                });
+        config.security.security = level;
+        if level == SecurityLevel::Full {
+            config.security.ask = AskMode::Off;
+        }
+    }
+
+    /// Every allowlist entry across all tools, ordered by tool name and then
+    /// by the order the entries were added.
+    pub fn allowlist_rows(&self) -> Vec<AllowlistRow> {
+        let mut tools: Vec<&String> = self.tools.keys().collect();
+        tools.sort();
+        tools
+            .into_iter()
+            .flat_map(|tool| {
+                self.tools[tool]
+                    .allowlist
+                    .iter()
+                    .map(move |entry| AllowlistRow {
+                        tool: tool.clone(),
+                        pattern: entry.pattern.clone(),
+                        last_used_at: entry.last_used_at,
+                    })
+            })
+            .collect()
+    }
+
+    /// Remove an exact pattern from a tool's allowlist. Returns whether an
+    /// entry was removed; the tool's security settings are left in place.
+    pub fn remove_from_allowlist(&mut self, tool_name: &str, pattern: &str) -> bool {
+        let Some(config) = self.tools.get_mut(tool_name) else {
+            return false;
+        };
+        let before = config.allowlist.len();
+        config.allowlist.retain(|entry| entry.pattern != pattern);
+        config.allowlist.len() != before
+    }
+
+    /// The first denylist pattern matching any of `candidates`, checking the
+    /// tool's own denylist before the global one.
+    pub fn denying_entry(&self, tool_name: &str, candidates: &[String]) -> Option<&str> {
+        let own = self
+            .tools
+            .get(tool_name)
+            .map(|config| config.denylist.as_slice());
+        own.unwrap_or_default()
+            .iter()
+            .chain(&self.defaults.denylist)
+            .find(|entry| {
+                Pattern::new(entry)
+                    .map(|p| candidates.iter().any(|c| p.matches(c)))
+                    .unwrap_or(false)
+            })
+            .map(String::as_str)
+    }
+
+    /// Add a denylist pattern for one tool, or for every tool when `tool_name`
+    /// is None. Returns false if the exact pattern was already there.
+    pub fn add_to_denylist(&mut self, tool_name: Option<&str>, pattern: &str) -> bool {
+        let list = match tool_name {
+            Some(tool) => {
+                let inherited = self.tool_security(tool).clone();
+                &mut self
+                    .tools
+                    .entry(tool.to_string())
+                    .or_insert_with(|| ToolApprovalConfig {
+                        security: inherited,
+                        allowlist: Vec::new(),
+                        denylist: Vec::new(),
+                    })
+                    .denylist
+            }
+            None => &mut self.defaults.denylist,
+        };
+        if list.iter().any(|p| p == pattern) {
+            return false;
+        }
+        list.push(pattern.to_string());
+        true
+    }
+
+    /// Remove an exact denylist pattern. Returns whether it was there.
+    pub fn remove_from_denylist(&mut self, tool_name: Option<&str>, pattern: &str) -> bool {
+        let list = match tool_name {
+            Some(tool) => match self.tools.get_mut(tool) {
+                Some(config) => &mut config.denylist,
+                None => return false,
+            },
+            None => &mut self.defaults.denylist,
+        };
+        let before = list.len();
+        list.retain(|p| p != pattern);
+        list.len() != before
+    }
+
+    /// Every denylist pattern: the global ones first, then by tool name.
+    pub fn denylist_rows(&self) -> Vec<DenylistRow> {
+        let mut tools: Vec<&String> = self.tools.keys().collect();
+        tools.sort();
+        let global = self.defaults.denylist.iter().map(|pattern| DenylistRow {
+            tool: None,
+            pattern: pattern.clone(),
+        });
+        let per_tool = tools.into_iter().flat_map(|tool| {
+            self.tools[tool]
+                .denylist
+                .iter()
+                .map(move |pattern| DenylistRow {
+                    tool: Some(tool.clone()),
+                    pattern: pattern.clone(),
+                })
+        });
+        global.chain(per_tool).collect()
+    }
+
+    /// Allowlist patterns for a tool that look like `pattern` without matching it,
+    /// most similar first, at most `limit` of them.
+    pub fn similar_patterns(&self, tool_name: &str, pattern: &str, limit: usize) -> Vec<String> {
+        let Some(config) = self.tools.get(tool_name) else {
+            return Vec::new();
+        };
+        let mut scored: Vec<(f64, &str)> = config
+            .allowlist
+            .iter()
+            .filter(|entry| entry.pattern != pattern)
+            .map(|entry| {
+                (
+                    pattern_similarity(&entry.pattern, pattern),
+                    entry.pattern.as_str(),
+                )
+            })
+            .filter(|(score, _)| *score >= SIMILARITY_THRESHOLD)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        scored.dedup_by(|a, b| a.1 == b.1);
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, p)| p.to_string())
+            .collect()
+    }
+}
+
+/// Similarity of two patterns in [0, 1], from the normalized edit distance
+/// of their last path components. Comparing only the executable name keeps a
+/// shared `/usr/bin/` prefix from making everything look alike, and puts an
+/// unresolved `git` right next to `/usr/bin/git`.
+pub fn pattern_similarity(a: &str, b: &str) -> f64 {
+    let basename = |s: &str| s.rsplit('/').next().unwrap_or(s).to_string();
+    edit_similarity(&basename(a), &basename(b))
+}
+
+/// 1 minus the Levenshtein distance over the longer length, in chars.
+fn edit_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    1.0 - prev[b.len()] as f64 / longest as f64
 }
 
 #[cfg(test)]
@@ -137,12 +365,48 @@ mod tests {
     use super::*;
     use crate::approval::types::{AskMode, SecurityLevel};
 
+    #[test]
+    fn allowlist_rows_are_grouped_by_tool() {
+        let mut file = ApprovalsFile::default();
+        file.add_to_allowlist("mcp_fetch", "mcp_fetch");
+        file.add_to_allowlist("bash", "/usr/bin/git");
+        file.add_to_allowlist("bash", "/usr/bin/cargo");
+
+        let rows: Vec<(String, String)> = file
+            .allowlist_rows()
+            .into_iter()
+            .map(|r| (r.tool, r.pattern))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("bash".to_string(), "/usr/bin/git".to_string()),
+                ("bash".to_string(), "/usr/bin/cargo".to_string()),
+                ("mcp_fetch".to_string(), "mcp_fetch".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_from_allowlist_only_removes_exact_pattern() {
+        let mut file = ApprovalsFile::default();
+        file.add_to_allowlist("bash", "/usr/bin/git");
+        file.add_to_allowlist("bash", "/usr/bin/*");
+
+        assert!(file.remove_from_allowlist("bash", "/usr/bin/*"));
+        assert!(!file.remove_from_allowlist("bash", "/usr/bin/*"));
+        assert!(!file.remove_from_allowlist("read_file", "/usr/bin/git"));
+        assert!(file.is_allowed("bash", "/usr/bin/git"));
+        assert!(!file.is_allowed("bash", "/usr/bin/ls"));
+    }
+
     #[test]
     fn default_approvals_file() {
         let file = ApprovalsFile::default();
         assert_eq!(file.version, 1);
-        assert_eq!(file.defaults.security, SecurityLevel::Allowlist);
-        assert_eq!(file.defaults.ask, AskMode::OnMiss);
+        assert_eq!(file.defaults.security.security, SecurityLevel::Allowlist);
+        assert_eq!(file.defaults.security.ask, AskMode::OnMiss);
+        assert!(file.defaults.denylist.is_empty());
         assert!(file.tools.is_empty());
     }
 
@@ -165,6 +429,7 @@ mod tests {
                     ..ToolSecurity::default()
                 },
                 allowlist: Vec::new(),
+                denylist: Vec::new(),
             },
         );
         let sec = file.tool_security("bash");
@@ -184,6 +449,7 @@ mod tests {
                     ..ToolSecurity::default()
                 },
                 allowlist: Vec::new(),
+                denylist: Vec::new(),
             },
         );
         // Unknown tool falls through to wildcard.
@@ -218,6 +484,42 @@ mod tests {
         assert_eq!(config.allowlist.len(), 1);
     }
 
+    #[test]
+    fn pattern_similarity_scores() {
+        assert_eq!(pattern_similarity("/usr/bin/git", "/usr/bin/git"), 1.0);
+        assert_eq!(pattern_similarity("git", "/usr/bin/git"), 1.0);
+        assert!(pattern_similarity("/usr/bin/gt", "/usr/bin/git") > SIMILARITY_THRESHOLD);
+        assert!(pattern_similarity("/usr/bin/cargo", "/usr/bin/rm") < SIMILARITY_THRESHOLD);
+        assert_eq!(pattern_similarity("", ""), 1.0);
+    }
+
+    #[test]
+    fn similar_patterns_ranks_and_limits() {
+        let mut file = ApprovalsFile::default();
+        for p in [
+            "/usr/bin/git",
+            "/usr/local/bin/git",
+            "/usr/bin/gh",
+            "/usr/bin/python3",
+            "/usr/bin/gt",
+        ] {
+            file.add_to_allowlist("bash", p);
+        }
+
+        let similar = file.similar_patterns("bash", "git", 3);
+        assert_eq!(similar.len(), 3);
+        assert_eq!(&similar[..2], &["/usr/bin/git", "/usr/local/bin/git"]);
+        assert!(!similar.contains(&"/usr/bin/python3".to_string()));
+
+        // An exact match isn't "similar", and unknown tools have none.
+        assert!(
+            !file
+                .similar_patterns("bash", "/usr/bin/git", 3)
+                .contains(&"/usr/bin/git".to_string())
+        );
+        assert!(file.similar_patterns("read_file", "git", 3).is_empty());
+    }
+
     #[test]
     fn save_and_load_roundtrip() {
         let dir = tempfile::tempdir().unwrap();
@@ -238,6 +540,62 @@ mod tests {
         assert!(!loaded.is_allowed("editor", "/usr/bin/emacs"));
     }
 
+    #[test]
+    fn denylist_roundtrips_and_checks_tool_then_global() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+
+        let mut original = ApprovalsFile::default();
+        assert!(original.add_to_denylist(Some("bash"), "*/sudo"));
+        assert!(!original.add_to_denylist(Some("bash"), "*/sudo"));
+        assert!(original.add_to_denylist(None, "mcp_deploy"));
+        original.save(&path).unwrap();
+
+        let loaded = ApprovalsFile::load(&path).unwrap();
+        assert_eq!(loaded.defaults.denylist, vec!["mcp_deploy".to_string()]);
+        assert_eq!(loaded.tools["bash"].denylist, vec!["*/sudo".to_string()]);
+        assert_eq!(
+            loaded.denying_entry("bash", &["/usr/bin/sudo".to_string()]),
+            Some("*/sudo")
+        );
+        assert_eq!(
+            loaded.denying_entry("mcp_deploy", &["mcp_deploy".to_string()]),
+            Some("mcp_deploy")
+        );
+        assert_eq!(
+            loaded.denying_entry("read_file", &["/usr/bin/sudo".to_string()]),
+            None
+        );
+        assert_eq!(
+            loaded.denylist_rows(),
+            vec![
+                DenylistRow {
+                    tool: None,
+                    pattern: "mcp_deploy".to_string()
+                },
+                DenylistRow {
+                    tool: Some("bash".to_string()),
+                    pattern: "*/sudo".to_string()
+                },
+            ]
+        );
+
+        let mut loaded = loaded;
+        assert!(loaded.remove_from_denylist(Some("bash"), "*/sudo"));
+        assert!(!loaded.remove_from_denylist(Some("read_file"), "*/sudo"));
+        assert!(loaded.remove_from_denylist(None, "mcp_deploy"));
+        assert!(loaded.denylist_rows().is_empty());
+
+        // Files from before denylists load, and empty ones aren't written.
+        let old: ApprovalsFile = serde_json::from_str(
+            r#"{"version":1,"defaults":{"security":"allowlist","ask":"on-miss"},
+                "tools":{"bash":{"security":"allowlist","ask":"on-miss","allowlist":[]}}}"#,
+        )
+        .unwrap();
+        assert!(old.denylist_rows().is_empty());
+        assert!(!serde_json::to_string(&old).unwrap().contains("denylist"));
+    }
+
     #[test]
     fn load_missing_file_returns_default() {
         let dir = tempfile::tempdir().unwrap();
@@ -12,6 +12,38 @@ pub struct CommandSegment {
     pub args: Vec<String>,
     /// Whether this segment only processes stdin (i.e. is a piped-to command).
     pub stdin_only: bool,
+    /// The operator between this segment and the previous one (None for the first).
+    pub preceded_by: Option<ShellOperator>,
+    /// Whether this segment's command list was sent to the background with `&`.
+    pub background: bool,
+}
+
+/// A shell control operator joining two segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellOperator {
+    /// `&&` — run the next command only if this one succeeded.
+    And,
+    /// `||` — run the next command only if this one failed.
+    Or,
+    /// `;` — run the next command regardless.
+    Sequence,
+    /// `&` — run this command in the background and continue immediately.
+    Background,
+    /// `|` — feed this command's output to the next.
+    Pipe,
+}
+
+impl ShellOperator {
+    /// The operator as written in the shell.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShellOperator::And => "&&",
+            ShellOperator::Or => "||",
+            ShellOperator::Sequence => ";",
+            ShellOperator::Background => "&",
+            ShellOperator::Pipe => "|",
+        }
+    }
 }
 
 /// The result of analyzing a shell command string.
@@ -23,8 +55,31 @@ pub struct AnalysisResult {
     pub resolved_path: Option<PathBuf>,
     /// Whether all segments use safe stdin-only binaries.
     pub safe: bool,
+    /// The command exceeded the length limit and was not parsed.
+    pub too_long: bool,
+    /// The command ends inside a quote or with a dangling backslash, so the
+    /// parse may not match what the shell would run.
+    pub malformed: bool,
+    /// Some part of the command runs detached in the background (`&`).
+    pub backgrounds: bool,
+}
+
+impl AnalysisResult {
+    /// Why the command couldn't be fully analyzed, if it couldn't.
+    pub fn limitation(&self) -> Option<&'static str> {
+        if self.too_long {
+            Some("too long to analyze")
+        } else if self.malformed {
+            Some("unterminated quote or trailing backslash")
+        } else {
+            None
+        }
+    }
 }
 
+/// Default cap on the length (in bytes) of a command that gets parsed.
+pub const DEFAULT_MAX_COMMAND_LEN: usize = 16 * 1024;
+
 /// Binaries considered safe because they only read/transform stdin or produce output.
 pub const SAFE_BINS: &[&str] = &[
     "awk", "base64", "cat", "column", "cut", "diff", "echo", "env", "expand", "expr", "false",
@@ -33,13 +88,111 @@ pub const SAFE_BINS: &[&str] = &[
     "wc", "xargs", "yes",
 ];
 
-/// Check if a binary name (possibly an absolute path) is in the safe list.
-pub fn is_safe_bin(name: &str) -> bool {
-    let basename = Path::new(name)
+/// Commands that run the command given in their arguments.
+const WRAPPERS: &[&str] = &["env", "command", "exec", "nohup", "nice", "time"];
+
+/// Wrapper options that take a value in the next word.
+const WRAPPER_VALUE_OPTIONS: &[&str] = &["-u", "--unset", "-C", "--chdir", "-n", "--adjustment"];
+
+/// Shells whose `-c` script is itself a command.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh"];
+
+/// How many wrappers deep `invoked_executables` looks, as in
+/// `sh -c "env bash -c 'sudo …'"`.
+const MAX_WRAPPER_DEPTH: usize = 4;
+
+/// The last component of an executable path.
+fn basename(name: &str) -> &str {
+    Path::new(name)
         .file_name()
         .and_then(|n| n.to_str())
-        .unwrap_or(name);
-    SAFE_BINS.contains(&basename)
+        .unwrap_or(name)
+}
+
+/// Check if a binary name (possibly an absolute path) is in the safe list.
+pub fn is_safe_bin(name: &str) -> bool {
+    SAFE_BINS.contains(&basename(name))
+}
+
+/// Every executable `command` may run, as written and by basename: each
+/// segment's, and those inside common wrappers (`env sudo …`,
+/// `sh -c "sudo …"`). Parsed without a length limit, so padding a command
+/// can't hide what it runs.
+pub fn invoked_executables(command: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_executables(command, 0, &mut found);
+    found
+}
+
+fn collect_executables(command: &str, depth: usize, found: &mut Vec<String>) {
+    for segment in parse_pipeline(command) {
+        let words: Vec<&str> = std::iter::once(segment.executable.as_str())
+            .chain(segment.args.iter().map(String::as_str))
+            .collect();
+        collect_invocation(&words, depth, found);
+    }
+}
+
+fn collect_invocation(words: &[&str], depth: usize, found: &mut Vec<String>) {
+    // Leading `NAME=value` assignments aren't the command.
+    let start = words
+        .iter()
+        .position(|w| !is_assignment(w))
+        .unwrap_or(words.len());
+    let Some((&executable, args)) = words[start..].split_first() else {
+        return;
+    };
+    let name = basename(executable);
+    found.push(executable.to_string());
+    if name != executable {
+        found.push(name.to_string());
+    }
+    if depth >= MAX_WRAPPER_DEPTH {
+        return;
+    }
+    if SHELLS.contains(&name) {
+        // The script follows -c, alone or combined as in -lc.
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with('-') && !arg.starts_with("--") && arg.contains('c') {
+                if let Some(script) = args.next() {
+                    collect_executables(script, depth + 1, found);
+                }
+                return;
+            }
+        }
+    } else if WRAPPERS.contains(&name) {
+        let mut i = 0;
+        while let Some(&arg) = args.get(i) {
+            match arg {
+                "--" => {
+                    i += 1;
+                    break;
+                }
+                // env -S splits its value into a command line.
+                "-S" | "--split-string" => {
+                    if let Some(script) = args.get(i + 1) {
+                        collect_executables(script, depth + 1, found);
+                    }
+                    return;
+                }
+                _ if WRAPPER_VALUE_OPTIONS.contains(&arg) => i += 2,
+                _ if arg.starts_with('-') => i += 1,
+                _ => break,
+            }
+        }
+        collect_invocation(args.get(i..).unwrap_or_default(), depth + 1, found);
+    }
+}
+
+/// Whether `word` is a shell variable assignment like `FOO=bar`.
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
 }
 
 /// Resolve an executable name to an absolute path by searching PATH.
@@ -119,23 +272,60 @@ fn shell_words(input: &str) -> Vec<String> {
     words
 }
 
+/// Whether the input ends inside a single or double quote, or with a
+/// backslash that has nothing to escape. Uses the same quoting rules as
+/// `shell_words`.
+fn has_unterminated_quoting(input: &str) -> bool {
+    let mut chars = input.chars();
+    let mut in_single_quote = false;
+    let mut in_double_quote = false;
+
+    while let Some(c) = chars.next() {
+        if in_single_quote {
+            if c == '\'' {
+                in_single_quote = false;
+            }
+        } else if c == '\\' {
+            if chars.next().is_none() {
+                return true;
+            }
+        } else if in_double_quote {
+            if c == '"' {
+                in_double_quote = false;
+            }
+        } else if c == '\'' {
+            in_single_quote = true;
+        } else if c == '"' {
+            in_double_quote = true;
+        }
+    }
+    in_single_quote || in_double_quote
+}
+
 /// Parse a shell command string into pipeline segments.
 ///
-/// Splits on chain operators (&&, ||, ;) to get independent commands,
-/// then splits each on | to get piped segments.
+/// Splits on chain operators (&&, ||, ;, &) to get independent commands,
+/// then splits each on | to get piped segments. Each segment records the
+/// operator that preceded it and whether it runs in the background.
 pub fn parse_pipeline(command: &str) -> Vec<CommandSegment> {
     let mut segments = Vec::new();
 
-    // Split on chain operators: &&, ||, ;
+    // Split on chain operators: &&, ||, ;, &
     // We need to split on the string literals, being careful about ordering
     // (check && and || before single & or |).
     let chains = split_on_chain_operators(command);
+    let mut prev_op: Option<ShellOperator> = None;
 
-    for chain in &chains {
+    for (chain, terminator) in &chains {
         let chain = chain.trim();
+        let chain_op = prev_op;
+        if terminator.is_some() {
+            prev_op = *terminator;
+        }
         if chain.is_empty() {
             continue;
         }
+        let background = *terminator == Some(ShellOperator::Background);
 
         // Split each chain on pipe |.
         let pipe_parts = split_on_pipe(chain);
@@ -153,6 +343,12 @@ pub fn parse_pipeline(command: &str) -> Vec<CommandSegment> {
                 executable: words[0].clone(),
                 args: words[1..].to_vec(),
                 stdin_only: i > 0,
+                preceded_by: if i > 0 {
+                    Some(ShellOperator::Pipe)
+                } else {
+                    chain_op
+                },
+                background,
             });
         }
     }
@@ -160,15 +356,20 @@ pub fn parse_pipeline(command: &str) -> Vec<CommandSegment> {
     segments
 }
 
-/// Split a command string on the chain operators &&, ||, and ;.
-fn split_on_chain_operators(input: &str) -> Vec<String> {
+/// Split a command string on the chain operators &&, ||, ;, and a lone &.
+/// Each part is paired with the operator that ended it (None for the last).
+/// `&` in redirections such as `2>&1` or `&>` is not an operator.
+fn split_on_chain_operators(input: &str) -> Vec<(String, Option<ShellOperator>)> {
     let mut parts = Vec::new();
     let mut current = String::new();
     let mut chars = input.chars().peekable();
     let mut in_single_quote = false;
     let mut in_double_quote = false;
+    let mut prev: Option<char> = None;
 
     while let Some(c) = chars.next() {
+        let before = prev;
+        prev = Some(c);
         if in_single_quote {
             current.push(c);
             if c == '\'' {
@@ -193,25 +394,33 @@ fn split_on_chain_operators(input: &str) -> Vec<String> {
         } else if c == '&' {
             if chars.peek() == Some(&'&') {
                 chars.next();
-                parts.push(std::mem::take(&mut current));
-            } else {
+                prev = None;
+                parts.push((std::mem::take(&mut current), Some(ShellOperator::And)));
+            } else if matches!(before, Some('>' | '<')) || chars.peek() == Some(&'>') {
+                // Redirection (`2>&1`, `<&3`, `&>file`), not backgrounding.
                 current.push(c);
+            } else {
+                parts.push((
+                    std::mem::take(&mut current),
+                    Some(ShellOperator::Background),
+                ));
             }
         } else if c == '|' {
             if chars.peek() == Some(&'|') {
                 chars.next();
-                parts.push(std::mem::take(&mut current));
+                prev = None;
+                parts.push((std::mem::take(&mut current), Some(ShellOperator::Or)));
             } else {
                 current.push(c);
             }
         } else if c == ';' {
-            parts.push(std::mem::take(&mut current));
+            parts.push((std::mem::take(&mut current), Some(ShellOperator::Sequence)));
         } else {
             current.push(c);
         }
     }
     if !current.is_empty() {
-        parts.push(current);
+        parts.push((current, None));
     }
     parts
 }
@@ -261,18 +470,47 @@ fn split_on_pipe(input: &str) -> Vec<String> {
 
 /// Analyze a shell command string: parse it, resolve the first executable, and check safety.
 pub fn analyze_command(command: &str) -> AnalysisResult {
+    analyze_command_bounded(command, DEFAULT_MAX_COMMAND_LEN)
+}
+
+/// Like `analyze_command`, but commands longer than `max_len` bytes are not
+/// parsed at all: they come back unsafe with no segments, which bounds the
+/// work spent on huge generated scripts and forces a human to look at them.
+pub fn analyze_command_bounded(command: &str, max_len: usize) -> AnalysisResult {
+    if command.len() > max_len {
+        return AnalysisResult {
+            segments: Vec::new(),
+            resolved_path: None,
+            safe: false,
+            too_long: true,
+            malformed: false,
+            backgrounds: false,
+        };
+    }
+
+    // Malformed quoting is still parsed best-effort for display, but never
+    // treated as safe: the shell may see something quite different.
+    let malformed = has_unterminated_quoting(command);
     let segments = parse_pipeline(command);
 
     let resolved_path = segments
         .first()
         .and_then(|seg| resolve_executable(&seg.executable));
 
-    let safe = !segments.is_empty() && segments.iter().all(|seg| is_safe_bin(&seg.executable));
+    // Backgrounded commands outlive the approval, so they always need a human.
+    let backgrounds = segments.iter().any(|seg| seg.background);
+    let safe = !malformed
+        && !backgrounds
+        && !segments.is_empty()
+        && segments.iter().all(|seg| is_safe_bin(&seg.executable));
 
     AnalysisResult {
         segments,
         resolved_path,
         safe,
+        too_long: false,
+        malformed,
+        backgrounds,
     }
 }
 
@@ -302,6 +540,27 @@ mod tests {
         assert!(!is_safe_bin("python"));
     }
 
+    #[test]
+    fn invoked_executables_look_through_wrappers_and_paths() {
+        let found = |command: &str| invoked_executables(command);
+        assert!(found("/usr/bin/sudo rm -rf /").contains(&"sudo".to_string()));
+        assert!(found("env FOO=1 -u HOME sudo ls").contains(&"sudo".to_string()));
+        assert!(found("FOO=1 sudo ls").contains(&"sudo".to_string()));
+        assert!(found("sh -c \"echo hi; sudo ls\"").contains(&"sudo".to_string()));
+        assert!(found("bash -lc 'env -- /bin/sudo ls'").contains(&"sudo".to_string()));
+        assert!(found("nohup nice -n 5 sudo ls").contains(&"sudo".to_string()));
+        assert!(found("env -S 'sudo ls'").contains(&"sudo".to_string()));
+        assert_eq!(found("ls -la"), ["ls"]);
+        assert_eq!(found("env"), ["env"]);
+    }
+
+    #[test]
+    fn invoked_executables_ignore_the_length_limit() {
+        let padded = format!("sudo rm -rf / # {}", "x".repeat(DEFAULT_MAX_COMMAND_LEN));
+        assert!(analyze_command(&padded).segments.is_empty());
+        assert_eq!(invoked_executables(&padded), ["sudo"]);
+    }
+
     #[test]
     fn is_safe_bin_handles_absolute_paths() {
         assert!(is_safe_bin("/usr/bin/cat"));
@@ -387,9 +646,14 @@ mod tests {
                 executable: "cat".to_string(),
                 args: vec![],
                 stdin_only: false,
+                preceded_by: None,
+                background: false,
             }],
             resolved_path: Some(PathBuf::from("/usr/bin/cat")),
             safe: true,
+            too_long: false,
+            malformed: false,
+            backgrounds: false,
         };
         assert_eq!(allowlist_pattern(&result), Some("/usr/bin/cat".to_string()));
     }
@@ -401,10 +665,179 @@ mod tests {
                 executable: "my_tool".to_string(),
                 args: vec![],
                 stdin_only: false,
+                preceded_by: None,
+                background: false,
             }],
             resolved_path: None,
             safe: false,
+            too_long: false,
+            malformed: false,
+            backgrounds: false,
         };
         assert_eq!(allowlist_pattern(&result), Some("my_tool".to_string()));
     }
+
+    #[test]
+    fn overlong_command_is_unsafe_and_unparsed() {
+        let command = format!("cat {}", "x".repeat(100));
+        let result = analyze_command_bounded(&command, 50);
+        assert!(result.too_long);
+        assert!(!result.safe);
+        assert!(result.segments.is_empty());
+        assert_eq!(allowlist_pattern(&result), None);
+
+        // At the limit it is still analyzed normally.
+        let result = analyze_command_bounded(&command, command.len());
+        assert!(!result.too_long);
+        assert!(result.safe);
+    }
+
+    #[test]
+    fn deeply_nested_quotes_terminate() {
+        let command = format!("echo {}", "\"'".repeat(20_000));
+        let segments = parse_pipeline(&command);
+        assert_eq!(segments[0].executable, "echo");
+    }
+
+    #[test]
+    fn unterminated_quotes_terminate() {
+        for command in [
+            "echo \"abc",
+            "echo 'abc",
+            "echo \"a\\",
+            "echo \\",
+            "'",
+            "\"",
+        ] {
+            let _ = parse_pipeline(command);
+        }
+        let segments = parse_pipeline("echo 'never closed | rm -rf /");
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].executable, "echo");
+    }
+
+    fn operators(command: &str) -> Vec<Option<ShellOperator>> {
+        parse_pipeline(command)
+            .iter()
+            .map(|seg| seg.preceded_by)
+            .collect()
+    }
+
+    #[test]
+    fn records_and_or_sequence_operators() {
+        assert_eq!(operators("a && b"), vec![None, Some(ShellOperator::And)]);
+        assert_eq!(operators("a || b"), vec![None, Some(ShellOperator::Or)]);
+        assert_eq!(operators("a; b"), vec![None, Some(ShellOperator::Sequence)]);
+        assert_eq!(
+            operators("a | b && c"),
+            vec![None, Some(ShellOperator::Pipe), Some(ShellOperator::And)]
+        );
+    }
+
+    #[test]
+    fn lone_ampersand_backgrounds_preceding_command() {
+        let segments = parse_pipeline("sleep 10 & echo done");
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].executable, "sleep");
+        assert!(segments[0].background);
+        assert_eq!(segments[1].preceded_by, Some(ShellOperator::Background));
+        assert!(!segments[1].background);
+
+        // Trailing & backgrounds the whole pipeline.
+        let segments = parse_pipeline("cat log | grep x &");
+        assert_eq!(segments.len(), 2);
+        assert!(segments.iter().all(|s| s.background));
+    }
+
+    #[test]
+    fn background_forces_unsafe() {
+        let result = analyze_command("cat file &");
+        assert!(result.backgrounds);
+        assert!(!result.safe);
+        assert!(analyze_command("cat file && echo ok").safe);
+    }
+
+    #[test]
+    fn redirection_ampersands_are_not_operators() {
+        let segments = parse_pipeline("grep x file 2>&1 | wc -l");
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].args, vec!["x", "file", "2>&1"]);
+        assert!(!segments[0].background);
+
+        let segments = parse_pipeline("make &> build.log");
+        assert_eq!(segments.len(), 1);
+        assert!(!segments[0].background);
+    }
+
+    #[test]
+    fn unterminated_single_quote_is_malformed() {
+        let result = analyze_command("echo 'oops | rm -rf /");
+        assert!(result.malformed);
+        assert!(!result.safe);
+        assert!(result.limitation().unwrap().contains("unterminated"));
+    }
+
+    #[test]
+    fn unterminated_double_quote_is_malformed() {
+        let result = analyze_command("cat \"oops");
+        assert!(result.malformed);
+        assert!(!result.safe);
+        // An escaped quote inside double quotes doesn't close them.
+        assert!(analyze_command(r#"echo "a\""#).malformed);
+    }
+
+    #[test]
+    fn trailing_backslash_is_malformed() {
+        assert!(analyze_command("echo hi \\").malformed);
+        assert!(analyze_command("echo \"hi \\").malformed);
+        // A backslash inside single quotes is literal.
+        assert!(!analyze_command(r"echo 'a\'").malformed);
+    }
+
+    #[test]
+    fn balanced_quotes_are_not_malformed() {
+        let result = analyze_command(r#"echo "it's" 'say "hi"' \"x\""#);
+        assert!(!result.malformed);
+        assert!(result.safe);
+        assert!(result.limitation().is_none());
+    }
+
+    mod prop {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn parse_pipeline_never_panics(command in "\\PC{0,200}") {
+                let _ = parse_pipeline(&command);
+            }
+
+            #[test]
+            fn parse_pipeline_handles_shell_metacharacters(
+                command in "[a-z \"'\\\\|&;]{0,200}"
+            ) {
+                let _ = analyze_command(&command);
+            }
+
+            #[test]
+            fn simple_command_round_trips(
+                words in prop::collection::vec("[A-Za-z0-9_./=-]{1,12}", 1..8)
+            ) {
+                let segments = parse_pipeline(&words.join(" "));
+                prop_assert_eq!(segments.len(), 1);
+                prop_assert_eq!(&segments[0].executable, &words[0]);
+                prop_assert_eq!(&segments[0].args, &words[1..].to_vec());
+            }
+
+            #[test]
+            fn single_quoted_args_round_trip(
+                args in prop::collection::vec("[A-Za-z0-9 |&;\"]{1,12}", 1..6)
+            ) {
+                let quoted: Vec<String> = args.iter().map(|a| format!("'{}'", a)).collect();
+                let segments = parse_pipeline(&format!("echo {}", quoted.join(" ")));
+                prop_assert_eq!(segments.len(), 1);
+                prop_assert_eq!(&segments[0].args, &args);
+            }
+        }
+    }
 }
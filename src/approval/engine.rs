@@ -1,35 +1,81 @@
 // ABOUTME: Approval engine — orchestrates policy, allowlist, and command analysis.
 // ABOUTME: Evaluates tool calls against security config and persists allow-always decisions.
 
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 use serde_json::Value;
 
+use crate::redact::{redact_params, redact_str};
+use crate::tools::diff::write_preview;
+use crate::tools::summary::{describe_tool_call, vcs_write_warning};
+
 use super::{
-    allowlist::ApprovalsFile,
-    analysis::{allowlist_pattern, analyze_command},
+    allowlist::{AllowlistRow, ApprovalsFile},
+    analysis::{
+        DEFAULT_MAX_COMMAND_LEN, allowlist_pattern, analyze_command_bounded, invoked_executables,
+        resolve_executable,
+    },
+    paths::{PATH_SCOPED_TOOLS, PathScope, path_scopes, resolve_target},
     policy::evaluate_approval,
-    types::{ApprovalDecision, ApprovalOutcome},
+    types::{ApprovalDecision, ApprovalOutcome, AskMode, SecurityLevel, ToolSecurity},
 };
 
+/// Most related allowlist entries shown alongside an approval prompt.
+const MAX_SIMILAR_APPROVALS: usize = 3;
+
 /// Information about a tool call to be evaluated by the engine.
 pub struct ToolCallInfo {
     pub tool_name: String,
     pub params: Value,
 }
 
+/// Why the engine let a tool call through without asking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApprovalReason {
+    /// Every command in the bash call runs a known-safe binary.
+    SafeBins,
+    /// The call matched this allowlist entry.
+    AllowlistPattern(String),
+    /// The tool runs at `full` security.
+    FullSecurity,
+    /// The user allowed this pattern earlier in the session (`ask = "once"`).
+    Session(String),
+    /// Approvals are bypassed for the whole session.
+    Bypass,
+}
+
+impl fmt::Display for ApprovalReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalReason::SafeBins => write!(f, "safe-bin"),
+            ApprovalReason::AllowlistPattern(pattern) => write!(f, "allowlist:{}", pattern),
+            ApprovalReason::FullSecurity => write!(f, "security:full"),
+            ApprovalReason::Session(pattern) => write!(f, "session:{}", pattern),
+            ApprovalReason::Bypass => write!(f, "bypass"),
+        }
+    }
+}
+
 /// The outcome of the engine's evaluation of a tool call.
 #[derive(Debug, PartialEq, Eq)]
 pub enum EngineOutcome {
     /// The tool call is allowed to proceed.
-    Allowed,
+    Allowed { reason: ApprovalReason },
     /// The tool call is denied.
     Denied { reason: String },
     /// The tool call requires user approval before proceeding.
     NeedsApproval {
         description: String,
         pattern: Option<String>,
+        /// Existing allowlist patterns close to `pattern`, most similar first.
+        similar: Vec<String>,
+        /// Narrower "always allow" choices for a file write, narrowest
+        /// first; empty for other tools.
+        scopes: Vec<PathScope>,
     },
 }
 
@@ -39,6 +85,17 @@ pub struct ApprovalEngine {
     approvals: Mutex<ApprovalsFile>,
     approvals_path: PathBuf,
     bypass_approvals: bool,
+    max_command_length: usize,
+    redact: bool,
+    /// (tool, pattern) pairs the user allowed this session, for `ask = "once"`.
+    session_approvals: Mutex<HashSet<(String, String)>>,
+    /// Tools held to allowlist security for this session, whatever approvals.json says.
+    restricted_tools: HashSet<String>,
+    /// Canonical workspace root that write paths are scoped under.
+    workspace: Option<PathBuf>,
+    /// Security level for tools approvals.json doesn't configure, overriding
+    /// its defaults for this session (`[approval] security`, onboarding).
+    default_security: Mutex<Option<SecurityLevel>>,
 }
 
 impl ApprovalEngine {
@@ -57,6 +114,12 @@ impl ApprovalEngine {
             approvals: Mutex::new(approvals),
             approvals_path,
             bypass_approvals,
+            max_command_length: DEFAULT_MAX_COMMAND_LEN,
+            redact: true,
+            session_approvals: Mutex::new(HashSet::new()),
+            restricted_tools: HashSet::new(),
+            workspace: None,
+            default_security: Mutex::new(None),
         })
     }
 
@@ -66,6 +129,99 @@ impl ApprovalEngine {
             approvals: Mutex::new(approvals),
             approvals_path: path,
             bypass_approvals: false,
+            max_command_length: DEFAULT_MAX_COMMAND_LEN,
+            redact: true,
+            session_approvals: Mutex::new(HashSet::new()),
+            restricted_tools: HashSet::new(),
+            workspace: None,
+            default_security: Mutex::new(None),
+        }
+    }
+
+    /// Set the bash command length beyond which commands skip analysis and
+    /// always need approval.
+    pub fn with_max_command_length(mut self, max_command_length: usize) -> Self {
+        self.max_command_length = max_command_length;
+        self
+    }
+
+    /// Set whether secret-looking parameters are masked in descriptions.
+    pub fn with_redaction(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Hold these tools to allowlist security for this session: `full` drops
+    /// to `allowlist`, and an `off` ask mode asks on a miss instead of
+    /// denying. Nothing is written to approvals.json.
+    pub fn with_restricted_tools(mut self, tools: &[&str]) -> Self {
+        self.restricted_tools = tools.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    /// Scope file write approvals to directories of this workspace: writes
+    /// can be allowed by path, and prompts offer directory choices.
+    pub fn with_workspace(mut self, workspace: &Path) -> Self {
+        self.workspace = Some(
+            workspace
+                .canonicalize()
+                .unwrap_or_else(|_| workspace.to_path_buf()),
+        );
+        self
+    }
+
+    /// Use `level` for tools approvals.json has no entry for (exact or "*").
+    pub fn with_default_security(self, level: SecurityLevel) -> Self {
+        self.set_default_security(level);
+        self
+    }
+
+    /// Change the level for unconfigured tools for the rest of the session.
+    /// Nothing is written to approvals.json.
+    pub fn set_default_security(&self, level: SecurityLevel) {
+        *self
+            .default_security
+            .lock()
+            .expect("default security lock poisoned") = Some(level);
+    }
+
+    /// A tool's security from approvals.json, with the session default
+    /// standing in for the file's defaults, then the session restriction.
+    fn configured(&self, approvals: &ApprovalsFile, tool_name: &str) -> ToolSecurity {
+        let mut tool_sec = approvals.tool_security(tool_name).clone();
+        let unconfigured =
+            !approvals.tools.contains_key(tool_name) && !approvals.tools.contains_key("*");
+        if unconfigured
+            && let Some(level) = *self
+                .default_security
+                .lock()
+                .expect("default security lock poisoned")
+        {
+            tool_sec.security = level;
+        }
+        self.restrict(tool_name, tool_sec)
+    }
+
+    /// Apply the session restriction, if any, to a tool's configured security.
+    fn restrict(&self, tool_name: &str, mut tool_sec: ToolSecurity) -> ToolSecurity {
+        if self.restricted_tools.contains(tool_name) {
+            if tool_sec.security == SecurityLevel::Full {
+                tool_sec.security = SecurityLevel::Allowlist;
+            }
+            if tool_sec.ask == AskMode::Off {
+                tool_sec.ask = AskMode::OnMiss;
+            }
+        }
+        tool_sec
+    }
+
+    /// Parameters as they should be shown to the user: redacted when
+    /// redaction is on. The tool itself always gets the originals.
+    pub fn display_params<'a>(&self, params: &'a Value) -> Cow<'a, Value> {
+        if self.redact {
+            Cow::Owned(redact_params(params))
+        } else {
+            Cow::Borrowed(params)
         }
     }
 
@@ -73,102 +229,316 @@ impl ApprovalEngine {
     ///
     /// For "bash" tools, performs command analysis (safe-bin detection, allowlist matching).
     /// For other tools, checks whether the tool name appears in its own allowlist.
+    /// Denylist entries are checked before anything else, bypass included.
     pub fn check(&self, info: &ToolCallInfo) -> EngineOutcome {
+        let approvals = self.approvals.lock().expect("approvals lock poisoned");
+        if let Some(entry) = self.denylisted(&approvals, info) {
+            return EngineOutcome::Denied {
+                reason: format!("denylisted: {}", entry),
+            };
+        }
+
         if self.bypass_approvals {
-            return EngineOutcome::Allowed;
+            return EngineOutcome::Allowed {
+                reason: ApprovalReason::Bypass,
+            };
         }
 
-        let approvals = self.approvals.lock().expect("approvals lock poisoned");
-        let tool_sec = approvals.tool_security(&info.tool_name);
+        let tool_sec = self.configured(&approvals, &info.tool_name);
         let security = tool_sec.security;
         let ask = tool_sec.ask;
 
         if info.tool_name == "bash" {
-            let (allowlist_satisfied, pattern) = self.check_bash(&approvals, &info.params);
+            let (satisfied_by, pattern, note) = self.check_bash(&approvals, &info.params);
 
-            let outcome = evaluate_approval(security, ask, allowlist_satisfied);
+            // Unanalyzable or backgrounded commands are asked about every time.
+            let session_approved = note.is_none()
+                && pattern
+                    .as_deref()
+                    .is_some_and(|p| self.session_approved(&info.tool_name, p));
+            let outcome =
+                evaluate_approval(security, ask, satisfied_by.is_some(), session_approved);
             match outcome {
-                ApprovalOutcome::Allow => EngineOutcome::Allowed,
+                ApprovalOutcome::Allow => EngineOutcome::Allowed {
+                    reason: allow_reason(
+                        satisfied_by,
+                        pattern
+                            .as_deref()
+                            .filter(|_| ask == AskMode::Once && session_approved),
+                    ),
+                },
                 ApprovalOutcome::Denied => EngineOutcome::Denied {
                     reason: "denied by policy".to_string(),
                 },
-                ApprovalOutcome::Ask => EngineOutcome::NeedsApproval {
-                    description: self.describe_tool_call(info),
-                    pattern,
-                },
+                ApprovalOutcome::Ask => {
+                    let mut description = self.describe_tool_call(info);
+                    if let Some(note) = note {
+                        description.push_str(&format!(" \u{2014} {}", note));
+                    }
+                    let similar = pattern
+                        .as_deref()
+                        .map(|p| approvals.similar_patterns("bash", p, MAX_SIMILAR_APPROVALS))
+                        .unwrap_or_default();
+                    EngineOutcome::NeedsApproval {
+                        description,
+                        pattern,
+                        similar,
+                        scopes: Vec::new(),
+                    }
+                }
             }
         } else {
-            // For non-bash tools, check if the tool name itself is in the allowlist.
-            let allowlist_satisfied = approvals.is_allowed(&info.tool_name, &info.tool_name);
+            // For non-bash tools, check if the tool name itself is in the
+            // allowlist, or for file writes, a directory the path is under.
+            let target = self.write_target(info);
+            let satisfied_by = approvals
+                .matching_entry(&info.tool_name, &info.tool_name)
+                .or_else(|| {
+                    let target = target.as_ref()?.to_string_lossy();
+                    approvals.matching_entry(&info.tool_name, &target)
+                })
+                .map(|entry| ApprovalReason::AllowlistPattern(entry.to_string()));
 
-            let outcome = evaluate_approval(security, ask, allowlist_satisfied);
+            let session_approved = self.session_approved(&info.tool_name, &info.tool_name);
+            let outcome =
+                evaluate_approval(security, ask, satisfied_by.is_some(), session_approved);
             match outcome {
-                ApprovalOutcome::Allow => EngineOutcome::Allowed,
+                ApprovalOutcome::Allow => EngineOutcome::Allowed {
+                    reason: allow_reason(
+                        satisfied_by,
+                        Some(info.tool_name.as_str())
+                            .filter(|_| ask == AskMode::Once && session_approved),
+                    ),
+                },
                 ApprovalOutcome::Denied => EngineOutcome::Denied {
                     reason: "denied by policy".to_string(),
                 },
                 ApprovalOutcome::Ask => EngineOutcome::NeedsApproval {
                     description: self.describe_tool_call(info),
                     pattern: Some(info.tool_name.clone()),
+                    similar: Vec::new(),
+                    scopes: match (&self.workspace, &target) {
+                        (Some(workspace), Some(target)) => path_scopes(workspace, target),
+                        _ => Vec::new(),
+                    },
                 },
             }
         }
     }
 
+    /// The effective security configuration for a tool (exact → "*" → defaults).
+    pub fn tool_security(&self, tool_name: &str) -> ToolSecurity {
+        let approvals = self.approvals.lock().expect("approvals lock poisoned");
+        self.configured(&approvals, tool_name)
+    }
+
+    /// Set a tool's security level and persist it. `full` also turns asking
+    /// off for the tool, so it stops prompting whatever the parameters.
+    pub fn set_tool_security(&self, tool_name: &str, level: SecurityLevel) -> anyhow::Result<()> {
+        let mut approvals = self.approvals.lock().expect("approvals lock poisoned");
+        approvals.set_security(tool_name, level);
+        approvals.save(&self.approvals_path)
+    }
+
     /// Resolve a pending approval by recording the user's decision.
     ///
-    /// If the decision is AllowAlways, the pattern is added to the allowlist and persisted.
+    /// Any allow is remembered for the rest of the session (used by `ask = "once"`).
+    /// If the decision is AllowAlways or AllowScope, the pattern is added to the
+    /// allowlist and persisted (for AllowScope, the caller passes the chosen
+    /// scope's pattern); AllowTool trusts the whole tool instead.
     pub fn resolve(&self, tool_name: &str, pattern: Option<&str>, decision: ApprovalDecision) {
-        if decision == ApprovalDecision::AllowAlways
+        if decision != ApprovalDecision::Deny
             && let Some(pat) = pattern
+        {
+            self.session_approvals
+                .lock()
+                .expect("session approvals lock poisoned")
+                .insert((tool_name.to_string(), pat.to_string()));
+        }
+        if matches!(
+            decision,
+            ApprovalDecision::AllowAlways | ApprovalDecision::AllowScope(_)
+        ) && let Some(pat) = pattern
         {
             let mut approvals = self.approvals.lock().expect("approvals lock poisoned");
             approvals.add_to_allowlist(tool_name, pat);
             // Best-effort save — callers should handle errors if critical.
             let _ = approvals.save(&self.approvals_path);
         }
+        if decision == ApprovalDecision::AllowTool {
+            let _ = self.set_tool_security(tool_name, SecurityLevel::Full);
+        }
+    }
+
+    /// Whether the user allowed `pattern` for `tool_name` earlier in this session.
+    fn session_approved(&self, tool_name: &str, pattern: &str) -> bool {
+        self.session_approvals
+            .lock()
+            .expect("session approvals lock poisoned")
+            .contains(&(tool_name.to_string(), pattern.to_string()))
+    }
+
+    /// All allowlist entries, for display in the allowlist editor.
+    pub fn allowlist_rows(&self) -> Vec<AllowlistRow> {
+        let approvals = self.approvals.lock().expect("approvals lock poisoned");
+        approvals.allowlist_rows()
+    }
+
+    /// Delete an allowlist entry and persist the change. Returns whether the
+    /// entry existed; nothing is written when it didn't.
+    pub fn remove_allowlist_entry(&self, tool_name: &str, pattern: &str) -> anyhow::Result<bool> {
+        let mut approvals = self.approvals.lock().expect("approvals lock poisoned");
+        if !approvals.remove_from_allowlist(tool_name, pattern) {
+            return Ok(false);
+        }
+        approvals.save(&self.approvals_path)?;
+        Ok(true)
+    }
+
+    /// The denylist entry that covers this call, if any. Candidates are the
+    /// tool name, a file write's target, and for bash every executable the
+    /// command may run, as written, by basename, and resolved, looking
+    /// through wrappers like `env` and `sh -c`. Bash commands are parsed
+    /// whatever their length, so a denied command anywhere in a chain is
+    /// caught.
+    fn denylisted(&self, approvals: &ApprovalsFile, info: &ToolCallInfo) -> Option<String> {
+        let mut candidates = vec![info.tool_name.clone()];
+        if info.tool_name == "bash" {
+            let command = info
+                .params
+                .get("command")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            for executable in invoked_executables(command) {
+                if let Some(path) = resolve_executable(&executable) {
+                    candidates.push(path.to_string_lossy().into_owned());
+                }
+                candidates.push(executable);
+            }
+        } else if let Some(target) = self.write_target(info) {
+            candidates.push(target.to_string_lossy().into_owned());
+        }
+        approvals
+            .denying_entry(&info.tool_name, &candidates)
+            .map(str::to_string)
     }
 
     /// Extract the command from bash params, analyze it, and check safe-bin/allowlist status.
     ///
-    /// Returns (allowlist_satisfied, pattern) where pattern is the resolved executable path
-    /// or executable name for potential allowlisting.
-    fn check_bash(&self, approvals: &ApprovalsFile, params: &Value) -> (bool, Option<String>) {
+    /// Returns (satisfied_by, pattern, note) where satisfied_by is the safe-bin
+    /// rule or allowlist entry that covers the command, pattern is the resolved
+    /// executable path or executable name for potential allowlisting, and note
+    /// explains why the command must be asked about (unanalyzable or backgrounded).
+    fn check_bash(
+        &self,
+        approvals: &ApprovalsFile,
+        params: &Value,
+    ) -> (Option<ApprovalReason>, Option<String>, Option<String>) {
         let command = params.get("command").and_then(|v| v.as_str()).unwrap_or("");
 
-        let analysis = analyze_command(command);
+        let analysis = analyze_command_bounded(command, self.max_command_length);
+
+        // Commands we couldn't analyze always go to the user, and can't be
+        // allowlisted since we don't trust the parsed executable.
+        if let Some(limitation) = analysis.limitation() {
+            return (
+                None,
+                None,
+                Some(format!("could not be fully analyzed ({})", limitation)),
+            );
+        }
+
+        // Detached processes outlive the approval; always ask, even if allowlisted.
+        if analysis.backgrounds {
+            return (
+                None,
+                allowlist_pattern(&analysis),
+                Some("starts a background process".to_string()),
+            );
+        }
 
         // Safe commands (all segments use safe bins) are auto-approved.
         if analysis.safe {
-            return (true, None);
+            return (Some(ApprovalReason::SafeBins), None, None);
         }
 
         // Check if the resolved executable is in the allowlist.
         let pattern = allowlist_pattern(&analysis);
-        let allowlist_satisfied = pattern
-            .as_ref()
-            .map(|p| approvals.is_allowed("bash", p))
-            .unwrap_or(false);
+        let satisfied_by = pattern
+            .as_deref()
+            .and_then(|p| approvals.matching_entry("bash", p))
+            .map(|entry| ApprovalReason::AllowlistPattern(entry.to_string()));
+
+        (satisfied_by, pattern, None)
+    }
 
-        (allowlist_satisfied, pattern)
+    /// Where a file write lands, for tools whose approvals are path-scoped.
+    fn write_target(&self, info: &ToolCallInfo) -> Option<PathBuf> {
+        if !PATH_SCOPED_TOOLS.contains(&info.tool_name.as_str()) {
+            return None;
+        }
+        let workspace = self.workspace.as_ref()?;
+        let path = info.params.get("path")?.as_str()?;
+        resolve_target(workspace, path)
     }
 
-    /// Format a tool call for display, truncating params to 60 characters.
+    /// Format a tool call for display, using the same summary the TUI shows,
+    /// flagged when it writes inside a VCS metadata directory. A file write
+    /// also gets a preview of what it changes, on the lines below.
     fn describe_tool_call(&self, info: &ToolCallInfo) -> String {
-        let params_str = info.params.to_string();
-        let truncated = if params_str.len() > 60 {
-            format!("{}...", &params_str[..60])
-        } else {
-            params_str
-        };
-        format!("{}({})", info.tool_name, truncated)
+        let mut description =
+            describe_tool_call(&info.tool_name, &self.display_params(&info.params));
+        if let Some(warning) = vcs_write_warning(&info.tool_name, &info.params) {
+            description.push_str(&format!(" \u{2014} {}", warning));
+        }
+        if let Some(preview) = self.write_preview(info) {
+            description.push_str(&format!(" \u{2014} {}", preview));
+        }
+        description
+    }
+
+    /// The diff preview for a write_file call, redacted line by line when
+    /// redaction is on. Relative paths are read from the workspace when
+    /// there is one.
+    fn write_preview(&self, info: &ToolCallInfo) -> Option<String> {
+        if info.tool_name != "write_file" {
+            return None;
+        }
+        let content = info.params.get("content")?.as_str()?;
+        let path = self
+            .write_target(info)
+            .or_else(|| Some(PathBuf::from(info.params.get("path")?.as_str()?)))?;
+        let preview = write_preview(&path, content)?;
+        if !self.redact {
+            return Some(preview);
+        }
+        let redacted: Vec<String> = preview
+            .lines()
+            .map(|line| match line.split_at_checked(1) {
+                Some((sign @ (" " | "-" | "+"), rest)) => format!("{}{}", sign, redact_str(rest)),
+                _ => line.to_string(),
+            })
+            .collect();
+        Some(redacted.join("\n"))
     }
 }
 
+/// Why an allowed call was allowed, in the order the policy checks: a safe-bin
+/// or allowlist match first, then a session approval, otherwise the tool's
+/// `full` security level.
+fn allow_reason(
+    satisfied_by: Option<ApprovalReason>,
+    session_pattern: Option<&str>,
+) -> ApprovalReason {
+    satisfied_by
+        .or_else(|| session_pattern.map(|p| ApprovalReason::Session(p.to_string())))
+        .unwrap_or(ApprovalReason::FullSecurity)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::approval::allowlist::ToolApprovalConfig;
+    use crate::approval::allowlist::{ApprovalDefaults, ToolApprovalConfig};
     use crate::approval::types::{AskMode, SecurityLevel, ToolSecurity};
     use std::collections::HashMap;
 
@@ -184,6 +554,7 @@ mod tests {
                     ..ToolSecurity::default()
                 },
                 allowlist: Vec::new(),
+                denylist: Vec::new(),
             },
         );
         tools.insert(
@@ -195,11 +566,12 @@ mod tests {
                     ..ToolSecurity::default()
                 },
                 allowlist: Vec::new(),
+                denylist: Vec::new(),
             },
         );
         ApprovalsFile {
             version: 1,
-            defaults: ToolSecurity::default(),
+            defaults: ApprovalDefaults::default(),
             tools,
         }
     }
@@ -215,7 +587,31 @@ mod tests {
             params: serde_json::json!({ "command": "cat file.txt | grep error" }),
         };
 
-        assert_eq!(engine.check(&info), EngineOutcome::Allowed);
+        assert_eq!(
+            engine.check(&info),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::SafeBins
+            }
+        );
+    }
+
+    #[test]
+    fn allowlisted_bash_command_reports_the_matching_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut approvals = test_approvals();
+        approvals.add_to_allowlist("bash", "*rm");
+        let engine = ApprovalEngine::with_approvals(approvals, dir.path().join("a.json"));
+
+        assert_eq!(
+            engine.check(&bash_call("rm -rf build")),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::AllowlistPattern("*rm".to_string())
+            }
+        );
+        assert_eq!(
+            ApprovalReason::AllowlistPattern("*rm".to_string()).to_string(),
+            "allowlist:*rm"
+        );
     }
 
     #[test]
@@ -236,6 +632,230 @@ mod tests {
         }
     }
 
+    #[test]
+    fn approval_description_matches_tui_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path);
+
+        let params = serde_json::json!({ "command": "rm   -rf\n/tmp/data" });
+        let info = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: params.clone(),
+        };
+
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval { description, .. } => {
+                assert_eq!(description, "bash(rm -rf /tmp/data)");
+                assert_eq!(
+                    description,
+                    format!(
+                        "bash({})",
+                        crate::tools::summary::summarize_params("bash", &params)
+                    )
+                );
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn default_security_applies_only_to_unconfigured_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path.clone())
+            .with_default_security(SecurityLevel::Full);
+
+        assert_eq!(
+            engine.tool_security("mystery").security,
+            SecurityLevel::Full
+        );
+        assert_eq!(
+            engine.tool_security("bash").security,
+            SecurityLevel::Allowlist
+        );
+
+        engine.set_default_security(SecurityLevel::Deny);
+        assert!(matches!(
+            engine.check(&ToolCallInfo {
+                tool_name: "mystery".to_string(),
+                params: serde_json::json!({}),
+            }),
+            EngineOutcome::Denied { .. }
+        ));
+        // Session-only: approvals.json is untouched.
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn tool_security_falls_back_to_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path);
+
+        assert_eq!(
+            engine.tool_security("read_file").security,
+            SecurityLevel::Full
+        );
+        assert_eq!(
+            engine.tool_security("mystery").security,
+            ToolSecurity::default().security
+        );
+    }
+
+    #[test]
+    fn overlong_safe_command_still_asks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine =
+            ApprovalEngine::with_approvals(test_approvals(), path).with_max_command_length(32);
+
+        let info = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": format!("echo {}", "x".repeat(64)) }),
+        };
+
+        // Only safe bins, but too long to analyze → asks, with nothing to allowlist.
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval {
+                description,
+                pattern,
+                ..
+            } => {
+                assert!(description.contains("too long to analyze"));
+                assert_eq!(pattern, None);
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unterminated_quote_asks_even_when_allowlisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let mut approvals = test_approvals();
+        approvals.add_to_allowlist("bash", "*");
+        let engine = ApprovalEngine::with_approvals(approvals, path);
+
+        let well_formed = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": "git log 'oops'; rm -rf /" }),
+        };
+        assert_eq!(
+            engine.check(&well_formed),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::AllowlistPattern("*".to_string())
+            }
+        );
+
+        let info = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": "git log 'oops; rm -rf /" }),
+        };
+
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval {
+                description,
+                pattern,
+                ..
+            } => {
+                assert!(description.contains("could not be fully analyzed"));
+                assert!(description.contains("unterminated quote"));
+                assert_eq!(pattern, None);
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn needs_approval_lists_similar_allowlist_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let mut approvals = test_approvals();
+        approvals.add_to_allowlist("bash", "/opt/tools/bin/mytool");
+        approvals.add_to_allowlist("bash", "/usr/bin/python3");
+        let engine = ApprovalEngine::with_approvals(approvals, path);
+
+        let info = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": "mytool --check" }),
+        };
+
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval { similar, .. } => {
+                assert_eq!(similar, vec!["/opt/tools/bin/mytool".to_string()]);
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn approval_description_redacts_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let info = ToolCallInfo {
+            tool_name: "mcp_deploy".to_string(),
+            params: serde_json::json!({ "service": "web", "api_token": "s3cr3t" }),
+        };
+
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path.clone());
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval { description, .. } => {
+                assert!(description.contains("service=web"));
+                assert!(!description.contains("s3cr3t"));
+                assert!(description.contains("\u{2022}\u{2022}\u{2022}(6)"));
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path).with_redaction(false);
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval { description, .. } => {
+                assert!(description.contains("s3cr3t"));
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn background_command_asks_even_when_allowlisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let mut approvals = test_approvals();
+        approvals.add_to_allowlist("bash", "*");
+        let engine = ApprovalEngine::with_approvals(approvals, path);
+
+        let info = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": "cat server.log &" }),
+        };
+
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval { description, .. } => {
+                assert!(description.contains("starts a background process"));
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn writes_into_git_are_flagged_in_the_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine =
+            ApprovalEngine::with_approvals(test_approvals(), dir.path().join("approvals.json"));
+
+        let info = ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": "./gen.sh > .git/HEAD" }),
+        };
+        match engine.check(&info) {
+            EngineOutcome::NeedsApproval { description, .. } => {
+                assert!(description.ends_with("\u{2014} \u{26a0}\u{fe0f} writes inside .git/"));
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
     #[test]
     fn read_file_auto_approves() {
         let dir = tempfile::tempdir().unwrap();
@@ -248,7 +868,51 @@ mod tests {
         };
 
         // read_file has Full security + Off ask mode → auto-allow.
-        assert_eq!(engine.check(&info), EngineOutcome::Allowed);
+        assert_eq!(
+            engine.check(&info),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::FullSecurity
+            }
+        );
+    }
+
+    #[test]
+    fn restricted_tool_asks_despite_full_security() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path.clone())
+            .with_restricted_tools(&["read_file"]);
+
+        let info = ToolCallInfo {
+            tool_name: "read_file".to_string(),
+            params: serde_json::json!({ "path": "/etc/hosts" }),
+        };
+        assert!(matches!(
+            engine.check(&info),
+            EngineOutcome::NeedsApproval { .. }
+        ));
+        assert_eq!(
+            engine.tool_security("read_file").security,
+            SecurityLevel::Allowlist
+        );
+
+        // Allowing it for good still works, and the file keeps the real level.
+        engine.resolve(
+            "read_file",
+            Some("read_file"),
+            ApprovalDecision::AllowAlways,
+        );
+        assert_eq!(
+            engine.check(&info),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::AllowlistPattern("read_file".to_string())
+            }
+        );
+        let reloaded = ApprovalsFile::load(&path).unwrap();
+        assert_eq!(
+            reloaded.tool_security("read_file").security,
+            SecurityLevel::Full
+        );
     }
 
     #[test]
@@ -288,6 +952,192 @@ mod tests {
         assert!(reloaded.is_allowed("bash", "/usr/bin/rm"));
     }
 
+    #[test]
+    fn set_tool_security_full_auto_allows_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path.clone());
+        let call = ToolCallInfo {
+            tool_name: "mcp_deploy".to_string(),
+            params: serde_json::json!({"env": "staging"}),
+        };
+        assert!(matches!(
+            engine.check(&call),
+            EngineOutcome::NeedsApproval { .. }
+        ));
+
+        engine
+            .set_tool_security("mcp_deploy", SecurityLevel::Full)
+            .unwrap();
+        let full = EngineOutcome::Allowed {
+            reason: ApprovalReason::FullSecurity,
+        };
+        assert_eq!(engine.check(&call), full);
+        let other_params = ToolCallInfo {
+            tool_name: "mcp_deploy".to_string(),
+            params: serde_json::json!({"env": "prod"}),
+        };
+        assert_eq!(engine.check(&other_params), full);
+
+        let reloaded = ApprovalsFile::load(&path).unwrap();
+        assert_eq!(
+            reloaded.tool_security("mcp_deploy").security,
+            SecurityLevel::Full
+        );
+        assert_eq!(reloaded.tool_security("mcp_deploy").ask, AskMode::Off);
+    }
+
+    #[test]
+    fn resolve_allow_tool_trusts_every_bash_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = ApprovalEngine::with_approvals(test_approvals(), dir.path().join("a.json"));
+        assert!(matches!(
+            engine.check(&bash_call("rm -rf build")),
+            EngineOutcome::NeedsApproval { .. }
+        ));
+
+        engine.resolve("bash", Some("/usr/bin/rm"), ApprovalDecision::AllowTool);
+        let full = EngineOutcome::Allowed {
+            reason: ApprovalReason::FullSecurity,
+        };
+        assert_eq!(engine.check(&bash_call("rm -rf build")), full);
+        assert_eq!(engine.check(&bash_call("curl example.com")), full);
+    }
+
+    #[test]
+    fn set_tool_security_deny_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = ApprovalEngine::with_approvals(test_approvals(), dir.path().join("a.json"));
+        engine
+            .set_tool_security("read_file", SecurityLevel::Deny)
+            .unwrap();
+        let call = ToolCallInfo {
+            tool_name: "read_file".to_string(),
+            params: serde_json::json!({"path": "a.txt"}),
+        };
+        assert!(matches!(engine.check(&call), EngineOutcome::Denied { .. }));
+    }
+
+    /// Engine whose bash and mcp_deploy tools use `ask = "once"`.
+    fn once_engine(path: PathBuf) -> ApprovalEngine {
+        let mut approvals = ApprovalsFile::default();
+        for tool in ["bash", "mcp_deploy"] {
+            approvals.tools.insert(
+                tool.to_string(),
+                ToolApprovalConfig {
+                    security: ToolSecurity {
+                        security: SecurityLevel::Allowlist,
+                        ask: AskMode::Once,
+                        ..ToolSecurity::default()
+                    },
+                    allowlist: Vec::new(),
+                    denylist: Vec::new(),
+                },
+            );
+        }
+        ApprovalEngine::with_approvals(approvals, path)
+    }
+
+    fn bash_call(command: &str) -> ToolCallInfo {
+        ToolCallInfo {
+            tool_name: "bash".to_string(),
+            params: serde_json::json!({ "command": command }),
+        }
+    }
+
+    #[test]
+    fn ask_once_allows_repeat_of_approved_pattern() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = once_engine(path.clone());
+
+        let pattern = match engine.check(&bash_call("git push origin main")) {
+            EngineOutcome::NeedsApproval { pattern, .. } => pattern,
+            other => panic!("first call should ask, got {:?}", other),
+        };
+        engine.resolve("bash", pattern.as_deref(), ApprovalDecision::AllowOnce);
+
+        assert_eq!(
+            engine.check(&bash_call("git push origin main")),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::Session(pattern.unwrap())
+            }
+        );
+        assert!(matches!(
+            engine.check(&bash_call("make install")),
+            EngineOutcome::NeedsApproval { .. }
+        ));
+        // Session approvals are never written to approvals.json.
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn ask_once_still_asks_after_deny_and_for_backgrounded_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = once_engine(dir.path().join("approvals.json"));
+        let info = ToolCallInfo {
+            tool_name: "mcp_deploy".to_string(),
+            params: serde_json::json!({}),
+        };
+
+        engine.resolve("mcp_deploy", Some("mcp_deploy"), ApprovalDecision::Deny);
+        assert!(matches!(
+            engine.check(&info),
+            EngineOutcome::NeedsApproval { .. }
+        ));
+        engine.resolve(
+            "mcp_deploy",
+            Some("mcp_deploy"),
+            ApprovalDecision::AllowOnce,
+        );
+        assert_eq!(
+            engine.check(&info),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::Session("mcp_deploy".to_string())
+            }
+        );
+
+        let pattern = match engine.check(&bash_call("git fetch")) {
+            EngineOutcome::NeedsApproval { pattern, .. } => pattern,
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        };
+        engine.resolve("bash", pattern.as_deref(), ApprovalDecision::AllowOnce);
+        assert!(matches!(
+            engine.check(&bash_call("git fetch &")),
+            EngineOutcome::NeedsApproval { .. }
+        ));
+    }
+
+    #[test]
+    fn remove_allowlist_entry_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path.clone());
+        engine.resolve("bash", Some("/usr/bin/git"), ApprovalDecision::AllowAlways);
+        engine.resolve("bash", Some("/usr/bin/make"), ApprovalDecision::AllowAlways);
+
+        assert!(
+            engine
+                .remove_allowlist_entry("bash", "/usr/bin/git")
+                .unwrap()
+        );
+        assert!(
+            !engine
+                .remove_allowlist_entry("bash", "/usr/bin/git")
+                .unwrap()
+        );
+
+        let patterns: Vec<String> = engine
+            .allowlist_rows()
+            .into_iter()
+            .map(|r| r.pattern)
+            .collect();
+        assert_eq!(patterns, vec!["/usr/bin/make"]);
+        let reloaded = ApprovalsFile::load(&path).unwrap();
+        assert!(!reloaded.is_allowed("bash", "/usr/bin/git"));
+        assert!(reloaded.is_allowed("bash", "/usr/bin/make"));
+    }
+
     #[test]
     fn resolve_allow_once_does_not_persist() {
         let dir = tempfile::tempdir().unwrap();
@@ -316,6 +1166,238 @@ mod tests {
             params: serde_json::json!({ "command": "rm -rf /" }),
         };
 
-        assert_eq!(engine.check(&info), EngineOutcome::Allowed);
+        assert_eq!(
+            engine.check(&info),
+            EngineOutcome::Allowed {
+                reason: ApprovalReason::Bypass
+            }
+        );
+    }
+
+    /// An engine scoped to a fresh workspace with a `src/agent` directory,
+    /// where read_file needs approval like write_file does.
+    fn workspace_engine() -> (tempfile::TempDir, PathBuf, ApprovalEngine) {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("ws");
+        std::fs::create_dir_all(workspace.join("src/agent")).unwrap();
+        let workspace = workspace.canonicalize().unwrap();
+        let mut approvals = test_approvals();
+        approvals.tools.remove("read_file");
+        let engine = ApprovalEngine::with_approvals(approvals, dir.path().join("approvals.json"))
+            .with_workspace(&workspace);
+        (dir, workspace, engine)
+    }
+
+    fn call(tool: &str, path: &str) -> ToolCallInfo {
+        ToolCallInfo {
+            tool_name: tool.to_string(),
+            params: serde_json::json!({ "path": path, "content": "x" }),
+        }
+    }
+
+    #[test]
+    fn writes_in_the_workspace_offer_directory_scopes() {
+        let (_dir, workspace, engine) = workspace_engine();
+        match engine.check(&call("write_file", "src/agent/new.rs")) {
+            EngineOutcome::NeedsApproval {
+                pattern, scopes, ..
+            } => {
+                // Plain "Always Allow" still covers the whole tool.
+                assert_eq!(pattern.as_deref(), Some("write_file"));
+                let labels: Vec<&str> = scopes.iter().map(|s| s.label.as_str()).collect();
+                assert_eq!(labels, ["src/agent/new.rs", "src/agent/**", "src/**"]);
+                assert_eq!(
+                    scopes[2].pattern,
+                    format!("{}/**", workspace.join("src").display())
+                );
+            }
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn writes_preview_what_they_change() {
+        let (_dir, workspace, engine) = workspace_engine();
+        std::fs::write(workspace.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        let info = ToolCallInfo {
+            tool_name: "write_file".to_string(),
+            params: serde_json::json!({ "path": "src/lib.rs", "content": "fn a() {}\nfn c() {}\n" }),
+        };
+        let EngineOutcome::NeedsApproval { description, .. } = engine.check(&info) else {
+            panic!("expected NeedsApproval");
+        };
+        assert_eq!(
+            description,
+            "write_file(src/lib.rs, 20 B) \u{2014} +1 -1 lines\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}"
+        );
+
+        let EngineOutcome::NeedsApproval { description, .. } =
+            engine.check(&call("write_file", "src/agent/new.rs"))
+        else {
+            panic!("expected NeedsApproval");
+        };
+        assert!(description.ends_with("\u{2014} creates new file (1 line)"));
+    }
+
+    #[test]
+    fn a_scoped_write_approval_covers_later_writes_under_it() {
+        let (_dir, workspace, engine) = workspace_engine();
+        let EngineOutcome::NeedsApproval { scopes, .. } =
+            engine.check(&call("write_file", "src/agent/new.rs"))
+        else {
+            panic!("expected NeedsApproval");
+        };
+        engine.resolve(
+            "write_file",
+            Some(&scopes[2].pattern),
+            ApprovalDecision::AllowScope(2),
+        );
+
+        let absolute = workspace.join("src/lib.rs");
+        for path in [
+            "src/agent/loop.rs",
+            "src/deep/er/mod.rs",
+            absolute.to_str().unwrap(),
+        ] {
+            assert!(
+                matches!(
+                    engine.check(&call("write_file", path)),
+                    EngineOutcome::Allowed {
+                        reason: ApprovalReason::AllowlistPattern(_)
+                    }
+                ),
+                "{path} should be allowed"
+            );
+        }
+        for path in ["README.md", "tests/it.rs", "src/../../outside.rs"] {
+            assert!(
+                matches!(
+                    engine.check(&call("write_file", path)),
+                    EngineOutcome::NeedsApproval { .. }
+                ),
+                "{path} should still ask"
+            );
+        }
+    }
+
+    #[test]
+    fn scoped_write_approvals_leave_reads_alone() {
+        let (_dir, _workspace, engine) = workspace_engine();
+        let EngineOutcome::NeedsApproval { scopes, .. } =
+            engine.check(&call("write_file", "src/main.rs"))
+        else {
+            panic!("expected NeedsApproval");
+        };
+        engine.resolve(
+            "write_file",
+            Some(&scopes[1].pattern),
+            ApprovalDecision::AllowScope(1),
+        );
+
+        match engine.check(&call("read_file", "src/main.rs")) {
+            EngineOutcome::NeedsApproval { scopes, .. } => assert!(scopes.is_empty()),
+            other => panic!("expected NeedsApproval, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn denylist_catches_any_segment_of_a_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut approvals = test_approvals();
+        approvals.add_to_denylist(Some("bash"), "sudo");
+        let engine = ApprovalEngine::with_approvals(approvals, dir.path().join("approvals.json"));
+
+        assert_eq!(
+            engine.check(&bash_call("echo hi && sudo rm -rf /")),
+            EngineOutcome::Denied {
+                reason: "denylisted: sudo".to_string()
+            }
+        );
+        assert!(matches!(
+            engine.check(&bash_call("ls | sudo tee /etc/hosts")),
+            EngineOutcome::Denied { .. }
+        ));
+        assert!(matches!(
+            engine.check(&bash_call("echo hi")),
+            EngineOutcome::Allowed { .. }
+        ));
+    }
+
+    #[test]
+    fn denylist_sees_through_padding_paths_and_wrappers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut approvals = test_approvals();
+        approvals.add_to_denylist(Some("bash"), "sudo");
+        let path = dir.path().join("approvals.json");
+        approvals.save(&path).unwrap();
+        let padded = format!("sudo rm -rf / # {}", "x".repeat(64));
+
+        for engine in [
+            ApprovalEngine::new(path.clone())
+                .unwrap()
+                .with_max_command_length(32),
+            ApprovalEngine::new_with_bypass(path.clone(), true).unwrap(),
+        ] {
+            for command in [
+                padded.as_str(),
+                "/usr/bin/sudo ls",
+                "env sudo ls",
+                "sh -c \"sudo ls\"",
+                "bash -c 'echo ok && sudo ls'",
+            ] {
+                assert_eq!(
+                    engine.check(&bash_call(command)),
+                    EngineOutcome::Denied {
+                        reason: "denylisted: sudo".to_string()
+                    },
+                    "{}",
+                    command
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn denylist_wins_over_allow_always_and_bypass() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = ApprovalEngine::with_approvals(test_approvals(), path.clone());
+        engine.resolve("bash", Some("*make"), ApprovalDecision::AllowAlways);
+        engine.resolve(
+            "mcp_deploy",
+            Some("mcp_deploy"),
+            ApprovalDecision::AllowAlways,
+        );
+        assert!(matches!(
+            engine.check(&bash_call("make")),
+            EngineOutcome::Allowed { .. }
+        ));
+
+        let mut approvals = ApprovalsFile::load(&path).unwrap();
+        approvals.add_to_denylist(Some("bash"), "make");
+        approvals.add_to_denylist(None, "mcp_*");
+        approvals.save(&path).unwrap();
+
+        let mcp_call = ToolCallInfo {
+            tool_name: "mcp_deploy".to_string(),
+            params: serde_json::json!({}),
+        };
+        for engine in [
+            ApprovalEngine::new(path.clone()).unwrap(),
+            ApprovalEngine::new_with_bypass(path.clone(), true).unwrap(),
+        ] {
+            assert_eq!(
+                engine.check(&bash_call("make")),
+                EngineOutcome::Denied {
+                    reason: "denylisted: make".to_string()
+                }
+            );
+            assert_eq!(
+                engine.check(&mcp_call),
+                EngineOutcome::Denied {
+                    reason: "denylisted: mcp_*".to_string()
+                }
+            );
+        }
     }
 }
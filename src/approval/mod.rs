@@ -4,11 +4,13 @@
 pub mod allowlist;
 pub mod analysis;
 pub mod engine;
+pub mod paths;
 pub mod policy;
 pub mod types;
 
 pub use allowlist::*;
 pub use analysis::*;
 pub use engine::*;
+pub use paths::*;
 pub use policy::*;
 pub use types::*;
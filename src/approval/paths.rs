@@ -0,0 +1,158 @@
+// ABOUTME: Path scopes for file-writing tools — "this file", "this directory", "this top-level dir".
+// ABOUTME: Resolves write targets inside the workspace and builds the allowlist globs offered for them.
+
+use std::path::{Component, Path, PathBuf};
+
+use glob::Pattern;
+
+/// Tools whose approvals can be scoped to a directory by the path they write.
+pub const PATH_SCOPED_TOOLS: &[&str] = &["write_file"];
+
+/// One "always allow" choice for a file write: everything the pattern
+/// covers, inside one workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathScope {
+    /// Shown to the user, relative to the workspace: `src/**`.
+    pub label: String,
+    /// Stored in the allowlist: an absolute glob, so the entry only ever
+    /// covers this workspace.
+    pub pattern: String,
+}
+
+/// The absolute, symlink-free path a write to `path` lands on, or None when
+/// it can't be worked out. Relative paths are taken from `workspace`. The
+/// file and its parent directories may not exist yet, so the deepest
+/// existing ancestor is canonicalized and the rest appended; a `..` in that
+/// remainder gives None rather than a guess, since `file_name` stops there.
+pub fn resolve_target(workspace: &Path, path: &str) -> Option<PathBuf> {
+    if path.is_empty() {
+        return None;
+    }
+    let joined = workspace.join(path);
+    let mut existing = joined.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let mut target = canonical;
+            for component in rest.iter().rev() {
+                target.push(component);
+            }
+            return Some(target);
+        }
+        rest.push(existing.file_name()?.to_owned());
+        existing = existing.parent()?;
+    }
+}
+
+/// The scopes offered for always allowing a write to `target`: the file
+/// itself, its directory, and the top-level workspace directory it's under,
+/// narrowest first. Empty when `target` is outside `workspace`.
+pub fn path_scopes(workspace: &Path, target: &Path) -> Vec<PathScope> {
+    let Ok(relative) = target.strip_prefix(workspace) else {
+        return Vec::new();
+    };
+    let parts: Vec<&str> = relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    if parts.is_empty() || parts.len() != relative.components().count() {
+        return Vec::new();
+    }
+
+    let file = PathScope {
+        label: parts.join("/"),
+        pattern: Pattern::escape(&target.to_string_lossy()),
+    };
+    let mut scopes = vec![file];
+    // The file's directory, then the top-level one, skipping the workspace
+    // root itself: that's what the tool-wide "Always Allow" is for.
+    for depth in [parts.len() - 1, 1] {
+        if depth == 0 || depth == parts.len() {
+            continue;
+        }
+        let dir = workspace.join(parts[..depth].join("/"));
+        let scope = PathScope {
+            label: format!("{}/**", parts[..depth].join("/")),
+            pattern: format!("{}/**", Pattern::escape(&dir.to_string_lossy())),
+        };
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    scopes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(scopes: &[PathScope]) -> Vec<&str> {
+        scopes.iter().map(|s| s.label.as_str()).collect()
+    }
+
+    #[test]
+    fn nested_paths_offer_file_parent_and_top_level_dir() {
+        let workspace = Path::new("/work/app");
+        let scopes = path_scopes(workspace, Path::new("/work/app/src/agent/loop.rs"));
+        assert_eq!(
+            labels(&scopes),
+            ["src/agent/loop.rs", "src/agent/**", "src/**"]
+        );
+        assert_eq!(scopes[0].pattern, "/work/app/src/agent/loop.rs");
+        assert_eq!(scopes[2].pattern, "/work/app/src/**");
+    }
+
+    #[test]
+    fn shallow_paths_offer_fewer_scopes() {
+        let workspace = Path::new("/work/app");
+        assert_eq!(
+            labels(&path_scopes(workspace, Path::new("/work/app/src/main.rs"))),
+            ["src/main.rs", "src/**"]
+        );
+        assert_eq!(
+            labels(&path_scopes(workspace, Path::new("/work/app/README.md"))),
+            ["README.md"]
+        );
+    }
+
+    #[test]
+    fn paths_outside_the_workspace_offer_nothing() {
+        let workspace = Path::new("/work/app");
+        assert!(path_scopes(workspace, Path::new("/etc/passwd")).is_empty());
+        assert!(path_scopes(workspace, Path::new("/work/application/x.rs")).is_empty());
+    }
+
+    #[test]
+    fn glob_characters_in_paths_are_escaped() {
+        let workspace = Path::new("/work/app");
+        let scopes = path_scopes(workspace, Path::new("/work/app/[draft]/a.md"));
+        assert_eq!(scopes[1].pattern, "/work/app/[[]draft[]]/**");
+        assert!(
+            Pattern::new(&scopes[1].pattern)
+                .unwrap()
+                .matches("/work/app/[draft]/b.md")
+        );
+    }
+
+    #[test]
+    fn targets_resolve_through_missing_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(workspace.join("src")).unwrap();
+
+        assert_eq!(
+            resolve_target(&workspace, "src/new/mod.rs"),
+            Some(workspace.join("src/new/mod.rs"))
+        );
+        // Existing `..` is resolved by the filesystem; a dangling one isn't guessed.
+        assert_eq!(
+            resolve_target(&workspace, "src/../notes.md"),
+            Some(workspace.join("notes.md"))
+        );
+        assert_eq!(resolve_target(&workspace, "missing/../../x"), None);
+        assert_eq!(resolve_target(&workspace, ""), None);
+    }
+}
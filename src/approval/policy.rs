@@ -5,12 +5,15 @@ use super::types::{ApprovalOutcome, AskMode, SecurityLevel};
 
 /// Evaluate the approval policy for a tool invocation.
 ///
-/// Given the security level, ask mode, and whether the allowlist is satisfied,
-/// returns the appropriate approval outcome (Allow, Denied, or Ask).
+/// Given the security level, ask mode, whether the allowlist is satisfied, and
+/// whether the user already allowed this pattern earlier in the session,
+/// returns the appropriate approval outcome (Allow, Denied, or Ask). Only the
+/// `once` ask mode looks at session approvals.
 pub fn evaluate_approval(
     security: SecurityLevel,
     ask: AskMode,
     allowlist_satisfied: bool,
+    session_approved: bool,
 ) -> ApprovalOutcome {
     // Rule 1: Deny always blocks, regardless of anything else.
     if security == SecurityLevel::Deny {
@@ -22,28 +25,38 @@ pub fn evaluate_approval(
         return ApprovalOutcome::Ask;
     }
 
+    // Rule 3: Once asks like on-miss, but a pattern allowed earlier in the
+    // session counts as covered.
+    if ask == AskMode::Once {
+        return if allowlist_satisfied || session_approved {
+            ApprovalOutcome::Allow
+        } else {
+            ApprovalOutcome::Ask
+        };
+    }
+
     match security {
         SecurityLevel::Deny => unreachable!("handled above"),
 
         SecurityLevel::Allowlist => {
             if allowlist_satisfied {
-                // Rule 3: Allowlist + satisfied → allow.
+                // Rule 4: Allowlist + satisfied → allow.
                 ApprovalOutcome::Allow
             } else {
                 match ask {
-                    AskMode::OnMiss => ApprovalOutcome::Ask, // Rule 4
-                    AskMode::Off => ApprovalOutcome::Denied, // Rule 5
-                    AskMode::Always => unreachable!("handled above"),
+                    AskMode::OnMiss => ApprovalOutcome::Ask, // Rule 5
+                    AskMode::Off => ApprovalOutcome::Denied, // Rule 6
+                    AskMode::Always | AskMode::Once => unreachable!("handled above"),
                 }
             }
         }
 
         SecurityLevel::Full => {
             if allowlist_satisfied || ask == AskMode::Off {
-                // Rule 6: Full + (satisfied or Off) → allow.
+                // Rule 7: Full + (satisfied or Off) → allow.
                 ApprovalOutcome::Allow
             } else {
-                // Rule 7: Full + OnMiss + !satisfied → ask.
+                // Rule 8: Full + OnMiss + !satisfied → ask.
                 ApprovalOutcome::Ask
             }
         }
@@ -57,15 +70,15 @@ mod tests {
     #[test]
     fn deny_always_blocks() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Deny, AskMode::Off, true),
+            evaluate_approval(SecurityLevel::Deny, AskMode::Off, true, false),
             ApprovalOutcome::Denied,
         );
         assert_eq!(
-            evaluate_approval(SecurityLevel::Deny, AskMode::Always, true),
+            evaluate_approval(SecurityLevel::Deny, AskMode::Always, true, false),
             ApprovalOutcome::Denied,
         );
         assert_eq!(
-            evaluate_approval(SecurityLevel::Deny, AskMode::OnMiss, false),
+            evaluate_approval(SecurityLevel::Deny, AskMode::OnMiss, false, false),
             ApprovalOutcome::Denied,
         );
     }
@@ -73,11 +86,11 @@ mod tests {
     #[test]
     fn allowlist_satisfied_allows() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Allowlist, AskMode::Off, true),
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::Off, true, false),
             ApprovalOutcome::Allow,
         );
         assert_eq!(
-            evaluate_approval(SecurityLevel::Allowlist, AskMode::OnMiss, true),
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::OnMiss, true, false),
             ApprovalOutcome::Allow,
         );
     }
@@ -85,7 +98,7 @@ mod tests {
     #[test]
     fn allowlist_miss_with_on_miss_asks() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Allowlist, AskMode::OnMiss, false),
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::OnMiss, false, false),
             ApprovalOutcome::Ask,
         );
     }
@@ -93,7 +106,7 @@ mod tests {
     #[test]
     fn allowlist_miss_with_off_denies() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Allowlist, AskMode::Off, false),
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::Off, false, false),
             ApprovalOutcome::Denied,
         );
     }
@@ -101,11 +114,11 @@ mod tests {
     #[test]
     fn allowlist_with_always_ask_asks() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Allowlist, AskMode::Always, true),
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::Always, true, false),
             ApprovalOutcome::Ask,
         );
         assert_eq!(
-            evaluate_approval(SecurityLevel::Allowlist, AskMode::Always, false),
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::Always, false, false),
             ApprovalOutcome::Ask,
         );
     }
@@ -113,11 +126,11 @@ mod tests {
     #[test]
     fn full_with_off_allows() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Full, AskMode::Off, false),
+            evaluate_approval(SecurityLevel::Full, AskMode::Off, false, false),
             ApprovalOutcome::Allow,
         );
         assert_eq!(
-            evaluate_approval(SecurityLevel::Full, AskMode::Off, true),
+            evaluate_approval(SecurityLevel::Full, AskMode::Off, true, false),
             ApprovalOutcome::Allow,
         );
     }
@@ -125,11 +138,11 @@ mod tests {
     #[test]
     fn full_with_always_asks() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Full, AskMode::Always, true),
+            evaluate_approval(SecurityLevel::Full, AskMode::Always, true, false),
             ApprovalOutcome::Ask,
         );
         assert_eq!(
-            evaluate_approval(SecurityLevel::Full, AskMode::Always, false),
+            evaluate_approval(SecurityLevel::Full, AskMode::Always, false, false),
             ApprovalOutcome::Ask,
         );
     }
@@ -137,7 +150,7 @@ mod tests {
     #[test]
     fn full_with_on_miss_allows_when_satisfied() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Full, AskMode::OnMiss, true),
+            evaluate_approval(SecurityLevel::Full, AskMode::OnMiss, true, false),
             ApprovalOutcome::Allow,
         );
     }
@@ -145,7 +158,41 @@ mod tests {
     #[test]
     fn full_with_on_miss_asks_when_not_satisfied() {
         assert_eq!(
-            evaluate_approval(SecurityLevel::Full, AskMode::OnMiss, false),
+            evaluate_approval(SecurityLevel::Full, AskMode::OnMiss, false, false),
+            ApprovalOutcome::Ask,
+        );
+    }
+
+    #[test]
+    fn once_asks_until_approved_in_session() {
+        for security in [SecurityLevel::Allowlist, SecurityLevel::Full] {
+            assert_eq!(
+                evaluate_approval(security, AskMode::Once, false, false),
+                ApprovalOutcome::Ask,
+            );
+            assert_eq!(
+                evaluate_approval(security, AskMode::Once, false, true),
+                ApprovalOutcome::Allow,
+            );
+            assert_eq!(
+                evaluate_approval(security, AskMode::Once, true, false),
+                ApprovalOutcome::Allow,
+            );
+        }
+        assert_eq!(
+            evaluate_approval(SecurityLevel::Deny, AskMode::Once, true, true),
+            ApprovalOutcome::Denied,
+        );
+    }
+
+    #[test]
+    fn session_approval_ignored_outside_once() {
+        assert_eq!(
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::OnMiss, false, true),
+            ApprovalOutcome::Ask,
+        );
+        assert_eq!(
+            evaluate_approval(SecurityLevel::Allowlist, AskMode::Always, true, true),
             ApprovalOutcome::Ask,
         );
     }
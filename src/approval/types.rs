@@ -15,6 +15,25 @@ pub enum SecurityLevel {
     Full,
 }
 
+impl SecurityLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SecurityLevel::Deny => "deny",
+            SecurityLevel::Allowlist => "allowlist",
+            SecurityLevel::Full => "full",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "deny" => Some(SecurityLevel::Deny),
+            "allowlist" => Some(SecurityLevel::Allowlist),
+            "full" => Some(SecurityLevel::Full),
+            _ => None,
+        }
+    }
+}
+
 /// When to prompt the user for approval.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -25,6 +44,9 @@ pub enum AskMode {
     OnMiss,
     /// Always ask, regardless of allowlist.
     Always,
+    /// Ask on a miss, but only once per pattern per session: after the user
+    /// allows a pattern, matching calls are allowed until the session ends.
+    Once,
 }
 
 /// What to do when an approval request times out.
@@ -48,6 +70,12 @@ pub enum ApprovalDecision {
     AllowAlways,
     /// Deny this invocation.
     Deny,
+    /// Allow and trust the whole tool from now on: its security becomes
+    /// `full` with asking off, whatever the parameters.
+    AllowTool,
+    /// Allow and add the prompt's path scope at this index to the
+    /// allowlist, e.g. writes under `src/`.
+    AllowScope(usize),
 }
 
 /// The outcome of evaluating an approval policy.
@@ -104,6 +132,12 @@ mod tests {
         assert_eq!(parsed, AskMode::OnMiss);
     }
 
+    #[test]
+    fn ask_mode_once_parses() {
+        let parsed: AskMode = serde_json::from_str("\"once\"").unwrap();
+        assert_eq!(parsed, AskMode::Once);
+    }
+
     #[test]
     fn tool_security_defaults() {
         let ts = ToolSecurity::default();
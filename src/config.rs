@@ -3,13 +3,14 @@
 
 use std::collections::HashMap;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::Deserialize;
 
 use mux::prelude::*;
 
-use crate::approval::ApprovalsFile;
+use crate::approval::{ApprovalsFile, SecurityLevel};
+use crate::tools::summary::DEFAULT_SUMMARY_CHARS;
 
 const APP_NAME: &str = "soloclaw";
 
@@ -22,6 +23,15 @@ pub struct Config {
     pub permissions: PermissionsConfig,
     pub skills: SkillsConfig,
     pub compaction: CompactionConfig,
+    pub sessions: SessionsConfig,
+    pub tui: TuiConfig,
+    pub tools: ToolsConfig,
+    pub mcp: McpConfig,
+    pub agent: AgentConfig,
+    pub privacy: PrivacyConfig,
+    pub workspace: WorkspaceConfig,
+    /// Canned prompts bound to function keys, from `[[snippets]]`.
+    pub snippets: Vec<SnippetConfig>,
 }
 
 /// LLM provider configuration.
@@ -31,6 +41,20 @@ pub struct LlmConfig {
     pub provider: String,
     pub model: String,
     pub max_tokens: u32,
+    /// Whether to stream responses: true, false, or "auto".
+    pub streaming: StreamingMode,
+    /// Reasoning effort for models that support it; unset sends nothing.
+    pub reasoning_effort: Option<Effort>,
+    /// Anthropic extended-thinking budget. 0 derives it from `reasoning_effort`.
+    pub thinking_budget_tokens: u32,
+    /// Give up on a non-streaming request, or on a stream's first event,
+    /// after this many seconds. 0 waits forever.
+    pub request_timeout_seconds: u64,
+    /// Give up on a stream that sends nothing for this many seconds.
+    /// 0 waits forever.
+    pub stream_idle_timeout_seconds: u64,
+    /// Models a turn moves to, in order, when the configured one fails.
+    pub fallbacks: Vec<FallbackModel>,
     pub openai: ProviderConfig,
     pub anthropic: ProviderConfig,
     pub gemini: ProviderConfig,
@@ -44,6 +68,12 @@ impl Default for LlmConfig {
             provider: "anthropic".to_string(),
             model: "claude-sonnet-4-5-20250929".to_string(),
             max_tokens: 4096,
+            streaming: StreamingMode::default(),
+            reasoning_effort: None,
+            thinking_budget_tokens: 0,
+            request_timeout_seconds: 600,
+            stream_idle_timeout_seconds: 120,
+            fallbacks: Vec::new(),
             openai: ProviderConfig::default(),
             anthropic: ProviderConfig::default(),
             gemini: ProviderConfig::default(),
@@ -53,11 +83,98 @@ impl Default for LlmConfig {
     }
 }
 
+/// How responses are requested from the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "StreamingSetting")]
+pub enum StreamingMode {
+    /// Always stream (`streaming = true`).
+    #[default]
+    On,
+    /// Never stream; use single non-streaming requests (`streaming = false`).
+    Off,
+    /// Stream, but fall back to non-streaming for the rest of the session if
+    /// the provider's stream fails up front because it can't stream
+    /// (`streaming = "auto"`). Other failures, like auth or rate limits, don't count.
+    Auto,
+}
+
+/// How hard a reasoning model should think (`llm.reasoning_effort`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effort {
+    Low,
+    Medium,
+    High,
+}
+
+impl Effort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Effort::Low => "low",
+            Effort::Medium => "medium",
+            Effort::High => "high",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(Effort::Low),
+            "medium" => Some(Effort::Medium),
+            "high" => Some(Effort::High),
+            _ => None,
+        }
+    }
+}
+
+/// Raw TOML shape of `llm.streaming`: a bool or the string "auto".
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StreamingSetting {
+    Bool(bool),
+    Name(String),
+}
+
+impl TryFrom<StreamingSetting> for StreamingMode {
+    type Error = String;
+
+    fn try_from(value: StreamingSetting) -> Result<Self, Self::Error> {
+        match value {
+            StreamingSetting::Bool(true) => Ok(StreamingMode::On),
+            StreamingSetting::Bool(false) => Ok(StreamingMode::Off),
+            StreamingSetting::Name(name) => match name.as_str() {
+                "auto" => Ok(StreamingMode::Auto),
+                "true" | "on" => Ok(StreamingMode::On),
+                "false" | "off" => Ok(StreamingMode::Off),
+                other => Err(format!(
+                    "invalid llm.streaming value '{}': expected true, false, or \"auto\"",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+/// One `[llm] fallbacks` entry: a provider and model to retry a turn on.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FallbackModel {
+    pub provider: String,
+    pub model: String,
+}
+
+impl std::fmt::Display for FallbackModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.provider, self.model)
+    }
+}
+
 /// Shared provider configuration.
 #[derive(Debug, Clone, Deserialize, Default)]
 #[serde(default)]
 pub struct ProviderConfig {
     pub base_url: Option<String>,
+    /// Extra HTTP headers attached to every request. Values may reference
+    /// environment variables as `${VAR}`.
+    pub headers: HashMap<String, String>,
 }
 
 /// Ollama-specific configuration.
@@ -65,16 +182,86 @@ pub struct ProviderConfig {
 #[serde(default)]
 pub struct OllamaConfig {
     pub base_url: String,
+    /// Extra HTTP headers attached to every request (supports `${VAR}`).
+    pub headers: HashMap<String, String>,
 }
 
 impl Default for OllamaConfig {
     fn default() -> Self {
         Self {
             base_url: "http://localhost:11434".to_string(),
+            headers: HashMap::new(),
         }
     }
 }
 
+impl LlmConfig {
+    /// This configuration switched to a fallback's provider and model, with
+    /// the same per-provider URLs and headers.
+    pub fn for_fallback(&self, fallback: &FallbackModel) -> LlmConfig {
+        LlmConfig {
+            provider: fallback.provider.clone(),
+            model: fallback.model.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Environment variable holding the API key for the active provider, or
+    /// None for providers that don't need one (ollama) or aren't recognized.
+    pub fn api_key_var(&self) -> Option<&'static str> {
+        match self.provider.as_str() {
+            "anthropic" => Some("ANTHROPIC_API_KEY"),
+            "openai" => Some("OPENAI_API_KEY"),
+            "gemini" => Some("GEMINI_API_KEY"),
+            "openrouter" => Some("OPENROUTER_API_KEY"),
+            _ => None,
+        }
+    }
+
+    /// Headers configured for the active provider, with `${VAR}` references
+    /// expanded from the environment, sorted by name for stable ordering.
+    pub fn provider_headers(&self) -> Vec<(String, String)> {
+        let headers = match self.provider.as_str() {
+            "anthropic" => &self.anthropic.headers,
+            "openai" => &self.openai.headers,
+            "gemini" => &self.gemini.headers,
+            "openrouter" => &self.openrouter.headers,
+            "ollama" => &self.ollama.headers,
+            _ => return Vec::new(),
+        };
+        let mut resolved: Vec<(String, String)> = headers
+            .iter()
+            .map(|(k, v)| (k.clone(), expand_env_vars(v)))
+            .collect();
+        resolved.sort();
+        resolved
+    }
+}
+
+/// Expand `${VAR}` references in a config value from the process environment.
+/// Unset variables expand to an empty string; text without `${` is returned as-is.
+pub fn expand_env_vars(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find('}') {
+            Some(end) => {
+                out.push_str(&std::env::var(&after[..end]).unwrap_or_default());
+                rest = &after[end + 1..];
+            }
+            None => {
+                // Unterminated reference: keep it literally.
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
 /// Approval defaults in config.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -83,6 +270,8 @@ pub struct ApprovalConfig {
     pub ask: String,
     pub ask_fallback: String,
     pub timeout_seconds: u64,
+    /// Bash commands longer than this (in bytes) are not parsed and always need approval.
+    pub max_command_length: usize,
 }
 
 impl Default for ApprovalConfig {
@@ -92,6 +281,7 @@ impl Default for ApprovalConfig {
             ask: "on-miss".to_string(),
             ask_fallback: "deny".to_string(),
             timeout_seconds: 120,
+            max_command_length: crate::approval::DEFAULT_MAX_COMMAND_LEN,
         }
     }
 }
@@ -112,8 +302,52 @@ pub struct CompactionConfig {
     pub enabled: bool,
     /// Override token limit triggering compaction (default: 90% of context window).
     pub threshold_token_limit: Option<u64>,
-    /// Maximum tokens allocated for retained user messages after compaction.
+    /// Maximum tokens allocated for retained messages after compaction.
     pub user_message_budget_tokens: usize,
+    /// Which recent messages are kept next to the summary.
+    pub retain: CompactionRetain,
+    /// Whether the summary replaces history right away or is shown for review first.
+    pub review: CompactionReview,
+    /// What to do when a resumed session is too large for the current model.
+    pub on_resume: OnResume,
+}
+
+/// Which messages compaction keeps alongside the summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompactionRetain {
+    /// Only what the user wrote.
+    #[default]
+    User,
+    /// User and assistant messages interleaved, for conversations where the
+    /// earlier answers are the context worth keeping.
+    Both,
+}
+
+/// How a compaction summary is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompactionReview {
+    /// Replace history with the summary without asking.
+    #[default]
+    Auto,
+    /// Show the summary and let the user accept, edit, or skip it.
+    Ask,
+}
+
+/// How a resumed session that exceeds the current model's window is handled,
+/// e.g. after switching to a model with a smaller context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnResume {
+    /// Summarize it before the first message; with compaction off, truncate
+    /// instead.
+    #[default]
+    Compact,
+    /// Keep only the most recent turns that fit.
+    Truncate,
+    /// Refuse to start.
+    Error,
 }
 
 impl Default for CompactionConfig {
@@ -123,10 +357,400 @@ impl Default for CompactionConfig {
             enabled: true,
             threshold_token_limit: None,
             user_message_budget_tokens: DEFAULT_USER_MESSAGE_BUDGET_TOKENS,
+            retain: CompactionRetain::User,
+            review: CompactionReview::Auto,
+            on_resume: OnResume::Compact,
+        }
+    }
+}
+
+/// Retention limits for persisted session data. A limit of 0 disables it,
+/// and all three are off until set, so nothing is pruned unless asked.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SessionsConfig {
+    /// Remove workspace sessions not touched in this many days.
+    pub max_age_days: u64,
+    /// Upper bound on the combined size of all session directories.
+    pub max_total_bytes: u64,
+    /// Maximum number of workspace session directories to keep.
+    pub max_sessions: usize,
+    /// Roll a JSONL log over to a numbered file once it reaches this size.
+    pub max_log_bytes: u64,
+    /// Which on-disk formats a session is saved in.
+    pub format: SessionFormat,
+    /// Save `session.json` mid-turn after this many tool results (0 = off).
+    pub autosave_tool_results: usize,
+    /// Save `session.json` mid-turn once this many seconds have passed since
+    /// the last save (0 = off). Checked as tool results arrive.
+    pub autosave_seconds: u64,
+    /// fsync the JSONL log after every this many entries: 1 syncs each one,
+    /// 0 leaves it to the OS. A crash can lose at most the unsynced entries.
+    pub fsync_every_writes: u64,
+    /// What a resumed session does with tool calls its last turn never ran.
+    pub pending_tool_calls: PendingToolCalls,
+    /// Warn when a resumed session was last active more than this many hours
+    /// ago (0 = off).
+    pub stale_after_hours: u64,
+    /// Tell the model, on the first request after a stale resume, that the
+    /// workspace may have changed since.
+    pub stale_context_note: bool,
+}
+
+/// How a resumed session settles tool calls left without results, e.g. by
+/// a crash or a dropped connection while an approval was pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PendingToolCalls {
+    /// Ask whether to run them before taking the first message.
+    #[default]
+    Ask,
+    /// Answer them with errors saying the session was interrupted.
+    Fail,
+}
+
+/// On-disk session formats: the append-only JSONL log, the full-state
+/// `session.json` rewritten after every turn, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionFormat {
+    /// JSONL log only; resume replays the log.
+    Jsonl,
+    /// `session.json` only; no JSONL log is written.
+    Json,
+    /// Both, resuming from `session.json`.
+    #[default]
+    Both,
+}
+
+impl SessionFormat {
+    /// Whether messages are appended to the JSONL log.
+    pub fn writes_jsonl(self) -> bool {
+        matches!(self, SessionFormat::Jsonl | SessionFormat::Both)
+    }
+
+    /// Whether the full state is saved to `session.json` after each turn.
+    pub fn writes_json(self) -> bool {
+        matches!(self, SessionFormat::Json | SessionFormat::Both)
+    }
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: 0,
+            max_total_bytes: 0,
+            max_sessions: 0,
+            max_log_bytes: 50 * 1024 * 1024,
+            format: SessionFormat::Both,
+            autosave_tool_results: 10,
+            autosave_seconds: 60,
+            fsync_every_writes: 1,
+            pending_tool_calls: PendingToolCalls::Ask,
+            stale_after_hours: 72,
+            stale_context_note: true,
+        }
+    }
+}
+
+/// How much of each tool call and result the chat shows (`[tui] tool_verbosity`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolVerbosity {
+    /// The call, then its result's first lines beneath it.
+    #[default]
+    Full,
+    /// One line per call with a summary of its result.
+    Compact,
+    /// A single tally line per turn.
+    Hidden,
+}
+
+impl ToolVerbosity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToolVerbosity::Full => "full",
+            ToolVerbosity::Compact => "compact",
+            ToolVerbosity::Hidden => "hidden",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "full" => Some(ToolVerbosity::Full),
+            "compact" => Some(ToolVerbosity::Compact),
+            "hidden" => Some(ToolVerbosity::Hidden),
+            _ => None,
+        }
+    }
+}
+
+/// Which keys drive the input and chat (`[tui] keymap`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keymap {
+    #[default]
+    Default,
+    /// Modal editing: insert mode types, normal mode moves around the chat.
+    Vim,
+}
+
+/// Terminal UI behavior.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Two Ctrl+C presses within this many milliseconds quit the app.
+    pub double_ctrl_c_ms: u64,
+    /// Pastes larger than this many bytes prompt instead of going straight
+    /// into the input.
+    pub max_paste_bytes: usize,
+    /// Lines kept when an oversized paste is inserted as its first lines.
+    pub paste_insert_lines: usize,
+    /// Show what auto-approved a tool call (safe-bin rule, allowlist entry,
+    /// security level) after it in the chat.
+    pub show_approval_reason: bool,
+    /// Columns per tab stop when showing answers and tool output.
+    pub tab_width: usize,
+    /// Use emoji in the header and status bar. Off draws them in plain
+    /// ASCII, for fonts that get emoji widths wrong.
+    pub emoji: bool,
+    /// Enter submits and Shift+Enter inserts a newline. Off swaps them:
+    /// Enter inserts a newline and Ctrl+Enter (or Ctrl+S) submits.
+    pub enter_submits: bool,
+    /// High-contrast colors, a role label on every message ("USER:",
+    /// "TOOL bash [denied]:"), plain-text status, and no blinking cursor,
+    /// for low-vision users and screen readers.
+    pub accessibility: bool,
+    /// How tool calls and results show in the chat; `/quiet` switches
+    /// between this and full for the session.
+    pub tool_verbosity: ToolVerbosity,
+    /// `vim` adds normal and visual modes, entered with Esc, which then no
+    /// longer quits.
+    pub keymap: Keymap,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        Self {
+            double_ctrl_c_ms: 500,
+            max_paste_bytes: 100 * 1024,
+            paste_insert_lines: 100,
+            show_approval_reason: true,
+            tab_width: 4,
+            emoji: true,
+            enter_submits: true,
+            accessibility: false,
+            tool_verbosity: ToolVerbosity::Full,
+            keymap: Keymap::Default,
+        }
+    }
+}
+
+/// What gets hidden from the screen, session logs, and the model, and which
+/// terminal features may act on the user's machine.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// Mask secret-looking tool parameters in approval prompts, tool-call
+    /// lines, and session logs. Tools still receive the real values. A
+    /// `[sessions] format = "jsonl"` log is left unmasked, since resuming
+    /// replays it.
+    pub redact_params: bool,
+    /// Tell the model the user's `$SHELL` in the system prompt.
+    pub include_shell_in_prompt: bool,
+    /// Tell the model the machine's hostname in the system prompt.
+    pub include_hostname: bool,
+    /// Let `/copy` write to the clipboard through the terminal (OSC 52)
+    /// without asking. Off asks the first time.
+    pub allow_osc52: bool,
+    /// Let desktop notifications go out through the terminal without asking.
+    /// Off asks the first time.
+    pub allow_notifications: bool,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            redact_params: true,
+            include_shell_in_prompt: true,
+            include_hostname: false,
+            allow_osc52: false,
+            allow_notifications: false,
+        }
+    }
+}
+
+/// Guards for where soloclaw is started.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    /// Run from the home directory or filesystem root without the
+    /// tightened defaults (no context files, file tools need approval).
+    pub allow_home: bool,
+}
+
+impl WorkspaceConfig {
+    /// The reason `workspace` is too broad to run in with normal defaults,
+    /// or `None` if it is a normal project directory or `allow_home` is set.
+    pub fn guard(&self, workspace: &Path, home: Option<&Path>) -> Option<BroadWorkspace> {
+        if self.allow_home {
+            return None;
+        }
+        broad_workspace(workspace, home)
+    }
+}
+
+/// A workspace that covers far more than one project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadWorkspace {
+    Home,
+    Root,
+    /// A directory that contains the home directory, like `/home` or `/Users`.
+    HomeParent,
+}
+
+impl BroadWorkspace {
+    pub fn describe(self) -> &'static str {
+        match self {
+            BroadWorkspace::Home => "your home directory",
+            BroadWorkspace::Root => "the filesystem root",
+            BroadWorkspace::HomeParent => "the directory containing your home directory",
+        }
+    }
+}
+
+/// Whether `workspace` is the home directory, the filesystem root, or the
+/// direct parent of home. Paths are compared as given, so callers should
+/// pass canonical ones.
+pub fn broad_workspace(workspace: &Path, home: Option<&Path>) -> Option<BroadWorkspace> {
+    if workspace.parent().is_none() {
+        return Some(BroadWorkspace::Root);
+    }
+    let home = home?;
+    if workspace == home {
+        Some(BroadWorkspace::Home)
+    } else if home.parent() == Some(workspace) {
+        Some(BroadWorkspace::HomeParent)
+    } else {
+        None
+    }
+}
+
+/// A canned prompt bound to a function key.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnippetConfig {
+    /// Function key name, "f1" through "f12".
+    pub key: String,
+    /// Text to insert; may use `{selection}` (the current draft) and `{clipboard}`.
+    pub text: String,
+    /// Send the text right away instead of inserting it at the cursor.
+    #[serde(default)]
+    pub submit: bool,
+}
+
+/// Tool execution behavior.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    /// Extra attempts for a failed read-only tool call before the error goes
+    /// to the model. Tools that write are never retried.
+    pub read_retries: u32,
+    /// Pause between retries, in milliseconds.
+    pub retry_delay_ms: u64,
+    /// Tools whose results are reused for identical calls within a turn.
+    /// Empty (the default) disables the cache. Only read-only tools count;
+    /// `bash` is only cached for commands that analyze as safe.
+    pub cache_reads: Vec<String>,
+    /// Tools never registered or offered to the model. MCP tools are matched
+    /// by the name the model sees.
+    pub disabled: Vec<String>,
+    /// Characters of a tool call's parameter summary shown in the chat,
+    /// live and when a session is replayed; longer ones end in "...".
+    pub summary_max_chars: usize,
+}
+
+impl ToolsConfig {
+    /// Whether `[tools] disabled` lists `name`.
+    pub fn is_disabled(&self, name: &str) -> bool {
+        self.disabled.iter().any(|d| d == name)
+    }
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            read_retries: 2,
+            retry_delay_ms: 250,
+            cache_reads: Vec::new(),
+            disabled: Vec::new(),
+            summary_max_chars: DEFAULT_SUMMARY_CHARS,
+        }
+    }
+}
+
+/// Limits on what a single turn may do on its own.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    /// Tokens (input, output, and cache) one turn may use before the user
+    /// is asked whether to keep going. None never asks.
+    pub per_turn_token_budget: Option<u64>,
+}
+
+/// How MCP servers are merged into the tool registry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct McpConfig {
+    /// What happens when a server offers a tool whose name is already taken.
+    pub on_conflict: McpConflictPolicy,
+    /// Minutes between re-listing each server's tools while idle, for servers
+    /// that add or remove tools mid-session. 0 disables it.
+    pub resync_minutes: u64,
+    /// Seconds each server gets to connect and list its tools before it's
+    /// reported as failed. Servers start concurrently.
+    pub connect_timeout_seconds: u64,
+    /// Open the TUI right away and connect servers in the background,
+    /// adding each one's tools as it comes up.
+    pub lazy: bool,
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        Self {
+            on_conflict: McpConflictPolicy::default(),
+            resync_minutes: 5,
+            connect_timeout_seconds: 30,
+            lazy: false,
         }
     }
 }
 
+impl McpConfig {
+    /// How long one server may take to start.
+    pub fn connect_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.connect_timeout_seconds.max(1))
+    }
+
+    /// How often to resync tool lists, or None when disabled.
+    pub fn resync_every(&self) -> Option<std::time::Duration> {
+        (self.resync_minutes > 0).then(|| std::time::Duration::from_secs(self.resync_minutes * 60))
+    }
+}
+
+/// Resolution for an MCP tool whose name a built-in or an earlier server
+/// already registered. The first registration always keeps the plain name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpConflictPolicy {
+    /// Register the newcomer as `server__tool`.
+    #[default]
+    Prefix,
+    /// Leave the newcomer out.
+    Skip,
+    /// Refuse to start, listing every conflict.
+    Error,
+}
+
 /// Skill prompt loading configuration.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -147,6 +771,42 @@ pub struct SkillsConfig {
     pub max_file_bytes: usize,
     /// Maximum total characters across all included skill contents.
     pub max_total_chars: usize,
+    /// Per-skill settings keyed by skill name (the SKILL.md's directory).
+    pub overrides: HashMap<String, SkillOverride>,
+}
+
+/// Per-skill settings under `[skills.overrides.<name>]`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SkillOverride {
+    /// Set to false to keep this skill out of the prompt.
+    pub enabled: bool,
+    /// Higher priorities load first, before the max_files/max_total_chars caps apply.
+    pub priority: Option<i64>,
+}
+
+impl Default for SkillOverride {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            priority: None,
+        }
+    }
+}
+
+impl SkillsConfig {
+    /// Whether the named skill may be loaded.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.overrides.get(name).is_none_or(|o| o.enabled)
+    }
+
+    /// Inclusion priority for the named skill (0 when unset).
+    pub fn priority(&self, name: &str) -> i64 {
+        self.overrides
+            .get(name)
+            .and_then(|o| o.priority)
+            .unwrap_or(0)
+    }
 }
 
 impl Default for SkillsConfig {
@@ -160,6 +820,7 @@ impl Default for SkillsConfig {
             max_files: 24,
             max_file_bytes: 128 * 1024,
             max_total_chars: 32_000,
+            overrides: HashMap::new(),
         }
     }
 }
@@ -178,11 +839,26 @@ struct McpServerEntry {
     args: Vec<String>,
     #[serde(default)]
     env: HashMap<String, String>,
+    /// Set to false to keep a server's config without connecting to it.
+    #[serde(default = "default_true")]
+    enabled: bool,
+    /// One line on what the server is for, shown to the model above its tools.
+    #[serde(default)]
+    description: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Config {
     /// Load config from XDG config path, falling back to legacy path and then defaults.
     pub fn load() -> anyhow::Result<Self> {
+        Self::load_with_provenance().map(|(config, _)| config)
+    }
+
+    /// Load config like `load`, also reporting where each tracked setting came from.
+    pub fn load_with_provenance() -> anyhow::Result<(Self, Provenance)> {
         let path = Self::resolved_config_path();
         if !path.exists() {
             let xdg_path = Self::config_path();
@@ -191,12 +867,40 @@ impl Config {
             }
             std::fs::write(&xdg_path, default_config_toml())?;
             let content = std::fs::read_to_string(&xdg_path)?;
-            let config: Self = toml::from_str(&content)?;
-            return Ok(config);
+            return Self::parse_with_provenance(&content);
         }
         let content = std::fs::read_to_string(&path)?;
-        let config: Self = toml::from_str(&content)?;
-        Ok(config)
+        Self::parse_with_provenance(&content)
+    }
+
+    /// Parse config file contents, recording which tracked settings the file sets.
+    pub fn parse_with_provenance(content: &str) -> anyhow::Result<(Self, Provenance)> {
+        let config: Self = toml::from_str(content)?;
+        let table: toml::Value = toml::from_str(content)?;
+        let mut provenance = Provenance::default();
+        for (key, path) in TRACKED_SETTINGS {
+            let in_file = path
+                .iter()
+                .try_fold(&table, |value, part| value.get(*part))
+                .is_some();
+            let source = if in_file {
+                Source::GlobalFile
+            } else {
+                Source::Default
+            };
+            provenance.set(key, config.tracked_value(key), source);
+        }
+        Ok((config, provenance))
+    }
+
+    /// Current value of a setting listed in `TRACKED_SETTINGS`.
+    fn tracked_value(&self, key: &str) -> String {
+        match key {
+            "provider" => self.llm.provider.clone(),
+            "model" => self.llm.model.clone(),
+            "security" => self.approval.security.clone(),
+            _ => String::new(),
+        }
     }
 
     /// Path to the XDG config directory for soloclaw.
@@ -257,7 +961,66 @@ impl Config {
         Self::data_dir().join("sessions")
     }
 
-    fn resolved_config_path() -> PathBuf {
+    /// Path to the XDG state directory for soloclaw: logs, usage records, and
+    /// lock files that are useful to keep but not worth backing up.
+    pub fn state_dir() -> PathBuf {
+        resolve_state_dir(
+            std::env::var("XDG_STATE_HOME").ok(),
+            dirs::state_dir(),
+            dirs::home_dir(),
+        )
+    }
+
+    /// Path to the diagnostic logs directory inside the state directory.
+    pub fn logs_dir() -> PathBuf {
+        Self::state_dir().join("logs")
+    }
+
+    /// Path to the token usage records inside the state directory.
+    pub fn usage_path() -> PathBuf {
+        Self::state_dir().join("usage.jsonl")
+    }
+
+    /// Path to the per-workspace first-run markers inside the state directory.
+    pub fn seen_workspaces_dir() -> PathBuf {
+        Self::state_dir().join("seen-workspaces")
+    }
+
+    /// Marker written once the first-run walkthrough was finished or skipped.
+    pub fn onboarded_marker_path() -> PathBuf {
+        Self::config_dir().join("onboarded")
+    }
+
+    /// "Always" answers to the clipboard and notification prompts.
+    pub fn consents_path() -> PathBuf {
+        Self::config_dir().join("consents.json")
+    }
+
+    /// Every directory and file location the app reads or writes, labelled,
+    /// for `claw setup --print-paths` and diagnostics.
+    pub fn paths_summary() -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("config dir", Self::config_dir()),
+            ("config file", Self::resolved_config_path()),
+            ("secrets", Self::secrets_env_path()),
+            ("approvals", Self::approvals_path()),
+            ("consents", Self::consents_path()),
+            (
+                "mcp servers",
+                find_mcp_config().unwrap_or_else(|| PathBuf::from(".mcp.json")),
+            ),
+            ("data dir", Self::data_dir()),
+            ("sessions", Self::sessions_dir()),
+            ("state dir", Self::state_dir()),
+            ("logs", Self::logs_dir()),
+            ("usage", Self::usage_path()),
+            ("seen workspaces", Self::seen_workspaces_dir()),
+        ]
+    }
+
+    /// The config file in use: the XDG one, else a legacy one, else where
+    /// the XDG one would go.
+    pub fn resolved_config_path() -> PathBuf {
         let xdg = Self::config_path();
         if xdg.exists() {
             return xdg;
@@ -272,6 +1035,59 @@ impl Config {
     }
 }
 
+/// Set `[approval] security` in the config file at `path`, keeping the rest
+/// of the file, comments included, as it is.
+pub fn save_approval_security(path: &Path, level: SecurityLevel) -> anyhow::Result<()> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let updated = set_toml_value(
+        &content,
+        "approval",
+        "security",
+        &format!("\"{}\"", level.as_str()),
+    );
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, updated)?;
+    Ok(())
+}
+
+/// Set `key = value` (a TOML literal) in `[section]` of a TOML document by
+/// editing lines: an existing assignment is replaced, a missing one goes
+/// right after the section header, and a missing section is appended.
+fn set_toml_value(content: &str, section: &str, key: &str, value: &str) -> String {
+    let assignment = format!("{} = {}", key, value);
+    let header = format!("[{}]", section);
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    let Some(start) = lines.iter().position(|line| line.trim() == header) else {
+        if !lines.is_empty() && !lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(header);
+        lines.push(assignment);
+        return lines.join("\n") + "\n";
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    let existing = (start + 1..end).find(|&i| {
+        lines[i]
+            .split_once('=')
+            .is_some_and(|(name, _)| name.trim() == key)
+    });
+    match existing {
+        Some(i) => lines[i] = assignment,
+        None => lines.insert(start + 1, assignment),
+    }
+    lines.join("\n") + "\n"
+}
+
 /// Recommended default model for each provider.
 pub fn default_model_for_provider(provider: &str) -> &'static str {
     match provider {
@@ -284,6 +1100,71 @@ pub fn default_model_for_provider(provider: &str) -> &'static str {
     }
 }
 
+/// What a model name alone says about which provider serves it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderInference {
+    /// Not a name we recognize; keep the configured provider.
+    Unknown,
+    /// A name only this provider serves.
+    Provider(&'static str),
+    /// A name several providers could serve; `--provider` has to say which.
+    Ambiguous,
+}
+
+/// Model families served locally through ollama.
+const LOCAL_MODEL_PREFIXES: [&str; 3] = ["llama", "mistral", "qwen"];
+
+/// Infer the provider from a model name: `vendor/model` is openrouter,
+/// `claude*` anthropic, `gpt*`/`o4*` openai, `gemini*` gemini. Open-weight
+/// families (llama, mistral, qwen) are ollama only when `ollama_reachable`;
+/// otherwise they could be anywhere, so they're ambiguous.
+pub fn infer_provider(model: &str, ollama_reachable: bool) -> ProviderInference {
+    let name = model.trim().to_ascii_lowercase();
+    if name.contains('/') {
+        return ProviderInference::Provider("openrouter");
+    }
+    if name.starts_with("claude") {
+        ProviderInference::Provider("anthropic")
+    } else if name.starts_with("gpt") || name.starts_with("o4") {
+        ProviderInference::Provider("openai")
+    } else if name.starts_with("gemini") {
+        ProviderInference::Provider("gemini")
+    } else if LOCAL_MODEL_PREFIXES.iter().any(|p| name.starts_with(p)) {
+        if ollama_reachable {
+            ProviderInference::Provider("ollama")
+        } else {
+            ProviderInference::Ambiguous
+        }
+    } else {
+        ProviderInference::Unknown
+    }
+}
+
+/// Whether an ollama server answers at `config.base_url`. Only a TCP
+/// connect, with a short timeout, so startup never waits long on it.
+pub fn ollama_reachable(config: &OllamaConfig) -> bool {
+    use std::net::{TcpStream, ToSocketAddrs};
+
+    let rest = config
+        .base_url
+        .split_once("://")
+        .map_or(config.base_url.as_str(), |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or_default();
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else if config.base_url.starts_with("https://") {
+        format!("{}:443", authority)
+    } else {
+        format!("{}:80", authority)
+    };
+    let Ok(mut addrs) = address.to_socket_addrs() else {
+        return false;
+    };
+    addrs.any(|addr| {
+        TcpStream::connect_timeout(&addr, std::time::Duration::from_millis(300)).is_ok()
+    })
+}
+
 /// Load MCP server configs from .mcp.json.
 pub fn load_mcp_configs() -> anyhow::Result<Vec<McpServerConfig>> {
     let path = find_mcp_config();
@@ -292,10 +1173,44 @@ pub fn load_mcp_configs() -> anyhow::Result<Vec<McpServerConfig>> {
     };
 
     let content = std::fs::read_to_string(&path)?;
-    let config: McpConfigFile = serde_json::from_str(&content)?;
+    parse_mcp_configs(&content)
+}
 
-    let servers = config
+/// Load each MCP server's `description` from .mcp.json, keyed by server name.
+pub fn load_mcp_descriptions() -> anyhow::Result<HashMap<String, String>> {
+    let Some(path) = find_mcp_config() else {
+        return Ok(HashMap::new());
+    };
+    let content = std::fs::read_to_string(&path)?;
+    parse_mcp_descriptions(&content)
+}
+
+/// Server descriptions from .mcp.json content; servers without one are left out.
+fn parse_mcp_descriptions(content: &str) -> anyhow::Result<HashMap<String, String>> {
+    let config: McpConfigFile = serde_json::from_str(content)?;
+    Ok(config
+        .mcp_servers
+        .into_iter()
+        .filter_map(|(name, entry)| {
+            let description = entry.description?.trim().to_string();
+            (!description.is_empty()).then_some((name, description))
+        })
+        .collect())
+}
+
+/// Parse .mcp.json content into server configs, skipping disabled servers.
+/// Servers are returned sorted by name so startup order is stable.
+pub(crate) fn parse_mcp_configs(content: &str) -> anyhow::Result<Vec<McpServerConfig>> {
+    let config: McpConfigFile = serde_json::from_str(content)?;
+
+    let mut entries: Vec<(String, McpServerEntry)> = config
         .mcp_servers
+        .into_iter()
+        .filter(|(_, entry)| entry.enabled)
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let servers = entries
         .into_iter()
         .map(|(name, entry)| McpServerConfig {
             name,
@@ -310,7 +1225,7 @@ pub fn load_mcp_configs() -> anyhow::Result<Vec<McpServerConfig>> {
     Ok(servers)
 }
 
-fn find_mcp_config() -> Option<PathBuf> {
+pub(crate) fn find_mcp_config() -> Option<PathBuf> {
     let local = PathBuf::from(".mcp.json");
     if local.exists() {
         return Some(local);
@@ -326,6 +1241,61 @@ fn find_mcp_config() -> Option<PathBuf> {
     None
 }
 
+/// Resolve the state directory: `$XDG_STATE_HOME`, then the platform state
+/// dir, then `~/.local/state`, each with the app name appended.
+fn resolve_state_dir(
+    xdg_state_home: Option<String>,
+    platform_state_dir: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> PathBuf {
+    if let Some(xdg) = xdg_state_home.filter(|s| !s.is_empty()) {
+        return PathBuf::from(xdg).join(APP_NAME);
+    }
+    if let Some(base) = platform_state_dir {
+        return base.join(APP_NAME);
+    }
+    if let Some(home) = home {
+        return home.join(".local").join("state").join(APP_NAME);
+    }
+    PathBuf::from(".").join(APP_NAME)
+}
+
+/// Entries that used to live in the data dir but belong in the state dir.
+const STATE_ENTRIES: &[&str] = &["logs", "usage.jsonl"];
+
+/// Move logs, usage records, and lock files from the data dir to the state
+/// dir. Entries already present at the destination are left alone, so this
+/// is safe to run on every start. Returns the (from, to) pairs that moved.
+pub fn migrate_state_files(
+    data_dir: &Path,
+    state_dir: &Path,
+) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved = Vec::new();
+    if !data_dir.exists() || data_dir == state_dir {
+        return Ok(moved);
+    }
+
+    let mut names: Vec<String> = STATE_ENTRIES.iter().map(|s| s.to_string()).collect();
+    for entry in std::fs::read_dir(data_dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if name.ends_with(".lock") {
+            names.push(name);
+        }
+    }
+
+    for name in names {
+        let from = data_dir.join(&name);
+        let to = state_dir.join(&name);
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        std::fs::create_dir_all(state_dir)?;
+        std::fs::rename(&from, &to)?;
+        moved.push((from, to));
+    }
+    Ok(moved)
+}
+
 /// Interactive setup command: initializes XDG config and provider secrets.
 pub fn run_setup() -> anyhow::Result<()> {
     let config_dir = Config::config_dir();
@@ -408,7 +1378,7 @@ fn prompt_line(prompt: &str) -> anyhow::Result<String> {
     Ok(input)
 }
 
-fn load_env_file(path: &PathBuf) -> anyhow::Result<HashMap<String, String>> {
+pub(crate) fn load_env_file(path: &Path) -> anyhow::Result<HashMap<String, String>> {
     if !path.exists() {
         return Ok(HashMap::new());
     }
@@ -454,6 +1424,17 @@ fn default_config_toml() -> String {
 provider = "anthropic"
 model = "claude-sonnet-4-5-20250929"
 max_tokens = 4096
+# true, false, or "auto" (fall back to non-streaming if the stream fails immediately)
+streaming = true
+# Reasoning effort for models that support it ("low", "medium", "high"); change with /effort.
+# reasoning_effort = "medium"
+# Anthropic thinking budget; 0 derives it from reasoning_effort.
+# thinking_budget_tokens = 0
+# Give up on a provider that doesn't answer, or whose stream goes quiet (0 = never).
+request_timeout_seconds = 600
+stream_idle_timeout_seconds = 120
+# Providers to retry a turn on, in order, when the one above fails.
+# fallbacks = [{ provider = "openai", model = "gpt-5.2" }, { provider = "ollama", model = "llama3.2" }]
 
 [llm.openai]
 base_url = "https://api.openai.com/v1"
@@ -467,15 +1448,22 @@ base_url = "https://generativelanguage.googleapis.com/v1beta"
 
 [llm.openrouter]
 base_url = "https://openrouter.ai/api/v1"
+# Optional request headers; values may use ${VAR} to read the environment.
+# [llm.openrouter.headers]
+# HTTP-Referer = "https://github.com/2389-research/soloclaw"
+# X-Title = "soloclaw"
 
 [llm.ollama]
 base_url = "http://localhost:11434"
 
 [approval]
-security = "allowlist"
+# Security for tools approvals.json has no entry for: deny, allowlist, or full.
+# Unset, approvals.json's defaults apply.
+# security = "allowlist"
 ask = "on-miss"
 ask_fallback = "deny"
 timeout_seconds = 120
+max_command_length = 16384
 
 [permissions]
 bypass_approvals = false
@@ -490,10 +1478,125 @@ max_files = 24
 max_file_bytes = 131072
 max_total_chars = 32000
 
+# Per-skill overrides, keyed by skill directory name:
+# [skills.overrides.peekaboo]
+# enabled = false
+# priority = 10        # higher loads first, before the caps apply
+
 [compaction]
 enabled = true
 # threshold_token_limit = 180000
 user_message_budget_tokens = 20000
+# Keep only your recent messages ("user"), or the recent dialogue with answers too ("both").
+retain = "user"
+# "ask" shows the summary to accept, edit, or skip before it replaces the history.
+review = "auto"
+# A resumed session too large for the model: "compact", "truncate", or "error".
+on_resume = "compact"
+
+[sessions]
+# Limits are applied at startup and by `claw sessions prune`; 0 disables a limit.
+# All are off by default, so no session data is deleted until you set one.
+# max_age_days = 90
+# max_total_bytes = 536870912 # 512 MB across all workspaces
+# max_sessions = 200
+max_log_bytes = 52428800      # roll JSONL logs over at 50 MB
+# "both" = JSONL log + session.json; "jsonl" skips the per-turn session.json rewrite
+# and resumes by replaying the log; "json" skips the log.
+format = "both"
+# Save session.json during long tool-calling turns, so a crash keeps most of the work:
+# after this many tool results, or this many seconds since the last save (0 = off).
+autosave_tool_results = 10
+autosave_seconds = 60
+# The JSONL log is written ahead of session.json; with format = "both", entries
+# session.json missed after a crash are replayed at startup. fsync the log after
+# this many entries (1 = every entry, 0 = leave it to the OS).
+fsync_every_writes = 1
+# Tool calls a crashed or disconnected session never ran: "ask" whether to run them
+# on resume, or "fail" them with an error result.
+pending_tool_calls = "ask"
+# Warn when resuming a session last active more than this many hours ago (0 = off),
+# with the commits and changed files since when the workspace is a git repo. The
+# first request then tells the model to re-read files before trusting its history.
+stale_after_hours = 72
+stale_context_note = true
+
+[tui]
+# Ctrl+C cancels the running turn (or clears the input); twice within this window quits.
+double_ctrl_c_ms = 500
+# Pastes over this size ask whether to attach them truncated or keep their first lines.
+max_paste_bytes = 102400
+paste_insert_lines = 100
+# Note what auto-approved each tool call, e.g. "· allowlist:/usr/bin/cargo".
+show_approval_reason = true
+# Tabs in answers and tool output are shown as spaces up to the next multiple of this.
+tab_width = 4
+# Emoji in the header and status bar; false draws them in plain ASCII.
+emoji = true
+# Enter sends and Shift+Enter adds a line; false swaps them, so Enter adds a line
+# and Ctrl+Enter sends. Terminals with the kitty keyboard protocol are asked to
+# report Ctrl+Enter as its own key; Ctrl+S sends in any terminal.
+enter_submits = true
+# High contrast, a text label on every message ("USER:", "TOOL bash [denied]:"),
+# no emoji, and no blinking cursor, for low vision and screen readers.
+accessibility = false
+# Tool calls in the chat: "full" (call and output), "compact" (one line each with a
+# summary of the output), or "hidden" (one tally per turn). /quiet toggles it.
+tool_verbosity = "full"
+# "vim": Esc leaves the input for normal mode (j/k scroll, gg/G, dd, v selects
+# messages to copy with y, i/a go back to typing, :q quits). Esc no longer quits.
+keymap = "default"
+
+[tools]
+# Retry failed read-only tool calls (read_file, list_files, search) before reporting the error.
+read_retries = 2
+retry_delay_ms = 250
+# Reuse results of identical calls within a turn; writes to a path invalidate it.
+# cache_reads = ["read_file", "list_files", "search"]
+# Hide tools from the model entirely (built-in or MCP names).
+# disabled = ["write_file", "bash"]
+# Tool-call parameters in the chat are cut to this many characters, marked with "...".
+summary_max_chars = 200
+
+[privacy]
+# Mask tokens, passwords, and similar tool parameters on screen and in session logs.
+redact_params = true
+# What the system prompt tells the model about this machine.
+include_shell_in_prompt = true
+include_hostname = false
+# Terminal features that reach outside soloclaw. Off asks the first time they are
+# used; answering "always" is remembered in consents.json next to this file.
+allow_osc52 = false             # /copy puts the last reply on the clipboard via OSC 52
+allow_notifications = false
+
+[mcp]
+# When an MCP server offers a tool whose name is already taken:
+# "prefix" registers it as server__tool, "skip" leaves it out, "error" refuses to start.
+on_conflict = "prefix"
+# Re-list each server's tools every N minutes while idle, picking up tools a
+# server added or removed mid-session. 0 disables it.
+resync_minutes = 5
+# Servers start concurrently; each gets this long to connect before it's reported as failed.
+connect_timeout_seconds = 30
+# Open the TUI right away and connect servers in the background, adding their
+# tools as each comes up. Turns sent before then use the tools registered so far.
+lazy = false
+
+[agent]
+# Once a turn has used this many tokens, ask before each further tool round
+# whether to continue, continue without asking again, or stop.
+# per_turn_token_budget = 100000
+
+[workspace]
+# Started from ~, /, or /home, soloclaw skips context files and asks before every
+# file tool call. Set this (or pass --allow-home) to run there with normal defaults.
+allow_home = false
+
+# Canned prompts on function keys. {selection} is the current draft, {clipboard} the clipboard.
+# [[snippets]]
+# key = "f2"
+# text = "Run the tests and fix any failures."
+# submit = true
 "#
     .to_string()
 }
@@ -516,6 +1619,170 @@ when `skills.enabled` and `skills.include_xdg_config` are true in `config.toml`.
 "#
 }
 
+/// Where the effective value of a setting came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Built-in default; the config file doesn't set it.
+    Default,
+    /// The global config.toml.
+    GlobalFile,
+    /// A command-line flag, e.g. `--model`.
+    Cli(&'static str),
+    /// Worked out from a command-line flag, e.g. the provider from `--model`.
+    Inferred(&'static str),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::GlobalFile => write!(f, "config file"),
+            Source::Cli(flag) => write!(f, "{}", flag),
+            Source::Inferred(flag) => write!(f, "{}, inferred", flag),
+        }
+    }
+}
+
+/// Settings whose provenance is tracked, with their path in config.toml.
+const TRACKED_SETTINGS: [(&str, &[&str]); 3] = [
+    ("provider", &["llm", "provider"]),
+    ("model", &["llm", "model"]),
+    ("security", &["approval", "security"]),
+];
+
+/// One tracked setting's effective value and its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub key: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+/// Where the effective provider, model, and security level came from.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    settings: Vec<Setting>,
+}
+
+impl Provenance {
+    fn set(&mut self, key: &'static str, value: String, source: Source) {
+        match self.settings.iter_mut().find(|s| s.key == key) {
+            Some(setting) => {
+                setting.value = value;
+                setting.source = source;
+            }
+            None => self.settings.push(Setting { key, value, source }),
+        }
+    }
+
+    /// The source of a tracked setting.
+    pub fn source(&self, key: &str) -> Option<Source> {
+        self.settings
+            .iter()
+            .find(|s| s.key == key)
+            .map(|s| s.source)
+    }
+
+    /// One line such as "model: gpt-5.2 (from --model), security: deny (from
+    /// config file)", or None when nothing was overridden on the command line.
+    pub fn summary(&self) -> Option<String> {
+        if !self
+            .settings
+            .iter()
+            .any(|s| matches!(s.source, Source::Cli(_) | Source::Inferred(_)))
+        {
+            return None;
+        }
+        let parts: Vec<String> = self
+            .settings
+            .iter()
+            .map(|s| format!("{}: {} (from {})", s.key, s.value, s.source))
+            .collect();
+        Some(parts.join(", "))
+    }
+}
+
+/// Settings given as command-line flags, applied on top of the loaded config.
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub security: Option<String>,
+    pub allow_home: bool,
+}
+
+impl CliOverrides {
+    /// Apply the flags to `config`, recording them in `provenance`. A new
+    /// provider without `--model` switches to that provider's default model;
+    /// `--model` without `--provider` switches to the provider its name
+    /// implies (see `infer_provider`). `ollama_reachable` is only asked for
+    /// open-weight model names. Fails when the name could belong to several
+    /// providers.
+    pub fn apply(
+        self,
+        config: &mut Config,
+        provenance: &mut Provenance,
+        ollama_reachable: impl FnOnce(&OllamaConfig) -> bool,
+    ) -> anyhow::Result<()> {
+        if self.provider.is_none()
+            && let Some(model) = &self.model
+        {
+            let local = LOCAL_MODEL_PREFIXES
+                .iter()
+                .any(|p| model.to_ascii_lowercase().starts_with(p));
+            let reachable =
+                local && (config.llm.provider == "ollama" || ollama_reachable(&config.llm.ollama));
+            match infer_provider(model, reachable) {
+                ProviderInference::Unknown => {}
+                ProviderInference::Provider(provider) => {
+                    if config.llm.provider != provider {
+                        config.llm.provider = provider.to_string();
+                        provenance.set(
+                            "provider",
+                            config.llm.provider.clone(),
+                            Source::Inferred("--model"),
+                        );
+                    }
+                }
+                ProviderInference::Ambiguous => anyhow::bail!(
+                    "Can't tell which provider serves '{}' (no ollama server answers at {}); \
+                     pass --provider",
+                    model,
+                    config.llm.ollama.base_url
+                ),
+            }
+        }
+        if let Some(provider) = self.provider {
+            config.llm.provider = provider;
+            provenance.set(
+                "provider",
+                config.llm.provider.clone(),
+                Source::Cli("--provider"),
+            );
+            if self.model.is_none() {
+                config.llm.model = default_model_for_provider(&config.llm.provider).to_string();
+                provenance.set("model", config.llm.model.clone(), Source::Cli("--provider"));
+            }
+        }
+        if let Some(model) = self.model {
+            config.llm.model = model;
+            provenance.set("model", config.llm.model.clone(), Source::Cli("--model"));
+        }
+        if let Some(security) = self.security {
+            config.approval.security = security;
+            provenance.set(
+                "security",
+                config.approval.security.clone(),
+                Source::Cli("--security"),
+            );
+        }
+        if self.allow_home {
+            config.workspace.allow_home = true;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -579,6 +1846,156 @@ max_files = 5
         assert_eq!(config.skills.max_files, 5);
     }
 
+    #[test]
+    fn mcp_conflict_policy_parses() {
+        assert_eq!(Config::default().mcp.on_conflict, McpConflictPolicy::Prefix);
+        let config: Config = toml::from_str("[mcp]\non_conflict = \"skip\"\n").unwrap();
+        assert_eq!(config.mcp.on_conflict, McpConflictPolicy::Skip);
+        assert!(toml::from_str::<Config>("[mcp]\non_conflict = \"merge\"\n").is_err());
+    }
+
+    #[test]
+    fn mcp_resync_defaults_on_and_zero_disables_it() {
+        let every = Config::default().mcp.resync_every();
+        assert_eq!(every, Some(std::time::Duration::from_secs(300)));
+        let config: Config = toml::from_str("[mcp]\nresync_minutes = 0\n").unwrap();
+        assert_eq!(config.mcp.resync_every(), None);
+    }
+
+    #[test]
+    fn turn_budget_is_off_unless_set() {
+        assert!(Config::default().agent.per_turn_token_budget.is_none());
+        let config: Config = toml::from_str("[agent]\nper_turn_token_budget = 100000\n").unwrap();
+        assert_eq!(config.agent.per_turn_token_budget, Some(100_000));
+    }
+
+    #[test]
+    fn mcp_startup_defaults_to_eager_with_a_timeout() {
+        let mcp = Config::default().mcp;
+        assert!(!mcp.lazy);
+        assert_eq!(mcp.connect_timeout(), std::time::Duration::from_secs(30));
+        let config: Config =
+            toml::from_str("[mcp]\nlazy = true\nconnect_timeout_seconds = 0\n").unwrap();
+        assert!(config.mcp.lazy);
+        assert_eq!(
+            config.mcp.connect_timeout(),
+            std::time::Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn skill_overrides_parse() {
+        let toml_str = r#"
+[skills.overrides.peekaboo]
+enabled = false
+
+[skills.overrides.deploy]
+priority = 5
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(!config.skills.is_enabled("peekaboo"));
+        assert!(config.skills.is_enabled("deploy"));
+        assert!(config.skills.is_enabled("unlisted"));
+        assert_eq!(config.skills.priority("deploy"), 5);
+        assert_eq!(config.skills.priority("peekaboo"), 0);
+    }
+
+    #[test]
+    fn provider_headers_parsed_and_scoped_to_active_provider() {
+        let toml_str = r#"
+[llm]
+provider = "openrouter"
+
+[llm.openrouter.headers]
+X-Title = "soloclaw"
+HTTP-Referer = "https://example.com"
+
+[llm.openai.headers]
+X-Other = "nope"
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.llm.provider_headers(),
+            vec![
+                (
+                    "HTTP-Referer".to_string(),
+                    "https://example.com".to_string()
+                ),
+                ("X-Title".to_string(), "soloclaw".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn streaming_mode_parses_bool_and_auto() {
+        let parse = |v: &str| -> Result<Config, toml::de::Error> {
+            toml::from_str(&format!("[llm]\nstreaming = {}\n", v))
+        };
+        assert_eq!(parse("true").unwrap().llm.streaming, StreamingMode::On);
+        assert_eq!(parse("false").unwrap().llm.streaming, StreamingMode::Off);
+        assert_eq!(
+            parse("\"auto\"").unwrap().llm.streaming,
+            StreamingMode::Auto
+        );
+        assert!(parse("\"sometimes\"").is_err());
+        assert_eq!(LlmConfig::default().streaming, StreamingMode::On);
+    }
+
+    #[test]
+    fn fallbacks_parse_in_order_and_keep_provider_settings() {
+        let config: Config = toml::from_str(
+            r#"
+            [llm]
+            fallbacks = [{ provider = "openai", model = "gpt-5.2" }, { provider = "ollama", model = "llama3.2" }]
+
+            [llm.openai]
+            base_url = "https://proxy.example/v1"
+            "#,
+        )
+        .unwrap();
+        let names: Vec<String> = config.llm.fallbacks.iter().map(|f| f.to_string()).collect();
+        assert_eq!(names, ["openai/gpt-5.2", "ollama/llama3.2"]);
+
+        let fallback = config.llm.for_fallback(&config.llm.fallbacks[0]);
+        assert_eq!(fallback.provider, "openai");
+        assert_eq!(fallback.model, "gpt-5.2");
+        assert_eq!(
+            fallback.openai.base_url.as_deref(),
+            Some("https://proxy.example/v1")
+        );
+        assert!(LlmConfig::default().fallbacks.is_empty());
+    }
+
+    #[test]
+    fn provider_headers_empty_by_default() {
+        assert!(LlmConfig::default().provider_headers().is_empty());
+    }
+
+    #[test]
+    fn provider_header_values_expand_env_vars() {
+        // SAFETY: test-only env var with a unique name.
+        unsafe { std::env::set_var("SOLOCLAW_TEST_PROXY_TOKEN", "s3cret") };
+        let toml_str = r#"
+[llm]
+provider = "ollama"
+
+[llm.ollama.headers]
+Authorization = "Bearer ${SOLOCLAW_TEST_PROXY_TOKEN}"
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.llm.provider_headers(),
+            vec![("Authorization".to_string(), "Bearer s3cret".to_string())]
+        );
+    }
+
+    #[test]
+    fn expand_env_vars_handles_edge_cases() {
+        assert_eq!(expand_env_vars("plain"), "plain");
+        assert_eq!(expand_env_vars("${SOLOCLAW_TEST_SURELY_UNSET}x"), "x");
+        assert_eq!(expand_env_vars("a${UNTERMINATED"), "a${UNTERMINATED");
+    }
+
     #[test]
     fn sessions_dir_is_subpath_of_data_dir() {
         let data_dir = Config::data_dir();
@@ -592,6 +2009,95 @@ max_files = 5
         assert_eq!(sessions_dir, data_dir.join("sessions"));
     }
 
+    #[test]
+    fn state_dir_resolution_order() {
+        let home = Some(PathBuf::from("/home/u"));
+        assert_eq!(
+            resolve_state_dir(Some("/xdg".into()), Some("/plat".into()), home.clone()),
+            PathBuf::from("/xdg/soloclaw")
+        );
+        // An empty XDG_STATE_HOME is treated as unset, per the XDG spec.
+        assert_eq!(
+            resolve_state_dir(Some(String::new()), Some("/plat".into()), home.clone()),
+            PathBuf::from("/plat/soloclaw")
+        );
+        assert_eq!(
+            resolve_state_dir(None, None, home),
+            PathBuf::from("/home/u/.local/state/soloclaw")
+        );
+        assert_eq!(
+            resolve_state_dir(None, None, None),
+            PathBuf::from("./soloclaw")
+        );
+    }
+
+    #[test]
+    fn logs_and_usage_live_in_state_dir() {
+        assert!(Config::logs_dir().starts_with(Config::state_dir()));
+        assert!(Config::usage_path().starts_with(Config::state_dir()));
+        assert!(Config::seen_workspaces_dir().starts_with(Config::state_dir()));
+        assert!(Config::sessions_dir().starts_with(Config::data_dir()));
+    }
+
+    #[test]
+    fn paths_summary_lists_every_location() {
+        let labels: Vec<&str> = Config::paths_summary().iter().map(|(l, _)| *l).collect();
+        for expected in [
+            "config file",
+            "secrets",
+            "approvals",
+            "sessions",
+            "state dir",
+            "logs",
+        ] {
+            assert!(labels.contains(&expected), "missing {}", expected);
+        }
+    }
+
+    #[test]
+    fn migrate_state_files_moves_logs_and_locks_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data = tmp.path().join("data");
+        let state = tmp.path().join("state");
+        std::fs::create_dir_all(data.join("logs")).unwrap();
+        std::fs::create_dir_all(data.join("sessions")).unwrap();
+        std::fs::write(data.join("logs").join("claw.log"), "log").unwrap();
+        std::fs::write(data.join("usage.jsonl"), "{}").unwrap();
+        std::fs::write(data.join("claw.lock"), "").unwrap();
+
+        let moved = migrate_state_files(&data, &state).unwrap();
+
+        assert_eq!(moved.len(), 3);
+        assert!(state.join("logs").join("claw.log").exists());
+        assert!(state.join("usage.jsonl").exists());
+        assert!(state.join("claw.lock").exists());
+        assert!(!data.join("logs").exists());
+        // Sessions stay in the data dir.
+        assert!(data.join("sessions").exists());
+        assert!(!state.join("sessions").exists());
+
+        // Second run finds nothing to do.
+        assert!(migrate_state_files(&data, &state).unwrap().is_empty());
+    }
+
+    #[test]
+    fn migrate_state_files_never_overwrites() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data = tmp.path().join("data");
+        let state = tmp.path().join("state");
+        std::fs::create_dir_all(&data).unwrap();
+        std::fs::create_dir_all(&state).unwrap();
+        std::fs::write(data.join("usage.jsonl"), "old").unwrap();
+        std::fs::write(state.join("usage.jsonl"), "new").unwrap();
+
+        assert!(migrate_state_files(&data, &state).unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(state.join("usage.jsonl")).unwrap(),
+            "new"
+        );
+        assert!(data.join("usage.jsonl").exists());
+    }
+
     #[test]
     fn data_dir_contains_app_name() {
         let data_dir = Config::data_dir();
@@ -623,6 +2129,8 @@ provider = "openai"
         assert!(config.enabled);
         assert!(config.threshold_token_limit.is_none());
         assert_eq!(config.user_message_budget_tokens, 20_000);
+        assert_eq!(config.review, CompactionReview::Auto);
+        assert_eq!(config.on_resume, OnResume::Compact);
     }
 
     #[test]
@@ -632,11 +2140,198 @@ provider = "openai"
 enabled = false
 threshold_token_limit = 100000
 user_message_budget_tokens = 10000
+retain = "both"
+review = "ask"
+on_resume = "truncate"
 "#;
         let config: Config = toml::from_str(toml_str).unwrap();
         assert!(!config.compaction.enabled);
         assert_eq!(config.compaction.threshold_token_limit, Some(100_000));
         assert_eq!(config.compaction.user_message_budget_tokens, 10_000);
+        assert_eq!(config.compaction.retain, CompactionRetain::Both);
+        assert_eq!(config.compaction.review, CompactionReview::Ask);
+        assert_eq!(config.compaction.on_resume, OnResume::Truncate);
+    }
+
+    #[test]
+    fn sessions_config_has_correct_defaults() {
+        let config = SessionsConfig::default();
+        assert_eq!(config.max_age_days, 0);
+        assert_eq!(config.max_total_bytes, 0);
+        assert_eq!(config.max_sessions, 0);
+        assert_eq!(config.max_log_bytes, 50 * 1024 * 1024);
+        assert_eq!(config.format, SessionFormat::Both);
+    }
+
+    #[test]
+    fn session_format_parsed_from_toml() {
+        let config: Config = toml::from_str("[sessions]\nformat = \"jsonl\"\n").unwrap();
+        assert_eq!(config.sessions.format, SessionFormat::Jsonl);
+        assert!(config.sessions.format.writes_jsonl());
+        assert!(!config.sessions.format.writes_json());
+        let config: Config = toml::from_str("[sessions]\nformat = \"json\"\n").unwrap();
+        assert!(!config.sessions.format.writes_jsonl());
+        assert!(toml::from_str::<Config>("[sessions]\nformat = \"xml\"\n").is_err());
+    }
+
+    #[test]
+    fn sessions_config_parsed_from_toml() {
+        let toml_str = r#"
+[sessions]
+max_age_days = 30
+max_total_bytes = 1000
+max_sessions = 3
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.sessions.max_age_days, 30);
+        assert_eq!(config.sessions.max_total_bytes, 1000);
+        assert_eq!(config.sessions.max_sessions, 3);
+        assert_eq!(config.sessions.max_log_bytes, 50 * 1024 * 1024);
+    }
+
+    #[test]
+    fn tools_config_defaults_and_parse() {
+        let config = Config::default();
+        assert_eq!(config.tools.read_retries, 2);
+        assert_eq!(config.tools.retry_delay_ms, 250);
+        let config: Config = toml::from_str("[tools]\nread_retries = 0\n").unwrap();
+        assert_eq!(config.tools.read_retries, 0);
+        assert_eq!(config.tools.retry_delay_ms, 250);
+        assert!(config.tools.cache_reads.is_empty());
+        assert!(config.tools.disabled.is_empty());
+    }
+
+    #[test]
+    fn disabled_tools_parse() {
+        let config: Config =
+            toml::from_str("[tools]\ndisabled = [\"write_file\", \"bash\"]\n").unwrap();
+        assert!(config.tools.is_disabled("write_file"));
+        assert!(config.tools.is_disabled("bash"));
+        assert!(!config.tools.is_disabled("read_file"));
+    }
+
+    #[test]
+    fn snippets_parse_from_array_of_tables() {
+        assert!(Config::default().snippets.is_empty());
+        let config: Config = toml::from_str(
+            "[[snippets]]\nkey = \"f2\"\ntext = \"run the tests\"\nsubmit = true\n\n[[snippets]]\nkey = \"f3\"\ntext = \"explain {selection}\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.snippets.len(), 2);
+        assert_eq!(config.snippets[0].key, "f2");
+        assert!(config.snippets[0].submit);
+        assert!(!config.snippets[1].submit);
+    }
+
+    #[test]
+    fn privacy_redacts_by_default() {
+        assert!(Config::default().privacy.redact_params);
+        let config: Config = toml::from_str("[privacy]\nredact_params = false\n").unwrap();
+        assert!(!config.privacy.redact_params);
+    }
+
+    #[test]
+    fn privacy_defaults_keep_the_current_behavior() {
+        let privacy = Config::default().privacy;
+        assert!(privacy.include_shell_in_prompt);
+        assert!(!privacy.include_hostname);
+        assert!(!privacy.allow_osc52);
+        assert!(!privacy.allow_notifications);
+        let config: Config = toml::from_str(&default_config_toml()).unwrap();
+        assert!(config.privacy.include_shell_in_prompt);
+        assert!(!config.privacy.allow_osc52);
+    }
+
+    #[test]
+    fn tui_config_defaults_and_parse() {
+        assert_eq!(TuiConfig::default().double_ctrl_c_ms, 500);
+        assert_eq!(TuiConfig::default().max_paste_bytes, 102400);
+        let config: Config = toml::from_str("[tui]\ndouble_ctrl_c_ms = 800\n").unwrap();
+        assert_eq!(config.tui.double_ctrl_c_ms, 800);
+        assert_eq!(config.tui.paste_insert_lines, 100);
+        assert_eq!(config.tui.tool_verbosity, ToolVerbosity::Full);
+
+        let config: Config = toml::from_str("[tui]\ntool_verbosity = \"compact\"\n").unwrap();
+        assert_eq!(config.tui.tool_verbosity, ToolVerbosity::Compact);
+        assert!(toml::from_str::<Config>("[tui]\ntool_verbosity = \"terse\"\n").is_err());
+
+        assert_eq!(config.tui.keymap, Keymap::Default);
+        let config: Config = toml::from_str("[tui]\nkeymap = \"vim\"\n").unwrap();
+        assert_eq!(config.tui.keymap, Keymap::Vim);
+    }
+
+    #[test]
+    fn reasoning_effort_parses_lowercase_levels() {
+        assert_eq!(Config::default().llm.reasoning_effort, None);
+        let config: Config =
+            toml::from_str("[llm]\nreasoning_effort = \"high\"\nthinking_budget_tokens = 8000\n")
+                .unwrap();
+        assert_eq!(config.llm.reasoning_effort, Some(Effort::High));
+        assert_eq!(config.llm.thinking_budget_tokens, 8000);
+        assert!(toml::from_str::<Config>("[llm]\nreasoning_effort = \"max\"\n").is_err());
+    }
+
+    #[test]
+    fn set_toml_value_replaces_an_existing_assignment() {
+        let content = "[llm]\nmodel = \"x\"\n\n[approval]\n# the level\nsecurity = \"allowlist\"\nask = \"on-miss\"\n";
+        let updated = set_toml_value(content, "approval", "security", "\"full\"");
+        assert_eq!(
+            updated,
+            "[llm]\nmodel = \"x\"\n\n[approval]\n# the level\nsecurity = \"full\"\nask = \"on-miss\"\n"
+        );
+    }
+
+    #[test]
+    fn set_toml_value_adds_missing_keys_and_sections() {
+        let updated = set_toml_value(
+            "[approval]\nask = \"on-miss\"\n[tui]\n",
+            "approval",
+            "security",
+            "\"deny\"",
+        );
+        assert_eq!(
+            updated,
+            "[approval]\nsecurity = \"deny\"\nask = \"on-miss\"\n[tui]\n"
+        );
+
+        let updated = set_toml_value("[llm]\nmodel = \"x\"\n", "approval", "security", "\"full\"");
+        assert_eq!(
+            updated,
+            "[llm]\nmodel = \"x\"\n\n[approval]\nsecurity = \"full\"\n"
+        );
+    }
+
+    #[test]
+    fn saved_approval_security_loads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, default_config_toml()).unwrap();
+
+        save_approval_security(&path, SecurityLevel::Full).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let config: Config = toml::from_str(&content).unwrap();
+        assert_eq!(config.approval.security, "full");
+        assert_eq!(config.approval.ask, "on-miss");
+        assert!(content.contains("# Emoji in the header"));
+    }
+
+    #[test]
+    fn default_config_toml_parses() {
+        let config: Config = toml::from_str(&default_config_toml()).unwrap();
+        // Left to approvals.json until the user picks a level.
+        let (_, provenance) = Config::parse_with_provenance(&default_config_toml()).unwrap();
+        assert_eq!(provenance.source("security"), Some(Source::Default));
+        assert_eq!(config.sessions.max_age_days, 0);
+        assert_eq!(config.sessions.max_log_bytes, 50 * 1024 * 1024);
+        assert_eq!(config.sessions.autosave_tool_results, 10);
+        assert_eq!(config.sessions.autosave_seconds, 60);
+        assert_eq!(config.sessions.fsync_every_writes, 1);
+        assert_eq!(config.sessions.pending_tool_calls, PendingToolCalls::Ask);
+        assert_eq!(config.sessions.stale_after_hours, 72);
+        assert!(config.sessions.stale_context_note);
+        assert_eq!(config.llm.request_timeout_seconds, 600);
+        assert_eq!(config.llm.stream_idle_timeout_seconds, 120);
     }
 
     #[test]
@@ -646,4 +2341,254 @@ user_message_budget_tokens = 10000
         assert!(config.compaction.threshold_token_limit.is_none());
         assert_eq!(config.compaction.user_message_budget_tokens, 20_000);
     }
+
+    #[test]
+    fn mcp_server_enabled_defaults_to_true() {
+        let file: McpConfigFile = serde_json::from_str(
+            r#"{"mcpServers": {
+                "on": {"command": "a"},
+                "off": {"command": "b", "enabled": false}
+            }}"#,
+        )
+        .unwrap();
+        assert!(file.mcp_servers["on"].enabled);
+        assert!(!file.mcp_servers["off"].enabled);
+    }
+
+    #[test]
+    fn disabled_mcp_servers_are_not_connected() {
+        let servers = parse_mcp_configs(
+            r#"{"mcpServers": {
+                "zeta": {"command": "z"},
+                "off": {"command": "b", "args": ["--x"], "enabled": false},
+                "alpha": {"command": "a", "enabled": true}
+            }}"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = servers.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["alpha", "zeta"]);
+    }
+
+    #[test]
+    fn mcp_server_descriptions_are_optional() {
+        let content = r#"{"mcpServers": {
+            "github": {"command": "gh-mcp", "description": "  Issues and PRs for this repo "},
+            "fs": {"command": "fs-mcp"},
+            "blank": {"command": "b", "description": ""}
+        }}"#;
+        let descriptions = parse_mcp_descriptions(content).unwrap();
+        assert_eq!(descriptions.len(), 1);
+        assert_eq!(descriptions["github"], "Issues and PRs for this repo");
+        // The new key doesn't affect the server configs.
+        assert_eq!(parse_mcp_configs(content).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn provenance_reports_file_and_default_sources() {
+        let (config, provenance) =
+            Config::parse_with_provenance("[approval]\nsecurity = \"deny\"\n").unwrap();
+        assert_eq!(config.approval.security, "deny");
+        assert_eq!(provenance.source("security"), Some(Source::GlobalFile));
+        assert_eq!(provenance.source("model"), Some(Source::Default));
+        assert_eq!(provenance.source("provider"), Some(Source::Default));
+        assert_eq!(provenance.summary(), None);
+    }
+
+    #[test]
+    fn cli_overrides_are_recorded_with_their_flag() {
+        let (mut config, mut provenance) =
+            Config::parse_with_provenance("[approval]\nsecurity = \"deny\"\n").unwrap();
+        CliOverrides {
+            model: Some("claude-opus-4".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut provenance, |_| false)
+        .unwrap();
+
+        assert_eq!(provenance.source("model"), Some(Source::Cli("--model")));
+        assert_eq!(provenance.source("provider"), Some(Source::Default));
+        assert_eq!(
+            provenance.summary().unwrap(),
+            "provider: anthropic (from default), model: claude-opus-4 (from --model), \
+             security: deny (from config file)"
+        );
+    }
+
+    #[test]
+    fn model_flag_alone_switches_to_the_provider_it_names() {
+        let (mut config, mut provenance) = Config::parse_with_provenance("").unwrap();
+        CliOverrides {
+            model: Some("gpt-5.2".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut provenance, |_| panic!("no probe for gpt"))
+        .unwrap();
+
+        assert_eq!(config.llm.provider, "openai");
+        assert_eq!(config.llm.model, "gpt-5.2");
+        assert_eq!(
+            provenance.summary().unwrap(),
+            "provider: openai (from --model, inferred), model: gpt-5.2 (from --model)"
+        );
+    }
+
+    #[test]
+    fn provider_flag_wins_over_inference() {
+        let mut config = Config::default();
+        CliOverrides {
+            provider: Some("openrouter".to_string()),
+            model: Some("gpt-5.2".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut Provenance::default(), |_| {
+            panic!("no probe")
+        })
+        .unwrap();
+        assert_eq!(config.llm.provider, "openrouter");
+    }
+
+    #[test]
+    fn unreachable_ollama_makes_open_weight_models_ambiguous() {
+        let mut config = Config::default();
+        let err = CliOverrides {
+            model: Some("llama3.2".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut Provenance::default(), |_| false)
+        .unwrap_err();
+        assert!(err.to_string().contains("pass --provider"));
+
+        let mut config = Config::default();
+        CliOverrides {
+            model: Some("qwen2.5-coder".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut Provenance::default(), |_| true)
+        .unwrap();
+        assert_eq!(config.llm.provider, "ollama");
+
+        // A configured ollama provider needs no probe.
+        let (mut config, mut provenance) =
+            Config::parse_with_provenance("[llm]\nprovider = \"ollama\"\n").unwrap();
+        CliOverrides {
+            model: Some("mistral".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut provenance, |_| panic!("no probe"))
+        .unwrap();
+        assert_eq!(config.llm.provider, "ollama");
+        assert_eq!(provenance.source("provider"), Some(Source::GlobalFile));
+    }
+
+    #[test]
+    fn infer_provider_covers_every_family() {
+        use ProviderInference::*;
+        let cases: &[(&str, bool, ProviderInference)] = &[
+            ("claude-sonnet-4-5-20250929", false, Provider("anthropic")),
+            ("Claude-Opus-4", false, Provider("anthropic")),
+            ("gpt-5.2", false, Provider("openai")),
+            ("gpt-4o-mini", false, Provider("openai")),
+            ("o4-mini", false, Provider("openai")),
+            ("gemini-2.5-pro", false, Provider("gemini")),
+            ("anthropic/claude-sonnet-4", false, Provider("openrouter")),
+            ("meta-llama/llama-3.1-70b", true, Provider("openrouter")),
+            ("llama3.2", true, Provider("ollama")),
+            ("mistral-small", true, Provider("ollama")),
+            ("qwen2.5-coder:7b", true, Provider("ollama")),
+            ("llama3.2", false, Ambiguous),
+            ("mistral-small", false, Ambiguous),
+            ("qwen3", false, Ambiguous),
+            ("deepseek-r1", true, Unknown),
+            ("my-finetune", false, Unknown),
+            ("", false, Unknown),
+        ];
+        for (model, reachable, expected) in cases {
+            assert_eq!(&infer_provider(model, *reachable), expected, "{}", model);
+        }
+    }
+
+    #[test]
+    fn ollama_is_unreachable_on_a_closed_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let open = OllamaConfig {
+            base_url: format!("http://127.0.0.1:{}", port),
+            ..OllamaConfig::default()
+        };
+        assert!(ollama_reachable(&open));
+        drop(listener);
+        assert!(!ollama_reachable(&open));
+    }
+
+    #[test]
+    fn provider_flag_without_model_sets_the_provider_default_model() {
+        let (mut config, mut provenance) =
+            Config::parse_with_provenance("[llm]\nmodel = \"claude-x\"\n").unwrap();
+        assert_eq!(provenance.source("model"), Some(Source::GlobalFile));
+        CliOverrides {
+            provider: Some("openai".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut config, &mut provenance, |_| false)
+        .unwrap();
+
+        assert_eq!(config.llm.model, default_model_for_provider("openai"));
+        assert_eq!(
+            provenance.source("provider"),
+            Some(Source::Cli("--provider"))
+        );
+        assert_eq!(provenance.source("model"), Some(Source::Cli("--provider")));
+    }
+
+    #[test]
+    fn home_root_and_home_parent_are_broad_workspaces() {
+        let home = Path::new("/home/alex");
+        assert_eq!(
+            broad_workspace(home, Some(home)),
+            Some(BroadWorkspace::Home)
+        );
+        assert_eq!(
+            broad_workspace(Path::new("/"), Some(home)),
+            Some(BroadWorkspace::Root)
+        );
+        assert_eq!(
+            broad_workspace(Path::new("/home"), Some(home)),
+            Some(BroadWorkspace::HomeParent)
+        );
+        assert_eq!(
+            broad_workspace(Path::new("/"), None),
+            Some(BroadWorkspace::Root)
+        );
+    }
+
+    #[test]
+    fn project_under_home_is_not_broad() {
+        let home = Path::new("/home/alex");
+        assert_eq!(
+            broad_workspace(Path::new("/home/alex/code/app"), Some(home)),
+            None
+        );
+        assert_eq!(broad_workspace(Path::new("/home/sam"), Some(home)), None);
+        assert_eq!(broad_workspace(Path::new("/srv/app"), None), None);
+    }
+
+    #[test]
+    fn allow_home_silences_the_guard() {
+        let home = Path::new("/home/alex");
+        let strict = WorkspaceConfig::default();
+        assert_eq!(strict.guard(home, Some(home)), Some(BroadWorkspace::Home));
+
+        let mut config = Config::default();
+        CliOverrides {
+            allow_home: true,
+            ..Default::default()
+        }
+        .apply(&mut config, &mut Provenance::default(), |_| false)
+        .unwrap();
+        assert_eq!(config.workspace.guard(home, Some(home)), None);
+        assert_eq!(config.workspace.guard(Path::new("/"), Some(home)), None);
+
+        let config: Config = toml::from_str("[workspace]\nallow_home = true\n").unwrap();
+        assert!(config.workspace.allow_home);
+    }
 }
@@ -0,0 +1,310 @@
+// ABOUTME: `claw doctor` — checks config, credentials, approvals, data dirs, and .mcp.json before a run.
+// ABOUTME: Each check is a plain function over paths and values, so the report is testable with fixtures.
+
+use std::path::Path;
+
+use crate::approval::{ApprovalsFile, SecurityLevel};
+use crate::config::{Config, LlmConfig, find_mcp_config, load_env_file, parse_mcp_configs};
+
+/// Providers `create_client` knows how to build.
+const KNOWN_PROVIDERS: &[&str] = &["anthropic", "openai", "gemini", "openrouter", "ollama"];
+
+/// The outcome of one doctor check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub label: &'static str,
+    pub passed: bool,
+    /// What was found, or what is wrong and how to fix it.
+    pub detail: String,
+}
+
+impl Check {
+    fn pass(label: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            label,
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(label: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            label,
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Parse and validate the config file. A missing file passes with the
+/// defaults, which are returned for the checks that depend on them.
+pub fn check_config(path: &Path) -> (Check, Option<Config>) {
+    const LABEL: &str = "config";
+    if !path.exists() {
+        let detail = format!("{} not found; using defaults", path.display());
+        return (Check::pass(LABEL, detail), Some(Config::default()));
+    }
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            return (
+                Check::fail(LABEL, format!("{}: {}", path.display(), e)),
+                None,
+            );
+        }
+    };
+    let config = match Config::parse_with_provenance(&content) {
+        Ok((config, _)) => config,
+        Err(e) => {
+            return (
+                Check::fail(LABEL, format!("{}: {}", path.display(), e)),
+                None,
+            );
+        }
+    };
+    match validate_config(&config) {
+        Ok(()) => (Check::pass(LABEL, path.display().to_string()), Some(config)),
+        Err(problem) => (
+            Check::fail(LABEL, format!("{}: {}", path.display(), problem)),
+            Some(config),
+        ),
+    }
+}
+
+/// Settings that parse but can't work.
+fn validate_config(config: &Config) -> Result<(), String> {
+    if !KNOWN_PROVIDERS.contains(&config.llm.provider.as_str()) {
+        return Err(format!(
+            "unknown provider '{}' (expected {})",
+            config.llm.provider,
+            KNOWN_PROVIDERS.join(", ")
+        ));
+    }
+    if config.llm.model.trim().is_empty() {
+        return Err("[llm] model is empty".to_string());
+    }
+    if SecurityLevel::parse(&config.approval.security).is_none() {
+        return Err(format!(
+            "unknown security level '{}' (expected deny, allowlist, full)",
+            config.approval.security
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the selected provider has an API key, in the environment (`env`
+/// says whether a variable is set) or in the secrets file.
+pub fn check_credential(llm: &LlmConfig, env: impl Fn(&str) -> bool, secrets: &Path) -> Check {
+    const LABEL: &str = "credential";
+    let Some(var) = llm.api_key_var() else {
+        return Check::pass(LABEL, format!("{} needs no API key", llm.provider));
+    };
+    if env(var) {
+        return Check::pass(LABEL, format!("{} is set in the environment", var));
+    }
+    match load_env_file(secrets) {
+        Ok(map) if map.get(var).is_some_and(|v| !v.is_empty()) => {
+            Check::pass(LABEL, format!("{} is in {}", var, secrets.display()))
+        }
+        Ok(_) => Check::fail(
+            LABEL,
+            format!(
+                "{} is not set for provider {}; export it or run `claw setup`",
+                var, llm.provider
+            ),
+        ),
+        Err(e) => Check::fail(LABEL, format!("{}: {}", secrets.display(), e)),
+    }
+}
+
+/// Whether the approvals file is valid. A missing file passes.
+pub fn check_approvals(path: &Path) -> Check {
+    const LABEL: &str = "approvals";
+    if !path.exists() {
+        return Check::pass(
+            LABEL,
+            format!("{} not found; using defaults", path.display()),
+        );
+    }
+    match ApprovalsFile::load(path) {
+        Ok(file) => Check::pass(
+            LABEL,
+            format!("{} ({} tools configured)", path.display(), file.tools.len()),
+        ),
+        Err(e) => Check::fail(LABEL, format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Whether files can be created in `dir`, creating it if needed.
+pub fn check_writable(label: &'static str, dir: &Path) -> Check {
+    let probe = dir.join(".claw-doctor-probe");
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::pass(label, dir.display().to_string()),
+        Err(e) => Check::fail(label, format!("{} is not writable: {}", dir.display(), e)),
+    }
+}
+
+/// Whether `.mcp.json` parses, when there is one.
+pub fn check_mcp(path: Option<&Path>) -> Check {
+    const LABEL: &str = "mcp";
+    let Some(path) = path else {
+        return Check::pass(LABEL, "no .mcp.json");
+    };
+    let parsed = std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| parse_mcp_configs(&content));
+    match parsed {
+        Ok(servers) => Check::pass(
+            LABEL,
+            format!("{} ({} servers enabled)", path.display(), servers.len()),
+        ),
+        Err(e) => Check::fail(LABEL, format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Run every check against the real config, data, and workspace locations.
+pub fn run_checks() -> Vec<Check> {
+    let (config_check, config) = check_config(&Config::resolved_config_path());
+    let mut checks = vec![config_check];
+    if let Some(config) = &config {
+        checks.push(check_credential(
+            &config.llm,
+            |var| std::env::var(var).is_ok_and(|v| !v.is_empty()),
+            &Config::secrets_env_path(),
+        ));
+    }
+    checks.push(check_approvals(&Config::approvals_path()));
+    checks.push(check_writable("sessions", &Config::sessions_dir()));
+    checks.push(check_writable("state", &Config::state_dir()));
+    checks.push(check_mcp(find_mcp_config().as_deref()));
+    checks
+}
+
+/// One line per check, marked pass or fail.
+pub fn report(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| {
+            let mark = if check.passed { "\u{2705}" } else { "\u{274c}" };
+            format!("{} {:<11} {}", mark, check.label, check.detail)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_checks_parse_and_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let (check, config) = check_config(&path);
+        assert!(check.passed, "a missing config uses defaults");
+        assert!(config.is_some());
+
+        std::fs::write(&path, "[llm]\nprovider = \"openai\"\nmodel = \"gpt-5.2\"\n").unwrap();
+        let (check, config) = check_config(&path);
+        assert!(check.passed, "{}", check.detail);
+        assert_eq!(config.unwrap().llm.provider, "openai");
+
+        std::fs::write(&path, "[llm\nprovider = ").unwrap();
+        let (check, config) = check_config(&path);
+        assert!(!check.passed);
+        assert!(config.is_none());
+
+        std::fs::write(&path, "[llm]\nprovider = \"mystery\"\n").unwrap();
+        let (check, _) = check_config(&path);
+        assert!(!check.passed);
+        assert!(check.detail.contains("unknown provider 'mystery'"));
+
+        std::fs::write(&path, "[approval]\nsecurity = \"yolo\"\n").unwrap();
+        let (check, _) = check_config(&path);
+        assert!(check.detail.contains("unknown security level 'yolo'"));
+    }
+
+    #[test]
+    fn credential_comes_from_env_or_secrets_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = dir.path().join("secrets.env");
+        let llm = LlmConfig {
+            provider: "anthropic".to_string(),
+            ..LlmConfig::default()
+        };
+
+        let check = check_credential(&llm, |var| var == "ANTHROPIC_API_KEY", &secrets);
+        assert!(check.passed);
+        assert!(check.detail.contains("environment"));
+
+        let check = check_credential(&llm, |_| false, &secrets);
+        assert!(!check.passed);
+        assert!(check.detail.contains("ANTHROPIC_API_KEY is not set"));
+
+        std::fs::write(&secrets, "# keys\nANTHROPIC_API_KEY=sk-test\n").unwrap();
+        let check = check_credential(&llm, |_| false, &secrets);
+        assert!(check.passed);
+        assert!(check.detail.contains("secrets.env"));
+
+        let ollama = LlmConfig {
+            provider: "ollama".to_string(),
+            ..LlmConfig::default()
+        };
+        assert!(check_credential(&ollama, |_| false, &dir.path().join("none")).passed);
+    }
+
+    #[test]
+    fn approvals_must_be_valid_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        assert!(check_approvals(&path).passed);
+
+        ApprovalsFile::default().save(&path).unwrap();
+        assert!(check_approvals(&path).passed);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(!check_approvals(&path).passed);
+    }
+
+    #[test]
+    fn writable_dirs_are_created_and_probed() {
+        let dir = tempfile::tempdir().unwrap();
+        let sessions = dir.path().join("data/sessions");
+        assert!(check_writable("sessions", &sessions).passed);
+        assert!(sessions.is_dir());
+        assert!(!sessions.join(".claw-doctor-probe").exists());
+
+        // A file where the directory should be can't hold anything.
+        let blocked = dir.path().join("blocked");
+        std::fs::write(&blocked, "").unwrap();
+        assert!(!check_writable("sessions", &blocked.join("sessions")).passed);
+    }
+
+    #[test]
+    fn mcp_config_must_parse_when_present() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mcp.json");
+        assert!(check_mcp(None).passed);
+
+        std::fs::write(&path, r#"{"mcpServers": {"fs": {"command": "mcp-fs"}}}"#).unwrap();
+        let check = check_mcp(Some(&path));
+        assert!(check.passed);
+        assert!(check.detail.contains("1 servers enabled"));
+
+        std::fs::write(&path, r#"{"servers": []}"#).unwrap();
+        assert!(!check_mcp(Some(&path)).passed);
+    }
+
+    #[test]
+    fn report_marks_each_check() {
+        let text = report(&[Check::pass("config", "ok"), Check::fail("mcp", "broken")]);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("\u{2705} config"));
+        assert!(lines[1].starts_with("\u{274c} mcp"));
+        assert!(lines[1].ends_with("broken"));
+    }
+}
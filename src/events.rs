@@ -0,0 +1,1036 @@
+// ABOUTME: JSON event stream — mirrors agent and user events as newline-delimited JSON.
+// ABOUTME: Tees the agent/TUI channels into a bounded, drop-oldest writer for integrations.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::agent::compaction::SummaryDecision;
+use crate::approval::ApprovalDecision;
+use crate::exit::ExitStatus;
+use crate::tools::ask_user::{CONFIRM_NO, CONFIRM_YES, QuestionStyle};
+use crate::tui::state::{AgentEvent, UserEvent};
+
+/// Lines buffered for a slow consumer before the oldest are dropped.
+pub const DEFAULT_EVENT_BUFFER: usize = 1024;
+
+/// A serializable mirror of one event, written as a single JSON line.
+///
+/// The `event` field names and the fields of each variant are a stable
+/// interface for integrations; add new variants rather than renaming.
+/// Approval, question, editor, and summary review prompts carry an `id` in
+/// place of their responder, matched by the `approval_resolved` /
+/// `question_answered` / `editor_closed` / `compaction_reviewed` that follows.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventRecord {
+    TextDelta {
+        text: String,
+    },
+    TextDone,
+    ToolCallStarted {
+        tool_call_id: String,
+        tool_name: String,
+        params_summary: String,
+    },
+    ToolCallApproved {
+        tool_call_id: String,
+        tool_name: String,
+        /// The safe-bin rule, allowlist entry, or security level that
+        /// auto-approved the call; null when the user approved it.
+        reason: Option<String>,
+    },
+    ToolCallNeedsApproval {
+        id: u64,
+        tool_call_id: String,
+        tool_name: String,
+        description: String,
+        pattern: Option<String>,
+        similar: Vec<String>,
+        /// Labels of the directory scopes offered for a file write.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        scopes: Vec<String>,
+    },
+    AskUser {
+        id: u64,
+        tool_call_id: String,
+        question: String,
+        options: Vec<String>,
+        /// "confirm" for a yes/no question; absent for an open one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        style: Option<&'static str>,
+        /// What Enter answers a confirm question with.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        default: Option<&'static str>,
+    },
+    OpenInEditor {
+        id: u64,
+        tool_call_id: String,
+        path: String,
+    },
+    ToolCallDenied {
+        tool_call_id: String,
+        tool_name: String,
+        reason: String,
+    },
+    ApprovalExpired {
+        tool_call_id: String,
+        tool_name: String,
+    },
+    ToolResult {
+        tool_call_id: String,
+        tool_name: String,
+        content: String,
+        is_error: bool,
+    },
+    Usage {
+        input_tokens: u32,
+        output_tokens: u32,
+        cache_read_tokens: u32,
+        cache_write_tokens: u32,
+    },
+    Error {
+        message: String,
+    },
+    Notice {
+        message: String,
+    },
+    Reloaded {
+        skills: Vec<SkillRecord>,
+    },
+    McpConnected {
+        server: String,
+        tools: usize,
+        tool_count: usize,
+    },
+    Forgotten {
+        nth: usize,
+    },
+    Pinned {
+        nth: usize,
+        reply: bool,
+        pinned: bool,
+    },
+    Done,
+    CompactionStarted,
+    CompactionProgress {
+        tokens: usize,
+    },
+    CompactionSummaryReady {
+        id: u64,
+        summary: String,
+    },
+    CompactionDone {
+        old_count: usize,
+        new_count: usize,
+    },
+    ResumeCompacted,
+    TurnCancelled,
+    /// The user sent a chat message.
+    UserMessage {
+        text: String,
+    },
+    /// The user asked to cancel the running turn.
+    CancelRequested,
+    /// The user asked for /reload.
+    ReloadRequested,
+    /// The user asked for /regen, with an optional steering hint.
+    RegenerateRequested {
+        hint: Option<String>,
+    },
+    /// The user changed the reasoning effort with /effort ("off" when cleared).
+    EffortChanged {
+        effort: String,
+    },
+    /// The user turned observe mode on or off with /observe.
+    ObserveChanged {
+        enabled: bool,
+    },
+    /// The user asked /forget to redact their `nth` most recent message.
+    ForgetRequested {
+        nth: usize,
+    },
+    /// The user asked /pin to pin (or unpin) their `nth` most recent
+    /// message, or with `reply` the `nth` most recent reply.
+    PinRequested {
+        nth: usize,
+        reply: bool,
+    },
+    /// The user answered the approval prompt with the same id.
+    ApprovalResolved {
+        id: u64,
+        decision: &'static str,
+    },
+    /// The user answered the question with the same id.
+    QuestionAnswered {
+        id: u64,
+        answer: String,
+    },
+    /// The editor opened with the same id was closed; `error` is set when it
+    /// couldn't run or failed.
+    EditorClosed {
+        id: u64,
+        error: Option<String>,
+    },
+    /// The user reviewed the compaction summary with the same id.
+    CompactionReviewed {
+        id: u64,
+        decision: &'static str,
+    },
+    /// Events were discarded because the consumer fell behind.
+    EventsDropped {
+        count: u64,
+    },
+    /// The session is over: the last line of the stream. `status` also
+    /// sets the exit code (0 success, 1 error, 2 every tool call denied).
+    ExitSummary {
+        status: ExitStatus,
+        turns: u64,
+        tool_calls_run: u64,
+        tool_calls_denied: u64,
+        total_tokens: u64,
+    },
+}
+
+/// A skill in a `reloaded` event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkillRecord {
+    pub name: String,
+    pub status: String,
+}
+
+/// One JSON line: the record plus a timestamp.
+#[derive(Serialize)]
+struct EventLine<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    record: &'a EventRecord,
+}
+
+impl EventRecord {
+    /// Mirror an agent event, using `id` in place of a responder.
+    pub fn from_agent_event(event: &AgentEvent, id: u64) -> Self {
+        match event {
+            AgentEvent::TextDelta(text) => EventRecord::TextDelta { text: text.clone() },
+            AgentEvent::TextDone => EventRecord::TextDone,
+            AgentEvent::ToolCallStarted {
+                tool_call_id,
+                tool_name,
+                params_summary,
+            } => EventRecord::ToolCallStarted {
+                tool_call_id: tool_call_id.clone(),
+                tool_name: tool_name.clone(),
+                params_summary: params_summary.clone(),
+            },
+            AgentEvent::ToolCallApproved {
+                tool_call_id,
+                tool_name,
+                reason,
+            } => EventRecord::ToolCallApproved {
+                tool_call_id: tool_call_id.clone(),
+                tool_name: tool_name.clone(),
+                reason: reason.as_ref().map(|r| r.to_string()),
+            },
+            AgentEvent::ToolCallNeedsApproval {
+                tool_call_id,
+                description,
+                pattern,
+                similar,
+                scopes,
+                tool_name,
+                ..
+            } => EventRecord::ToolCallNeedsApproval {
+                id,
+                tool_call_id: tool_call_id.clone(),
+                tool_name: tool_name.clone(),
+                description: description.clone(),
+                pattern: pattern.clone(),
+                similar: similar.clone(),
+                scopes: scopes.iter().map(|s| s.label.clone()).collect(),
+            },
+            AgentEvent::AskUser {
+                question,
+                tool_call_id,
+                options,
+                style,
+                ..
+            } => EventRecord::AskUser {
+                id,
+                tool_call_id: tool_call_id.clone(),
+                question: question.clone(),
+                options: options.clone(),
+                style: style.label(),
+                default: match style {
+                    QuestionStyle::Confirm { default_yes: true } => Some(CONFIRM_YES),
+                    QuestionStyle::Confirm { default_yes: false } => Some(CONFIRM_NO),
+                    QuestionStyle::Open => None,
+                },
+            },
+            AgentEvent::OpenInEditor {
+                tool_call_id, path, ..
+            } => EventRecord::OpenInEditor {
+                id,
+                tool_call_id: tool_call_id.clone(),
+                path: path.clone(),
+            },
+            AgentEvent::ToolCallDenied {
+                tool_call_id,
+                tool_name,
+                reason,
+            } => EventRecord::ToolCallDenied {
+                tool_call_id: tool_call_id.clone(),
+                tool_name: tool_name.clone(),
+                reason: reason.clone(),
+            },
+            AgentEvent::ApprovalExpired {
+                tool_call_id,
+                tool_name,
+            } => EventRecord::ApprovalExpired {
+                tool_call_id: tool_call_id.clone(),
+                tool_name: tool_name.clone(),
+            },
+            AgentEvent::ToolResult {
+                tool_call_id,
+                tool_name,
+                content,
+                is_error,
+            } => EventRecord::ToolResult {
+                tool_call_id: tool_call_id.clone(),
+                tool_name: tool_name.clone(),
+                content: content.clone(),
+                is_error: *is_error,
+            },
+            AgentEvent::Usage {
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_write_tokens,
+            } => EventRecord::Usage {
+                input_tokens: *input_tokens,
+                output_tokens: *output_tokens,
+                cache_read_tokens: *cache_read_tokens,
+                cache_write_tokens: *cache_write_tokens,
+            },
+            AgentEvent::Error(message) => EventRecord::Error {
+                message: message.clone(),
+            },
+            AgentEvent::Notice(message) => EventRecord::Notice {
+                message: message.clone(),
+            },
+            AgentEvent::Reloaded { skills } => EventRecord::Reloaded {
+                skills: skills
+                    .iter()
+                    .map(|s| SkillRecord {
+                        name: s.name.clone(),
+                        status: s.status.to_string(),
+                    })
+                    .collect(),
+            },
+            AgentEvent::McpConnected {
+                server,
+                tools,
+                tool_count,
+            } => EventRecord::McpConnected {
+                server: server.clone(),
+                tools: *tools,
+                tool_count: *tool_count,
+            },
+            AgentEvent::Forgotten { nth } => EventRecord::Forgotten { nth: *nth },
+            AgentEvent::Pinned { nth, reply, pinned } => EventRecord::Pinned {
+                nth: *nth,
+                reply: *reply,
+                pinned: *pinned,
+            },
+            AgentEvent::Done => EventRecord::Done,
+            AgentEvent::CompactionStarted => EventRecord::CompactionStarted,
+            AgentEvent::CompactionProgress { tokens } => {
+                EventRecord::CompactionProgress { tokens: *tokens }
+            }
+            AgentEvent::CompactionSummaryReady { summary, .. } => {
+                EventRecord::CompactionSummaryReady {
+                    id,
+                    summary: summary.clone(),
+                }
+            }
+            AgentEvent::CompactionDone {
+                old_count,
+                new_count,
+            } => EventRecord::CompactionDone {
+                old_count: *old_count,
+                new_count: *new_count,
+            },
+            AgentEvent::ResumeCompacted => EventRecord::ResumeCompacted,
+            AgentEvent::TurnCancelled => EventRecord::TurnCancelled,
+        }
+    }
+
+    /// Mirror a user event; None for events integrations don't need (quit).
+    pub fn from_user_event(event: &UserEvent) -> Option<Self> {
+        match event {
+            UserEvent::Message(text) => Some(EventRecord::UserMessage { text: text.clone() }),
+            UserEvent::Cancel => Some(EventRecord::CancelRequested),
+            UserEvent::Reload => Some(EventRecord::ReloadRequested),
+            UserEvent::Regenerate { hint } => {
+                Some(EventRecord::RegenerateRequested { hint: hint.clone() })
+            }
+            UserEvent::SetEffort(effort) => Some(EventRecord::EffortChanged {
+                effort: effort.map_or("off", |e| e.as_str()).to_string(),
+            }),
+            UserEvent::SetObserve(enabled) => {
+                Some(EventRecord::ObserveChanged { enabled: *enabled })
+            }
+            UserEvent::Forget { nth } => Some(EventRecord::ForgetRequested { nth: *nth }),
+            UserEvent::Pin { nth, reply } => Some(EventRecord::PinRequested {
+                nth: *nth,
+                reply: *reply,
+            }),
+            UserEvent::Quit => None,
+        }
+    }
+}
+
+/// Stable name for an approval decision in the event stream.
+fn decision_name(decision: ApprovalDecision) -> &'static str {
+    match decision {
+        ApprovalDecision::AllowOnce => "allow_once",
+        ApprovalDecision::AllowAlways => "allow_always",
+        ApprovalDecision::Deny => "deny",
+        ApprovalDecision::AllowTool => "allow_tool",
+        ApprovalDecision::AllowScope(_) => "allow_scope",
+    }
+}
+
+/// Bounded line queue that drops the oldest line when full.
+struct EventQueue {
+    lines: VecDeque<String>,
+    capacity: usize,
+    /// Lines dropped since the writer last reported it.
+    dropped: u64,
+    closed: bool,
+}
+
+struct Shared {
+    queue: Mutex<EventQueue>,
+    ready: Condvar,
+    /// Total lines dropped over the life of the sink.
+    dropped_total: AtomicU64,
+}
+
+/// Non-blocking handle for emitting event lines. Cloning shares the queue.
+///
+/// `emit` never waits on the consumer: a dedicated thread does the writing,
+/// and when it falls behind the oldest lines are dropped and counted.
+#[derive(Clone)]
+pub struct EventSink {
+    shared: Arc<Shared>,
+}
+
+impl EventSink {
+    /// Create a sink with the given buffer size. No writer is attached yet.
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(EventQueue {
+                    lines: VecDeque::new(),
+                    capacity: capacity.max(1),
+                    dropped: 0,
+                    closed: false,
+                }),
+                ready: Condvar::new(),
+                dropped_total: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Start a writer thread that drains the sink into `open()`'s writer.
+    ///
+    /// The writer is opened on that thread, so opening a FIFO with no reader
+    /// yet doesn't hold up startup.
+    pub fn spawn<W, F>(capacity: usize, open: F) -> Self
+    where
+        W: Write,
+        F: FnOnce() -> std::io::Result<W> + Send + 'static,
+    {
+        let sink = Self::with_capacity(capacity);
+        let shared = sink.shared.clone();
+        std::thread::spawn(move || {
+            let Ok(mut writer) = open() else {
+                return;
+            };
+            while let Some((lines, dropped)) = next_batch(&shared) {
+                if dropped > 0 {
+                    let line = serialize_line(&EventRecord::EventsDropped { count: dropped });
+                    if writeln!(writer, "{}", line).is_err() {
+                        return;
+                    }
+                }
+                for line in lines {
+                    if writeln!(writer, "{}", line).is_err() {
+                        return;
+                    }
+                }
+                if writer.flush().is_err() {
+                    return;
+                }
+            }
+        });
+        sink
+    }
+
+    /// Queue a record for writing. Never blocks on the consumer.
+    pub fn emit(&self, record: &EventRecord) {
+        let line = serialize_line(record);
+        let mut queue = self.shared.queue.lock().expect("event queue poisoned");
+        if queue.lines.len() >= queue.capacity {
+            queue.lines.pop_front();
+            queue.dropped += 1;
+            self.shared.dropped_total.fetch_add(1, Ordering::Relaxed);
+        }
+        queue.lines.push_back(line);
+        drop(queue);
+        self.shared.ready.notify_one();
+    }
+
+    /// Total number of lines dropped because the consumer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped_total.load(Ordering::Relaxed)
+    }
+
+    /// Stop the writer thread once it has drained what's queued.
+    pub fn close(&self) {
+        self.shared
+            .queue
+            .lock()
+            .expect("event queue poisoned")
+            .closed = true;
+        self.shared.ready.notify_one();
+    }
+}
+
+/// Wait for queued lines; None once the sink is closed and drained.
+fn next_batch(shared: &Shared) -> Option<(Vec<String>, u64)> {
+    let mut queue = shared.queue.lock().expect("event queue poisoned");
+    while queue.lines.is_empty() && !queue.closed {
+        queue = shared.ready.wait(queue).expect("event queue poisoned");
+    }
+    if queue.lines.is_empty() {
+        return None;
+    }
+    let dropped = std::mem::take(&mut queue.dropped);
+    Some((queue.lines.drain(..).collect(), dropped))
+}
+
+fn serialize_line(record: &EventRecord) -> String {
+    let line = EventLine {
+        timestamp: Utc::now().to_rfc3339(),
+        record,
+    };
+    serde_json::to_string(&line).unwrap_or_default()
+}
+
+/// Open the event stream target: `-` is stderr (stdout belongs to the TUI),
+/// anything else is a file or FIFO opened for appending.
+pub fn spawn_sink(target: &str) -> EventSink {
+    if target == "-" {
+        EventSink::spawn(DEFAULT_EVENT_BUFFER, || Ok(std::io::stderr()))
+    } else {
+        let path = std::path::PathBuf::from(target);
+        EventSink::spawn(DEFAULT_EVENT_BUFFER, move || {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+        })
+    }
+}
+
+/// Forward agent events to the TUI, mirroring each into the sink.
+///
+/// Prompt responders are swapped for relays that record the user's answer
+/// before passing it on to the agent loop.
+pub fn spawn_agent_tee(
+    mut from_agent: mpsc::Receiver<AgentEvent>,
+    to_tui: mpsc::Sender<AgentEvent>,
+    sink: EventSink,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut next_id: u64 = 0;
+        while let Some(event) = from_agent.recv().await {
+            next_id += 1;
+            sink.emit(&EventRecord::from_agent_event(&event, next_id));
+            let event = relay_responder(event, next_id, &sink);
+            if to_tui.send(event).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Forward user events to the agent loop, mirroring each into the sink.
+pub fn spawn_user_tee(
+    mut from_tui: mpsc::Receiver<UserEvent>,
+    to_agent: mpsc::Sender<UserEvent>,
+    sink: EventSink,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(event) = from_tui.recv().await {
+            if let Some(record) = EventRecord::from_user_event(&event) {
+                sink.emit(&record);
+            }
+            if to_agent.send(event).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Replace a prompt's responder with one that records the answer first.
+/// If the TUI drops the relay unanswered, the original is dropped too.
+fn relay_responder(event: AgentEvent, id: u64, sink: &EventSink) -> AgentEvent {
+    match event {
+        AgentEvent::ToolCallNeedsApproval {
+            tool_call_id,
+            description,
+            pattern,
+            similar,
+            scopes,
+            tool_name,
+            responder,
+        } => {
+            let (relay_tx, relay_rx) = oneshot::channel::<ApprovalDecision>();
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                if let Ok(decision) = relay_rx.await {
+                    sink.emit(&EventRecord::ApprovalResolved {
+                        id,
+                        decision: decision_name(decision),
+                    });
+                    let _ = responder.send(decision);
+                }
+            });
+            AgentEvent::ToolCallNeedsApproval {
+                tool_call_id,
+                description,
+                pattern,
+                similar,
+                scopes,
+                tool_name,
+                responder: relay_tx,
+            }
+        }
+        AgentEvent::AskUser {
+            question,
+            tool_call_id,
+            options,
+            style,
+            responder,
+        } => {
+            let (relay_tx, relay_rx) = oneshot::channel::<String>();
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                if let Ok(answer) = relay_rx.await {
+                    sink.emit(&EventRecord::QuestionAnswered {
+                        id,
+                        answer: answer.clone(),
+                    });
+                    let _ = responder.send(answer);
+                }
+            });
+            AgentEvent::AskUser {
+                question,
+                tool_call_id,
+                options,
+                style,
+                responder: relay_tx,
+            }
+        }
+        AgentEvent::OpenInEditor {
+            tool_call_id,
+            path,
+            responder,
+        } => {
+            let (relay_tx, relay_rx) = oneshot::channel::<Result<String, String>>();
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                if let Ok(outcome) = relay_rx.await {
+                    sink.emit(&EventRecord::EditorClosed {
+                        id,
+                        error: outcome.as_ref().err().cloned(),
+                    });
+                    let _ = responder.send(outcome);
+                }
+            });
+            AgentEvent::OpenInEditor {
+                tool_call_id,
+                path,
+                responder: relay_tx,
+            }
+        }
+        AgentEvent::CompactionSummaryReady { summary, responder } => {
+            let (relay_tx, relay_rx) = oneshot::channel::<SummaryDecision>();
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                if let Ok(decision) = relay_rx.await {
+                    sink.emit(&EventRecord::CompactionReviewed {
+                        id,
+                        decision: decision.name(),
+                    });
+                    let _ = responder.send(decision);
+                }
+            });
+            AgentEvent::CompactionSummaryReady {
+                summary,
+                responder: relay_tx,
+            }
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::ApprovalReason;
+    use crate::prompt::{SkillEntry, SkillStatus};
+
+    fn json(record: &EventRecord) -> serde_json::Value {
+        serde_json::to_value(record).unwrap()
+    }
+
+    #[test]
+    fn mirrors_every_agent_event_variant() {
+        let (approval_tx, _approval_rx) = oneshot::channel();
+        let (answer_tx, _answer_rx) = oneshot::channel();
+        let (confirm_tx, _confirm_rx) = oneshot::channel();
+        let (review_tx, _review_rx) = oneshot::channel();
+        let (editor_tx, _editor_rx) = oneshot::channel();
+        let cases: Vec<(AgentEvent, serde_json::Value)> = vec![
+            (
+                AgentEvent::TextDelta("hi".into()),
+                serde_json::json!({"event": "text_delta", "text": "hi"}),
+            ),
+            (
+                AgentEvent::TextDone,
+                serde_json::json!({"event": "text_done"}),
+            ),
+            (
+                AgentEvent::ToolCallStarted {
+                    tool_call_id: "tc1".into(),
+                    tool_name: "bash".into(),
+                    params_summary: "ls".into(),
+                },
+                serde_json::json!({
+                    "event": "tool_call_started", "tool_call_id": "tc1",
+                    "tool_name": "bash", "params_summary": "ls"
+                }),
+            ),
+            (
+                AgentEvent::ToolCallApproved {
+                    tool_call_id: "tc1".into(),
+                    tool_name: "bash".into(),
+                    reason: Some(ApprovalReason::AllowlistPattern("/usr/bin/cargo".into())),
+                },
+                serde_json::json!({
+                    "event": "tool_call_approved", "tool_call_id": "tc1", "tool_name": "bash",
+                    "reason": "allowlist:/usr/bin/cargo"
+                }),
+            ),
+            (
+                AgentEvent::ToolCallNeedsApproval {
+                    tool_call_id: "tc1".into(),
+                    description: "bash: rm x".into(),
+                    pattern: Some("/bin/rm".into()),
+                    similar: vec![],
+                    scopes: vec![],
+                    tool_name: "bash".into(),
+                    responder: approval_tx,
+                },
+                serde_json::json!({
+                    "event": "tool_call_needs_approval", "id": 7, "tool_call_id": "tc1",
+                    "tool_name": "bash", "description": "bash: rm x", "pattern": "/bin/rm",
+                    "similar": []
+                }),
+            ),
+            (
+                AgentEvent::AskUser {
+                    question: "Which?".into(),
+                    tool_call_id: "tc1".into(),
+                    options: vec!["a".into()],
+                    style: QuestionStyle::Open,
+                    responder: answer_tx,
+                },
+                serde_json::json!({
+                    "event": "ask_user", "id": 7, "tool_call_id": "tc1",
+                    "question": "Which?", "options": ["a"]
+                }),
+            ),
+            (
+                AgentEvent::AskUser {
+                    question: "Delete it?".into(),
+                    tool_call_id: "tc1".into(),
+                    options: vec![],
+                    style: QuestionStyle::Confirm { default_yes: false },
+                    responder: confirm_tx,
+                },
+                serde_json::json!({
+                    "event": "ask_user", "id": 7, "tool_call_id": "tc1",
+                    "question": "Delete it?", "options": [], "style": "confirm", "default": "no"
+                }),
+            ),
+            (
+                AgentEvent::OpenInEditor {
+                    tool_call_id: "tc1".into(),
+                    path: "notes.md".into(),
+                    responder: editor_tx,
+                },
+                serde_json::json!({
+                    "event": "open_in_editor", "id": 7, "tool_call_id": "tc1",
+                    "path": "notes.md"
+                }),
+            ),
+            (
+                AgentEvent::ToolCallDenied {
+                    tool_call_id: "tc1".into(),
+                    tool_name: "bash".into(),
+                    reason: "no".into(),
+                },
+                serde_json::json!({
+                    "event": "tool_call_denied", "tool_call_id": "tc1",
+                    "tool_name": "bash", "reason": "no"
+                }),
+            ),
+            (
+                AgentEvent::ApprovalExpired {
+                    tool_call_id: "tc1".into(),
+                    tool_name: "bash".into(),
+                },
+                serde_json::json!({"event": "approval_expired", "tool_call_id": "tc1", "tool_name": "bash"}),
+            ),
+            (
+                AgentEvent::ToolResult {
+                    tool_call_id: "tc1".into(),
+                    tool_name: "bash".into(),
+                    content: "out".into(),
+                    is_error: false,
+                },
+                serde_json::json!({
+                    "event": "tool_result", "tool_call_id": "tc1", "tool_name": "bash",
+                    "content": "out", "is_error": false
+                }),
+            ),
+            (
+                AgentEvent::Usage {
+                    input_tokens: 10,
+                    output_tokens: 2,
+                    cache_read_tokens: 300,
+                    cache_write_tokens: 40,
+                },
+                serde_json::json!({
+                    "event": "usage",
+                    "input_tokens": 10,
+                    "output_tokens": 2,
+                    "cache_read_tokens": 300,
+                    "cache_write_tokens": 40,
+                }),
+            ),
+            (
+                AgentEvent::Error("boom".into()),
+                serde_json::json!({"event": "error", "message": "boom"}),
+            ),
+            (
+                AgentEvent::Notice("fyi".into()),
+                serde_json::json!({"event": "notice", "message": "fyi"}),
+            ),
+            (
+                AgentEvent::Reloaded {
+                    skills: vec![SkillEntry {
+                        name: "peekaboo".into(),
+                        path: "/s/peekaboo/SKILL.md".into(),
+                        bytes: 10,
+                        status: SkillStatus::Loaded,
+                    }],
+                },
+                serde_json::json!({
+                    "event": "reloaded",
+                    "skills": [{"name": "peekaboo", "status": SkillStatus::Loaded.to_string()}]
+                }),
+            ),
+            (
+                AgentEvent::McpConnected {
+                    server: "github".into(),
+                    tools: 12,
+                    tool_count: 19,
+                },
+                serde_json::json!({
+                    "event": "mcp_connected", "server": "github", "tools": 12, "tool_count": 19
+                }),
+            ),
+            (
+                AgentEvent::Forgotten { nth: 2 },
+                serde_json::json!({"event": "forgotten", "nth": 2}),
+            ),
+            (
+                AgentEvent::Pinned {
+                    nth: 1,
+                    reply: true,
+                    pinned: true,
+                },
+                serde_json::json!({"event": "pinned", "nth": 1, "reply": true, "pinned": true}),
+            ),
+            (AgentEvent::Done, serde_json::json!({"event": "done"})),
+            (
+                AgentEvent::CompactionStarted,
+                serde_json::json!({"event": "compaction_started"}),
+            ),
+            (
+                AgentEvent::CompactionProgress { tokens: 150 },
+                serde_json::json!({"event": "compaction_progress", "tokens": 150}),
+            ),
+            (
+                AgentEvent::CompactionSummaryReady {
+                    summary: "Goal: ship it".into(),
+                    responder: review_tx,
+                },
+                serde_json::json!({"event": "compaction_summary_ready", "id": 7, "summary": "Goal: ship it"}),
+            ),
+            (
+                AgentEvent::CompactionDone {
+                    old_count: 40,
+                    new_count: 3,
+                },
+                serde_json::json!({"event": "compaction_done", "old_count": 40, "new_count": 3}),
+            ),
+            (
+                AgentEvent::ResumeCompacted,
+                serde_json::json!({"event": "resume_compacted"}),
+            ),
+            (
+                AgentEvent::TurnCancelled,
+                serde_json::json!({"event": "turn_cancelled"}),
+            ),
+        ];
+
+        for (event, expected) in &cases {
+            assert_eq!(&json(&EventRecord::from_agent_event(event, 7)), expected);
+        }
+    }
+
+    #[test]
+    fn mirrors_user_events() {
+        let record = EventRecord::from_user_event(&UserEvent::Message("hello".into())).unwrap();
+        assert_eq!(
+            json(&record),
+            serde_json::json!({"event": "user_message", "text": "hello"})
+        );
+        assert_eq!(
+            EventRecord::from_user_event(&UserEvent::Cancel),
+            Some(EventRecord::CancelRequested)
+        );
+        assert_eq!(
+            EventRecord::from_user_event(&UserEvent::Reload),
+            Some(EventRecord::ReloadRequested)
+        );
+        let regen = EventRecord::from_user_event(&UserEvent::Regenerate {
+            hint: Some("shorter".into()),
+        })
+        .unwrap();
+        assert_eq!(
+            json(&regen),
+            serde_json::json!({"event": "regenerate_requested", "hint": "shorter"})
+        );
+        assert_eq!(EventRecord::from_user_event(&UserEvent::Quit), None);
+    }
+
+    #[test]
+    fn exit_summary_names_its_status() {
+        let record = EventRecord::ExitSummary {
+            status: ExitStatus::Denied,
+            turns: 2,
+            tool_calls_run: 0,
+            tool_calls_denied: 3,
+            total_tokens: 1_500,
+        };
+        assert_eq!(
+            json(&record),
+            serde_json::json!({
+                "event": "exit_summary", "status": "denied", "turns": 2,
+                "tool_calls_run": 0, "tool_calls_denied": 3, "total_tokens": 1500,
+            })
+        );
+    }
+
+    #[test]
+    fn emit_drops_oldest_when_consumer_is_behind() {
+        // No writer attached: nothing drains the queue, and emit still returns.
+        let sink = EventSink::with_capacity(2);
+        for i in 0..5 {
+            sink.emit(&EventRecord::TextDelta {
+                text: i.to_string(),
+            });
+        }
+        assert_eq!(sink.dropped(), 3);
+
+        let queue = sink.shared.queue.lock().unwrap();
+        assert_eq!(queue.lines.len(), 2);
+        assert!(queue.lines[0].contains("\"text\":\"3\""));
+        assert!(queue.lines[1].contains("\"text\":\"4\""));
+        assert_eq!(queue.dropped, 3);
+    }
+
+    #[test]
+    fn writer_reports_dropped_count_before_remaining_lines() {
+        let sink = EventSink::with_capacity(1);
+        sink.emit(&EventRecord::Done);
+        sink.emit(&EventRecord::TurnCancelled);
+        sink.close();
+
+        let (lines, dropped) = next_batch(&sink.shared).unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("\"event\":\"turn_cancelled\""));
+        assert!(next_batch(&sink.shared).is_none());
+    }
+
+    #[tokio::test]
+    async fn tee_forwards_events_and_records_approval_resolution() {
+        let (agent_tx, agent_rx) = mpsc::channel(8);
+        let (tui_tx, mut tui_rx) = mpsc::channel(8);
+        let sink = EventSink::with_capacity(16);
+        let handle = spawn_agent_tee(agent_rx, tui_tx, sink.clone());
+
+        let (responder, decision_rx) = oneshot::channel();
+        agent_tx
+            .send(AgentEvent::ToolCallNeedsApproval {
+                tool_call_id: "tc1".into(),
+                description: "bash: rm x".into(),
+                pattern: None,
+                similar: vec![],
+                scopes: vec![],
+                tool_name: "bash".into(),
+                responder,
+            })
+            .await
+            .unwrap();
+
+        let Some(AgentEvent::ToolCallNeedsApproval { responder, .. }) = tui_rx.recv().await else {
+            panic!("expected approval prompt");
+        };
+        responder.send(ApprovalDecision::Deny).unwrap();
+        assert_eq!(decision_rx.await.unwrap(), ApprovalDecision::Deny);
+
+        drop(agent_tx);
+        handle.await.unwrap();
+
+        let queue = sink.shared.queue.lock().unwrap();
+        assert_eq!(queue.lines.len(), 2);
+        assert!(queue.lines[0].contains("\"event\":\"tool_call_needs_approval\""));
+        assert!(queue.lines[1].contains("\"event\":\"approval_resolved\""));
+        assert!(queue.lines[1].contains("\"decision\":\"deny\""));
+        assert!(queue.lines[1].contains("\"id\":1"));
+    }
+}
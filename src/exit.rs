@@ -0,0 +1,177 @@
+// ABOUTME: Exit summary — tallies a session's turns and tool calls into an overall status on quit.
+// ABOUTME: The status goes into a final `exit_summary` event and picks the process exit code.
+
+use serde::Serialize;
+
+use crate::tui::state::AgentEvent;
+
+/// How the session ended, for scripts wrapping claw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    /// The last turn finished without an error.
+    Success,
+    /// Tools were asked for, and every one was denied or timed out.
+    Denied,
+    /// The last turn ended in an error, or the app itself failed.
+    Error,
+}
+
+impl ExitStatus {
+    /// The process exit code: 0 for success, 1 for an error, 2 when every
+    /// tool call was denied.
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Error => 1,
+            ExitStatus::Denied => 2,
+        }
+    }
+
+    /// Why the session didn't succeed, in words; None on success.
+    pub fn reason(self) -> Option<&'static str> {
+        match self {
+            ExitStatus::Success => None,
+            ExitStatus::Error => Some("the last turn ended in an error"),
+            ExitStatus::Denied => Some("every tool call was denied"),
+        }
+    }
+}
+
+/// Running counts of what happened over a session, fed every agent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionTally {
+    /// Turns that reached `Done`, cancelled ones included. A `Done` with no
+    /// turn running (startup compaction) isn't one.
+    pub turns: u64,
+    pub tool_calls_run: u64,
+    /// Calls denied by policy or the user, or whose approval timed out.
+    pub tool_calls_denied: u64,
+    /// Whether a turn is running: from `start_turn` to its `Done`.
+    in_turn: bool,
+    /// Whether the running turn has reported an error yet.
+    turn_failed: bool,
+    /// Whether the most recent finished turn ended in an error.
+    last_turn_failed: bool,
+}
+
+impl SessionTally {
+    /// A message or regeneration went to the agent, starting a turn.
+    pub fn start_turn(&mut self) {
+        self.in_turn = true;
+        self.turn_failed = false;
+    }
+
+    /// Count `event`. Errors outside a turn (a failed /reload, a startup
+    /// notice) don't fail one.
+    pub fn record(&mut self, event: &AgentEvent) {
+        match event {
+            AgentEvent::ToolResult { .. } => self.tool_calls_run += 1,
+            AgentEvent::ToolCallDenied { .. } | AgentEvent::ApprovalExpired { .. } => {
+                self.tool_calls_denied += 1
+            }
+            AgentEvent::Error(_) if self.in_turn => self.turn_failed = true,
+            AgentEvent::Done if self.in_turn => {
+                self.in_turn = false;
+                self.turns += 1;
+                self.last_turn_failed = std::mem::take(&mut self.turn_failed);
+            }
+            _ => {}
+        }
+    }
+
+    /// The overall status; `fatal` when the app stopped on an error of its
+    /// own. A failed turn the user moved on from doesn't count against it.
+    pub fn status(&self, fatal: bool) -> ExitStatus {
+        if fatal || self.last_turn_failed || self.turn_failed {
+            ExitStatus::Error
+        } else if self.tool_calls_denied > 0 && self.tool_calls_run == 0 {
+            ExitStatus::Denied
+        } else {
+            ExitStatus::Success
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn denied() -> AgentEvent {
+        AgentEvent::ToolCallDenied {
+            tool_call_id: "t1".into(),
+            tool_name: "bash".into(),
+            reason: "denied by user".into(),
+        }
+    }
+
+    fn ran() -> AgentEvent {
+        AgentEvent::ToolResult {
+            tool_call_id: "t2".into(),
+            tool_name: "read_file".into(),
+            content: "ok".into(),
+            is_error: false,
+        }
+    }
+
+    /// The tally after one turn that produced `events`.
+    fn tally(events: &[AgentEvent]) -> SessionTally {
+        let mut tally = SessionTally::default();
+        tally.start_turn();
+        for event in events {
+            tally.record(event);
+        }
+        tally
+    }
+
+    #[test]
+    fn status_follows_the_tally() {
+        assert_eq!(tally(&[]).status(false), ExitStatus::Success);
+        assert_eq!(
+            tally(&[ran(), AgentEvent::Done]).status(false),
+            ExitStatus::Success
+        );
+        assert_eq!(
+            tally(&[ran(), AgentEvent::Done]).status(true),
+            ExitStatus::Error
+        );
+
+        let all_denied = tally(&[denied(), denied(), AgentEvent::Done]);
+        assert_eq!(all_denied.tool_calls_denied, 2);
+        assert_eq!(all_denied.status(false), ExitStatus::Denied);
+        assert_eq!(
+            tally(&[denied(), ran(), AgentEvent::Done]).status(false),
+            ExitStatus::Success
+        );
+    }
+
+    #[test]
+    fn only_the_last_turn_error_decides() {
+        let failed = [AgentEvent::Error("Stream error".into()), AgentEvent::Done];
+        assert_eq!(tally(&failed).status(false), ExitStatus::Error);
+
+        let mut recovered = tally(&failed);
+        recovered.start_turn();
+        recovered.record(&AgentEvent::Done);
+        assert_eq!(recovered.turns, 2);
+        assert_eq!(recovered.status(false), ExitStatus::Success);
+    }
+
+    #[test]
+    fn events_outside_a_turn_dont_count_as_one() {
+        let mut tally = SessionTally::default();
+        // Settling a resumed session's tool calls ends with a Done of its
+        // own, and startup compaction with ResumeCompacted.
+        tally.record(&AgentEvent::Done);
+        tally.record(&AgentEvent::ResumeCompacted);
+        assert_eq!(tally.turns, 0);
+
+        tally.record(&AgentEvent::Error("reload failed".into()));
+        assert_eq!(tally.status(false), ExitStatus::Success);
+
+        tally.start_turn();
+        tally.record(&AgentEvent::Done);
+        assert_eq!(tally.turns, 1);
+        assert_eq!(tally.status(false), ExitStatus::Success);
+    }
+}
@@ -5,7 +5,13 @@ pub mod agent;
 pub mod app;
 pub mod approval;
 pub mod config;
+pub mod doctor;
+pub mod events;
+pub mod exit;
 pub mod prompt;
+pub mod redact;
 pub mod session;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod tools;
 pub mod tui;
@@ -2,7 +2,7 @@
 // ABOUTME: Parses CLI args, loads config, and launches the app.
 
 use clap::Parser;
-use soloclaw::{app, config};
+use soloclaw::{app, approval::ApprovalsFile, config, doctor, exit::ExitStatus, session};
 
 /// TUI agent with layered tool approval.
 #[derive(Parser)]
@@ -15,7 +15,7 @@ struct Cli {
     #[arg(long)]
     provider: Option<String>,
 
-    /// Model name to use.
+    /// Model name to use; without --provider, also picks the provider it implies.
     #[arg(long)]
     model: Option<String>,
 
@@ -23,41 +23,194 @@ struct Cli {
     #[arg(long)]
     security: Option<String>,
 
+    /// Run from the home directory or filesystem root with normal defaults.
+    #[arg(long)]
+    allow_home: bool,
+
+    /// Send this as the first message on startup (`-` reads it from stdin).
+    #[arg(long, value_name = "TEXT|-")]
+    prompt: Option<String>,
+
     /// Start a fresh session instead of resuming an existing one.
     #[arg(long)]
     fresh: bool,
+
+    /// Skip connecting to MCP servers from .mcp.json.
+    #[arg(long)]
+    no_mcp: bool,
+
+    /// Refuse every tool call, so the model explains what it would run instead.
+    #[arg(long)]
+    observe: bool,
+
+    /// Write every agent and user event as JSON lines to a file or FIFO (`-` for stderr).
+    #[arg(long, value_name = "PATH|-")]
+    json_events: Option<String>,
 }
 
 #[derive(clap::Subcommand)]
 enum Command {
     /// Initialize XDG config and secrets for soloclaw.
-    Setup,
+    Setup {
+        /// Print every config, data, and state path instead of running setup.
+        #[arg(long)]
+        print_paths: bool,
+    },
+    /// Check config, credentials, approvals, data dirs, and .mcp.json.
+    Doctor,
+    /// Manage persisted session data.
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsCommand,
+    },
+    /// List approvals.json entries and manage the denylist.
+    Approvals {
+        #[command(subcommand)]
+        action: ApprovalsCommand,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum ApprovalsCommand {
+    /// Print every allowlist and denylist entry.
+    List,
+    /// Always deny calls matching a glob pattern, before any allow rule or bypass.
+    Deny {
+        /// Glob matched against tool names, bash executables, and write targets.
+        pattern: String,
+        /// Only deny for this tool (default: every tool).
+        #[arg(long)]
+        tool: Option<String>,
+    },
+    /// Remove a denylist pattern.
+    Undeny {
+        pattern: String,
+        /// The tool the pattern was added for (default: the global denylist).
+        #[arg(long)]
+        tool: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum SessionsCommand {
+    /// Delete old session data according to the [sessions] limits.
+    Prune {
+        /// List what would be removed without deleting anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    if matches!(cli.command, Some(Command::Setup)) {
+    if let Some(Command::Setup { print_paths }) = cli.command {
+        if print_paths {
+            for (label, path) in config::Config::paths_summary() {
+                println!("{:<12} {}", label, path.display());
+            }
+            return Ok(());
+        }
         return config::run_setup();
     }
 
-    let mut config = config::Config::load()?;
+    // Before loading config, which fails on a broken file and writes a
+    // default one when there is none.
+    if let Some(Command::Doctor) = cli.command {
+        let checks = doctor::run_checks();
+        println!("{}", doctor::report(&checks));
+        if checks.iter().any(|check| !check.passed) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    // Apply CLI overrides.
-    if let Some(provider) = cli.provider {
-        config.llm.provider = provider;
-        if cli.model.is_none() {
-            config.llm.model = config::default_model_for_provider(&config.llm.provider).to_string();
+    if let Some(Command::Approvals { action }) = cli.command {
+        return run_approvals(action);
+    }
+
+    let (mut config, mut provenance) = config::Config::load_with_provenance()?;
+
+    if let Some(Command::Sessions {
+        action: SessionsCommand::Prune { dry_run },
+    }) = cli.command
+    {
+        let workspace = std::env::current_dir()?;
+        let report = session::prune_sessions(
+            &config::Config::sessions_dir(),
+            &config.sessions,
+            Some(&session::workspace_hash(&workspace)),
+            dry_run,
+        )?;
+        for line in report.describe() {
+            println!("{}", line);
         }
+        return Ok(());
     }
-    if let Some(model) = cli.model {
-        config.llm.model = model;
+
+    // Apply CLI overrides.
+    config::CliOverrides {
+        provider: cli.provider,
+        model: cli.model,
+        security: cli.security,
+        allow_home: cli.allow_home,
     }
-    if let Some(security) = cli.security {
-        config.approval.security = security;
+    .apply(&mut config, &mut provenance, config::ollama_reachable)?;
+
+    let initial_prompt = match cli.prompt.as_deref() {
+        Some(arg) => app::read_prompt_arg(arg, std::io::stdin())?,
+        None => None,
+    };
+
+    let app = app::App::new(config, cli.fresh)
+        .with_initial_prompt(initial_prompt)
+        .with_provenance(provenance)
+        .with_mcp(!cli.no_mcp)
+        .with_observe(cli.observe)
+        .with_json_events(cli.json_events);
+    let status = app.run().await?;
+    if status != ExitStatus::Success {
+        std::process::exit(status.code());
     }
+    Ok(())
+}
 
-    let app = app::App::new(config, cli.fresh);
-    app.run().await
+/// `claw approvals`: edits approvals.json directly, so a running session
+/// picks the change up on its next start.
+fn run_approvals(action: ApprovalsCommand) -> anyhow::Result<()> {
+    let path = config::Config::approvals_path();
+    let mut approvals = ApprovalsFile::load(&path)?;
+    match action {
+        ApprovalsCommand::List => {
+            for row in approvals.allowlist_rows() {
+                println!("allow  {:<12} {}", row.tool, row.pattern);
+            }
+            for row in approvals.denylist_rows() {
+                println!(
+                    "deny   {:<12} {}",
+                    row.tool.as_deref().unwrap_or("(all tools)"),
+                    row.pattern
+                );
+            }
+        }
+        ApprovalsCommand::Deny { pattern, tool } => {
+            glob::Pattern::new(&pattern)
+                .map_err(|e| anyhow::anyhow!("invalid pattern {:?}: {}", pattern, e))?;
+            if approvals.add_to_denylist(tool.as_deref(), &pattern) {
+                approvals.save(&path)?;
+                println!("Denied {}", pattern);
+            } else {
+                println!("{} is already denylisted", pattern);
+            }
+        }
+        ApprovalsCommand::Undeny { pattern, tool } => {
+            if !approvals.remove_from_denylist(tool.as_deref(), &pattern) {
+                anyhow::bail!("{} is not on that denylist", pattern);
+            }
+            approvals.save(&path)?;
+            println!("Removed {} from the denylist", pattern);
+        }
+    }
+    Ok(())
 }
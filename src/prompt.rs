@@ -1,12 +1,13 @@
 // ABOUTME: Dynamic system prompt builder — assembles prompt from runtime capabilities.
 // ABOUTME: Faithful port of openclaw's buildAgentSystemPrompt() pattern.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
 use glob::glob;
 
-use crate::config::{Config, SkillsConfig};
+use crate::config::{Config, PrivacyConfig, SkillsConfig};
+use crate::tools::catalog::ToolOrigin;
 
 /// A context file loaded from the workspace to inject into the system prompt.
 #[derive(Debug, Clone)]
@@ -30,6 +31,10 @@ pub struct SystemPromptParams {
     pub tool_names: Vec<String>,
     /// Tool name -> description mapping.
     pub tool_summaries: HashMap<String, String>,
+    /// Where each tool came from; tools not listed are built in.
+    pub tool_origins: HashMap<String, ToolOrigin>,
+    /// MCP server name -> one-line description from `.mcp.json`.
+    pub mcp_descriptions: HashMap<String, String>,
     /// Current working directory.
     pub workspace_dir: String,
     /// Operating system name (e.g., "macos", "linux").
@@ -38,6 +43,10 @@ pub struct SystemPromptParams {
     pub arch: String,
     /// User's shell (e.g., "/bin/zsh").
     pub shell: String,
+    /// Machine hostname.
+    pub hostname: String,
+    /// Which of the details above the runtime section may disclose.
+    pub privacy: PrivacyConfig,
     /// LLM model name.
     pub model: String,
     /// Context files loaded from the workspace.
@@ -46,6 +55,16 @@ pub struct SystemPromptParams {
     pub skill_files: Vec<SkillFile>,
 }
 
+/// This machine's hostname: `$HOSTNAME`, else `/etc/hostname`, else empty.
+pub fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .unwrap_or_default()
+}
+
 /// Build the system prompt from runtime parameters.
 ///
 /// Mirrors openclaw's buildAgentSystemPrompt(): assembles sections conditionally
@@ -108,10 +127,59 @@ pub fn load_context_files(workspace_dir: &str) -> Vec<ContextFile> {
     files
 }
 
+/// Whether a discovered SKILL.md made it into the prompt, and if not, why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkillStatus {
+    Loaded,
+    /// Loaded, but cut short to fit the max_total_chars budget.
+    Truncated,
+    /// Turned off via `[skills.overrides.<name>] enabled = false`.
+    Disabled,
+    /// Larger than max_file_bytes.
+    TooLarge,
+    /// max_files skills were already loaded.
+    FileLimit,
+    /// The max_total_chars budget was used up by earlier skills.
+    BudgetExhausted,
+}
+
+impl std::fmt::Display for SkillStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkillStatus::Loaded => write!(f, "loaded"),
+            SkillStatus::Truncated => write!(f, "truncated"),
+            SkillStatus::Disabled => write!(f, "disabled"),
+            SkillStatus::TooLarge => write!(f, "too large"),
+            SkillStatus::FileLimit => write!(f, "over max_files"),
+            SkillStatus::BudgetExhausted => write!(f, "budget exhausted"),
+        }
+    }
+}
+
+/// A discovered skill and what happened to it.
+#[derive(Debug, Clone)]
+pub struct SkillEntry {
+    pub name: String,
+    pub path: String,
+    pub bytes: u64,
+    pub status: SkillStatus,
+}
+
+/// Result of loading skills: the files for the prompt plus a report on
+/// every SKILL.md that was considered.
+#[derive(Debug, Clone, Default)]
+pub struct SkillLoad {
+    pub files: Vec<SkillFile>,
+    pub entries: Vec<SkillEntry>,
+}
+
 /// Load SKILL.md files from configured directories with prompt-safe limits.
-pub fn load_skill_files(workspace_dir: &str, cfg: &SkillsConfig) -> Vec<SkillFile> {
+///
+/// Skills are considered in priority order (highest `priority` override
+/// first, then by path) so that the caps drop the least important ones.
+pub fn load_skill_files(workspace_dir: &str, cfg: &SkillsConfig) -> SkillLoad {
     if !cfg.enabled {
-        return Vec::new();
+        return SkillLoad::default();
     }
 
     let mut roots: Vec<PathBuf> = Vec::new();
@@ -150,18 +218,40 @@ pub fn load_skill_files(workspace_dir: &str, cfg: &SkillsConfig) -> Vec<SkillFil
     candidates.sort();
     candidates.dedup();
 
-    let mut out = Vec::new();
-    let mut total_chars: usize = 0;
+    let mut candidates: Vec<(String, PathBuf)> = candidates
+        .into_iter()
+        .map(|path| (skill_name(&path), path))
+        .collect();
+    // Stable sort keeps path order among equal priorities.
+    candidates.sort_by_key(|(name, _)| std::cmp::Reverse(cfg.priority(name)));
 
-    for path in candidates {
-        if out.len() >= cfg.max_files {
-            break;
-        }
+    let mut load = SkillLoad::default();
+    let mut total_chars: usize = 0;
 
+    for (name, path) in candidates {
         let Ok(meta) = std::fs::metadata(&path) else {
             continue;
         };
+        let mut entry = SkillEntry {
+            name,
+            path: path.to_string_lossy().to_string(),
+            bytes: meta.len(),
+            status: SkillStatus::Loaded,
+        };
+
+        if !cfg.is_enabled(&entry.name) {
+            entry.status = SkillStatus::Disabled;
+            load.entries.push(entry);
+            continue;
+        }
         if meta.len() as usize > cfg.max_file_bytes {
+            entry.status = SkillStatus::TooLarge;
+            load.entries.push(entry);
+            continue;
+        }
+        if load.files.len() >= cfg.max_files {
+            entry.status = SkillStatus::FileLimit;
+            load.entries.push(entry);
             continue;
         }
 
@@ -175,32 +265,65 @@ pub fn load_skill_files(workspace_dir: &str, cfg: &SkillsConfig) -> Vec<SkillFil
 
         let remaining = cfg.max_total_chars.saturating_sub(total_chars);
         if remaining == 0 {
-            break;
+            entry.status = SkillStatus::BudgetExhausted;
+            load.entries.push(entry);
+            continue;
         }
 
         let mut normalized = trimmed.to_string();
         if normalized.chars().count() > remaining {
             normalized = normalized.chars().take(remaining).collect::<String>();
-        }
-        if normalized.is_empty() {
-            continue;
+            entry.status = SkillStatus::Truncated;
         }
 
         total_chars += normalized.chars().count();
 
-        let name = path
-            .parent()
-            .and_then(|p| p.file_name())
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| "unknown".to_string());
-
-        out.push(SkillFile {
-            name,
-            path: path.to_string_lossy().to_string(),
+        load.files.push(SkillFile {
+            name: entry.name.clone(),
+            path: entry.path.clone(),
             content: normalized,
         });
+        load.entries.push(entry);
     }
 
+    load
+}
+
+/// A skill's name is the directory containing its SKILL.md.
+fn skill_name(path: &std::path::Path) -> String {
+    path.parent()
+        .and_then(|p| p.file_name())
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Format the skill report as a system message: one row per discovered
+/// skill with its name, source path, size, and status.
+pub fn format_skill_list(entries: &[SkillEntry]) -> String {
+    if entries.is_empty() {
+        return "No skills found.".to_string();
+    }
+    let loaded = entries
+        .iter()
+        .filter(|e| matches!(e.status, SkillStatus::Loaded | SkillStatus::Truncated))
+        .count();
+    let name_width = entries
+        .iter()
+        .map(|e| e.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = format!("\u{1f4da} {} of {} skills loaded", loaded, entries.len());
+    for entry in entries {
+        out.push_str(&format!(
+            "\n  {:<width$}  {:<16}  {:>8}  {}",
+            entry.name,
+            entry.status.to_string(),
+            crate::tools::summary::format_size(entry.bytes as usize),
+            entry.path,
+            width = name_width
+        ));
+    }
     out
 }
 
@@ -212,15 +335,38 @@ fn build_tooling_section(lines: &mut Vec<String>, params: &SystemPromptParams) {
     if params.tool_names.is_empty() {
         lines.push("No tools currently available.".to_string());
     } else {
+        // Group MCP tools under their server, servers and their tools sorted
+        // by name; built-in tools come first, in the order given.
+        let mut builtin: Vec<&String> = Vec::new();
+        let mut servers: BTreeMap<&str, Vec<&String>> = BTreeMap::new();
         for name in &params.tool_names {
-            if let Some(desc) = params.tool_summaries.get(name) {
-                if desc.is_empty() {
-                    lines.push(format!("- {}", name));
-                } else {
-                    lines.push(format!("- {}: {}", name, desc));
+            match params.tool_origins.get(name) {
+                Some(ToolOrigin::Mcp(server)) => {
+                    servers.entry(server.as_str()).or_default().push(name)
+                }
+                _ => builtin.push(name),
+            }
+        }
+        if servers.is_empty() {
+            for name in builtin {
+                push_tool_line(lines, params, name);
+            }
+        } else {
+            if !builtin.is_empty() {
+                lines.push("### Built-in".to_string());
+                for name in builtin {
+                    push_tool_line(lines, params, name);
+                }
+            }
+            for (server, mut names) in servers {
+                names.sort();
+                lines.push(format!("### MCP: {}", server));
+                if let Some(description) = params.mcp_descriptions.get(server) {
+                    lines.push(description.clone());
+                }
+                for name in names {
+                    push_tool_line(lines, params, name);
                 }
-            } else {
-                lines.push(format!("- {}", name));
             }
         }
     }
@@ -232,6 +378,14 @@ fn build_tooling_section(lines: &mut Vec<String>, params: &SystemPromptParams) {
     lines.push(String::new());
 }
 
+/// One tool as `- name: description`, or just `- name` without one.
+fn push_tool_line(lines: &mut Vec<String>, params: &SystemPromptParams, name: &str) {
+    match params.tool_summaries.get(name) {
+        Some(desc) if !desc.is_empty() => lines.push(format!("- {}: {}", name, desc)),
+        _ => lines.push(format!("- {}", name)),
+    }
+}
+
 fn build_tool_call_style_section(lines: &mut Vec<String>) {
     lines.push("## Tool Call Style".to_string());
     lines.push(
@@ -347,9 +501,12 @@ fn build_runtime_section(lines: &mut Vec<String>, params: &SystemPromptParams) {
     if !params.model.is_empty() {
         parts.push(format!("model={}", params.model));
     }
-    if !params.shell.is_empty() {
+    if params.privacy.include_shell_in_prompt && !params.shell.is_empty() {
         parts.push(format!("shell={}", params.shell));
     }
+    if params.privacy.include_hostname && !params.hostname.is_empty() {
+        parts.push(format!("host={}", params.hostname));
+    }
 
     if parts.is_empty() {
         lines.push("Runtime: unknown".to_string());
@@ -371,10 +528,14 @@ mod tests {
                 m.insert("read_file".to_string(), "Read file contents".to_string());
                 m
             },
+            tool_origins: HashMap::new(),
+            mcp_descriptions: HashMap::new(),
             workspace_dir: "/tmp/test-project".to_string(),
             os: "macos".to_string(),
             arch: "aarch64".to_string(),
             shell: "/bin/zsh".to_string(),
+            hostname: "devbox".to_string(),
+            privacy: PrivacyConfig::default(),
             model: "claude-sonnet-4".to_string(),
             context_files: vec![],
             skill_files: vec![],
@@ -430,6 +591,20 @@ mod tests {
         assert!(prompt.contains("os=macos (aarch64)"));
         assert!(prompt.contains("model=claude-sonnet-4"));
         assert!(prompt.contains("shell=/bin/zsh"));
+        assert!(!prompt.contains("host="), "hostname is opt-in");
+    }
+
+    #[test]
+    fn privacy_flags_control_runtime_disclosure() {
+        let mut params = base_params();
+        params.privacy.include_shell_in_prompt = false;
+        let prompt = build_system_prompt(&params);
+        assert!(!prompt.contains("shell="));
+        assert!(prompt.contains("os=macos (aarch64)"));
+
+        params.privacy.include_hostname = true;
+        let prompt = build_system_prompt(&params);
+        assert!(prompt.contains("model=claude-sonnet-4 | host=devbox"));
     }
 
     #[test]
@@ -484,6 +659,73 @@ mod tests {
         assert!(!prompt.contains("- custom_tool:"));
     }
 
+    fn mcp_params() -> SystemPromptParams {
+        let mut params = base_params();
+        let mcp = |server: &str| ToolOrigin::Mcp(server.to_string());
+        params.tool_names = [
+            "github_search",
+            "bash",
+            "fs_read",
+            "github_create_issue",
+            "read_file",
+        ]
+        .map(String::from)
+        .to_vec();
+        params.tool_origins = HashMap::from([
+            ("github_search".to_string(), mcp("github")),
+            ("github_create_issue".to_string(), mcp("github")),
+            ("fs_read".to_string(), mcp("fs")),
+        ]);
+        params.mcp_descriptions = HashMap::from([(
+            "github".to_string(),
+            "Issues and PRs for this repo".to_string(),
+        )]);
+        params
+    }
+
+    #[test]
+    fn mcp_tools_are_grouped_by_server() {
+        let prompt = build_system_prompt(&mcp_params());
+        let start = prompt.find("## Tooling").unwrap();
+        let end = prompt[start..].find("TOOLS.md").unwrap() + start;
+        let listing: Vec<&str> = prompt[start..end]
+            .lines()
+            .filter(|l| l.starts_with("###") || l.starts_with("- ") || l.starts_with("Issues"))
+            .collect();
+        assert_eq!(
+            listing,
+            [
+                "### Built-in",
+                "- bash: Execute a bash command",
+                "- read_file: Read file contents",
+                "### MCP: fs",
+                "- fs_read",
+                "### MCP: github",
+                "Issues and PRs for this repo",
+                "- github_create_issue",
+                "- github_search",
+            ]
+        );
+    }
+
+    #[test]
+    fn mcp_grouping_is_stable_whatever_the_registry_order() {
+        let mut reversed = mcp_params();
+        reversed.tool_names.reverse();
+        let mcp_listing = |params: &SystemPromptParams| {
+            let prompt = build_system_prompt(params);
+            prompt[prompt.find("### MCP").unwrap()..].to_string()
+        };
+        assert_eq!(mcp_listing(&mcp_params()), mcp_listing(&reversed));
+    }
+
+    #[test]
+    fn builtin_only_tooling_has_no_subheadings() {
+        let prompt = build_system_prompt(&base_params());
+        assert!(!prompt.contains("### Built-in"));
+        assert!(prompt.contains("- bash: Execute a bash command"));
+    }
+
     #[test]
     fn load_context_files_from_nonexistent_dir() {
         let files = load_context_files("/nonexistent/path/that/does/not/exist");
@@ -546,13 +788,103 @@ mod tests {
         };
         let skills = load_skill_files(dir.to_str().unwrap(), &cfg);
         assert!(
-            skills.iter().any(|s| s.name == "peekaboo"),
+            skills.files.iter().any(|s| s.name == "peekaboo"),
             "should find workspace skill"
         );
 
         let _ = std::fs::remove_file(&skill_path);
     }
 
+    /// Create `<workspace>/skills/<name>/SKILL.md` with the given content.
+    fn write_skill(workspace: &std::path::Path, name: &str, content: &str) {
+        let dir = workspace.join("skills").join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("SKILL.md"), content).unwrap();
+    }
+
+    /// Only the workspace skills directory, so the user's own skills don't leak in.
+    fn workspace_only() -> SkillsConfig {
+        SkillsConfig {
+            include_xdg_config: false,
+            include_agents_home: false,
+            include_codex_home: false,
+            ..SkillsConfig::default()
+        }
+    }
+
+    fn override_with(enabled: bool, priority: Option<i64>) -> crate::config::SkillOverride {
+        crate::config::SkillOverride { enabled, priority }
+    }
+
+    fn status_of(load: &SkillLoad, name: &str) -> SkillStatus {
+        load.entries.iter().find(|e| e.name == name).unwrap().status
+    }
+
+    #[test]
+    fn skill_priority_controls_inclusion_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_skill(tmp.path(), "alpha", "alpha skill");
+        write_skill(tmp.path(), "beta", "beta skill");
+        write_skill(tmp.path(), "gamma", "gamma skill");
+
+        let mut cfg = SkillsConfig {
+            max_files: 2,
+            ..workspace_only()
+        };
+        cfg.overrides
+            .insert("gamma".to_string(), override_with(true, Some(10)));
+
+        let load = load_skill_files(tmp.path().to_str().unwrap(), &cfg);
+
+        let names: Vec<&str> = load.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["gamma", "alpha"]);
+        assert_eq!(status_of(&load, "beta"), SkillStatus::FileLimit);
+    }
+
+    #[test]
+    fn disabled_skill_is_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_skill(tmp.path(), "alpha", "alpha skill");
+        write_skill(tmp.path(), "beta", "beta skill");
+
+        let mut cfg = workspace_only();
+        cfg.overrides
+            .insert("alpha".to_string(), override_with(false, None));
+
+        let load = load_skill_files(tmp.path().to_str().unwrap(), &cfg);
+
+        assert_eq!(load.files.len(), 1);
+        assert_eq!(load.files[0].name, "beta");
+        assert_eq!(status_of(&load, "alpha"), SkillStatus::Disabled);
+    }
+
+    #[test]
+    fn skip_reasons_are_reported() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_skill(tmp.path(), "a-big", &"x".repeat(500));
+        write_skill(tmp.path(), "b-first", &"y".repeat(60));
+        write_skill(tmp.path(), "c-cut", &"z".repeat(60));
+        write_skill(tmp.path(), "d-late", "late");
+
+        let cfg = SkillsConfig {
+            max_file_bytes: 100,
+            max_total_chars: 100,
+            ..workspace_only()
+        };
+        let load = load_skill_files(tmp.path().to_str().unwrap(), &cfg);
+
+        assert_eq!(status_of(&load, "a-big"), SkillStatus::TooLarge);
+        assert_eq!(status_of(&load, "b-first"), SkillStatus::Loaded);
+        assert_eq!(status_of(&load, "c-cut"), SkillStatus::Truncated);
+        assert_eq!(status_of(&load, "d-late"), SkillStatus::BudgetExhausted);
+        assert_eq!(load.files.len(), 2);
+
+        let listing = format_skill_list(&load.entries);
+        assert!(listing.starts_with("\u{1f4da} 2 of 4 skills loaded"));
+        assert!(listing.contains("too large"));
+        assert!(listing.contains("budget exhausted"));
+    }
+
     #[test]
     fn section_order_matches_openclaw() {
         let prompt = build_system_prompt(&base_params());
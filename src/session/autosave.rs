@@ -0,0 +1,117 @@
+// ABOUTME: Mid-turn auto-save policy — decides when a long tool-calling turn should save its progress.
+// ABOUTME: Triggers on a count of tool results or on time since the last save; 0 disables either trigger.
+
+use std::time::{Duration, Instant};
+
+use crate::config::SessionsConfig;
+
+/// Tracks a turn's progress since the last save and says when another is due.
+/// Checked only when tool results land, so a saved history never ends on a
+/// tool call still waiting for its result.
+#[derive(Debug, Clone)]
+pub struct AutoSave {
+    every_results: usize,
+    every: Option<Duration>,
+    results_since_save: usize,
+    last_save: Instant,
+}
+
+impl AutoSave {
+    /// Save after `every_results` tool results or once `every_seconds` have
+    /// passed, whichever comes first. 0 disables a trigger.
+    pub fn new(every_results: usize, every_seconds: u64) -> Self {
+        Self {
+            every_results,
+            every: (every_seconds > 0).then(|| Duration::from_secs(every_seconds)),
+            results_since_save: 0,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// The policy from `[sessions]`.
+    pub fn from_config(config: &SessionsConfig) -> Self {
+        Self::new(config.autosave_tool_results, config.autosave_seconds)
+    }
+
+    /// Never saves mid-turn.
+    pub fn disabled() -> Self {
+        Self::new(0, 0)
+    }
+
+    /// Start counting afresh for a new turn; the previous turn ended in a save.
+    pub fn start_turn(&mut self, now: Instant) {
+        self.results_since_save = 0;
+        self.last_save = now;
+    }
+
+    /// Record `count` tool results arriving at `now`. Returns true when a save
+    /// is due, and counts it as done.
+    pub fn record_results(&mut self, count: usize, now: Instant) -> bool {
+        self.results_since_save += count;
+        let by_count = self.every_results > 0 && self.results_since_save >= self.every_results;
+        let by_time = self
+            .every
+            .is_some_and(|every| now.duration_since(self.last_save) >= every);
+        if by_count || by_time {
+            self.start_turn(now);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_every_m_tool_results() {
+        let start = Instant::now();
+        let mut autosave = AutoSave::new(3, 0);
+        autosave.start_turn(start);
+        assert!(!autosave.record_results(1, start));
+        assert!(!autosave.record_results(1, start));
+        assert!(autosave.record_results(1, start));
+        // The count starts over after a save.
+        assert!(!autosave.record_results(2, start));
+        assert!(autosave.record_results(1, start));
+    }
+
+    #[test]
+    fn a_parallel_batch_can_trigger_a_save_on_its_own() {
+        let start = Instant::now();
+        let mut autosave = AutoSave::new(3, 0);
+        autosave.start_turn(start);
+        assert!(autosave.record_results(4, start));
+        assert!(!autosave.record_results(1, start));
+    }
+
+    #[test]
+    fn saves_once_the_interval_has_passed() {
+        let start = Instant::now();
+        let mut autosave = AutoSave::new(0, 30);
+        autosave.start_turn(start);
+        assert!(!autosave.record_results(1, start + Duration::from_secs(29)));
+        assert!(autosave.record_results(1, start + Duration::from_secs(30)));
+        assert!(!autosave.record_results(1, start + Duration::from_secs(45)));
+        assert!(autosave.record_results(1, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn a_new_turn_resets_the_count() {
+        let start = Instant::now();
+        let mut autosave = AutoSave::new(2, 0);
+        autosave.start_turn(start);
+        assert!(!autosave.record_results(1, start));
+        autosave.start_turn(start);
+        assert!(!autosave.record_results(1, start));
+    }
+
+    #[test]
+    fn disabled_never_saves() {
+        let start = Instant::now();
+        let mut autosave = AutoSave::disabled();
+        autosave.start_turn(start);
+        assert!(!autosave.record_results(1000, start + Duration::from_secs(86_400)));
+    }
+}
@@ -0,0 +1,578 @@
+// ABOUTME: Change journal — every file the agent wrote this session, with the contents it replaced.
+// ABOUTME: Backs /changes and /revert; kept in the session directory so a resumed session can still undo.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::session::log::fnv1a_hex;
+
+/// The journal's file in the session directory.
+pub const JOURNAL_FILE: &str = "changes.json";
+
+/// Where replaced contents are kept, gzipped, next to the journal.
+pub const SAVED_DIR: &str = "changes";
+
+/// Extension of a gzipped saved file. Journals from before compression
+/// name plain ones.
+const GZIP_EXTENSION: &str = ".gz";
+
+/// Files bigger than this before a write aren't saved, so can't be reverted.
+pub const MAX_SAVED_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Saved contents past this total on disk are dropped, oldest first.
+pub const MAX_SAVED_TOTAL_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The agent wrote the file.
+    Write,
+    /// /revert put back what a write replaced.
+    Revert,
+}
+
+/// One change to one file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    /// Absolute path of the file.
+    pub path: String,
+    /// RFC 3339 time of the change.
+    pub at: String,
+    pub kind: ChangeKind,
+    /// Size after minus size before.
+    pub byte_delta: i64,
+    /// Hash of the contents before; None when the file didn't exist.
+    pub before_hash: Option<String>,
+    /// Hash of the contents after; None when the change deleted the file.
+    pub after_hash: Option<String>,
+    /// File under [`SAVED_DIR`] holding the contents before, while kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved: Option<String>,
+    /// Size of the saved file on disk.
+    #[serde(default)]
+    pub saved_bytes: u64,
+    /// Whether a later /revert undid this write.
+    #[serde(default)]
+    pub reverted: bool,
+}
+
+impl Change {
+    /// Whether the contents before can be put back: the file didn't exist,
+    /// or what it held is still saved.
+    pub fn restorable(&self) -> bool {
+        self.before_hash.is_none() || self.saved.is_some()
+    }
+}
+
+/// What /changes shows for one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChanges {
+    pub path: String,
+    pub writes: usize,
+    pub reverts: usize,
+    pub byte_delta: i64,
+}
+
+/// The session's change journal, saved after every change.
+#[derive(Debug)]
+pub struct ChangeJournal {
+    dir: PathBuf,
+    changes: Vec<Change>,
+    max_file_bytes: u64,
+    max_total_bytes: u64,
+}
+
+impl ChangeJournal {
+    /// The journal in session directory `dir`; an empty one when `fresh`,
+    /// with whatever an earlier session left there removed.
+    pub fn open(dir: &Path, fresh: bool) -> anyhow::Result<Self> {
+        let path = dir.join(JOURNAL_FILE);
+        let changes = if fresh {
+            let _ = std::fs::remove_file(&path);
+            let _ = std::fs::remove_dir_all(dir.join(SAVED_DIR));
+            Vec::new()
+        } else if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            changes,
+            max_file_bytes: MAX_SAVED_FILE_BYTES,
+            max_total_bytes: MAX_SAVED_TOTAL_BYTES,
+        })
+    }
+
+    /// Cap saved contents at `per_file` bytes each and `total` overall.
+    pub fn with_limits(mut self, per_file: u64, total: u64) -> Self {
+        self.max_file_bytes = per_file;
+        self.max_total_bytes = total;
+        self
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Record that the agent changed `path` from `before` to `after` (None
+    /// for a missing file), saving `before` compressed when it's small
+    /// enough.
+    pub fn record_write(
+        &mut self,
+        path: &Path,
+        before: Option<&[u8]>,
+        after: Option<&[u8]>,
+    ) -> anyhow::Result<()> {
+        let path = normalize_path(path);
+        let mut change = new_change(&path, ChangeKind::Write, before, after);
+        if let Some(bytes) = before
+            && bytes.len() as u64 <= self.max_file_bytes
+        {
+            let name = format!(
+                "{}-{}{}",
+                self.changes.len(),
+                fnv1a_hex(bytes),
+                GZIP_EXTENSION
+            );
+            let saved_dir = self.dir.join(SAVED_DIR);
+            std::fs::create_dir_all(&saved_dir)?;
+            let compressed = compress(bytes)?;
+            std::fs::write(saved_dir.join(&name), &compressed)?;
+            change.saved = Some(name);
+            change.saved_bytes = compressed.len() as u64;
+        }
+        self.changes.push(change);
+        self.evict();
+        self.save()
+    }
+
+    /// Drop the oldest saved contents until the total fits.
+    fn evict(&mut self) {
+        let mut total: u64 = self.changes.iter().map(|c| c.saved_bytes).sum();
+        for change in &mut self.changes {
+            if total <= self.max_total_bytes {
+                break;
+            }
+            if let Some(name) = change.saved.take() {
+                let _ = std::fs::remove_file(self.dir.join(SAVED_DIR).join(name));
+                total -= change.saved_bytes;
+                change.saved_bytes = 0;
+            }
+        }
+    }
+
+    /// Each changed file with its write and revert counts, in the order
+    /// they were first changed.
+    pub fn files(&self) -> Vec<FileChanges> {
+        let mut files: Vec<FileChanges> = Vec::new();
+        for change in &self.changes {
+            let index = match files.iter().position(|f| f.path == change.path) {
+                Some(index) => index,
+                None => {
+                    files.push(FileChanges {
+                        path: change.path.clone(),
+                        writes: 0,
+                        reverts: 0,
+                        byte_delta: 0,
+                    });
+                    files.len() - 1
+                }
+            };
+            let file = &mut files[index];
+            match change.kind {
+                ChangeKind::Write => file.writes += 1,
+                ChangeKind::Revert => file.reverts += 1,
+            }
+            file.byte_delta += change.byte_delta;
+        }
+        files
+    }
+
+    /// The write a /revert of `path` would undo: its latest one not yet
+    /// reverted. Refused when the file has changed since, or what it
+    /// replaced wasn't kept.
+    pub fn revertible(&self, path: &Path) -> anyhow::Result<&Change> {
+        self.revertible_index(path)
+            .map(|index| &self.changes[index])
+    }
+
+    fn revertible_index(&self, path: &Path) -> anyhow::Result<usize> {
+        let path = normalize_path(path);
+        let key = path.to_string_lossy();
+        let Some(index) = self
+            .changes
+            .iter()
+            .rposition(|c| c.path == key && c.kind == ChangeKind::Write && !c.reverted)
+        else {
+            anyhow::bail!("no write to {} left to revert", key);
+        };
+        let change = &self.changes[index];
+        if current_hash(&path)? != change.after_hash {
+            anyhow::bail!(
+                "{} has changed since the agent wrote it; not reverting over those edits",
+                key
+            );
+        }
+        if !change.restorable() {
+            anyhow::bail!(
+                "what the write to {} replaced wasn't kept (over the size limits)",
+                key
+            );
+        }
+        Ok(index)
+    }
+
+    /// Put back what the latest write to `path` replaced, and record the
+    /// revert. Returns what was done.
+    pub fn revert(&mut self, path: &Path) -> anyhow::Result<String> {
+        let path = normalize_path(path);
+        let index = self.revertible_index(&path)?;
+        let current = read_existing(&path)?;
+        let restored = match &self.changes[index].saved {
+            Some(name) => {
+                let saved = std::fs::read(self.dir.join(SAVED_DIR).join(name))?;
+                let bytes = if name.ends_with(GZIP_EXTENSION) {
+                    decompress(&saved)?
+                } else {
+                    saved
+                };
+                std::fs::write(&path, &bytes)?;
+                Some(bytes)
+            }
+            None => {
+                std::fs::remove_file(&path)?;
+                None
+            }
+        };
+        self.changes[index].reverted = true;
+        self.changes.push(new_change(
+            &path,
+            ChangeKind::Revert,
+            current.as_deref(),
+            restored.as_deref(),
+        ));
+        self.save()?;
+        Ok(match restored {
+            Some(bytes) => format!("Restored {} ({} bytes)", path.display(), bytes.len()),
+            None => format!("Removed {}, which the agent created", path.display()),
+        })
+    }
+
+    /// Write the journal and drop saved files it no longer names (atomic
+    /// write via tmp + rename).
+    fn save(&self) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(JOURNAL_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(&self.changes)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+
+        let kept: HashSet<&str> = self
+            .changes
+            .iter()
+            .filter_map(|c| c.saved.as_deref())
+            .collect();
+        if let Ok(entries) = std::fs::read_dir(self.dir.join(SAVED_DIR)) {
+            for entry in entries.flatten() {
+                if !kept.contains(entry.file_name().to_string_lossy().as_ref()) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn new_change(
+    path: &Path,
+    kind: ChangeKind,
+    before: Option<&[u8]>,
+    after: Option<&[u8]>,
+) -> Change {
+    let len = |bytes: Option<&[u8]>| bytes.map_or(0, |b| b.len() as i64);
+    Change {
+        path: path.to_string_lossy().to_string(),
+        at: chrono::Utc::now().to_rfc3339(),
+        kind,
+        byte_delta: len(after) - len(before),
+        before_hash: before.map(fnv1a_hex),
+        after_hash: after.map(fnv1a_hex),
+        saved: None,
+        saved_bytes: 0,
+        reverted: false,
+    }
+}
+
+/// `path` made absolute against the current directory, with `.` and `..`
+/// resolved without touching the filesystem, so `./a.rs`, `src/../a.rs`,
+/// and the absolute path name the same journal entry.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+fn compress(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+fn decompress(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// The contents of `path`, or None when it doesn't exist.
+pub fn read_existing(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn current_hash(path: &Path) -> anyhow::Result<Option<String>> {
+    Ok(read_existing(path)?.as_deref().map(fnv1a_hex))
+}
+
+/// The /changes listing, paths shown relative to `workspace`.
+pub fn format_changes(files: &[FileChanges], workspace: &Path) -> String {
+    if files.is_empty() {
+        return "No files changed this session.".to_string();
+    }
+    let mut lines = vec![format!(
+        "{} file{} changed this session:",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" }
+    )];
+    for file in files {
+        let path = Path::new(&file.path);
+        let shown = path.strip_prefix(workspace).unwrap_or(path);
+        let mut line = format!(
+            "  {}  {} write{}, {:+} bytes",
+            shown.display(),
+            file.writes,
+            if file.writes == 1 { "" } else { "s" },
+            file.byte_delta
+        );
+        if file.reverts > 0 {
+            line.push_str(&format!(", {} reverted", file.reverts));
+        }
+        lines.push(line);
+    }
+    lines.push("/revert <path> undoes a file's latest write.".to_string());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(journal: &mut ChangeJournal, path: &Path, contents: &str) {
+        let before = read_existing(path).unwrap();
+        std::fs::write(path, contents).unwrap();
+        journal
+            .record_write(path, before.as_deref(), Some(contents.as_bytes()))
+            .unwrap();
+    }
+
+    #[test]
+    fn the_journal_survives_a_resume_but_not_a_fresh_start() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let file = workspace.path().join("notes.md");
+        std::fs::write(&file, "old").unwrap();
+
+        let mut journal = ChangeJournal::open(session.path(), false).unwrap();
+        write(&mut journal, &file, "newer");
+
+        let resumed = ChangeJournal::open(session.path(), false).unwrap();
+        assert_eq!(resumed.changes(), journal.changes());
+        assert_eq!(
+            resumed.files(),
+            vec![FileChanges {
+                path: file.to_string_lossy().to_string(),
+                writes: 1,
+                reverts: 0,
+                byte_delta: 2,
+            }]
+        );
+
+        let fresh = ChangeJournal::open(session.path(), true).unwrap();
+        assert!(fresh.changes().is_empty());
+        assert!(!session.path().join(SAVED_DIR).exists());
+    }
+
+    #[test]
+    fn revert_restores_the_contents_and_removes_created_files() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let edited = workspace.path().join("main.rs");
+        let created = workspace.path().join("new.rs");
+        std::fs::write(&edited, "fn main() {}\n").unwrap();
+
+        let mut journal = ChangeJournal::open(session.path(), false).unwrap();
+        write(&mut journal, &edited, "fn main() { todo!() }\n");
+        write(&mut journal, &edited, "broken");
+        write(&mut journal, &created, "mod x;\n");
+
+        journal.revert(&edited).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&edited).unwrap(),
+            "fn main() { todo!() }\n"
+        );
+        journal.revert(&edited).unwrap();
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "fn main() {}\n");
+        assert!(journal.revert(&edited).is_err());
+
+        journal.revert(&created).unwrap();
+        assert!(!created.exists());
+
+        let files = journal.files();
+        assert_eq!(
+            (files[0].writes, files[0].reverts, files[0].byte_delta),
+            (2, 2, 0)
+        );
+        let resumed = ChangeJournal::open(session.path(), false).unwrap();
+        let writes = resumed
+            .changes()
+            .iter()
+            .filter(|c| c.kind == ChangeKind::Write);
+        assert!(writes.clone().all(|c| c.reverted));
+    }
+
+    #[test]
+    fn revert_refuses_a_file_edited_since() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let file = workspace.path().join("config.toml");
+        std::fs::write(&file, "a = 1\n").unwrap();
+
+        let mut journal = ChangeJournal::open(session.path(), false).unwrap();
+        write(&mut journal, &file, "a = 2\n");
+        std::fs::write(&file, "a = 3\n").unwrap();
+
+        let err = journal.revert(&file).unwrap_err().to_string();
+        assert!(err.contains("has changed since"), "{}", err);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "a = 3\n");
+        assert!(
+            journal
+                .changes()
+                .iter()
+                .all(|c| c.kind == ChangeKind::Write)
+        );
+    }
+
+    #[test]
+    fn saved_contents_are_capped_per_file_and_in_total() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let big = workspace.path().join("big.txt");
+        let small = workspace.path().join("small.txt");
+        std::fs::write(&big, "x".repeat(20)).unwrap();
+
+        // Room for one saved copy of small.txt, not two.
+        let one_copy = compress(b"abcdefgh").unwrap().len() as u64;
+        let mut journal = ChangeJournal::open(session.path(), false)
+            .unwrap()
+            .with_limits(10, one_copy + 1);
+        write(&mut journal, &big, "y");
+        assert!(journal.changes()[0].saved.is_none());
+        assert!(
+            journal
+                .revert(&big)
+                .unwrap_err()
+                .to_string()
+                .contains("wasn't kept")
+        );
+
+        std::fs::write(&small, "12345678").unwrap();
+        write(&mut journal, &small, "abcdefgh");
+        write(&mut journal, &small, "ABCDEFGH");
+        // The first save of small.txt was dropped to fit the second.
+        assert!(journal.changes()[1].saved.is_none());
+        assert!(journal.changes()[2].saved.is_some());
+        let saved = std::fs::read_dir(session.path().join(SAVED_DIR))
+            .unwrap()
+            .count();
+        assert_eq!(saved, 1);
+
+        journal.revert(&small).unwrap();
+        assert_eq!(std::fs::read_to_string(&small).unwrap(), "abcdefgh");
+        assert!(journal.revert(&small).is_err());
+    }
+
+    #[test]
+    fn replaced_contents_are_saved_compressed() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let file = workspace.path().join("log.txt");
+        let original = "the same line over and over\n".repeat(200);
+        std::fs::write(&file, &original).unwrap();
+
+        let mut journal = ChangeJournal::open(session.path(), false).unwrap();
+        write(&mut journal, &file, "short");
+        let change = &journal.changes()[0];
+        assert!(change.saved.as_deref().unwrap().ends_with(".gz"));
+        assert!(change.saved_bytes < original.len() as u64 / 10);
+
+        journal.revert(&file).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), original);
+    }
+
+    #[test]
+    fn paths_are_normalized_before_they_are_keyed() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::create_dir(workspace.path().join("src")).unwrap();
+        let file = workspace.path().join("src/lib.rs");
+        std::fs::write(&file, "old").unwrap();
+
+        let mut journal = ChangeJournal::open(session.path(), false).unwrap();
+        let roundabout = workspace.path().join("src/./../src/lib.rs");
+        write(&mut journal, &roundabout, "new");
+        assert_eq!(journal.changes()[0].path, file.to_string_lossy());
+
+        let via_dot = workspace.path().join("./src/lib.rs");
+        assert!(journal.revertible(&via_dot).is_ok());
+        journal.revert(&via_dot).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "old");
+    }
+
+    #[test]
+    fn the_listing_is_relative_to_the_workspace() {
+        let files = vec![FileChanges {
+            path: "/work/src/lib.rs".to_string(),
+            writes: 2,
+            reverts: 1,
+            byte_delta: -4,
+        }];
+        assert_eq!(
+            format_changes(&files, Path::new("/work")),
+            "1 file changed this session:\n  src/lib.rs  2 writes, -4 bytes, 1 reverted\n\
+             /revert <path> undoes a file's latest write."
+        );
+        assert_eq!(
+            format_changes(&[], Path::new("/work")),
+            "No files changed this session."
+        );
+    }
+}
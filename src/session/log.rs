@@ -1,8 +1,9 @@
-// ABOUTME: JSONL session logger — appends each conversation message to a log file.
+// ABOUTME: JSONL session logger — the write-ahead log: appends each message with a sequence number, fsyncs per policy.
 // ABOUTME: Stores logs per workspace in ~/.local/share/soloclaw/sessions/<workspace_hash>/.
 
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
 use chrono::Utc;
@@ -10,21 +11,127 @@ use mux::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
+use crate::redact::redact_params;
 
 /// A single JSONL log entry containing a timestamp and the conversation message.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LogEntry {
+    /// Position in the logger run, counting from 1 across rolled-over parts.
+    /// 0 on entries written before sequence numbers existed.
+    #[serde(default)]
+    pub seq: u64,
     pub timestamp: String,
     pub message: Message,
+    /// Model in use when the message was logged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// On an assistant message a `[llm] fallbacks` model wrote, which one
+    /// ("openai/gpt-5.2"); otherwise `model` wrote it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answered_by: Option<String>,
+    /// Marks the first message of a replacement history (a resumed or
+    /// compacted conversation); everything logged before it is superseded.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reset: bool,
+    /// Set on a regeneration tombstone: the previous `discarded` entries (an
+    /// answer and its tool calls) were thrown away by /regen, and `message`
+    /// replaces the user message logged before them.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub discarded: usize,
+    /// On a tool results entry: why each approved call ran, keyed by tool
+    /// call id ("safe-bin", "allowlist:<pattern>", "user", ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub approvals: BTreeMap<String, String>,
+    /// Set on a redaction marker: /forget rewrote earlier entries in place,
+    /// and `message` is what they now hold. Replay skips markers.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redaction: bool,
+    /// Ids of the tool calls in `message` whose parameters were masked.
+    /// Replay can't run or show them as they were, so it drops them along
+    /// with their results.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub masked: Vec<String>,
+    /// Set on a pin marker: the indexes in the replayed history of the
+    /// messages pinned from here on. `message` is the message whose pin
+    /// changed. Replay skips markers for the history.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pins: Option<Vec<usize>>,
 }
 
-/// Computes a deterministic hex hash of the workspace directory path.
-///
-/// Uses FNV-1a (64-bit) which is a well-defined, stable algorithm — unlike
-/// `std::hash::DefaultHasher` whose output can change between Rust versions,
-/// which would orphan saved sessions.
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Computes a deterministic hex hash of the workspace directory, after
+/// canonicalizing it so symlinks, trailing separators, and case variants on
+/// case-insensitive filesystems all map to the same session.
 pub fn workspace_hash(workspace_dir: &Path) -> String {
-    let bytes = workspace_dir.as_os_str().as_encoded_bytes();
+    fnv1a_hex(
+        canonical_workspace_path(workspace_dir)
+            .as_os_str()
+            .as_encoded_bytes(),
+    )
+}
+
+/// The hash of the path exactly as given, used before workspace paths were
+/// canonicalized. Only needed to find sessions saved under it.
+pub fn legacy_workspace_hash(workspace_dir: &Path) -> String {
+    fnv1a_hex(workspace_dir.as_os_str().as_encoded_bytes())
+}
+
+/// Resolve symlinks and `.`/`..`, drop trailing separators, and fold case on
+/// case-insensitive filesystems. Paths that can't be resolved (e.g. deleted
+/// directories) are only normalized lexically.
+pub fn canonical_workspace_path(workspace_dir: &Path) -> PathBuf {
+    let path =
+        fs::canonicalize(workspace_dir).unwrap_or_else(|_| workspace_dir.components().collect());
+    if is_case_insensitive(&path) {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path
+    }
+}
+
+/// Whether `path` lives on a case-insensitive filesystem, probed by looking
+/// up its last component with the case of every letter flipped.
+fn is_case_insensitive(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let flipped: String = name
+        .chars()
+        .flat_map(|c| {
+            if c.is_uppercase() {
+                c.to_lowercase().collect::<Vec<_>>()
+            } else {
+                c.to_uppercase().collect::<Vec<_>>()
+            }
+        })
+        .collect();
+    if flipped == name {
+        return false;
+    }
+    same_file(path, &path.with_file_name(flipped))
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    a.exists() && b.exists()
+}
+
+/// FNV-1a (64-bit) as 16 hex characters. A well-defined, stable algorithm —
+/// unlike `std::hash::DefaultHasher` whose output can change between Rust
+/// versions, which would orphan saved sessions.
+pub(crate) fn fnv1a_hex(bytes: &[u8]) -> String {
     let mut hash: u64 = 0xcbf29ce484222325; // FNV offset basis
     for &byte in bytes {
         hash ^= byte as u64;
@@ -33,10 +140,68 @@ pub fn workspace_hash(workspace_dir: &Path) -> String {
     format!("{:016x}", hash)
 }
 
+/// When the logger asks the OS to put written entries on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// After every entry.
+    EveryWrite,
+    /// After every N entries.
+    EveryN(u64),
+    /// Never; the OS writes back when it likes.
+    Never,
+}
+
+impl FsyncPolicy {
+    /// From `[sessions] fsync_every_writes`: 1 syncs every entry, N every
+    /// N entries, 0 never.
+    pub fn from_every(writes: u64) -> Self {
+        match writes {
+            0 => FsyncPolicy::Never,
+            1 => FsyncPolicy::EveryWrite,
+            n => FsyncPolicy::EveryN(n),
+        }
+    }
+
+    /// Whether to sync after `unsynced` entries have been written since the last sync.
+    fn due(self, unsynced: u64) -> bool {
+        match self {
+            FsyncPolicy::EveryWrite => true,
+            FsyncPolicy::EveryN(n) => unsynced >= n,
+            FsyncPolicy::Never => false,
+        }
+    }
+}
+
+/// Flushes a log file to disk; swapped out in tests to count syncs.
+type SyncFn = Box<dyn FnMut(&File) -> std::io::Result<()> + Send>;
+
 /// Appends conversation messages as JSONL lines to a session log file.
+///
+/// The log is the write-ahead record of the session: every entry carries a
+/// sequence number, and is synced to disk according to the fsync policy, so
+/// a `session.json` snapshot that missed the end of a crashed run can be
+/// brought up to date from it.
+///
+/// When a size limit is set, the active file is rolled over to a numbered
+/// sibling (`<stem>.1.jsonl`, `<stem>.2.jsonl`, ...) once it reaches the limit
+/// and logging continues in a fresh file at the original path.
 pub struct SessionLogger {
     writer: BufWriter<File>,
     pub session_dir: PathBuf,
+    log_path: PathBuf,
+    bytes_written: u64,
+    /// Rollover threshold in bytes; 0 disables rollover.
+    max_bytes: u64,
+    /// Mask secret-looking tool parameters before writing.
+    redact: bool,
+    /// Model recorded on each entry.
+    model: Option<String>,
+    /// Sequence number of the next entry.
+    next_seq: u64,
+    fsync: FsyncPolicy,
+    /// Entries written since the last sync.
+    unsynced: u64,
+    sync: SyncFn,
 }
 
 impl SessionLogger {
@@ -65,26 +230,340 @@ impl SessionLogger {
         Ok(Self {
             writer,
             session_dir: session_dir.to_path_buf(),
+            log_path,
+            bytes_written: 0,
+            max_bytes: 0,
+            redact: false,
+            model: None,
+            next_seq: 1,
+            fsync: FsyncPolicy::EveryWrite,
+            unsynced: 0,
+            sync: Box::new(|file: &File| file.sync_data()),
         })
     }
 
+    /// When to sync written entries to disk (every entry by default).
+    pub fn with_fsync(mut self, fsync: FsyncPolicy) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Replace how the log file is synced (for testing).
+    pub fn with_sync_fn(
+        mut self,
+        sync: impl FnMut(&File) -> std::io::Result<()> + Send + 'static,
+    ) -> Self {
+        self.sync = Box::new(sync);
+        self
+    }
+
+    /// Enable size-based rollover at `max_bytes` (0 disables it).
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Mask secret-looking tool call parameters in the log.
+    pub fn with_redaction(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Record the model on each entry so a replayed log knows what it was saved with.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    /// Path of the JSONL file currently being written.
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Move the active log to the next free numbered name and start a new file.
+    fn roll_over(&mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        let stem = self
+            .log_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut n = 1;
+        let rolled = loop {
+            let candidate = self.session_dir.join(format!("{}.{}.jsonl", stem, n));
+            if !candidate.exists() {
+                break candidate;
+            }
+            n += 1;
+        };
+        if self.unsynced > 0 && self.fsync != FsyncPolicy::Never {
+            (self.sync)(self.writer.get_ref())?;
+            self.unsynced = 0;
+        }
+        fs::rename(&self.log_path, &rolled)?;
+        self.writer = BufWriter::new(File::create(&self.log_path)?);
+        self.bytes_written = 0;
+        Ok(())
+    }
+
     /// Append a message to the JSONL log file.
     pub fn log_message(&mut self, msg: &Message) -> anyhow::Result<()> {
-        let entry = LogEntry {
+        self.write_entry(msg, false)
+    }
+
+    /// Append an assistant message, noting the fallback that wrote it when
+    /// the session's model didn't.
+    pub fn log_answer(&mut self, msg: &Message, answered_by: Option<&str>) -> anyhow::Result<()> {
+        self.write(LogEntry {
+            answered_by: answered_by.map(str::to_string),
+            ..self.entry(msg)
+        })
+    }
+
+    /// Append a whole conversation that replaces everything logged so far, so
+    /// replaying the log yields exactly `history`, pinned at `pins`.
+    pub fn log_history(&mut self, history: &[Message], pins: &[usize]) -> anyhow::Result<()> {
+        for (i, msg) in history.iter().enumerate() {
+            self.write_entry(msg, i == 0)?;
+        }
+        match pins.first().and_then(|&i| history.get(i)) {
+            Some(pinned) => self.log_pins(pinned, pins),
+            None => Ok(()),
+        }
+    }
+
+    /// Record that /pin changed the pins to `pins`; `message` is the message
+    /// pinned or unpinned.
+    pub fn log_pins(&mut self, message: &Message, pins: &[usize]) -> anyhow::Result<()> {
+        self.write(LogEntry {
+            pins: Some(pins.to_vec()),
+            ..self.entry(message)
+        })
+    }
+
+    /// Append a tool results message along with why each call was approved.
+    pub fn log_tool_results(
+        &mut self,
+        msg: &Message,
+        approvals: &BTreeMap<String, String>,
+    ) -> anyhow::Result<()> {
+        self.write_entry_with(msg, false, 0, approvals.clone(), false)
+    }
+
+    /// Record that /regen discarded the last `discarded` messages; `user_msg`
+    /// is the user message being answered again.
+    pub fn log_regeneration(&mut self, user_msg: &Message, discarded: usize) -> anyhow::Result<()> {
+        self.write_entry_with(user_msg, false, discarded, BTreeMap::new(), false)
+    }
+
+    /// Record that /forget redacted `original` into `redacted`. Every entry
+    /// in the session's logs holding `original` is rewritten in place, so the
+    /// text is gone from disk, then a marker noting the redaction (without
+    /// the text) is appended. Returns how many entries were rewritten.
+    pub fn log_redaction(
+        &mut self,
+        original: &Message,
+        redacted: &Message,
+    ) -> anyhow::Result<usize> {
+        self.writer.flush()?;
+        let original = serde_json::to_value(self.masked(original))?;
+        let mut rewritten = 0;
+        for run in log_runs_newest_first(&self.session_dir)? {
+            for part in run {
+                rewritten += rewrite_entries(&part, &original, redacted)?;
+            }
+        }
+        // The active file was replaced; keep appending to the new one.
+        self.writer = BufWriter::new(OpenOptions::new().append(true).open(&self.log_path)?);
+        self.bytes_written = fs::metadata(&self.log_path)?.len();
+        self.write_entry_with(redacted, false, 0, BTreeMap::new(), true)?;
+        Ok(rewritten)
+    }
+
+    fn write_entry(&mut self, msg: &Message, reset: bool) -> anyhow::Result<()> {
+        self.write_entry_with(msg, reset, 0, BTreeMap::new(), false)
+    }
+
+    /// `msg` as it is written to the log, with tool parameters masked when
+    /// redaction is on.
+    fn masked(&self, msg: &Message) -> Message {
+        let mut message = msg.clone();
+        if self.redact {
+            for block in &mut message.content {
+                if let ContentBlock::ToolUse { input, .. } = block {
+                    *input = redact_params(input);
+                }
+            }
+        }
+        message
+    }
+
+    fn write_entry_with(
+        &mut self,
+        msg: &Message,
+        reset: bool,
+        discarded: usize,
+        approvals: BTreeMap<String, String>,
+        redaction: bool,
+    ) -> anyhow::Result<()> {
+        self.write(LogEntry {
+            reset,
+            discarded,
+            approvals,
+            redaction,
+            ..self.entry(msg)
+        })
+    }
+
+    /// A plain entry for `msg` as masked, stamped with the next sequence number.
+    fn entry(&self, msg: &Message) -> LogEntry {
+        let message = self.masked(msg);
+        let masked = msg
+            .content
+            .iter()
+            .zip(&message.content)
+            .filter_map(|(original, logged)| match (original, logged) {
+                (
+                    ContentBlock::ToolUse { id, input, .. },
+                    ContentBlock::ToolUse { input: shown, .. },
+                ) if input != shown => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        LogEntry {
+            seq: self.next_seq,
             timestamp: Utc::now().to_rfc3339(),
-            message: msg.clone(),
-        };
+            message,
+            masked,
+            model: self.model.clone(),
+            answered_by: None,
+            reset: false,
+            discarded: 0,
+            approvals: BTreeMap::new(),
+            redaction: false,
+            pins: None,
+        }
+    }
+
+    fn write(&mut self, entry: LogEntry) -> anyhow::Result<()> {
         let line = serde_json::to_string(&entry)?;
         writeln!(self.writer, "{}", line)?;
         self.writer.flush()?;
+        self.next_seq += 1;
+        self.unsynced += 1;
+        if self.fsync.due(self.unsynced) {
+            (self.sync)(self.writer.get_ref())?;
+            self.unsynced = 0;
+        }
+        self.bytes_written += line.len() as u64 + 1;
+        if self.max_bytes > 0 && self.bytes_written >= self.max_bytes {
+            self.roll_over()?;
+        }
         Ok(())
     }
 }
 
+/// Log files in `session_dir` grouped by logger run, newest run first. Each
+/// group lists the rolled-over parts in write order, ending with the base file.
+pub fn log_runs_newest_first(session_dir: &Path) -> anyhow::Result<Vec<Vec<PathBuf>>> {
+    if !session_dir.exists() {
+        return Ok(Vec::new());
+    }
+    // Base logs are `<timestamp>.jsonl`; rolled parts are `<timestamp>.<n>.jsonl`.
+    let mut bases: Vec<String> = Vec::new();
+    for entry in fs::read_dir(session_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "jsonl")
+            && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            && !stem.contains('.')
+        {
+            bases.push(stem.to_string());
+        }
+    }
+    bases.sort_unstable_by(|a, b| b.cmp(a));
+
+    Ok(bases
+        .into_iter()
+        .map(|stem| {
+            let mut parts = Vec::new();
+            let mut n = 1;
+            loop {
+                let part = session_dir.join(format!("{}.{}.jsonl", stem, n));
+                if !part.exists() {
+                    break;
+                }
+                parts.push(part);
+                n += 1;
+            }
+            parts.push(session_dir.join(format!("{}.jsonl", stem)));
+            parts
+        })
+        .collect())
+}
+
+/// Replace the message of every entry in the log at `path` whose message is
+/// `original` with `redacted`. The file is rewritten through a temporary
+/// copy; a backup of the old file exists only until the new one is in
+/// place, so a crash mid-rewrite loses nothing but the original text is not
+/// kept. Lines that don't parse are kept as they are.
+fn rewrite_entries(
+    path: &Path,
+    original: &serde_json::Value,
+    redacted: &Message,
+) -> anyhow::Result<usize> {
+    let content = fs::read_to_string(path)?;
+    let mut rewritten = 0;
+    let mut out = String::with_capacity(content.len());
+    for line in content.lines() {
+        match serde_json::from_str::<LogEntry>(line) {
+            Ok(mut entry)
+                if !entry.redaction && serde_json::to_value(&entry.message)? == *original =>
+            {
+                entry.message = redacted.clone();
+                out.push_str(&serde_json::to_string(&entry)?);
+                rewritten += 1;
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    if rewritten == 0 {
+        return Ok(0);
+    }
+    let backup = path.with_extension("jsonl.bak");
+    let temp = path.with_extension("jsonl.tmp");
+    fs::copy(path, &backup)?;
+    let mut file = File::create(&temp)?;
+    file.write_all(out.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    fs::remove_file(&backup)?;
+    Ok(rewritten)
+}
+
+/// Read the entries of a JSONL log. A line that fails to parse (e.g. cut
+/// short by a crash mid-write) is skipped rather than failing the whole log.
+pub fn read_log_entries(path: &Path) -> anyhow::Result<Vec<LogEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(entry) = serde_json::from_str::<LogEntry>(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::path::Path;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn workspace_hash_is_deterministic() {
@@ -104,6 +583,41 @@ mod tests {
         assert_ne!(hash_a, hash_b);
     }
 
+    #[test]
+    fn workspace_hash_ignores_trailing_separator() {
+        let tmp = tempfile::tempdir().unwrap();
+        let with_slash = PathBuf::from(format!("{}/", tmp.path().display()));
+        assert_eq!(workspace_hash(tmp.path()), workspace_hash(&with_slash));
+        assert_eq!(
+            workspace_hash(Path::new("/gone/project/")),
+            workspace_hash(Path::new("/gone/project"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn workspace_hash_resolves_symlinks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let real = tmp.path().join("real");
+        fs::create_dir(&real).unwrap();
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        assert_eq!(workspace_hash(&link), workspace_hash(&real));
+        assert_eq!(workspace_hash(&link.join(".")), workspace_hash(&real));
+        assert_ne!(legacy_workspace_hash(&link), legacy_workspace_hash(&real));
+    }
+
+    #[test]
+    fn case_variants_share_a_hash_only_when_the_filesystem_folds_case() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("Project");
+        fs::create_dir(&dir).unwrap();
+        let folds_case = tmp.path().join("PROJECT").exists();
+        let same = workspace_hash(&dir) == workspace_hash(&tmp.path().join("PROJECT"));
+        assert_eq!(same, folds_case);
+    }
+
     #[test]
     fn session_logger_writes_valid_jsonl() {
         let tmp = tempfile::tempdir().unwrap();
@@ -158,6 +672,179 @@ mod tests {
         assert_eq!(text, "test content for roundtrip");
     }
 
+    #[test]
+    fn session_logger_redacts_tool_params() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("sessions").join("redact");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir)
+            .unwrap()
+            .with_redaction(true);
+        let msg = Message {
+            role: Role::Assistant,
+            content: vec![ContentBlock::ToolUse {
+                id: "call-1".to_string(),
+                name: "deploy".to_string(),
+                input: serde_json::json!({"service": "web", "password": "hunter2"}),
+            }],
+        };
+        logger.log_message(&msg).unwrap();
+
+        let content = fs::read_to_string(logger.log_path()).unwrap();
+        assert!(content.contains("\"service\":\"web\""));
+        assert!(!content.contains("hunter2"));
+
+        let entries = read_log_entries(logger.log_path()).unwrap();
+        assert_eq!(entries[0].masked, ["call-1"]);
+    }
+
+    #[test]
+    fn answers_from_a_fallback_name_it() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut logger = SessionLogger::new_in_dir(tmp.path())
+            .unwrap()
+            .with_model("claude-sonnet-4");
+        logger
+            .log_answer(&Message::assistant("first"), None)
+            .unwrap();
+        logger
+            .log_answer(&Message::assistant("second"), Some("openai/gpt-5.2"))
+            .unwrap();
+
+        let entries = read_log_entries(logger.log_path()).unwrap();
+        assert_eq!(entries[0].answered_by, None);
+        assert_eq!(entries[1].answered_by.as_deref(), Some("openai/gpt-5.2"));
+        assert_eq!(entries[1].model.as_deref(), Some("claude-sonnet-4"));
+    }
+
+    #[test]
+    fn tool_results_entry_records_approval_reasons() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut logger = SessionLogger::new_in_dir(tmp.path()).unwrap();
+        let approvals = BTreeMap::from([
+            ("call-1".to_string(), "safe-bin".to_string()),
+            ("call-2".to_string(), "user".to_string()),
+        ]);
+        logger.log_message(&Message::user("hi")).unwrap();
+        logger
+            .log_tool_results(
+                &Message::tool_results(vec![ContentBlock::tool_result("call-1", "ok")]),
+                &approvals,
+            )
+            .unwrap();
+
+        let entries = read_log_entries(logger.log_path()).unwrap();
+        assert!(entries[0].approvals.is_empty());
+        assert_eq!(entries[1].approvals, approvals);
+        let content = fs::read_to_string(logger.log_path()).unwrap();
+        assert_eq!(content.matches("\"approvals\"").count(), 1);
+    }
+
+    #[test]
+    fn redaction_rewrites_every_copy_and_appends_a_marker() {
+        let tmp = tempfile::tempdir().unwrap();
+        let secret = Message::user("the key is sk-live-123");
+        let redacted = Message::user("[redacted by user]");
+
+        // An earlier run that logged the same message, as a resumed session would have.
+        let mut earlier = SessionLogger::new_in_dir(tmp.path()).unwrap();
+        earlier.log_message(&secret).unwrap();
+        let earlier_path = tmp.path().join("2000-01-01T00-00-00.jsonl");
+        fs::rename(earlier.log_path(), &earlier_path).unwrap();
+        drop(earlier);
+        let mut logger = SessionLogger::new_in_dir(tmp.path()).unwrap();
+        let current = logger.log_path().to_path_buf();
+        logger.log_message(&secret).unwrap();
+        logger.log_message(&Message::assistant("noted")).unwrap();
+
+        assert_eq!(logger.log_redaction(&secret, &redacted).unwrap(), 2);
+        logger.log_message(&Message::user("next")).unwrap();
+
+        for path in [&earlier_path, &current] {
+            let content = fs::read_to_string(path).unwrap();
+            assert!(!content.contains("sk-live-123"), "{}", content);
+        }
+        let entries = read_log_entries(&current).unwrap();
+        let seqs: Vec<u64> = entries.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [1, 2, 3, 4]);
+        assert!(entries[2].redaction);
+        assert!(!entries[3].redaction);
+        assert_eq!(
+            serde_json::to_value(&entries[0].message).unwrap(),
+            serde_json::to_value(&redacted).unwrap()
+        );
+        let leftovers = fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|x| x != "jsonl"))
+            .count();
+        assert_eq!(leftovers, 0, "no backup or temp file is left behind");
+    }
+
+    #[test]
+    fn log_history_marks_reset_on_first_entry() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut logger = SessionLogger::new_in_dir(tmp.path())
+            .unwrap()
+            .with_model("claude-sonnet-4");
+        logger.log_message(&Message::user("old")).unwrap();
+        logger
+            .log_history(&[Message::user("summary"), Message::user("next")], &[1])
+            .unwrap();
+
+        let entries = read_log_entries(logger.log_path()).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(!entries[0].reset);
+        assert!(entries[1].reset);
+        assert!(!entries[2].reset);
+        assert_eq!(entries[2].model.as_deref(), Some("claude-sonnet-4"));
+        // The pins follow the history as a marker.
+        assert_eq!(entries[3].pins.as_deref(), Some(&[1][..]));
+        assert!(entries[2].pins.is_none());
+    }
+
+    #[test]
+    fn log_runs_group_rolled_parts_newest_first() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        for name in [
+            "2026-01-01T10-00-00.jsonl",
+            "2026-01-02T10-00-00.1.jsonl",
+            "2026-01-02T10-00-00.2.jsonl",
+            "2026-01-02T10-00-00.jsonl",
+            "session.json",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let runs = log_runs_newest_first(dir).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(
+            runs[0],
+            vec![
+                dir.join("2026-01-02T10-00-00.1.jsonl"),
+                dir.join("2026-01-02T10-00-00.2.jsonl"),
+                dir.join("2026-01-02T10-00-00.jsonl"),
+            ]
+        );
+        assert_eq!(runs[1], vec![dir.join("2026-01-01T10-00-00.jsonl")]);
+    }
+
+    #[test]
+    fn read_log_entries_skips_truncated_lines() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut logger = SessionLogger::new_in_dir(tmp.path()).unwrap();
+        logger.log_message(&Message::user("kept")).unwrap();
+        let path = logger.log_path().to_path_buf();
+        drop(logger);
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("{\"timestamp\":\"2026");
+        fs::write(&path, content).unwrap();
+
+        let entries = read_log_entries(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
     #[test]
     fn session_logger_multiple_messages() {
         let tmp = tempfile::tempdir().unwrap();
@@ -187,4 +874,137 @@ mod tests {
             let _entry: LogEntry = serde_json::from_str(line).unwrap();
         }
     }
+
+    #[test]
+    fn session_logger_rolls_over_at_size_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("sessions").join("rollover");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir)
+            .unwrap()
+            .with_max_bytes(64);
+        let active = logger.log_path().to_path_buf();
+        logger.log_message(&Message::user("first message")).unwrap();
+        logger
+            .log_message(&Message::user("second message"))
+            .unwrap();
+
+        let stem = active.file_stem().unwrap().to_string_lossy().to_string();
+        let first_roll = session_dir.join(format!("{}.1.jsonl", stem));
+        let second_roll = session_dir.join(format!("{}.2.jsonl", stem));
+        assert!(first_roll.exists(), "first rollover file should exist");
+        assert!(second_roll.exists(), "second rollover file should exist");
+        assert!(active.exists(), "a fresh active log should be open");
+
+        let first = fs::read_to_string(&first_roll).unwrap();
+        assert!(first.contains("first message"));
+        let second = fs::read_to_string(&second_roll).unwrap();
+        assert!(second.contains("second message"));
+        assert!(fs::read_to_string(&active).unwrap().is_empty());
+    }
+
+    /// A logger whose syncs are counted instead of performed.
+    fn counting_logger(dir: &Path, fsync: FsyncPolicy) -> (SessionLogger, Arc<AtomicUsize>) {
+        let syncs = Arc::new(AtomicUsize::new(0));
+        let counter = syncs.clone();
+        let logger = SessionLogger::new_in_dir(dir)
+            .unwrap()
+            .with_fsync(fsync)
+            .with_sync_fn(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+        (logger, syncs)
+    }
+
+    #[test]
+    fn fsync_policy_decides_how_often_to_sync() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (fsync, expected) in [
+            (FsyncPolicy::EveryWrite, 5),
+            (FsyncPolicy::EveryN(2), 2),
+            (FsyncPolicy::Never, 0),
+        ] {
+            let (mut logger, syncs) = counting_logger(tmp.path(), fsync);
+            for i in 0..5 {
+                logger
+                    .log_message(&Message::user(&format!("msg {}", i)))
+                    .unwrap();
+            }
+            assert_eq!(syncs.load(Ordering::SeqCst), expected, "{:?}", fsync);
+        }
+    }
+
+    #[test]
+    fn fsync_policy_from_config_value() {
+        assert_eq!(FsyncPolicy::from_every(0), FsyncPolicy::Never);
+        assert_eq!(FsyncPolicy::from_every(1), FsyncPolicy::EveryWrite);
+        assert_eq!(FsyncPolicy::from_every(8), FsyncPolicy::EveryN(8));
+    }
+
+    #[test]
+    fn a_failed_sync_fails_the_write() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut logger = SessionLogger::new_in_dir(tmp.path())
+            .unwrap()
+            .with_sync_fn(|_| Err(std::io::Error::other("disk gone")));
+        assert!(logger.log_message(&Message::user("hi")).is_err());
+    }
+
+    #[test]
+    fn entries_are_numbered_across_rollovers() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (logger, _) = counting_logger(tmp.path(), FsyncPolicy::EveryWrite);
+        let mut logger = logger.with_max_bytes(64);
+        logger.log_message(&Message::user("first message")).unwrap();
+        logger
+            .log_message(&Message::user("second message"))
+            .unwrap();
+        logger.log_message(&Message::user("third")).unwrap();
+
+        let run = &log_runs_newest_first(tmp.path()).unwrap()[0];
+        let seqs: Vec<u64> = run
+            .iter()
+            .flat_map(|part| read_log_entries(part).unwrap())
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, [1, 2, 3]);
+    }
+
+    #[test]
+    fn entries_without_a_sequence_number_still_parse() {
+        let mut value = serde_json::to_value(LogEntry {
+            seq: 5,
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            message: Message::user("old"),
+            model: None,
+            answered_by: None,
+            reset: false,
+            discarded: 0,
+            approvals: BTreeMap::new(),
+            redaction: false,
+            masked: Vec::new(),
+            pins: None,
+        })
+        .unwrap();
+        value.as_object_mut().unwrap().remove("seq");
+        let entry: LogEntry = serde_json::from_value(value).unwrap();
+        assert_eq!(entry.seq, 0);
+    }
+
+    #[test]
+    fn session_logger_without_limit_never_rolls_over() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("sessions").join("no_rollover");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir).unwrap();
+        for i in 0..20 {
+            logger
+                .log_message(&Message::user(&format!("msg {}", i)))
+                .unwrap();
+        }
+
+        let count = fs::read_dir(&session_dir).unwrap().count();
+        assert_eq!(count, 1);
+    }
 }
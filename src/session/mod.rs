@@ -1,8 +1,21 @@
 // ABOUTME: Session module — persistence of conversation history to disk.
-// ABOUTME: Provides JSONL logging of messages per workspace session.
+// ABOUTME: Provides JSONL logging, retention pruning, and resume validation per workspace session.
 
+pub mod autosave;
+pub mod changes;
 pub mod log;
 pub mod persistence;
+pub mod resume;
+pub mod retention;
+pub mod staleness;
 
-pub use log::{SessionLogger, workspace_hash};
-pub use persistence::{SessionState, load_session, save_session, new_session_state};
+pub use autosave::AutoSave;
+pub use changes::{ChangeJournal, format_changes};
+pub use log::{FsyncPolicy, SessionLogger, workspace_hash};
+pub use persistence::{
+    Recovery, SessionState, WorkspaceInfo, load_session, new_session_state, recover_session,
+    save_session, workspace_info,
+};
+pub use resume::{ResumeCheck, ResumeFallback, check_resume};
+pub use retention::{PruneReport, prune_sessions};
+pub use staleness::{Staleness, check_staleness, stale_threshold};
@@ -1,17 +1,23 @@
-// ABOUTME: Session state persistence — save and load full conversation state as JSON.
+// ABOUTME: Session state persistence — save and load full conversation state as JSON, or rebuild it from the JSONL log.
 // ABOUTME: Enables auto-resume of sessions per workspace directory via atomic file writes.
 
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
-use chrono::Utc;
+use chrono::{DateTime, FixedOffset, Utc};
 use mux::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
+use crate::agent::pins::{live_pins, message_text};
+use crate::config::{Config, SessionFormat};
+use crate::session::log::{
+    LogEntry, canonical_workspace_path, legacy_workspace_hash, log_runs_newest_first,
+    read_log_entries,
+};
 use crate::session::workspace_hash;
 
 /// Full conversation state persisted between sessions.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionState {
     pub workspace_dir: String,
     pub model: String,
@@ -19,6 +25,13 @@ pub struct SessionState {
     pub updated_at: String,
     pub messages: Vec<Message>,
     pub total_tokens: u64,
+    /// Indexes in `messages` of the messages pinned with /pin.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pins: Vec<usize>,
+    /// The git commit checked out when the session was saved, to tell on
+    /// resume what changed since.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_commit: Option<String>,
 }
 
 /// Path to the session state file for a given workspace directory.
@@ -27,10 +40,371 @@ pub fn session_state_path(workspace_dir: &Path) -> PathBuf {
     Config::sessions_dir().join(&hash).join("session.json")
 }
 
-/// Load a session state from disk, if it exists.
-pub fn load_session(workspace_dir: &Path) -> anyhow::Result<Option<SessionState>> {
-    let path = session_state_path(workspace_dir);
-    load_session_from(&path)
+/// Where a workspace's session and settings are stored, for `/info`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceInfo {
+    /// The workspace as sessions are keyed: canonical, symlinks resolved.
+    pub workspace_dir: PathBuf,
+    pub hash: String,
+    pub session_path: PathBuf,
+    pub config_path: PathBuf,
+    pub approvals_path: PathBuf,
+}
+
+/// Resolve every path session resume depends on for `workspace_dir`.
+pub fn workspace_info(workspace_dir: &Path) -> WorkspaceInfo {
+    WorkspaceInfo {
+        workspace_dir: canonical_workspace_path(workspace_dir),
+        hash: workspace_hash(workspace_dir),
+        session_path: session_state_path(workspace_dir),
+        config_path: Config::resolved_config_path(),
+        approvals_path: Config::approvals_path(),
+    }
+}
+
+impl WorkspaceInfo {
+    /// One line per path, noting files that don't exist yet.
+    pub fn describe(&self) -> String {
+        let path_line = |label: &str, path: &Path| {
+            let missing = if path.exists() { "" } else { " (not found)" };
+            format!("{}: {}{}", label, path.display(), missing)
+        };
+        [
+            format!("Workspace: {}", self.workspace_dir.display()),
+            format!("Workspace hash: {}", self.hash),
+            path_line("Session", &self.session_path),
+            path_line("Config", &self.config_path),
+            path_line("Approvals", &self.approvals_path),
+        ]
+        .join("\n")
+    }
+}
+
+/// Load a session state from disk, if it exists. `format` picks the source:
+/// `session.json`, or a replay of the JSONL log when that is all there is.
+pub fn load_session(
+    workspace_dir: &Path,
+    format: SessionFormat,
+) -> anyhow::Result<Option<SessionState>> {
+    load_session_in(&Config::sessions_dir(), workspace_dir, format)
+}
+
+/// Load a session from under `sessions_dir` (for testing). Falls back to a
+/// session saved under the legacy, uncanonicalized hash of the path and moves
+/// it to the canonical location so later runs find it directly.
+pub fn load_session_in(
+    sessions_dir: &Path,
+    workspace_dir: &Path,
+    format: SessionFormat,
+) -> anyhow::Result<Option<SessionState>> {
+    let session_dir = sessions_dir.join(workspace_hash(workspace_dir));
+    if let Some(state) = load_from_dir(&session_dir, workspace_dir, format)? {
+        return Ok(Some(state));
+    }
+
+    let legacy_dir = sessions_dir.join(legacy_workspace_hash(workspace_dir));
+    if legacy_dir == session_dir {
+        return Ok(None);
+    }
+    let Some(state) = load_from_dir(&legacy_dir, workspace_dir, format)? else {
+        return Ok(None);
+    };
+    // Best effort: the session is already loaded, so a failed move only means
+    // the fallback runs again next time.
+    if !session_dir.exists() {
+        let _ = std::fs::rename(&legacy_dir, &session_dir);
+    } else if format.writes_json() {
+        let _ = save_session_to(&session_dir.join("session.json"), &state);
+    }
+    Ok(Some(state))
+}
+
+/// Load from one session directory in the given format.
+fn load_from_dir(
+    session_dir: &Path,
+    workspace_dir: &Path,
+    format: SessionFormat,
+) -> anyhow::Result<Option<SessionState>> {
+    if format.writes_json() {
+        load_session_from(&session_dir.join("session.json"))
+    } else {
+        load_from_jsonl(session_dir, workspace_dir)
+    }
+}
+
+/// Rebuild a session by replaying the most recent JSONL log run in
+/// `session_dir`. Runs that logged nothing are skipped, so opening and
+/// quitting without a message doesn't hide the previous conversation.
+/// Tool calls whose parameters the log masked are left out, with their
+/// results, as recovery leaves them out.
+pub fn load_from_jsonl(
+    session_dir: &Path,
+    workspace_dir: &Path,
+) -> anyhow::Result<Option<SessionState>> {
+    for run in log_runs_newest_first(session_dir)? {
+        let mut entries = Vec::new();
+        for part in &run {
+            entries.extend(read_log_entries(part)?);
+        }
+        // Redaction markers note a rewrite already applied to the entries.
+        entries.retain(|e| !e.redaction);
+        // The session began with the run, even if /clear reset it since.
+        let Some(created_at) = entries.first().map(|e| e.timestamp.clone()) else {
+            continue;
+        };
+        // Only the history after the last reset is current.
+        if let Some(start) = entries.iter().rposition(|e| e.reset) {
+            entries.drain(..start);
+        }
+        let (entries, mut pins) = apply_regenerations(entries);
+        let Some(updated_at) = entries.last().map(|e| e.timestamp.clone()) else {
+            continue;
+        };
+        let model = entries
+            .iter()
+            .rev()
+            .find_map(|e| e.model.clone())
+            .unwrap_or_default();
+        let masked: HashSet<String> = entries.iter().flat_map(|e| e.masked.clone()).collect();
+        let mut messages: Vec<Message> = entries.into_iter().map(|e| e.message).collect();
+        drop_masked_calls(&mut messages, &masked, &mut pins);
+        return Ok(Some(SessionState {
+            workspace_dir: workspace_dir.to_string_lossy().to_string(),
+            model,
+            created_at,
+            updated_at,
+            pins: live_pins(&messages, &pins),
+            messages,
+            total_tokens: 0,
+            // The log doesn't record HEAD; the staleness check then counts
+            // commits since the last entry, but not files changed.
+            head_commit: None,
+        }));
+    }
+    Ok(None)
+}
+
+/// Replay regeneration tombstones: each one drops the answer it discarded
+/// and stands in for the user message that answer was for. Pin markers are
+/// taken out; returns the pins in force at the end, as they were set.
+fn apply_regenerations(entries: Vec<LogEntry>) -> (Vec<LogEntry>, Vec<usize>) {
+    let mut kept: Vec<LogEntry> = Vec::with_capacity(entries.len());
+    let mut pins = Vec::new();
+    for entry in entries {
+        if let Some(marked) = entry.pins {
+            pins = marked;
+            continue;
+        }
+        if entry.discarded > 0 {
+            let len = kept.len().saturating_sub(entry.discarded + 1);
+            kept.truncate(len);
+            // A pin on a discarded answer doesn't carry over to the next one.
+            pins.retain(|&p| p < len);
+        }
+        kept.push(entry);
+    }
+    (kept, pins)
+}
+
+/// A break in a log run's sequence numbers: the entries in between were
+/// never made durable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqGap {
+    /// The last sequence number before the break (0 when the run starts late).
+    pub after: u64,
+    /// The sequence number found where `after + 1` should have been.
+    pub next: u64,
+}
+
+/// Index of the first entry that doesn't follow on from the one before it,
+/// with the gap it leaves. Entries written before sequence numbers existed
+/// (seq 0) are never counted as a gap.
+pub fn first_seq_gap(entries: &[LogEntry]) -> Option<(usize, SeqGap)> {
+    let mut previous = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.seq == 0 {
+            continue;
+        }
+        let expected_first = index == 0 && entry.seq != 1;
+        if expected_first || (previous > 0 && entry.seq != previous + 1) {
+            return Some((
+                index,
+                SeqGap {
+                    after: previous,
+                    next: entry.seq,
+                },
+            ));
+        }
+        previous = entry.seq;
+    }
+    None
+}
+
+/// What replaying the write-ahead log added to a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// Log entries replayed on top of `session.json`.
+    pub recovered: usize,
+    /// Where the log's sequence broke off; replay stopped there.
+    pub gap: Option<SeqGap>,
+}
+
+impl Recovery {
+    /// A startup notice, or None when the snapshot was already complete.
+    pub fn describe(&self) -> Option<String> {
+        let mut lines = Vec::new();
+        if self.recovered > 0 {
+            let plural = if self.recovered == 1 { "" } else { "s" };
+            lines.push(format!(
+                "\u{267b}\u{fe0f} Recovered {} message{} from the session log that session.json was missing.",
+                self.recovered, plural
+            ));
+        }
+        if let Some(gap) = self.gap {
+            lines.push(format!(
+                "\u{26a0}\u{fe0f} The session log skips from entry {} to {}; replay stopped at the gap.",
+                gap.after, gap.next
+            ));
+        }
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// Bring `snapshot` (the loaded `session.json`) up to date from the JSONL
+/// log, which is written ahead of it. Used with `format = "both"`.
+pub fn recover_session(
+    workspace_dir: &Path,
+    snapshot: &mut Option<SessionState>,
+) -> anyhow::Result<Recovery> {
+    let session_dir = Config::sessions_dir().join(workspace_hash(workspace_dir));
+    recover_session_in(&session_dir, workspace_dir, snapshot)
+}
+
+/// Replay the entries of the newest log run in `session_dir` that were written
+/// after `snapshot` was saved, stopping at the first sequence gap, and re-save
+/// `session.json` when anything was replayed. Without a snapshot the whole run
+/// is replayed. `snapshot` is left untouched on error.
+pub fn recover_session_in(
+    session_dir: &Path,
+    workspace_dir: &Path,
+    snapshot: &mut Option<SessionState>,
+) -> anyhow::Result<Recovery> {
+    let Some(entries) = newest_log_run(session_dir)? else {
+        return Ok(Recovery::default());
+    };
+
+    let start = match snapshot.as_ref() {
+        None => 0,
+        Some(state) => match parse_timestamp(&state.updated_at) {
+            Some(saved) => entries
+                .iter()
+                .position(|e| parse_timestamp(&e.timestamp).is_some_and(|t| t > saved))
+                .unwrap_or(entries.len()),
+            None => entries.len(),
+        },
+    };
+    // A gap before `start` is history the snapshot already holds.
+    let (end, gap) = match first_seq_gap(&entries) {
+        Some((index, gap)) if index >= start => (index, Some(gap)),
+        _ => (entries.len(), None),
+    };
+    let tail = &entries[start..end];
+    let (Some(first), Some(last)) = (tail.first(), tail.last()) else {
+        return Ok(Recovery { recovered: 0, gap });
+    };
+
+    let mut masked = HashSet::new();
+    let mut state = match snapshot.clone() {
+        Some(state) => state,
+        None => SessionState {
+            workspace_dir: workspace_dir.to_string_lossy().to_string(),
+            model: String::new(),
+            created_at: first.timestamp.clone(),
+            updated_at: first.timestamp.clone(),
+            messages: Vec::new(),
+            total_tokens: 0,
+            pins: Vec::new(),
+            head_commit: None,
+        },
+    };
+    for entry in tail {
+        if entry.redaction {
+            continue;
+        }
+        if let Some(pins) = &entry.pins {
+            state.pins = pins.clone();
+            continue;
+        }
+        if entry.reset {
+            state.messages.clear();
+            state.pins.clear();
+        }
+        if entry.discarded > 0 {
+            let len = state.messages.len().saturating_sub(entry.discarded + 1);
+            state.messages.truncate(len);
+            state.pins.retain(|&p| p < len);
+        }
+        masked.extend(entry.masked.iter().cloned());
+        state.messages.push(entry.message.clone());
+    }
+    drop_masked_calls(&mut state.messages, &masked, &mut state.pins);
+    state.pins = live_pins(&state.messages, &state.pins);
+    if let Some(model) = tail.iter().rev().find_map(|e| e.model.clone()) {
+        state.model = model;
+    }
+    state.updated_at = last.timestamp.clone();
+
+    save_session_to(&session_dir.join("session.json"), &state)?;
+    *snapshot = Some(state);
+    Ok(Recovery {
+        recovered: tail
+            .iter()
+            .filter(|e| !e.redaction && e.pins.is_none())
+            .count(),
+        gap,
+    })
+}
+
+/// Drop the tool calls whose parameters the log masked, with their results:
+/// replaying a masked call would show the model a call it never made.
+/// Messages left empty go too, and `pins` move down past them.
+fn drop_masked_calls(messages: &mut Vec<Message>, masked: &HashSet<String>, pins: &mut [usize]) {
+    if masked.is_empty() {
+        return;
+    }
+    for message in messages.iter_mut() {
+        message.content.retain(|block| match block {
+            ContentBlock::ToolUse { id, .. } => !masked.contains(id),
+            ContentBlock::ToolResult { tool_use_id, .. } => !masked.contains(tool_use_id),
+            _ => true,
+        });
+    }
+    // Pinned messages have text, so they are never the ones dropped.
+    let dropped: Vec<usize> = (0..messages.len())
+        .filter(|&i| messages[i].content.is_empty())
+        .collect();
+    for pin in pins.iter_mut() {
+        *pin -= dropped.iter().filter(|&&d| d < *pin).count();
+    }
+    messages.retain(|m| !m.content.is_empty());
+}
+
+/// Every entry of the newest log run that logged anything.
+fn newest_log_run(session_dir: &Path) -> anyhow::Result<Option<Vec<LogEntry>>> {
+    for run in log_runs_newest_first(session_dir)? {
+        let mut entries = Vec::new();
+        for part in &run {
+            entries.extend(read_log_entries(part)?);
+        }
+        if !entries.is_empty() {
+            return Ok(Some(entries));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(timestamp).ok()
 }
 
 /// Load a session state from an explicit file path (for testing).
@@ -39,10 +413,37 @@ pub fn load_session_from(path: &Path) -> anyhow::Result<Option<SessionState>> {
         return Ok(None);
     }
     let content = std::fs::read_to_string(path)?;
-    let state: SessionState = serde_json::from_str(&content)?;
+    let mut state: SessionState = serde_json::from_str(&content)?;
+    if state.pins.is_empty() {
+        let legacy: LegacyPins = serde_json::from_str(&content)?;
+        state.pins = legacy.resolve(&state.messages);
+    }
     Ok(Some(state))
 }
 
+/// Pins as sessions saved them before they were kept by index: the texts
+/// of the pinned user messages.
+#[derive(Deserialize)]
+struct LegacyPins {
+    #[serde(default)]
+    pinned: Vec<String>,
+}
+
+impl LegacyPins {
+    /// The user messages in `messages` with a pinned text.
+    fn resolve(&self, messages: &[Message]) -> Vec<usize> {
+        if self.pinned.is_empty() {
+            return Vec::new();
+        }
+        messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == Role::User && self.pinned.contains(&message_text(m)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
 /// Save a session state to disk (atomic write via tmp + rename).
 pub fn save_session(workspace_dir: &Path, state: &SessionState) -> anyhow::Result<()> {
     let path = session_state_path(workspace_dir);
@@ -71,14 +472,36 @@ pub fn new_session_state(workspace_dir: &Path, model: &str) -> SessionState {
         updated_at: now,
         messages: Vec::new(),
         total_tokens: 0,
+        pins: Vec::new(),
+        head_commit: None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::session::SessionLogger;
     use std::path::Path;
 
+    #[test]
+    fn workspace_info_matches_the_resume_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let info = workspace_info(dir.path());
+
+        assert_eq!(info.hash, workspace_hash(dir.path()));
+        assert_eq!(info.session_path, session_state_path(dir.path()));
+        assert!(
+            info.session_path
+                .ends_with(format!("{}/session.json", info.hash))
+        );
+        assert_eq!(info.workspace_dir, dir.path().canonicalize().unwrap());
+        assert_eq!(info.approvals_path, Config::approvals_path());
+
+        let text = info.describe();
+        assert!(text.contains(&format!("Workspace hash: {}", info.hash)));
+        assert!(text.contains(&format!("Session: {}", info.session_path.display())));
+    }
+
     /// Helper: build a SessionState with some messages for testing.
     fn sample_session_state() -> SessionState {
         SessionState {
@@ -111,6 +534,8 @@ mod tests {
                 },
             ],
             total_tokens: 1234,
+            pins: vec![2],
+            head_commit: Some("4f2a9c1e8b7d6a5f4e3d2c1b0a9f8e7d6c5b4a39".to_string()),
         }
     }
 
@@ -132,6 +557,8 @@ mod tests {
         assert_eq!(loaded.updated_at, original.updated_at);
         assert_eq!(loaded.total_tokens, original.total_tokens);
         assert_eq!(loaded.messages.len(), original.messages.len());
+        assert_eq!(loaded.pins, original.pins);
+        assert_eq!(loaded.head_commit, original.head_commit);
 
         // Verify first user message content.
         assert_eq!(loaded.messages[0].role, Role::User);
@@ -231,4 +658,518 @@ mod tests {
         assert_eq!(loaded.messages.len(), 6);
         assert_eq!(loaded.total_tokens, 9999);
     }
+
+    #[test]
+    fn jsonl_roundtrip_reconstructs_conversation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_jsonl");
+        let original = sample_session_state();
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir)
+            .unwrap()
+            .with_model(&original.model);
+        for msg in &original.messages {
+            logger.log_message(msg).unwrap();
+        }
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new(&original.workspace_dir))
+            .unwrap()
+            .expect("log should replay into a session");
+        assert_eq!(loaded.workspace_dir, original.workspace_dir);
+        assert_eq!(loaded.model, original.model);
+        assert_eq!(
+            serde_json::to_value(&loaded.messages).unwrap(),
+            serde_json::to_value(&original.messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn jsonl_replay_starts_at_last_reset() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_compacted");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir).unwrap();
+        logger.log_message(&Message::user("long ago")).unwrap();
+        logger
+            .log_history(&[Message::user("[summary]"), Message::user("recent")], &[])
+            .unwrap();
+        logger.log_message(&Message::user("latest")).unwrap();
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new("/ws"))
+            .unwrap()
+            .unwrap();
+        let texts: Vec<String> = loaded
+            .messages
+            .iter()
+            .map(|m| match &m.content[0] {
+                ContentBlock::Text { text } => text.clone(),
+                other => panic!("expected Text, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(texts, vec!["[summary]", "recent", "latest"]);
+
+        // The session is as old as its first entry, not its last reset.
+        let run = log_runs_newest_first(&session_dir).unwrap().remove(0);
+        let entries = read_log_entries(&run[0]).unwrap();
+        assert_eq!(loaded.created_at, entries[0].timestamp);
+    }
+
+    #[test]
+    fn jsonl_replay_drops_tool_calls_the_log_masked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_jsonl_masked");
+        let mut logger = SessionLogger::new_in_dir(&session_dir)
+            .unwrap()
+            .with_redaction(true);
+        logger.log_message(&Message::user("deploy")).unwrap();
+        logger
+            .log_message(&Message {
+                role: Role::Assistant,
+                content: vec![ContentBlock::ToolUse {
+                    id: "call-1".to_string(),
+                    name: "deploy".to_string(),
+                    input: serde_json::json!({"password": "hunter2"}),
+                }],
+            })
+            .unwrap();
+        logger
+            .log_message(&Message {
+                role: Role::User,
+                content: vec![ContentBlock::ToolResult {
+                    tool_use_id: "call-1".to_string(),
+                    content: "denied".to_string(),
+                    is_error: true,
+                }],
+            })
+            .unwrap();
+        logger
+            .log_message(&Message::assistant("Not deployed."))
+            .unwrap();
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new("/ws"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded.messages).unwrap(),
+            serde_json::to_value([Message::user("deploy"), Message::assistant("Not deployed.")])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn jsonl_replay_reflects_a_redaction() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_forget");
+        let secret = Message::user("customer is Acme Corp");
+        let redacted = Message::user("[redacted by user]");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir).unwrap();
+        logger.log_message(&secret).unwrap();
+        logger.log_message(&Message::user("next")).unwrap();
+        logger.log_redaction(&secret, &redacted).unwrap();
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new("/ws"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded.messages).unwrap(),
+            serde_json::to_value([redacted, Message::user("next")]).unwrap()
+        );
+    }
+
+    #[test]
+    fn jsonl_replay_keeps_only_the_regenerated_answer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_regen");
+        let assistant = |text: &str| Message {
+            role: Role::Assistant,
+            content: vec![ContentBlock::text(text)],
+        };
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir).unwrap();
+        logger.log_message(&Message::user("question")).unwrap();
+        logger.log_message(&assistant("first take")).unwrap();
+        logger.log_message(&Message::user("explain")).unwrap();
+        logger.log_message(&assistant("too long")).unwrap();
+        logger
+            .log_regeneration(&Message::user("explain briefly"), 1)
+            .unwrap();
+        logger.log_message(&assistant("short")).unwrap();
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new("/ws"))
+            .unwrap()
+            .unwrap();
+        let texts: Vec<String> = loaded
+            .messages
+            .iter()
+            .map(|m| match &m.content[0] {
+                ContentBlock::Text { text } => text.clone(),
+                other => panic!("expected Text, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            texts,
+            vec!["question", "first take", "explain briefly", "short"]
+        );
+    }
+
+    #[test]
+    fn jsonl_replay_skips_empty_runs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let entry = serde_json::json!({
+            "timestamp": "2026-01-01T10:00:00+00:00",
+            "message": Message::user("earlier"),
+            "model": "gpt-4o",
+        });
+        std::fs::write(
+            dir.join("2026-01-01T10-00-00.jsonl"),
+            format!("{}\n", entry),
+        )
+        .unwrap();
+        std::fs::write(dir.join("2026-01-02T10-00-00.jsonl"), "").unwrap();
+
+        let loaded = load_from_jsonl(dir, Path::new("/ws")).unwrap().unwrap();
+        assert_eq!(loaded.messages.len(), 1);
+        assert_eq!(loaded.model, "gpt-4o");
+        assert_eq!(loaded.created_at, "2026-01-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn jsonl_replay_restores_pins() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_pins");
+        let question = Message::user("which port?");
+        let answer = Message::assistant("8443");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir).unwrap();
+        logger.log_message(&question).unwrap();
+        logger.log_message(&answer).unwrap();
+        logger.log_pins(&answer, &[1]).unwrap();
+        logger.log_pins(&question, &[0, 1]).unwrap();
+        logger.log_message(&Message::user("thanks")).unwrap();
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new("/ws"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded.messages.len(), 3);
+        assert_eq!(loaded.pins, [0, 1]);
+    }
+
+    #[test]
+    fn jsonl_replay_drops_the_pin_on_a_regenerated_answer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_pin_regen");
+        let answer = Message::assistant("too long");
+
+        let mut logger = SessionLogger::new_in_dir(&session_dir).unwrap();
+        logger.log_message(&Message::user("explain")).unwrap();
+        logger.log_message(&answer).unwrap();
+        logger.log_pins(&answer, &[1]).unwrap();
+        logger
+            .log_regeneration(&Message::user("explain"), 1)
+            .unwrap();
+        logger.log_message(&Message::assistant("short")).unwrap();
+        drop(logger);
+
+        let loaded = load_from_jsonl(&session_dir, Path::new("/ws"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(texts(&loaded.messages), ["explain", "short"]);
+        assert!(loaded.pins.is_empty());
+    }
+
+    #[test]
+    fn sessions_pinned_by_text_load_as_indexes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("session.json");
+        let mut value = serde_json::to_value(sample_session_state()).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("pins");
+        object.insert(
+            "pinned".to_string(),
+            serde_json::json!(["Can you list files?"]),
+        );
+        std::fs::write(&path, value.to_string()).unwrap();
+
+        let loaded = load_session_from(&path).unwrap().unwrap();
+        assert_eq!(loaded.pins, [2]);
+    }
+
+    #[test]
+    fn jsonl_replay_without_logs_returns_none() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(
+            load_from_jsonl(tmp.path(), Path::new("/ws"))
+                .unwrap()
+                .is_none()
+        );
+        let missing = tmp.path().join("missing");
+        assert!(
+            load_from_jsonl(&missing, Path::new("/ws"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| match &m.content[0] {
+                ContentBlock::Text { text } => text.clone(),
+                other => panic!("expected Text, got {:?}", other),
+            })
+            .collect()
+    }
+
+    /// Log `messages` through a real logger and return the entries written.
+    fn write_log(session_dir: &Path, messages: &[&str]) -> Vec<LogEntry> {
+        let mut logger = SessionLogger::new_in_dir(session_dir)
+            .unwrap()
+            .with_model("log-model");
+        for text in messages {
+            logger.log_message(&Message::user(*text)).unwrap();
+        }
+        drop(logger);
+        let run = log_runs_newest_first(session_dir).unwrap().remove(0);
+        read_log_entries(&run[0]).unwrap()
+    }
+
+    #[test]
+    fn recovery_replays_what_the_snapshot_missed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_crash");
+        let entries = write_log(&session_dir, &["one", "two", "three", "four"]);
+
+        // The crash hit after "two" was saved to session.json.
+        let mut snapshot = Some(SessionState {
+            workspace_dir: "/ws".to_string(),
+            model: "snapshot-model".to_string(),
+            created_at: entries[0].timestamp.clone(),
+            updated_at: entries[1].timestamp.clone(),
+            messages: vec![Message::user("one"), Message::user("two")],
+            total_tokens: 42,
+            pins: Vec::new(),
+            head_commit: None,
+        });
+        let recovery = recover_session_in(&session_dir, Path::new("/ws"), &mut snapshot).unwrap();
+        assert_eq!(recovery.recovered, 2);
+        assert_eq!(recovery.gap, None);
+        assert!(
+            recovery
+                .describe()
+                .unwrap()
+                .contains("Recovered 2 messages")
+        );
+
+        let state = snapshot.unwrap();
+        assert_eq!(texts(&state.messages), ["one", "two", "three", "four"]);
+        assert_eq!(state.updated_at, entries[3].timestamp);
+        assert_eq!(state.model, "log-model");
+        assert_eq!(state.total_tokens, 42);
+
+        // session.json was re-saved, so a second pass finds nothing to do.
+        let mut reloaded = load_session_from(&session_dir.join("session.json")).unwrap();
+        assert_eq!(reloaded.as_ref().unwrap().messages.len(), 4);
+        let again = recover_session_in(&session_dir, Path::new("/ws"), &mut reloaded).unwrap();
+        assert_eq!(again, Recovery::default());
+        assert_eq!(again.describe(), None);
+    }
+
+    #[test]
+    fn recovery_without_a_snapshot_replays_the_whole_run() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_nosnapshot");
+        write_log(&session_dir, &["one", "two"]);
+
+        let mut snapshot = None;
+        let recovery = recover_session_in(&session_dir, Path::new("/ws"), &mut snapshot).unwrap();
+        assert_eq!(recovery.recovered, 2);
+        let state = snapshot.unwrap();
+        assert_eq!(state.workspace_dir, "/ws");
+        assert_eq!(texts(&state.messages), ["one", "two"]);
+        assert!(session_dir.join("session.json").exists());
+    }
+
+    #[test]
+    fn recovery_drops_tool_calls_the_log_masked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_masked");
+        let call = |id: &str, input: serde_json::Value| ContentBlock::ToolUse {
+            id: id.to_string(),
+            name: "deploy".to_string(),
+            input,
+        };
+        let result = |id: &str| ContentBlock::ToolResult {
+            tool_use_id: id.to_string(),
+            content: "ok".to_string(),
+            is_error: false,
+        };
+        let mut logger = SessionLogger::new_in_dir(&session_dir)
+            .unwrap()
+            .with_redaction(true);
+        logger.log_message(&Message::user("deploy")).unwrap();
+        logger
+            .log_message(&Message {
+                role: Role::Assistant,
+                content: vec![
+                    call("call-1", serde_json::json!({"password": "hunter2"})),
+                    call("call-2", serde_json::json!({"service": "web"})),
+                ],
+            })
+            .unwrap();
+        logger
+            .log_message(&Message {
+                role: Role::User,
+                content: vec![result("call-1"), result("call-2")],
+            })
+            .unwrap();
+        logger
+            .log_message(&Message {
+                role: Role::Assistant,
+                content: vec![call("call-3", serde_json::json!({"token": "abc123"}))],
+            })
+            .unwrap();
+        logger
+            .log_message(&Message {
+                role: Role::User,
+                content: vec![result("call-3")],
+            })
+            .unwrap();
+        drop(logger);
+
+        let mut snapshot = None;
+        recover_session_in(&session_dir, Path::new("/ws"), &mut snapshot).unwrap();
+        let messages = snapshot.unwrap().messages;
+        assert_eq!(messages.len(), 3);
+        let ids: Vec<&str> = messages[1..]
+            .iter()
+            .flat_map(|m| &m.content)
+            .map(|block| match block {
+                ContentBlock::ToolUse { id, .. } => id.as_str(),
+                ContentBlock::ToolResult { tool_use_id, .. } => tool_use_id.as_str(),
+                other => panic!("unexpected block {:?}", other),
+            })
+            .collect();
+        assert_eq!(ids, ["call-2", "call-2"]);
+    }
+
+    #[test]
+    fn recovery_stops_at_a_sequence_gap() {
+        let tmp = tempfile::tempdir().unwrap();
+        let session_dir = tmp.path().join("workspace_gap");
+        write_log(&session_dir, &["one", "two", "three", "four"]);
+
+        // Drop entry 3, as if it never reached the disk.
+        let run = log_runs_newest_first(&session_dir).unwrap().remove(0);
+        let content = std::fs::read_to_string(&run[0]).unwrap();
+        let kept: Vec<&str> = content
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 2)
+            .map(|(_, line)| line)
+            .collect();
+        std::fs::write(&run[0], kept.join("\n") + "\n").unwrap();
+
+        let entries = read_log_entries(&run[0]).unwrap();
+        let gap = SeqGap { after: 2, next: 4 };
+        assert_eq!(first_seq_gap(&entries), Some((2, gap)));
+
+        let mut snapshot = None;
+        let recovery = recover_session_in(&session_dir, Path::new("/ws"), &mut snapshot).unwrap();
+        assert_eq!(recovery.recovered, 2);
+        assert_eq!(recovery.gap, Some(gap));
+        assert!(
+            recovery
+                .describe()
+                .unwrap()
+                .contains("skips from entry 2 to 4")
+        );
+        assert_eq!(texts(&snapshot.unwrap().messages), ["one", "two"]);
+    }
+
+    #[test]
+    fn entries_without_sequence_numbers_have_no_gaps() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut entries = write_log(&tmp.path().join("legacy"), &["a", "b", "c"]);
+        for entry in &mut entries {
+            entry.seq = 0;
+        }
+        assert_eq!(first_seq_gap(&entries), None);
+
+        // A run that starts past 1 lost its beginning.
+        entries[0].seq = 3;
+        assert_eq!(
+            first_seq_gap(&entries),
+            Some((0, SeqGap { after: 0, next: 3 }))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn legacy_session_is_migrated_to_canonical_hash() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sessions = tmp.path().join("sessions");
+        let real = tmp.path().join("project");
+        std::fs::create_dir(&real).unwrap();
+        let link = tmp.path().join("alias");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        // Saved by an older version that hashed the symlinked path as typed.
+        let legacy_dir = sessions.join(legacy_workspace_hash(&link));
+        save_session_to(&legacy_dir.join("session.json"), &sample_session_state()).unwrap();
+
+        let loaded = load_session_in(&sessions, &link, SessionFormat::Both)
+            .unwrap()
+            .expect("legacy session should be found");
+        assert_eq!(loaded.messages.len(), 5);
+
+        let canonical_dir = sessions.join(workspace_hash(&real));
+        assert!(canonical_dir.join("session.json").exists());
+        assert!(!legacy_dir.exists());
+        // Both spellings now resolve to the migrated session.
+        assert!(
+            load_session_in(&sessions, &real, SessionFormat::Both)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn legacy_session_is_copied_when_canonical_dir_exists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sessions = tmp.path().join("sessions");
+        let workspace = tmp.path().join("project");
+        std::fs::create_dir(&workspace).unwrap();
+        let typed = PathBuf::from(format!("{}/", workspace.display()));
+
+        let legacy_dir = sessions.join(legacy_workspace_hash(&typed));
+        save_session_to(&legacy_dir.join("session.json"), &sample_session_state()).unwrap();
+        let canonical_dir = sessions.join(workspace_hash(&typed));
+        std::fs::create_dir_all(&canonical_dir).unwrap();
+
+        assert!(
+            load_session_in(&sessions, &typed, SessionFormat::Json)
+                .unwrap()
+                .is_some()
+        );
+        assert!(canonical_dir.join("session.json").exists());
+    }
+
+    #[test]
+    fn no_session_under_either_hash_returns_none() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sessions = tmp.path().join("sessions");
+        assert!(
+            load_session_in(&sessions, tmp.path(), SessionFormat::Jsonl)
+                .unwrap()
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,214 @@
+// ABOUTME: Resume validation — checks a saved session against the current provider setup.
+// ABOUTME: Catches missing credentials and model changes before the first request fails confusingly.
+
+use crate::config::LlmConfig;
+
+/// What to do with a saved session given the current LLM configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeCheck {
+    /// Everything lines up; resume as-is.
+    Resume,
+    /// The provider's API key is not set, so no request can succeed.
+    MissingCredentials {
+        provider: String,
+        env_var: &'static str,
+    },
+    /// The session was saved with a different model; resume with the
+    /// configured one. `foreign` is set when the saved model doesn't look
+    /// like it belongs to the current provider at all.
+    ModelSwitch {
+        saved: String,
+        current: String,
+        foreign: bool,
+    },
+}
+
+impl ResumeCheck {
+    /// A user-facing explanation, or None when there's nothing to say.
+    pub fn describe(&self) -> Option<String> {
+        match self {
+            ResumeCheck::Resume => None,
+            ResumeCheck::MissingCredentials { provider, env_var } => Some(format!(
+                "Cannot resume: provider '{}' needs {}, which is not set. \
+                 Add it to your secrets file (claw setup) or switch [llm] provider.",
+                provider, env_var
+            )),
+            ResumeCheck::ModelSwitch {
+                saved,
+                current,
+                foreign,
+            } => {
+                let why = if *foreign {
+                    " (not available from the current provider)"
+                } else {
+                    ""
+                };
+                Some(format!(
+                    "Session was saved with model '{}'{}; continuing with '{}'. \
+                     Run with --fresh to start over.",
+                    saved, why, current
+                ))
+            }
+        }
+    }
+}
+
+/// How to go on when the saved session can't be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeFallback {
+    /// Start a fresh session instead; the saved one stays until this one
+    /// saves over it.
+    Fresh,
+    /// Stop, leaving the saved session to resume once it can.
+    Quit,
+}
+
+impl ResumeFallback {
+    /// The question [`ResumeFallback::from_answer`] reads the answer to.
+    pub const QUESTION: &'static str = "Start a fresh session instead? [Y/n] ";
+
+    /// Fresh unless the user said no.
+    pub fn from_answer(answer: &str) -> Self {
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "n" | "no" | "q" | "quit" => ResumeFallback::Quit,
+            _ => ResumeFallback::Fresh,
+        }
+    }
+}
+
+/// Best-effort check that a model name belongs to a provider, based on the
+/// naming conventions each provider uses. Unknown providers accept anything.
+pub fn model_plausible_for(provider: &str, model: &str) -> bool {
+    let model = model.to_ascii_lowercase();
+    match provider {
+        "anthropic" => model.starts_with("claude"),
+        "openai" => ["gpt", "o1", "o3", "o4", "chatgpt", "codex"]
+            .iter()
+            .any(|p| model.starts_with(p)),
+        "gemini" => model.starts_with("gemini"),
+        // OpenRouter models are namespaced by vendor.
+        "openrouter" => model.contains('/'),
+        _ => true,
+    }
+}
+
+/// Validate resuming a session saved with `saved_model` under `llm`.
+/// `has_env` reports whether an environment variable is set and non-empty.
+pub fn check_resume(
+    saved_model: &str,
+    llm: &LlmConfig,
+    has_env: impl Fn(&str) -> bool,
+) -> ResumeCheck {
+    if let Some(env_var) = llm.api_key_var()
+        && !has_env(env_var)
+    {
+        return ResumeCheck::MissingCredentials {
+            provider: llm.provider.clone(),
+            env_var,
+        };
+    }
+
+    if saved_model.is_empty() || saved_model == llm.model {
+        return ResumeCheck::Resume;
+    }
+
+    ResumeCheck::ModelSwitch {
+        saved: saved_model.to_string(),
+        current: llm.model.clone(),
+        foreign: !model_plausible_for(&llm.provider, saved_model),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn llm(provider: &str, model: &str) -> LlmConfig {
+        LlmConfig {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_model_with_credentials_resumes() {
+        let config = llm("anthropic", "claude-sonnet-4-5");
+        assert_eq!(
+            check_resume("claude-sonnet-4-5", &config, |_| true),
+            ResumeCheck::Resume
+        );
+        assert!(ResumeCheck::Resume.describe().is_none());
+    }
+
+    #[test]
+    fn missing_credentials_blocks_resume() {
+        let config = llm("openai", "gpt-5.2");
+        let check = check_resume("gpt-5.2", &config, |var| var != "OPENAI_API_KEY");
+        assert_eq!(
+            check,
+            ResumeCheck::MissingCredentials {
+                provider: "openai".to_string(),
+                env_var: "OPENAI_API_KEY",
+            }
+        );
+        assert!(check.describe().unwrap().contains("OPENAI_API_KEY"));
+    }
+
+    #[test]
+    fn missing_credentials_falls_back_to_fresh_unless_declined() {
+        assert_eq!(ResumeFallback::from_answer("\n"), ResumeFallback::Fresh);
+        assert_eq!(ResumeFallback::from_answer("y"), ResumeFallback::Fresh);
+        assert_eq!(ResumeFallback::from_answer(" No\n"), ResumeFallback::Quit);
+        assert_eq!(ResumeFallback::from_answer("q"), ResumeFallback::Quit);
+    }
+
+    #[test]
+    fn ollama_needs_no_credentials() {
+        let config = llm("ollama", "llama3.2");
+        assert_eq!(
+            check_resume("llama3.2", &config, |_| false),
+            ResumeCheck::Resume
+        );
+    }
+
+    #[test]
+    fn model_from_other_provider_needs_switch() {
+        let config = llm("openai", "gpt-5.2");
+        let check = check_resume("claude-sonnet-4-5", &config, |_| true);
+        assert_eq!(
+            check,
+            ResumeCheck::ModelSwitch {
+                saved: "claude-sonnet-4-5".to_string(),
+                current: "gpt-5.2".to_string(),
+                foreign: true,
+            }
+        );
+        let text = check.describe().unwrap();
+        assert!(text.contains("not available from the current provider"));
+        assert!(text.contains("--fresh"));
+    }
+
+    #[test]
+    fn changed_model_same_provider_switches_quietly() {
+        let config = llm("anthropic", "claude-opus-4");
+        let check = check_resume("claude-3-5-sonnet", &config, |_| true);
+        assert!(matches!(
+            check,
+            ResumeCheck::ModelSwitch { foreign: false, .. }
+        ));
+    }
+
+    #[test]
+    fn plausibility_by_provider() {
+        assert!(model_plausible_for("anthropic", "claude-sonnet-4-5"));
+        assert!(!model_plausible_for("anthropic", "gpt-4o"));
+        assert!(model_plausible_for("openai", "o3-mini"));
+        assert!(model_plausible_for(
+            "openrouter",
+            "anthropic/claude-sonnet-4"
+        ));
+        assert!(!model_plausible_for("openrouter", "claude-sonnet-4"));
+        assert!(model_plausible_for("ollama", "anything"));
+    }
+}
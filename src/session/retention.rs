@@ -0,0 +1,447 @@
+// ABOUTME: Session retention — prunes old or oversized per-workspace session directories.
+// ABOUTME: Applies [sessions] age, count, and total-size limits, oldest first, sparing the current workspace.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::config::SessionsConfig;
+
+/// On-disk summary of a single workspace's session directory.
+#[derive(Debug, Clone)]
+pub struct SessionDirInfo {
+    pub path: PathBuf,
+    /// Directory name, i.e. the workspace hash.
+    pub hash: String,
+    /// Most recent modification time of any file under the directory.
+    pub modified: SystemTime,
+    /// Total size of all files under the directory, including subdirectories
+    /// such as the saved file copies in `changes/`, in bytes.
+    pub bytes: u64,
+}
+
+/// Why a session directory was selected for removal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneReason {
+    MaxAge,
+    MaxSessions,
+    MaxTotalBytes,
+}
+
+impl std::fmt::Display for PruneReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PruneReason::MaxAge => write!(f, "older than max_age_days"),
+            PruneReason::MaxSessions => write!(f, "beyond max_sessions"),
+            PruneReason::MaxTotalBytes => write!(f, "over max_total_bytes"),
+        }
+    }
+}
+
+/// A session directory that was (or, in dry-run mode, would be) removed.
+#[derive(Debug, Clone)]
+pub struct PrunedSession {
+    pub info: SessionDirInfo,
+    pub reason: PruneReason,
+}
+
+/// Result of a prune pass.
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub removed: Vec<PrunedSession>,
+    pub kept: usize,
+    pub dry_run: bool,
+}
+
+impl PruneReport {
+    /// Total bytes freed (or that would be freed) by this prune.
+    pub fn freed_bytes(&self) -> u64 {
+        self.removed.iter().map(|p| p.info.bytes).sum()
+    }
+
+    /// Human-readable lines describing what was removed.
+    pub fn describe(&self) -> Vec<String> {
+        let verb = if self.dry_run {
+            "would remove"
+        } else {
+            "removed"
+        };
+        let mut lines: Vec<String> = self
+            .removed
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} {} ({} bytes, {})",
+                    verb,
+                    p.info.path.display(),
+                    p.info.bytes,
+                    p.reason
+                )
+            })
+            .collect();
+        lines.push(format!(
+            "{} {} session(s), {} bytes; kept {}",
+            verb,
+            self.removed.len(),
+            self.freed_bytes(),
+            self.kept
+        ));
+        lines
+    }
+}
+
+/// Scan a sessions root and return one entry per workspace directory.
+pub fn scan_sessions(root: &Path) -> anyhow::Result<Vec<SessionDirInfo>> {
+    let mut sessions = Vec::new();
+    if !root.exists() {
+        return Ok(sessions);
+    }
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let mut bytes = 0u64;
+        let mut latest: Option<SystemTime> = None;
+        tally_files(&path, &mut bytes, &mut latest)?;
+        // Empty directories fall back to their own mtime.
+        let modified = match latest {
+            Some(m) => m,
+            None => entry
+                .metadata()?
+                .modified()
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        };
+
+        sessions.push(SessionDirInfo {
+            hash: entry.file_name().to_string_lossy().to_string(),
+            path,
+            modified,
+            bytes,
+        });
+    }
+
+    Ok(sessions)
+}
+
+/// Add up the sizes and latest mtime of every file under `dir`, descending
+/// into subdirectories. Symlinks are neither followed nor counted.
+fn tally_files(dir: &Path, bytes: &mut u64, latest: &mut Option<SystemTime>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            tally_files(&entry.path(), bytes, latest)?;
+            continue;
+        }
+        if !meta.is_file() {
+            continue;
+        }
+        *bytes += meta.len();
+        if let Ok(m) = meta.modified()
+            && latest.is_none_or(|l| m > l)
+        {
+            *latest = Some(m);
+        }
+    }
+    Ok(())
+}
+
+/// Select which sessions exceed the configured limits.
+///
+/// Sessions are considered oldest-first. The session whose hash matches
+/// `current_hash` is never selected and still counts toward the limits.
+pub fn select_for_pruning(
+    mut sessions: Vec<SessionDirInfo>,
+    config: &SessionsConfig,
+    current_hash: Option<&str>,
+    now: SystemTime,
+) -> (Vec<PrunedSession>, usize) {
+    // Newest first, so the tail holds the best candidates for removal.
+    sessions.sort_by(|a, b| b.modified.cmp(&a.modified));
+
+    let is_current = |s: &SessionDirInfo| current_hash.is_some_and(|h| h == s.hash);
+    let mut removed: Vec<PrunedSession> = Vec::new();
+    let mut kept: Vec<SessionDirInfo> = Vec::new();
+
+    let max_age = if config.max_age_days > 0 {
+        Some(Duration::from_secs(config.max_age_days * 24 * 60 * 60))
+    } else {
+        None
+    };
+    for session in sessions {
+        let too_old = max_age.is_some_and(|max| {
+            now.duration_since(session.modified)
+                .is_ok_and(|age| age > max)
+        });
+        if too_old && !is_current(&session) {
+            removed.push(PrunedSession {
+                info: session,
+                reason: PruneReason::MaxAge,
+            });
+        } else {
+            kept.push(session);
+        }
+    }
+
+    if config.max_sessions > 0 {
+        while kept.len() > config.max_sessions {
+            let Some(idx) = kept.iter().rposition(|s| !is_current(s)) else {
+                break;
+            };
+            removed.push(PrunedSession {
+                info: kept.remove(idx),
+                reason: PruneReason::MaxSessions,
+            });
+        }
+    }
+
+    if config.max_total_bytes > 0 {
+        let mut total: u64 = kept.iter().map(|s| s.bytes).sum();
+        while total > config.max_total_bytes {
+            let Some(idx) = kept.iter().rposition(|s| !is_current(s)) else {
+                break;
+            };
+            let info = kept.remove(idx);
+            total -= info.bytes;
+            removed.push(PrunedSession {
+                info,
+                reason: PruneReason::MaxTotalBytes,
+            });
+        }
+    }
+
+    (removed, kept.len())
+}
+
+/// Prune session directories under `root` according to `config`.
+///
+/// In dry-run mode nothing is deleted; the report lists what would be removed.
+pub fn prune_sessions(
+    root: &Path,
+    config: &SessionsConfig,
+    current_hash: Option<&str>,
+    dry_run: bool,
+) -> anyhow::Result<PruneReport> {
+    let sessions = scan_sessions(root)?;
+    let (removed, kept) = select_for_pruning(sessions, config, current_hash, SystemTime::now());
+
+    if !dry_run {
+        for pruned in &removed {
+            fs::remove_dir_all(&pruned.info.path)?;
+        }
+    }
+
+    Ok(PruneReport {
+        removed,
+        kept,
+        dry_run,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::changes::SAVED_DIR;
+    use std::fs::File;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Create a session dir with one file of `bytes` size, last modified `age` ago.
+    fn make_session(root: &Path, hash: &str, bytes: usize, age: Duration) {
+        let dir = root.join(hash);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+        fs::write(&path, vec![b'x'; bytes]).unwrap();
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    fn unlimited() -> SessionsConfig {
+        SessionsConfig {
+            max_age_days: 0,
+            max_total_bytes: 0,
+            max_sessions: 0,
+            ..SessionsConfig::default()
+        }
+    }
+
+    fn remaining(root: &Path) -> Vec<String> {
+        let mut names: Vec<String> = scan_sessions(root)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.hash)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn scan_missing_root_is_empty() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sessions = scan_sessions(&tmp.path().join("nope")).unwrap();
+        assert!(sessions.is_empty());
+    }
+
+    #[test]
+    fn scan_sums_file_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "aaaa", 100, Duration::ZERO);
+        fs::write(tmp.path().join("aaaa").join("log.jsonl"), vec![b'y'; 50]).unwrap();
+        let sessions = scan_sessions(tmp.path()).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].bytes, 150);
+    }
+
+    #[test]
+    fn scan_counts_saved_changes_toward_max_total_bytes() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "old", 100, DAY * 2);
+        make_session(tmp.path(), "new", 100, DAY);
+        let saved = tmp.path().join("old").join(SAVED_DIR).join("ab");
+        fs::create_dir_all(&saved).unwrap();
+        fs::write(saved.join("blob"), vec![b'z'; 900]).unwrap();
+        let blob = File::options()
+            .write(true)
+            .open(saved.join("blob"))
+            .unwrap();
+        blob.set_modified(SystemTime::now() - DAY * 2).unwrap();
+
+        let sessions = scan_sessions(tmp.path()).unwrap();
+        let old = sessions.iter().find(|s| s.hash == "old").unwrap();
+        assert_eq!(old.bytes, 1000);
+
+        // Top-level files alone (200 bytes) would fit; the blob store does not.
+        let config = SessionsConfig {
+            max_total_bytes: 500,
+            ..unlimited()
+        };
+        let report = prune_sessions(tmp.path(), &config, None, false).unwrap();
+
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].info.hash, "old");
+        assert_eq!(report.removed[0].reason, PruneReason::MaxTotalBytes);
+        assert_eq!(remaining(tmp.path()), vec!["new"]);
+    }
+
+    #[test]
+    fn prunes_sessions_older_than_max_age() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "old", 10, DAY * 40);
+        make_session(tmp.path(), "new", 10, DAY);
+        let config = SessionsConfig {
+            max_age_days: 30,
+            ..unlimited()
+        };
+
+        let report = prune_sessions(tmp.path(), &config, None, false).unwrap();
+
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].info.hash, "old");
+        assert_eq!(report.removed[0].reason, PruneReason::MaxAge);
+        assert_eq!(report.kept, 1);
+        assert_eq!(remaining(tmp.path()), vec!["new"]);
+    }
+
+    #[test]
+    fn prunes_oldest_beyond_max_total_bytes() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "a", 400, DAY * 3);
+        make_session(tmp.path(), "b", 400, DAY * 2);
+        make_session(tmp.path(), "c", 400, DAY);
+        let config = SessionsConfig {
+            max_total_bytes: 1000,
+            ..unlimited()
+        };
+
+        let report = prune_sessions(tmp.path(), &config, None, false).unwrap();
+
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].info.hash, "a");
+        assert_eq!(report.removed[0].reason, PruneReason::MaxTotalBytes);
+        assert_eq!(report.freed_bytes(), 400);
+        assert_eq!(remaining(tmp.path()), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn prunes_oldest_beyond_max_sessions() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "a", 10, DAY * 3);
+        make_session(tmp.path(), "b", 10, DAY * 2);
+        make_session(tmp.path(), "c", 10, DAY);
+        let config = SessionsConfig {
+            max_sessions: 2,
+            ..unlimited()
+        };
+
+        let report = prune_sessions(tmp.path(), &config, None, false).unwrap();
+
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].reason, PruneReason::MaxSessions);
+        assert_eq!(remaining(tmp.path()), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn dry_run_deletes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "old", 10, DAY * 400);
+        let config = SessionsConfig {
+            max_age_days: 30,
+            ..unlimited()
+        };
+
+        let report = prune_sessions(tmp.path(), &config, None, true).unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(remaining(tmp.path()), vec!["old"]);
+        assert!(report.describe()[0].starts_with("would remove"));
+    }
+
+    #[test]
+    fn current_session_is_never_pruned() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "current", 5000, DAY * 400);
+        make_session(tmp.path(), "other", 10, DAY);
+        let config = SessionsConfig {
+            max_age_days: 30,
+            max_total_bytes: 100,
+            max_sessions: 1,
+            ..SessionsConfig::default()
+        };
+
+        let report = prune_sessions(tmp.path(), &config, Some("current"), false).unwrap();
+
+        assert!(report.removed.iter().all(|p| p.info.hash != "current"));
+        assert_eq!(remaining(tmp.path()), vec!["current"]);
+    }
+
+    #[test]
+    fn default_config_prunes_nothing() {
+        let tmp = tempfile::tempdir().unwrap();
+        for i in 0..201 {
+            make_session(tmp.path(), &format!("s{i}"), 10, DAY * 1000);
+        }
+
+        let report = prune_sessions(tmp.path(), &SessionsConfig::default(), None, false).unwrap();
+
+        assert!(report.removed.is_empty());
+        assert_eq!(report.kept, 201);
+        assert_eq!(remaining(tmp.path()).len(), 201);
+    }
+
+    #[test]
+    fn unlimited_config_keeps_everything() {
+        let tmp = tempfile::tempdir().unwrap();
+        make_session(tmp.path(), "a", 10_000, DAY * 1000);
+        make_session(tmp.path(), "b", 10_000, DAY);
+
+        let report = prune_sessions(tmp.path(), &unlimited(), None, false).unwrap();
+
+        assert!(report.removed.is_empty());
+        assert_eq!(report.kept, 2);
+    }
+}
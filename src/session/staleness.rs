@@ -0,0 +1,279 @@
+// ABOUTME: Stale sessions — how long a resumed session sat idle and what git says changed in the meantime.
+// ABOUTME: Feeds a startup warning, and a note telling the model its memory of the workspace may be out of date.
+
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Runs git with the given arguments in the workspace; the stdout of a
+/// successful run, else None.
+pub type Git<'a> = &'a dyn Fn(&[&str]) -> Option<String>;
+
+/// What changed in a git workspace since a session was last active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkspaceDrift {
+    pub commits: usize,
+    /// Files that differ from the commit the session was saved at, edits
+    /// not yet committed included.
+    pub files_changed: usize,
+}
+
+impl WorkspaceDrift {
+    /// "12 commits and 34 files changed since this session was last active".
+    pub fn describe(&self) -> String {
+        format!(
+            "{} and {} changed since this session was last active",
+            plural(self.commits, "commit"),
+            plural(self.files_changed, "file")
+        )
+    }
+}
+
+/// A resumed session's age and drift, once it's worth mentioning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Staleness {
+    /// Set when the session is older than the threshold.
+    pub age: Option<Duration>,
+    pub drift: Option<WorkspaceDrift>,
+}
+
+impl Staleness {
+    /// The startup warning.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(age) = self.age {
+            parts.push(format!(
+                "This session was last active {} ago; files may have changed since the model \
+                 last read them.",
+                format_age(age)
+            ));
+        }
+        if let Some(drift) = self.drift {
+            parts.push(format!("{}.", capitalize(&drift.describe())));
+        }
+        parts.join(" ")
+    }
+
+    /// The note added to the system prompt of the first request.
+    pub fn context_note(&self) -> String {
+        format!(
+            "Note: this conversation is being resumed. {} Re-read any file before relying on what \
+             earlier messages say it contains.",
+            self.describe()
+        )
+    }
+}
+
+/// The commit checked out in `workspace`, or None outside a git repo.
+pub fn head_commit(workspace: &Path) -> Option<String> {
+    let commit = run_git(workspace, &["rev-parse", "HEAD"])?;
+    Some(commit.trim().to_string()).filter(|c| !c.is_empty())
+}
+
+/// Run git in `workspace`.
+pub fn run_git(workspace: &Path, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(workspace)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The age past which a session is stale, from `stale_after_hours`. None
+/// when 0 turns the warning off, or the value is too large to be a duration.
+pub fn stale_threshold(hours: u64) -> Option<Duration> {
+    if hours == 0 {
+        return None;
+    }
+    Duration::try_hours(i64::try_from(hours).ok()?)
+}
+
+/// How stale a session last saved at `updated_at` (RFC 3339) is at `now`:
+/// its age once past `threshold` (None leaves the age out), and, in a git
+/// workspace, the commits since then and files changed since `commit`. None
+/// when neither is worth a warning.
+pub fn check_staleness(
+    updated_at: &str,
+    commit: Option<&str>,
+    now: DateTime<Utc>,
+    threshold: Option<Duration>,
+    git: Git,
+) -> Option<Staleness> {
+    let saved = DateTime::parse_from_rfc3339(updated_at)
+        .ok()?
+        .with_timezone(&Utc);
+    let age = now.signed_duration_since(saved);
+    let age = threshold.filter(|t| age > *t).map(|_| age);
+    let drift = workspace_drift(&saved.to_rfc3339(), commit, git);
+    // Fresh sessions are only worth a word when the history moved.
+    let drift = drift.filter(|d| age.is_some() || d.commits > 0);
+    (age.is_some() || drift.is_some()).then_some(Staleness { age, drift })
+}
+
+/// Commits since `since` and files changed since `commit`, when git can
+/// tell; a commit that's gone (rebased away, say) counts no files.
+fn workspace_drift(since: &str, commit: Option<&str>, git: Git) -> Option<WorkspaceDrift> {
+    let since_arg = format!("--since={}", since);
+    let log = git(&["log", "--oneline", since_arg.as_str()])?;
+    let commits = log.lines().filter(|l| !l.trim().is_empty()).count();
+    let files_changed = commit
+        .and_then(|commit| git(&["diff", "--stat", commit]))
+        .map_or(0, |stat| files_in_diff_stat(&stat));
+    (commits > 0 || files_changed > 0).then_some(WorkspaceDrift {
+        commits,
+        files_changed,
+    })
+}
+
+/// The file count from `git diff --stat`'s summary line, e.g.
+/// " 34 files changed, 120 insertions(+), 8 deletions(-)".
+fn files_in_diff_stat(stat: &str) -> usize {
+    stat.lines()
+        .rev()
+        .find(|l| l.contains(" changed"))
+        .and_then(|l| l.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0)
+}
+
+/// "3 days", "5 hours", or "40 minutes".
+fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        plural(age.num_days() as usize, "day")
+    } else if age.num_hours() > 0 {
+        plural(age.num_hours() as usize, "hour")
+    } else {
+        plural(age.num_minutes().max(0) as usize, "minute")
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAVED: &str = "2026-10-01T12:00:00+00:00";
+
+    fn at(days: i64) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(SAVED)
+            .unwrap()
+            .with_timezone(&Utc)
+            + Duration::days(days)
+    }
+
+    fn no_git(_: &[&str]) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn only_sessions_past_the_threshold_are_stale() {
+        let week = Some(Duration::days(7));
+        assert_eq!(check_staleness(SAVED, None, at(6), week, &no_git), None);
+        let stale = check_staleness(SAVED, None, at(8), week, &no_git).unwrap();
+        assert_eq!(stale.age, Some(Duration::days(8)));
+        assert_eq!(
+            stale.describe(),
+            "This session was last active 8 days ago; files may have changed since the model \
+             last read them."
+        );
+        assert_eq!(check_staleness(SAVED, None, at(30), None, &no_git), None);
+        assert_eq!(
+            check_staleness("yesterday", None, at(30), week, &no_git),
+            None
+        );
+    }
+
+    #[test]
+    fn git_history_since_the_save_is_summarized() {
+        let git = |args: &[&str]| -> Option<String> {
+            match args {
+                ["log", "--oneline", since] => {
+                    assert_eq!(*since, "--since=2026-10-01T12:00:00+00:00");
+                    Some("a1b2c3d Fix parser\n9f8e7d6 Add tests\n".to_string())
+                }
+                ["diff", "--stat", "abc123"] => Some(
+                    " src/lib.rs | 4 ++--\n src/main.rs | 1 +\n 2 files changed, 3 insertions(+), 2 deletions(-)\n"
+                        .to_string(),
+                ),
+                _ => None,
+            }
+        };
+        let stale =
+            check_staleness(SAVED, Some("abc123"), at(10), Some(Duration::days(3)), &git).unwrap();
+        assert_eq!(
+            stale.drift,
+            Some(WorkspaceDrift {
+                commits: 2,
+                files_changed: 2
+            })
+        );
+        assert!(
+            stale
+                .describe()
+                .ends_with("2 commits and 2 files changed since this session was last active.")
+        );
+        assert!(
+            stale
+                .context_note()
+                .starts_with("Note: this conversation is being resumed.")
+        );
+
+        // A recent session is mentioned only when commits landed since.
+        let recent = check_staleness(SAVED, Some("abc123"), at(1), Some(Duration::days(3)), &git);
+        assert_eq!(recent.unwrap().age, None);
+        let quiet = |args: &[&str]| -> Option<String> {
+            match args {
+                ["log", ..] => Some(String::new()),
+                _ => Some(" 1 file changed, 1 insertion(+)\n".to_string()),
+            }
+        };
+        assert_eq!(
+            check_staleness(
+                SAVED,
+                Some("abc123"),
+                at(1),
+                Some(Duration::days(3)),
+                &quiet
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn drift_reads_singular_counts() {
+        let drift = WorkspaceDrift {
+            commits: 1,
+            files_changed: 34,
+        };
+        assert_eq!(
+            drift.describe(),
+            "1 commit and 34 files changed since this session was last active"
+        );
+        assert_eq!(files_in_diff_stat(" 1 file changed, 2 deletions(-)"), 1);
+        assert_eq!(files_in_diff_stat(""), 0);
+        assert_eq!(format_age(Duration::hours(5)), "5 hours");
+    }
+
+    #[test]
+    fn zero_and_out_of_range_thresholds_turn_the_warning_off() {
+        assert_eq!(stale_threshold(72), Some(Duration::hours(72)));
+        assert_eq!(stale_threshold(0), None);
+        assert_eq!(stale_threshold(u64::MAX), None);
+        assert_eq!(stale_threshold(i64::MAX as u64), None);
+    }
+}
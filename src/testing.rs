@@ -0,0 +1,745 @@
+// ABOUTME: Test support — MockLlmClient replays scripted responses; LoopHarness runs the real agent loop on it.
+// ABOUTME: Compiled for the crate's own tests and for dependents via the `testing` feature.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::Stream;
+use mux::llm::LlmError;
+use mux::prelude::*;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::agent::compaction::SummaryDecision;
+use crate::agent::effort::Reasoning;
+use crate::agent::fallback::Fallbacks;
+use crate::agent::pins::Pins;
+use crate::agent::shutdown::ShutdownFlag;
+use crate::agent::timeouts::RequestTimeouts;
+use crate::agent::{AgentLoopParams, run_agent_loop};
+use crate::approval::{ApprovalDecision, ApprovalEngine};
+use crate::config::{
+    CompactionConfig, PendingToolCalls, PrivacyConfig, SessionFormat, StreamingMode, ToolsConfig,
+};
+use crate::events::EventRecord;
+use crate::prompt::SystemPromptParams;
+use crate::session::persistence::load_from_jsonl;
+use crate::session::{AutoSave, SessionLogger};
+use crate::tools::mcp::{McpLink, McpServer};
+use crate::tools::roster::McpRoster;
+use crate::tui::state::{AgentEvent, UserEvent};
+
+/// One scripted LLM response, built up part by part.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedResponse {
+    parts: Vec<Part>,
+    stop_reason: Option<StopReason>,
+    input_tokens: u32,
+    output_tokens: u32,
+    cache_read_tokens: u32,
+    cache_write_tokens: u32,
+    stream_error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+}
+
+impl ScriptedResponse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a text delta. Consecutive deltas form one text block.
+    pub fn text(mut self, delta: impl Into<String>) -> Self {
+        self.parts.push(Part::Text(delta.into()));
+        self
+    }
+
+    /// Append a tool-use block.
+    pub fn tool_use(
+        mut self,
+        id: impl Into<String>,
+        name: impl Into<String>,
+        input: serde_json::Value,
+    ) -> Self {
+        self.parts.push(Part::ToolUse {
+            id: id.into(),
+            name: name.into(),
+            input,
+        });
+        self
+    }
+
+    /// Report this token usage with the response.
+    pub fn usage(mut self, input_tokens: u32, output_tokens: u32) -> Self {
+        self.input_tokens = input_tokens;
+        self.output_tokens = output_tokens;
+        self
+    }
+
+    /// Report prompt tokens read from and written to the provider's cache.
+    pub fn cache_usage(mut self, read_tokens: u32, write_tokens: u32) -> Self {
+        self.cache_read_tokens = read_tokens;
+        self.cache_write_tokens = write_tokens;
+        self
+    }
+
+    /// Fail with this error after the parts so far have streamed, before
+    /// the response completes. Non-streaming requests fail outright.
+    pub fn stream_error(mut self, message: impl Into<String>) -> Self {
+        self.stream_error = Some(message.into());
+        self
+    }
+
+    /// Override the stop reason. Defaults to ToolUse when the response has a
+    /// tool-use block and EndTurn otherwise.
+    pub fn stop(mut self, reason: StopReason) -> Self {
+        self.stop_reason = Some(reason);
+        self
+    }
+
+    fn stop_reason(&self) -> StopReason {
+        self.stop_reason.as_ref().cloned().unwrap_or_else(|| {
+            if self.parts.iter().any(|p| matches!(p, Part::ToolUse { .. })) {
+                StopReason::ToolUse
+            } else {
+                StopReason::EndTurn
+            }
+        })
+    }
+
+    fn usage_value(&self) -> Usage {
+        Usage {
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+            cache_write_tokens: self.cache_write_tokens,
+        }
+    }
+
+    /// The response as content blocks, for non-streaming requests.
+    pub fn content(&self) -> Vec<ContentBlock> {
+        let mut blocks = Vec::new();
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(delta) => text.push_str(delta),
+                Part::ToolUse { id, name, input } => {
+                    if !text.is_empty() {
+                        blocks.push(ContentBlock::text(&text));
+                        text.clear();
+                    }
+                    blocks.push(ContentBlock::ToolUse {
+                        id: id.clone(),
+                        name: name.clone(),
+                        input: input.clone(),
+                    });
+                }
+            }
+        }
+        if !text.is_empty() {
+            blocks.push(ContentBlock::text(&text));
+        }
+        blocks
+    }
+
+    /// The response as the stream events a provider would send.
+    pub fn events(&self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        let mut index = 0;
+        let mut in_text = false;
+        for part in &self.parts {
+            match part {
+                Part::Text(delta) => {
+                    if !in_text {
+                        events.push(StreamEvent::ContentBlockStart {
+                            index,
+                            block: ContentBlock::text(""),
+                        });
+                        in_text = true;
+                    }
+                    events.push(StreamEvent::ContentBlockDelta {
+                        index,
+                        text: delta.clone(),
+                    });
+                }
+                Part::ToolUse { id, name, input } => {
+                    if in_text {
+                        events.push(StreamEvent::ContentBlockStop { index });
+                        index += 1;
+                        in_text = false;
+                    }
+                    events.push(StreamEvent::ContentBlockStart {
+                        index,
+                        block: ContentBlock::ToolUse {
+                            id: id.clone(),
+                            name: name.clone(),
+                            input: serde_json::json!({}),
+                        },
+                    });
+                    events.push(StreamEvent::InputJsonDelta {
+                        index,
+                        partial_json: input.to_string(),
+                    });
+                    events.push(StreamEvent::ContentBlockStop { index });
+                    index += 1;
+                }
+            }
+        }
+        if in_text {
+            events.push(StreamEvent::ContentBlockStop { index });
+        }
+        events.push(StreamEvent::MessageDelta {
+            stop_reason: Some(self.stop_reason()),
+            usage: self.usage_value(),
+        });
+        events.push(StreamEvent::MessageStop);
+        events
+    }
+}
+
+/// An `LlmClient` that answers each request with the next scripted response,
+/// streaming or not, and keeps every request for later assertions. Once the
+/// script runs out it answers with a short "script exhausted" text.
+pub struct MockLlmClient {
+    script: Mutex<VecDeque<ScriptedResponse>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MockLlmClient {
+    pub fn new(script: Vec<ScriptedResponse>) -> Self {
+        Self {
+            script: Mutex::new(script.into()),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .lock()
+            .expect("requests lock poisoned")
+            .clone()
+    }
+
+    /// Scripted responses not yet used.
+    pub fn remaining(&self) -> usize {
+        self.script.lock().expect("script lock poisoned").len()
+    }
+
+    fn next(&self, req: &Request) -> ScriptedResponse {
+        self.requests
+            .lock()
+            .expect("requests lock poisoned")
+            .push(req.clone());
+        self.script
+            .lock()
+            .expect("script lock poisoned")
+            .pop_front()
+            .unwrap_or_else(|| ScriptedResponse::new().text("[mock script exhausted]"))
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmClient for MockLlmClient {
+    async fn create_message(&self, req: &Request) -> Result<Response, LlmError> {
+        let scripted = self.next(req);
+        if let Some(message) = &scripted.stream_error {
+            return Err(LlmError::Stream(message.clone()));
+        }
+        Ok(Response {
+            id: "mock".to_string(),
+            content: scripted.content(),
+            model: req.model.clone(),
+            stop_reason: scripted.stop_reason(),
+            usage: scripted.usage_value(),
+        })
+    }
+
+    fn create_message_stream(
+        &self,
+        req: &Request,
+    ) -> Pin<Box<dyn Stream<Item = Result<StreamEvent, LlmError>> + Send + '_>> {
+        let scripted = self.next(req);
+        let mut events: Vec<Result<StreamEvent, LlmError>> =
+            scripted.events().into_iter().map(Ok).collect();
+        if let Some(message) = scripted.stream_error {
+            // Cut the stream before MessageDelta and MessageStop.
+            events.truncate(events.len() - 2);
+            events.push(Err(LlmError::Stream(message)));
+        }
+        Box::pin(futures::stream::iter(events))
+    }
+}
+
+/// An in-memory tool that returns a fixed result and records the params of
+/// every call. Clones share the call log, so keep one to inspect after
+/// registering the other.
+#[derive(Clone)]
+pub struct FakeTool {
+    name: String,
+    output: String,
+    is_error: bool,
+    calls: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl FakeTool {
+    pub fn new(name: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            output: output.into(),
+            is_error: false,
+            calls: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// A tool whose every call fails with `message`.
+    pub fn failing(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            is_error: true,
+            ..Self::new(name, message)
+        }
+    }
+
+    /// Params of every call so far, oldest first.
+    pub fn calls(&self) -> Vec<serde_json::Value> {
+        self.calls.lock().expect("calls lock poisoned").clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for FakeTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "in-memory test tool"
+    }
+
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({"type": "object"})
+    }
+
+    fn requires_approval(&self, _params: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        self.calls.lock().expect("calls lock poisoned").push(params);
+        Ok(if self.is_error {
+            ToolResult::error(self.output.clone())
+        } else {
+            ToolResult::text(self.output.clone())
+        })
+    }
+}
+
+/// Answers the prompts a turn blocks on, the way a user at the TUI would.
+#[derive(Debug, Clone)]
+pub struct Autopilot {
+    approval: ApprovalDecision,
+    answers: VecDeque<String>,
+    review: SummaryDecision,
+}
+
+impl Autopilot {
+    /// Allow every tool call once and accept compaction summaries.
+    pub fn approving() -> Self {
+        Self {
+            approval: ApprovalDecision::AllowOnce,
+            answers: VecDeque::new(),
+            review: SummaryDecision::Accept,
+        }
+    }
+
+    /// Deny every tool call.
+    pub fn denying() -> Self {
+        Self {
+            approval: ApprovalDecision::Deny,
+            ..Self::approving()
+        }
+    }
+
+    /// Queue an answer for the next ask_user question.
+    pub fn answering(mut self, answer: impl Into<String>) -> Self {
+        self.answers.push_back(answer.into());
+        self
+    }
+
+    /// Answer compaction summary reviews with `decision`.
+    pub fn reviewing(mut self, decision: SummaryDecision) -> Self {
+        self.review = decision;
+        self
+    }
+
+    /// Answer the prompt `event` carries, if any, consuming its responder.
+    /// Questions with no queued answer get "[no scripted answer]".
+    pub fn respond(&mut self, event: AgentEvent) {
+        match event {
+            AgentEvent::ToolCallNeedsApproval { responder, .. } => {
+                let _ = responder.send(self.approval);
+            }
+            AgentEvent::AskUser { responder, .. } => {
+                let answer = self
+                    .answers
+                    .pop_front()
+                    .unwrap_or_else(|| "[no scripted answer]".to_string());
+                let _ = responder.send(answer);
+            }
+            AgentEvent::CompactionSummaryReady { responder, .. } => {
+                let _ = responder.send(self.review.clone());
+            }
+            AgentEvent::OpenInEditor { responder, .. } => {
+                let _ = responder.send(Err("no editor in tests".to_string()));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A fake MCP server offering a FakeTool for each name in a list the test
+/// can change, as a server whose tools come and go while it runs.
+#[derive(Clone, Default)]
+pub struct FakeMcpClient {
+    tools: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeMcpClient {
+    pub fn new(tools: &[&str]) -> Self {
+        let client = Self::default();
+        client.offer(tools);
+        client
+    }
+
+    /// Offer `tools` from the next listing on.
+    pub fn offer(&self, tools: &[&str]) {
+        *self.tools.lock().expect("tools lock poisoned") =
+            tools.iter().map(|tool| tool.to_string()).collect();
+    }
+}
+
+#[async_trait::async_trait]
+impl McpLink for FakeMcpClient {
+    async fn list_tools(&self, tools: &Registry, _server: &str) -> anyhow::Result<()> {
+        let names = self.tools.lock().expect("tools lock poisoned").clone();
+        for name in names {
+            tools.register(FakeTool::new(name, "ok")).await;
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) {}
+}
+
+/// Receive events until `Done` or `ResumeCompacted` (or the loop exits), answering prompts with
+/// `autopilot`. Each event is mirrored as it would appear in
+/// `--json-events`, numbered from 1 in place of responders.
+pub async fn capture_turn(
+    agent_rx: &mut mpsc::Receiver<AgentEvent>,
+    autopilot: &mut Autopilot,
+) -> Vec<EventRecord> {
+    let mut records = Vec::new();
+    while let Some(event) = agent_rx.recv().await {
+        let done = matches!(event, AgentEvent::Done | AgentEvent::ResumeCompacted);
+        records.push(EventRecord::from_agent_event(
+            &event,
+            records.len() as u64 + 1,
+        ));
+        autopilot.respond(event);
+        if done {
+            break;
+        }
+    }
+    records
+}
+
+/// The `event` names of captured records, for comparing with a golden list.
+pub fn event_names(records: &[EventRecord]) -> Vec<String> {
+    records
+        .iter()
+        .map(|record| {
+            serde_json::to_value(record).expect("event records serialize")["event"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Model name the harness passes to the loop; unknown to pricing and
+/// context window tables, so it gets the defaults.
+pub const HARNESS_MODEL: &str = "mock-model";
+
+/// The real agent loop running in the background against a scripted client,
+/// with an approval engine, workspace, and JSONL session log under one
+/// directory. Unknown tools use default security, so every FakeTool call
+/// asks for approval.
+pub struct LoopHarness {
+    user_tx: mpsc::Sender<UserEvent>,
+    agent_rx: mpsc::Receiver<AgentEvent>,
+    handle: JoinHandle<()>,
+    dir: PathBuf,
+    session_dir: PathBuf,
+}
+
+impl LoopHarness {
+    /// Start `run_agent_loop` with `client`, `tools`, and streaming on.
+    pub async fn start(
+        client: Arc<dyn LlmClient>,
+        tools: Vec<FakeTool>,
+        dir: &Path,
+        compaction_config: CompactionConfig,
+    ) -> anyhow::Result<Self> {
+        Self::resume(client, tools, dir, compaction_config, Vec::new()).await
+    }
+
+    /// Like `start`, with `history` as the resumed conversation.
+    pub async fn resume(
+        client: Arc<dyn LlmClient>,
+        tools: Vec<FakeTool>,
+        dir: &Path,
+        compaction_config: CompactionConfig,
+        history: Vec<Message>,
+    ) -> anyhow::Result<Self> {
+        Self::resume_settling(
+            client,
+            tools,
+            dir,
+            compaction_config,
+            history,
+            PendingToolCalls::default(),
+        )
+        .await
+    }
+
+    /// Like `resume`, settling tool calls `history` left unanswered as
+    /// `pending_tool_calls` says.
+    pub async fn resume_settling(
+        client: Arc<dyn LlmClient>,
+        tools: Vec<FakeTool>,
+        dir: &Path,
+        compaction_config: CompactionConfig,
+        history: Vec<Message>,
+        pending_tool_calls: PendingToolCalls,
+    ) -> anyhow::Result<Self> {
+        let registry = Registry::new();
+        for tool in tools {
+            registry.register(tool).await;
+        }
+        Self::launch(
+            client,
+            registry,
+            dir,
+            compaction_config,
+            history,
+            pending_tool_calls,
+            McpRoster::default(),
+        )
+        .await
+    }
+
+    /// Like `start`, with `servers` connected and their tools re-listed
+    /// every `resync_every`.
+    pub async fn with_mcp(
+        client: Arc<dyn LlmClient>,
+        servers: Vec<Arc<McpServer>>,
+        resync_every: Duration,
+        dir: &Path,
+    ) -> anyhow::Result<Self> {
+        let registry = Registry::new();
+        let mut mcp = McpRoster::default().with_resync_every(Some(resync_every));
+        for server in servers {
+            mcp.admit(server, &registry, &ToolsConfig::default()).await;
+        }
+        Self::launch(
+            client,
+            registry,
+            dir,
+            CompactionConfig {
+                enabled: false,
+                ..CompactionConfig::default()
+            },
+            Vec::new(),
+            PendingToolCalls::default(),
+            mcp,
+        )
+        .await
+    }
+
+    async fn launch(
+        client: Arc<dyn LlmClient>,
+        registry: Registry,
+        dir: &Path,
+        compaction_config: CompactionConfig,
+        history: Vec<Message>,
+        pending_tool_calls: PendingToolCalls,
+        mcp: McpRoster,
+    ) -> anyhow::Result<Self> {
+        let tool_defs = registry.to_definitions().await;
+        let engine = Arc::new(ApprovalEngine::new(dir.join("approvals.json"))?);
+        let session_dir = dir.join("session");
+        let logger = SessionLogger::new_in_dir(&session_dir)?;
+
+        let (user_tx, user_rx) = mpsc::channel(16);
+        let (agent_tx, agent_rx) = mpsc::channel(64);
+        let handle = tokio::spawn(run_agent_loop(
+            AgentLoopParams {
+                client,
+                registry,
+                engine,
+                model: HARNESS_MODEL.to_string(),
+                max_tokens: 1024,
+                approval_timeout_seconds: 60,
+                system_prompt: "test system prompt".to_string(),
+                prompt_params: SystemPromptParams {
+                    tool_names: tool_defs.iter().map(|d| d.name.clone()).collect(),
+                    tool_summaries: tool_defs
+                        .iter()
+                        .map(|d| (d.name.clone(), d.description.clone()))
+                        .collect(),
+                    tool_origins: HashMap::new(),
+                    mcp_descriptions: HashMap::new(),
+                    workspace_dir: dir.to_string_lossy().to_string(),
+                    os: std::env::consts::OS.to_string(),
+                    arch: std::env::consts::ARCH.to_string(),
+                    shell: String::new(),
+                    hostname: String::new(),
+                    privacy: PrivacyConfig::default(),
+                    model: HARNESS_MODEL.to_string(),
+                    context_files: Vec::new(),
+                    skill_files: Vec::new(),
+                },
+                initial_messages: history,
+                session_logger: Some(Arc::new(tokio::sync::Mutex::new(logger))),
+                workspace_dir: dir.to_path_buf(),
+                compaction_config,
+                existing_created_at: None,
+                streaming: StreamingMode::On,
+                tools_config: ToolsConfig::default(),
+                session_format: SessionFormat::Jsonl,
+                reasoning: Reasoning::default(),
+                autosave: AutoSave::disabled(),
+                observe: false,
+                timeouts: RequestTimeouts::none(),
+                mcp,
+                mcp_arrivals: None,
+                shutdown: ShutdownFlag::default(),
+                pins: Pins::default(),
+                token_budget: None,
+                fallbacks: Fallbacks::default(),
+                pending_tool_calls,
+                context_note: None,
+            },
+            user_rx,
+            agent_tx,
+        ));
+
+        Ok(Self {
+            user_tx,
+            agent_rx,
+            handle,
+            dir: dir.to_path_buf(),
+            session_dir,
+        })
+    }
+
+    /// Send a user message and capture the turn's events through `Done`.
+    pub async fn turn(
+        &mut self,
+        message: impl Into<String>,
+        autopilot: &mut Autopilot,
+    ) -> Vec<EventRecord> {
+        let _ = self.user_tx.send(UserEvent::Message(message.into())).await;
+        capture_turn(&mut self.agent_rx, autopilot).await
+    }
+
+    /// The next event the loop sends on its own between turns, such as a
+    /// resync's notice.
+    pub async fn next_event(&mut self) -> Option<AgentEvent> {
+        self.agent_rx.recv().await
+    }
+
+    /// Capture what the loop does on its own before the first message,
+    /// through `Done`.
+    pub async fn startup(&mut self, autopilot: &mut Autopilot) -> Vec<EventRecord> {
+        capture_turn(&mut self.agent_rx, autopilot).await
+    }
+
+    /// Quit the loop, wait for it to exit, and return the conversation as
+    /// the session log recorded it.
+    pub async fn finish(self) -> anyhow::Result<Vec<Message>> {
+        let _ = self.user_tx.send(UserEvent::Quit).await;
+        self.handle.await?;
+        Ok(load_from_jsonl(&self.session_dir, &self.dir)?
+            .map(|state| state.messages)
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_merges_text_around_tool_use() {
+        let response = ScriptedResponse::new()
+            .text("Let me ")
+            .text("look.")
+            .tool_use("c1", "read_file", serde_json::json!({"path": "a.txt"}));
+        let content = response.content();
+        assert_eq!(content.len(), 2);
+        assert!(matches!(&content[0], ContentBlock::Text { text } if text == "Let me look."));
+        assert!(matches!(&content[1], ContentBlock::ToolUse { name, .. } if name == "read_file"));
+        assert_eq!(response.stop_reason(), StopReason::ToolUse);
+    }
+
+    #[test]
+    fn events_give_each_block_its_own_index() {
+        let events = ScriptedResponse::new()
+            .text("hi")
+            .tool_use("c1", "bash", serde_json::json!({"command": "ls"}))
+            .events();
+        let starts: Vec<usize> = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamEvent::ContentBlockStart { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(starts, vec![0, 1]);
+        assert!(matches!(events.last(), Some(StreamEvent::MessageStop)));
+    }
+
+    #[tokio::test]
+    async fn stream_error_cuts_the_stream_after_the_parts() {
+        use futures::StreamExt;
+
+        let client = MockLlmClient::new(vec![
+            ScriptedResponse::new()
+                .text("partial")
+                .stream_error("connection reset"),
+        ]);
+        let request = Request::new("mock-model");
+        let items: Vec<_> = client.create_message_stream(&request).collect().await;
+        assert!(matches!(items.last(), Some(Err(_))));
+        assert!(
+            !items
+                .iter()
+                .any(|item| matches!(item, Ok(StreamEvent::MessageStop)))
+        );
+        assert!(items.iter().any(
+            |item| matches!(item, Ok(StreamEvent::ContentBlockDelta { text, .. }) if text == "partial")
+        ));
+    }
+}
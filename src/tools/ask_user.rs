@@ -1,4 +1,4 @@
-// ABOUTME: AskUser tool — lets the LLM ask the user a free-text question.
+// ABOUTME: AskUser tool — lets the LLM ask the user a free-text, multiple-choice, or yes/no question.
 // ABOUTME: The tool is registered so the LLM sees it, but execution is intercepted by the agent loop.
 
 use async_trait::async_trait;
@@ -7,6 +7,45 @@ use mux::prelude::*;
 /// The tool name used for both registration and interception in the agent loop.
 pub const ASK_USER_TOOL_NAME: &str = "ask_user";
 
+/// The answer to a confirm-style question the user accepted.
+pub const CONFIRM_YES: &str = "yes";
+
+/// The answer to a confirm-style question the user turned down.
+pub const CONFIRM_NO: &str = "no";
+
+/// How a question is put to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuestionStyle {
+    /// Free text, or a pick from the options when there are any.
+    #[default]
+    Open,
+    /// A yes/no confirmation answered with [`CONFIRM_YES`] or [`CONFIRM_NO`];
+    /// Enter gives `default_yes`.
+    Confirm { default_yes: bool },
+}
+
+impl QuestionStyle {
+    /// The style asked for in an ask_user call. A missing or unrecognized
+    /// `style` is an open question; a confirm defaults to yes unless
+    /// `default` is "no".
+    pub fn from_input(input: &serde_json::Value) -> Self {
+        match input.get("style").and_then(|v| v.as_str()) {
+            Some("confirm") => QuestionStyle::Confirm {
+                default_yes: input.get("default").and_then(|v| v.as_str()) != Some(CONFIRM_NO),
+            },
+            _ => QuestionStyle::Open,
+        }
+    }
+
+    /// The name of the style, for event records; None for an open question.
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            QuestionStyle::Open => None,
+            QuestionStyle::Confirm { .. } => Some("confirm"),
+        }
+    }
+}
+
 /// Tool that allows the LLM to ask the user a question and receive a free-text response.
 pub struct AskUserTool;
 
@@ -17,7 +56,7 @@ impl Tool for AskUserTool {
     }
 
     fn description(&self) -> &str {
-        "Ask the user a question. Prefer providing multiple-choice options when possible. Use free-text only when the answer is truly open-ended."
+        "Ask the user a question. Use style \"confirm\" for yes/no questions, and prefer providing multiple-choice options when possible. Use free-text only when the answer is truly open-ended."
     }
 
     fn schema(&self) -> serde_json::Value {
@@ -32,6 +71,16 @@ impl Tool for AskUserTool {
                     "type": "array",
                     "items": { "type": "string" },
                     "description": "Multiple-choice options for the user to select from. Preferred over free-text when the answer is one of a known set."
+                },
+                "style": {
+                    "type": "string",
+                    "enum": ["confirm"],
+                    "description": "\"confirm\" asks a yes/no question; the answer is \"yes\" or \"no\"."
+                },
+                "default": {
+                    "type": "string",
+                    "enum": ["yes", "no"],
+                    "description": "For a confirm question, the answer Enter gives. Defaults to \"yes\"."
                 }
             },
             "required": ["question"]
@@ -96,6 +145,41 @@ mod tests {
         assert!(!required_arr.iter().any(|v| v == "options"));
     }
 
+    #[test]
+    fn confirm_style_defaults_to_yes() {
+        let input = serde_json::json!({"question": "Proceed?", "style": "confirm"});
+        assert_eq!(
+            QuestionStyle::from_input(&input),
+            QuestionStyle::Confirm { default_yes: true }
+        );
+        let input = serde_json::json!({"question": "Delete?", "style": "confirm", "default": "no"});
+        assert_eq!(
+            QuestionStyle::from_input(&input),
+            QuestionStyle::Confirm { default_yes: false }
+        );
+        // Anything other than "no" keeps the yes default.
+        let input = serde_json::json!({"question": "Go?", "style": "confirm", "default": 0});
+        assert_eq!(
+            QuestionStyle::from_input(&input),
+            QuestionStyle::Confirm { default_yes: true }
+        );
+    }
+
+    #[test]
+    fn malformed_style_falls_back_to_an_open_question() {
+        for style in [
+            serde_json::json!("Confirm!"),
+            serde_json::json!(true),
+            serde_json::json!(["confirm"]),
+        ] {
+            let input = serde_json::json!({"question": "Proceed?", "style": style});
+            assert_eq!(QuestionStyle::from_input(&input), QuestionStyle::Open);
+        }
+        let input = serde_json::json!({"question": "Proceed?"});
+        assert_eq!(QuestionStyle::from_input(&input), QuestionStyle::Open);
+        assert_eq!(QuestionStyle::Open.label(), None);
+    }
+
     #[test]
     fn requires_approval_always_false() {
         let tool = AskUserTool;
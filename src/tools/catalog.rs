@@ -0,0 +1,195 @@
+// ABOUTME: Tool catalog — a snapshot of registered tools for display in the TUI.
+// ABOUTME: Records each tool's origin (built-in or MCP server) and security level for /tools.
+
+use mux::prelude::{Registry, Tool};
+
+use crate::approval::SecurityLevel;
+use crate::config::ToolsConfig;
+
+/// Built-in tools that only read, so running one twice is harmless.
+pub const READ_ONLY_TOOLS: &[&str] = &["read_file", "list_files", "search"];
+
+/// Built-in tools that read or write files in the workspace.
+pub const FILE_TOOLS: &[&str] = &["read_file", "write_file", "list_files", "search"];
+
+/// Whether a tool is known to be read-only (and therefore safe to retry).
+pub fn is_read_only_tool(name: &str) -> bool {
+    READ_ONLY_TOOLS.contains(&name)
+}
+
+/// Result returned when the model calls a tool `[tools] disabled` hides.
+pub const DISABLED_TOOL_ERROR: &str = "tool disabled by configuration";
+
+/// Register `tool` unless `[tools] disabled` lists it. Returns whether it
+/// was registered.
+pub async fn register_enabled<T: Tool + 'static>(
+    registry: &Registry,
+    config: &ToolsConfig,
+    tool: T,
+) -> bool {
+    if config.is_disabled(tool.name()) {
+        return false;
+    }
+    registry.register(tool).await;
+    true
+}
+
+/// Where a registered tool came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolOrigin {
+    BuiltIn,
+    /// Provided by the named MCP server.
+    Mcp(String),
+}
+
+/// Display snapshot of one registered tool.
+#[derive(Debug, Clone)]
+pub struct ToolInfo {
+    pub name: String,
+    pub description: String,
+    pub origin: ToolOrigin,
+    pub security: SecurityLevel,
+}
+
+/// Format the tool list as a system message: built-in tools first, then one
+/// group per MCP server (sorted by server name), tools sorted by name within
+/// each group. Only the first line of each description is shown.
+pub fn format_tool_list(tools: &[ToolInfo]) -> String {
+    if tools.is_empty() {
+        return "No tools registered.".to_string();
+    }
+
+    let mut groups: Vec<(String, Vec<&ToolInfo>)> = Vec::new();
+    let mut built_in: Vec<&ToolInfo> = tools
+        .iter()
+        .filter(|t| t.origin == ToolOrigin::BuiltIn)
+        .collect();
+    if !built_in.is_empty() {
+        built_in.sort_by(|a, b| a.name.cmp(&b.name));
+        groups.push(("Built-in".to_string(), built_in));
+    }
+
+    let mut servers: Vec<&str> = tools
+        .iter()
+        .filter_map(|t| match &t.origin {
+            ToolOrigin::Mcp(server) => Some(server.as_str()),
+            ToolOrigin::BuiltIn => None,
+        })
+        .collect();
+    servers.sort();
+    servers.dedup();
+    for server in servers {
+        let mut members: Vec<&ToolInfo> = tools
+            .iter()
+            .filter(|t| matches!(&t.origin, ToolOrigin::Mcp(s) if s == server))
+            .collect();
+        members.sort_by(|a, b| a.name.cmp(&b.name));
+        groups.push((format!("MCP: {}", server), members));
+    }
+
+    let mut out = format!("\u{1f9f0} {} tools available", tools.len());
+    for (title, members) in groups {
+        out.push_str(&format!("\n\n{} ({})", title, members.len()));
+        for tool in members {
+            let summary = tool.description.lines().next().unwrap_or("").trim();
+            out.push_str(&format!(
+                "\n  {} [{}] \u{2014} {}",
+                tool.name,
+                tool.security.as_str(),
+                summary
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, origin: ToolOrigin, security: SecurityLevel) -> ToolInfo {
+        ToolInfo {
+            name: name.to_string(),
+            description: format!("Does {} things.\nMore detail here.", name),
+            origin,
+            security,
+        }
+    }
+
+    #[test]
+    fn empty_list_says_so() {
+        assert_eq!(format_tool_list(&[]), "No tools registered.");
+    }
+
+    #[test]
+    fn groups_built_in_before_mcp_servers() {
+        let tools = vec![
+            tool(
+                "query",
+                ToolOrigin::Mcp("postgres".to_string()),
+                SecurityLevel::Allowlist,
+            ),
+            tool("read_file", ToolOrigin::BuiltIn, SecurityLevel::Full),
+            tool(
+                "fetch",
+                ToolOrigin::Mcp("browser".to_string()),
+                SecurityLevel::Deny,
+            ),
+            tool("bash", ToolOrigin::BuiltIn, SecurityLevel::Allowlist),
+        ];
+        let out = format_tool_list(&tools);
+
+        assert!(out.starts_with("\u{1f9f0} 4 tools available"));
+        let built_in = out.find("Built-in (2)").unwrap();
+        let browser = out.find("MCP: browser (1)").unwrap();
+        let postgres = out.find("MCP: postgres (1)").unwrap();
+        assert!(built_in < browser && browser < postgres);
+
+        // Tools sorted by name within a group.
+        assert!(out.find("  bash").unwrap() < out.find("  read_file").unwrap());
+    }
+
+    #[test]
+    fn shows_security_level_and_first_description_line() {
+        let tools = vec![tool("bash", ToolOrigin::BuiltIn, SecurityLevel::Allowlist)];
+        let out = format_tool_list(&tools);
+        assert!(out.contains("  bash [allowlist] \u{2014} Does bash things."));
+        assert!(!out.contains("More detail here."));
+    }
+
+    #[test]
+    fn omits_empty_built_in_group() {
+        let tools = vec![tool(
+            "query",
+            ToolOrigin::Mcp("db".to_string()),
+            SecurityLevel::Full,
+        )];
+        let out = format_tool_list(&tools);
+        assert!(!out.contains("Built-in"));
+        assert!(out.contains("MCP: db (1)"));
+        assert!(out.contains("query [full]"));
+    }
+
+    #[tokio::test]
+    async fn disabled_tools_are_not_registered() {
+        use mux::prelude::{BashTool, ReadFileTool, WriteFileTool};
+
+        let config = ToolsConfig {
+            disabled: vec!["write_file".to_string(), "bash".to_string()],
+            ..ToolsConfig::default()
+        };
+        let registry = Registry::new();
+        assert!(!register_enabled(&registry, &config, BashTool).await);
+        assert!(register_enabled(&registry, &config, ReadFileTool).await);
+        assert!(!register_enabled(&registry, &config, WriteFileTool).await);
+
+        let names: Vec<String> = registry
+            .to_definitions()
+            .await
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["read_file"]);
+        assert_eq!(registry.count().await, 1);
+    }
+}
@@ -0,0 +1,162 @@
+// ABOUTME: MCP tool name conflicts — decides what a server's tool is registered as when its name is taken.
+// ABOUTME: Applies [mcp] on_conflict: prefix the newcomer as server__tool, skip it, or refuse to start.
+
+use std::collections::HashSet;
+
+use crate::config::McpConflictPolicy;
+
+/// Separator between server and tool in a prefixed name.
+pub const PREFIX_SEPARATOR: &str = "__";
+
+/// A server tool whose name was already registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolConflict {
+    pub server: String,
+    pub tool: String,
+}
+
+impl std::fmt::Display for ToolConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.server, self.tool)
+    }
+}
+
+/// What one server tool is registered as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// Registered under this name: its own, or prefixed with the server's.
+    As(String),
+    /// Left out.
+    Skipped,
+}
+
+/// Place `server`'s tools against the names in `taken`, in order, adding
+/// each registered name to `taken`. Every tool whose name was already taken
+/// is also returned as a conflict. Under `Prefix`, a tool whose prefixed
+/// name is taken too is skipped; under `Error` the caller refuses to start,
+/// so conflicting tools are skipped here.
+pub fn place_tools(
+    server: &str,
+    tools: &[String],
+    taken: &mut HashSet<String>,
+    policy: McpConflictPolicy,
+) -> (Vec<(String, Placement)>, Vec<ToolConflict>) {
+    let mut placements = Vec::with_capacity(tools.len());
+    let mut conflicts = Vec::new();
+    for tool in tools {
+        let placement = if taken.insert(tool.clone()) {
+            Placement::As(tool.clone())
+        } else {
+            conflicts.push(ToolConflict {
+                server: server.to_string(),
+                tool: tool.clone(),
+            });
+            let prefixed = format!("{}{}{}", server, PREFIX_SEPARATOR, tool);
+            match policy {
+                McpConflictPolicy::Prefix if taken.insert(prefixed.clone()) => {
+                    Placement::As(prefixed)
+                }
+                _ => Placement::Skipped,
+            }
+        };
+        placements.push((tool.clone(), placement));
+    }
+    (placements, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn taken() -> HashSet<String> {
+        names(&["bash", "read_file", "search"])
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn prefix_renames_the_newcomer() {
+        let mut taken = taken();
+        let (placements, conflicts) = place_tools(
+            "github",
+            &names(&["search", "create_issue"]),
+            &mut taken,
+            McpConflictPolicy::Prefix,
+        );
+        assert_eq!(
+            placements,
+            vec![
+                (
+                    "search".to_string(),
+                    Placement::As("github__search".to_string())
+                ),
+                (
+                    "create_issue".to_string(),
+                    Placement::As("create_issue".to_string())
+                ),
+            ]
+        );
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].to_string(), "github: search");
+        assert!(taken.contains("search") && taken.contains("github__search"));
+
+        // A second server's duplicate of the same tool is prefixed with its own name.
+        let (placements, _) = place_tools(
+            "jira",
+            &names(&["create_issue"]),
+            &mut taken,
+            McpConflictPolicy::Prefix,
+        );
+        assert_eq!(
+            placements[0].1,
+            Placement::As("jira__create_issue".to_string())
+        );
+    }
+
+    #[test]
+    fn prefix_skips_when_the_prefixed_name_is_taken_too() {
+        let mut taken = taken();
+        taken.insert("github__search".to_string());
+        let (placements, conflicts) = place_tools(
+            "github",
+            &names(&["search"]),
+            &mut taken,
+            McpConflictPolicy::Prefix,
+        );
+        assert_eq!(placements[0].1, Placement::Skipped);
+        assert_eq!(conflicts.len(), 1);
+    }
+
+    #[test]
+    fn skip_leaves_the_newcomer_out() {
+        let mut taken = taken();
+        let (placements, conflicts) = place_tools(
+            "github",
+            &names(&["search", "create_issue"]),
+            &mut taken,
+            McpConflictPolicy::Skip,
+        );
+        assert_eq!(placements[0].1, Placement::Skipped);
+        assert_eq!(placements[1].1, Placement::As("create_issue".to_string()));
+        assert_eq!(conflicts.len(), 1);
+        assert!(!taken.contains("github__search"));
+    }
+
+    #[test]
+    fn error_reports_every_conflict() {
+        let mut taken = taken();
+        let (placements, conflicts) = place_tools(
+            "fs",
+            &names(&["read_file", "search", "tree"]),
+            &mut taken,
+            McpConflictPolicy::Error,
+        );
+        let listed: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+        assert_eq!(listed, ["fs: read_file", "fs: search"]);
+        assert_eq!(placements[2].1, Placement::As("tree".to_string()));
+    }
+}
@@ -0,0 +1,246 @@
+// ABOUTME: Line diffs for file writes — a unified diff between a file's current and proposed contents.
+// ABOUTME: The approval prompt shows a truncated preview so a write can be judged before it lands.
+
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Diff lines shown in a preview before the rest is summarized.
+pub const PREVIEW_LINES: usize = 12;
+
+/// Unchanged lines kept around each change.
+const CONTEXT_LINES: usize = 2;
+
+/// Above this many old × new lines (after trimming the common ends), the
+/// changed middle is shown as removed then added rather than aligned.
+const MAX_ALIGN_CELLS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// A unified diff from `old` to `new`: `@@ -a,b +c,d @@` hunk headers, then
+/// lines prefixed with ' ', '-' or '+'. Empty when the contents match line
+/// for line.
+pub fn unified_diff(old: &str, new: &str, context: usize) -> Vec<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = line_ops(&old_lines, &new_lines);
+
+    // Each change with its context, merged where they touch.
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Keep)
+    {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    // Old and new line counts consumed before each op.
+    let mut before = Vec::with_capacity(ops.len());
+    let (mut old_seen, mut new_seen) = (0, 0);
+    for (op, _) in &ops {
+        before.push((old_seen, new_seen));
+        match op {
+            Op::Keep => {
+                old_seen += 1;
+                new_seen += 1;
+            }
+            Op::Remove => old_seen += 1,
+            Op::Add => new_seen += 1,
+        }
+    }
+
+    let mut out = Vec::new();
+    for (start, end) in ranges {
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|(op, _)| *op != Op::Add).count();
+        let new_len = hunk.iter().filter(|(op, _)| *op != Op::Remove).count();
+        let (old_start, new_start) = before[start];
+        out.push(format!(
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        out.extend(hunk.iter().map(|(op, line)| {
+            let sign = match op {
+                Op::Keep => ' ',
+                Op::Remove => '-',
+                Op::Add => '+',
+            };
+            format!("{}{}", sign, line)
+        }));
+    }
+    out
+}
+
+/// "3,4" for four lines starting at line 3; an empty range names the line
+/// before it, as `diff -u` does.
+fn hunk_range(seen: usize, len: usize) -> String {
+    let start = if len == 0 { seen } else { seen + 1 };
+    format!("{},{}", start, len)
+}
+
+/// Align two files line by line: the common head and tail as-is, the middle
+/// by longest common subsequence when it's small enough.
+fn line_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let head = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[head..old.len() - tail];
+    let new_mid = &new[head..new.len() - tail];
+
+    let mut ops: Vec<(Op, &str)> = old[..head].iter().map(|l| (Op::Keep, *l)).collect();
+    if old_mid.len().saturating_mul(new_mid.len()) <= MAX_ALIGN_CELLS {
+        ops.extend(align(old_mid, new_mid));
+    } else {
+        ops.extend(old_mid.iter().map(|l| (Op::Remove, *l)));
+        ops.extend(new_mid.iter().map(|l| (Op::Add, *l)));
+    }
+    ops.extend(old[old.len() - tail..].iter().map(|l| (Op::Keep, *l)));
+    ops
+}
+
+/// Longest-common-subsequence alignment, removals before additions.
+fn align<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let width = new.len() + 1;
+    // lcs[i * width + j]: common lines between old[i..] and new[j..].
+    let mut lcs = vec![0usize; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push((Op::Keep, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            ops.push((Op::Remove, old[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Add, new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|l| (Op::Remove, *l)));
+    ops.extend(new[j..].iter().map(|l| (Op::Add, *l)));
+    ops
+}
+
+/// A summary line ("+2 -1 lines") over the first `max_lines` of the diff,
+/// with a count of what was cut.
+pub fn change_preview(old: &str, new: &str, max_lines: usize) -> String {
+    let diff = unified_diff(old, new, CONTEXT_LINES);
+    if diff.is_empty() {
+        return "no changes".to_string();
+    }
+    let added = diff.iter().filter(|l| l.starts_with('+')).count();
+    let removed = diff.iter().filter(|l| l.starts_with('-')).count();
+    let mut lines = vec![format!("+{} -{} lines", added, removed)];
+    lines.extend(diff.iter().take(max_lines).cloned());
+    if diff.len() > max_lines {
+        lines.push(format!(
+            "\u{2026} {} more diff lines",
+            diff.len() - max_lines
+        ));
+    }
+    lines.join("\n")
+}
+
+/// "creates new file (3 lines)".
+pub fn new_file_note(content: &str) -> String {
+    let count = content.lines().count();
+    format!(
+        "creates new file ({} line{})",
+        count,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// What writing `content` to `path` would do: a diff preview of an existing
+/// file, or a note that it creates one. None when the current file can't be
+/// read as text.
+pub fn write_preview(path: &Path, content: &str) -> Option<String> {
+    match std::fs::read_to_string(path) {
+        Ok(current) => Some(change_preview(&current, content, PREVIEW_LINES)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Some(new_file_note(content)),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_render_as_unified_hunks_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\ni\n";
+        assert_eq!(
+            unified_diff(old, new, 1),
+            vec![
+                "@@ -3,3 +3,3 @@",
+                " c",
+                "-d",
+                "+D",
+                " e",
+                "@@ -8,1 +8,2 @@",
+                " h",
+                "+i",
+            ]
+        );
+        assert!(unified_diff(old, old, 1).is_empty());
+    }
+
+    #[test]
+    fn previews_summarize_and_truncate() {
+        let old: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+        let new = old.replace("line 5\n", "five\n").replace("line 15\n", "");
+        let preview = change_preview(&old, &new, 4);
+        let lines: Vec<&str> = preview.lines().collect();
+        assert_eq!(lines[0], "+1 -2 lines");
+        assert_eq!(
+            lines[1..5],
+            ["@@ -3,5 +3,5 @@", " line 3", " line 4", "-line 5"]
+        );
+        assert_eq!(lines[5], "\u{2026} 9 more diff lines");
+        assert_eq!(change_preview(&old, &old, 4), "no changes");
+    }
+
+    #[test]
+    fn writes_to_missing_files_are_new_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        assert_eq!(
+            write_preview(&path, "one\ntwo\nthree\n").as_deref(),
+            Some("creates new file (3 lines)")
+        );
+
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        assert_eq!(
+            write_preview(&path, "one\n2\n").as_deref(),
+            Some("+1 -1 lines\n@@ -1,2 +1,2 @@\n one\n-two\n+2")
+        );
+    }
+}
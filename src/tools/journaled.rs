@@ -0,0 +1,70 @@
+// ABOUTME: Journaled tool wrapper — records each successful file write in the session's change journal.
+// ABOUTME: Wraps write_file so /changes can list what the agent touched and /revert can undo it.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use mux::prelude::*;
+
+use crate::session::ChangeJournal;
+use crate::session::changes::{normalize_path, read_existing};
+
+/// `inner`, a tool that writes the file named by its `path` parameter,
+/// with every successful write recorded in `journal`.
+pub struct Journaled<T> {
+    inner: T,
+    journal: Arc<Mutex<ChangeJournal>>,
+}
+
+impl<T> Journaled<T> {
+    pub fn new(inner: T, journal: Arc<Mutex<ChangeJournal>>) -> Self {
+        Self { inner, journal }
+    }
+}
+
+#[async_trait]
+impl<T: Tool> Tool for Journaled<T> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn schema(&self) -> serde_json::Value {
+        self.inner.schema()
+    }
+
+    fn requires_approval(&self, params: &serde_json::Value) -> bool {
+        self.inner.requires_approval(params)
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let path = params
+            .get("path")
+            .and_then(|p| p.as_str())
+            .map(|p| normalize_path(Path::new(p)));
+        let before = path.as_deref().map(read_existing);
+        let result = self.inner.execute(params).await?;
+        if let (Some(path), Some(Ok(before))) = (path, before)
+            && !result.is_error
+        {
+            let recorded = read_existing(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|after| {
+                    let mut journal = self.journal.lock().unwrap();
+                    journal.record_write(&path, before.as_deref(), after.as_deref())
+                });
+            if let Err(e) = recorded {
+                eprintln!(
+                    "Warning: failed to journal the write to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,492 @@
+// ABOUTME: MCP server handles that survive a server dying mid-session — reconnect once on a transport error.
+// ABOUTME: McpTool registers a server's tool (under its own or a conflict-resolved name) through its handle.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use mux::prelude::*;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+
+/// How the connection to an MCP server failed under a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportFailure {
+    /// Sending the request failed, so the server never saw it and the call
+    /// can safely be made again.
+    NotSent,
+    /// The connection went while waiting for the answer; the server may
+    /// have run the call.
+    MaybeSent,
+}
+
+/// Whether `error` from an MCP call means the server's transport failed, so
+/// reconnecting could help, told from the I/O error underneath. Errors the
+/// tool reported about its own work come back as `ToolResult` errors and
+/// never get here; their text is never looked at.
+pub fn transport_failure(error: &anyhow::Error) -> Option<TransportFailure> {
+    error.chain().find_map(|cause| {
+        let io = cause.downcast_ref::<std::io::Error>()?;
+        match io.kind() {
+            std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::NotConnected
+            | std::io::ErrorKind::ConnectionRefused => Some(TransportFailure::NotSent),
+            std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::UnexpectedEof => Some(TransportFailure::MaybeSent),
+            _ => None,
+        }
+    })
+}
+
+/// Starting a server failed at `stage` ("failed to connect", ...).
+#[derive(Debug)]
+pub struct ConnectError {
+    pub stage: &'static str,
+    pub error: anyhow::Error,
+}
+
+impl ConnectError {
+    fn new(stage: &'static str, error: impl Into<anyhow::Error>) -> Self {
+        Self {
+            stage,
+            error: error.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.stage, self.error)
+    }
+}
+
+/// The client end of a connection to a server. A trait so tests can stand
+/// in a server without spawning one.
+#[async_trait]
+pub trait McpLink: Send + Sync {
+    /// Register the server's current tools in `tools`, for `server`.
+    async fn list_tools(&self, tools: &Registry, server: &str) -> anyhow::Result<()>;
+
+    async fn shutdown(&self);
+}
+
+/// A real MCP client.
+struct LiveLink(Arc<McpClient>);
+
+#[async_trait]
+impl McpLink for LiveLink {
+    async fn list_tools(&self, tools: &Registry, server: &str) -> anyhow::Result<()> {
+        tools.merge_mcp(self.0.clone(), Some(server)).await?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        let _ = self.0.shutdown().await;
+    }
+}
+
+/// A live connection: the client and the tools it offers.
+struct Connection {
+    client: Arc<dyn McpLink>,
+    tools: Registry,
+    /// Bumped on every reconnect, so callers that raced can tell whether
+    /// someone else already replaced a dead connection.
+    generation: u64,
+}
+
+/// One configured MCP server and its current connection, replaced when the
+/// server has to be reconnected.
+pub struct McpServer {
+    name: String,
+    /// None for a server reached through a link handed in, which can't be
+    /// reconnected.
+    config: Option<McpServerConfig>,
+    connection: Mutex<Connection>,
+}
+
+impl McpServer {
+    /// Connect, initialize, and load the server's tools.
+    pub async fn start(config: McpServerConfig) -> Result<Self, ConnectError> {
+        let connection = open(&config, 0).await?;
+        Ok(Self {
+            name: config.name.clone(),
+            config: Some(config),
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// A server reached through `link` instead of started from config, as
+    /// tests do with a fake client.
+    pub async fn with_link(name: &str, link: Arc<dyn McpLink>) -> anyhow::Result<Self> {
+        let tools = Registry::new();
+        link.list_tools(&tools, name).await?;
+        Ok(Self {
+            name: name.to_string(),
+            config: None,
+            connection: Mutex::new(Connection {
+                client: link,
+                tools,
+                generation: 0,
+            }),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Names of the tools the server offered when it last connected.
+    pub async fn tool_names(&self) -> Vec<String> {
+        let connection = self.connection.lock().await;
+        connection
+            .tools
+            .to_definitions()
+            .await
+            .into_iter()
+            .map(|d| d.name)
+            .collect()
+    }
+
+    /// Re-list the server's tools, for servers whose tool list changes while
+    /// they run. A dead connection is reconnected, which lists them afresh.
+    pub async fn refresh(&self) -> anyhow::Result<Vec<String>> {
+        let generation = {
+            let mut connection = self.connection.lock().await;
+            let tools = Registry::new();
+            match connection.client.list_tools(&tools, &self.name).await {
+                Ok(()) => {
+                    connection.tools = tools;
+                    None
+                }
+                Err(e) => {
+                    // Listing tools changes nothing, so it's fine either way.
+                    if transport_failure(&e).is_none() {
+                        return Err(e);
+                    }
+                    Some(connection.generation)
+                }
+            }
+        };
+        if let Some(generation) = generation {
+            self.reconnect(generation)
+                .await
+                .map_err(|e| anyhow!("{}", e))?;
+        }
+        Ok(self.tool_names().await)
+    }
+
+    /// The server's tool named `tool` on the current connection, with the
+    /// connection's generation.
+    async fn tool(&self, tool: &str) -> Option<(Arc<dyn Tool>, u64)> {
+        let connection = self.connection.lock().await;
+        let found = connection.tools.get(tool).await?;
+        Some((found, connection.generation))
+    }
+
+    /// Replace the connection seen at `generation` with a fresh one. Does
+    /// nothing when another call already reconnected since then.
+    async fn reconnect(&self, generation: u64) -> Result<(), ConnectError> {
+        let mut connection = self.connection.lock().await;
+        if connection.generation != generation {
+            return Ok(());
+        }
+        let Some(config) = &self.config else {
+            return Err(ConnectError::new(
+                "failed to reconnect",
+                anyhow!("it wasn't started from config"),
+            ));
+        };
+        connection.client.shutdown().await;
+        *connection = open(config, generation + 1).await?;
+        Ok(())
+    }
+
+    /// Shut down the current connection.
+    pub async fn shutdown(&self) {
+        self.connection.lock().await.client.shutdown().await;
+    }
+}
+
+/// Run `start`, giving up with a "timed out" [`ConnectError`] after `timeout`.
+async fn within<T>(
+    timeout: Duration,
+    start: impl Future<Output = Result<T, ConnectError>>,
+) -> Result<T, ConnectError> {
+    tokio::time::timeout(timeout, start)
+        .await
+        .unwrap_or_else(|_| {
+            Err(ConnectError::new(
+                "timed out",
+                anyhow!("no answer after {}s", timeout.as_secs_f32()),
+            ))
+        })
+}
+
+/// Run every server's start at once, each limited to `timeout`, so launch
+/// waits on the slowest server rather than on all of them in turn. Results
+/// come back in the order given.
+pub async fn start_all<T, F>(starts: Vec<F>, timeout: Duration) -> Vec<Result<T, ConnectError>>
+where
+    F: Future<Output = Result<T, ConnectError>>,
+{
+    futures::future::join_all(starts.into_iter().map(|start| within(timeout, start))).await
+}
+
+/// Start every configured server concurrently, in config order.
+pub async fn start_servers(
+    configs: Vec<McpServerConfig>,
+    timeout: Duration,
+) -> Vec<(String, Result<McpServer, ConnectError>)> {
+    let names: Vec<String> = configs.iter().map(|c| c.name.clone()).collect();
+    let results = start_all(configs.into_iter().map(McpServer::start).collect(), timeout).await;
+    names.into_iter().zip(results).collect()
+}
+
+/// A server started in the background with `[mcp] lazy`, or why it didn't.
+pub struct McpArrival {
+    pub name: String,
+    pub result: Result<Arc<McpServer>, ConnectError>,
+}
+
+/// Servers still being started in the background, and those that already
+/// came up, so quitting early can stop both.
+pub struct BackgroundStart {
+    started: Arc<std::sync::Mutex<Vec<Arc<McpServer>>>>,
+    task: JoinHandle<()>,
+}
+
+impl BackgroundStart {
+    /// Stop starting servers and shut down the ones that came up.
+    pub async fn shutdown(self) {
+        self.task.abort();
+        let started = std::mem::take(&mut *self.started.lock().unwrap());
+        for server in started {
+            server.shutdown().await;
+        }
+    }
+}
+
+/// Start every configured server concurrently in a background task, sending
+/// each one as it comes up (or fails) rather than waiting for the slowest.
+pub fn start_in_background(
+    configs: Vec<McpServerConfig>,
+    timeout: Duration,
+) -> (mpsc::Receiver<McpArrival>, BackgroundStart) {
+    let (tx, rx) = mpsc::channel(configs.len().max(1));
+    let started: Arc<std::sync::Mutex<Vec<Arc<McpServer>>>> = Arc::default();
+    let record = started.clone();
+    let task = tokio::spawn(async move {
+        let mut pending: FuturesUnordered<_> = configs
+            .into_iter()
+            .map(|config| async move {
+                let name = config.name.clone();
+                (name, within(timeout, McpServer::start(config)).await)
+            })
+            .collect();
+        while let Some((name, result)) = pending.next().await {
+            let result = result.map(Arc::new);
+            if let Ok(server) = &result {
+                record.lock().unwrap().push(server.clone());
+            }
+            if tx.send(McpArrival { name, result }).await.is_err() {
+                break;
+            }
+        }
+    });
+    (rx, BackgroundStart { started, task })
+}
+
+async fn open(config: &McpServerConfig, generation: u64) -> Result<Connection, ConnectError> {
+    let mut client = McpClient::connect(config.clone())
+        .await
+        .map_err(|e| ConnectError::new("failed to connect", e))?;
+    client
+        .initialize()
+        .await
+        .map_err(|e| ConnectError::new("failed to initialize", e))?;
+    let client = LiveLink(Arc::new(client));
+    let tools = Registry::new();
+    client
+        .list_tools(&tools, &config.name)
+        .await
+        .map_err(|e| ConnectError::new("failed to load tools", e))?;
+    Ok(Connection {
+        client: Arc::new(client),
+        tools,
+        generation,
+    })
+}
+
+/// A tool from an MCP server, registered under `name`: the server's own
+/// name for it, or the one `[mcp] on_conflict` gave it. Calls go through
+/// the server handle, and a call that fails because the transport died
+/// reconnects the server once. It's retried only when the request never got
+/// out, since a call the server may have run can't safely run twice.
+pub struct McpTool {
+    name: String,
+    /// What the server calls the tool.
+    server_tool: String,
+    description: String,
+    schema: serde_json::Value,
+    /// The tool as first loaded, for the approval check.
+    initial: Arc<dyn Tool>,
+    server: Arc<McpServer>,
+}
+
+impl McpTool {
+    /// Register-ready handle for `server`'s tool `server_tool` under `name`.
+    /// None if the server doesn't offer that tool.
+    pub async fn new(name: &str, server_tool: &str, server: Arc<McpServer>) -> Option<Self> {
+        let (initial, _) = server.tool(server_tool).await?;
+        Some(Self {
+            name: name.to_string(),
+            server_tool: server_tool.to_string(),
+            description: initial.description().to_string(),
+            schema: initial.schema(),
+            initial,
+            server,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn schema(&self) -> serde_json::Value {
+        self.schema.clone()
+    }
+
+    fn requires_approval(&self, params: &serde_json::Value) -> bool {
+        self.initial.requires_approval(params)
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        let missing = || {
+            anyhow!(
+                "MCP server '{}' no longer offers {}",
+                self.server.name(),
+                self.server_tool
+            )
+        };
+        let (tool, generation) = self
+            .server
+            .tool(&self.server_tool)
+            .await
+            .ok_or_else(missing)?;
+        let e = match tool.execute(params.clone()).await {
+            Err(e) => e,
+            ok => return ok,
+        };
+        let Some(failure) = transport_failure(&e) else {
+            return Err(e);
+        };
+        if let Err(reconnect) = self.server.reconnect(generation).await {
+            return Err(anyhow!(
+                "{} (reconnecting to MCP server '{}' {})",
+                e,
+                self.server.name(),
+                reconnect
+            ));
+        }
+        if failure == TransportFailure::MaybeSent {
+            return Err(anyhow!(
+                "{} (MCP server '{}' reconnected; the call may or may not have run, so it wasn't retried)",
+                e,
+                self.server.name()
+            ));
+        }
+        let (tool, _) = self
+            .server
+            .tool(&self.server_tool)
+            .await
+            .ok_or_else(missing)?;
+        tool.execute(params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_dead_transport_triggers_a_reconnect() {
+        let pipe = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "write failed");
+        assert_eq!(
+            transport_failure(&anyhow::Error::from(pipe)),
+            Some(TransportFailure::NotSent)
+        );
+
+        let wrapped = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+            .context("reading MCP response");
+        assert_eq!(
+            transport_failure(&wrapped),
+            Some(TransportFailure::MaybeSent)
+        );
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert_eq!(
+            transport_failure(&anyhow::Error::from(reset)),
+            Some(TransportFailure::MaybeSent)
+        );
+    }
+
+    #[tokio::test]
+    async fn startup_runs_servers_at_once_and_times_out_the_stuck_one() {
+        let starts: Vec<_> = [0u64, 1, 2]
+            .into_iter()
+            .map(|i| async move {
+                match i {
+                    0 => {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        Ok(i)
+                    }
+                    1 => {
+                        tokio::time::sleep(Duration::from_secs(3600)).await;
+                        Ok(i)
+                    }
+                    _ => Err(ConnectError::new("failed to connect", anyhow!("refused"))),
+                }
+            })
+            .collect();
+        let began = std::time::Instant::now();
+        let results = start_all(starts, Duration::from_millis(300)).await;
+        // Waited for the timeout once, not for each server in turn.
+        assert!(began.elapsed() < Duration::from_secs(2));
+
+        assert_eq!(results[0].as_ref().unwrap(), &0);
+        let stuck = results[1].as_ref().unwrap_err();
+        assert_eq!(stuck.stage, "timed out");
+        assert_eq!(stuck.to_string(), "timed out: no answer after 0.3s");
+        assert_eq!(results[2].as_ref().unwrap_err().stage, "failed to connect");
+    }
+
+    #[test]
+    fn a_tool_failure_does_not_reconnect() {
+        assert_eq!(
+            transport_failure(&anyhow!("invalid params: missing field `query`")),
+            None
+        );
+        assert_eq!(
+            transport_failure(&anyhow!("rate limited, try again later")),
+            None
+        );
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(transport_failure(&anyhow::Error::from(denied)), None);
+        // Words in what the tool said don't count, only the error's type.
+        assert_eq!(
+            transport_failure(&anyhow!("grep: connection closed by peer in server.log")),
+            None
+        );
+    }
+}
@@ -2,3 +2,11 @@
 // ABOUTME: Provides tools beyond the built-in mux-rs set.
 
 pub mod ask_user;
+pub mod catalog;
+pub mod conflicts;
+pub mod diff;
+pub mod journaled;
+pub mod mcp;
+pub mod open_in_editor;
+pub mod roster;
+pub mod summary;
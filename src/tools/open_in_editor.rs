@@ -0,0 +1,61 @@
+// ABOUTME: OpenInEditor tool — lets the LLM hand a file to the user's $EDITOR and read back the result.
+// ABOUTME: Registered so the LLM sees it; approved calls are handed to the TUI, which runs the editor.
+
+use async_trait::async_trait;
+use mux::prelude::*;
+
+/// The tool name used for both registration and interception in the agent loop.
+pub const OPEN_IN_EDITOR_TOOL_NAME: &str = "open_in_editor";
+
+/// Tool that opens a file in the user's editor and returns its contents
+/// once they close it.
+pub struct OpenInEditorTool;
+
+#[async_trait]
+impl Tool for OpenInEditorTool {
+    fn name(&self) -> &str {
+        OPEN_IN_EDITOR_TOOL_NAME
+    }
+
+    fn description(&self) -> &str {
+        "Open a file in the user's editor so they can review or change it by hand. Blocks until they close the editor, then returns the file's contents. Use when the user should write or adjust something themselves."
+    }
+
+    fn schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "The file to open, relative to the workspace"
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    /// The file's contents go back to the model, like a read.
+    fn requires_approval(&self, _params: &serde_json::Value) -> bool {
+        true
+    }
+
+    async fn execute(&self, _params: serde_json::Value) -> Result<ToolResult, anyhow::Error> {
+        Ok(ToolResult::text(
+            "[open_in_editor tool: should be intercepted by agent loop]",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_requires_path() {
+        let tool = OpenInEditorTool;
+        assert_eq!(tool.name(), "open_in_editor");
+        let schema = tool.schema();
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.iter().any(|v| v == "path"));
+    }
+}
@@ -0,0 +1,341 @@
+// ABOUTME: MCP tool roster — which registered tools came from which server, kept in step with each server.
+// ABOUTME: A resync re-lists a server's tools, registers newcomers, and withdraws tools that disappeared.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use mux::prelude::*;
+
+use crate::config::{McpConflictPolicy, ToolsConfig};
+use crate::tools::conflicts::{Placement, place_tools};
+use crate::tools::mcp::{McpServer, McpTool};
+
+/// A server tool as registered.
+#[derive(Debug, Clone)]
+struct RosterEntry {
+    server: String,
+    /// What the server calls the tool.
+    tool: String,
+}
+
+/// How one server's tool list changed, by registered name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolDelta {
+    pub server: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ToolDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for ToolDelta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            let noun = if self.added.len() == 1 {
+                "tool"
+            } else {
+                "tools"
+            };
+            parts.push(format!("+{} {}", self.added.len(), noun));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("-{}", self.removed.len()));
+        }
+        write!(f, "MCP {}: {}", self.server, parts.join(", "))
+    }
+}
+
+/// The result of syncing one server's tool list.
+#[derive(Debug, Default)]
+pub struct ToolChange {
+    /// Tools to register, as (registered name, server's name for it).
+    pub register: Vec<(String, String)>,
+    pub delta: ToolDelta,
+}
+
+/// The MCP servers in the session and the tools registered from them. The
+/// registry can't unregister a tool, so one a server stopped offering is
+/// withdrawn here and left out of the definitions sent to the model.
+#[derive(Default)]
+pub struct McpRoster {
+    servers: Vec<Arc<McpServer>>,
+    /// Registered name → where it came from.
+    tools: BTreeMap<String, RosterEntry>,
+    withdrawn: HashSet<String>,
+    policy: McpConflictPolicy,
+    resync_every: Option<Duration>,
+}
+
+impl McpRoster {
+    /// An empty roster placing newcomers per `policy`. Under `Error`, a
+    /// conflicting newcomer is skipped, since the session is already running.
+    pub fn new(policy: McpConflictPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Re-list every server's tools this often; None never does.
+    pub fn with_resync_every(mut self, every: Option<Duration>) -> Self {
+        self.resync_every = every;
+        self
+    }
+
+    pub fn add_server(&mut self, server: Arc<McpServer>) {
+        self.servers.push(server);
+    }
+
+    /// Note that `server`'s tool `tool` was registered as `registered`.
+    pub fn record(&mut self, registered: &str, server: &str, tool: &str) {
+        self.withdrawn.remove(registered);
+        self.tools.insert(
+            registered.to_string(),
+            RosterEntry {
+                server: server.to_string(),
+                tool: tool.to_string(),
+            },
+        );
+    }
+
+    /// How often to resync, or None when it's off or there are no servers.
+    pub fn resync_every(&self) -> Option<Duration> {
+        self.resync_every.filter(|_| !self.servers.is_empty())
+    }
+
+    /// Whether `name` was registered from a server that no longer offers it.
+    pub fn is_withdrawn(&self, name: &str) -> bool {
+        self.withdrawn.contains(name)
+    }
+
+    /// The server a registered tool came from, if it's an MCP tool.
+    pub fn server_of(&self, name: &str) -> Option<&str> {
+        self.tools.get(name).map(|e| e.server.as_str())
+    }
+
+    /// Bring `server`'s tools in step with `offered`, its current list. Tools
+    /// it dropped are withdrawn, freeing their names; new ones are placed
+    /// against `taken` like at startup and returned for registering.
+    pub fn sync(
+        &mut self,
+        server: &str,
+        offered: &[String],
+        taken: &mut HashSet<String>,
+    ) -> ToolChange {
+        let mut change = ToolChange::default();
+        change.delta.server = server.to_string();
+
+        let current: Vec<(String, String)> = self
+            .tools
+            .iter()
+            .filter(|(_, e)| e.server == server)
+            .map(|(name, e)| (name.clone(), e.tool.clone()))
+            .collect();
+        for (registered, tool) in &current {
+            if !offered.contains(tool) {
+                self.tools.remove(registered);
+                self.withdrawn.insert(registered.clone());
+                taken.remove(registered);
+                change.delta.removed.push(registered.clone());
+            }
+        }
+
+        let mut added: Vec<String> = offered
+            .iter()
+            .filter(|tool| !current.iter().any(|(_, t)| t == *tool))
+            .cloned()
+            .collect();
+        added.sort();
+        let (placements, _) = place_tools(server, &added, taken, self.policy);
+        for (tool, placement) in placements {
+            if let Placement::As(registered) = placement {
+                self.record(&registered, server, &tool);
+                change.delta.added.push(registered.clone());
+                change.register.push((registered, tool));
+            }
+        }
+        change
+    }
+
+    /// Registered names still in use.
+    async fn taken(&self, registry: &Registry) -> HashSet<String> {
+        registry
+            .to_definitions()
+            .await
+            .into_iter()
+            .map(|d| d.name)
+            .filter(|name| !self.is_withdrawn(name))
+            .collect()
+    }
+
+    /// Sync `server` to `offered` (minus disabled tools) and register what
+    /// it added.
+    async fn apply(
+        &mut self,
+        server: &Arc<McpServer>,
+        offered: Vec<String>,
+        taken: &mut HashSet<String>,
+        registry: &Registry,
+        tools_config: &ToolsConfig,
+    ) -> ToolDelta {
+        let offered: Vec<String> = offered
+            .into_iter()
+            .filter(|tool| !tools_config.is_disabled(tool))
+            .collect();
+        let change = self.sync(server.name(), &offered, taken);
+        for (registered, tool) in change.register {
+            if let Some(mcp_tool) = McpTool::new(&registered, &tool, server.clone()).await {
+                registry.register(mcp_tool).await;
+            }
+        }
+        change.delta
+    }
+
+    /// Add a server that came up after startup (`[mcp] lazy`) and register
+    /// its tools under the same rules as a resync. Returns what it added.
+    pub async fn admit(
+        &mut self,
+        server: Arc<McpServer>,
+        registry: &Registry,
+        tools_config: &ToolsConfig,
+    ) -> ToolDelta {
+        let mut taken = self.taken(registry).await;
+        let offered = server.tool_names().await;
+        let delta = self
+            .apply(&server, offered, &mut taken, registry, tools_config)
+            .await;
+        self.servers.push(server);
+        delta
+    }
+
+    /// Re-list every server's tools and register or withdraw to match,
+    /// returning each server's change. A server that can't be reached is
+    /// left as it was; its next tool call reconnects it.
+    pub async fn resync(
+        &mut self,
+        registry: &Registry,
+        tools_config: &ToolsConfig,
+    ) -> Vec<ToolDelta> {
+        let mut taken = self.taken(registry).await;
+        let mut deltas = Vec::new();
+        for server in self.servers.clone() {
+            let Ok(offered) = server.refresh().await else {
+                continue;
+            };
+            let delta = self
+                .apply(&server, offered, &mut taken, registry, tools_config)
+                .await;
+            if !delta.is_empty() {
+                deltas.push(delta);
+            }
+        }
+        deltas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeTool;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Tool names the model would be offered.
+    async fn offered(registry: &Registry, roster: &McpRoster) -> Vec<String> {
+        let mut names: Vec<String> = registry
+            .to_definitions()
+            .await
+            .into_iter()
+            .map(|d| d.name)
+            .filter(|name| !roster.is_withdrawn(name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Sync `server` to `list`, as a fake client that returned it, and
+    /// register what the sync asks for.
+    async fn sync_to(
+        roster: &mut McpRoster,
+        registry: &Registry,
+        server: &str,
+        list: &[&str],
+    ) -> ToolDelta {
+        let mut taken: HashSet<String> = offered(registry, roster).await.into_iter().collect();
+        let change = roster.sync(server, &names(list), &mut taken);
+        for (registered, _) in &change.register {
+            registry
+                .register(FakeTool::new(registered.clone(), "ok"))
+                .await;
+        }
+        change.delta
+    }
+
+    #[tokio::test]
+    async fn a_changed_tool_list_updates_the_registry() {
+        let registry = Registry::new();
+        registry.register(FakeTool::new("bash", "ok")).await;
+        let mut roster = McpRoster::new(McpConflictPolicy::Prefix);
+
+        let delta = sync_to(&mut roster, &registry, "github", &["search_issues"]).await;
+        assert_eq!(delta.to_string(), "MCP github: +1 tool");
+        assert_eq!(offered(&registry, &roster).await, ["bash", "search_issues"]);
+
+        let delta = sync_to(&mut roster, &registry, "github", &["create_pr", "merge_pr"]).await;
+        assert_eq!(delta.to_string(), "MCP github: +2 tools, -1");
+        assert_eq!(delta.removed, ["search_issues"]);
+        assert_eq!(
+            offered(&registry, &roster).await,
+            ["bash", "create_pr", "merge_pr"]
+        );
+        assert!(roster.is_withdrawn("search_issues"));
+        assert_eq!(roster.server_of("create_pr"), Some("github"));
+        assert_eq!(roster.server_of("search_issues"), None);
+    }
+
+    #[tokio::test]
+    async fn an_unchanged_list_is_no_delta_and_a_returning_tool_comes_back() {
+        let registry = Registry::new();
+        let mut roster = McpRoster::new(McpConflictPolicy::Prefix);
+        sync_to(&mut roster, &registry, "fs", &["tree", "stat"]).await;
+        assert!(
+            sync_to(&mut roster, &registry, "fs", &["stat", "tree"])
+                .await
+                .is_empty()
+        );
+
+        sync_to(&mut roster, &registry, "fs", &["tree"]).await;
+        assert_eq!(offered(&registry, &roster).await, ["tree"]);
+        let delta = sync_to(&mut roster, &registry, "fs", &["tree", "stat"]).await;
+        assert_eq!(delta.added, ["stat"]);
+        assert_eq!(offered(&registry, &roster).await, ["stat", "tree"]);
+    }
+
+    #[tokio::test]
+    async fn a_new_tool_with_a_taken_name_is_placed_per_policy() {
+        let registry = Registry::new();
+        registry.register(FakeTool::new("search", "ok")).await;
+        let mut roster = McpRoster::new(McpConflictPolicy::Prefix);
+        let delta = sync_to(&mut roster, &registry, "github", &["search"]).await;
+        assert_eq!(delta.added, ["github__search"]);
+
+        let mut roster = McpRoster::new(McpConflictPolicy::Error);
+        let delta = sync_to(&mut roster, &registry, "jira", &["search"]).await;
+        assert!(delta.is_empty());
+    }
+
+    #[test]
+    fn resync_is_off_without_servers() {
+        let roster = McpRoster::new(McpConflictPolicy::Prefix)
+            .with_resync_every(Some(Duration::from_secs(60)));
+        assert_eq!(roster.resync_every(), None);
+    }
+}
@@ -0,0 +1,342 @@
+// ABOUTME: Tool call summaries — short, human-readable renderings of tool parameters.
+// ABOUTME: Shared by the TUI tool-call lines and the approval prompt so both show the same text.
+
+use std::path::{Component, Path};
+
+use serde_json::Value;
+
+/// Characters a summary is cut to unless `[tools] summary_max_chars` says
+/// otherwise.
+pub const DEFAULT_SUMMARY_CHARS: usize = 200;
+
+/// Maximum characters of any single value in the generic fallback.
+const FALLBACK_VALUE_MAX_CHARS: usize = 40;
+
+/// Maximum characters of the whole generic fallback summary.
+const FALLBACK_MAX_CHARS: usize = 120;
+
+/// Summarize a tool call's parameters for display, leading with the fields
+/// that matter for each known tool. The result is the text that goes inside
+/// `tool_name(...)`.
+pub fn summarize_params(tool_name: &str, params: &Value) -> String {
+    summarize_params_within(tool_name, params, DEFAULT_SUMMARY_CHARS)
+}
+
+/// [`summarize_params`] cut to at most `max_chars` characters, then "..."
+/// when anything was cut.
+pub fn summarize_params_within(tool_name: &str, params: &Value, max_chars: usize) -> String {
+    match known_tool_summary(tool_name, params) {
+        Some(summary) => truncate_chars(&summary, max_chars),
+        None => truncate_chars(
+            &summarize_generic(params),
+            FALLBACK_MAX_CHARS.min(max_chars),
+        ),
+    }
+}
+
+/// The fields that matter for a tool we know, or None to fall back.
+fn known_tool_summary(tool_name: &str, params: &Value) -> Option<String> {
+    match tool_name {
+        "bash" => str_field(params, "command").map(collapse_whitespace),
+        "write_file" => str_field(params, "path").map(|path| {
+            let len = str_field(params, "content").map_or(0, |c| c.len());
+            format!("{}, {}", path, format_size(len))
+        }),
+        "read_file" | "list_files" => str_field(params, "path").map(str::to_string),
+        "search" => str_field(params, "pattern").map(|pattern| match str_field(params, "path") {
+            Some(path) => format!("{:?} in {}", pattern, path),
+            None => format!("{:?}", pattern),
+        }),
+        _ => None,
+    }
+}
+
+/// Render a tool call as `tool_name(summary)`.
+pub fn describe_tool_call(tool_name: &str, params: &Value) -> String {
+    describe_tool_call_within(tool_name, params, DEFAULT_SUMMARY_CHARS)
+}
+
+/// [`describe_tool_call`] with the summary cut to `max_chars`.
+pub fn describe_tool_call_within(tool_name: &str, params: &Value, max_chars: usize) -> String {
+    format!(
+        "{}({})",
+        tool_name,
+        summarize_params_within(tool_name, params, max_chars)
+    )
+}
+
+/// Version-control metadata directories; writing inside one can corrupt a repo.
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn"];
+
+/// Commands whose plain arguments are paths they change.
+const WRITING_COMMANDS: &[&str] = &[
+    "rm", "rmdir", "mv", "cp", "tee", "touch", "mkdir", "ln", "chmod", "chown", "truncate",
+];
+
+/// A warning for a call that writes inside a VCS metadata directory, e.g.
+/// "⚠️ writes inside .git/". A heuristic: write_file's `path`, and for bash
+/// the targets of redirections and of common file-changing commands.
+pub fn vcs_write_warning(tool_name: &str, params: &Value) -> Option<String> {
+    let targets = match tool_name {
+        "write_file" => str_field(params, "path").into_iter().collect(),
+        "bash" => str_field(params, "command").map_or_else(Vec::new, bash_write_targets),
+        _ => Vec::new(),
+    };
+    let dir = targets.into_iter().find_map(vcs_dir_in)?;
+    Some(format!("\u{26a0}\u{fe0f} writes inside {}/", dir))
+}
+
+/// The VCS metadata directory `path` is inside (or is), if any.
+fn vcs_dir_in(path: &str) -> Option<&'static str> {
+    Path::new(path)
+        .components()
+        .find_map(|component| match component {
+            Component::Normal(name) => VCS_DIRS.iter().copied().find(|dir| name == *dir),
+            _ => None,
+        })
+}
+
+/// Paths a shell command looks like it writes: redirection targets, and the
+/// non-flag arguments of `WRITING_COMMANDS`.
+fn bash_write_targets(command: &str) -> Vec<&str> {
+    let mut targets = Vec::new();
+    for segment in command.split([';', '|', '&', '\n']) {
+        let mut words = segment.split_whitespace();
+        let writes = words
+            .clone()
+            .next()
+            .is_some_and(|program| WRITING_COMMANDS.contains(&program));
+        while let Some(word) = words.next() {
+            let target = match word.find('>') {
+                Some(at) => match word[at..].trim_start_matches('>') {
+                    "" => words.next(),
+                    rest => Some(rest),
+                },
+                None if writes && !word.starts_with('-') => Some(word),
+                None => None,
+            };
+            if let Some(target) = target {
+                targets.push(target.trim_matches(['"', '\'']));
+            }
+        }
+    }
+    targets
+}
+
+/// Truncate to at most `max` characters, appending "..." when shortened.
+/// Counts chars rather than bytes so multi-byte text never splits mid-character.
+pub fn truncate_chars(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let truncated: String = s.chars().take(max).collect();
+    format!("{}...", truncated)
+}
+
+/// Format a byte count as "512 B", "2.1 KB", or "3.4 MB".
+pub fn format_size(bytes: usize) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+fn str_field<'a>(params: &'a Value, key: &str) -> Option<&'a str> {
+    params.get(key).and_then(|v| v.as_str())
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Fallback for tools without a dedicated summarizer: `key=value` pairs for
+/// the top-level fields, with each value shortened. The caller caps the whole.
+fn summarize_generic(params: &Value) -> String {
+    let Some(obj) = params.as_object() else {
+        return params.to_string();
+    };
+    let parts: Vec<String> = obj
+        .iter()
+        .map(|(key, value)| {
+            let rendered = match value {
+                Value::String(s) => collapse_whitespace(s),
+                other => other.to_string(),
+            };
+            format!(
+                "{}={}",
+                key,
+                truncate_chars(&rendered, FALLBACK_VALUE_MAX_CHARS)
+            )
+        })
+        .collect();
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bash_shows_collapsed_command() {
+        let params = json!({"command": "cargo   test\n  --workspace"});
+        assert_eq!(summarize_params("bash", &params), "cargo test --workspace");
+    }
+
+    #[test]
+    fn bash_long_command_truncated_at_larger_cap() {
+        let cmd = format!("echo {}", "x".repeat(300));
+        let summary = summarize_params("bash", &json!({ "command": cmd }));
+        assert_eq!(summary.chars().count(), DEFAULT_SUMMARY_CHARS + 3);
+        assert!(summary.ends_with("..."));
+    }
+
+    #[test]
+    fn write_file_shows_path_and_size() {
+        let params = json!({"path": "src/main.rs", "content": "x".repeat(2150)});
+        assert_eq!(
+            summarize_params("write_file", &params),
+            "src/main.rs, 2.1 KB"
+        );
+    }
+
+    #[test]
+    fn write_file_small_content_in_bytes() {
+        let params = json!({"path": "a.txt", "content": "hello"});
+        assert_eq!(summarize_params("write_file", &params), "a.txt, 5 B");
+    }
+
+    #[test]
+    fn read_and_list_show_path() {
+        assert_eq!(
+            summarize_params("read_file", &json!({"path": "/etc/hosts"})),
+            "/etc/hosts"
+        );
+        assert_eq!(
+            summarize_params("list_files", &json!({"path": "src", "recursive": true})),
+            "src"
+        );
+    }
+
+    #[test]
+    fn search_shows_pattern_and_path() {
+        let params = json!({"pattern": "fn main", "path": "src"});
+        assert_eq!(summarize_params("search", &params), "\"fn main\" in src");
+        assert_eq!(
+            summarize_params("search", &json!({"pattern": "todo"})),
+            "\"todo\""
+        );
+    }
+
+    #[test]
+    fn writes_into_vcs_directories_are_flagged() {
+        let warning = Some("\u{26a0}\u{fe0f} writes inside .git/".to_string());
+        let write = json!({"path": "repo/.git/config", "content": "x"});
+        assert_eq!(vcs_write_warning("write_file", &write), warning);
+        let redirect = json!({"command": "echo hi >> .git/hooks/pre-commit"});
+        assert_eq!(vcs_write_warning("bash", &redirect), warning);
+        let remove = json!({"command": "cd repo && rm -rf .git/refs"});
+        assert_eq!(vcs_write_warning("bash", &remove), warning);
+        let hg = json!({"command": "echo x>.hg/hgrc"});
+        assert_eq!(
+            vcs_write_warning("bash", &hg).as_deref(),
+            Some("\u{26a0}\u{fe0f} writes inside .hg/")
+        );
+    }
+
+    #[test]
+    fn ordinary_paths_and_reads_are_not_flagged() {
+        let write = json!({"path": "src/.gitignore", "content": "target"});
+        assert_eq!(vcs_write_warning("write_file", &write), None);
+        let read = json!({"command": "cat .git/config > config.txt"});
+        assert_eq!(vcs_write_warning("bash", &read), None);
+        let status = json!({"command": "git status 2>&1 | tee status.log"});
+        assert_eq!(vcs_write_warning("bash", &status), None);
+        assert_eq!(
+            vcs_write_warning("read_file", &json!({"path": ".git/HEAD"})),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_tool_falls_back_to_key_value_pairs() {
+        let params = json!({"query": "rust async", "limit": 5});
+        assert_eq!(
+            summarize_params("web_search", &params),
+            "limit=5, query=rust async"
+        );
+    }
+
+    #[test]
+    fn fallback_truncates_long_values() {
+        let params = json!({"body": "y".repeat(100)});
+        let summary = summarize_params("mcp_tool", &params);
+        assert_eq!(
+            summary,
+            format!("body={}...", "y".repeat(FALLBACK_VALUE_MAX_CHARS))
+        );
+    }
+
+    #[test]
+    fn known_tool_missing_fields_uses_fallback() {
+        let params = json!({"cmd": "ls"});
+        assert_eq!(summarize_params("bash", &params), "cmd=ls");
+    }
+
+    #[test]
+    fn describe_wraps_in_tool_name() {
+        let params = json!({"command": "ls -la"});
+        assert_eq!(describe_tool_call("bash", &params), "bash(ls -la)");
+    }
+
+    #[test]
+    fn truncate_chars_is_unicode_safe() {
+        assert_eq!(truncate_chars("héllo wörld", 5), "héllo...");
+        assert_eq!(truncate_chars("short", 10), "short");
+    }
+
+    #[test]
+    fn multibyte_summaries_are_marked_only_when_cut() {
+        // 200 two-byte characters: over 200 bytes, but within the limit.
+        let fits = json!({ "command": "é".repeat(DEFAULT_SUMMARY_CHARS) });
+        assert_eq!(
+            summarize_params("bash", &fits),
+            "é".repeat(DEFAULT_SUMMARY_CHARS)
+        );
+
+        let over = json!({ "command": "日本".repeat(DEFAULT_SUMMARY_CHARS) });
+        let summary = summarize_params("bash", &over);
+        assert!(summary.ends_with("..."));
+        assert_eq!(summary.chars().count(), DEFAULT_SUMMARY_CHARS + 3);
+    }
+
+    #[test]
+    fn summaries_follow_the_configured_limit() {
+        let params = json!({"command": "cargo test --workspace"});
+        assert_eq!(
+            summarize_params_within("bash", &params, 10),
+            "cargo test..."
+        );
+        assert_eq!(
+            describe_tool_call_within("bash", &params, 22),
+            "bash(cargo test --workspace)"
+        );
+
+        // The generic fallback keeps its own, smaller cap under a large limit.
+        let params = json!({"a": "x".repeat(39), "b": "y".repeat(39), "c": "z".repeat(39)});
+        let summary = summarize_params_within("mcp_tool", &params, 1000);
+        assert_eq!(summary.chars().count(), FALLBACK_MAX_CHARS + 3);
+        let summary = summarize_params_within("mcp_tool", &params, 20);
+        assert_eq!(summary, format!("a={}...", "x".repeat(18)));
+    }
+
+    #[test]
+    fn format_size_units() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
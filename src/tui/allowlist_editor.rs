@@ -0,0 +1,246 @@
+// ABOUTME: Allowlist editor — modal list of approved patterns per tool, opened by /approvals.
+// ABOUTME: Handles navigation and asks for confirmation before a deletion is handed back to persist.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::approval::AllowlistRow;
+
+/// What the model should do after the editor handled a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorAction {
+    None,
+    Close,
+    /// The user confirmed deleting this entry.
+    Delete {
+        tool: String,
+        pattern: String,
+    },
+}
+
+/// State of the open allowlist editor.
+pub struct AllowlistEditor {
+    pub rows: Vec<AllowlistRow>,
+    pub selected: usize,
+    /// Set while the "delete this entry?" confirmation is showing.
+    pub confirm_delete: bool,
+}
+
+impl AllowlistEditor {
+    pub fn new(rows: Vec<AllowlistRow>) -> Self {
+        Self {
+            rows,
+            selected: 0,
+            confirm_delete: false,
+        }
+    }
+
+    /// Handle a key press. While confirming, `y` deletes and anything else
+    /// backs out.
+    pub fn handle_key(&mut self, key: KeyEvent) -> EditorAction {
+        if self.confirm_delete {
+            self.confirm_delete = false;
+            return match (key.code, self.rows.get(self.selected)) {
+                (KeyCode::Char('y' | 'Y'), Some(row)) => EditorAction::Delete {
+                    tool: row.tool.clone(),
+                    pattern: row.pattern.clone(),
+                },
+                _ => EditorAction::None,
+            };
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                EditorAction::None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+                EditorAction::None
+            }
+            KeyCode::Char('d') | KeyCode::Delete | KeyCode::Backspace => {
+                self.confirm_delete = !self.rows.is_empty();
+                EditorAction::None
+            }
+            KeyCode::Esc | KeyCode::Char('q') => EditorAction::Close,
+            _ => EditorAction::None,
+        }
+    }
+
+    /// Drop a deleted entry from the list, keeping the selection in range.
+    pub fn remove(&mut self, tool: &str, pattern: &str) {
+        self.rows
+            .retain(|r| !(r.tool == tool && r.pattern == pattern));
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    /// Render the editor: a title, entries grouped under their tool, and the
+    /// key hints or delete confirmation at the bottom.
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(Span::styled(
+            format!("\u{1f513} Allowlist ({} entries)", self.rows.len()),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ))];
+
+        if self.rows.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "   No allowlist entries yet. Choose \"Always Allow\" on a prompt to add one.",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+
+        let mut current_tool: Option<&str> = None;
+        for (i, row) in self.rows.iter().enumerate() {
+            if current_tool != Some(row.tool.as_str()) {
+                current_tool = Some(row.tool.as_str());
+                lines.push(Line::from(Span::styled(
+                    format!(" {}", row.tool),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )));
+            }
+            let last_used = row
+                .last_used_at
+                .map(|t| format!("  last used {}", t.format("%Y-%m-%d")))
+                .unwrap_or_default();
+            let (marker, style) = if i == self.selected {
+                (
+                    "\u{25b6} ",
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ("  ", Style::default())
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("  {}", marker)),
+                Span::styled(row.pattern.clone(), style),
+                Span::styled(last_used, Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+
+        lines.push(Line::from(""));
+        let footer = match self.rows.get(self.selected) {
+            Some(row) if self.confirm_delete => Span::styled(
+                format!(
+                    "Delete {} entry '{}'? [y] yes  [n] no",
+                    row.tool, row.pattern
+                ),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            _ => Span::styled(
+                "\u{2191}\u{2193} select  [d] delete  [Esc] close",
+                Style::default().fg(Color::DarkGray),
+            ),
+        };
+        lines.push(Line::from(footer));
+        lines
+    }
+
+    /// Index into `lines()` of the selected entry, for scrolling it into view.
+    pub fn selected_line(&self) -> usize {
+        let mut line = 1;
+        let mut current_tool: Option<&str> = None;
+        for (i, row) in self.rows.iter().enumerate() {
+            if current_tool != Some(row.tool.as_str()) {
+                current_tool = Some(row.tool.as_str());
+                line += 1;
+            }
+            if i == self.selected {
+                return line;
+            }
+            line += 1;
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    fn row(tool: &str, pattern: &str) -> AllowlistRow {
+        AllowlistRow {
+            tool: tool.to_string(),
+            pattern: pattern.to_string(),
+            last_used_at: None,
+        }
+    }
+
+    fn press(editor: &mut AllowlistEditor, code: KeyCode) -> EditorAction {
+        editor.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn editor() -> AllowlistEditor {
+        AllowlistEditor::new(vec![
+            row("bash", "/usr/bin/git"),
+            row("bash", "/usr/bin/make"),
+            row("mcp_fetch", "mcp_fetch"),
+        ])
+    }
+
+    #[test]
+    fn navigation_stays_in_range() {
+        let mut editor = editor();
+        press(&mut editor, KeyCode::Up);
+        assert_eq!(editor.selected, 0);
+        for _ in 0..5 {
+            press(&mut editor, KeyCode::Down);
+        }
+        assert_eq!(editor.selected, 2);
+        assert_eq!(press(&mut editor, KeyCode::Esc), EditorAction::Close);
+    }
+
+    #[test]
+    fn delete_requires_confirmation() {
+        let mut editor = editor();
+        press(&mut editor, KeyCode::Down);
+        assert_eq!(press(&mut editor, KeyCode::Char('d')), EditorAction::None);
+        assert!(editor.confirm_delete);
+        assert_eq!(press(&mut editor, KeyCode::Char('n')), EditorAction::None);
+        assert!(!editor.confirm_delete);
+
+        press(&mut editor, KeyCode::Char('d'));
+        assert_eq!(
+            press(&mut editor, KeyCode::Char('y')),
+            EditorAction::Delete {
+                tool: "bash".to_string(),
+                pattern: "/usr/bin/make".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn delete_on_empty_list_does_nothing() {
+        let mut editor = AllowlistEditor::new(Vec::new());
+        press(&mut editor, KeyCode::Char('d'));
+        assert!(!editor.confirm_delete);
+        assert_eq!(press(&mut editor, KeyCode::Char('y')), EditorAction::None);
+    }
+
+    #[test]
+    fn remove_clamps_selection() {
+        let mut editor = editor();
+        editor.selected = 2;
+        editor.remove("mcp_fetch", "mcp_fetch");
+        assert_eq!(editor.rows.len(), 2);
+        assert_eq!(editor.selected, 1);
+    }
+
+    #[test]
+    fn lines_group_entries_under_tools() {
+        let editor = editor();
+        let text: Vec<String> = editor.lines().iter().map(|l| l.to_string()).collect();
+        assert_eq!(text[0], "\u{1f513} Allowlist (3 entries)");
+        assert_eq!(text[1], " bash");
+        assert!(text[2].contains("/usr/bin/git"));
+        assert_eq!(text[4], " mcp_fetch");
+        assert_eq!(editor.selected_line(), 2);
+    }
+}
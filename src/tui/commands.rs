@@ -0,0 +1,557 @@
+// ABOUTME: Slash commands — the SlashCommand trait, the built-in commands, and the registry the TUI consults.
+// ABOUTME: Parses "/name args", resolves names and aliases, completes typed prefixes, and splits quoted arguments.
+
+use crate::tui::state::UserEvent;
+
+/// Most completions listed in the popup at once.
+pub const MAX_COMPLETIONS: usize = 8;
+
+/// Something the TUI does itself when a command runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalAction {
+    Help,
+    Tools,
+    Info,
+    Skills,
+    Approvals,
+    Observe,
+    Quiet(String),
+    Copy,
+    Effort(String),
+    Edit(String),
+    Forget(String),
+    Regenerate(String),
+    Changes,
+    Revert(String),
+}
+
+/// What running a slash command asks for.
+pub enum SlashAction {
+    /// Handled by the TUI.
+    Local(LocalAction),
+    /// Sent straight to the agent loop.
+    Send(UserEvent),
+}
+
+/// A command typed as `/name args` in the input.
+pub trait SlashCommand: Send + Sync {
+    /// The name, without the slash.
+    fn name(&self) -> &str;
+
+    /// Other names it answers to.
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// What goes after the name, e.g. `<path>`; None when it takes nothing.
+    fn arg_hint(&self) -> Option<&str> {
+        None
+    }
+
+    /// One line for the completion popup and /help.
+    fn help(&self) -> &str;
+
+    /// Refused while a turn is running.
+    fn idle_only(&self) -> bool {
+        false
+    }
+
+    /// Run with `args`, the raw text after the name. An error is shown to the
+    /// user as is, e.g. a usage line.
+    fn execute(&self, args: &str) -> Result<SlashAction, String>;
+}
+
+/// How `command` is typed: `/name <args>`.
+pub fn signature(command: &dyn SlashCommand) -> String {
+    match command.arg_hint() {
+        Some(hint) => format!("/{} {}", command.name(), hint),
+        None => format!("/{}", command.name()),
+    }
+}
+
+/// The `Usage: /name <args>` line for `command`.
+pub fn usage(command: &dyn SlashCommand) -> String {
+    format!("Usage: {}", signature(command))
+}
+
+/// A command built in to soloclaw.
+struct Builtin {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    arg_hint: Option<&'static str>,
+    help: &'static str,
+    idle_only: bool,
+    run: Run,
+}
+
+/// How a built-in command runs.
+enum Run {
+    /// Ignores any arguments and does this.
+    Always(LocalAction),
+    /// Works out what to do from the arguments.
+    With(fn(&str) -> Result<SlashAction, String>),
+}
+
+impl SlashCommand for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn aliases(&self) -> &[&str] {
+        self.aliases
+    }
+
+    fn arg_hint(&self) -> Option<&str> {
+        self.arg_hint
+    }
+
+    fn help(&self) -> &str {
+        self.help
+    }
+
+    fn idle_only(&self) -> bool {
+        self.idle_only
+    }
+
+    fn execute(&self, args: &str) -> Result<SlashAction, String> {
+        match &self.run {
+            Run::Always(action) => Ok(SlashAction::Local(action.clone())),
+            Run::With(run) => run(args),
+        }
+    }
+}
+
+fn local(action: LocalAction) -> Result<SlashAction, String> {
+    Ok(SlashAction::Local(action))
+}
+
+/// A command that takes no arguments.
+fn simple(name: &'static str, help: &'static str, action: LocalAction) -> Builtin {
+    Builtin {
+        name,
+        aliases: &[],
+        arg_hint: None,
+        help,
+        idle_only: false,
+        run: Run::Always(action),
+    }
+}
+
+fn builtins() -> Vec<Builtin> {
+    vec![
+        simple("help", "List slash commands", LocalAction::Help),
+        simple(
+            "tools",
+            "List available tools and their security level",
+            LocalAction::Tools,
+        ),
+        simple(
+            "info",
+            "Show the workspace, session, and config paths in use",
+            LocalAction::Info,
+        ),
+        simple(
+            "skills",
+            "Show discovered skills and whether they loaded",
+            LocalAction::Skills,
+        ),
+        Builtin {
+            name: "reload",
+            aliases: &[],
+            arg_hint: None,
+            help: "Re-read config, context files, and skills",
+            idle_only: true,
+            run: Run::With(|_| Ok(SlashAction::Send(UserEvent::Reload))),
+        },
+        simple(
+            "approvals",
+            "Browse and delete allowlist entries",
+            LocalAction::Approvals,
+        ),
+        simple(
+            "copy",
+            "Copy the last reply to the clipboard",
+            LocalAction::Copy,
+        ),
+        simple("observe", "Toggle observe mode", LocalAction::Observe),
+        Builtin {
+            name: "quiet",
+            aliases: &[],
+            arg_hint: Some("[full|compact|hidden]"),
+            help: "Toggle one-line tool output, or pick how much to show",
+            idle_only: false,
+            run: Run::With(|args| local(LocalAction::Quiet(args.trim().to_string()))),
+        },
+        Builtin {
+            name: "effort",
+            aliases: &[],
+            arg_hint: Some("low|medium|high|off"),
+            help: "Change the reasoning effort",
+            idle_only: false,
+            run: Run::With(|args| local(LocalAction::Effort(args.trim().to_string()))),
+        },
+        Builtin {
+            name: "regen",
+            aliases: &["retry"],
+            arg_hint: Some("[hint]"),
+            help: "Discard the last answer and ask again",
+            idle_only: false,
+            run: Run::With(|args| local(LocalAction::Regenerate(args.trim().to_string()))),
+        },
+        Builtin {
+            name: "forget",
+            aliases: &[],
+            arg_hint: Some("[n]"),
+            help: "Redact your last (or nth most recent) message",
+            idle_only: false,
+            run: Run::With(|args| local(LocalAction::Forget(args.trim().to_string()))),
+        },
+        Builtin {
+            name: "pin",
+            aliases: &[],
+            arg_hint: Some("[reply] [n]"),
+            help: "Keep your last (or nth most recent) message or reply through compaction",
+            idle_only: true,
+            run: Run::With(|args| {
+                let args = args.trim();
+                let (reply, n) = match args.strip_prefix("reply") {
+                    Some(rest) => (true, rest.trim()),
+                    None => (false, args),
+                };
+                match n {
+                    "" => Ok(SlashAction::Send(UserEvent::Pin { nth: 1, reply })),
+                    n => match n.parse::<usize>() {
+                        Ok(nth) if nth > 0 => Ok(SlashAction::Send(UserEvent::Pin { nth, reply })),
+                        _ => Err(
+                            "Usage: /pin [reply] [n] (n counts back from your last message, or the last reply)"
+                                .to_string(),
+                        ),
+                    },
+                }
+            }),
+        },
+        Builtin {
+            name: "edit",
+            aliases: &[],
+            arg_hint: Some("<path>"),
+            help: "Open a workspace file in your editor",
+            idle_only: false,
+            run: Run::With(|args| match split_args(args)?.as_slice() {
+                [path] => local(LocalAction::Edit(path.clone())),
+                _ => Err("Usage: /edit <path>".to_string()),
+            }),
+        },
+        simple(
+            "changes",
+            "List the files the agent changed this session",
+            LocalAction::Changes,
+        ),
+        Builtin {
+            name: "revert",
+            aliases: &[],
+            arg_hint: Some("<path>"),
+            help: "Undo the agent's latest write to a file",
+            idle_only: true,
+            run: Run::With(|args| match split_args(args)?.as_slice() {
+                [path] => local(LocalAction::Revert(path.clone())),
+                _ => Err("Usage: /revert <path>".to_string()),
+            }),
+        },
+    ]
+}
+
+/// The slash commands the TUI knows, in the order /help lists them.
+pub struct CommandRegistry {
+    commands: Vec<Box<dyn SlashCommand>>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            commands: Vec::new(),
+        };
+        for command in builtins() {
+            registry.register(command);
+        }
+        registry
+    }
+}
+
+impl CommandRegistry {
+    /// Add `command`, replacing one already registered under its name.
+    pub fn register(&mut self, command: impl SlashCommand + 'static) {
+        self.commands.retain(|c| c.name() != command.name());
+        self.commands.push(Box::new(command));
+    }
+
+    /// The command called `name` (without the slash) or aliased to it.
+    pub fn resolve(&self, name: &str) -> Option<&dyn SlashCommand> {
+        self.commands
+            .iter()
+            .find(|c| c.name() == name)
+            .or_else(|| self.commands.iter().find(|c| c.aliases().contains(&name)))
+            .map(|c| c.as_ref())
+    }
+
+    /// Commands whose name or an alias starts with `prefix`, sorted by name.
+    pub fn completions(&self, prefix: &str) -> Vec<&dyn SlashCommand> {
+        let mut matches: Vec<&dyn SlashCommand> = self
+            .commands
+            .iter()
+            .filter(|c| {
+                c.name().starts_with(prefix) || c.aliases().iter().any(|a| a.starts_with(prefix))
+            })
+            .map(|c| c.as_ref())
+            .collect();
+        matches.sort_by(|a, b| a.name().cmp(b.name()));
+        matches
+    }
+
+    /// What Tab turns the typed `prefix` into: the whole name when only one
+    /// command matches (with a space when it takes arguments), otherwise the
+    /// longest prefix the matching names share. None when that adds nothing.
+    pub fn complete(&self, prefix: &str) -> Option<String> {
+        let matches = self.completions(prefix);
+        let completed = match matches.as_slice() {
+            [] => return None,
+            [only] if only.arg_hint().is_some() => format!("{} ", only.name()),
+            [only] => only.name().to_string(),
+            [first, rest @ ..] => rest.iter().fold(first.name().to_string(), |common, c| {
+                common
+                    .chars()
+                    .zip(c.name().chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect()
+            }),
+        };
+        (completed.len() > prefix.len() && completed.starts_with(prefix)).then_some(completed)
+    }
+
+    /// The /help listing.
+    pub fn help(&self) -> String {
+        let mut out = String::from("Slash commands:");
+        for command in &self.commands {
+            let signature = signature(command.as_ref());
+            out.push_str(&format!("\n  {:<28} {}", signature, command.help()));
+            if !command.aliases().is_empty() {
+                let aliases: Vec<String> = command
+                    .aliases()
+                    .iter()
+                    .map(|a| format!("/{}", a))
+                    .collect();
+                out.push_str(&format!(" (also {})", aliases.join(", ")));
+            }
+        }
+        out
+    }
+}
+
+/// Whether `c` can be part of a command name.
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'
+}
+
+/// Split `text` into a command name (without the slash) and the raw
+/// arguments after it. None when `text` isn't a command: it doesn't start
+/// with `/`, or the first word isn't a plain name, so a message starting
+/// with a path like `/usr/bin/env` is sent as typed.
+pub fn parse(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix('/')?;
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let (name, args) = rest.split_at(end);
+    name.chars()
+        .all(is_name_char)
+        .then_some((name, args.trim_start()))
+}
+
+/// The command name being typed, when the input is `/` and a name with
+/// nothing after it yet; this is when the completion popup shows.
+pub fn typed_prefix(input: &str) -> Option<&str> {
+    let (name, args) = parse(input)?;
+    (args.is_empty() && !input.ends_with(char::is_whitespace)).then_some(name)
+}
+
+/// Split arguments on whitespace, keeping text in single or double quotes
+/// together, e.g. `"my notes.md" -v` is `my notes.md` and `-v`. A
+/// backslash outside single quotes escapes the next character.
+pub fn split_args(args: &str) -> Result<Vec<String>, String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                in_word = true;
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    out.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("Unclosed {} in arguments", q));
+    }
+    if in_word {
+        out.push(current);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(commands: &[&dyn SlashCommand]) -> Vec<String> {
+        commands.iter().map(|c| c.name().to_string()).collect()
+    }
+
+    #[test]
+    fn prefixes_match_names_and_aliases() {
+        let registry = CommandRegistry::default();
+        assert_eq!(
+            names(&registry.completions("re")),
+            ["regen", "reload", "revert"]
+        );
+        assert_eq!(names(&registry.completions("ret")), ["regen"]);
+        assert!(registry.completions("zz").is_empty());
+        assert_eq!(registry.completions("").len(), builtins().len());
+
+        assert_eq!(registry.complete("ap").as_deref(), Some("approvals"));
+        assert_eq!(registry.complete("ed").as_deref(), Some("edit "));
+        assert_eq!(registry.complete("s").as_deref(), Some("skills"));
+        assert_eq!(registry.complete("re").as_deref(), None);
+        assert_eq!(registry.complete("t").as_deref(), Some("tools"));
+    }
+
+    #[test]
+    fn aliases_resolve_to_their_command() {
+        let registry = CommandRegistry::default();
+        assert_eq!(registry.resolve("retry").unwrap().name(), "regen");
+        assert_eq!(registry.resolve("regen").unwrap().name(), "regen");
+        assert!(registry.resolve("retr").is_none());
+        assert!(registry.help().contains("(also /retry)"));
+    }
+
+    #[test]
+    fn only_plain_names_are_commands() {
+        assert_eq!(
+            parse("/regen make it shorter"),
+            Some(("regen", "make it shorter"))
+        );
+        assert_eq!(parse("/tools"), Some(("tools", "")));
+        assert_eq!(parse("/typo"), Some(("typo", "")));
+        assert_eq!(parse("/"), Some(("", "")));
+        assert_eq!(parse("/usr/bin/env is missing"), None);
+        assert_eq!(parse("/Tools"), None);
+        assert_eq!(parse("tools"), None);
+
+        assert_eq!(typed_prefix("/ed"), Some("ed"));
+        assert_eq!(typed_prefix("/"), Some(""));
+        assert_eq!(typed_prefix("/edit "), None);
+        assert_eq!(typed_prefix("/edit notes.md"), None);
+    }
+
+    #[test]
+    fn arguments_split_on_spaces_outside_quotes() {
+        assert_eq!(split_args("  a  b ").unwrap(), ["a", "b"]);
+        assert_eq!(
+            split_args(r#""my notes.md" -v"#).unwrap(),
+            ["my notes.md", "-v"]
+        );
+        assert_eq!(split_args("'it''s' x").unwrap(), ["its", "x"]);
+        assert_eq!(
+            split_args(r#"say\ hi "a \"b\"""#).unwrap(),
+            ["say hi", r#"a "b""#]
+        );
+        assert_eq!(split_args(r#""""#).unwrap(), [""]);
+        assert!(split_args("").unwrap().is_empty());
+        assert!(split_args(r#"open "notes"#).is_err());
+    }
+
+    #[test]
+    fn pin_counts_back_from_the_last_message() {
+        let registry = CommandRegistry::default();
+        let pin = registry.resolve("pin").unwrap();
+        assert!(pin.idle_only());
+        assert!(matches!(
+            pin.execute(""),
+            Ok(SlashAction::Send(UserEvent::Pin {
+                nth: 1,
+                reply: false
+            }))
+        ));
+        assert!(matches!(
+            pin.execute(" 3 "),
+            Ok(SlashAction::Send(UserEvent::Pin {
+                nth: 3,
+                reply: false
+            }))
+        ));
+        assert!(matches!(
+            pin.execute("reply"),
+            Ok(SlashAction::Send(UserEvent::Pin {
+                nth: 1,
+                reply: true
+            }))
+        ));
+        assert!(matches!(
+            pin.execute("reply 2"),
+            Ok(SlashAction::Send(UserEvent::Pin {
+                nth: 2,
+                reply: true
+            }))
+        ));
+        assert!(pin.execute("reply 0").is_err());
+        assert!(pin.execute("0").is_err());
+        assert!(pin.execute("last").is_err());
+    }
+
+    #[test]
+    fn edit_takes_exactly_one_path() {
+        let registry = CommandRegistry::default();
+        let edit = registry.resolve("edit").unwrap();
+        let Ok(SlashAction::Local(action)) = edit.execute(r#""my notes.md""#) else {
+            panic!("expected a local action");
+        };
+        assert_eq!(action, LocalAction::Edit("my notes.md".to_string()));
+        assert_eq!(
+            edit.execute("").err().as_deref(),
+            Some("Usage: /edit <path>")
+        );
+        assert_eq!(edit.execute("a b").err(), Some(usage(edit)));
+    }
+
+    #[test]
+    fn revert_takes_exactly_one_path() {
+        let registry = CommandRegistry::default();
+        let revert = registry.resolve("revert").unwrap();
+        assert!(revert.idle_only());
+        let Ok(SlashAction::Local(action)) = revert.execute("src/main.rs") else {
+            panic!("expected a local action");
+        };
+        assert_eq!(action, LocalAction::Revert("src/main.rs".to_string()));
+        assert_eq!(
+            revert.execute("").err().as_deref(),
+            Some("Usage: /revert <path>")
+        );
+    }
+}
@@ -0,0 +1,230 @@
+// ABOUTME: Consent for terminal features that reach outside soloclaw — OSC 52 clipboard writes, notifications.
+// ABOUTME: A feature whose [privacy] flag is off asks once per session; "always" is remembered in consents.json.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PrivacyConfig;
+
+/// Answers to a consent prompt, in key order (1, 2, 3).
+pub const CONSENT_OPTIONS: &[&str] = &["\u{2705} Yes", "\u{1f513} Always", "\u{1f6ab} No"];
+
+/// A terminal feature gated behind a `[privacy]` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Clipboard writes through the terminal's OSC 52 integration.
+    Osc52,
+    /// Desktop notifications through the terminal.
+    Notifications,
+}
+
+impl Feature {
+    /// Name the feature is remembered under in consents.json.
+    pub fn key(self) -> &'static str {
+        match self {
+            Feature::Osc52 => "osc52",
+            Feature::Notifications => "notifications",
+        }
+    }
+
+    /// The `[privacy]` setting that allows it without asking.
+    pub fn setting(self) -> &'static str {
+        match self {
+            Feature::Osc52 => "allow_osc52",
+            Feature::Notifications => "allow_notifications",
+        }
+    }
+
+    /// What the consent prompt asks.
+    pub fn question(self) -> &'static str {
+        match self {
+            Feature::Osc52 => {
+                "The assistant's reply will be copied via your terminal's clipboard integration \u{2014} allow?"
+            }
+            Feature::Notifications => {
+                "soloclaw will send desktop notifications through your terminal \u{2014} allow?"
+            }
+        }
+    }
+
+    fn allowed_by(self, privacy: &PrivacyConfig) -> bool {
+        match self {
+            Feature::Osc52 => privacy.allow_osc52,
+            Feature::Notifications => privacy.allow_notifications,
+        }
+    }
+}
+
+/// Whether a feature may be used right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    Allowed,
+    /// Not decided yet: show the consent prompt.
+    Ask,
+    /// Declined earlier this session.
+    Refused,
+}
+
+/// An answer to the consent prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentAnswer {
+    /// Allow for the rest of this session.
+    Yes,
+    /// Allow from now on, remembered across sessions.
+    Always,
+    /// Refuse for the rest of this session.
+    No,
+}
+
+impl ConsentAnswer {
+    /// The answer for a `CONSENT_OPTIONS` index; anything past the end is No.
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            0 => ConsentAnswer::Yes,
+            1 => ConsentAnswer::Always,
+            _ => ConsentAnswer::No,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ConsentsFile {
+    #[serde(default)]
+    always: BTreeSet<String>,
+}
+
+/// Consent answers: "always" answers from disk plus this session's.
+#[derive(Debug, Default)]
+pub struct Consents {
+    /// Where "always" answers are saved; None keeps them in memory.
+    path: Option<PathBuf>,
+    always: BTreeSet<String>,
+    session: HashMap<Feature, bool>,
+}
+
+impl Consents {
+    /// Read remembered answers from `path`. A missing or unreadable file
+    /// means nothing was remembered, so every gated feature asks again.
+    pub fn load(path: &Path) -> Self {
+        let file: ConsentsFile = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path: Some(path.to_path_buf()),
+            always: file.always,
+            session: HashMap::new(),
+        }
+    }
+
+    /// Whether `feature` may run: its flag is on, or it was allowed before.
+    pub fn gate(&self, feature: Feature, privacy: &PrivacyConfig) -> Gate {
+        if feature.allowed_by(privacy) || self.always.contains(feature.key()) {
+            return Gate::Allowed;
+        }
+        match self.session.get(&feature) {
+            Some(true) => Gate::Allowed,
+            Some(false) => Gate::Refused,
+            None => Gate::Ask,
+        }
+    }
+
+    /// Record an answer; "always" is saved to disk. Returns whether the
+    /// feature is now allowed.
+    pub fn answer(&mut self, feature: Feature, answer: ConsentAnswer) -> anyhow::Result<bool> {
+        let allowed = answer != ConsentAnswer::No;
+        self.session.insert(feature, allowed);
+        if answer == ConsentAnswer::Always {
+            self.always.insert(feature.key().to_string());
+            if let Some(path) = &self.path {
+                save(path, &self.always)?;
+            }
+        }
+        Ok(allowed)
+    }
+}
+
+fn save(path: &Path, always: &BTreeSet<String>) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = ConsentsFile {
+        always: always.clone(),
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&file)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn off_flags_ask_until_answered_for_the_session() {
+        let privacy = PrivacyConfig::default();
+        let mut consents = Consents::default();
+        assert_eq!(consents.gate(Feature::Osc52, &privacy), Gate::Ask);
+
+        assert!(!consents.answer(Feature::Osc52, ConsentAnswer::No).unwrap());
+        assert_eq!(consents.gate(Feature::Osc52, &privacy), Gate::Refused);
+        // Answers are per feature.
+        assert_eq!(consents.gate(Feature::Notifications, &privacy), Gate::Ask);
+
+        assert!(
+            consents
+                .answer(Feature::Notifications, ConsentAnswer::Yes)
+                .unwrap()
+        );
+        assert_eq!(
+            consents.gate(Feature::Notifications, &privacy),
+            Gate::Allowed
+        );
+    }
+
+    #[test]
+    fn an_enabled_flag_never_asks() {
+        let privacy = PrivacyConfig {
+            allow_osc52: true,
+            ..PrivacyConfig::default()
+        };
+        assert_eq!(
+            Consents::default().gate(Feature::Osc52, &privacy),
+            Gate::Allowed
+        );
+    }
+
+    #[test]
+    fn always_is_remembered_across_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config/consents.json");
+        let privacy = PrivacyConfig::default();
+
+        let mut consents = Consents::load(&path);
+        consents.answer(Feature::Osc52, ConsentAnswer::Yes).unwrap();
+        assert!(!path.exists(), "yes only lasts the session");
+        consents
+            .answer(Feature::Osc52, ConsentAnswer::Always)
+            .unwrap();
+
+        let next_session = Consents::load(&path);
+        assert_eq!(next_session.gate(Feature::Osc52, &privacy), Gate::Allowed);
+        assert_eq!(
+            next_session.gate(Feature::Notifications, &privacy),
+            Gate::Ask
+        );
+    }
+
+    #[test]
+    fn a_corrupt_consents_file_asks_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("consents.json");
+        std::fs::write(&path, "not json").unwrap();
+        let consents = Consents::load(&path);
+        assert_eq!(
+            consents.gate(Feature::Osc52, &PrivacyConfig::default()),
+            Gate::Ask
+        );
+    }
+}
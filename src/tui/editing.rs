@@ -0,0 +1,232 @@
+// ABOUTME: Readline-style input editing — word movement, kill-to-start/end, and yank.
+// ABOUTME: Pure functions over char-indexed drafts; the model turns them into text area edits.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// An editing command beyond what the text area does by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditCommand {
+    /// Alt+← / Alt+b: move to the start of the previous word.
+    WordLeft,
+    /// Alt+→ / Alt+f: move past the end of the next word.
+    WordRight,
+    /// Ctrl+W / Alt+Backspace: kill the word before the cursor.
+    KillWordBack,
+    /// Ctrl+U: kill from the start of the line to the cursor.
+    KillToLineStart,
+    /// Ctrl+K: kill from the cursor to the end of the line.
+    KillToLineEnd,
+    /// Ctrl+Y: insert the last killed text.
+    Yank,
+}
+
+impl EditCommand {
+    pub fn from_key(key: &KeyEvent) -> Option<Self> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Left if alt => Some(EditCommand::WordLeft),
+            KeyCode::Right if alt => Some(EditCommand::WordRight),
+            KeyCode::Char('b') if alt => Some(EditCommand::WordLeft),
+            KeyCode::Char('f') if alt => Some(EditCommand::WordRight),
+            KeyCode::Backspace if alt => Some(EditCommand::KillWordBack),
+            KeyCode::Char('w') if ctrl => Some(EditCommand::KillWordBack),
+            KeyCode::Char('u') if ctrl => Some(EditCommand::KillToLineStart),
+            KeyCode::Char('k') if ctrl => Some(EditCommand::KillToLineEnd),
+            KeyCode::Char('y') if ctrl => Some(EditCommand::Yank),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punct,
+}
+
+/// Letters and digits in any script, plus `_`, make up words; everything
+/// else that isn't whitespace is punctuation. An approximation of Unicode
+/// word segmentation that is good enough for cursor movement.
+fn class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punct
+    }
+}
+
+/// Char index of the start of the word before `cursor`: skip whitespace,
+/// then a run of word characters or a run of punctuation.
+pub fn prev_word_boundary(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = cursor.min(chars.len());
+    while i > 0 && class(chars[i - 1]) == CharClass::Space {
+        i -= 1;
+    }
+    if i > 0 {
+        let run = class(chars[i - 1]);
+        while i > 0 && class(chars[i - 1]) == run {
+            i -= 1;
+        }
+    }
+    i
+}
+
+/// Char index just past the end of the word after `cursor`: skip
+/// whitespace, then a run of word characters or a run of punctuation.
+pub fn next_word_boundary(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = cursor.min(chars.len());
+    while i < chars.len() && class(chars[i]) == CharClass::Space {
+        i += 1;
+    }
+    if i < chars.len() {
+        let run = class(chars[i]);
+        while i < chars.len() && class(chars[i]) == run {
+            i += 1;
+        }
+    }
+    i
+}
+
+/// Char index of the start of the line containing `cursor`.
+pub fn line_start(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().take(cursor).collect();
+    chars.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1)
+}
+
+/// Char index of the end of the line containing `cursor` (before its `\n`).
+pub fn line_end(text: &str, cursor: usize) -> usize {
+    let rest = text.chars().skip(cursor);
+    cursor + rest.take_while(|&c| c != '\n').count()
+}
+
+/// Char index of a (row, column) cursor position in a multi-line draft.
+pub fn cursor_index(text: &str, row: usize, col: usize) -> usize {
+    let mut index = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        let len = line.chars().count();
+        if i == row {
+            return index + col.min(len);
+        }
+        index += len + 1;
+    }
+    text.chars().count()
+}
+
+/// The char range a kill command removes, given the cursor. Killing to the
+/// end of a line that's already at its end takes the newline, so repeated
+/// Ctrl+K joins lines.
+pub fn kill_range(text: &str, cursor: usize, command: EditCommand) -> (usize, usize) {
+    match command {
+        EditCommand::KillWordBack => (prev_word_boundary(text, cursor), cursor),
+        EditCommand::KillToLineStart => (line_start(text, cursor), cursor),
+        EditCommand::KillToLineEnd => {
+            let end = line_end(text, cursor);
+            if end == cursor && cursor < text.chars().count() {
+                (cursor, cursor + 1)
+            } else {
+                (cursor, end)
+            }
+        }
+        _ => (cursor, cursor),
+    }
+}
+
+/// The text between two char indices.
+pub fn char_slice(text: &str, start: usize, end: usize) -> String {
+    text.chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_boundaries_skip_spaces_then_a_word() {
+        let text = "let value = 42;";
+        assert_eq!(prev_word_boundary(text, 9), 4);
+        assert_eq!(prev_word_boundary(text, 4), 0);
+        assert_eq!(next_word_boundary(text, 0), 3);
+        assert_eq!(next_word_boundary(text, 3), 9);
+    }
+
+    #[test]
+    fn punctuation_is_its_own_word() {
+        let text = "foo.bar(baz)";
+        assert_eq!(prev_word_boundary(text, 7), 4);
+        assert_eq!(prev_word_boundary(text, 4), 3);
+        assert_eq!(next_word_boundary(text, 3), 4);
+        assert_eq!(next_word_boundary(text, 7), 8);
+    }
+
+    #[test]
+    fn word_boundaries_handle_unicode() {
+        let text = "caf\u{e9} na\u{ef}ve \u{65e5}\u{672c}\u{8a9e}";
+        assert_eq!(prev_word_boundary(text, 14), 11);
+        assert_eq!(prev_word_boundary(text, 11), 5);
+        assert_eq!(next_word_boundary(text, 0), 4);
+        assert_eq!(next_word_boundary(text, 4), 10);
+    }
+
+    #[test]
+    fn word_boundaries_cross_lines() {
+        let text = "first line\nsecond";
+        assert_eq!(prev_word_boundary(text, 11), 6);
+        assert_eq!(next_word_boundary(text, 10), 17);
+    }
+
+    #[test]
+    fn line_bounds_in_multi_line_drafts() {
+        let text = "ab\ncdef\ng";
+        assert_eq!(line_start(text, 5), 3);
+        assert_eq!(line_end(text, 5), 7);
+        assert_eq!(line_start(text, 1), 0);
+        assert_eq!(line_end(text, 9), 9);
+    }
+
+    #[test]
+    fn cursor_index_counts_newlines() {
+        let text = "ab\ncdef\ng";
+        assert_eq!(cursor_index(text, 0, 1), 1);
+        assert_eq!(cursor_index(text, 1, 2), 5);
+        assert_eq!(cursor_index(text, 2, 5), 9);
+    }
+
+    #[test]
+    fn kill_ranges() {
+        let text = "one two\nthree";
+        assert_eq!(kill_range(text, 7, EditCommand::KillWordBack), (4, 7));
+        assert_eq!(kill_range(text, 10, EditCommand::KillToLineStart), (8, 10));
+        assert_eq!(kill_range(text, 4, EditCommand::KillToLineEnd), (4, 7));
+        // At the end of a line, Ctrl+K takes the newline.
+        assert_eq!(kill_range(text, 7, EditCommand::KillToLineEnd), (7, 8));
+        assert_eq!(char_slice(text, 4, 7), "two");
+    }
+
+    #[test]
+    fn keys_map_to_commands() {
+        let key = |code, modifiers| EditCommand::from_key(&KeyEvent::new(code, modifiers));
+        assert_eq!(
+            key(KeyCode::Left, KeyModifiers::ALT),
+            Some(EditCommand::WordLeft)
+        );
+        assert_eq!(
+            key(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            Some(EditCommand::KillWordBack)
+        );
+        assert_eq!(
+            key(KeyCode::Char('y'), KeyModifiers::CONTROL),
+            Some(EditCommand::Yank)
+        );
+        assert_eq!(key(KeyCode::Left, KeyModifiers::NONE), None);
+        assert_eq!(key(KeyCode::Char('w'), KeyModifiers::NONE), None);
+    }
+}
@@ -0,0 +1,325 @@
+// ABOUTME: External editor hand-off — suspends the TUI, runs $VISUAL/$EDITOR on a file, restores the TUI.
+// ABOUTME: The terminal is always restored, even when the editor fails to start or the editing code panics.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+use anyhow::Context;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute};
+
+use crate::tui::keyboard;
+
+/// Editor used when neither $VISUAL nor $EDITOR is set.
+const FALLBACK_EDITOR: &str = "vi";
+
+/// The terminal modes the TUI runs in, switched off while another program
+/// owns the terminal.
+pub trait TerminalControl {
+    /// Leave raw mode and the alternate screen, as a shell expects.
+    fn suspend(&mut self) -> io::Result<()>;
+    /// Return to the modes the TUI runs in.
+    fn resume(&mut self) -> io::Result<()>;
+}
+
+/// The real terminal, in the modes boba sets up.
+pub struct Crossterm;
+
+impl TerminalControl for Crossterm {
+    fn suspend(&mut self) -> io::Result<()> {
+        if keyboard::enabled() {
+            keyboard::pop()?;
+        }
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            DisableBracketedPaste,
+            DisableMouseCapture,
+            LeaveAlternateScreen,
+            cursor::Show
+        )?;
+        terminal::disable_raw_mode()?;
+        stdout.flush()
+    }
+
+    fn resume(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste,
+            Clear(ClearType::All)
+        )?;
+        if keyboard::enabled() {
+            keyboard::push()?;
+        }
+        stdout.flush()
+    }
+}
+
+/// Resumes the terminal when dropped, so a panic in the suspended section
+/// can't leave the user in a half-restored TUI.
+struct ResumeGuard<'a, T: TerminalControl + ?Sized> {
+    terminal: Option<&'a mut T>,
+}
+
+impl<T: TerminalControl + ?Sized> ResumeGuard<'_, T> {
+    fn resume(mut self) -> io::Result<()> {
+        match self.terminal.take() {
+            Some(terminal) => terminal.resume(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: TerminalControl + ?Sized> Drop for ResumeGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(terminal) = self.terminal.take() {
+            let _ = terminal.resume();
+        }
+    }
+}
+
+/// Run `run` with the terminal suspended, then resume it. A suspend that
+/// fails partway is rolled back and `run` never starts.
+pub fn with_terminal_suspended<T, R>(terminal: &mut T, run: impl FnOnce() -> R) -> io::Result<R>
+where
+    T: TerminalControl + ?Sized,
+{
+    if let Err(e) = terminal.suspend() {
+        let _ = terminal.resume();
+        return Err(e);
+    }
+    let guard = ResumeGuard {
+        terminal: Some(terminal),
+    };
+    let result = run();
+    guard.resume()?;
+    Ok(result)
+}
+
+/// The editor command: $VISUAL, then $EDITOR, then `vi`. Blank values are
+/// skipped.
+pub fn editor_command(visual: Option<String>, editor: Option<String>) -> String {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|command| command.trim().to_string())
+        .find(|command| !command.is_empty())
+        .unwrap_or_else(|| FALLBACK_EDITOR.to_string())
+}
+
+/// Run `editor` on `path` through the shell, so commands with arguments
+/// like `code --wait` work, and wait for it to exit.
+fn spawn_editor(editor: &str, path: &Path) -> io::Result<ExitStatus> {
+    std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("editor")
+        .arg(path)
+        .status()
+}
+
+/// Open `path` in `editor` with the terminal suspended and return the
+/// file's contents once the editor exits. Fails when the editor can't be
+/// started or exits unsuccessfully.
+pub fn edit_file<T>(terminal: &mut T, editor: &str, path: &Path) -> anyhow::Result<String>
+where
+    T: TerminalControl + ?Sized,
+{
+    let status = with_terminal_suspended(terminal, || spawn_editor(editor, path))
+        .context("failed to hand the terminal to the editor")?
+        .with_context(|| format!("failed to start editor `{}`", editor))?;
+    if !status.success() {
+        anyhow::bail!("editor `{}` exited with {}", editor, status);
+    }
+    std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// `path`, relative to `workspace`, as a canonical path inside it. `..` and
+/// symlinks are resolved before the check, so neither reaches a file
+/// outside. The file may not exist yet, but the directory it goes in must.
+pub fn resolve_in_workspace(workspace: &Path, path: &str) -> anyhow::Result<PathBuf> {
+    let root = workspace
+        .canonicalize()
+        .with_context(|| format!("can't resolve the workspace {}", workspace.display()))?;
+    let joined = root.join(path);
+    let resolved = match joined.canonicalize() {
+        Ok(resolved) => resolved,
+        // A dangling symlink could still be followed by the editor.
+        Err(_) if joined.symlink_metadata().is_ok() => {
+            anyhow::bail!("can't resolve {}", path)
+        }
+        Err(_) => {
+            let (Some(dir), Some(name)) = (joined.parent(), joined.file_name()) else {
+                anyhow::bail!("{} is not a file", path);
+            };
+            dir.canonicalize()
+                .with_context(|| format!("no directory for {}", path))?
+                .join(name)
+        }
+    };
+    if !resolved.starts_with(&root) {
+        anyhow::bail!("{} is outside the workspace", path);
+    }
+    Ok(resolved)
+}
+
+/// Open `path` in the user's editor on the real terminal.
+pub fn open_in_terminal(path: &Path) -> anyhow::Result<String> {
+    let editor = editor_command(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok());
+    edit_file(&mut Crossterm, &editor, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the calls it gets, optionally failing a suspend.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<&'static str>,
+        fail_suspend: bool,
+    }
+
+    impl TerminalControl for Recorder {
+        fn suspend(&mut self) -> io::Result<()> {
+            self.calls.push("suspend");
+            if self.fail_suspend {
+                return Err(io::Error::other("not a terminal"));
+            }
+            Ok(())
+        }
+
+        fn resume(&mut self) -> io::Result<()> {
+            self.calls.push("resume");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn the_editor_runs_between_suspend_and_resume() {
+        let mut terminal = Recorder::default();
+        let ran = std::cell::Cell::new(false);
+        let result = with_terminal_suspended(&mut terminal, || {
+            ran.set(true);
+            42
+        })
+        .unwrap();
+        assert_eq!(result, 42);
+        assert!(ran.get());
+        assert_eq!(terminal.calls, ["suspend", "resume"]);
+    }
+
+    #[test]
+    fn a_failed_suspend_is_rolled_back_without_running_the_editor() {
+        let mut terminal = Recorder {
+            fail_suspend: true,
+            ..Recorder::default()
+        };
+        let mut ran = false;
+        let result = with_terminal_suspended(&mut terminal, || ran = true);
+        assert!(result.is_err());
+        assert!(!ran);
+        assert_eq!(terminal.calls, ["suspend", "resume"]);
+    }
+
+    #[test]
+    fn the_terminal_is_resumed_when_the_editor_code_panics() {
+        let mut terminal = Recorder::default();
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_terminal_suspended(&mut terminal, || panic!("editor crashed"))
+        }));
+        assert!(outcome.is_err());
+        assert_eq!(terminal.calls, ["suspend", "resume"]);
+    }
+
+    #[test]
+    fn editor_command_prefers_visual_then_editor() {
+        assert_eq!(
+            editor_command(Some("nvim".into()), Some("nano".into())),
+            "nvim"
+        );
+        assert_eq!(
+            editor_command(None, Some("code --wait".into())),
+            "code --wait"
+        );
+        assert_eq!(
+            editor_command(Some("  ".into()), Some("nano".into())),
+            "nano"
+        );
+        assert_eq!(editor_command(None, None), "vi");
+    }
+
+    #[test]
+    fn paths_resolve_inside_the_workspace_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::write(workspace.join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("secret"), "").unwrap();
+        let root = workspace.canonicalize().unwrap();
+
+        let resolved = resolve_in_workspace(&workspace, "src/../src/main.rs").unwrap();
+        assert_eq!(resolved, root.join("src/main.rs"));
+        // New files are fine, in a directory that exists.
+        assert_eq!(
+            resolve_in_workspace(&workspace, "src/new.rs").unwrap(),
+            root.join("src/new.rs")
+        );
+        assert!(resolve_in_workspace(&workspace, "missing/new.rs").is_err());
+
+        for outside in ["../secret", "/etc/passwd", "src/../../secret", ".."] {
+            assert!(
+                resolve_in_workspace(&workspace, outside).is_err(),
+                "{} should be refused",
+                outside
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_workspace_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        std::fs::write(dir.path().join("secret"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret"), workspace.join("link")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("gone"), workspace.join("dangling")).unwrap();
+
+        let err = resolve_in_workspace(&workspace, "link").unwrap_err();
+        assert!(err.to_string().contains("outside the workspace"));
+        assert!(resolve_in_workspace(&workspace, "dangling").is_err());
+    }
+
+    #[test]
+    fn edit_file_returns_what_the_editor_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "draft\n").unwrap();
+        let mut terminal = Recorder::default();
+        // A stand-in editor that appends a line to the file it's given.
+        let contents = edit_file(&mut terminal, "sh -c 'echo edited >> \"$0\"'", &path).unwrap();
+        assert_eq!(contents, "draft\nedited\n");
+        assert_eq!(terminal.calls, ["suspend", "resume"]);
+    }
+
+    #[test]
+    fn edit_file_fails_when_the_editor_does() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "draft\n").unwrap();
+        let mut terminal = Recorder::default();
+        let err = edit_file(&mut terminal, "false", &path).unwrap_err();
+        assert!(err.to_string().contains("exited with"));
+        // The terminal comes back either way.
+        assert_eq!(terminal.calls, ["suspend", "resume"]);
+    }
+}
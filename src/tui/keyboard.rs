@@ -0,0 +1,51 @@
+// ABOUTME: Keyboard enhancement — asks the terminal to report Ctrl+Enter and Shift+Enter as their own keys.
+// ABOUTME: Most terminals send both as plain Enter otherwise, which `[tui] enter_submits = false` can't work with.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::event::{
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{execute, terminal};
+
+/// Whether the flags were pushed and not yet popped.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the terminal speaks the kitty keyboard protocol. It is asked and
+/// answers on stdin, so call this before the TUI starts reading keys.
+pub fn supported() -> bool {
+    terminal::supports_keyboard_enhancement().unwrap_or(false)
+}
+
+/// Have the terminal disambiguate modified keys. The flags belong to the
+/// screen they were pushed on, so call this once the TUI is on its own.
+pub fn enable() {
+    if push().is_ok() {
+        ENABLED.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Undo [`enable`]; nothing when it never took.
+pub fn disable() {
+    if ENABLED.swap(false, Ordering::SeqCst) {
+        let _ = pop();
+    }
+}
+
+/// Whether [`enable`] is in effect, so handing the terminal to another
+/// program can take the flags off and put them back after.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+pub(crate) fn push() -> io::Result<()> {
+    execute!(
+        io::stdout(),
+        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+    )
+}
+
+pub(crate) fn pop() -> io::Result<()> {
+    execute!(io::stdout(), PopKeyboardEnhancementFlags)
+}
@@ -0,0 +1,344 @@
+// ABOUTME: Vim-style keymap — insert, normal, and visual modes layered over the input's usual keys.
+// ABOUTME: Turns keys into actions for the TUI; with the default keymap every key passes straight through.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::Keymap;
+
+/// Where vim-style input is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimMode {
+    /// Keys type into the input as usual.
+    Insert,
+    /// Keys move around the chat and act on the input.
+    Normal,
+    /// j/k extend a selection of chat messages for y to copy.
+    Visual,
+}
+
+impl VimMode {
+    /// The status bar label.
+    pub fn label(self) -> &'static str {
+        match self {
+            VimMode::Insert => "INSERT",
+            VimMode::Normal => "NORMAL",
+            VimMode::Visual => "VISUAL",
+        }
+    }
+}
+
+/// What a key asks the TUI to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Not a keymap key: handle it as usual.
+    Pass,
+    /// Used up by the keymap: a mode change, half of "gg" or "dd", or a key
+    /// that does nothing in this mode.
+    Consumed,
+    ScrollUp,
+    ScrollDown,
+    /// gg: the first line of the chat.
+    Top,
+    /// G: the latest line, following new content again.
+    Bottom,
+    /// a: back to typing, one character further on.
+    Append,
+    /// dd: empty the input.
+    ClearInput,
+    /// v: start selecting at the latest message.
+    StartSelection,
+    SelectUp,
+    SelectDown,
+    /// y: copy the selection.
+    Yank,
+    /// A `:` command, without the colon.
+    Command(String),
+}
+
+/// The keymap and, for vim, the mode and any half-typed command.
+#[derive(Debug, Clone)]
+pub struct KeymapState {
+    keymap: Keymap,
+    mode: VimMode,
+    /// The first key of a two-key command ("gg", "dd").
+    pending: Option<char>,
+    /// A `:` command being typed.
+    command: Option<String>,
+}
+
+impl KeymapState {
+    /// Vim starts in insert mode, ready to type.
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            mode: VimMode::Insert,
+            pending: None,
+            command: None,
+        }
+    }
+
+    /// The current mode; None with the default keymap.
+    pub fn mode(&self) -> Option<VimMode> {
+        (self.keymap == Keymap::Vim).then_some(self.mode)
+    }
+
+    /// What the status bar shows: the mode, or the `:` command being typed.
+    pub fn status(&self) -> Option<String> {
+        match &self.command {
+            Some(command) => Some(format!(":{}", command)),
+            None => self.mode().map(|mode| mode.label().to_string()),
+        }
+    }
+
+    /// Leave visual mode, e.g. when there's nothing to select.
+    pub fn end_selection(&mut self) {
+        if self.mode == VimMode::Visual {
+            self.mode = VimMode::Normal;
+        }
+    }
+
+    /// Act on `key`. `popup_open` is whether the completion popup is up, so
+    /// Esc closes it before it leaves insert mode. Keys with Ctrl or Alt
+    /// always pass through.
+    pub fn handle(&mut self, key: &KeyEvent, popup_open: bool) -> KeyAction {
+        if self.keymap == Keymap::Default
+            || key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return KeyAction::Pass;
+        }
+        if self.command.is_some() {
+            return self.command_key(key);
+        }
+        match self.mode {
+            VimMode::Insert => match key.code {
+                KeyCode::Esc if !popup_open => {
+                    self.mode = VimMode::Normal;
+                    KeyAction::Consumed
+                }
+                _ => KeyAction::Pass,
+            },
+            VimMode::Normal => self.normal_key(key),
+            VimMode::Visual => self.visual_key(key),
+        }
+    }
+
+    fn normal_key(&mut self, key: &KeyEvent) -> KeyAction {
+        let pending = self.pending.take();
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => KeyAction::ScrollDown,
+            KeyCode::Char('k') | KeyCode::Up => KeyAction::ScrollUp,
+            KeyCode::Char('g') if pending == Some('g') => KeyAction::Top,
+            KeyCode::Char('d') if pending == Some('d') => KeyAction::ClearInput,
+            KeyCode::Char(c @ ('g' | 'd')) => {
+                self.pending = Some(c);
+                KeyAction::Consumed
+            }
+            KeyCode::Char('G') => KeyAction::Bottom,
+            KeyCode::Char('i') => {
+                self.mode = VimMode::Insert;
+                KeyAction::Consumed
+            }
+            KeyCode::Char('a') => {
+                self.mode = VimMode::Insert;
+                KeyAction::Append
+            }
+            KeyCode::Char('v') => {
+                self.mode = VimMode::Visual;
+                KeyAction::StartSelection
+            }
+            KeyCode::Char(':') => {
+                self.command = Some(String::new());
+                KeyAction::Consumed
+            }
+            // Nothing types or sends in normal mode, and Esc doesn't quit.
+            KeyCode::Char(_)
+            | KeyCode::Esc
+            | KeyCode::Enter
+            | KeyCode::Backspace
+            | KeyCode::Delete => KeyAction::Consumed,
+            _ => KeyAction::Pass,
+        }
+    }
+
+    fn visual_key(&mut self, key: &KeyEvent) -> KeyAction {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => KeyAction::SelectDown,
+            KeyCode::Char('k') | KeyCode::Up => KeyAction::SelectUp,
+            KeyCode::Char('y') => {
+                self.mode = VimMode::Normal;
+                KeyAction::Yank
+            }
+            KeyCode::Char('v') | KeyCode::Esc => {
+                self.mode = VimMode::Normal;
+                KeyAction::Consumed
+            }
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Backspace | KeyCode::Delete => {
+                KeyAction::Consumed
+            }
+            _ => KeyAction::Pass,
+        }
+    }
+
+    fn command_key(&mut self, key: &KeyEvent) -> KeyAction {
+        let Some(command) = self.command.as_mut() else {
+            return KeyAction::Pass;
+        };
+        match key.code {
+            KeyCode::Enter => KeyAction::Command(self.command.take().unwrap_or_default()),
+            KeyCode::Esc => {
+                self.command = None;
+                KeyAction::Consumed
+            }
+            KeyCode::Backspace => {
+                if command.pop().is_none() {
+                    self.command = None;
+                }
+                KeyAction::Consumed
+            }
+            KeyCode::Char(c) => {
+                command.push(c);
+                KeyAction::Consumed
+            }
+            _ => KeyAction::Consumed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(state: &mut KeymapState, keys: &str) -> Vec<KeyAction> {
+        keys.chars()
+            .map(|c| state.handle(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), false))
+            .collect()
+    }
+
+    fn esc(state: &mut KeymapState) -> KeyAction {
+        state.handle(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE), false)
+    }
+
+    fn normal() -> KeymapState {
+        let mut state = KeymapState::new(Keymap::Vim);
+        esc(&mut state);
+        state
+    }
+
+    #[test]
+    fn the_default_keymap_passes_everything() {
+        let mut state = KeymapState::new(Keymap::Default);
+        assert_eq!(state.mode(), None);
+        assert_eq!(state.status(), None);
+        assert_eq!(esc(&mut state), KeyAction::Pass);
+        assert_eq!(press(&mut state, "jk"), [KeyAction::Pass, KeyAction::Pass]);
+    }
+
+    #[test]
+    fn esc_and_i_a_v_switch_modes() {
+        let mut state = KeymapState::new(Keymap::Vim);
+        assert_eq!(state.mode(), Some(VimMode::Insert));
+        assert_eq!(press(&mut state, "j"), [KeyAction::Pass]);
+        assert_eq!(
+            state.handle(&KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE), true),
+            KeyAction::Pass,
+            "Esc closes the completion popup first"
+        );
+
+        assert_eq!(esc(&mut state), KeyAction::Consumed);
+        assert_eq!(state.status().as_deref(), Some("NORMAL"));
+        assert_eq!(esc(&mut state), KeyAction::Consumed, "Esc never quits");
+        assert_eq!(press(&mut state, "i"), [KeyAction::Consumed]);
+        assert_eq!(state.mode(), Some(VimMode::Insert));
+
+        esc(&mut state);
+        assert_eq!(press(&mut state, "a"), [KeyAction::Append]);
+        assert_eq!(state.mode(), Some(VimMode::Insert));
+
+        esc(&mut state);
+        assert_eq!(press(&mut state, "v"), [KeyAction::StartSelection]);
+        assert_eq!(state.mode(), Some(VimMode::Visual));
+        assert_eq!(esc(&mut state), KeyAction::Consumed);
+        assert_eq!(state.mode(), Some(VimMode::Normal));
+    }
+
+    #[test]
+    fn normal_mode_motions() {
+        let mut state = normal();
+        assert_eq!(
+            press(&mut state, "jk"),
+            [KeyAction::ScrollDown, KeyAction::ScrollUp]
+        );
+        assert_eq!(
+            press(&mut state, "gg"),
+            [KeyAction::Consumed, KeyAction::Top]
+        );
+        assert_eq!(press(&mut state, "G"), [KeyAction::Bottom]);
+        assert_eq!(
+            press(&mut state, "dd"),
+            [KeyAction::Consumed, KeyAction::ClearInput]
+        );
+        assert_eq!(
+            press(&mut state, "gj"),
+            [KeyAction::Consumed, KeyAction::ScrollDown]
+        );
+        assert_eq!(press(&mut state, "x"), [KeyAction::Consumed]);
+
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(state.handle(&ctrl_c, false), KeyAction::Pass);
+        let page_up = KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE);
+        assert_eq!(state.handle(&page_up, false), KeyAction::Pass);
+    }
+
+    #[test]
+    fn normal_mode_arrows_scroll_and_enter_sends_nothing() {
+        let mut state = normal();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(state.handle(&key(KeyCode::Up), false), KeyAction::ScrollUp);
+        assert_eq!(
+            state.handle(&key(KeyCode::Down), false),
+            KeyAction::ScrollDown
+        );
+        assert_eq!(
+            state.handle(&key(KeyCode::Enter), false),
+            KeyAction::Consumed
+        );
+        assert_eq!(state.mode(), Some(VimMode::Normal));
+    }
+
+    #[test]
+    fn visual_mode_selects_and_yanks() {
+        let mut state = normal();
+        press(&mut state, "v");
+        assert_eq!(
+            press(&mut state, "kj"),
+            [KeyAction::SelectUp, KeyAction::SelectDown]
+        );
+        assert_eq!(press(&mut state, "y"), [KeyAction::Yank]);
+        assert_eq!(state.mode(), Some(VimMode::Normal));
+
+        press(&mut state, "v");
+        state.end_selection();
+        assert_eq!(state.mode(), Some(VimMode::Normal));
+    }
+
+    #[test]
+    fn colon_commands_are_typed_in_the_status_bar() {
+        let mut state = normal();
+        press(&mut state, ":qx");
+        assert_eq!(state.status().as_deref(), Some(":qx"));
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        state.handle(&backspace, false);
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(
+            state.handle(&enter, false),
+            KeyAction::Command("q".to_string())
+        );
+        assert_eq!(state.status().as_deref(), Some("NORMAL"));
+
+        press(&mut state, ":w");
+        assert_eq!(esc(&mut state), KeyAction::Consumed);
+        assert_eq!(state.status().as_deref(), Some("NORMAL"));
+    }
+}
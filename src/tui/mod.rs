@@ -1,7 +1,19 @@
 // ABOUTME: TUI module — boba (Elm Architecture) full-screen interface for soloclaw.
 // ABOUTME: Chat display, input handling, status bar, and inline approval prompts.
 
+pub mod allowlist_editor;
+pub mod clipboard;
+pub mod commands;
+pub mod consent;
+pub mod editing;
+pub mod editor;
+pub mod keyboard;
+pub mod keymap;
 pub mod model;
+pub mod navigation;
+pub mod onboarding;
+pub mod paste;
+pub mod snippets;
 pub mod state;
 pub mod subscriptions;
 pub mod widgets;
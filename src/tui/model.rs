@@ -1,35 +1,126 @@
 // ABOUTME: Boba Model implementation — ClawApp is the Elm Architecture TUI.
 // ABOUTME: All TUI state, message handling, and rendering lives here.
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
 
 use boba::widgets::text_area;
 use boba::widgets::text_area::TextArea;
 use boba::widgets::viewport::{self, Viewport};
 use boba::{subscribe, terminal_events, Command, Component, Model, Subscription, TerminalEvent};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
-use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::Frame;
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
-use ratatui::Frame;
-use tokio::sync::{mpsc, Mutex};
-
-use crate::tui::widgets::approval::approval_line;
-use crate::tui::widgets::chat::render_chat_lines;
-use crate::tui::widgets::question::{multichoice_lines, question_lines};
-use crate::tui::widgets::status::{StatusBarParams, status_line};
-
-use crate::approval::ApprovalDecision;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+use tokio::sync::{Mutex, mpsc, oneshot};
+
+use crate::tui::widgets::approval::{APPROVAL_OPTIONS, approval_line, consent_lines, scope_lines};
+use crate::tui::widgets::chat::{ChatOptions, is_thinking, render_chat};
+use crate::tui::widgets::completion::{CompletionItem, completion_lines, popup_area};
+use crate::tui::widgets::draft::{draft_cost, draft_title, draft_tokens};
+use crate::tui::widgets::header::header_line;
+use crate::tui::widgets::paste::paste_prompt_lines;
+use crate::tui::widgets::question::{confirm_lines, multichoice_lines, question_lines};
+use crate::tui::widgets::quit::quit_confirm_lines;
+use crate::tui::widgets::status::{StatusBarParams, forecast_turns_until, status_line};
+
+use crate::agent::compaction::SummaryDecision;
+use crate::agent::cost::{ModelPricing, TokenUsage, pricing_for_model};
+use crate::agent::forget::REDACTED;
+use crate::agent::shutdown::ShutdownFlag;
+use crate::approval::{ApprovalDecision, ApprovalEngine, ApprovalReason, SecurityLevel};
+use crate::config::{
+    Effort, PrivacyConfig, SnippetConfig, ToolVerbosity, TuiConfig, save_approval_security,
+};
+use crate::exit::SessionTally;
+use crate::prompt::{SkillEntry, SkillStatus, format_skill_list};
+use crate::session::ChangeJournal;
+use crate::session::changes::normalize_path;
+use crate::tools::ask_user::{CONFIRM_NO, CONFIRM_YES, QuestionStyle};
+use crate::tools::catalog::{ToolInfo, format_tool_list};
+use crate::tui::allowlist_editor::{AllowlistEditor, EditorAction};
+use crate::tui::clipboard;
+use crate::tui::commands::{self, CommandRegistry, LocalAction, MAX_COMPLETIONS, SlashAction};
+use crate::tui::consent::{ConsentAnswer, Consents, Feature, Gate};
+use crate::tui::editing::{self, EditCommand};
+use crate::tui::editor;
+use crate::tui::keyboard;
+use crate::tui::keymap::{KeyAction, KeymapState, VimMode};
+use crate::tui::navigation::{self, Jump, NEW_BELOW_NOTICE, TOP_NOTICE, TOP_NOTICE_DURATION};
+use crate::tui::onboarding::{self, Onboarding, OnboardingStep, SAVE_OPTIONS, WALKTHROUGH};
+use crate::tui::paste::{self, PasteAction};
+use crate::tui::snippets;
 use crate::tui::state::{
-    AgentEvent, ChatMessage, ChatMessageKind, PendingApproval, PendingQuestion, ToolCallStatus,
-    UserEvent,
+    AgentEvent, ChatMessage, ChatMessageKind, PendingApproval, PendingConsent, PendingPaste,
+    PendingQuestion, PendingQuit, PendingSummaryReview, ToolCallStatus, UserEvent, conversation,
 };
-use crate::tui::subscriptions::AgentEventSource;
+use crate::tui::subscriptions::{AgentEventSource, CursorBlink};
 
 const MOUSE_SCROLL_STEP: u16 = 3;
 
+/// Lines kept on screen from the previous page when paging through chat.
+const PAGE_OVERLAP: u16 = 1;
+
+/// Page size used before the first frame has been rendered.
+const DEFAULT_PAGE_SIZE: u16 = 10;
+
+/// Maximum height the input area can grow to (in terminal rows).
+const MAX_INPUT_HEIGHT: u16 = 8;
+
+/// Number of per-turn context samples kept for the compaction forecast.
+const CONTEXT_HISTORY_LEN: usize = 6;
+
+/// Smallest terminal the normal layout is drawn in: header, three rows of
+/// chat, a three-row input, and the status bar.
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 8;
+
+/// Wait before repainting after the editor exits: long enough for the blank
+/// frame to be drawn at 30 fps.
+const REPAINT_DELAY: Duration = Duration::from_millis(100);
+
+/// Answer sent when the user dismisses a question with Esc.
+const DECLINED_ANSWER: &str = "[User declined to answer]";
+
+/// Choices offered when reviewing a compaction summary.
+const SUMMARY_REVIEW_OPTIONS: [&str; 3] = ["accept", "edit", "skip compaction this time"];
+
+/// The line shown while compacting; progress reports extend it in place.
+const COMPACTING_MESSAGE: &str = "\u{1f5dc}\u{fe0f} Compacting conversation...";
+
+/// A system message repeated back to back, shown once with a count.
+struct SystemRun {
+    /// Position of the collapsed message in `messages`.
+    index: usize,
+    /// The repeated text, without the count.
+    text: String,
+    count: usize,
+}
+
+impl SystemRun {
+    /// What the collapsed message shows.
+    fn display(&self) -> String {
+        if self.count > 1 {
+            format!("{} (\u{d7}{})", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// A file open in the user's editor. Until it closes, terminal events are
+/// unsubscribed, so the editor gets every key, and nothing is drawn.
+struct EditSession {
+    /// As given, relative to the workspace.
+    path: String,
+    /// Where the agent loop waits for the result; None for /edit.
+    responder: Option<oneshot::Sender<Result<String, String>>>,
+}
+
 /// Messages that drive the ClawApp update cycle.
 pub enum Msg {
     Key(KeyEvent),
@@ -38,6 +129,17 @@ pub enum Msg {
     Agent(AgentEvent),
     Input(text_area::Message),
     MessageSent,
+    /// The editor exited: the file as saved, or why editing failed.
+    EditorClosed(Result<String, String>),
+    /// Draw the whole screen again after the editor gave the terminal back.
+    Repaint,
+    /// Blink the cursor at the end of a streaming reply.
+    CursorBlink,
+    /// The terminal reports Ctrl+Enter as its own key now; the initial
+    /// prompt waits for this.
+    KeyboardEnhanced,
+    /// The clipboard a `{clipboard}` snippet waited for, if it could be read.
+    SnippetClipboard(SnippetConfig, Option<String>),
 }
 
 /// Initialization data passed to ClawApp::init.
@@ -47,29 +149,186 @@ pub struct Flags {
     pub model_name: String,
     pub tool_count: usize,
     pub context_window: u64,
+    /// Context size at which auto-compaction triggers; None when disabled.
+    pub compaction_limit: Option<u64>,
     pub workspace_dir: String,
     pub replay_messages: Vec<ChatMessage>,
     pub startup_message: String,
+    pub tui_config: TuiConfig,
+    pub tools: Vec<ToolInfo>,
+    pub skills: Vec<SkillEntry>,
+    pub snippets: Vec<SnippetConfig>,
+    /// Approval engine backing the /approvals editor.
+    pub engine: Option<Arc<ApprovalEngine>>,
+    /// Sent as the first message once the TUI is up (`--prompt`).
+    pub initial_prompt: Option<String>,
+    /// The terminal can report Ctrl+Enter distinctly, asked before the TUI
+    /// started; with `enter_submits` off it's told to.
+    pub keyboard_enhancement: bool,
+    /// The first-run walkthrough, when this is the first run.
+    pub onboarding: Option<Onboarding>,
+    /// Start in observe mode (`--observe`).
+    pub observe: bool,
+    /// Which terminal features may run without asking.
+    pub privacy: PrivacyConfig,
+    /// Earlier answers to the consent prompt for those features.
+    pub consents: Consents,
+    /// The agent's file writes this session, for /changes and /revert.
+    pub changes: Option<Arc<std::sync::Mutex<ChangeJournal>>>,
+    /// Shared with the agent loop; set as soon as the user quits.
+    pub shutdown: ShutdownFlag,
+}
+
+/// What a Ctrl+C press should do, given the current state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtrlCAction {
+    /// Second press inside the double-tap window.
+    Quit,
+    /// Abort the turn that is currently streaming.
+    CancelTurn,
+    /// Discard the draft in the input area.
+    ClearInput,
+    /// Nothing to cancel; just arm the double-tap timer.
+    Prime,
+}
+
+/// Decide what a Ctrl+C press at `now` does. A second press strictly within
+/// `window` of the previous one quits; otherwise it cancels the running turn,
+/// or clears the input, in that order of preference.
+pub fn ctrl_c_action(
+    last: Option<Instant>,
+    now: Instant,
+    window: Duration,
+    streaming: bool,
+    has_input: bool,
+) -> CtrlCAction {
+    if let Some(prev) = last
+        && now.duration_since(prev) < window
+    {
+        return CtrlCAction::Quit;
+    }
+    if streaming {
+        CtrlCAction::CancelTurn
+    } else if has_input {
+        CtrlCAction::ClearInput
+    } else {
+        CtrlCAction::Prime
+    }
 }
 
 /// The top-level TUI application state, driven by the boba runtime.
 pub struct ClawApp {
     pub input: TextArea,
     pub messages: Vec<ChatMessage>,
+    /// The last system message pushed, for collapsing identical repeats.
+    system_run: Option<SystemRun>,
     pub chat_viewport: Viewport,
+    /// Chat area height from the last rendered frame, used for paging.
+    chat_height: AtomicU16,
+    /// Rendered lines of chat content, for clamping jumps to the bottom.
+    chat_lines: usize,
+    /// First rendered line of each message, for jumping between turns.
+    message_lines: Vec<usize>,
+    /// When scrolling up was last refused at the top of history.
+    top_notice: Option<Instant>,
+    /// Content arrived below while the chat was scrolled up.
+    new_below: bool,
     pub streaming: bool,
     pub queued_message: Option<String>,
     pub pending_approval: Option<PendingApproval>,
     pub pending_question: Option<PendingQuestion>,
+    /// Quit confirmation, shown on double Ctrl+C while a turn is running.
+    pub pending_quit: Option<PendingQuit>,
+    /// A compaction summary awaiting review; its prompt is the pending question.
+    pub summary_review: Option<PendingSummaryReview>,
+    /// The first-run walkthrough, while its questions are being answered.
+    onboarding: Option<Onboarding>,
+    /// A file /revert is waiting on confirmation to restore.
+    pending_revert: Option<PathBuf>,
+    /// Tool calls are refused rather than run; toggled with /observe.
+    pub observe: bool,
+    /// An oversized paste waiting for the user to choose how to include it.
+    pub pending_paste: Option<PendingPaste>,
+    /// `[privacy]` flags for clipboard writes and notifications.
+    privacy: PrivacyConfig,
+    consents: Consents,
+    /// A gated feature waiting for the user to allow it.
+    pub pending_consent: Option<PendingConsent>,
+    /// Quit as soon as the running turn finishes.
+    pub quit_after_turn: bool,
+    /// The /approvals editor, shown in place of the chat while open.
+    pub allowlist_editor: Option<AllowlistEditor>,
+    engine: Option<Arc<ApprovalEngine>>,
+    changes: Option<Arc<std::sync::Mutex<ChangeJournal>>>,
+    shutdown: ShutdownFlag,
     pub model_name: String,
     pub tool_count: usize,
     pub total_tokens: u64,
+    /// Turns and tool call outcomes, for the exit summary.
+    pub tally: SessionTally,
+    /// Session token totals by kind, for the cost estimate.
+    pub usage: TokenUsage,
+    pricing: Option<ModelPricing>,
     pub context_window: u64,
     pub context_used: u64,
+    /// Context size at the end of each recent turn, oldest first.
+    pub context_history: Vec<u64>,
+    pub compaction_limit: Option<u64>,
     pub session_start: Instant,
     pub workspace_dir: String,
+    /// Snapshot of registered tools, shown by /tools.
+    pub tools: Vec<ToolInfo>,
+    /// Discovered skills and whether each was loaded, shown by /skills.
+    pub skills: Vec<SkillEntry>,
+    /// Canned prompts bound to function keys.
+    pub snippets: Vec<SnippetConfig>,
     /// Timestamp of the last Ctrl+C press for double-tap quit detection.
     last_ctrl_c: Option<Instant>,
+    /// Double-tap window for Ctrl+C to quit.
+    double_ctrl_c_window: Duration,
+    /// Pastes over this many bytes prompt instead of being inserted.
+    max_paste_bytes: usize,
+    /// Lines kept when an oversized paste is inserted as its first lines.
+    paste_insert_lines: usize,
+    /// Keep what auto-approved each tool call for display.
+    show_approval_reason: bool,
+    /// Draw the header and status bar without emoji (`[tui] emoji = false`).
+    ascii: bool,
+    /// Enter submits and Shift/Alt+Enter insert a newline; off, Enter
+    /// inserts and Ctrl+Enter submits (`[tui] enter_submits`). Ctrl+S
+    /// submits either way.
+    enter_submits: bool,
+    /// `--prompt`, until it's sent.
+    initial_prompt: Option<String>,
+    /// How the chat transcript is rendered.
+    chat_options: ChatOptions,
+    /// What `/quiet` and Ctrl+O switch tool output to from full: the
+    /// configured verbosity, or compact when that's full.
+    quiet_verbosity: ToolVerbosity,
+    /// Whether the streaming cursor is in the "on" half of its blink.
+    cursor_on: bool,
+    /// The slash commands the input understands.
+    commands: Arc<CommandRegistry>,
+    /// The input as it was when Esc closed the completion popup; it stays
+    /// closed until the input changes.
+    dismissed_completion: Option<String>,
+    /// Text removed by the last Ctrl+W / Ctrl+U / Ctrl+K, inserted by Ctrl+Y.
+    kill_buffer: String,
+    /// Set after Enter on an empty free-text answer; a second Enter submits it.
+    confirm_empty_answer: bool,
+    /// `[tui] keymap` and, for vim, the current mode.
+    keymap: KeymapState,
+    /// Messages selected in vim's visual mode: where it started, and the
+    /// end j/k move.
+    selection: Option<(usize, usize)>,
+    /// Runs the user's editor on a file and returns what it holds afterwards.
+    edit_file: fn(&Path) -> anyhow::Result<String>,
+    editing: Option<EditSession>,
+    /// Puts text on the system clipboard.
+    copy_text: fn(&str) -> std::io::Result<()>,
+    /// Render one empty frame, so the frame after it redraws every cell of
+    /// a screen the editor cleared.
+    blank_frame: bool,
     user_tx: mpsc::Sender<UserEvent>,
     agent_rx: Arc<Mutex<Option<mpsc::Receiver<AgentEvent>>>>,
 }
@@ -85,19 +344,76 @@ impl Model for ClawApp {
         let mut app = ClawApp {
             input,
             messages: Vec::new(),
+            system_run: None,
             chat_viewport: Viewport::new(""),
+            chat_height: AtomicU16::new(0),
+            chat_lines: 0,
+            message_lines: Vec::new(),
+            top_notice: None,
+            new_below: false,
             streaming: false,
             queued_message: None,
             pending_approval: None,
             pending_question: None,
+            pending_quit: None,
+            pending_paste: None,
+            summary_review: None,
+            onboarding: None,
+            pending_revert: None,
+            observe: flags.observe,
+            privacy: flags.privacy,
+            consents: flags.consents,
+            pending_consent: None,
+            quit_after_turn: false,
+            allowlist_editor: None,
+            engine: flags.engine,
+            changes: flags.changes,
+            shutdown: flags.shutdown,
+            pricing: pricing_for_model(&flags.model_name),
             model_name: flags.model_name,
             tool_count: flags.tool_count,
             total_tokens: 0,
+            tally: SessionTally::default(),
+            usage: TokenUsage::default(),
             context_window: flags.context_window,
             context_used: 0,
+            context_history: Vec::new(),
+            compaction_limit: flags.compaction_limit,
             session_start: Instant::now(),
             workspace_dir: flags.workspace_dir,
+            tools: flags.tools,
+            skills: flags.skills,
+            snippets: flags.snippets,
             last_ctrl_c: None,
+            double_ctrl_c_window: Duration::from_millis(flags.tui_config.double_ctrl_c_ms),
+            max_paste_bytes: flags.tui_config.max_paste_bytes,
+            paste_insert_lines: flags.tui_config.paste_insert_lines,
+            show_approval_reason: flags.tui_config.show_approval_reason,
+            // Accessible mode spells status out in text rather than emoji.
+            ascii: !flags.tui_config.emoji || flags.tui_config.accessibility,
+            enter_submits: flags.tui_config.enter_submits,
+            initial_prompt: flags.initial_prompt,
+            chat_options: ChatOptions {
+                tab_width: flags.tui_config.tab_width,
+                cursor: false,
+                accessible: flags.tui_config.accessibility,
+                tool_verbosity: flags.tui_config.tool_verbosity,
+            },
+            quiet_verbosity: match flags.tui_config.tool_verbosity {
+                ToolVerbosity::Full => ToolVerbosity::Compact,
+                quiet => quiet,
+            },
+            cursor_on: true,
+            commands: Arc::new(CommandRegistry::default()),
+            dismissed_completion: None,
+            kill_buffer: String::new(),
+            confirm_empty_answer: false,
+            keymap: KeymapState::new(flags.tui_config.keymap),
+            selection: None,
+            edit_file: editor::open_in_terminal,
+            editing: None,
+            copy_text: clipboard::write_osc52,
+            blank_frame: false,
             user_tx: flags.user_tx,
             agent_rx: Arc::new(Mutex::new(Some(flags.agent_rx))),
         };
@@ -117,12 +433,29 @@ impl Model for ClawApp {
             );
         }
 
+        if let Some(onboarding) = flags.onboarding {
+            for line in WALKTHROUGH {
+                app.push_message(ChatMessageKind::System, line.to_string());
+            }
+            app.pending_question = Some(onboarding.question());
+            app.onboarding = Some(onboarding);
+        }
+
         app.rebuild_chat_content();
 
-        (app, Command::none())
+        let cmd = if !app.enter_submits && flags.keyboard_enhancement {
+            Command::perform(async { keyboard::enable() }, |_| Msg::KeyboardEnhanced)
+        } else {
+            app.send_initial_prompt()
+        };
+
+        (app, cmd)
     }
 
     fn update(&mut self, msg: Msg) -> Command<Msg> {
+        if let Msg::Agent(event) = &msg {
+            self.tally.record(event);
+        }
         match msg {
             Msg::Agent(event) => match event {
                 AgentEvent::TextDelta(text) => {
@@ -131,101 +464,315 @@ impl Model for ClawApp {
                 }
                 AgentEvent::TextDone => Command::none(),
                 AgentEvent::ToolCallStarted {
+                    tool_call_id,
                     tool_name,
                     params_summary,
                 } => {
                     let content = format!("{}({})", tool_name, params_summary);
                     self.push_message(
                         ChatMessageKind::ToolCall {
+                            tool_call_id,
                             tool_name,
                             status: ToolCallStatus::Pending,
+                            approval_reason: None,
                         },
                         content,
                     );
                     Command::none()
                 }
-                AgentEvent::ToolCallApproved { tool_name } => {
-                    self.update_tool_status(&tool_name, ToolCallStatus::Allowed);
+                AgentEvent::ToolCallApproved {
+                    tool_call_id,
+                    reason,
+                    ..
+                } => {
+                    self.update_tool_status(&tool_call_id, ToolCallStatus::Allowed);
+                    if self.show_approval_reason {
+                        self.set_approval_reason(&tool_call_id, reason);
+                    }
                     Command::none()
                 }
                 AgentEvent::ToolCallNeedsApproval {
                     description,
                     pattern,
+                    similar,
+                    scopes,
                     tool_name,
                     responder,
+                    ..
                 } => {
                     self.pending_approval = Some(PendingApproval {
                         description,
                         pattern,
+                        similar,
+                        scopes,
+                        scope_selected: None,
                         tool_name,
                         selected: 0,
                         responder: Some(responder),
                     });
-                    self.chat_viewport.goto_bottom();
+                    self.scroll_to_latest();
                     Command::none()
                 }
                 AgentEvent::AskUser {
                     question,
                     tool_call_id,
                     options,
+                    style,
                     responder,
                 } => {
                     self.pending_question = Some(PendingQuestion {
                         question,
                         tool_call_id,
                         options,
+                        style,
                         selected: 0,
                         responder: Some(responder),
                     });
-                    self.chat_viewport.goto_bottom();
+                    self.scroll_to_latest();
                     Command::none()
                 }
-                AgentEvent::ToolCallDenied { tool_name, reason } => {
-                    self.update_tool_status(&tool_name, ToolCallStatus::Denied);
+                AgentEvent::OpenInEditor {
+                    path, responder, ..
+                } => self.open_in_editor(&path, Some(responder)),
+                AgentEvent::ToolCallDenied {
+                    tool_call_id,
+                    tool_name,
+                    reason,
+                } => {
+                    self.update_tool_status(&tool_call_id, ToolCallStatus::Denied);
                     self.push_message(
                         ChatMessageKind::System,
                         format!("Tool '{}' denied: {}", tool_name, reason),
                     );
                     Command::none()
                 }
+                AgentEvent::ApprovalExpired {
+                    tool_call_id,
+                    tool_name,
+                } => {
+                    // The agent loop already gave up waiting; any responder we
+                    // still hold is dead, so drop the prompt instead of letting
+                    // the user answer into the void.
+                    self.pending_approval = None;
+                    self.pending_question = None;
+                    self.update_tool_status(&tool_call_id, ToolCallStatus::TimedOut);
+                    self.push_message(
+                        ChatMessageKind::System,
+                        format!("\u{23f1}\u{fe0f} Approval for '{}' timed out", tool_name),
+                    );
+                    Command::none()
+                }
                 AgentEvent::ToolResult {
-                    tool_name: _,
+                    tool_call_id,
                     content,
                     is_error,
+                    ..
                 } => {
-                    self.push_message(ChatMessageKind::ToolResult { is_error }, content);
+                    self.push_tool_result(&tool_call_id, is_error, content);
                     Command::none()
                 }
                 AgentEvent::Usage {
                     input_tokens,
                     output_tokens,
+                    cache_read_tokens,
+                    cache_write_tokens,
                 } => {
-                    self.total_tokens += (input_tokens + output_tokens) as u64;
-                    self.context_used = input_tokens as u64;
+                    let usage = TokenUsage {
+                        input: input_tokens as u64,
+                        output: output_tokens as u64,
+                        cache_read: cache_read_tokens as u64,
+                        cache_write: cache_write_tokens as u64,
+                    };
+                    self.usage.add(usage);
+                    self.total_tokens += usage.total();
+                    // Cached prompt tokens still occupy the context window.
+                    self.context_used = usage.prompt_tokens();
                     Command::none()
                 }
                 AgentEvent::Error(msg) => {
+                    // Stopped first, so the error takes the place of a
+                    // "thinking…" line that never got a reply.
+                    self.streaming = false;
                     self.push_message(
                         ChatMessageKind::System,
                         format!("\u{26a0}\u{fe0f} Error: {}", msg),
                     );
-                    self.streaming = false;
                     Command::none()
                 }
-                AgentEvent::Done => {
+                AgentEvent::Reloaded { skills } => {
+                    let loaded = skills
+                        .iter()
+                        .filter(|s| {
+                            matches!(s.status, SkillStatus::Loaded | SkillStatus::Truncated)
+                        })
+                        .count();
+                    let skipped = skills.len() - loaded;
+                    self.skills = skills;
+                    self.push_message(
+                        ChatMessageKind::System,
+                        format!(
+                            "\u{1f504} Reloaded config, context files, and skills ({} loaded, {} skipped)",
+                            loaded, skipped
+                        ),
+                    );
+                    Command::none()
+                }
+                AgentEvent::McpConnected {
+                    server,
+                    tools,
+                    tool_count,
+                } => {
+                    self.tool_count = tool_count;
+                    let noun = if tools == 1 { "tool" } else { "tools" };
+                    let thinking = self.messages.last().is_some_and(is_thinking);
+                    self.push_message(
+                        ChatMessageKind::System,
+                        format!("\u{1f50c} MCP '{}' connected: {} {}", server, tools, noun),
+                    );
+                    if thinking && self.streaming {
+                        self.show_thinking();
+                    }
+                    Command::none()
+                }
+                AgentEvent::Forgotten { nth } => {
+                    // The agent counted typed messages the way the chat does.
+                    let target = self
+                        .messages
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .filter(|(_, m)| m.kind == ChatMessageKind::User && !m.display_only)
+                        .nth(nth.saturating_sub(1))
+                        .map(|(i, _)| i);
+                    if let Some(target) = target {
+                        self.messages[target].content = REDACTED.to_string();
+                        // The agent drops the pin on a forgotten message.
+                        self.messages[target].pinned = false;
+                        self.rebuild_chat_content();
+                    }
+                    Command::none()
+                }
+                AgentEvent::Pinned { nth, reply, pinned } => {
+                    // The agent counted typed messages and replies the way
+                    // the chat does.
+                    let target = self
+                        .messages
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .filter(|(_, m)| {
+                            !m.display_only
+                                && if reply {
+                                    m.kind == ChatMessageKind::Assistant && !is_thinking(m)
+                                } else {
+                                    m.kind == ChatMessageKind::User
+                                }
+                        })
+                        .nth(nth.saturating_sub(1))
+                        .map(|(i, _)| i);
+                    if let Some(target) = target {
+                        self.messages[target].pinned = pinned;
+                        self.rebuild_chat_content();
+                    }
+                    Command::none()
+                }
+                AgentEvent::Notice(msg) => {
+                    // A notice while waiting for the reply ("falling back
+                    // to ...") keeps the wait visible below it.
+                    let thinking = self.messages.last().is_some_and(is_thinking);
+                    self.push_message(ChatMessageKind::System, format!("\u{2139}\u{fe0f} {}", msg));
+                    if thinking && self.streaming {
+                        self.show_thinking();
+                    }
+                    Command::none()
+                }
+                AgentEvent::Done | AgentEvent::ResumeCompacted => {
+                    // Drop a placeholder the turn never filled, and the
+                    // cursor after the reply.
+                    let cursor_shown = self.reply_streaming();
                     self.streaming = false;
+                    if self.clear_thinking() || cursor_shown {
+                        self.rebuild_chat_content();
+                    }
+                    self.record_context_sample();
+                    // The agent loop saves the session right after Done and
+                    // only then reads the quit signal, so nothing is lost.
+                    if self.quit_after_turn {
+                        self.quit_after_turn = false;
+                        self.pending_quit = None;
+                        return self.quit();
+                    }
                     if let Some(queued) = self.queued_message.take() {
                         self.push_message(ChatMessageKind::User, queued.clone());
                         self.streaming = true;
+                        self.show_thinking();
                         return self.send_message(queued);
                     }
                     Command::none()
                 }
+                AgentEvent::TurnCancelled => {
+                    // Any prompt still on screen belongs to the aborted turn.
+                    self.pending_approval = None;
+                    self.pending_question = None;
+                    self.summary_review = None;
+                    // Hand a queued follow-up back to the user rather than
+                    // firing it off right after they asked to stop.
+                    if let Some(queued) = self.queued_message.take()
+                        && self.input.value().is_empty()
+                    {
+                        self.input.set_value(&queued);
+                    }
+                    self.push_message(
+                        ChatMessageKind::System,
+                        "\u{23f9}\u{fe0f} Turn cancelled".to_string(),
+                    );
+                    Command::none()
+                }
                 AgentEvent::CompactionStarted => {
+                    // Compaction of a resumed session starts before any turn,
+                    // so input has to be blocked here until ResumeCompacted.
+                    self.streaming = true;
+                    self.push_message(ChatMessageKind::System, COMPACTING_MESSAGE.to_string());
+                    Command::none()
+                }
+                AgentEvent::CompactionProgress { tokens } => {
+                    // Grow the count on the "Compacting" line rather than
+                    // adding a line per report.
+                    if let Some(last) = self.messages.last_mut()
+                        && last.kind == ChatMessageKind::System
+                        && last.content.starts_with(COMPACTING_MESSAGE)
+                    {
+                        last.content = format!(
+                            "{} \u{2248}{} tokens of summary so far",
+                            COMPACTING_MESSAGE, tokens
+                        );
+                        self.rebuild_chat_content();
+                    }
+                    Command::none()
+                }
+                AgentEvent::CompactionSummaryReady { summary, responder } => {
                     self.push_message(
                         ChatMessageKind::System,
-                        "\u{1f5dc}\u{fe0f} Compacting conversation...".to_string(),
+                        format!("\u{1f4dd} Compaction summary:\n{}", summary),
                     );
+                    self.pending_question = Some(PendingQuestion {
+                        question: "Replace the conversation history with the summary above?"
+                            .to_string(),
+                        tool_call_id: String::new(),
+                        options: SUMMARY_REVIEW_OPTIONS
+                            .iter()
+                            .map(|o| o.to_string())
+                            .collect(),
+                        style: QuestionStyle::Open,
+                        selected: 0,
+                        responder: None,
+                    });
+                    self.summary_review = Some(PendingSummaryReview {
+                        summary,
+                        editing: false,
+                        responder,
+                    });
+                    self.scroll_to_latest();
                     Command::none()
                 }
                 AgentEvent::CompactionDone {
@@ -244,29 +791,48 @@ impl Model for ClawApp {
             },
             Msg::Key(key) => {
                 // Ctrl+Q always quits immediately.
-                if key.modifiers.contains(KeyModifiers::CONTROL)
-                    && key.code == KeyCode::Char('q')
-                {
-                    return Command::quit();
+                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('q') {
+                    return self.quit();
                 }
 
-                // Double Ctrl+C within 500ms quits; single Ctrl+C just primes
-                // the timer and clears the input as a "cancel" gesture.
-                if key.modifiers.contains(KeyModifiers::CONTROL)
-                    && key.code == KeyCode::Char('c')
-                {
+                if self.pending_quit.is_some() {
+                    return self.handle_quit_key(key);
+                }
+
+                if self.pending_paste.is_some() {
+                    return self.handle_paste_key(key);
+                }
+
+                if self.pending_consent.is_some() {
+                    return self.handle_consent_key(key);
+                }
+
+                // Double Ctrl+C within the configured window quits (after a
+                // confirmation while a turn is running); a single Ctrl+C
+                // cancels the running turn or clears the input.
+                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
                     let now = Instant::now();
-                    if let Some(prev) = self.last_ctrl_c {
-                        if now.duration_since(prev).as_millis() < 500 {
-                            return Command::quit();
-                        }
-                    }
+                    let action = ctrl_c_action(
+                        self.last_ctrl_c,
+                        now,
+                        self.double_ctrl_c_window,
+                        self.streaming,
+                        !self.input.value().is_empty(),
+                    );
                     self.last_ctrl_c = Some(now);
-                    // Single Ctrl+C cancels current input.
-                    if !self.input.value().is_empty() {
-                        self.input.set_value("");
-                    }
-                    return Command::none();
+                    return match action {
+                        CtrlCAction::Quit if self.streaming => {
+                            self.pending_quit = Some(PendingQuit { selected: 0 });
+                            Command::none()
+                        }
+                        CtrlCAction::Quit => self.quit(),
+                        CtrlCAction::CancelTurn => self.cancel_turn(),
+                        CtrlCAction::ClearInput => {
+                            self.input.set_value("");
+                            Command::none()
+                        }
+                        CtrlCAction::Prime => Command::none(),
+                    };
                 }
 
                 // Route to approval/question mode handlers when active
@@ -276,14 +842,53 @@ impl Model for ClawApp {
                 if self.pending_question.is_some() {
                     return self.handle_question_key(key);
                 }
+                if self.allowlist_editor.is_some() {
+                    return self.handle_allowlist_key(key);
+                }
+                let popup_open = self.completion_popup().is_some();
+                match self.keymap.handle(&key, popup_open) {
+                    KeyAction::Pass => {}
+                    action => return self.run_key_action(action),
+                }
+                if let Some(command) = EditCommand::from_key(&key) {
+                    return self.apply_edit(command);
+                }
 
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
                 match key.code {
+                    KeyCode::Home if ctrl => {
+                        self.scroll_to_top();
+                        Command::none()
+                    }
+                    KeyCode::End if ctrl => {
+                        self.scroll_to_latest();
+                        Command::none()
+                    }
+                    // Scrolled up while a reply streams: back to following it.
+                    KeyCode::End if self.streaming && !self.chat_at_bottom() => {
+                        self.scroll_to_latest();
+                        Command::none()
+                    }
+                    KeyCode::Char('o') if ctrl => {
+                        self.toggle_tool_output();
+                        Command::none()
+                    }
+                    KeyCode::PageUp if ctrl => {
+                        self.jump_turn(Jump::Back);
+                        Command::none()
+                    }
+                    KeyCode::PageDown if ctrl => {
+                        self.jump_turn(Jump::Forward);
+                        Command::none()
+                    }
                     KeyCode::PageUp => {
-                        self.chat_viewport.update(viewport::Message::ScrollUp(10));
+                        self.note_if_at_top();
+                        let page = self.page_size();
+                        self.chat_viewport.update(viewport::Message::ScrollUp(page));
                         Command::none()
                     }
                     KeyCode::PageDown => {
-                        self.chat_viewport.update(viewport::Message::ScrollDown(10));
+                        self.scroll_down(self.page_size());
                         Command::none()
                     }
                     KeyCode::Up if self.streaming => {
@@ -291,7 +896,7 @@ impl Model for ClawApp {
                         Command::none()
                     }
                     KeyCode::Down if self.streaming => {
-                        self.chat_viewport.update(viewport::Message::ScrollDown(1));
+                        self.scroll_down(1);
                         Command::none()
                     }
                     KeyCode::Up => {
@@ -305,10 +910,8 @@ impl Model for ClawApp {
                         }
                     }
                     KeyCode::Down => {
-                        if self.input.cursor_row()
-                            >= self.input.line_count().saturating_sub(1)
-                        {
-                            self.chat_viewport.update(viewport::Message::ScrollDown(1));
+                        if self.input.cursor_row() >= self.input.line_count().saturating_sub(1) {
+                            self.scroll_down(1);
                             Command::none()
                         } else {
                             self.input
@@ -316,27 +919,38 @@ impl Model for ClawApp {
                                 .map(Msg::Input)
                         }
                     }
-                    KeyCode::Enter if !key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    KeyCode::Enter if is_newline_key(&key, self.enter_submits) => self
+                        .input
+                        .update(text_area::Message::KeyPress(KeyEvent::new(
+                            KeyCode::Enter,
+                            KeyModifiers::SHIFT,
+                        )))
+                        .map(Msg::Input),
+                    KeyCode::Enter => {
                         let text = self.input.value();
-                        if text.trim().is_empty() {
-                            return Command::none();
-                        }
-                        if self.streaming {
-                            self.queued_message = Some(text);
-                            self.input.set_value("");
-                            Command::none()
-                        } else {
-                            self.push_message(ChatMessageKind::User, text.clone());
-                            self.streaming = true;
-                            self.input.set_value("");
-                            self.send_message(text)
-                        }
+                        self.submit(text)
+                    }
+                    // For terminals that send Ctrl+Enter as plain Enter.
+                    _ if is_send_fallback(&key) => {
+                        let text = self.input.value();
+                        self.submit(text)
+                    }
+                    KeyCode::F(_) if snippets::snippet_for_key(&self.snippets, &key).is_some() => {
+                        self.apply_snippet(key)
+                    }
+                    KeyCode::Tab if commands::typed_prefix(&self.input.value()).is_some() => {
+                        self.complete_command();
+                        Command::none()
+                    }
+                    KeyCode::Esc if self.completion_popup().is_some() => {
+                        self.dismissed_completion = Some(self.input.value());
+                        Command::none()
                     }
                     KeyCode::Esc => {
                         if self.streaming {
                             Command::none()
                         } else {
-                            Command::quit()
+                            self.quit()
                         }
                     }
                     _ => self
@@ -351,77 +965,114 @@ impl Model for ClawApp {
                     Command::none()
                 }
                 MouseEventKind::ScrollDown => {
-                    self.chat_viewport.update(viewport::Message::ScrollDown(MOUSE_SCROLL_STEP));
+                    self.scroll_down(MOUSE_SCROLL_STEP);
                     Command::none()
                 }
                 _ => Command::none(),
             },
             Msg::Paste(text) => {
-                // Block paste during approval and multichoice question modes
-                // where the input area is not active.
-                let in_multichoice = self
+                // Block paste during approval, multichoice, and confirm
+                // question modes where the input area is not active.
+                let picking_answer = self
                     .pending_question
                     .as_ref()
-                    .is_some_and(|q| !q.options.is_empty());
-                if self.pending_approval.is_some() || in_multichoice {
-                    Command::none()
-                } else {
-                    self.input
-                        .update(text_area::Message::Paste(text))
-                        .map(Msg::Input)
+                    .is_some_and(|q| !q.takes_text());
+                if self.pending_approval.is_some()
+                    || self.pending_consent.is_some()
+                    || picking_answer
+                {
+                    return Command::none();
+                }
+                match paste::classify(&text, self.max_paste_bytes) {
+                    PasteAction::Insert(text) => self.insert_paste(text),
+                    PasteAction::Prompt(text) => {
+                        self.pending_paste = Some(PendingPaste { text, selected: 0 });
+                        Command::none()
+                    }
                 }
             }
             Msg::Input(_) => Command::none(),
             Msg::MessageSent => Command::none(),
+            Msg::KeyboardEnhanced => self.send_initial_prompt(),
+            Msg::SnippetClipboard(snippet, clipboard) => {
+                self.fill_snippet(&snippet, clipboard.as_deref())
+            }
+            Msg::EditorClosed(outcome) => {
+                let Some(session) = self.editing.take() else {
+                    return Command::none();
+                };
+                self.finish_edit(&session.path, outcome, session.responder);
+                self.blank_frame = true;
+                Command::perform(async { tokio::time::sleep(REPAINT_DELAY).await }, |_| {
+                    Msg::Repaint
+                })
+            }
+            Msg::Repaint => {
+                self.blank_frame = false;
+                Command::none()
+            }
+            Msg::CursorBlink => {
+                self.cursor_on = !self.cursor_on;
+                if self.reply_streaming() {
+                    self.rebuild_chat_content();
+                }
+                Command::none()
+            }
         }
     }
 
     fn view(&self, frame: &mut Frame) {
+        if self.blank_frame || self.editing.is_some() {
+            return;
+        }
         let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            self.chat_height.store(0, Ordering::Relaxed);
+            frame.render_widget(
+                Paragraph::new(too_small_lines(area.width, area.height)).wrap(Wrap { trim: true }),
+                area,
+            );
+            return;
+        }
+        let has_quit = self.pending_quit.is_some();
+        let has_paste = self.pending_paste.is_some();
+        let has_consent = self.pending_consent.is_some();
         let has_approval = self.pending_approval.is_some();
         let has_question = self.pending_question.is_some();
 
-        // Maximum height the input area can grow to (in terminal rows).
-        const MAX_INPUT_HEIGHT: u16 = 8;
-
-        // Calculate input height based on visual line count (accounting for soft
-        // wrap at terminal width). The inner width is the frame width minus 2 for
-        // the left/right border cells.
+        // The input grows with the draft, both for chat messages and for
+        // free-text question answers.
+        let draft = self.input.value();
         let input_height = if has_approval {
             3
         } else {
-            let inner_width = area.width.saturating_sub(2).max(1) as usize;
-            let visual_lines: usize = self
-                .input
-                .value()
-                .split('\n')
-                .map(|line| {
-                    let w = unicode_width::UnicodeWidthStr::width(line);
-                    if w == 0 { 1 } else { (w + inner_width - 1) / inner_width }
-                })
-                .sum();
-            // +2 accounts for top and bottom borders
-            (visual_lines as u16 + 2).clamp(3, MAX_INPUT_HEIGHT)
+            input_height(&draft, area.width)
         };
 
         // Compute prompt area height dynamically so long questions/options wrap
         // instead of being truncated. Each logical Line is measured against the
         // terminal width to determine how many visual rows it occupies.
-        let prompt_height = if has_approval {
+        let prompt_height = if let Some(ref quit) = self.pending_quit {
+            visual_line_height(&quit_confirm_lines(quit.selected), area.width)
+        } else if let Some(ref pending) = self.pending_paste {
+            visual_line_height(&self.paste_prompt(pending), area.width)
+        } else if let Some(ref pending) = self.pending_consent {
+            visual_line_height(
+                &consent_lines(pending.feature.question(), pending.selected),
+                area.width,
+            )
+        } else if has_approval {
             if let Some(ref approval) = self.pending_approval {
-                let lines = approval_line(&approval.description, approval.selected);
-                visual_line_height(&lines, area.width)
+                visual_line_height(&approval_prompt_lines(approval), area.width)
             } else {
                 3
             }
         } else if has_question {
             if let Some(ref question) = self.pending_question {
-                let lines = if question.options.is_empty() {
-                    question_lines(&question.question)
-                } else {
-                    multichoice_lines(&question.question, &question.options, question.selected)
-                };
-                visual_line_height(&lines, area.width)
+                visual_line_height(
+                    &question_prompt_lines(question, self.enter_submits),
+                    area.width,
+                )
             } else {
                 3
             }
@@ -430,7 +1081,7 @@ impl Model for ClawApp {
         };
 
         // Dynamic layout: insert a dedicated prompt area when approval or question is pending.
-        let constraints = if has_approval || has_question {
+        let constraints = if has_quit || has_paste || has_consent || has_approval || has_question {
             vec![
                 Constraint::Length(1),                   // Header
                 Constraint::Min(3),                      // Chat area
@@ -452,39 +1103,99 @@ impl Model for ClawApp {
             .constraints(constraints)
             .split(area);
 
-        // 1. Header (with debug key counter)
-        let header = Line::from(vec![
-            Span::styled(
-                " \u{1f43e} claw",
-                Style::default()
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
-            ),
-        ]);
-        frame.render_widget(Paragraph::new(header), chunks[0]);
+        // 1. Header
+        frame.render_widget(
+            Paragraph::new(header_line(
+                chunks[0].width,
+                self.ascii,
+                self.observe.then_some("observe"),
+            )),
+            chunks[0],
+        );
 
         // 2. Chat area — Viewport handles scrolling and rendering.
-        self.chat_viewport.view(frame, chunks[1]);
+        self.chat_height.store(chunks[1].height, Ordering::Relaxed);
+        if let Some(ref editor) = self.allowlist_editor {
+            let scroll = editor
+                .selected_line()
+                .saturating_sub(chunks[1].height.saturating_sub(2) as usize);
+            frame.render_widget(
+                Paragraph::new(editor.lines()).scroll((scroll as u16, 0)),
+                chunks[1],
+            );
+        } else {
+            self.chat_viewport.view(frame, chunks[1]);
+            if self
+                .top_notice
+                .is_some_and(|shown| shown.elapsed() < TOP_NOTICE_DURATION)
+            {
+                let row = Rect {
+                    height: 1,
+                    ..chunks[1]
+                };
+                let notice = Span::styled(
+                    format!(" {} ", TOP_NOTICE),
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::REVERSED),
+                );
+                frame.render_widget(
+                    Paragraph::new(Line::from(notice)).alignment(Alignment::Right),
+                    row,
+                );
+            }
+            if self.new_below && !self.chat_at_bottom() {
+                let row = Rect {
+                    y: chunks[1].bottom().saturating_sub(1),
+                    height: 1,
+                    ..chunks[1]
+                };
+                let notice = Span::styled(
+                    format!(" {} ", NEW_BELOW_NOTICE),
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::REVERSED),
+                );
+                frame.render_widget(
+                    Paragraph::new(Line::from(notice)).alignment(Alignment::Right),
+                    row,
+                );
+            }
+        }
 
         // 3. Approval or question prompt (only when pending)
-        let (input_chunk, status_chunk) = if has_approval {
+        let (input_chunk, status_chunk) = if let Some(ref quit) = self.pending_quit {
+            frame.render_widget(
+                Paragraph::new(quit_confirm_lines(quit.selected)).wrap(Wrap { trim: false }),
+                chunks[2],
+            );
+            (chunks[3], chunks[4])
+        } else if let Some(ref pending) = self.pending_paste {
+            frame.render_widget(
+                Paragraph::new(self.paste_prompt(pending)).wrap(Wrap { trim: false }),
+                chunks[2],
+            );
+            (chunks[3], chunks[4])
+        } else if let Some(ref pending) = self.pending_consent {
+            frame.render_widget(
+                Paragraph::new(consent_lines(pending.feature.question(), pending.selected))
+                    .wrap(Wrap { trim: false }),
+                chunks[2],
+            );
+            (chunks[3], chunks[4])
+        } else if has_approval {
             if let Some(ref approval) = self.pending_approval {
-                let approval_lines = approval_line(&approval.description, approval.selected);
                 frame.render_widget(
-                    Paragraph::new(approval_lines).wrap(Wrap { trim: false }),
+                    Paragraph::new(approval_prompt_lines(approval)).wrap(Wrap { trim: false }),
                     chunks[2],
                 );
             }
             (chunks[3], chunks[4])
         } else if has_question {
             if let Some(ref question) = self.pending_question {
-                let q_lines = if question.options.is_empty() {
-                    question_lines(&question.question)
-                } else {
-                    multichoice_lines(&question.question, &question.options, question.selected)
-                };
                 frame.render_widget(
-                    Paragraph::new(q_lines).wrap(Wrap { trim: false }),
+                    Paragraph::new(question_prompt_lines(question, self.enter_submits))
+                        .wrap(Wrap { trim: false }),
                     chunks[2],
                 );
             }
@@ -513,7 +1224,22 @@ impl Model for ClawApp {
             let mut block = Block::default()
                 .borders(Borders::TOP | Borders::BOTTOM)
                 .border_style(Style::default().fg(Color::DarkGray));
-            if self.streaming {
+            let answering = self
+                .pending_question
+                .as_ref()
+                .is_some_and(PendingQuestion::takes_text);
+            if answering {
+                let (submit, newline) = enter_keys(self.enter_submits);
+                let title = if self.confirm_empty_answer {
+                    format!(" \u{21b5} press {} again to send an empty answer ", submit)
+                } else {
+                    format!(
+                        " \u{270f}\u{fe0f} answer \u{2014} {} to submit, {} for newline ",
+                        submit, newline
+                    )
+                };
+                block = block.title(Span::styled(title, Style::default().fg(Color::Cyan)));
+            } else if self.streaming {
                 let title = if self.queued_message.is_some() {
                     " \u{1f4e8} message queued "
                 } else {
@@ -521,611 +1247,4049 @@ impl Model for ClawApp {
                 };
                 block = block.title(Span::styled(title, Style::default().fg(Color::DarkGray)));
             }
+            let draft_tokens = draft_tokens(&draft);
+            let cost = draft_cost(
+                draft_tokens,
+                self.context_used,
+                self.context_window,
+                self.compaction_limit,
+            );
+            if let Some(title) = draft_title(draft_tokens, cost) {
+                block = block.title(title);
+            }
             let inner = block.inner(input_chunk);
             frame.render_widget(block, input_chunk);
             self.input.view(frame, inner);
         }
 
+        // Slash command completions, drawn over the bottom of the chat.
+        if let Some((items, more)) = self.completion_popup() {
+            let lines = completion_lines(&items, more);
+            let popup = popup_area(input_chunk, chunks[1], lines.len());
+            frame.render_widget(Clear, popup);
+            frame.render_widget(
+                Paragraph::new(lines).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::DarkGray))
+                        .title(" Tab to complete, Esc to close "),
+                ),
+                popup,
+            );
+        }
+
         // 5. Status bar
         let status = status_line(&StatusBarParams {
             workspace_dir: &self.workspace_dir,
             context_used: self.context_used,
             context_window: self.context_window,
+            compaction_forecast: self.compaction_forecast(),
+            cost: self.session_cost(),
             session_start: self.session_start,
             streaming: self.streaming,
+            width: status_chunk.width,
+            ascii: self.ascii,
+            mode: self.keymap.status().as_deref(),
         });
         frame.render_widget(Paragraph::new(status), status_chunk);
     }
 
     fn subscriptions(&self) -> Vec<Subscription<Msg>> {
-        vec![
-            terminal_events(|ev| match ev {
-                TerminalEvent::Key(key) => Some(Msg::Key(key)),
-                TerminalEvent::Mouse(mouse) => Some(Msg::Mouse(mouse)),
-                TerminalEvent::Paste(text) => Some(Msg::Paste(text)),
-                _ => None,
-            }),
+        let mut subscriptions = vec![
             subscribe(AgentEventSource {
                 rx: self.agent_rx.clone(),
             })
             .map(Msg::Agent),
-        ]
+        ];
+        // Dropped while the editor runs, so the two don't race for stdin.
+        if self.editing.is_none() {
+            subscriptions.push(terminal_events(|ev| match ev {
+                TerminalEvent::Key(key) => Some(Msg::Key(key)),
+                TerminalEvent::Mouse(mouse) => Some(Msg::Mouse(mouse)),
+                TerminalEvent::Paste(text) => Some(Msg::Paste(text)),
+                _ => None,
+            }));
+        }
+        // No blinking in accessible mode; every repaint is noise to a screen reader.
+        if self.streaming && !self.chat_options.accessible {
+            subscriptions.push(subscribe(CursorBlink).map(|()| Msg::CursorBlink));
+        }
+        subscriptions
     }
 }
 
 impl ClawApp {
-    /// Add a message to the chat history and reset scroll to bottom.
-    pub fn push_message(&mut self, kind: ChatMessageKind, content: String) {
-        self.messages.push(ChatMessage { kind, content });
-        self.rebuild_chat_content();
+    /// Lines to scroll for PageUp/PageDown: one screen of chat, less the overlap.
+    fn page_size(&self) -> u16 {
+        page_size(self.chat_height.load(Ordering::Relaxed), PAGE_OVERLAP)
     }
 
-    /// Append text to the last assistant message, or create a new one if needed.
-    /// Keeps scroll pinned to the bottom so new content is always visible.
-    pub fn append_to_last_assistant(&mut self, text: &str) {
-        if let Some(msg) = self.messages.last_mut()
-            && msg.kind == ChatMessageKind::Assistant
-        {
-            msg.content.push_str(text);
-            self.rebuild_chat_content();
-            return;
+    /// Flash the top-of-history notice when the chat can't scroll up further.
+    fn note_if_at_top(&mut self) {
+        if self.chat_viewport.y_offset() == 0 {
+            self.top_notice = Some(Instant::now());
         }
-        self.push_message(ChatMessageKind::Assistant, text.to_string());
     }
 
-    /// Rebuild the viewport's styled content from current messages and scroll to bottom.
-    fn rebuild_chat_content(&mut self) {
-        self.chat_viewport.set_styled_content(render_chat_lines(&self.messages));
-        self.chat_viewport.goto_bottom();
+    /// Ctrl+Home: scroll the chat to its first line.
+    fn scroll_to_top(&mut self) {
+        self.note_if_at_top();
+        self.chat_viewport.goto_top();
     }
 
-    /// Update the status of the most recent tool call message matching the given tool name.
-    fn update_tool_status(&mut self, tool_name: &str, new_status: ToolCallStatus) {
-        for msg in self.messages.iter_mut().rev() {
-            if let ChatMessageKind::ToolCall {
-                tool_name: ref name,
-                ref mut status,
-            } = msg.kind
-                && name == tool_name
-            {
-                *status = new_status;
-                self.rebuild_chat_content();
-                return;
-            }
-        }
+    /// Whether the chat shows its last line, so new content should keep it
+    /// there.
+    fn chat_at_bottom(&self) -> bool {
+        let height = self.chat_height.load(Ordering::Relaxed) as usize;
+        navigation::at_bottom(
+            self.chat_viewport.y_offset() as usize,
+            self.chat_lines,
+            height,
+        )
     }
 
-    /// Send a user message to the agent loop via the mpsc channel.
-    fn send_message(&self, text: String) -> Command<Msg> {
-        let tx = self.user_tx.clone();
-        Command::perform(
-            async move {
-                let _ = tx.send(UserEvent::Message(text)).await;
-            },
-            |_| Msg::MessageSent,
-        )
+    /// Scroll to the last line and follow new content again.
+    fn scroll_to_latest(&mut self) {
+        self.chat_viewport.goto_bottom();
+        self.new_below = false;
     }
 
-    /// Handle key events while a tool approval prompt is active.
-    fn handle_approval_key(&mut self, key: KeyEvent) -> Command<Msg> {
-        match key.code {
-            KeyCode::Left => {
-                if let Some(ref mut approval) = self.pending_approval {
-                    approval.selected = approval.selected.saturating_sub(1);
-                }
-                Command::none()
-            }
-            KeyCode::Right => {
-                if let Some(ref mut approval) = self.pending_approval {
-                    approval.selected = (approval.selected + 1).min(2);
-                }
-                Command::none()
-            }
-            KeyCode::Char('1') => self.resolve_approval(0),
-            KeyCode::Char('2') => self.resolve_approval(1),
-            KeyCode::Char('3') => self.resolve_approval(2),
-            KeyCode::Enter => {
-                let selected = self
-                    .pending_approval
-                    .as_ref()
-                    .map_or(0, |a| a.selected);
-                self.resolve_approval(selected)
-            }
-            _ => Command::none(),
+    /// Scroll the chat down, dropping the "new messages" indicator once the
+    /// bottom is reached.
+    fn scroll_down(&mut self, lines: u16) {
+        self.chat_viewport
+            .update(viewport::Message::ScrollDown(lines));
+        if self.chat_at_bottom() {
+            self.new_below = false;
         }
     }
 
-    /// Resolve the pending approval by mapping the selected index to a decision
-    /// and sending it via the oneshot channel.
-    fn resolve_approval(&mut self, selected: usize) -> Command<Msg> {
-        if let Some(mut approval) = self.pending_approval.take() {
-            let decision = match selected {
-                0 => ApprovalDecision::AllowOnce,
-                1 => ApprovalDecision::AllowAlways,
-                _ => ApprovalDecision::Deny,
-            };
-            if let Some(responder) = approval.responder.take() {
-                let _ = responder.send(decision);
+    /// Carry out what a vim-style key asked for.
+    fn run_key_action(&mut self, action: KeyAction) -> Command<Msg> {
+        let command = match action {
+            KeyAction::Pass | KeyAction::Consumed => Command::none(),
+            KeyAction::ScrollUp => {
+                self.note_if_at_top();
+                self.chat_viewport.update(viewport::Message::ScrollUp(1));
+                Command::none()
             }
-        }
-        Command::none()
-    }
-
-    /// Handle key events while a question prompt is active.
-    /// Dispatches to multichoice or free-text handling based on whether options exist.
-    fn handle_question_key(&mut self, key: KeyEvent) -> Command<Msg> {
-        let has_options = self
-            .pending_question
-            .as_ref()
-            .is_some_and(|q| !q.options.is_empty());
-
-        if has_options {
-            return self.handle_multichoice_key(key);
-        }
-
-        // Free-text question mode
-        match key.code {
-            KeyCode::Enter => {
-                let text = self.input.value();
-                self.input.set_value("");
-                self.resolve_question(text);
+            KeyAction::ScrollDown => {
+                self.scroll_down(1);
                 Command::none()
             }
-            KeyCode::Esc => {
-                self.resolve_question("[User declined to answer]".to_string());
+            KeyAction::Top => {
+                self.scroll_to_top();
+                Command::none()
+            }
+            KeyAction::Bottom => {
+                self.scroll_to_latest();
                 Command::none()
             }
-            _ => self
+            KeyAction::Append => self
                 .input
-                .update(text_area::Message::KeyPress(key))
+                .update(text_area::Message::KeyPress(KeyEvent::new(
+                    KeyCode::Right,
+                    KeyModifiers::NONE,
+                )))
                 .map(Msg::Input),
-        }
-    }
-
-    /// Handle key events for multiple-choice question mode.
-    fn handle_multichoice_key(&mut self, key: KeyEvent) -> Command<Msg> {
-        match key.code {
-            KeyCode::Left => {
-                if let Some(ref mut q) = self.pending_question {
-                    q.selected = q.selected.saturating_sub(1);
+            KeyAction::ClearInput => {
+                self.input.set_value("");
+                Command::none()
+            }
+            KeyAction::StartSelection => {
+                match self.messages.len().checked_sub(1) {
+                    Some(last) => self.select(last, last),
+                    None => self.keymap.end_selection(),
                 }
                 Command::none()
             }
-            KeyCode::Right => {
-                if let Some(ref mut q) = self.pending_question {
-                    let max = q.options.len().saturating_sub(1);
-                    q.selected = (q.selected + 1).min(max);
+            KeyAction::SelectUp => {
+                if let Some((anchor, end)) = self.selection {
+                    self.select(anchor, end.saturating_sub(1));
                 }
                 Command::none()
             }
-            KeyCode::Enter => {
-                let answer = self
-                    .pending_question
-                    .as_ref()
-                    .and_then(|q| q.options.get(q.selected).cloned())
-                    .unwrap_or_default();
-                self.resolve_question(answer);
+            KeyAction::SelectDown => {
+                if let Some((anchor, end)) = self.selection {
+                    self.select(anchor, (end + 1).min(self.messages.len().saturating_sub(1)));
+                }
                 Command::none()
             }
-            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
-                let idx = (c as usize) - ('1' as usize);
-                let option_count = self
-                    .pending_question
-                    .as_ref()
-                    .map_or(0, |q| q.options.len());
-                if idx < option_count {
-                    if let Some(ref mut q) = self.pending_question {
-                        q.selected = idx;
-                    }
-                    let answer = self
-                        .pending_question
-                        .as_ref()
-                        .and_then(|q| q.options.get(q.selected).cloned())
-                        .unwrap_or_default();
-                    self.resolve_question(answer);
+            KeyAction::Yank => {
+                if let Some((anchor, end)) = self.selection
+                    && let Some(selected) = self.messages.get(anchor.min(end)..=anchor.max(end))
+                {
+                    let text = selected
+                        .iter()
+                        .map(|m| m.content.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    let what = match selected.len() {
+                        1 => "1 message".to_string(),
+                        n => format!("{} messages", n),
+                    };
+                    self.copy_to_clipboard(text, what);
                 }
                 Command::none()
             }
-            KeyCode::Esc => {
-                self.resolve_question("[User declined to answer]".to_string());
+            KeyAction::Command(command) => self.run_vim_command(&command),
+        };
+        if self.keymap.mode() != Some(VimMode::Visual) && self.selection.take().is_some() {
+            self.rebuild_chat_content();
+        }
+        command
+    }
+
+    /// Select messages `anchor` through `end` and scroll `end` into view.
+    fn select(&mut self, anchor: usize, end: usize) {
+        self.selection = Some((anchor, end));
+        self.rebuild_chat_content();
+        let height = self.chat_height.load(Ordering::Relaxed) as usize;
+        let max = navigation::max_offset(self.chat_lines, height);
+        let target = self.message_lines.get(end).copied().unwrap_or(max).min(max);
+        if target == max {
+            self.scroll_to_latest();
+        } else {
+            self.chat_viewport.set_y_offset(target);
+        }
+    }
+
+    /// A vim `:` command: `:q` quits (asking first while a turn runs) and
+    /// `:q!` quits at once.
+    fn run_vim_command(&mut self, command: &str) -> Command<Msg> {
+        match command.trim() {
+            "" => Command::none(),
+            "q" | "quit" | "qa" if self.streaming => {
+                self.pending_quit = Some(PendingQuit { selected: 0 });
+                Command::none()
+            }
+            "q" | "quit" | "qa" | "q!" | "qa!" | "wq" | "x" => self.quit(),
+            other => {
+                self.push_message(
+                    ChatMessageKind::System,
+                    format!("Not an editor command: :{} (:q quits)", other),
+                );
                 Command::none()
             }
-            _ => Command::none(),
         }
     }
 
-    /// Resolve the pending question by sending the answer via the oneshot channel.
-    fn resolve_question(&mut self, answer: String) {
-        if let Some(mut question) = self.pending_question.take()
-            && let Some(responder) = question.responder.take()
+    /// Ctrl+PageUp/PageDown: scroll the chat to the previous or next turn.
+    fn jump_turn(&mut self, jump: Jump) {
+        if jump == Jump::Back {
+            self.note_if_at_top();
+        }
+        let height = self.chat_height.load(Ordering::Relaxed) as usize;
+        let max = navigation::max_offset(self.chat_lines, height);
+        let offset = (self.chat_viewport.y_offset() as usize).min(max);
+        let starts = navigation::turn_starts(&self.messages, &self.message_lines);
+        let target = navigation::jump_target(&starts, offset, max, jump);
+        if target == max {
+            self.scroll_to_latest();
+        } else {
+            self.chat_viewport.set_y_offset(target);
+        }
+    }
+
+    /// Record the context size at a turn boundary for the compaction forecast.
+    fn record_context_sample(&mut self) {
+        if self.context_used == 0 || self.context_history.last() == Some(&self.context_used) {
+            return;
+        }
+        // Compaction shrinks the context; growth before it says nothing about growth after.
+        if self
+            .context_history
+            .last()
+            .is_some_and(|&prev| self.context_used < prev)
         {
-            let _ = responder.send(answer);
+            self.context_history.clear();
+        }
+        self.context_history.push(self.context_used);
+        if self.context_history.len() > CONTEXT_HISTORY_LEN {
+            self.context_history.remove(0);
         }
     }
-}
 
-/// Calculate how many terminal rows a set of styled Lines will occupy when
-/// wrapped at the given width. Each Line's spans are measured by unicode
-/// display width and ceiling-divided by the available width.
-fn visual_line_height(lines: &[Line], width: u16) -> u16 {
-    let w = width.max(1) as usize;
-    lines
-        .iter()
-        .map(|line| {
-            let line_width: usize = line
-                .spans
-                .iter()
-                .map(|s| unicode_width::UnicodeWidthStr::width(s.content.as_ref()))
-                .sum();
-            if line_width == 0 {
-                1
-            } else {
-                ((line_width + w - 1) / w) as u16
+    /// Estimated turns until auto-compaction, if it's enabled and predictable.
+    pub fn compaction_forecast(&self) -> Option<u64> {
+        forecast_turns_until(&self.context_history, self.compaction_limit?)
+    }
+
+    /// Estimated cost of the session so far, if the model's pricing is known.
+    pub fn session_cost(&self) -> Option<f64> {
+        self.pricing
+            .as_ref()
+            .map(|pricing| self.usage.cost(pricing))
+    }
+
+    /// Add a message to the chat history and reset scroll to bottom. A
+    /// system message identical to the one right before it bumps a count on
+    /// that one instead ("... (×3)"), so retries and repeated errors don't
+    /// flood the transcript.
+    pub fn push_message(&mut self, kind: ChatMessageKind, content: String) {
+        // Whatever arrives first takes the place of the "thinking…" line.
+        self.clear_thinking();
+        if kind == ChatMessageKind::System
+            && let Some(run) = self.system_run.as_mut()
+            && run.text == content
+            && run.index + 1 == self.messages.len()
+            && self.messages[run.index].kind == ChatMessageKind::System
+            && self.messages[run.index].content == run.display()
+        {
+            run.count += 1;
+            self.messages[run.index].content = run.display();
+            self.rebuild_chat_content();
+            return;
+        }
+        self.system_run = (kind == ChatMessageKind::System).then(|| SystemRun {
+            index: self.messages.len(),
+            text: content.clone(),
+            count: 1,
+        });
+        // The TUI only ever adds system messages of its own; everything
+        // else mirrors what the agent loop put in the history.
+        self.messages.push(match kind {
+            ChatMessageKind::System => ChatMessage::note(content),
+            kind => ChatMessage::new(kind, content),
+        });
+        self.rebuild_chat_content();
+    }
+
+    /// Show a "thinking…" placeholder for the reply until its first token.
+    fn show_thinking(&mut self) {
+        self.messages
+            .push(ChatMessage::new(ChatMessageKind::Assistant, String::new()));
+        self.rebuild_chat_content();
+    }
+
+    /// Remove a trailing "thinking…" placeholder, without re-rendering.
+    /// Returns whether there was one.
+    fn clear_thinking(&mut self) -> bool {
+        if self.messages.last().is_some_and(is_thinking) {
+            self.messages.pop();
+            return true;
+        }
+        false
+    }
+
+    /// Whether the last message is a reply whose text is still streaming in,
+    /// so it gets the cursor.
+    fn reply_streaming(&self) -> bool {
+        self.streaming
+            && self
+                .messages
+                .last()
+                .is_some_and(|m| m.kind == ChatMessageKind::Assistant && !is_thinking(m))
+    }
+
+    /// Append text to the last assistant message, or create a new one if needed.
+    /// The first token fills the "thinking…" placeholder in place.
+    pub fn append_to_last_assistant(&mut self, text: &str) {
+        if let Some(msg) = self.messages.last_mut()
+            && msg.kind == ChatMessageKind::Assistant
+        {
+            msg.content.push_str(text);
+            self.rebuild_chat_content();
+            return;
+        }
+        self.push_message(ChatMessageKind::Assistant, text.to_string());
+    }
+
+    /// Rebuild the viewport's styled content from current messages. A chat
+    /// at the bottom follows the new content; one scrolled up to read stays
+    /// put, and the "new messages" indicator shows when content was added.
+    fn rebuild_chat_content(&mut self) {
+        // Messages can go while a selection is up (the thinking placeholder
+        // on Done, /clear): keep it on the ones left.
+        if let Some((anchor, end)) = self.selection {
+            match self.messages.len().checked_sub(1) {
+                Some(last) => self.selection = Some((anchor.min(last), end.min(last))),
+                None => {
+                    self.selection = None;
+                    self.keymap.end_selection();
+                }
+            }
+        }
+        let follow = self.chat_at_bottom();
+        let before = self.chat_lines;
+        let options = ChatOptions {
+            cursor: self.streaming && self.cursor_on && !self.chat_options.accessible,
+            ..self.chat_options
+        };
+        let (mut lines, starts) = render_chat(&self.messages, &options);
+        if let Some((anchor, end)) = self.selection {
+            let first = starts.get(anchor.min(end)).copied().unwrap_or(lines.len());
+            let last = starts
+                .get(anchor.max(end) + 1)
+                .copied()
+                .unwrap_or(lines.len());
+            for line in &mut lines[first..last] {
+                let style = Style::default().add_modifier(Modifier::REVERSED);
+                *line = std::mem::take(line).patch_style(style);
             }
+        }
+        self.chat_lines = lines.len();
+        self.message_lines = starts;
+        self.chat_viewport.set_styled_content(lines);
+        if follow {
+            self.scroll_to_latest();
+        } else if self.chat_lines > before {
+            self.new_below = true;
+        }
+    }
+
+    /// Index of the tool call message with the given id.
+    fn tool_call_index(&self, tool_call_id: &str) -> Option<usize> {
+        self.messages.iter().rposition(|msg| match &msg.kind {
+            ChatMessageKind::ToolCall {
+                tool_call_id: id, ..
+            } => id == tool_call_id,
+            _ => false,
         })
-        .sum()
-}
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Update the status of the tool call message with the given id.
+    fn update_tool_status(&mut self, tool_call_id: &str, new_status: ToolCallStatus) {
+        let Some(idx) = self.tool_call_index(tool_call_id) else {
+            return;
+        };
+        if let ChatMessageKind::ToolCall { ref mut status, .. } = self.messages[idx].kind {
+            *status = new_status;
+            self.rebuild_chat_content();
+        }
+    }
 
-    fn test_flags() -> Flags {
-        let (user_tx, _user_rx) = mpsc::channel(16);
-        let (_agent_tx, agent_rx) = mpsc::channel(64);
-        Flags {
-            user_tx,
-            agent_rx,
-            model_name: "test-model".to_string(),
-            tool_count: 5,
-            context_window: 128_000,
-            workspace_dir: "/tmp/test".to_string(),
-            replay_messages: vec![],
-            startup_message: "Test startup".to_string(),
+    /// Record what auto-approved the tool call with the given id.
+    fn set_approval_reason(&mut self, tool_call_id: &str, reason: Option<ApprovalReason>) {
+        let Some(idx) = self.tool_call_index(tool_call_id) else {
+            return;
+        };
+        if let ChatMessageKind::ToolCall {
+            ref mut approval_reason,
+            ..
+        } = self.messages[idx].kind
+        {
+            *approval_reason = reason;
+            self.rebuild_chat_content();
+        }
+    }
+
+    /// Show a tool result under the call it belongs to, so it nests under
+    /// the right call even when later calls are already on screen. A result
+    /// with no matching call goes at the end.
+    fn push_tool_result(&mut self, tool_call_id: &str, is_error: bool, content: String) {
+        let Some(idx) = self.tool_call_index(tool_call_id) else {
+            self.push_message(ChatMessageKind::ToolResult { is_error }, content);
+            return;
+        };
+        let mut at = idx + 1;
+        while at < self.messages.len()
+            && matches!(self.messages[at].kind, ChatMessageKind::ToolResult { .. })
+        {
+            at += 1;
+        }
+        self.messages.insert(
+            at,
+            ChatMessage::new(ChatMessageKind::ToolResult { is_error }, content),
+        );
+        self.rebuild_chat_content();
+    }
+
+    /// Send `--prompt`, if there is one, exactly as if typed and sent with
+    /// Enter; on a resumed session it continues the replayed history.
+    fn send_initial_prompt(&mut self) -> Command<Msg> {
+        match self.initial_prompt.take() {
+            Some(prompt) => self.submit(prompt),
+            None => Command::none(),
+        }
+    }
+
+    /// Submit text as if typed and sent: run a slash command, queue it behind
+    /// the running turn, or send it now. Clears the input.
+    fn submit(&mut self, text: String) -> Command<Msg> {
+        if text.trim().is_empty() {
+            return Command::none();
+        }
+        // Whatever it shows, the user wants to see it.
+        self.scroll_to_latest();
+        if let Some(cmd) = self.handle_slash_command(text.trim()) {
+            self.input.set_value("");
+            return cmd;
+        }
+        if self.streaming {
+            self.queued_message = Some(text);
+            self.input.set_value("");
+            Command::none()
+        } else {
+            self.push_message(ChatMessageKind::User, text.clone());
+            self.streaming = true;
+            self.show_thinking();
+            self.input.set_value("");
+            self.send_message(text)
+        }
+    }
+
+    /// Insert the snippet bound to `key` at the cursor, or send it directly
+    /// when it's marked `submit`. A `{clipboard}` snippet waits for the
+    /// clipboard to be read first.
+    fn apply_snippet(&mut self, key: KeyEvent) -> Command<Msg> {
+        let Some(snippet) = snippets::snippet_for_key(&self.snippets, &key).cloned() else {
+            return Command::none();
+        };
+        if snippets::uses_clipboard(&snippet.text) {
+            return Command::perform(
+                async move { (snippet, snippets::read_clipboard().await) },
+                |(snippet, clipboard)| Msg::SnippetClipboard(snippet, clipboard),
+            );
+        }
+        self.fill_snippet(&snippet, None)
+    }
+
+    /// Expand `snippet` against the draft and `clipboard`, then insert or
+    /// send it. A `{selection}` snippet replaces the draft.
+    fn fill_snippet(&mut self, snippet: &SnippetConfig, clipboard: Option<&str>) -> Command<Msg> {
+        let draft = self.input.value();
+        let text = snippets::expand(&snippet.text, &draft, clipboard);
+        if snippet.submit {
+            return self.submit(text);
+        }
+        if snippets::uses_selection(&snippet.text) {
+            self.input.set_value(&text);
+            return Command::none();
+        }
+        self.input
+            .update(text_area::Message::Paste(text))
+            .map(Msg::Input)
+    }
+
+    fn send_message(&mut self, text: String) -> Command<Msg> {
+        self.tally.start_turn();
+        let tx = self.user_tx.clone();
+        Command::perform(
+            async move {
+                let _ = tx.send(UserEvent::Message(text)).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// Handle a slash command. Returns None when the text isn't one, so it
+    /// is sent to the agent as a normal message; an unknown command is
+    /// reported instead of being sent.
+    fn handle_slash_command(&mut self, text: &str) -> Option<Command<Msg>> {
+        let (name, args) = commands::parse(text)?;
+        let registry = self.commands.clone();
+        let Some(command) = registry.resolve(name) else {
+            self.push_message(
+                ChatMessageKind::System,
+                format!("Unknown command /{} \u{2014} /help lists them", name),
+            );
+            return Some(Command::none());
+        };
+        if command.idle_only() && self.streaming {
+            self.push_message(
+                ChatMessageKind::System,
+                format!("Can't {} while a turn is running", command.name()),
+            );
+            return Some(Command::none());
         }
+        Some(match command.execute(args) {
+            Ok(SlashAction::Local(action)) => self.run_local_action(action),
+            Ok(SlashAction::Send(event)) => self.send_user_event(event),
+            Err(message) => {
+                self.push_message(ChatMessageKind::System, message);
+                Command::none()
+            }
+        })
+    }
+
+    /// Do what a slash command asked of the TUI.
+    fn run_local_action(&mut self, action: LocalAction) -> Command<Msg> {
+        match action {
+            LocalAction::Help => {
+                let help = self.commands.help();
+                self.push_message(ChatMessageKind::System, help);
+            }
+            LocalAction::Tools => {
+                let listing = format_tool_list(&self.tools);
+                self.push_message(ChatMessageKind::System, listing);
+            }
+            LocalAction::Info => {
+                let info = crate::session::workspace_info(Path::new(&self.workspace_dir));
+                self.push_message(ChatMessageKind::System, info.describe());
+            }
+            LocalAction::Skills => {
+                let listing = format_skill_list(&self.skills);
+                self.push_message(ChatMessageKind::System, listing);
+            }
+            LocalAction::Approvals => match &self.engine {
+                Some(engine) => {
+                    self.allowlist_editor = Some(AllowlistEditor::new(engine.allowlist_rows()));
+                }
+                None => self.push_message(
+                    ChatMessageKind::System,
+                    "Approvals are not available in this session".to_string(),
+                ),
+            },
+            LocalAction::Observe => return self.toggle_observe(),
+            LocalAction::Quiet(arg) => self.set_tool_verbosity(&arg),
+            LocalAction::Copy => self.copy_last_reply(),
+            LocalAction::Effort(arg) => return self.set_effort(&arg),
+            LocalAction::Edit(path) => return self.open_in_editor(&path, None),
+            LocalAction::Forget(arg) => return self.forget(&arg),
+            LocalAction::Regenerate(hint) => return self.regenerate(&hint),
+            LocalAction::Changes => self.list_changes(),
+            LocalAction::Revert(path) => self.confirm_revert(&path),
+        }
+        Command::none()
+    }
+
+    /// Send `event` to the agent loop.
+    fn send_user_event(&self, event: UserEvent) -> Command<Msg> {
+        let tx = self.user_tx.clone();
+        Command::perform(
+            async move {
+                let _ = tx.send(event).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// The completion popup's rows, and how many more commands matched,
+    /// while a command name is being typed with no prompt in the way.
+    fn completion_popup(&self) -> Option<(Vec<CompletionItem>, usize)> {
+        if self.pending_approval.is_some()
+            || self.pending_question.is_some()
+            || self.pending_quit.is_some()
+            || self.pending_paste.is_some()
+            || self.pending_consent.is_some()
+            || self.allowlist_editor.is_some()
+        {
+            return None;
+        }
+        let input = self.input.value();
+        if self.dismissed_completion.as_deref() == Some(input.as_str()) {
+            return None;
+        }
+        let matches = self.commands.completions(commands::typed_prefix(&input)?);
+        if matches.is_empty() {
+            return None;
+        }
+        let more = matches.len().saturating_sub(MAX_COMPLETIONS);
+        let items = matches
+            .iter()
+            .take(MAX_COMPLETIONS)
+            .map(|command| CompletionItem {
+                usage: commands::signature(*command),
+                help: command.help().to_string(),
+            })
+            .collect();
+        Some((items, more))
+    }
+
+    /// Complete the command name being typed as far as the matches agree.
+    fn complete_command(&mut self) {
+        let input = self.input.value();
+        if let Some(prefix) = commands::typed_prefix(&input)
+            && let Some(completed) = self.commands.complete(prefix)
+        {
+            self.input.set_value(&format!("/{}", completed));
+        }
+    }
+
+    /// Open `path`, relative to the workspace, in the user's editor. Paths
+    /// that resolve outside the workspace are refused. The returned command
+    /// runs the editor on a blocking thread once the terminal event reader
+    /// has stopped, and answers with `Msg::EditorClosed`.
+    fn open_in_editor(
+        &mut self,
+        path: &str,
+        responder: Option<oneshot::Sender<Result<String, String>>>,
+    ) -> Command<Msg> {
+        let file = if path.trim().is_empty() {
+            Err(anyhow::anyhow!("no path given"))
+        } else {
+            editor::resolve_in_workspace(Path::new(&self.workspace_dir), path)
+        };
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                self.finish_edit(path, Err(format!("{:#}", e)), responder);
+                return Command::none();
+            }
+        };
+        self.editing = Some(EditSession {
+            path: path.to_string(),
+            responder,
+        });
+        let edit_file = self.edit_file;
+        Command::perform(
+            async move {
+                // Let the runtime drop the terminal event subscription first.
+                tokio::time::sleep(REPAINT_DELAY).await;
+                match tokio::task::spawn_blocking(move || edit_file(&file)).await {
+                    Ok(outcome) => outcome.map_err(|e| format!("{:#}", e)),
+                    Err(e) => Err(format!("the editor task failed: {}", e)),
+                }
+            },
+            Msg::EditorClosed,
+        )
+    }
+
+    /// Report how editing `path` went, in the chat and to the agent loop
+    /// when it asked.
+    fn finish_edit(
+        &mut self,
+        path: &str,
+        outcome: Result<String, String>,
+        responder: Option<oneshot::Sender<Result<String, String>>>,
+    ) {
+        let note = match &outcome {
+            Ok(contents) => format!(
+                "\u{270f}\u{fe0f} Edited {} ({} lines)",
+                path,
+                contents.lines().count()
+            ),
+            Err(e) => format!("Couldn't edit {}: {}", path, e),
+        };
+        self.push_message(ChatMessageKind::System, note);
+        if let Some(responder) = responder {
+            let _ = responder.send(outcome);
+        }
+    }
+
+    /// Put the last assistant reply on the clipboard.
+    fn copy_last_reply(&mut self) {
+        let Some(reply) = self
+            .messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m.kind, ChatMessageKind::Assistant) && !is_thinking(m))
+            .map(|m| m.content.clone())
+        else {
+            self.push_message(ChatMessageKind::System, "Nothing to copy yet".to_string());
+            return;
+        };
+        self.copy_to_clipboard(reply, "the last reply".to_string());
+    }
+
+    /// Put `text` on the clipboard, asking first when `[privacy]
+    /// allow_osc52` is off and it hasn't been allowed yet. `what` names it
+    /// in the note once copied.
+    fn copy_to_clipboard(&mut self, text: String, what: String) {
+        match self.consents.gate(Feature::Osc52, &self.privacy) {
+            Gate::Allowed => self.use_feature(Feature::Osc52, &text, &what),
+            Gate::Ask => {
+                self.pending_consent = Some(PendingConsent {
+                    feature: Feature::Osc52,
+                    payload: text,
+                    what,
+                    selected: 0,
+                });
+            }
+            Gate::Refused => self.push_message(
+                ChatMessageKind::System,
+                format!(
+                    "Clipboard writes were declined for this session; set [privacy] {} = true to allow them",
+                    Feature::Osc52.setting()
+                ),
+            ),
+        }
+    }
+
+    /// The files the agent changed this session.
+    fn list_changes(&mut self) {
+        let listing = match &self.changes {
+            Some(journal) => crate::session::format_changes(
+                &journal.lock().unwrap().files(),
+                Path::new(&self.workspace_dir),
+            ),
+            None => "Change tracking is not available in this session".to_string(),
+        };
+        self.push_message(ChatMessageKind::System, listing);
+    }
+
+    /// Ask before putting back what the agent's latest write to `path`
+    /// replaced; refused up front when it can't be reverted.
+    fn confirm_revert(&mut self, path: &str) {
+        let Some(journal) = &self.changes else {
+            self.push_message(
+                ChatMessageKind::System,
+                "Change tracking is not available in this session".to_string(),
+            );
+            return;
+        };
+        let path = normalize_path(&Path::new(&self.workspace_dir).join(path));
+        let question = match journal.lock().unwrap().revertible(&path) {
+            Ok(change) if change.before_hash.is_none() => {
+                format!("Delete {}, which the agent created?", path.display())
+            }
+            Ok(change) => format!(
+                "Restore {} to before the agent's write at {}?",
+                path.display(),
+                change.at
+            ),
+            Err(e) => {
+                self.push_message(ChatMessageKind::System, format!("Can't revert: {}", e));
+                return;
+            }
+        };
+        self.pending_question = Some(PendingQuestion {
+            question,
+            tool_call_id: String::new(),
+            options: Vec::new(),
+            style: QuestionStyle::Confirm { default_yes: false },
+            selected: 0,
+            responder: None,
+        });
+        self.pending_revert = Some(path);
+    }
+
+    /// Revert `path` once confirmed.
+    fn resolve_revert(&mut self, path: &Path, confirmed: bool) {
+        let note = if !confirmed {
+            "Revert cancelled.".to_string()
+        } else {
+            let reverted = match &self.changes {
+                Some(journal) => journal.lock().unwrap().revert(path),
+                None => return,
+            };
+            match reverted {
+                Ok(done) => format!("\u{21a9}\u{fe0f} {}", done),
+                Err(e) => format!("Can't revert: {}", e),
+            }
+        };
+        self.push_message(ChatMessageKind::System, note);
+    }
+
+    /// Run an allowed feature on `payload`.
+    fn use_feature(&mut self, feature: Feature, payload: &str, what: &str) {
+        let note = match feature {
+            Feature::Osc52 => match (self.copy_text)(payload) {
+                Ok(()) => format!(
+                    "\u{1f4cb} Copied {} ({} chars)",
+                    what,
+                    payload.chars().count()
+                ),
+                Err(e) => format!("Couldn't copy to the clipboard: {}", e),
+            },
+            // Nothing sends notifications yet; the consent is only recorded.
+            Feature::Notifications => return,
+        };
+        self.push_message(ChatMessageKind::System, note);
+    }
+
+    /// Handle key events while a consent prompt is shown: the approval keys,
+    /// with Esc answering no.
+    fn handle_consent_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        match key.code {
+            KeyCode::Left => {
+                if let Some(ref mut pending) = self.pending_consent {
+                    pending.selected = pending.selected.saturating_sub(1);
+                }
+            }
+            KeyCode::Right => {
+                if let Some(ref mut pending) = self.pending_consent {
+                    pending.selected = (pending.selected + 1).min(2);
+                }
+            }
+            KeyCode::Char('1') => self.resolve_consent(0),
+            KeyCode::Char('2') => self.resolve_consent(1),
+            KeyCode::Char('3') | KeyCode::Esc => self.resolve_consent(2),
+            KeyCode::Enter => {
+                let selected = self.pending_consent.as_ref().map_or(2, |p| p.selected);
+                self.resolve_consent(selected);
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    /// Record the consent answer and, when allowed, do what was asked.
+    fn resolve_consent(&mut self, selected: usize) {
+        let Some(pending) = self.pending_consent.take() else {
+            return;
+        };
+        let allowed = match self
+            .consents
+            .answer(pending.feature, ConsentAnswer::from_index(selected))
+        {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                self.push_message(
+                    ChatMessageKind::System,
+                    format!("\u{26a0}\u{fe0f} Failed to remember the answer: {}", e),
+                );
+                true
+            }
+        };
+        if allowed {
+            self.use_feature(pending.feature, &pending.payload, &pending.what);
+        }
+    }
+
+    /// Turn observe mode on or off from the next turn on.
+    fn toggle_observe(&mut self) -> Command<Msg> {
+        if self.streaming {
+            self.push_message(
+                ChatMessageKind::System,
+                "Can't toggle observe mode while a turn is running".to_string(),
+            );
+            return Command::none();
+        }
+        self.observe = !self.observe;
+        let tx = self.user_tx.clone();
+        let enabled = self.observe;
+        Command::perform(
+            async move {
+                let _ = tx.send(UserEvent::SetObserve(enabled)).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// Show tool output in full, or at the quiet verbosity when it already is.
+    fn toggle_tool_output(&mut self) {
+        self.chat_options.tool_verbosity = match self.chat_options.tool_verbosity {
+            ToolVerbosity::Full => self.quiet_verbosity,
+            _ => ToolVerbosity::Full,
+        };
+        self.rebuild_chat_content();
+    }
+
+    /// `/quiet [full|compact|hidden]`: toggle, or switch to the level named.
+    /// Only the rendering changes, so switching back shows everything again.
+    fn set_tool_verbosity(&mut self, arg: &str) {
+        if arg.is_empty() {
+            self.toggle_tool_output();
+        } else {
+            let Some(verbosity) = ToolVerbosity::parse(arg) else {
+                self.push_message(
+                    ChatMessageKind::System,
+                    "Usage: /quiet [full|compact|hidden]".to_string(),
+                );
+                return;
+            };
+            if verbosity != ToolVerbosity::Full {
+                self.quiet_verbosity = verbosity;
+            }
+            self.chat_options.tool_verbosity = verbosity;
+        }
+        self.push_message(
+            ChatMessageKind::System,
+            format!("Tool output: {}", self.chat_options.tool_verbosity.as_str()),
+        );
+    }
+
+    /// Ask the agent loop to use a new reasoning effort from the next turn on.
+    fn set_effort(&mut self, arg: &str) -> Command<Msg> {
+        if self.streaming {
+            self.push_message(
+                ChatMessageKind::System,
+                "Can't change effort while a turn is running".to_string(),
+            );
+            return Command::none();
+        }
+        let effort = match arg {
+            "off" => None,
+            other => match Effort::parse(other) {
+                Some(effort) => Some(effort),
+                None => {
+                    self.push_message(
+                        ChatMessageKind::System,
+                        "Usage: /effort low|medium|high|off".to_string(),
+                    );
+                    return Command::none();
+                }
+            },
+        };
+        let tx = self.user_tx.clone();
+        Command::perform(
+            async move {
+                let _ = tx.send(UserEvent::SetEffort(effort)).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// Ask the agent loop to redact one of the user's messages from history,
+    /// `session.json`, and the log; the chat blanks its copy once the agent
+    /// says which one it was. `arg` counts back from the last message typed;
+    /// empty means the last one.
+    fn forget(&mut self, arg: &str) -> Command<Msg> {
+        if self.streaming {
+            self.push_message(
+                ChatMessageKind::System,
+                "Can't forget while a turn is running".to_string(),
+            );
+            return Command::none();
+        }
+        let nth = match arg {
+            "" => 1,
+            n => match n.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    self.push_message(
+                        ChatMessageKind::System,
+                        "Usage: /forget [n] (n counts back from your last message)".to_string(),
+                    );
+                    return Command::none();
+                }
+            },
+        };
+        let tx = self.user_tx.clone();
+        Command::perform(
+            async move {
+                let _ = tx.send(UserEvent::Forget { nth }).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// Drop the last answer from the chat and ask the agent for a new one.
+    fn regenerate(&mut self, hint: &str) -> Command<Msg> {
+        if self.streaming {
+            self.push_message(
+                ChatMessageKind::System,
+                "Can't regenerate while a turn is running".to_string(),
+            );
+            return Command::none();
+        }
+        let last_user = self
+            .messages
+            .iter()
+            .rposition(|m| m.kind == ChatMessageKind::User && !m.display_only);
+        // Notes shown since (an error, /help) aren't an answer, and stay.
+        let Some(last_user) =
+            last_user.filter(|&i| conversation(&self.messages[i + 1..]).next().is_some())
+        else {
+            self.push_message(ChatMessageKind::System, "Nothing to regenerate".to_string());
+            return Command::none();
+        };
+        let mut index = 0;
+        self.messages.retain(|m| {
+            index += 1;
+            index <= last_user + 1 || m.display_only
+        });
+        self.push_message(
+            ChatMessageKind::System,
+            "\u{267b}\u{fe0f} regenerating\u{2026}".to_string(),
+        );
+        self.streaming = true;
+        self.tally.start_turn();
+        let hint = (!hint.is_empty()).then(|| hint.to_string());
+        let tx = self.user_tx.clone();
+        Command::perform(
+            async move {
+                let _ = tx.send(UserEvent::Regenerate { hint }).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// Ask the agent loop to abort the turn in progress.
+    fn cancel_turn(&mut self) -> Command<Msg> {
+        self.push_message(
+            ChatMessageKind::System,
+            "Cancelling... (Ctrl+C again to quit)".to_string(),
+        );
+        let tx = self.user_tx.clone();
+        Command::perform(
+            async move {
+                let _ = tx.send(UserEvent::Cancel).await;
+            },
+            |_| Msg::MessageSent,
+        )
+    }
+
+    /// Handle key events while the quit confirmation is shown. Ctrl+C and Esc
+    /// dismiss it, so repeated Ctrl+C presses can't quit by accident.
+    fn handle_quit_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        match key.code {
+            KeyCode::Left => {
+                if let Some(ref mut quit) = self.pending_quit {
+                    quit.selected = quit.selected.saturating_sub(1);
+                }
+                Command::none()
+            }
+            KeyCode::Right => {
+                if let Some(ref mut quit) = self.pending_quit {
+                    quit.selected = (quit.selected + 1).min(2);
+                }
+                Command::none()
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.resolve_quit(2)
+            }
+            KeyCode::Char('y' | 'Y') => self.resolve_quit(0),
+            KeyCode::Char('s' | 'S') => self.resolve_quit(1),
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => self.resolve_quit(2),
+            KeyCode::Enter => {
+                let selected = self.pending_quit.as_ref().map_or(2, |q| q.selected);
+                self.resolve_quit(selected)
+            }
+            _ => Command::none(),
+        }
+    }
+
+    /// Quit, telling the agent loop first: approval prompts the closing TUI
+    /// drops must read as the session ending, not as the user's answer.
+    fn quit(&self) -> Command<Msg> {
+        self.shutdown.request();
+        keyboard::disable();
+        Command::quit()
+    }
+
+    /// Act on a quit confirmation choice: quit now, quit once the running
+    /// turn is done, or go back to the session.
+    fn resolve_quit(&mut self, selected: usize) -> Command<Msg> {
+        self.pending_quit = None;
+        self.last_ctrl_c = None;
+        match selected {
+            0 => self.quit(),
+            // The turn may have ended while the prompt was open.
+            1 if !self.streaming => self.quit(),
+            1 => {
+                self.quit_after_turn = true;
+                self.push_message(
+                    ChatMessageKind::System,
+                    "Will quit when this turn finishes".to_string(),
+                );
+                Command::none()
+            }
+            _ => Command::none(),
+        }
+    }
+
+    /// Apply a word move, kill, or yank to the input. The text area only
+    /// moves and deletes a character at a time, so moves and kills are
+    /// replayed as that many arrow, Backspace, or Delete presses.
+    fn apply_edit(&mut self, command: EditCommand) -> Command<Msg> {
+        let text = self.input.value();
+        let cursor = editing::cursor_index(&text, self.input.cursor_row(), self.input.cursor_col());
+        match command {
+            EditCommand::WordLeft => {
+                let target = editing::prev_word_boundary(&text, cursor);
+                self.press_input_key(KeyCode::Left, cursor - target);
+            }
+            EditCommand::WordRight => {
+                let target = editing::next_word_boundary(&text, cursor);
+                self.press_input_key(KeyCode::Right, target - cursor);
+            }
+            EditCommand::Yank => {
+                if !self.kill_buffer.is_empty() {
+                    return self.insert_paste(self.kill_buffer.clone());
+                }
+            }
+            kill => {
+                let (start, end) = editing::kill_range(&text, cursor, kill);
+                if start < end {
+                    self.kill_buffer = editing::char_slice(&text, start, end);
+                    self.press_input_key(KeyCode::Backspace, cursor - start);
+                    self.press_input_key(KeyCode::Delete, end - cursor);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn press_input_key(&mut self, code: KeyCode, times: usize) {
+        for _ in 0..times {
+            let _ = self
+                .input
+                .update(text_area::Message::KeyPress(KeyEvent::new(
+                    code,
+                    KeyModifiers::NONE,
+                )));
+        }
+    }
+
+    /// Insert (sanitized) pasted text into the input at the cursor.
+    fn insert_paste(&mut self, text: String) -> Command<Msg> {
+        self.input
+            .update(text_area::Message::Paste(text))
+            .map(Msg::Input)
+    }
+
+    fn paste_prompt(&self, pending: &PendingPaste) -> Vec<Line<'static>> {
+        paste_prompt_lines(
+            pending.text.len(),
+            pending.text.lines().count(),
+            self.paste_insert_lines,
+            pending.selected,
+        )
+    }
+
+    /// Handle key events while the large paste prompt is shown.
+    fn handle_paste_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        match key.code {
+            KeyCode::Left => {
+                if let Some(ref mut pending) = self.pending_paste {
+                    pending.selected = pending.selected.saturating_sub(1);
+                }
+                Command::none()
+            }
+            KeyCode::Right => {
+                if let Some(ref mut pending) = self.pending_paste {
+                    pending.selected = (pending.selected + 1).min(2);
+                }
+                Command::none()
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.resolve_paste(2)
+            }
+            KeyCode::Char('a' | 'A') => self.resolve_paste(0),
+            KeyCode::Char('f' | 'F') => self.resolve_paste(1),
+            KeyCode::Char('c' | 'C') | KeyCode::Esc => self.resolve_paste(2),
+            KeyCode::Enter => {
+                let selected = self.pending_paste.as_ref().map_or(2, |p| p.selected);
+                self.resolve_paste(selected)
+            }
+            _ => Command::none(),
+        }
+    }
+
+    /// Act on a large paste choice: insert it as a truncated attachment block,
+    /// insert its first lines, or drop it.
+    fn resolve_paste(&mut self, selected: usize) -> Command<Msg> {
+        let Some(pending) = self.pending_paste.take() else {
+            return Command::none();
+        };
+        match selected {
+            0 => self.insert_paste(paste::attachment_block(&pending.text, self.max_paste_bytes)),
+            1 => self.insert_paste(paste::first_lines(&pending.text, self.paste_insert_lines)),
+            _ => Command::none(),
+        }
+    }
+
+    /// Handle key events while the /approvals editor is open, persisting
+    /// confirmed deletions through the approval engine.
+    fn handle_allowlist_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        let Some(editor) = self.allowlist_editor.as_mut() else {
+            return Command::none();
+        };
+        match editor.handle_key(key) {
+            EditorAction::None => {}
+            EditorAction::Close => self.allowlist_editor = None,
+            EditorAction::Delete { tool, pattern } => {
+                let Some(engine) = &self.engine else {
+                    return Command::none();
+                };
+                match engine.remove_allowlist_entry(&tool, &pattern) {
+                    Ok(_) => {
+                        editor.remove(&tool, &pattern);
+                        self.push_message(
+                            ChatMessageKind::System,
+                            format!("Removed {} allowlist entry '{}'", tool, pattern),
+                        );
+                    }
+                    Err(e) => self.push_message(
+                        ChatMessageKind::System,
+                        format!("\u{26a0}\u{fe0f} Failed to save approvals: {}", e),
+                    ),
+                }
+            }
+        }
+        Command::none()
+    }
+
+    /// Handle key events while a tool approval prompt is active.
+    fn handle_approval_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        if self
+            .pending_approval
+            .as_ref()
+            .is_some_and(|a| a.scope_selected.is_some())
+        {
+            return self.handle_scope_key(key);
+        }
+        match key.code {
+            KeyCode::Left => {
+                if let Some(ref mut approval) = self.pending_approval {
+                    approval.selected = approval.selected.saturating_sub(1);
+                }
+                Command::none()
+            }
+            KeyCode::Right => {
+                if let Some(ref mut approval) = self.pending_approval {
+                    approval.selected = (approval.selected + 1).min(APPROVAL_OPTIONS.len() - 1);
+                }
+                Command::none()
+            }
+            KeyCode::Char('1') => self.resolve_approval(0),
+            KeyCode::Char('2') => self.resolve_approval(1),
+            KeyCode::Char('3') => self.resolve_approval(2),
+            KeyCode::Char('4') => self.resolve_approval(3),
+            KeyCode::Enter => {
+                let selected = self.pending_approval.as_ref().map_or(0, |a| a.selected);
+                self.resolve_approval(selected)
+            }
+            _ => Command::none(),
+        }
+    }
+
+    /// Resolve the pending approval by mapping the selected index to a decision
+    /// and sending it via the oneshot channel.
+    fn resolve_approval(&mut self, selected: usize) -> Command<Msg> {
+        // "Always Allow" on a file write first asks how far it should reach.
+        if selected == 1
+            && let Some(approval) = self.pending_approval.as_mut()
+            && !approval.scopes.is_empty()
+        {
+            approval.scope_selected = Some(0);
+            return Command::none();
+        }
+        if let Some(mut approval) = self.pending_approval.take() {
+            let decision = match selected {
+                0 => ApprovalDecision::AllowOnce,
+                1 => ApprovalDecision::AllowAlways,
+                3 => ApprovalDecision::AllowTool,
+                _ => ApprovalDecision::Deny,
+            };
+            if let Some(responder) = approval.responder.take() {
+                let _ = responder.send(decision);
+            }
+        }
+        Command::none()
+    }
+
+    /// Handle keys in the write scope chooser: pick a scope, or "anywhere"
+    /// (the last option) for the whole tool; Esc goes back to the main options.
+    fn handle_scope_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        let Some(approval) = self.pending_approval.as_mut() else {
+            return Command::none();
+        };
+        let count = approval.scopes.len() + 1;
+        let current = approval.scope_selected.unwrap_or(0);
+        let choice = match key.code {
+            KeyCode::Left => {
+                approval.scope_selected = Some(current.saturating_sub(1));
+                return Command::none();
+            }
+            KeyCode::Right => {
+                approval.scope_selected = Some((current + 1).min(count - 1));
+                return Command::none();
+            }
+            KeyCode::Esc => {
+                approval.scope_selected = None;
+                return Command::none();
+            }
+            KeyCode::Char(c) => match c.to_digit(10) {
+                Some(n) if (1..=count as u32).contains(&n) => n as usize - 1,
+                _ => return Command::none(),
+            },
+            KeyCode::Enter => current,
+            _ => return Command::none(),
+        };
+        if let Some(mut approval) = self.pending_approval.take() {
+            let decision = if choice < approval.scopes.len() {
+                ApprovalDecision::AllowScope(choice)
+            } else {
+                ApprovalDecision::AllowAlways
+            };
+            if let Some(responder) = approval.responder.take() {
+                let _ = responder.send(decision);
+            }
+        }
+        Command::none()
+    }
+
+    /// Handle key events while a question prompt is active.
+    /// Dispatches to confirm, multichoice, or free-text handling by the
+    /// question's style and whether options exist.
+    fn handle_question_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        let style = self.pending_question.as_ref().map(|q| q.style);
+        if let Some(QuestionStyle::Confirm { default_yes }) = style {
+            return self.handle_confirm_key(key, default_yes);
+        }
+
+        let has_options = self
+            .pending_question
+            .as_ref()
+            .is_some_and(|q| !q.options.is_empty());
+
+        if has_options {
+            return self.handle_multichoice_key(key);
+        }
+
+        // Free-text question mode: same editing keys as the main input.
+        if let Some(command) = EditCommand::from_key(&key) {
+            return self.apply_edit(command);
+        }
+        match key.code {
+            KeyCode::Enter if is_newline_key(&key, self.enter_submits) => {
+                self.confirm_empty_answer = false;
+                self.input
+                    .update(text_area::Message::KeyPress(KeyEvent::new(
+                        KeyCode::Enter,
+                        KeyModifiers::SHIFT,
+                    )))
+                    .map(Msg::Input)
+            }
+            _ if key.code == KeyCode::Enter || is_send_fallback(&key) => {
+                let text = self.input.value();
+                // An accidental Enter on an empty draft shouldn't send a blank
+                // answer; require a second Enter to confirm.
+                if text.trim().is_empty() && !self.confirm_empty_answer {
+                    self.confirm_empty_answer = true;
+                    return Command::none();
+                }
+                self.input.set_value("");
+                self.resolve_question(text);
+                Command::none()
+            }
+            KeyCode::Esc => {
+                self.resolve_question(DECLINED_ANSWER.to_string());
+                Command::none()
+            }
+            _ => {
+                self.confirm_empty_answer = false;
+                self.input
+                    .update(text_area::Message::KeyPress(key))
+                    .map(Msg::Input)
+            }
+        }
+    }
+
+    /// Handle key events for a yes/no confirmation: y and n answer, Enter
+    /// gives the default, Esc is a no. Other keys are ignored.
+    fn handle_confirm_key(&mut self, key: KeyEvent, default_yes: bool) -> Command<Msg> {
+        let yes = match key.code {
+            KeyCode::Char('y' | 'Y') => true,
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => false,
+            KeyCode::Enter => default_yes,
+            _ => return Command::none(),
+        };
+        let answer = if yes { CONFIRM_YES } else { CONFIRM_NO };
+        self.resolve_question(answer.to_string());
+        Command::none()
+    }
+
+    /// Handle key events for multiple-choice question mode.
+    fn handle_multichoice_key(&mut self, key: KeyEvent) -> Command<Msg> {
+        match key.code {
+            KeyCode::Left => {
+                if let Some(ref mut q) = self.pending_question {
+                    q.selected = q.selected.saturating_sub(1);
+                }
+                Command::none()
+            }
+            KeyCode::Right => {
+                if let Some(ref mut q) = self.pending_question {
+                    let max = q.options.len().saturating_sub(1);
+                    q.selected = (q.selected + 1).min(max);
+                }
+                Command::none()
+            }
+            KeyCode::Enter => {
+                let answer = self
+                    .pending_question
+                    .as_ref()
+                    .and_then(|q| q.options.get(q.selected).cloned())
+                    .unwrap_or_default();
+                self.resolve_question(answer);
+                Command::none()
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() && c != '0' => {
+                let idx = (c as usize) - ('1' as usize);
+                let option_count = self
+                    .pending_question
+                    .as_ref()
+                    .map_or(0, |q| q.options.len());
+                if idx < option_count {
+                    if let Some(ref mut q) = self.pending_question {
+                        q.selected = idx;
+                    }
+                    let answer = self
+                        .pending_question
+                        .as_ref()
+                        .and_then(|q| q.options.get(q.selected).cloned())
+                        .unwrap_or_default();
+                    self.resolve_question(answer);
+                }
+                Command::none()
+            }
+            KeyCode::Esc => {
+                self.resolve_question(DECLINED_ANSWER.to_string());
+                Command::none()
+            }
+            _ => Command::none(),
+        }
+    }
+
+    /// Resolve the pending question by sending the answer via the oneshot channel.
+    fn resolve_question(&mut self, answer: String) {
+        self.confirm_empty_answer = false;
+        if self.summary_review.is_some() {
+            self.resolve_summary_review(answer);
+            return;
+        }
+        if self.onboarding.is_some() {
+            self.resolve_onboarding(answer);
+            return;
+        }
+        if let Some(path) = self.pending_revert.take() {
+            self.pending_question = None;
+            self.resolve_revert(&path, answer == CONFIRM_YES);
+            return;
+        }
+        if let Some(mut question) = self.pending_question.take()
+            && let Some(responder) = question.responder.take()
+        {
+            let _ = responder.send(answer);
+        }
+    }
+
+    /// Act on an answer to the compaction summary prompt. "edit" puts the
+    /// summary in the input and reopens the prompt as free text; the text
+    /// submitted from there becomes the summary. Esc or an emptied summary
+    /// skips compaction.
+    fn resolve_summary_review(&mut self, answer: String) {
+        let Some(mut review) = self.summary_review.take() else {
+            return;
+        };
+        self.pending_question = None;
+        let decision = if review.editing {
+            self.input.set_value("");
+            if answer == DECLINED_ANSWER || answer.trim().is_empty() {
+                SummaryDecision::Skip
+            } else {
+                SummaryDecision::Edit(answer)
+            }
+        } else if answer == SUMMARY_REVIEW_OPTIONS[0] {
+            SummaryDecision::Accept
+        } else if answer == SUMMARY_REVIEW_OPTIONS[1] {
+            self.input.set_value(&review.summary);
+            self.pending_question = Some(PendingQuestion {
+                question: format!(
+                    "Edit the summary, then press {} to use it (Esc skips compaction)",
+                    enter_keys(self.enter_submits).0
+                ),
+                tool_call_id: String::new(),
+                options: Vec::new(),
+                style: QuestionStyle::Open,
+                selected: 0,
+                responder: None,
+            });
+            review.editing = true;
+            self.summary_review = Some(review);
+            return;
+        } else {
+            SummaryDecision::Skip
+        };
+        let _ = review.responder.send(decision);
+    }
+
+    /// Act on an answer in the first-run walkthrough: a security level
+    /// applies to this session and leads to the question of saving it. Esc
+    /// at either question ends the walkthrough; it's marked done either way.
+    fn resolve_onboarding(&mut self, answer: String) {
+        let Some(mut onboarding) = self.onboarding.take() else {
+            return;
+        };
+        self.pending_question = None;
+        let note = match onboarding.step {
+            OnboardingStep::Security => match SecurityLevel::parse(&answer) {
+                Some(level) => {
+                    if let Some(engine) = &self.engine {
+                        engine.set_default_security(level);
+                    }
+                    self.push_message(
+                        ChatMessageKind::System,
+                        format!("Security level set to {} for this session.", level.as_str()),
+                    );
+                    onboarding.step = OnboardingStep::Save(level);
+                    self.pending_question = Some(onboarding.question());
+                    self.onboarding = Some(onboarding);
+                    return;
+                }
+                None => "Walkthrough skipped.".to_string(),
+            },
+            OnboardingStep::Save(level) if answer == SAVE_OPTIONS[0] => {
+                match save_approval_security(&onboarding.config_path, level) {
+                    Ok(()) => format!(
+                        "Saved security = \"{}\" to {}.",
+                        level.as_str(),
+                        onboarding.config_path.display()
+                    ),
+                    Err(e) => format!("\u{26a0}\u{fe0f} Couldn't save the security level: {}", e),
+                }
+            }
+            OnboardingStep::Save(_) => {
+                "Kept for this session only; [approval] security in config.toml sets the default."
+                    .to_string()
+            }
+        };
+        let note = match onboarding::mark_onboarded(&onboarding.marker) {
+            Ok(()) => format!("{} Type a message to get started.", note),
+            Err(e) => format!(
+                "{}\n\u{26a0}\u{fe0f} Couldn't record that the walkthrough ran: {}",
+                note, e
+            ),
+        };
+        self.push_message(ChatMessageKind::System, note);
+    }
+}
+
+/// The approval prompt, or its write scope chooser once "Always Allow" was
+/// picked for a file write.
+fn approval_prompt_lines(approval: &PendingApproval) -> Vec<Line<'static>> {
+    match approval.scope_selected {
+        Some(selected) => scope_lines(&approval.description, &approval.scopes, selected),
+        None => approval_line(&approval.description, &approval.similar, approval.selected),
+    }
+}
+
+/// The question prompt: a yes/no confirmation, the options to pick from, or
+/// a free-text prompt.
+fn question_prompt_lines(question: &PendingQuestion, enter_submits: bool) -> Vec<Line<'static>> {
+    match question.style {
+        QuestionStyle::Confirm { default_yes } => confirm_lines(&question.question, default_yes),
+        QuestionStyle::Open if question.options.is_empty() => {
+            let (submit, newline) = enter_keys(enter_submits);
+            question_lines(&question.question, submit, newline)
+        }
+        QuestionStyle::Open => {
+            multichoice_lines(&question.question, &question.options, question.selected)
+        }
+    }
+}
+
+/// What to show instead of the layout when the terminal is below the minimum.
+fn too_small_lines(width: u16, height: u16) -> Vec<Line<'static>> {
+    vec![
+        Line::from(Span::styled(
+            "Too small",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!(
+            "Resize to at least {}x{} (now {}x{})",
+            MIN_WIDTH, MIN_HEIGHT, width, height
+        )),
+    ]
+}
+
+/// Whether `key` inserts a newline instead of submitting. By default
+/// Shift+Enter and Alt+Enter do; without `enter_submits` every Enter does
+/// except Ctrl+Enter.
+fn is_newline_key(key: &KeyEvent, enter_submits: bool) -> bool {
+    if key.code != KeyCode::Enter {
+        return false;
+    }
+    if enter_submits {
+        key.modifiers.contains(KeyModifiers::SHIFT) || key.modifiers.contains(KeyModifiers::ALT)
+    } else {
+        !key.modifiers.contains(KeyModifiers::CONTROL)
+    }
+}
+
+/// Whether `key` is Ctrl+S, which sends like the submitting Enter does, in
+/// case the terminal can't tell Ctrl+Enter from Enter.
+fn is_send_fallback(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('s') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// The keys that submit and that insert a newline, for hints.
+fn enter_keys(enter_submits: bool) -> (&'static str, &'static str) {
+    if enter_submits {
+        ("Enter", "Shift+Enter")
+    } else {
+        ("Ctrl+Enter", "Enter")
+    }
+}
+
+/// Scroll distance for one page of a viewport `height` rows tall, keeping
+/// `overlap` rows of context. Falls back to a fixed size before the first render.
+fn page_size(height: u16, overlap: u16) -> u16 {
+    if height == 0 {
+        return DEFAULT_PAGE_SIZE;
+    }
+    height.saturating_sub(overlap).max(1)
+}
+
+/// Calculate the input area height for a draft based on its visual line count
+/// (accounting for soft wrap at terminal width), clamped to MAX_INPUT_HEIGHT.
+/// The inner width is the frame width minus 2 for the left/right border cells.
+/// Measuring stops once the cap is reached, so giant drafts cost no more than
+/// small ones.
+fn input_height(value: &str, width: u16) -> u16 {
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    let max_rows = (MAX_INPUT_HEIGHT - 2) as usize;
+    // No line needs more characters measured than it takes to fill the input.
+    let max_chars = inner_width * max_rows;
+    let mut visual_lines = 0;
+    for line in value.split('\n') {
+        let end = line
+            .char_indices()
+            .nth(max_chars)
+            .map_or(line.len(), |(i, _)| i);
+        let w = unicode_width::UnicodeWidthStr::width(&line[..end]);
+        visual_lines += if w == 0 { 1 } else { w.div_ceil(inner_width) };
+        if visual_lines >= max_rows {
+            break;
+        }
+    }
+    // +2 accounts for top and bottom borders
+    (visual_lines.min(max_rows) as u16 + 2).clamp(3, MAX_INPUT_HEIGHT)
+}
+
+/// Calculate how many terminal rows a set of styled Lines will occupy when
+/// wrapped at the given width. Each Line's spans are measured by unicode
+/// display width and ceiling-divided by the available width.
+fn visual_line_height(lines: &[Line], width: u16) -> u16 {
+    let w = width.max(1) as usize;
+    lines
+        .iter()
+        .map(|line| {
+            let line_width: usize = line
+                .spans
+                .iter()
+                .map(|s| unicode_width::UnicodeWidthStr::width(s.content.as_ref()))
+                .sum();
+            if line_width == 0 {
+                1
+            } else {
+                ((line_width + w - 1) / w) as u16
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::widgets::chat::render_chat_lines;
+
+    fn test_flags() -> Flags {
+        let (user_tx, _user_rx) = mpsc::channel(16);
+        let (_agent_tx, agent_rx) = mpsc::channel(64);
+        Flags {
+            user_tx,
+            agent_rx,
+            model_name: "test-model".to_string(),
+            tool_count: 5,
+            context_window: 128_000,
+            compaction_limit: Some(115_200),
+            workspace_dir: "/tmp/test".to_string(),
+            replay_messages: vec![],
+            startup_message: "Test startup".to_string(),
+            tui_config: TuiConfig::default(),
+            tools: vec![],
+            snippets: vec![],
+            engine: None,
+            skills: vec![],
+            initial_prompt: None,
+            keyboard_enhancement: false,
+            onboarding: None,
+            observe: false,
+            privacy: PrivacyConfig::default(),
+            consents: Consents::default(),
+            changes: None,
+            shutdown: ShutdownFlag::default(),
+        }
+    }
+
+    #[test]
+    fn init_creates_valid_state() {
+        let flags = test_flags();
+        let (app, _cmd) = ClawApp::init(flags);
+
+        assert_eq!(app.model_name, "test-model");
+        assert_eq!(app.tool_count, 5);
+        assert_eq!(app.context_window, 128_000);
+        assert!(!app.streaming);
+        assert!(app.pending_approval.is_none());
+        assert!(app.pending_question.is_none());
+        // Startup message should be present
+        assert_eq!(app.messages.len(), 1);
+        assert_eq!(app.messages[0].kind, ChatMessageKind::System);
+        assert_eq!(app.messages[0].content, "Test startup");
+    }
+
+    #[test]
+    fn push_message_resets_scroll() {
+        let flags = test_flags();
+        let (mut app, _cmd) = ClawApp::init(flags);
+        app.push_message(ChatMessageKind::User, "hello".to_string());
+        // After push, viewport should be at bottom (auto-scroll)
+        assert!(app.chat_viewport.at_bottom());
+    }
+
+    #[test]
+    fn identical_system_messages_collapse_into_a_count() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let before = app.messages.len();
+        for _ in 0..3 {
+            app.push_message(
+                ChatMessageKind::System,
+                "\u{26a0}\u{fe0f} Error: rate limited".to_string(),
+            );
+        }
+        assert_eq!(app.messages.len(), before + 1);
+        assert_eq!(
+            app.messages.last().unwrap().content,
+            "\u{26a0}\u{fe0f} Error: rate limited (\u{d7}3)"
+        );
+    }
+
+    #[test]
+    fn a_different_message_breaks_the_run() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let before = app.messages.len();
+        app.push_message(ChatMessageKind::System, "retrying".to_string());
+        app.push_message(ChatMessageKind::System, "retrying".to_string());
+        app.push_message(ChatMessageKind::System, "retrying in 2s".to_string());
+        app.push_message(ChatMessageKind::System, "retrying".to_string());
+        app.push_message(ChatMessageKind::User, "retrying".to_string());
+        app.push_message(ChatMessageKind::User, "retrying".to_string());
+        let contents: Vec<&str> = app.messages[before..]
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                "retrying (\u{d7}2)",
+                "retrying in 2s",
+                "retrying",
+                "retrying",
+                "retrying"
+            ]
+        );
+    }
+
+    #[test]
+    fn append_to_last_assistant() {
+        let flags = test_flags();
+        let (mut app, _cmd) = ClawApp::init(flags);
+
+        app.push_message(ChatMessageKind::Assistant, "Hello".to_string());
+        app.append_to_last_assistant(" world");
+        // Should still be a single assistant message (plus the startup system message)
+        assert_eq!(app.messages.len(), 2);
+        assert_eq!(app.messages[1].content, "Hello world");
+    }
+
+    #[test]
+    fn streaming_leaves_a_scrolled_up_chat_alone_until_end() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.chat_height.store(5, Ordering::Relaxed);
+        for n in 0..20 {
+            app.push_message(ChatMessageKind::User, format!("message {}", n));
+        }
+        app.streaming = true;
+        app.append_to_last_assistant("partial");
+        assert!(app.chat_at_bottom());
+
+        app.chat_viewport.set_y_offset(0);
+        app.append_to_last_assistant(" reply");
+        app.append_to_last_assistant("\nmore\nlines");
+        assert_eq!(app.chat_viewport.y_offset(), 0);
+        assert!(!app.chat_at_bottom());
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::End, KeyModifiers::NONE)));
+        assert!(app.chat_at_bottom());
+        assert!(app.input.value().is_empty());
+        app.append_to_last_assistant("\nand more");
+        assert!(app.chat_at_bottom());
+    }
+
+    /// An app with more chat than its 5-row viewport, scrolled to the top.
+    fn scrolled_up_app() -> ClawApp {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.chat_height.store(5, Ordering::Relaxed);
+        for n in 0..20 {
+            app.push_message(ChatMessageKind::User, format!("message {}", n));
+        }
+        app.chat_viewport.set_y_offset(0);
+        app
+    }
+
+    fn vim_app() -> ClawApp {
+        let mut flags = test_flags();
+        flags.tui_config.keymap = crate::config::Keymap::Vim;
+        let (mut app, _) = ClawApp::init(flags);
+        app.chat_height.store(5, Ordering::Relaxed);
+        for n in 0..20 {
+            app.push_message(ChatMessageKind::User, format!("message {}", n));
+        }
+        app
+    }
+
+    fn type_keys(app: &mut ClawApp, keys: &str) -> Vec<Command<Msg>> {
+        keys.chars()
+            .map(|c| {
+                app.update(Msg::Key(KeyEvent::new(
+                    KeyCode::Char(c),
+                    KeyModifiers::NONE,
+                )))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn vim_esc_switches_to_normal_mode_instead_of_quitting() {
+        let mut app = vim_app();
+        assert_eq!(app.keymap.status().as_deref(), Some("INSERT"));
+        type_keys(&mut app, "hi");
+        assert_eq!(app.input.value(), "hi");
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(cmd.is_none());
+        assert_eq!(app.keymap.status().as_deref(), Some("NORMAL"));
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(cmd.is_none(), "Esc doesn't quit in vim mode");
+        type_keys(&mut app, "xz");
+        assert_eq!(app.input.value(), "hi");
+
+        type_keys(&mut app, "a!");
+        assert_eq!(app.keymap.status().as_deref(), Some("INSERT"));
+        assert_eq!(app.input.value(), "hi!");
+    }
+
+    #[test]
+    fn vim_normal_mode_scrolls_jumps_and_clears() {
+        let mut app = vim_app();
+        app.input.set_value("draft");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(app.chat_at_bottom());
+        let bottom = app.chat_viewport.y_offset();
+
+        type_keys(&mut app, "kk");
+        assert_eq!(app.chat_viewport.y_offset(), bottom - 2);
+        type_keys(&mut app, "j");
+        assert_eq!(app.chat_viewport.y_offset(), bottom - 1);
+        type_keys(&mut app, "gg");
+        assert_eq!(app.chat_viewport.y_offset(), 0);
+        type_keys(&mut app, "G");
+        assert!(app.chat_at_bottom());
+
+        type_keys(&mut app, "d");
+        assert_eq!(app.input.value(), "draft");
+        type_keys(&mut app, "d");
+        assert_eq!(app.input.value(), "");
+    }
+
+    #[test]
+    fn vim_normal_mode_arrows_scroll_and_enter_keeps_the_draft() {
+        let mut app = vim_app();
+        app.input.set_value("draft");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        let bottom = app.chat_viewport.y_offset();
+        let messages = app.messages.len();
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)));
+        assert_eq!(app.chat_viewport.y_offset(), bottom - 1);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)));
+        assert!(app.chat_at_bottom());
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(app.input.value(), "draft");
+        assert_eq!(app.messages.len(), messages);
+        assert_eq!(app.keymap.status().as_deref(), Some("NORMAL"));
+    }
+
+    #[test]
+    fn vim_visual_mode_copies_the_selected_messages() {
+        let mut app = vim_app();
+        app.copy_text = |_| Ok(());
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+
+        type_keys(&mut app, "vk");
+        assert_eq!(app.keymap.status().as_deref(), Some("VISUAL"));
+        assert_eq!(app.selection, Some((20, 19)));
+        type_keys(&mut app, "y");
+        assert_eq!(app.selection, None);
+        assert_eq!(app.keymap.status().as_deref(), Some("NORMAL"));
+        let pending = app.pending_consent.as_ref().expect("consent prompt shown");
+        assert_eq!(pending.payload, "message 18\n\nmessage 19");
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('1'),
+            KeyModifiers::NONE,
+        )));
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Copied 2 messages (22 chars)")
+        );
+    }
+
+    #[test]
+    fn vim_yank_after_the_selected_placeholder_is_dropped() {
+        let mut app = vim_app();
+        app.copy_text = |_| Ok(());
+        app.streaming = true;
+        app.push_message(ChatMessageKind::Assistant, String::new());
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        type_keys(&mut app, "v");
+        assert_eq!(app.selection, Some((20, 20)));
+
+        // The turn ends without text, taking the placeholder with it.
+        app.update(Msg::Agent(AgentEvent::Done));
+        assert_eq!(app.messages.len(), 20);
+        assert_eq!(app.selection, Some((19, 19)));
+
+        type_keys(&mut app, "y");
+        let pending = app.pending_consent.as_ref().expect("consent prompt shown");
+        assert_eq!(pending.payload, "message 19");
+    }
+
+    #[test]
+    fn vim_colon_q_quits() {
+        let mut app = vim_app();
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        type_keys(&mut app, ":wat");
+        assert_eq!(app.keymap.status().as_deref(), Some(":wat"));
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(cmd.is_none());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Not an editor command: :wat")
+        );
+
+        type_keys(&mut app, ":q");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!cmd.is_none());
+    }
+
+    #[test]
+    fn vim_normal_mode_leaves_approval_keys_alone() {
+        let mut app = vim_app();
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.pending_approval = Some(PendingApproval {
+            description: "bash(ls)".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
+            tool_name: "bash".to_string(),
+            selected: 0,
+            responder: Some(tx),
+        });
+        let offset = app.chat_viewport.y_offset();
+        type_keys(&mut app, "k");
+        assert_eq!(app.chat_viewport.y_offset(), offset);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('1'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_approval.is_none());
+        assert!(rx.blocking_recv().is_ok());
+    }
+
+    #[test]
+    fn new_content_follows_only_a_chat_at_the_bottom() {
+        let mut app = scrolled_up_app();
+        app.push_message(ChatMessageKind::System, "tool finished".to_string());
+        assert_eq!(app.chat_viewport.y_offset(), 0);
+        assert!(app.new_below);
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::End, KeyModifiers::CONTROL)));
+        assert!(app.chat_at_bottom());
+        assert!(!app.new_below);
+        app.push_message(ChatMessageKind::System, "another".to_string());
+        assert!(app.chat_at_bottom());
+        assert!(!app.new_below);
+    }
+
+    #[test]
+    fn scrolling_down_to_the_bottom_clears_the_new_messages_notice() {
+        let mut app = scrolled_up_app();
+        app.push_message(ChatMessageKind::System, "tool finished".to_string());
+        assert!(app.new_below);
+
+        for _ in 0..app.chat_lines {
+            app.scroll_down(1);
+        }
+        assert!(app.chat_at_bottom());
+        assert!(!app.new_below);
+    }
+
+    #[test]
+    fn sending_a_message_scrolls_to_it() {
+        let mut app = scrolled_up_app();
+        app.input.set_value("next question");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.chat_at_bottom());
+        assert!(!app.new_below);
+    }
+
+    #[test]
+    fn append_creates_new_if_no_assistant() {
+        let flags = test_flags();
+        let (mut app, _cmd) = ClawApp::init(flags);
+
+        app.push_message(ChatMessageKind::User, "hi".to_string());
+        app.append_to_last_assistant("response");
+        // Should have: system startup + user msg + new assistant msg
+        assert_eq!(app.messages.len(), 3);
+        assert_eq!(app.messages[2].kind, ChatMessageKind::Assistant);
+        assert_eq!(app.messages[2].content, "response");
+    }
+
+    #[test]
+    fn init_with_replay_messages() {
+        let (user_tx, _user_rx) = mpsc::channel(16);
+        let (_agent_tx, agent_rx) = mpsc::channel(64);
+        let flags = Flags {
+            user_tx,
+            agent_rx,
+            model_name: "test-model".to_string(),
+            tool_count: 5,
+            context_window: 128_000,
+            compaction_limit: Some(115_200),
+            workspace_dir: "/tmp/test".to_string(),
+            replay_messages: vec![
+                ChatMessage::new(ChatMessageKind::User, "replayed user msg".to_string()),
+                ChatMessage::new(
+                    ChatMessageKind::Assistant,
+                    "replayed assistant msg".to_string(),
+                ),
+            ],
+            startup_message: "Test startup".to_string(),
+            tui_config: TuiConfig::default(),
+            tools: vec![],
+            snippets: vec![],
+            engine: None,
+            skills: vec![],
+            initial_prompt: None,
+            keyboard_enhancement: false,
+            onboarding: None,
+            observe: false,
+            privacy: PrivacyConfig::default(),
+            consents: Consents::default(),
+            changes: None,
+            shutdown: ShutdownFlag::default(),
+        };
+
+        let (app, _cmd) = ClawApp::init(flags);
+
+        // Should have: startup message + 2 replay messages + "Session resumed"
+        assert_eq!(app.messages.len(), 4);
+        assert_eq!(app.messages[0].kind, ChatMessageKind::System);
+        assert_eq!(app.messages[0].content, "Test startup");
+        assert_eq!(app.messages[1].kind, ChatMessageKind::User);
+        assert_eq!(app.messages[1].content, "replayed user msg");
+        assert_eq!(app.messages[2].kind, ChatMessageKind::Assistant);
+        assert_eq!(app.messages[2].content, "replayed assistant msg");
+        assert_eq!(app.messages[3].kind, ChatMessageKind::System);
+        assert!(app.messages[3].content.contains("Session resumed"));
+    }
+
+    #[test]
+    fn initial_prompt_is_sent_once_on_init() {
+        let mut flags = test_flags();
+        flags.initial_prompt = Some("fix the failing tests".to_string());
+        let (mut app, cmd) = ClawApp::init(flags);
+
+        assert!(!cmd.is_none(), "init should dispatch the send");
+        assert!(app.streaming);
+        let users: Vec<&ChatMessage> = app
+            .messages
+            .iter()
+            .filter(|m| m.kind == ChatMessageKind::User)
+            .collect();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].content, "fix the failing tests");
+        assert!(app.input.value().is_empty());
+
+        // Later update cycles don't send it again.
+        assert!(app.update(Msg::MessageSent).is_none());
+        app.update(Msg::Agent(AgentEvent::TextDelta("On it.".to_string())));
+        assert!(app.update(Msg::Agent(AgentEvent::Done)).is_none());
+        assert!(app.update(Msg::MessageSent).is_none());
+        let user_count = app
+            .messages
+            .iter()
+            .filter(|m| m.kind == ChatMessageKind::User)
+            .count();
+        assert_eq!(user_count, 1);
+        assert!(!app.streaming);
+    }
+
+    #[test]
+    fn initial_prompt_follows_resumed_history() {
+        let mut flags = test_flags();
+        flags.replay_messages = vec![ChatMessage::new(
+            ChatMessageKind::User,
+            "earlier question".to_string(),
+        )];
+        flags.initial_prompt = Some("and now this".to_string());
+        let (app, _cmd) = ClawApp::init(flags);
+
+        let n = app.messages.len();
+        assert_eq!(app.messages[n - 2].kind, ChatMessageKind::User);
+        assert_eq!(app.messages[n - 2].content, "and now this");
+        assert!(is_thinking(&app.messages[n - 1]));
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.content.contains("Session resumed"))
+        );
+    }
+
+    #[test]
+    fn no_initial_prompt_sends_nothing() {
+        let (app, cmd) = ClawApp::init(test_flags());
+        assert!(cmd.is_none());
+        assert!(!app.streaming);
+    }
+
+    // --- Agent event update() tests ---
+
+    #[test]
+    fn update_text_delta_appends() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        app.update(Msg::Agent(AgentEvent::TextDelta("Hello".to_string())));
+        app.update(Msg::Agent(AgentEvent::TextDelta(" world".to_string())));
+
+        // Startup message + one assistant message
+        assert_eq!(app.messages.len(), 2);
+        assert_eq!(app.messages[1].kind, ChatMessageKind::Assistant);
+        assert_eq!(app.messages[1].content, "Hello world");
+    }
+
+    #[test]
+    fn update_done_stops_streaming() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        app.streaming = true;
+
+        let cmd = app.update(Msg::Agent(AgentEvent::Done));
+
+        assert!(!app.streaming);
+        assert!(cmd.is_none());
+    }
+
+    #[test]
+    fn update_done_sends_queued_message() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.queued_message = Some("follow up".to_string());
+
+        let cmd = app.update(Msg::Agent(AgentEvent::Done));
+
+        assert!(app.streaming); // re-set to true for the queued send
+        assert!(app.queued_message.is_none());
+        assert!(!cmd.is_none()); // should have returned a send command
+        // The queued message should have been pushed as a User message
+        let user_msgs: Vec<_> = app
+            .messages
+            .iter()
+            .filter(|m| m.kind == ChatMessageKind::User)
+            .collect();
+        assert_eq!(user_msgs.len(), 1);
+        assert_eq!(user_msgs[0].content, "follow up");
+    }
+
+    #[test]
+    fn update_error_stops_streaming() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        app.streaming = true;
+
+        app.update(Msg::Agent(AgentEvent::Error("oops".to_string())));
+
+        assert!(!app.streaming);
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::System);
+        assert!(last.content.contains("oops"));
+    }
+
+    /// Send `text` as if typed and sent with Enter.
+    fn send(app: &mut ClawApp, text: &str) {
+        app.input.set_value(text);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn first_token_replaces_the_thinking_placeholder() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        send(&mut app, "hello");
+        assert!(is_thinking(app.messages.last().unwrap()));
+
+        app.update(Msg::Agent(AgentEvent::TextDelta("Hi".to_string())));
+        app.update(Msg::Agent(AgentEvent::TextDelta(" there".to_string())));
+        let replies: Vec<&ChatMessage> = app
+            .messages
+            .iter()
+            .filter(|m| m.kind == ChatMessageKind::Assistant)
+            .collect();
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].content, "Hi there");
+
+        app.update(Msg::Agent(AgentEvent::Done));
+        assert_eq!(app.messages.last().unwrap().content, "Hi there");
+    }
+
+    #[test]
+    fn error_before_the_first_token_takes_the_placeholders_place() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        send(&mut app, "hello");
+        let count = app.messages.len();
+
+        app.update(Msg::Agent(AgentEvent::Error("overloaded".to_string())));
+
+        assert_eq!(app.messages.len(), count);
+        assert!(!app.messages.iter().any(is_thinking));
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::System);
+        assert!(last.content.contains("overloaded"));
+    }
+
+    #[test]
+    fn placeholder_gives_way_to_tool_calls_but_outlasts_notices() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        send(&mut app, "hello");
+
+        app.update(Msg::Agent(AgentEvent::Notice("falling back".to_string())));
+        let n = app.messages.len();
+        assert!(app.messages[n - 2].content.contains("falling back"));
+        assert!(is_thinking(&app.messages[n - 1]));
+
+        start_bash(&mut app, "c1", "ls");
+        assert!(!app.messages.iter().any(is_thinking));
+        app.update(Msg::Agent(AgentEvent::Done));
+
+        // A turn that ends before any text leaves no placeholder behind.
+        send(&mut app, "again");
+        assert!(is_thinking(app.messages.last().unwrap()));
+        app.update(Msg::Agent(AgentEvent::Done));
+        assert!(!app.messages.iter().any(is_thinking));
+    }
+
+    #[test]
+    fn accessible_mode_never_blinks_and_drops_emoji() {
+        let mut flags = test_flags();
+        flags.tui_config.accessibility = true;
+        let (mut app, _cmd) = ClawApp::init(flags);
+        assert!(app.ascii);
+
+        send(&mut app, "hello");
+        app.update(Msg::Agent(AgentEvent::TextDelta("Hi".to_string())));
+        assert_eq!(app.subscriptions().len(), 2);
+
+        let backend = ratatui::backend::TestBackend::new(80, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.view(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("USER: hello"));
+        assert!(screen.contains("ASSISTANT: Hi"));
+        assert!(!screen.contains(crate::tui::widgets::chat::STREAMING_CURSOR));
+    }
+
+    #[test]
+    fn cursor_blinks_only_while_streaming() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        assert_eq!(app.subscriptions().len(), 2);
+
+        send(&mut app, "hello");
+        app.update(Msg::Agent(AgentEvent::TextDelta("Hi".to_string())));
+        assert_eq!(app.subscriptions().len(), 3);
+        assert!(app.cursor_on);
+        app.update(Msg::CursorBlink);
+        assert!(!app.cursor_on);
+        app.update(Msg::CursorBlink);
+        assert!(app.cursor_on);
+
+        app.update(Msg::Agent(AgentEvent::Done));
+        assert_eq!(app.subscriptions().len(), 2);
+        assert_eq!(app.messages.last().unwrap().content, "Hi");
+    }
+
+    #[test]
+    fn update_notice_pushes_system_message_without_stopping() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        app.streaming = true;
+
+        app.update(Msg::Agent(AgentEvent::Notice("falling back".to_string())));
+
+        assert!(app.streaming);
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::System);
+        assert!(last.content.contains("falling back"));
+    }
+
+    #[test]
+    fn update_tool_call_started() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        app.update(Msg::Agent(AgentEvent::ToolCallStarted {
+            tool_call_id: "c1".to_string(),
+            tool_name: "read_file".to_string(),
+            params_summary: "path=/tmp".to_string(),
+        }));
+
+        let last = app.messages.last().unwrap();
+        assert_eq!(
+            last.kind,
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "read_file".to_string(),
+                status: ToolCallStatus::Pending,
+                approval_reason: None,
+            }
+        );
+        assert_eq!(last.content, "read_file(path=/tmp)");
+    }
+
+    #[test]
+    fn update_tool_approved_updates_status() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        app.update(Msg::Agent(AgentEvent::ToolCallStarted {
+            tool_call_id: "c1".to_string(),
+            tool_name: "write_file".to_string(),
+            params_summary: "path=/tmp".to_string(),
+        }));
+        app.update(Msg::Agent(AgentEvent::ToolCallApproved {
+            tool_call_id: "c1".to_string(),
+            tool_name: "write_file".to_string(),
+            reason: Some(ApprovalReason::FullSecurity),
+        }));
+
+        let last = app.messages.last().unwrap();
+        assert_eq!(
+            last.kind,
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "write_file".to_string(),
+                status: ToolCallStatus::Allowed,
+                approval_reason: Some(ApprovalReason::FullSecurity),
+            }
+        );
+    }
+
+    #[test]
+    fn approval_reason_is_dropped_when_hidden() {
+        let mut flags = test_flags();
+        flags.tui_config.show_approval_reason = false;
+        let (mut app, _cmd) = ClawApp::init(flags);
+        start_bash(&mut app, "c1", "cargo build");
+
+        app.update(Msg::Agent(AgentEvent::ToolCallApproved {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+            reason: Some(ApprovalReason::AllowlistPattern(
+                "/usr/bin/cargo".to_string(),
+            )),
+        }));
+
+        assert_eq!(
+            app.messages.last().unwrap().kind,
+            tool_call_kind("c1", ToolCallStatus::Allowed)
+        );
+    }
+
+    fn tool_call_kind(id: &str, status: ToolCallStatus) -> ChatMessageKind {
+        ChatMessageKind::ToolCall {
+            tool_call_id: id.to_string(),
+            tool_name: "bash".to_string(),
+            status,
+            approval_reason: None,
+        }
+    }
+
+    fn start_bash(app: &mut ClawApp, id: &str, command: &str) {
+        app.update(Msg::Agent(AgentEvent::ToolCallStarted {
+            tool_call_id: id.to_string(),
+            tool_name: "bash".to_string(),
+            params_summary: command.to_string(),
+        }));
+    }
+
+    #[test]
+    fn status_updates_target_the_call_by_id() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        start_bash(&mut app, "c1", "make build");
+        start_bash(&mut app, "c2", "make test");
+
+        // Approving the first call must not touch the second, later one.
+        app.update(Msg::Agent(AgentEvent::ToolCallApproved {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+            reason: None,
+        }));
+        let n = app.messages.len();
+        assert_eq!(
+            app.messages[n - 2].kind,
+            tool_call_kind("c1", ToolCallStatus::Allowed)
+        );
+        assert_eq!(
+            app.messages[n - 1].kind,
+            tool_call_kind("c2", ToolCallStatus::Pending)
+        );
+
+        app.update(Msg::Agent(AgentEvent::ToolCallDenied {
+            tool_call_id: "c2".to_string(),
+            tool_name: "bash".to_string(),
+            reason: "denied by user".to_string(),
+        }));
+        assert_eq!(
+            app.messages[n - 2].kind,
+            tool_call_kind("c1", ToolCallStatus::Allowed)
+        );
+        assert_eq!(
+            app.messages[n - 1].kind,
+            tool_call_kind("c2", ToolCallStatus::Denied)
+        );
+    }
+
+    #[test]
+    fn results_nest_under_their_own_call() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        start_bash(&mut app, "c1", "make build");
+        start_bash(&mut app, "c2", "make test");
+        let first = app.messages.len() - 2;
+
+        app.update(Msg::Agent(AgentEvent::ToolResult {
+            tool_call_id: "c2".to_string(),
+            tool_name: "bash".to_string(),
+            content: "tests passed".to_string(),
+            is_error: false,
+        }));
+        app.update(Msg::Agent(AgentEvent::ToolResult {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+            content: "build failed".to_string(),
+            is_error: true,
+        }));
+
+        let tail: Vec<(&ChatMessageKind, &str)> = app.messages[first..]
+            .iter()
+            .map(|m| (&m.kind, m.content.as_str()))
+            .collect();
+        assert_eq!(
+            tail,
+            [
+                (
+                    &tool_call_kind("c1", ToolCallStatus::Pending),
+                    "bash(make build)"
+                ),
+                (
+                    &ChatMessageKind::ToolResult { is_error: true },
+                    "build failed"
+                ),
+                (
+                    &tool_call_kind("c2", ToolCallStatus::Pending),
+                    "bash(make test)"
+                ),
+                (
+                    &ChatMessageKind::ToolResult { is_error: false },
+                    "tests passed"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn result_for_an_unknown_call_goes_at_the_end() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        app.update(Msg::Agent(AgentEvent::ToolResult {
+            tool_call_id: "missing".to_string(),
+            tool_name: "bash".to_string(),
+            content: "out".to_string(),
+            is_error: false,
+        }));
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::ToolResult { is_error: false });
+        assert_eq!(last.content, "out");
+    }
+
+    #[test]
+    fn update_needs_approval_sets_pending() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.update(Msg::Agent(AgentEvent::ToolCallNeedsApproval {
+            tool_call_id: "c1".to_string(),
+            description: "Write to disk".to_string(),
+            pattern: Some("write_*".to_string()),
+            similar: vec![],
+            scopes: vec![],
+            tool_name: "write_file".to_string(),
+            responder: tx,
+        }));
+
+        assert!(app.pending_approval.is_some());
+        let approval = app.pending_approval.as_ref().unwrap();
+        assert_eq!(approval.description, "Write to disk");
+        assert_eq!(approval.tool_name, "write_file");
+        assert_eq!(approval.pattern, Some("write_*".to_string()));
+        assert!(app.chat_viewport.at_bottom());
+    }
+
+    #[test]
+    fn update_ask_user_sets_pending_question() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.update(Msg::Agent(AgentEvent::AskUser {
+            question: "What is your name?".to_string(),
+            tool_call_id: "call-42".to_string(),
+            options: vec!["Alice".to_string(), "Bob".to_string()],
+            style: QuestionStyle::Open,
+            responder: tx,
+        }));
+
+        assert!(app.pending_question.is_some());
+        let q = app.pending_question.as_ref().unwrap();
+        assert_eq!(q.question, "What is your name?");
+        assert_eq!(q.tool_call_id, "call-42");
+        assert_eq!(q.options, vec!["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn ask_user_options_from_the_agent_are_answerable_by_key() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.update(Msg::Agent(AgentEvent::AskUser {
+            question: "Which color?".to_string(),
+            tool_call_id: "call-7".to_string(),
+            options: vec!["red".to_string(), "blue".to_string()],
+            style: QuestionStyle::Open,
+            responder: tx,
+        }));
+        assert!(!app.pending_question.as_ref().unwrap().takes_text());
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_question.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), "blue");
+    }
+
+    #[test]
+    fn update_usage_tracks_tokens() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        app.update(Msg::Agent(AgentEvent::Usage {
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+        }));
+
+        assert_eq!(app.total_tokens, 150);
+        assert_eq!(app.context_used, 100);
+    }
+
+    #[test]
+    fn cache_tokens_count_toward_context_and_cost() {
+        let mut flags = test_flags();
+        flags.model_name = "claude-sonnet-4-5".to_string();
+        let (mut app, _cmd) = ClawApp::init(flags);
+
+        app.update(Msg::Agent(AgentEvent::Usage {
+            input_tokens: 1_000,
+            output_tokens: 500,
+            cache_read_tokens: 20_000,
+            cache_write_tokens: 4_000,
+        }));
+
+        assert_eq!(app.context_used, 25_000);
+        assert_eq!(app.total_tokens, 25_500);
+        assert_eq!(app.usage.cache_read, 20_000);
+        // 1k × $3 + 500 × $15 + 20k × $0.30 + 4k × $3.75, per million.
+        let cost = app.session_cost().unwrap();
+        assert!((cost - 0.0315).abs() < 1e-9, "{cost}");
+    }
+
+    #[test]
+    fn unknown_models_have_no_cost() {
+        let (app, _cmd) = ClawApp::init(test_flags());
+        assert_eq!(app.session_cost(), None);
+    }
+
+    fn finish_turn(app: &mut ClawApp, input_tokens: u32) {
+        app.update(Msg::Agent(AgentEvent::Usage {
+            input_tokens,
+            output_tokens: 10,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+        }));
+        app.update(Msg::Agent(AgentEvent::Done));
+    }
+
+    #[test]
+    fn context_history_sampled_at_turn_end() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        // Several Usage events in one turn yield a single sample.
+        app.update(Msg::Agent(AgentEvent::Usage {
+            input_tokens: 1_000,
+            output_tokens: 10,
+            cache_read_tokens: 0,
+            cache_write_tokens: 0,
+        }));
+        finish_turn(&mut app, 2_000);
+        assert_eq!(app.context_history, vec![2_000]);
+        assert_eq!(app.compaction_forecast(), None);
+
+        finish_turn(&mut app, 12_000);
+        assert_eq!(app.context_history, vec![2_000, 12_000]);
+        // 10k per turn, 103.2k left before the 115.2k limit → 11 turns.
+        assert_eq!(app.compaction_forecast(), Some(11));
+    }
+
+    #[test]
+    fn context_history_resets_after_compaction_and_is_bounded() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        for turn in 1..=10 {
+            finish_turn(&mut app, turn * 1_000);
+        }
+        assert_eq!(app.context_history.len(), CONTEXT_HISTORY_LEN);
+        assert_eq!(app.context_history.last(), Some(&10_000));
+
+        // Context shrank: compaction happened, start over.
+        finish_turn(&mut app, 3_000);
+        assert_eq!(app.context_history, vec![3_000]);
+    }
+
+    #[test]
+    fn no_forecast_when_compaction_disabled() {
+        let mut flags = test_flags();
+        flags.compaction_limit = None;
+        let (mut app, _cmd) = ClawApp::init(flags);
+        finish_turn(&mut app, 1_000);
+        finish_turn(&mut app, 5_000);
+        assert_eq!(app.compaction_forecast(), None);
+    }
+
+    #[test]
+    fn update_compaction_messages() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        app.update(Msg::Agent(AgentEvent::CompactionStarted));
+        assert!(app.streaming, "input is blocked while compacting");
+        let compacting_msg = app.messages.last().unwrap();
+        assert_eq!(compacting_msg.kind, ChatMessageKind::System);
+        assert!(compacting_msg.content.contains("Compacting"));
+
+        let count = app.messages.len();
+        app.update(Msg::Agent(AgentEvent::CompactionProgress { tokens: 50 }));
+        app.update(Msg::Agent(AgentEvent::CompactionProgress { tokens: 100 }));
+        assert_eq!(
+            app.messages.len(),
+            count,
+            "progress updates the line in place"
+        );
+        let progress_msg = app.messages.last().unwrap();
+        assert!(progress_msg.content.starts_with(COMPACTING_MESSAGE));
+        assert!(progress_msg.content.contains("\u{2248}100 tokens"));
+
+        app.update(Msg::Agent(AgentEvent::CompactionDone {
+            old_count: 50,
+            new_count: 10,
+        }));
+        let done_msg = app.messages.last().unwrap();
+        assert_eq!(done_msg.kind, ChatMessageKind::System);
+        assert!(done_msg.content.contains("50"));
+        assert!(done_msg.content.contains("10"));
+        assert!(done_msg.content.contains("Compacted"));
+        assert!(app.streaming, "a turn's compaction ends with its Done");
+
+        app.update(Msg::Agent(AgentEvent::ResumeCompacted));
+        assert!(
+            !app.streaming,
+            "input unblocks once a resumed session is compacted"
+        );
+    }
+
+    fn review_summary(app: &mut ClawApp) -> tokio::sync::oneshot::Receiver<SummaryDecision> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.update(Msg::Agent(AgentEvent::CompactionSummaryReady {
+            summary: "Goal: fix the parser.".to_string(),
+            responder: tx,
+        }));
+        rx
+    }
+
+    #[test]
+    fn summary_review_shows_summary_and_options() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let _rx = review_summary(&mut app);
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Goal: fix the parser.")
+        );
+        let question = app.pending_question.as_ref().unwrap();
+        assert_eq!(question.options, SUMMARY_REVIEW_OPTIONS.to_vec());
+    }
+
+    #[test]
+    fn summary_review_accept() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let mut rx = review_summary(&mut app);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(rx.try_recv().unwrap(), SummaryDecision::Accept);
+        assert!(app.pending_question.is_none());
+        assert!(app.summary_review.is_none());
+    }
+
+    #[test]
+    fn summary_review_edit_resubmits_the_edited_text() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let mut rx = review_summary(&mut app);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        assert_eq!(app.input.value(), "Goal: fix the parser.");
+        assert!(app.pending_question.as_ref().unwrap().options.is_empty());
+        assert!(
+            rx.try_recv().is_err(),
+            "nothing sent until the edit is submitted"
+        );
+
+        app.input
+            .set_value("Goal: fix the parser. Keep the public API.");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            SummaryDecision::Edit("Goal: fix the parser. Keep the public API.".to_string())
+        );
+        assert!(app.input.value().is_empty());
+    }
+
+    #[test]
+    fn summary_review_skip_and_esc() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let mut rx = review_summary(&mut app);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('3'),
+            KeyModifiers::NONE,
+        )));
+        assert_eq!(rx.try_recv().unwrap(), SummaryDecision::Skip);
+
+        let mut rx = review_summary(&mut app);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert_eq!(rx.try_recv().unwrap(), SummaryDecision::Skip);
+        assert!(app.pending_question.is_none());
+    }
+
+    // --- Key, Mouse, Paste handling tests (Task 5) ---
+
+    #[test]
+    fn key_esc_quits() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        let cmd = app.update(Msg::Key(key));
+        assert!(!cmd.is_none());
+    }
+
+    #[test]
+    fn key_esc_during_streaming_does_nothing() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        let cmd = app.update(Msg::Key(key));
+        assert!(cmd.is_none());
+    }
+
+    #[test]
+    fn key_enter_sends_message() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("hello world");
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        let cmd = app.update(Msg::Key(key));
+        assert!(!cmd.is_none());
+        assert!(app.streaming);
+        assert_eq!(app.input.value(), "");
+        // User message should have been pushed
+        let user_msgs: Vec<_> = app
+            .messages
+            .iter()
+            .filter(|m| m.kind == ChatMessageKind::User)
+            .collect();
+        assert_eq!(user_msgs.len(), 1);
+        assert_eq!(user_msgs[0].content, "hello world");
+    }
+
+    #[test]
+    fn key_enter_empty_does_nothing() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        let cmd = app.update(Msg::Key(key));
+        assert!(cmd.is_none());
+        assert!(!app.streaming);
+    }
+
+    #[test]
+    fn key_enter_during_streaming_queues() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.input.set_value("follow up");
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        app.update(Msg::Key(key));
+        assert_eq!(app.queued_message, Some("follow up".to_string()));
+        assert_eq!(app.input.value(), "");
+    }
+
+    #[test]
+    fn single_ctrl_c_clears_input_does_not_quit() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("some text");
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let cmd = app.update(Msg::Key(key));
+        assert!(cmd.is_none(), "single Ctrl+C should not quit");
+        assert_eq!(app.input.value(), "", "single Ctrl+C should clear input");
+    }
+
+    #[test]
+    fn double_ctrl_c_quits() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        // First press primes the timer.
+        let cmd = app.update(Msg::Key(key));
+        assert!(cmd.is_none());
+        // Second press within 500ms quits.
+        let cmd = app.update(Msg::Key(key));
+        assert!(!cmd.is_none(), "double Ctrl+C should quit");
+    }
+
+    #[test]
+    fn ctrl_c_window_just_under_quits() {
+        let window = Duration::from_millis(500);
+        let prev = Instant::now();
+        let now = prev + Duration::from_millis(499);
+        assert_eq!(
+            ctrl_c_action(Some(prev), now, window, false, false),
+            CtrlCAction::Quit
+        );
+    }
+
+    #[test]
+    fn ctrl_c_window_just_over_does_not_quit() {
+        let window = Duration::from_millis(500);
+        let prev = Instant::now();
+        let now = prev + Duration::from_millis(501);
+        assert_eq!(
+            ctrl_c_action(Some(prev), now, window, false, false),
+            CtrlCAction::Prime
+        );
+        assert_eq!(
+            ctrl_c_action(Some(prev), now, window, false, true),
+            CtrlCAction::ClearInput
+        );
+    }
+
+    #[test]
+    fn ctrl_c_cancels_turn_before_clearing_input() {
+        let now = Instant::now();
+        let window = Duration::from_millis(500);
+        assert_eq!(
+            ctrl_c_action(None, now, window, true, true),
+            CtrlCAction::CancelTurn
+        );
+        assert_eq!(
+            ctrl_c_action(None, now, window, false, true),
+            CtrlCAction::ClearInput
+        );
+    }
+
+    #[test]
+    fn ctrl_c_while_streaming_requests_cancel() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.input.set_value("draft survives");
+
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let cmd = app.update(Msg::Key(key));
+
+        assert!(!cmd.is_none(), "cancel should dispatch a send command");
+        assert!(app.streaming, "streaming continues until the loop confirms");
+        assert_eq!(app.input.value(), "draft survives");
+        let last = app.messages.last().unwrap();
+        assert!(last.content.contains("Cancelling"));
+    }
+
+    #[test]
+    fn double_ctrl_c_while_streaming_asks_before_quitting() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        app.update(Msg::Key(key));
+        let cmd = app.update(Msg::Key(key));
+
+        assert!(cmd.is_none(), "should confirm instead of quitting");
+        assert_eq!(app.pending_quit.as_ref().map(|q| q.selected), Some(0));
+    }
+
+    #[test]
+    fn quit_confirm_y_quits_now() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.pending_quit = Some(PendingQuit { selected: 0 });
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('y'),
+            KeyModifiers::NONE,
+        )));
+
+        assert!(!cmd.is_none(), "y should quit");
+        assert!(app.pending_quit.is_none());
+    }
+
+    #[test]
+    fn quit_confirm_n_and_ctrl_c_dismiss() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.pending_quit = Some(PendingQuit { selected: 0 });
+        let cmd = app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('n'),
+            KeyModifiers::NONE,
+        )));
+        assert!(cmd.is_none());
+        assert!(app.pending_quit.is_none());
+        assert!(!app.quit_after_turn);
+
+        app.pending_quit = Some(PendingQuit { selected: 0 });
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let cmd = app.update(Msg::Key(ctrl_c));
+        assert!(cmd.is_none());
+        assert!(app.pending_quit.is_none());
+        // The dismissing press doesn't arm the double-tap timer.
+        let cmd = app.update(Msg::Key(ctrl_c));
+        assert!(cmd.is_none());
+        assert!(app.pending_quit.is_none());
+    }
+
+    #[test]
+    fn quit_after_turn_fires_once_on_done() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.queued_message = Some("never sent".to_string());
+        app.pending_quit = Some(PendingQuit { selected: 0 });
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('s'),
+            KeyModifiers::NONE,
+        )));
+        assert!(cmd.is_none(), "should wait for the turn to finish");
+        assert!(app.quit_after_turn);
+
+        let cmd = app.update(Msg::Agent(AgentEvent::Done));
+        assert!(!cmd.is_none(), "Done should quit");
+        assert!(!app.quit_after_turn);
+        assert!(!app.streaming, "the queued message must not be sent");
+
+        let cmd = app.update(Msg::Agent(AgentEvent::Done));
+        assert!(cmd.is_none(), "deferred quit fires only once");
+    }
+
+    #[test]
+    fn quit_after_turn_quits_immediately_when_idle() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.pending_quit = Some(PendingQuit { selected: 1 });
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(!cmd.is_none());
+        assert!(!app.quit_after_turn);
+    }
+
+    /// An app on its first run, with a config file and an empty approvals engine.
+    fn onboarding_app(dir: &Path) -> (ClawApp, Arc<ApprovalEngine>) {
+        std::fs::write(
+            dir.join("config.toml"),
+            "[approval]\nsecurity = \"allowlist\"\n",
+        )
+        .unwrap();
+        let engine = Arc::new(ApprovalEngine::with_approvals(
+            crate::approval::ApprovalsFile::default(),
+            dir.join("approvals.json"),
+        ));
+        let mut flags = test_flags();
+        flags.engine = Some(Arc::clone(&engine));
+        flags.onboarding = Some(Onboarding::new(
+            dir.join("onboarded"),
+            dir.join("config.toml"),
+        ));
+        (ClawApp::init(flags).0, engine)
+    }
+
+    #[test]
+    fn first_run_walkthrough_applies_and_saves_the_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, engine) = onboarding_app(dir.path());
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.content.contains("Welcome to soloclaw"))
+        );
+        assert_eq!(
+            app.pending_question.as_ref().unwrap().question,
+            onboarding::SECURITY_QUESTION
+        );
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        assert_eq!(engine.tool_security("bash").security, SecurityLevel::Full);
+        assert_eq!(
+            app.pending_question.as_ref().unwrap().question,
+            onboarding::SAVE_QUESTION
+        );
+        assert!(!dir.path().join("onboarded").exists());
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('1'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_question.is_none());
+        let config = std::fs::read_to_string(dir.path().join("config.toml")).unwrap();
+        assert_eq!(config, "[approval]\nsecurity = \"full\"\n");
+        assert!(dir.path().join("onboarded").exists());
+        // The engine's choice stays a session setting.
+        assert!(!dir.path().join("approvals.json").exists());
+    }
+
+    #[test]
+    fn first_run_walkthrough_can_keep_the_level_to_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, engine) = onboarding_app(dir.path());
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('3'),
+            KeyModifiers::NONE,
+        )));
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        assert_eq!(engine.tool_security("bash").security, SecurityLevel::Deny);
+        let config = std::fs::read_to_string(dir.path().join("config.toml")).unwrap();
+        assert!(config.contains("\"allowlist\""));
+        assert!(dir.path().join("onboarded").exists());
+    }
+
+    #[test]
+    fn esc_skips_the_first_run_walkthrough() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut app, engine) = onboarding_app(dir.path());
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(app.pending_question.is_none());
+        assert!(app.onboarding.is_none());
+        assert_eq!(
+            engine.tool_security("bash").security,
+            SecurityLevel::Allowlist
+        );
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .starts_with("Walkthrough skipped.")
+        );
+        assert!(dir.path().join("onboarded").exists());
+    }
+
+    #[test]
+    fn info_command_shows_where_the_session_is_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut flags = test_flags();
+        flags.workspace_dir = dir.path().to_string_lossy().to_string();
+        let (mut app, _) = ClawApp::init(flags);
+
+        app.input.set_value("/info");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        let hash = crate::session::workspace_hash(dir.path());
+        let shown = &app.messages.last().unwrap().content;
+        assert!(
+            shown.contains(&format!("Workspace hash: {}", hash)),
+            "{}",
+            shown
+        );
+        assert!(shown.contains("session.json"));
+    }
+
+    #[test]
+    fn approvals_command_opens_editor_and_deletes_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("approvals.json");
+        let engine = Arc::new(ApprovalEngine::with_approvals(
+            crate::approval::ApprovalsFile::default(),
+            path.clone(),
+        ));
+        engine.resolve("bash", Some("/usr/bin/git"), ApprovalDecision::AllowAlways);
+        engine.resolve("bash", Some("/usr/bin/make"), ApprovalDecision::AllowAlways);
+        let mut flags = test_flags();
+        flags.engine = Some(Arc::clone(&engine));
+        let (mut app, _) = ClawApp::init(flags);
+
+        app.input.set_value("/approvals");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(app.allowlist_editor.as_ref().map(|e| e.rows.len()), Some(2));
+
+        for code in [KeyCode::Char('d'), KeyCode::Char('y')] {
+            app.update(Msg::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+        }
+        assert_eq!(app.allowlist_editor.as_ref().map(|e| e.rows.len()), Some(1));
+        let reloaded = crate::approval::ApprovalsFile::load(&path).unwrap();
+        assert!(!reloaded.is_allowed("bash", "/usr/bin/git"));
+        assert!(reloaded.is_allowed("bash", "/usr/bin/make"));
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(app.allowlist_editor.is_none());
+    }
+
+    #[test]
+    fn configured_window_is_respected() {
+        let mut flags = test_flags();
+        flags.tui_config = TuiConfig {
+            double_ctrl_c_ms: 0,
+            ..TuiConfig::default()
+        };
+        let (mut app, _) = ClawApp::init(flags);
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        app.update(Msg::Key(key));
+        let cmd = app.update(Msg::Key(key));
+        assert!(cmd.is_none(), "a zero-length window never quits");
+    }
+
+    #[test]
+    fn turn_cancelled_clears_prompts_and_restores_queued() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.queued_message = Some("next thing".to_string());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_approval = Some(PendingApproval {
+            description: "test".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
+            tool_name: "bash".to_string(),
+            selected: 0,
+            responder: Some(tx),
+        });
+
+        app.update(Msg::Agent(AgentEvent::TurnCancelled));
+        app.update(Msg::Agent(AgentEvent::Done));
+
+        assert!(app.pending_approval.is_none());
+        assert!(app.queued_message.is_none());
+        assert!(!app.streaming, "restored draft must not be auto-sent");
+        assert_eq!(app.input.value(), "next thing");
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.kind == ChatMessageKind::System && m.content.contains("cancelled"))
+        );
+    }
+
+    #[test]
+    fn slash_tools_lists_tools_without_sending() {
+        use crate::approval::SecurityLevel;
+        use crate::tools::catalog::ToolOrigin;
+
+        let mut flags = test_flags();
+        flags.tools = vec![ToolInfo {
+            name: "bash".to_string(),
+            description: "Run a shell command".to_string(),
+            origin: ToolOrigin::BuiltIn,
+            security: SecurityLevel::Allowlist,
+        }];
+        let (mut app, _) = ClawApp::init(flags);
+        app.input.set_value("/tools");
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(cmd.is_none(), "/tools must not be sent to the agent");
+        assert!(!app.streaming);
+        assert_eq!(app.input.value(), "");
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::System);
+        assert!(last.content.contains("bash [allowlist]"));
+    }
+
+    fn skill(name: &str, status: SkillStatus) -> SkillEntry {
+        SkillEntry {
+            name: name.to_string(),
+            path: format!("/skills/{}/SKILL.md", name),
+            bytes: 1200,
+            status,
+        }
+    }
+
+    #[test]
+    fn slash_skills_shows_status_table() {
+        let mut flags = test_flags();
+        flags.skills = vec![
+            skill("deploy", SkillStatus::Loaded),
+            skill("peekaboo", SkillStatus::Disabled),
+        ];
+        let (mut app, _) = ClawApp::init(flags);
+        app.input.set_value("/skills");
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(cmd.is_none());
+        assert!(!app.streaming);
+        let last = app.messages.last().unwrap();
+        assert!(last.content.contains("1 of 2 skills loaded"));
+        assert!(last.content.contains("peekaboo"));
+        assert!(last.content.contains("disabled"));
     }
 
     #[test]
-    fn init_creates_valid_state() {
-        let flags = test_flags();
-        let (app, _cmd) = ClawApp::init(flags);
+    fn slash_reload_asks_agent_without_starting_a_turn() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("/reload");
 
-        assert_eq!(app.model_name, "test-model");
-        assert_eq!(app.tool_count, 5);
-        assert_eq!(app.context_window, 128_000);
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(!cmd.is_none());
         assert!(!app.streaming);
-        assert!(app.pending_approval.is_none());
-        assert!(app.pending_question.is_none());
-        // Startup message should be present
-        assert_eq!(app.messages.len(), 1);
-        assert_eq!(app.messages[0].kind, ChatMessageKind::System);
-        assert_eq!(app.messages[0].content, "Test startup");
+        assert_eq!(app.input.value(), "");
     }
 
     #[test]
-    fn push_message_resets_scroll() {
-        let flags = test_flags();
-        let (mut app, _cmd) = ClawApp::init(flags);
-        app.push_message(ChatMessageKind::User, "hello".to_string());
-        // After push, viewport should be at bottom (auto-scroll)
-        assert!(app.chat_viewport.at_bottom());
+    fn slash_reload_refused_while_streaming() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.streaming = true;
+        app.input.set_value("/reload");
+
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(cmd.is_none());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Can't reload")
+        );
     }
 
     #[test]
-    fn append_to_last_assistant() {
-        let flags = test_flags();
-        let (mut app, _cmd) = ClawApp::init(flags);
+    fn slash_regen_drops_the_last_answer_from_the_chat() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "explain".to_string());
+        app.push_message(ChatMessageKind::Assistant, "a long answer".to_string());
+        app.push_message(
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "read_file".to_string(),
+                status: ToolCallStatus::Allowed,
+                approval_reason: None,
+            },
+            "read_file a.txt".to_string(),
+        );
+        app.input.set_value("/regen make it shorter");
 
-        app.push_message(ChatMessageKind::Assistant, "Hello".to_string());
-        app.append_to_last_assistant(" world");
-        // Should still be a single assistant message (plus the startup system message)
-        assert_eq!(app.messages.len(), 2);
-        assert_eq!(app.messages[1].content, "Hello world");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(!cmd.is_none());
+        assert!(app.streaming);
+        let n = app.messages.len();
+        assert_eq!(app.messages[n - 2].kind, ChatMessageKind::User);
+        assert_eq!(app.messages[n - 2].content, "explain");
+        assert!(app.messages[n - 1].content.contains("regenerating"));
     }
 
     #[test]
-    fn append_creates_new_if_no_assistant() {
-        let flags = test_flags();
-        let (mut app, _cmd) = ClawApp::init(flags);
+    fn slash_retry_truncates_to_the_prior_user_message_and_streams() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "first".to_string());
+        app.push_message(ChatMessageKind::Assistant, "first answer".to_string());
+        app.push_message(ChatMessageKind::User, "explain".to_string());
+        app.push_message(
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "read_file".to_string(),
+                status: ToolCallStatus::Allowed,
+                approval_reason: None,
+            },
+            "read_file a.txt".to_string(),
+        );
+        app.push_message(
+            ChatMessageKind::ToolResult { is_error: false },
+            "contents".to_string(),
+        );
+        app.push_message(ChatMessageKind::Assistant, "an answer".to_string());
+        app.input.set_value("/retry");
 
-        app.push_message(ChatMessageKind::User, "hi".to_string());
-        app.append_to_last_assistant("response");
-        // Should have: system startup + user msg + new assistant msg
-        assert_eq!(app.messages.len(), 3);
-        assert_eq!(app.messages[2].kind, ChatMessageKind::Assistant);
-        assert_eq!(app.messages[2].content, "response");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(!cmd.is_none());
+        assert!(app.streaming);
+        let n = app.messages.len();
+        assert_eq!(app.messages[n - 2].kind, ChatMessageKind::User);
+        assert_eq!(app.messages[n - 2].content, "explain");
+        assert_eq!(app.messages[n - 3].content, "first answer");
+        assert!(app.messages[n - 1].content.contains("regenerating"));
+        assert!(app.input.value().is_empty());
     }
 
     #[test]
-    fn init_with_replay_messages() {
-        let (user_tx, _user_rx) = mpsc::channel(16);
-        let (_agent_tx, agent_rx) = mpsc::channel(64);
-        let flags = Flags {
-            user_tx,
-            agent_rx,
-            model_name: "test-model".to_string(),
-            tool_count: 5,
-            context_window: 128_000,
-            workspace_dir: "/tmp/test".to_string(),
-            replay_messages: vec![
-                ChatMessage {
-                    kind: ChatMessageKind::User,
-                    content: "replayed user msg".to_string(),
-                },
-                ChatMessage {
-                    kind: ChatMessageKind::Assistant,
-                    content: "replayed assistant msg".to_string(),
-                },
-            ],
-            startup_message: "Test startup".to_string(),
-        };
+    fn slash_forget_blanks_the_chosen_user_message() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "the key is sk-live-123".to_string());
+        app.push_message(
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "read_file".to_string(),
+                status: ToolCallStatus::Allowed,
+                approval_reason: None,
+            },
+            "read_file a.txt".to_string(),
+        );
+        app.push_message(ChatMessageKind::Assistant, "done".to_string());
+        app.push_message(ChatMessageKind::User, "thanks".to_string());
+        let before = app.messages.len();
 
-        let (app, _cmd) = ClawApp::init(flags);
+        app.input.set_value("/forget 2");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!cmd.is_none());
+        // Nothing is blanked until the agent has redacted it.
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.content.contains("sk-live-123"))
+        );
 
-        // Should have: startup message + 2 replay messages + "Session resumed"
-        assert_eq!(app.messages.len(), 4);
-        assert_eq!(app.messages[0].kind, ChatMessageKind::System);
-        assert_eq!(app.messages[0].content, "Test startup");
-        assert_eq!(app.messages[1].kind, ChatMessageKind::User);
-        assert_eq!(app.messages[1].content, "replayed user msg");
-        assert_eq!(app.messages[2].kind, ChatMessageKind::Assistant);
-        assert_eq!(app.messages[2].content, "replayed assistant msg");
-        assert_eq!(app.messages[3].kind, ChatMessageKind::System);
-        assert!(app.messages[3].content.contains("Session resumed"));
+        app.update(Msg::Agent(AgentEvent::Forgotten { nth: 2 }));
+        assert_eq!(app.messages.len(), before);
+        assert!(
+            app.messages
+                .iter()
+                .all(|m| !m.content.contains("sk-live-123"))
+        );
+        assert!(app.messages.iter().any(|m| m.content == REDACTED));
+        assert_eq!(app.messages[before - 1].content, "thanks");
     }
 
-    // --- Agent event update() tests ---
-
     #[test]
-    fn update_text_delta_appends() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
+    fn pins_are_marked_on_the_chosen_message() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "which port?".to_string());
+        app.push_message(ChatMessageKind::Assistant, "8443".to_string());
+        app.push_message(ChatMessageKind::User, "thanks".to_string());
+        app.push_message(ChatMessageKind::Assistant, "any time".to_string());
+
+        app.update(Msg::Agent(AgentEvent::Pinned {
+            nth: 2,
+            reply: true,
+            pinned: true,
+        }));
+        app.update(Msg::Agent(AgentEvent::Pinned {
+            nth: 2,
+            reply: false,
+            pinned: true,
+        }));
+        let pinned: Vec<&str> = app
+            .messages
+            .iter()
+            .filter(|m| m.pinned)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(pinned, ["which port?", "8443"]);
 
-        app.update(Msg::Agent(AgentEvent::TextDelta("Hello".to_string())));
-        app.update(Msg::Agent(AgentEvent::TextDelta(" world".to_string())));
+        app.update(Msg::Agent(AgentEvent::Pinned {
+            nth: 2,
+            reply: true,
+            pinned: false,
+        }));
+        assert!(!app.messages.iter().any(|m| m.content == "8443" && m.pinned));
+    }
 
-        // Startup message + one assistant message
-        assert_eq!(app.messages.len(), 2);
-        assert_eq!(app.messages[1].kind, ChatMessageKind::Assistant);
-        assert_eq!(app.messages[1].content, "Hello world");
+    #[test]
+    fn slash_forget_needs_a_message_that_exists() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "only".to_string());
+
+        for input in ["/forget 0", "/forget zero"] {
+            app.input.set_value(input);
+            let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+            assert!(cmd.is_none(), "{}", input);
+        }
+        // The agent found nothing that far back, so it didn't answer Forgotten.
+        app.input.set_value("/forget 2");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.messages.iter().any(|m| m.content == "only"));
     }
 
     #[test]
-    fn update_done_stops_streaming() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
+    fn slash_retry_is_refused_while_streaming() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "explain".to_string());
+        app.push_message(ChatMessageKind::Assistant, "an answer".to_string());
         app.streaming = true;
+        app.input.set_value("/retry");
 
-        let cmd = app.update(Msg::Agent(AgentEvent::Done));
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
 
-        assert!(!app.streaming);
         assert!(cmd.is_none());
+        assert!(app.messages.iter().any(|m| m.content == "an answer"));
+    }
+
+    /// An app whose workspace holds notes.md.
+    fn editor_app() -> (ClawApp, tempfile::TempDir) {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let workspace = tempfile::tempdir().unwrap();
+        std::fs::write(workspace.path().join("notes.md"), "draft\n").unwrap();
+        app.workspace_dir = workspace.path().to_string_lossy().to_string();
+        (app, workspace)
+    }
+
+    fn open_notes(
+        app: &mut ClawApp,
+        path: &str,
+    ) -> (Command<Msg>, oneshot::Receiver<Result<String, String>>) {
+        let (tx, rx) = oneshot::channel();
+        let cmd = app.update(Msg::Agent(AgentEvent::OpenInEditor {
+            tool_call_id: "call-1".to_string(),
+            path: path.to_string(),
+            responder: tx,
+        }));
+        (cmd, rx)
     }
 
     #[test]
-    fn update_done_sends_queued_message() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
-        app.streaming = true;
-        app.queued_message = Some("follow up".to_string());
+    fn editor_request_stops_terminal_input_until_the_editor_closes() {
+        let (mut app, _workspace) = editor_app();
+        let subscribed = app.subscriptions().len();
 
-        let cmd = app.update(Msg::Agent(AgentEvent::Done));
+        let (cmd, mut rx) = open_notes(&mut app, "notes.md");
+        assert!(!cmd.is_none(), "the editor runs from a command");
+        assert!(app.editing.is_some());
+        assert_eq!(
+            app.subscriptions().len(),
+            subscribed - 1,
+            "the terminal event reader is dropped while the editor runs"
+        );
+        assert!(rx.try_recv().is_err());
+
+        let cmd = app.update(Msg::EditorClosed(Ok("first\nsecond\n".to_string())));
+        assert_eq!(rx.try_recv().unwrap(), Ok("first\nsecond\n".to_string()));
+        assert!(app.editing.is_none());
+        assert_eq!(app.subscriptions().len(), subscribed);
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Edited notes.md (2 lines)")
+        );
+        // One blank frame, then a full repaint.
+        assert!(app.blank_frame);
+        assert!(!cmd.is_none());
+        app.update(Msg::Repaint);
+        assert!(!app.blank_frame);
+    }
 
-        assert!(app.streaming); // re-set to true for the queued send
-        assert!(app.queued_message.is_none());
-        assert!(!cmd.is_none()); // should have returned a send command
-        // The queued message should have been pushed as a User message
-        let user_msgs: Vec<_> = app
-            .messages
-            .iter()
-            .filter(|m| m.kind == ChatMessageKind::User)
-            .collect();
-        assert_eq!(user_msgs.len(), 1);
-        assert_eq!(user_msgs[0].content, "follow up");
+    #[test]
+    fn editor_failures_go_back_to_the_agent() {
+        let (mut app, _workspace) = editor_app();
+        let (_, mut rx) = open_notes(&mut app, "notes.md");
+
+        app.update(Msg::EditorClosed(Err(
+            "editor `vim` exited with 1".to_string()
+        )));
+
+        let outcome = rx.try_recv().unwrap();
+        assert!(outcome.unwrap_err().contains("exited with 1"));
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .starts_with("Couldn't edit notes.md")
+        );
     }
 
     #[test]
-    fn update_error_stops_streaming() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
-        app.streaming = true;
+    fn editor_requests_outside_the_workspace_are_refused() {
+        let (mut app, _workspace) = editor_app();
+        for path in ["../outside.md", "/etc/passwd"] {
+            let (cmd, mut rx) = open_notes(&mut app, path);
+            assert!(cmd.is_none(), "{} must not open", path);
+            assert!(app.editing.is_none());
+            assert!(
+                rx.try_recv()
+                    .unwrap()
+                    .unwrap_err()
+                    .contains("outside the workspace")
+            );
+        }
+    }
 
-        app.update(Msg::Agent(AgentEvent::Error("oops".to_string())));
+    #[test]
+    fn slash_edit_opens_the_file_and_needs_a_path() {
+        let (mut app, _workspace) = editor_app();
 
+        app.input.set_value("/edit");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(app.messages.last().unwrap().content, "Usage: /edit <path>");
+
+        app.input.set_value("/edit notes.md");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!cmd.is_none());
+        assert!(app.editing.is_some());
+        app.update(Msg::EditorClosed(Ok("edited\n".to_string())));
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Edited notes.md")
+        );
         assert!(!app.streaming);
-        let last = app.messages.last().unwrap();
-        assert_eq!(last.kind, ChatMessageKind::System);
-        assert!(last.content.contains("oops"));
     }
 
     #[test]
-    fn update_tool_call_started() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
+    fn slash_revert_asks_before_restoring_a_file() {
+        let session = tempfile::tempdir().unwrap();
+        let workspace = tempfile::tempdir().unwrap();
+        let file = workspace.path().join("notes.md");
+        std::fs::write(&file, "agent's").unwrap();
+        let mut journal = ChangeJournal::open(session.path(), false).unwrap();
+        journal
+            .record_write(&file, Some(b"mine"), Some(b"agent's"))
+            .unwrap();
 
-        app.update(Msg::Agent(AgentEvent::ToolCallStarted {
-            tool_name: "read_file".to_string(),
-            params_summary: "path=/tmp".to_string(),
-        }));
+        let mut flags = test_flags();
+        flags.workspace_dir = workspace.path().to_string_lossy().to_string();
+        flags.changes = Some(Arc::new(std::sync::Mutex::new(journal)));
+        let (mut app, _) = ClawApp::init(flags);
+        let enter = || Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+
+        app.input.set_value("/changes");
+        app.update(enter());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("notes.md  1 write, +3 bytes")
+        );
 
-        let last = app.messages.last().unwrap();
-        assert_eq!(
-            last.kind,
-            ChatMessageKind::ToolCall {
-                tool_name: "read_file".to_string(),
-                status: ToolCallStatus::Pending,
-            }
+        app.input.set_value("/revert notes.md");
+        app.update(enter());
+        assert!(
+            app.pending_question
+                .as_ref()
+                .unwrap()
+                .question
+                .starts_with("Restore ")
+        );
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('n'),
+            KeyModifiers::NONE,
+        )));
+        assert_eq!(app.messages.last().unwrap().content, "Revert cancelled.");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "agent's");
+
+        app.input.set_value("/revert notes.md");
+        app.update(enter());
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('y'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_question.is_none());
+        assert!(app.messages.last().unwrap().content.contains("Restored"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "mine");
+
+        app.input.set_value("/revert notes.md");
+        app.update(enter());
+        assert!(app.pending_question.is_none());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .starts_with("Can't revert: no write")
         );
-        assert_eq!(last.content, "read_file(path=/tmp)");
     }
 
     #[test]
-    fn update_tool_approved_updates_status() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
+    fn slash_effort_sends_valid_levels_only() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("/effort high");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!cmd.is_none());
+        assert!(!app.streaming);
+
+        app.input.set_value("/effort extreme");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(cmd.is_none());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Usage: /effort")
+        );
+    }
+
+    #[test]
+    fn slash_observe_toggles_between_turns_only() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("/observe");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!cmd.is_none());
+        assert!(app.observe);
+
+        app.streaming = true;
+        app.input.set_value("/observe");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.observe);
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Can't toggle observe")
+        );
+    }
+
+    fn quiet(app: &mut ClawApp, command: &str) {
+        app.input.set_value(command);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+    }
+
+    fn rendered_chat(app: &ClawApp) -> Vec<String> {
+        render_chat_lines(&app.messages, &app.chat_options)
+            .iter()
+            .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+            .collect()
+    }
 
+    #[test]
+    fn slash_quiet_switches_tool_output_without_losing_it() {
+        let (mut app, _) = ClawApp::init(test_flags());
         app.update(Msg::Agent(AgentEvent::ToolCallStarted {
-            tool_name: "write_file".to_string(),
-            params_summary: "path=/tmp".to_string(),
+            tool_call_id: "c1".into(),
+            tool_name: "bash".into(),
+            params_summary: "cargo test".into(),
         }));
         app.update(Msg::Agent(AgentEvent::ToolCallApproved {
-            tool_name: "write_file".to_string(),
+            tool_call_id: "c1".into(),
+            tool_name: "bash".into(),
+            reason: None,
         }));
-
-        let last = app.messages.last().unwrap();
-        assert_eq!(
-            last.kind,
-            ChatMessageKind::ToolCall {
-                tool_name: "write_file".to_string(),
-                status: ToolCallStatus::Allowed,
-            }
+        app.update(Msg::Agent(AgentEvent::ToolResult {
+            tool_call_id: "c1".into(),
+            tool_name: "bash".into(),
+            content: "114 passed\nfinished in 2.3s".into(),
+            is_error: false,
+        }));
+        let full = rendered_chat(&app);
+        assert!(full.iter().any(|l| l.ends_with("finished in 2.3s")));
+
+        quiet(&mut app, "/quiet");
+        assert_eq!(app.chat_options.tool_verbosity, ToolVerbosity::Compact);
+        assert_eq!(app.messages.last().unwrap().content, "Tool output: compact");
+        let compact = rendered_chat(&app);
+        assert!(
+            compact
+                .iter()
+                .any(|l| l.ends_with("→ 114 passed (+1 line)"))
+        );
+        assert!(!compact.iter().any(|l| l.ends_with("finished in 2.3s")));
+
+        quiet(&mut app, "/quiet hidden");
+        assert!(rendered_chat(&app).contains(&"🔧 1 tool call".to_string()));
+
+        // Ctrl+O shows everything again, and goes back to hidden after.
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('o'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.chat_options.tool_verbosity, ToolVerbosity::Full);
+        assert!(
+            rendered_chat(&app)
+                .iter()
+                .any(|l| l.ends_with("finished in 2.3s"))
+        );
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('o'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.chat_options.tool_verbosity, ToolVerbosity::Hidden);
+
+        quiet(&mut app, "/quiet loud");
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .starts_with("Usage: /quiet")
         );
+        assert_eq!(app.chat_options.tool_verbosity, ToolVerbosity::Hidden);
     }
 
     #[test]
-    fn update_needs_approval_sets_pending() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
-
-        let (tx, _rx) = tokio::sync::oneshot::channel();
-        app.update(Msg::Agent(AgentEvent::ToolCallNeedsApproval {
-            description: "Write to disk".to_string(),
-            pattern: Some("write_*".to_string()),
-            tool_name: "write_file".to_string(),
-            responder: tx,
-        }));
+    fn slash_command_output_stays_out_of_the_conversation() {
+        let mut flags = test_flags();
+        let (user_tx, mut user_rx) = mpsc::channel(16);
+        flags.user_tx = user_tx;
+        let (mut app, _) = ClawApp::init(flags);
+
+        app.input.set_value("/help");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(cmd.is_none());
+        assert!(user_rx.try_recv().is_err());
+        let help = app.messages.last().unwrap();
+        assert!(help.content.contains("/quiet"));
+        assert!(help.display_only);
 
-        assert!(app.pending_approval.is_some());
-        let approval = app.pending_approval.as_ref().unwrap();
-        assert_eq!(approval.description, "Write to disk");
-        assert_eq!(approval.tool_name, "write_file");
-        assert_eq!(approval.pattern, Some("write_*".to_string()));
-        assert!(app.chat_viewport.at_bottom());
+        app.push_message(ChatMessageKind::User, "hi".to_string());
+        let sent: Vec<&str> = conversation(&app.messages)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(sent, ["hi"]);
+    }
+
+    fn send_copy(app: &mut ClawApp) {
+        app.input.set_value("/copy");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
     }
 
     #[test]
-    fn update_ask_user_sets_pending_question() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
+    fn slash_copy_asks_once_then_copies() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.copy_text = |_| Ok(());
+        app.push_message(ChatMessageKind::Assistant, "the reply".to_string());
 
-        let (tx, _rx) = tokio::sync::oneshot::channel();
-        app.update(Msg::Agent(AgentEvent::AskUser {
-            question: "What is your name?".to_string(),
-            tool_call_id: "call-42".to_string(),
-            options: vec!["Alice".to_string(), "Bob".to_string()],
-            responder: tx,
-        }));
+        send_copy(&mut app);
+        let pending = app.pending_consent.as_ref().expect("consent prompt shown");
+        assert_eq!(pending.feature, Feature::Osc52);
+        assert_eq!(pending.payload, "the reply");
 
-        assert!(app.pending_question.is_some());
-        let q = app.pending_question.as_ref().unwrap();
-        assert_eq!(q.question, "What is your name?");
-        assert_eq!(q.tool_call_id, "call-42");
-        assert_eq!(q.options, vec!["Alice", "Bob"]);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('1'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_consent.is_none());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Copied the last reply (9 chars)")
+        );
+
+        // Allowed for the rest of the session: no second prompt.
+        send_copy(&mut app);
+        assert!(app.pending_consent.is_none());
     }
 
     #[test]
-    fn update_usage_tracks_tokens() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
-
-        app.update(Msg::Agent(AgentEvent::Usage {
-            input_tokens: 100,
-            output_tokens: 50,
-        }));
+    fn slash_copy_declined_stays_declined_for_the_session() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.copy_text = |_| panic!("must not copy");
+        app.push_message(ChatMessageKind::Assistant, "the reply".to_string());
+
+        send_copy(&mut app);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(app.pending_consent.is_none());
+
+        send_copy(&mut app);
+        assert!(app.pending_consent.is_none());
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("allow_osc52 = true")
+        );
+    }
 
-        assert_eq!(app.total_tokens, 150);
-        assert_eq!(app.context_used, 100);
+    #[test]
+    fn slash_copy_always_is_saved_and_skips_the_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("consents.json");
+        let mut flags = test_flags();
+        flags.consents = Consents::load(&path);
+        let (mut app, _) = ClawApp::init(flags);
+        app.copy_text = |_| Ok(());
+        app.push_message(ChatMessageKind::Assistant, "the reply".to_string());
+
+        send_copy(&mut app);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        assert!(path.exists());
+
+        let mut flags = test_flags();
+        flags.consents = Consents::load(&path);
+        let (mut next, _) = ClawApp::init(flags);
+        next.copy_text = |_| Ok(());
+        next.push_message(ChatMessageKind::Assistant, "later".to_string());
+        send_copy(&mut next);
+        assert!(next.pending_consent.is_none());
+        assert!(next.messages.last().unwrap().content.contains("Copied"));
     }
 
     #[test]
-    fn update_compaction_messages() {
-        let (mut app, _cmd) = ClawApp::init(test_flags());
+    fn slash_regen_without_an_answer_does_nothing() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "explain".to_string());
+        // A note after the question isn't an answer either.
+        app.push_message(ChatMessageKind::System, "Stream error".to_string());
+        app.input.set_value("/regen");
 
-        app.update(Msg::Agent(AgentEvent::CompactionStarted));
-        let compacting_msg = app.messages.last().unwrap();
-        assert_eq!(compacting_msg.kind, ChatMessageKind::System);
-        assert!(compacting_msg.content.contains("Compacting"));
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
 
-        app.update(Msg::Agent(AgentEvent::CompactionDone {
-            old_count: 50,
-            new_count: 10,
-        }));
-        let done_msg = app.messages.last().unwrap();
-        assert_eq!(done_msg.kind, ChatMessageKind::System);
-        assert!(done_msg.content.contains("50"));
-        assert!(done_msg.content.contains("10"));
-        assert!(done_msg.content.contains("Compacted"));
+        assert!(cmd.is_none());
+        assert!(!app.streaming);
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("Nothing to regenerate")
+        );
     }
 
-    // --- Key, Mouse, Paste handling tests (Task 5) ---
-
     #[test]
-    fn key_esc_quits() {
+    fn slash_regen_keeps_the_notes_shown_after_the_answer() {
         let (mut app, _) = ClawApp::init(test_flags());
-        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
-        let cmd = app.update(Msg::Key(key));
-        assert!(!cmd.is_none());
+        app.push_message(ChatMessageKind::User, "explain".to_string());
+        app.push_message(ChatMessageKind::Assistant, "a long answer".to_string());
+        app.push_message(ChatMessageKind::System, "Context: 40% used".to_string());
+        app.input.set_value("/regen");
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+
+        assert!(app.messages.iter().all(|m| m.content != "a long answer"));
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.content == "Context: 40% used")
+        );
+        let sent: Vec<&str> = conversation(&app.messages)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(sent.last(), Some(&"explain"));
     }
 
     #[test]
-    fn key_esc_during_streaming_does_nothing() {
+    fn reloaded_event_replaces_skill_report() {
         let (mut app, _) = ClawApp::init(test_flags());
-        app.streaming = true;
-        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
-        let cmd = app.update(Msg::Key(key));
-        assert!(cmd.is_none());
+
+        app.update(Msg::Agent(AgentEvent::Reloaded {
+            skills: vec![
+                skill("deploy", SkillStatus::Loaded),
+                skill("huge", SkillStatus::TooLarge),
+            ],
+        }));
+
+        assert_eq!(app.skills.len(), 2);
+        assert!(
+            app.messages
+                .last()
+                .unwrap()
+                .content
+                .contains("1 loaded, 1 skipped")
+        );
     }
 
     #[test]
-    fn key_enter_sends_message() {
+    fn mcp_server_coming_up_updates_the_tool_count() {
         let (mut app, _) = ClawApp::init(test_flags());
-        app.input.set_value("hello world");
-        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
-        let cmd = app.update(Msg::Key(key));
-        assert!(!cmd.is_none());
-        assert!(app.streaming);
-        assert_eq!(app.input.value(), "");
-        // User message should have been pushed
-        let user_msgs: Vec<_> = app
-            .messages
-            .iter()
-            .filter(|m| m.kind == ChatMessageKind::User)
-            .collect();
-        assert_eq!(user_msgs.len(), 1);
-        assert_eq!(user_msgs[0].content, "hello world");
+        assert_eq!(app.tool_count, 5);
+
+        app.update(Msg::Agent(AgentEvent::McpConnected {
+            server: "github".to_string(),
+            tools: 12,
+            tool_count: 17,
+        }));
+
+        assert_eq!(app.tool_count, 17);
+        assert_eq!(
+            app.messages.last().unwrap().content,
+            "\u{1f50c} MCP 'github' connected: 12 tools"
+        );
     }
 
     #[test]
-    fn key_enter_empty_does_nothing() {
+    fn unknown_command_is_reported_not_sent() {
         let (mut app, _) = ClawApp::init(test_flags());
-        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
-        let cmd = app.update(Msg::Key(key));
+        app.input.set_value("/not-a-command");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
         assert!(cmd.is_none());
         assert!(!app.streaming);
+        assert!(app.input.value().is_empty());
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::System);
+        assert!(last.content.contains("Unknown command /not-a-command"));
+        assert!(!app.messages.iter().any(|m| m.kind == ChatMessageKind::User));
     }
 
     #[test]
-    fn key_enter_during_streaming_queues() {
+    fn text_starting_with_a_path_is_sent_as_message() {
         let (mut app, _) = ClawApp::init(test_flags());
-        app.streaming = true;
-        app.input.set_value("follow up");
-        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
-        app.update(Msg::Key(key));
-        assert_eq!(app.queued_message, Some("follow up".to_string()));
-        assert_eq!(app.input.value(), "");
+        app.input.set_value("/usr/bin/env is missing");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!cmd.is_none());
+        assert!(app.streaming);
     }
 
     #[test]
-    fn single_ctrl_c_clears_input_does_not_quit() {
+    fn slash_help_lists_every_command() {
         let (mut app, _) = ClawApp::init(test_flags());
-        app.input.set_value("some text");
-        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
-        let cmd = app.update(Msg::Key(key));
-        assert!(cmd.is_none(), "single Ctrl+C should not quit");
-        assert_eq!(app.input.value(), "", "single Ctrl+C should clear input");
+        app.input.set_value("/help");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(cmd.is_none());
+        let help = &app.messages.last().unwrap().content;
+        for command in app.commands.completions("") {
+            assert!(help.contains(&format!("/{}", command.name())));
+        }
     }
 
     #[test]
-    fn double_ctrl_c_quits() {
+    fn typing_a_command_shows_completions_and_tab_completes() {
         let (mut app, _) = ClawApp::init(test_flags());
-        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
-        // First press primes the timer.
-        let cmd = app.update(Msg::Key(key));
+        assert!(app.completion_popup().is_none());
+
+        app.input.set_value("/re");
+        let (items, more) = app.completion_popup().unwrap();
+        let usages: Vec<&str> = items.iter().map(|i| i.usage.as_str()).collect();
+        assert_eq!(usages, ["/regen [hint]", "/reload", "/revert <path>"]);
+        assert_eq!(more, 0);
+
+        app.input.set_value("/ed");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)));
+        assert_eq!(app.input.value(), "/edit ");
+        assert!(
+            app.completion_popup().is_none(),
+            "closes once arguments start"
+        );
+
+        // Esc closes the popup without quitting, until the input changes.
+        app.input.set_value("/to");
+        let cmd = app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
         assert!(cmd.is_none());
-        // Second press within 500ms quits.
-        let cmd = app.update(Msg::Key(key));
-        assert!(!cmd.is_none(), "double Ctrl+C should quit");
+        assert!(app.completion_popup().is_none());
+        app.input.set_value("/t");
+        assert!(app.completion_popup().is_some());
     }
 
     #[test]
@@ -1136,6 +5300,24 @@ mod tests {
         assert!(!cmd.is_none(), "Ctrl+Q should quit immediately");
     }
 
+    #[test]
+    fn quitting_signals_shutdown_before_the_tui_closes() {
+        let mut flags = test_flags();
+        let shutdown = ShutdownFlag::default();
+        flags.shutdown = shutdown.clone();
+        let (mut app, _) = ClawApp::init(flags);
+        app.streaming = true;
+
+        // Quitting once the turn is done waits for Done.
+        app.pending_quit = Some(PendingQuit { selected: 1 });
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.quit_after_turn);
+        assert!(!shutdown.is_requested());
+
+        app.update(Msg::Agent(AgentEvent::Done));
+        assert!(shutdown.is_requested());
+    }
+
     #[test]
     fn key_pageup_scrolls() {
         let (mut app, _) = ClawApp::init(test_flags());
@@ -1152,6 +5334,56 @@ mod tests {
         assert!(cmd.is_none());
     }
 
+    #[test]
+    fn ctrl_home_at_the_top_shows_the_notice() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        assert!(app.top_notice.is_none());
+        let key = KeyEvent::new(KeyCode::Home, KeyModifiers::CONTROL);
+        let cmd = app.update(Msg::Key(key));
+        assert!(cmd.is_none());
+        assert!(app.top_notice.is_some());
+    }
+
+    #[test]
+    fn rebuilding_the_chat_records_message_lines() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "hi".to_string());
+        app.push_message(ChatMessageKind::Assistant, "hello".to_string());
+        assert_eq!(app.message_lines.len(), app.messages.len());
+        assert!(app.chat_lines > *app.message_lines.last().unwrap());
+    }
+
+    #[test]
+    fn page_size_is_viewport_height_minus_overlap() {
+        assert_eq!(page_size(40, 1), 39);
+        assert_eq!(page_size(10, 2), 8);
+    }
+
+    #[test]
+    fn page_size_never_zero() {
+        assert_eq!(page_size(1, 1), 1);
+        assert_eq!(page_size(1, 5), 1);
+    }
+
+    #[test]
+    fn page_size_defaults_before_first_render() {
+        assert_eq!(page_size(0, PAGE_OVERLAP), DEFAULT_PAGE_SIZE);
+    }
+
+    #[test]
+    fn render_records_chat_height_for_paging() {
+        let (app, _) = ClawApp::init(test_flags());
+        assert_eq!(app.page_size(), DEFAULT_PAGE_SIZE);
+
+        let backend = ratatui::backend::TestBackend::new(80, 40);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.view(frame)).unwrap();
+
+        let height = app.chat_height.load(Ordering::Relaxed);
+        assert!(height > 20 && height < 40);
+        assert_eq!(app.page_size(), height - PAGE_OVERLAP);
+    }
+
     #[test]
     fn mouse_scroll_up() {
         let (mut app, _) = ClawApp::init(test_flags());
@@ -1203,6 +5435,63 @@ mod tests {
         assert_eq!(app.input.value(), "hello");
     }
 
+    fn snippet_app(text: &str, submit: bool) -> ClawApp {
+        let mut flags = test_flags();
+        flags.snippets = vec![SnippetConfig {
+            key: "f2".to_string(),
+            text: text.to_string(),
+            submit,
+        }];
+        ClawApp::init(flags).0
+    }
+
+    #[test]
+    fn snippet_key_inserts_text_at_cursor() {
+        let mut app = snippet_app("run the tests", false);
+        app.input.set_value("please ");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE)));
+        assert_eq!(app.input.value(), "please run the tests");
+        assert!(!app.streaming);
+    }
+
+    #[test]
+    fn submit_snippet_sends_immediately() {
+        let mut app = snippet_app("run the tests and fix failures", true);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE)));
+        assert!(app.streaming);
+        assert!(app.input.value().is_empty());
+        let n = app.messages.len();
+        assert_eq!(app.messages[n - 2].kind, ChatMessageKind::User);
+        assert_eq!(
+            app.messages[n - 2].content,
+            "run the tests and fix failures"
+        );
+        assert!(is_thinking(&app.messages[n - 1]));
+    }
+
+    #[test]
+    fn selection_snippet_wraps_the_draft() {
+        let mut app = snippet_app("Explain this:\n{selection}", false);
+        app.input.set_value("fn main() {}");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE)));
+        assert_eq!(app.input.value(), "Explain this:\nfn main() {}");
+    }
+
+    #[test]
+    fn clipboard_snippet_fills_in_once_the_clipboard_arrives() {
+        let mut app = snippet_app("Review:\n{clipboard}", false);
+        let snippet = app.snippets[0].clone();
+        app.update(Msg::SnippetClipboard(
+            snippet.clone(),
+            Some("+ added".to_string()),
+        ));
+        assert_eq!(app.input.value(), "Review:\n+ added");
+
+        app.input.set_value("");
+        app.update(Msg::SnippetClipboard(snippet, None));
+        assert_eq!(app.input.value(), "Review:\n");
+    }
+
     #[test]
     fn paste_inserts_text() {
         let (mut app, _) = ClawApp::init(test_flags());
@@ -1210,6 +5499,18 @@ mod tests {
         assert!(app.input.value().contains("pasted text"));
     }
 
+    #[test]
+    fn draft_estimate_updates_after_paste() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        assert_eq!(draft_tokens(&app.input.value()), 0);
+        app.update(Msg::Paste("x".repeat(4_000)));
+        assert_eq!(draft_tokens(&app.input.value()), 1_000);
+        app.update(Msg::Paste("y".repeat(400)));
+        assert_eq!(draft_tokens(&app.input.value()), 1_100);
+        app.input.set_value("");
+        assert_eq!(draft_tokens(&app.input.value()), 0);
+    }
+
     #[test]
     fn paste_blocked_during_approval() {
         let (mut app, _) = ClawApp::init(test_flags());
@@ -1217,6 +5518,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1225,6 +5529,129 @@ mod tests {
         assert!(!app.input.value().contains("should not appear"));
     }
 
+    fn small_paste_app() -> ClawApp {
+        let mut flags = test_flags();
+        flags.tui_config = TuiConfig {
+            max_paste_bytes: 64,
+            paste_insert_lines: 2,
+            ..TuiConfig::default()
+        };
+        ClawApp::init(flags).0
+    }
+
+    #[test]
+    fn kill_word_and_yank_round_trip() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("git commit --amend");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('w'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.input.value(), "git commit --");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('w'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.input.value(), "git commit ");
+        assert_eq!(app.kill_buffer, "--");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('y'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.input.value(), "git commit --");
+    }
+
+    #[test]
+    fn kill_to_line_start_keeps_earlier_lines() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("keep this\ndrop this");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('u'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.input.value(), "keep this\n");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('y'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.input.value(), "keep this\ndrop this");
+    }
+
+    #[test]
+    fn word_left_then_kill_to_end() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("cargo test --workspace");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Left, KeyModifiers::ALT)));
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Left, KeyModifiers::ALT)));
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('k'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(app.input.value(), "cargo test ");
+        assert_eq!(app.kill_buffer, "--workspace");
+    }
+
+    #[test]
+    fn paste_is_sanitized() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.update(Msg::Paste("one\r\ntwo\0\rthree".to_string()));
+        assert_eq!(app.input.value(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn oversized_paste_prompts_instead_of_inserting() {
+        let mut app = small_paste_app();
+        app.update(Msg::Paste("x".repeat(64)));
+        assert!(app.pending_paste.is_none());
+        assert_eq!(app.input.value().len(), 64);
+
+        app.input.set_value("");
+        app.update(Msg::Paste("x".repeat(65)));
+        assert!(app.pending_paste.is_some());
+        assert!(app.input.value().is_empty());
+    }
+
+    #[test]
+    fn oversized_paste_first_lines() {
+        let mut app = small_paste_app();
+        let text = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        app.update(Msg::Paste(text));
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('f'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_paste.is_none());
+        assert_eq!(app.input.value(), "line 1\nline 2");
+    }
+
+    #[test]
+    fn oversized_paste_attach_truncates() {
+        let mut app = small_paste_app();
+        let text = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        app.update(Msg::Paste(text));
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        let value = app.input.value();
+        assert!(value.starts_with("[pasted text: 20 lines"));
+        assert!(value.contains("line 1\n"));
+        assert!(!value.contains("line 20"));
+        assert!(value.contains("truncated"));
+    }
+
+    #[test]
+    fn oversized_paste_cancel_drops_it() {
+        let mut app = small_paste_app();
+        app.update(Msg::Paste("x".repeat(100)));
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(app.pending_paste.is_none());
+        assert!(app.input.value().is_empty());
+    }
+
     #[test]
     fn key_up_on_first_line_scrolls_chat() {
         let (mut app, _) = ClawApp::init(test_flags());
@@ -1277,6 +5704,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1296,6 +5726,7 @@ mod tests {
             question: "test?".to_string(),
             tool_call_id: "call-1".to_string(),
             options: vec!["a".to_string(), "b".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1317,6 +5748,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "bash(ls)".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1334,6 +5768,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1351,6 +5788,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1358,7 +5798,95 @@ mod tests {
         let key = KeyEvent::new(KeyCode::Char('3'), KeyModifiers::NONE);
         app.update(Msg::Key(key));
         assert!(app.pending_approval.is_none());
-        assert_eq!(rx.blocking_recv().unwrap(), ApprovalDecision::Deny);
+        assert_eq!(rx.blocking_recv().unwrap(), ApprovalDecision::Deny);
+    }
+
+    #[test]
+    fn approval_char_4_trusts_the_tool() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.pending_approval = Some(PendingApproval {
+            description: "test".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
+            tool_name: "mcp_deploy".to_string(),
+            selected: 0,
+            responder: Some(tx),
+        });
+        let key = KeyEvent::new(KeyCode::Char('4'), KeyModifiers::NONE);
+        app.update(Msg::Key(key));
+        assert!(app.pending_approval.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), ApprovalDecision::AllowTool);
+    }
+
+    fn scoped_write_approval() -> (
+        PendingApproval,
+        tokio::sync::oneshot::Receiver<ApprovalDecision>,
+    ) {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let scope = |label: &str| crate::approval::PathScope {
+            label: label.to_string(),
+            pattern: format!("/work/{}", label),
+        };
+        let approval = PendingApproval {
+            description: "write_file: src/agent/loop.rs".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![scope("src/agent/loop.rs"), scope("src/agent/**")],
+            scope_selected: None,
+            tool_name: "write_file".to_string(),
+            selected: 0,
+            responder: Some(tx),
+        };
+        (approval, rx)
+    }
+
+    #[test]
+    fn always_allowing_a_write_picks_a_scope() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (approval, mut rx) = scoped_write_approval();
+        app.pending_approval = Some(approval);
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        let pending = app.pending_approval.as_ref().expect("chooser stays open");
+        assert_eq!(pending.scope_selected, Some(0));
+        assert!(rx.try_recv().is_err());
+
+        // Esc backs out to the main prompt without answering.
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert_eq!(app.pending_approval.as_ref().unwrap().scope_selected, None);
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE)));
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.pending_approval.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), ApprovalDecision::AllowScope(1));
+    }
+
+    #[test]
+    fn the_last_scope_choice_allows_writes_anywhere() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (approval, rx) = scoped_write_approval();
+        app.pending_approval = Some(approval);
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('2'),
+            KeyModifiers::NONE,
+        )));
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('3'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_approval.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), ApprovalDecision::AllowAlways);
     }
 
     #[test]
@@ -1368,6 +5896,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1384,6 +5915,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 0,
             responder: Some(tx),
@@ -1394,19 +5928,22 @@ mod tests {
     }
 
     #[test]
-    fn approval_right_clamps_at_2() {
+    fn approval_right_clamps_at_last_option() {
         let (mut app, _) = ClawApp::init(test_flags());
         let (tx, _rx) = tokio::sync::oneshot::channel();
         app.pending_approval = Some(PendingApproval {
             description: "test".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
-            selected: 2,
+            selected: 3,
             responder: Some(tx),
         });
         let key = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
         app.update(Msg::Key(key));
-        assert_eq!(app.pending_approval.as_ref().unwrap().selected, 2);
+        assert_eq!(app.pending_approval.as_ref().unwrap().selected, 3);
     }
 
     // --- Question mode tests (Task 7) ---
@@ -1419,6 +5956,7 @@ mod tests {
             question: "Name?".to_string(),
             tool_call_id: "c1".to_string(),
             options: vec![],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1430,6 +5968,329 @@ mod tests {
         assert_eq!(app.input.value(), "");
     }
 
+    fn confirm_question(default_yes: bool) -> (ClawApp, tokio::sync::oneshot::Receiver<String>) {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Proceed?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Confirm { default_yes },
+            selected: 0,
+            responder: Some(tx),
+        });
+        (app, rx)
+    }
+
+    #[test]
+    fn confirm_question_answers_with_y_and_n_keys() {
+        for (c, expected) in [('y', "yes"), ('Y', "yes"), ('n', "no"), ('N', "no")] {
+            let (mut app, rx) = confirm_question(true);
+            app.update(Msg::Key(KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+            )));
+            assert!(app.pending_question.is_none(), "{} should answer", c);
+            assert_eq!(rx.blocking_recv().unwrap(), expected);
+            assert_eq!(app.input.value(), "", "{} must not reach the input", c);
+        }
+    }
+
+    #[test]
+    fn confirm_question_enter_gives_the_default() {
+        let (mut app, rx) = confirm_question(true);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(rx.blocking_recv().unwrap(), "yes");
+
+        let (mut app, rx) = confirm_question(false);
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(rx.blocking_recv().unwrap(), "no");
+    }
+
+    #[test]
+    fn confirm_question_esc_is_no_and_other_keys_are_ignored() {
+        let (mut app, rx) = confirm_question(true);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('x'),
+            KeyModifiers::NONE,
+        )));
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('1'),
+            KeyModifiers::NONE,
+        )));
+        assert!(app.pending_question.is_some());
+        assert_eq!(app.input.value(), "");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(app.pending_question.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), "no");
+    }
+
+    #[test]
+    fn question_freetext_multiline_answer_arrives_intact() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Steps?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+        for c in ['o', 'n', 'e'] {
+            app.update(Msg::Key(KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+            )));
+        }
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::SHIFT)));
+        for c in ['t', 'w', 'o'] {
+            app.update(Msg::Key(KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+            )));
+        }
+        assert!(
+            app.pending_question.is_some(),
+            "Shift+Enter must not submit"
+        );
+
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.pending_question.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), "one\ntwo");
+    }
+
+    #[test]
+    fn question_freetext_alt_enter_inserts_newline() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Steps?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+        app.input.set_value("first");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)));
+        assert!(app.pending_question.is_some());
+        assert_eq!(app.input.line_count(), 2);
+    }
+
+    #[test]
+    fn question_freetext_pasted_multiline_answer_arrives_intact() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Steps?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+        app.input.set_value("a\nb\nc");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(rx.blocking_recv().unwrap(), "a\nb\nc");
+    }
+
+    #[test]
+    fn question_freetext_empty_enter_requires_confirm() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Anything else?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        app.update(Msg::Key(enter));
+        assert!(
+            app.pending_question.is_some(),
+            "first empty Enter only arms"
+        );
+        app.update(Msg::Key(enter));
+        assert!(app.pending_question.is_none());
+        assert_eq!(rx.blocking_recv().unwrap(), "");
+    }
+
+    #[test]
+    fn question_freetext_typing_disarms_empty_confirm() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Anything else?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(app.confirm_empty_answer);
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('x'),
+            KeyModifiers::NONE,
+        )));
+        assert!(!app.confirm_empty_answer);
+    }
+
+    #[test]
+    fn main_input_alt_enter_inserts_newline() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.input.set_value("draft");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)));
+        assert!(!app.streaming, "Alt+Enter must not send");
+        assert_eq!(app.input.line_count(), 2);
+    }
+
+    #[test]
+    fn with_enter_submits_off_enter_inserts_and_ctrl_enter_sends() {
+        let mut flags = test_flags();
+        flags.tui_config.enter_submits = false;
+        let (mut app, _) = ClawApp::init(flags);
+        app.input.set_value("first");
+        app.update(Msg::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!app.streaming, "Enter must not send");
+        assert_eq!(app.input.line_count(), 2);
+
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Enter,
+            KeyModifiers::CONTROL,
+        )));
+        assert!(app.streaming);
+        assert!(app.input.value().is_empty());
+        assert!(
+            app.messages
+                .iter()
+                .any(|m| m.kind == ChatMessageKind::User && m.content.starts_with("first"))
+        );
+    }
+
+    #[test]
+    fn ctrl_s_sends_when_ctrl_enter_arrives_as_enter() {
+        let mut flags = test_flags();
+        flags.tui_config.enter_submits = false;
+        let (mut app, _) = ClawApp::init(flags);
+        app.input.set_value("first");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('s'),
+            KeyModifiers::CONTROL,
+        )));
+        assert!(app.streaming);
+        assert!(app.messages.iter().any(|m| m.content == "first"));
+
+        app.pending_question = Some(PendingQuestion {
+            question: "Steps?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: None,
+        });
+        app.input.set_value("all of them");
+        app.update(Msg::Key(KeyEvent::new(
+            KeyCode::Char('s'),
+            KeyModifiers::CONTROL,
+        )));
+        assert!(app.pending_question.is_none());
+    }
+
+    #[test]
+    fn initial_prompt_waits_for_the_keyboard_enhancement() {
+        let mut flags = test_flags();
+        flags.tui_config.enter_submits = false;
+        flags.keyboard_enhancement = true;
+        flags.initial_prompt = Some("go".to_string());
+        let (mut app, cmd) = ClawApp::init(flags);
+        assert!(!cmd.is_none());
+        assert!(!app.streaming);
+
+        app.update(Msg::KeyboardEnhanced);
+        assert!(app.streaming);
+        assert!(app.messages.iter().any(|m| m.content == "go"));
+    }
+
+    #[test]
+    fn enter_binding_shows_in_the_answer_hint() {
+        let question = PendingQuestion {
+            question: "Steps?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: None,
+        };
+        let hint = |enter_submits| -> String {
+            question_prompt_lines(&question, enter_submits)[1]
+                .spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect()
+        };
+        assert!(hint(true).contains("press Enter, Shift+Enter for a new line"));
+        assert!(hint(false).contains("press Ctrl+Enter, Enter for a new line"));
+
+        assert!(!is_newline_key(
+            &KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            true
+        ));
+        assert!(is_newline_key(
+            &KeyEvent::new(KeyCode::Enter, KeyModifiers::SHIFT),
+            false
+        ));
+        assert!(!is_newline_key(
+            &KeyEvent::new(KeyCode::Enter, KeyModifiers::CONTROL),
+            false
+        ));
+    }
+
+    #[test]
+    fn input_height_grows_with_lines() {
+        assert_eq!(input_height("", 80), 3);
+        assert_eq!(input_height("a\nb", 80), 4);
+        assert_eq!(input_height("a\nb\nc", 80), 5);
+    }
+
+    #[test]
+    fn input_height_caps_at_max() {
+        let draft = ["line"; 40].join("\n");
+        assert_eq!(input_height(&draft, 80), MAX_INPUT_HEIGHT);
+    }
+
+    #[test]
+    fn input_height_caps_giant_single_line() {
+        assert_eq!(input_height(&"x".repeat(1_000_000), 80), MAX_INPUT_HEIGHT);
+    }
+
+    #[test]
+    fn input_height_counts_soft_wrap() {
+        // 30 chars in a 12-col frame (10 inner) = 3 rows + 2 borders
+        assert_eq!(input_height(&"x".repeat(30), 12), 5);
+    }
+
+    #[test]
+    fn view_with_multiline_answer_does_not_panic() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Steps?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec![],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+        app.input.set_value(&["step"; 20].join("\n"));
+        let backend = ratatui::backend::TestBackend::new(80, 24);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.view(frame)).unwrap();
+    }
+
     #[test]
     fn question_freetext_esc_dismisses() {
         let (mut app, _) = ClawApp::init(test_flags());
@@ -1438,6 +6299,7 @@ mod tests {
             question: "Name?".to_string(),
             tool_call_id: "c1".to_string(),
             options: vec![],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1455,6 +6317,7 @@ mod tests {
             question: "Name?".to_string(),
             tool_call_id: "c1".to_string(),
             options: vec![],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1473,6 +6336,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c2".to_string(),
             options: vec!["red".to_string(), "green".to_string(), "blue".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1490,6 +6354,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c2".to_string(),
             options: vec!["red".to_string(), "green".to_string(), "blue".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1507,6 +6372,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c3".to_string(),
             options: vec!["red".to_string(), "green".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1523,6 +6389,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c2".to_string(),
             options: vec!["red".to_string(), "green".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1540,6 +6407,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c2".to_string(),
             options: vec!["red".to_string(), "green".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1557,6 +6425,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c2".to_string(),
             options: vec!["red".to_string(), "green".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1593,6 +6462,9 @@ mod tests {
         app.pending_approval = Some(PendingApproval {
             description: "bash(ls)".to_string(),
             pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
             tool_name: "bash".to_string(),
             selected: 1,
             responder: Some(tx),
@@ -1610,6 +6482,7 @@ mod tests {
             question: "Name?".to_string(),
             tool_call_id: "c1".to_string(),
             options: vec![],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1626,6 +6499,7 @@ mod tests {
             question: "Color?".to_string(),
             tool_call_id: "c2".to_string(),
             options: vec!["red".to_string(), "green".to_string()],
+            style: QuestionStyle::Open,
             selected: 0,
             responder: Some(tx),
         });
@@ -1640,6 +6514,64 @@ mod tests {
         let backend = ratatui::backend::TestBackend::new(20, 10);
         let mut terminal = ratatui::Terminal::new(backend).unwrap();
         terminal.draw(|frame| app.view(frame)).unwrap();
+        let content: String = terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|c| c.symbol().to_string())
+            .collect();
+        assert!(
+            !content.contains("Too small"),
+            "20x10 still gets the normal layout"
+        );
+    }
+
+    fn render_at(app: &ClawApp, width: u16, height: u16) -> String {
+        let backend = ratatui::backend::TestBackend::new(width, height);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.view(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|c| c.symbol().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn tiny_terminal_shows_resize_overlay() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        app.push_message(ChatMessageKind::User, "hello".to_string());
+        for (width, height) in [(10, 2), (80, 3), (12, 24)] {
+            let content = render_at(&app, width, height);
+            assert!(
+                content.contains("Too small"),
+                "{}x{}: {}",
+                width,
+                height,
+                content
+            );
+            assert!(!content.contains("hello"));
+        }
+    }
+
+    #[test]
+    fn one_cell_terminal_does_not_panic() {
+        let (mut app, _) = ClawApp::init(test_flags());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_approval = Some(PendingApproval {
+            description: "bash(ls)".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
+            tool_name: "bash".to_string(),
+            selected: 0,
+            responder: Some(tx),
+        });
+        render_at(&app, 1, 1);
     }
 
     #[test]
@@ -1659,10 +6591,12 @@ mod tests {
         let (mut app, _cmd) = ClawApp::init(test_flags());
 
         app.update(Msg::Agent(AgentEvent::ToolCallStarted {
+            tool_call_id: "c1".to_string(),
             tool_name: "rm_rf".to_string(),
             params_summary: "path=/".to_string(),
         }));
         app.update(Msg::Agent(AgentEvent::ToolCallDenied {
+            tool_call_id: "c1".to_string(),
             tool_name: "rm_rf".to_string(),
             reason: "too dangerous".to_string(),
         }));
@@ -1681,8 +6615,10 @@ mod tests {
         assert_eq!(
             tool_msg.kind,
             ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
                 tool_name: "rm_rf".to_string(),
                 status: ToolCallStatus::Denied,
+                approval_reason: None,
             }
         );
 
@@ -1694,6 +6630,97 @@ mod tests {
         assert!(denial_msg.content.contains("too dangerous"));
     }
 
+    #[test]
+    fn approval_expired_clears_pending_approval() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+
+        app.update(Msg::Agent(AgentEvent::ToolCallStarted {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+            params_summary: "rm -rf build".to_string(),
+        }));
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.update(Msg::Agent(AgentEvent::ToolCallNeedsApproval {
+            tool_call_id: "c1".to_string(),
+            description: "bash(rm -rf build)".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            tool_name: "bash".to_string(),
+            responder: tx,
+        }));
+        assert!(app.pending_approval.is_some());
+
+        app.update(Msg::Agent(AgentEvent::ApprovalExpired {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+        }));
+
+        assert!(app.pending_approval.is_none());
+        let tool_msg = app
+            .messages
+            .iter()
+            .find(|m| matches!(m.kind, ChatMessageKind::ToolCall { .. }))
+            .unwrap();
+        assert_eq!(
+            tool_msg.kind,
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "bash".to_string(),
+                status: ToolCallStatus::TimedOut,
+                approval_reason: None,
+            }
+        );
+        let last = app.messages.last().unwrap();
+        assert_eq!(last.kind, ChatMessageKind::System);
+        assert!(last.content.contains("timed out"));
+    }
+
+    #[test]
+    fn approval_expired_clears_pending_question() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_question = Some(PendingQuestion {
+            question: "Continue?".to_string(),
+            tool_call_id: "c1".to_string(),
+            options: vec!["yes".to_string(), "no".to_string()],
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: Some(tx),
+        });
+
+        app.update(Msg::Agent(AgentEvent::ApprovalExpired {
+            tool_call_id: "c1".to_string(),
+            tool_name: "ask_user".to_string(),
+        }));
+
+        assert!(app.pending_question.is_none());
+    }
+
+    #[test]
+    fn keys_after_approval_expired_go_to_input() {
+        let (mut app, _cmd) = ClawApp::init(test_flags());
+        let (tx, _rx) = tokio::sync::oneshot::channel();
+        app.pending_approval = Some(PendingApproval {
+            description: "test".to_string(),
+            pattern: None,
+            similar: vec![],
+            scopes: vec![],
+            scope_selected: None,
+            tool_name: "bash".to_string(),
+            selected: 0,
+            responder: Some(tx),
+        });
+        app.update(Msg::Agent(AgentEvent::ApprovalExpired {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+        }));
+
+        let key = KeyEvent::new(KeyCode::Char('1'), KeyModifiers::NONE);
+        app.update(Msg::Key(key));
+        assert_eq!(app.input.value(), "1");
+    }
+
     #[test]
     fn visual_line_height_short_line_is_one_row() {
         let lines = vec![Line::from("hello")];
@@ -1716,6 +6743,22 @@ mod tests {
         assert_eq!(visual_line_height(&lines, 20), 3);
     }
 
+    #[test]
+    fn visual_line_height_counts_expanded_tabs() {
+        let options = ChatOptions {
+            tab_width: 8,
+            ..ChatOptions::default()
+        };
+        let messages = vec![ChatMessage::new(
+            ChatMessageKind::Assistant,
+            "\t\tx".to_string(),
+        )];
+        let lines = render_chat_lines(&messages, &options);
+        // "🤖 " is 3 columns, then two tab stops of 8 and the "x".
+        assert_eq!(visual_line_height(&lines, 80), 1);
+        assert_eq!(visual_line_height(&lines, 10), 2);
+    }
+
     #[test]
     fn visual_line_height_empty_line_counts_as_one() {
         let lines = vec![Line::from("")];
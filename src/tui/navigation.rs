@@ -0,0 +1,139 @@
+// ABOUTME: Chat navigation — where Ctrl+Home/End and Ctrl+PageUp/PageDown move the chat viewport.
+// ABOUTME: Turns start at each user message; the offset math clamps at the top and bottom of history.
+
+use std::time::Duration;
+
+use crate::tui::state::{ChatMessage, ChatMessageKind};
+
+/// Shown briefly when asked to scroll up from the top of history.
+pub const TOP_NOTICE: &str = "top of history";
+/// How long `TOP_NOTICE` stays up.
+pub const TOP_NOTICE_DURATION: Duration = Duration::from_secs(2);
+/// Shown while the chat is scrolled up and content has arrived below.
+pub const NEW_BELOW_NOTICE: &str = "new messages \u{2193}";
+
+/// Which way a turn jump goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+    Back,
+    Forward,
+}
+
+/// Largest scroll offset for `content_lines` of chat in a viewport `height`
+/// rows tall: the offset that shows the last line on the bottom row.
+pub fn max_offset(content_lines: usize, height: usize) -> usize {
+    content_lines.saturating_sub(height)
+}
+
+/// Whether a chat scrolled to `offset` shows its last line, so new content
+/// should keep it there. Before the first render (`height` 0) nothing is
+/// on screen yet, and the chat follows.
+pub fn at_bottom(offset: usize, content_lines: usize, height: usize) -> bool {
+    height == 0 || offset >= max_offset(content_lines, height)
+}
+
+/// The first rendered line of each turn. A turn starts at a user message;
+/// with no user messages yet, every message is its own stop.
+/// `message_lines` holds the first line of each message.
+pub fn turn_starts(messages: &[ChatMessage], message_lines: &[usize]) -> Vec<usize> {
+    let user_starts: Vec<usize> = messages
+        .iter()
+        .zip(message_lines)
+        .filter(|(msg, _)| matches!(msg.kind, ChatMessageKind::User))
+        .map(|(_, &line)| line)
+        .collect();
+    if user_starts.is_empty() {
+        message_lines.to_vec()
+    } else {
+        user_starts
+    }
+}
+
+/// Offset after jumping one turn from `offset`: back to the nearest turn
+/// starting above it (or the top), forward to the next one below it (or the
+/// bottom). Never past `max_offset`.
+pub fn jump_target(starts: &[usize], offset: usize, max_offset: usize, jump: Jump) -> usize {
+    let target = match jump {
+        Jump::Back => starts
+            .iter()
+            .rev()
+            .copied()
+            .find(|&line| line < offset)
+            .unwrap_or(0),
+        Jump::Forward => starts
+            .iter()
+            .copied()
+            .find(|&line| line > offset)
+            .unwrap_or(max_offset),
+    };
+    target.min(max_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(kind: ChatMessageKind) -> ChatMessage {
+        ChatMessage::new(kind, String::new())
+    }
+
+    #[test]
+    fn turns_start_at_user_messages() {
+        let messages = vec![
+            msg(ChatMessageKind::System),
+            msg(ChatMessageKind::User),
+            msg(ChatMessageKind::Assistant),
+            msg(ChatMessageKind::User),
+            msg(ChatMessageKind::Assistant),
+        ];
+        assert_eq!(turn_starts(&messages, &[0, 2, 4, 10, 12]), [2, 10]);
+    }
+
+    #[test]
+    fn without_user_messages_every_message_is_a_stop() {
+        let messages = vec![msg(ChatMessageKind::System), msg(ChatMessageKind::System)];
+        assert_eq!(turn_starts(&messages, &[0, 3]), [0, 3]);
+    }
+
+    #[test]
+    fn jumping_back_lands_on_the_previous_turn() {
+        let starts = [2, 10, 30];
+        assert_eq!(jump_target(&starts, 35, 50, Jump::Back), 30);
+        // From exactly a turn start, go to the one before it.
+        assert_eq!(jump_target(&starts, 30, 50, Jump::Back), 10);
+    }
+
+    #[test]
+    fn jumping_forward_lands_on_the_next_turn() {
+        let starts = [2, 10, 30];
+        assert_eq!(jump_target(&starts, 2, 50, Jump::Forward), 10);
+        assert_eq!(jump_target(&starts, 11, 50, Jump::Forward), 30);
+    }
+
+    #[test]
+    fn jumps_clamp_at_both_extremes() {
+        let starts = [2, 10, 30];
+        // Above the first turn, back goes to the very top.
+        assert_eq!(jump_target(&starts, 2, 50, Jump::Back), 0);
+        assert_eq!(jump_target(&starts, 0, 50, Jump::Back), 0);
+        // Past the last turn, forward goes to the bottom.
+        assert_eq!(jump_target(&starts, 30, 50, Jump::Forward), 50);
+        // A turn that starts below the last full screen stops at the bottom.
+        assert_eq!(jump_target(&starts, 10, 20, Jump::Forward), 20);
+        assert_eq!(jump_target(&[], 7, 20, Jump::Back), 0);
+    }
+
+    #[test]
+    fn max_offset_is_zero_when_everything_fits() {
+        assert_eq!(max_offset(100, 20), 80);
+        assert_eq!(max_offset(5, 20), 0);
+    }
+
+    #[test]
+    fn only_a_chat_showing_its_last_line_is_at_the_bottom() {
+        assert!(at_bottom(80, 100, 20));
+        assert!(!at_bottom(79, 100, 20));
+        assert!(at_bottom(0, 5, 20));
+        assert!(at_bottom(0, 100, 0));
+    }
+}
@@ -0,0 +1,135 @@
+// ABOUTME: First-run walkthrough — explains approvals, ask_user, and slash commands, then asks for a security level.
+// ABOUTME: Shown only without approvals.json or saved sessions, and never again once its marker file exists.
+
+use std::path::{Path, PathBuf};
+
+use crate::approval::SecurityLevel;
+use crate::tools::ask_user::QuestionStyle;
+use crate::tui::state::PendingQuestion;
+
+/// System messages shown before the security question.
+pub const WALKTHROUGH: &[&str] = &[
+    "\u{1f44b} Welcome to soloclaw! A quick tour before you start (Esc skips it).",
+    "\u{1f6e1}\u{fe0f} Tools run under a security level. allowlist: ask before anything you \
+     haven't allowed yet, and remember \"Always Allow\" answers. full: run tools without asking. \
+     deny: block tool calls entirely. Per-tool levels live in approvals.json.",
+    "\u{2753} The agent can ask you questions mid-task. Pick an option with \u{2190}/\u{2192} \
+     and Enter or its number, type a free-text answer, or press Esc to decline.",
+    "\u{2328}\u{fe0f} Slash commands: /tools and /skills show what's loaded, /approvals edits \
+     what you've allowed, /regen asks for the last answer again, and /edit opens a file in \
+     your editor.",
+];
+
+/// The question that ends the walkthrough.
+pub const SECURITY_QUESTION: &str = "Which security level do you want to start with?";
+/// Answers to `SECURITY_QUESTION`, each a level name.
+pub const SECURITY_OPTIONS: [&str; 3] = ["allowlist", "full", "deny"];
+
+/// Asked after a level is picked.
+pub const SAVE_QUESTION: &str = "Save it as the default in config.toml?";
+pub const SAVE_OPTIONS: [&str; 2] = ["save to config.toml", "this session only"];
+
+/// Where the walkthrough is in its two questions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingStep {
+    /// Waiting for a security level.
+    Security,
+    /// A level was picked and applied; waiting to hear whether to save it.
+    Save(SecurityLevel),
+}
+
+/// A first-run walkthrough in progress.
+#[derive(Debug, Clone)]
+pub struct Onboarding {
+    /// Written when the walkthrough is finished or skipped.
+    pub marker: PathBuf,
+    /// The config file a chosen level is saved to.
+    pub config_path: PathBuf,
+    pub step: OnboardingStep,
+}
+
+impl Onboarding {
+    pub fn new(marker: PathBuf, config_path: PathBuf) -> Self {
+        Self {
+            marker,
+            config_path,
+            step: OnboardingStep::Security,
+        }
+    }
+
+    /// The multichoice prompt for the current step.
+    pub fn question(&self) -> PendingQuestion {
+        let (question, options): (&str, &[&str]) = match self.step {
+            OnboardingStep::Security => (SECURITY_QUESTION, &SECURITY_OPTIONS),
+            OnboardingStep::Save(_) => (SAVE_QUESTION, &SAVE_OPTIONS),
+        };
+        PendingQuestion {
+            question: question.to_string(),
+            tool_call_id: String::new(),
+            options: options.iter().map(|o| o.to_string()).collect(),
+            style: QuestionStyle::Open,
+            selected: 0,
+            responder: None,
+        }
+    }
+}
+
+/// Whether this looks like the very first run: no walkthrough marker, no
+/// approvals.json, and no saved sessions.
+pub fn is_first_run(marker: &Path, approvals_path: &Path, sessions_dir: &Path) -> bool {
+    let has_sessions = std::fs::read_dir(sessions_dir)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    !marker.exists() && !approvals_path.exists() && !has_sessions
+}
+
+/// Record that the walkthrough was shown, so it never is again.
+pub fn mark_onboarded(marker: &Path) -> std::io::Result<()> {
+    if let Some(parent) = marker.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(marker, chrono::Utc::now().to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_run_needs_no_marker_approvals_or_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("config/onboarded");
+        let approvals = dir.path().join("config/approvals.json");
+        let sessions = dir.path().join("data/sessions");
+        assert!(is_first_run(&marker, &approvals, &sessions));
+
+        // An empty sessions directory still counts as no sessions.
+        std::fs::create_dir_all(&sessions).unwrap();
+        assert!(is_first_run(&marker, &approvals, &sessions));
+
+        std::fs::create_dir(sessions.join("abc123")).unwrap();
+        assert!(!is_first_run(&marker, &approvals, &sessions));
+    }
+
+    #[test]
+    fn approvals_or_the_marker_end_the_first_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("config/onboarded");
+        let approvals = dir.path().join("approvals.json");
+        let sessions = dir.path().join("sessions");
+
+        std::fs::write(&approvals, "{}").unwrap();
+        assert!(!is_first_run(&marker, &approvals, &sessions));
+        std::fs::remove_file(&approvals).unwrap();
+
+        mark_onboarded(&marker).unwrap();
+        assert!(!is_first_run(&marker, &approvals, &sessions));
+    }
+
+    #[test]
+    fn every_security_option_is_a_level() {
+        for option in SECURITY_OPTIONS {
+            assert!(SecurityLevel::parse(option).is_some(), "{}", option);
+        }
+    }
+}
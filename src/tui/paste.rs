@@ -0,0 +1,139 @@
+// ABOUTME: Paste guards — sanitizes pasted text and decides whether it is small enough to insert.
+// ABOUTME: Oversized pastes become a truncated attachment block or their first lines, after a prompt.
+
+/// What to do with a (sanitized) paste.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasteAction {
+    /// Small enough to go straight into the input.
+    Insert(String),
+    /// Over the size limit; ask how to include it.
+    Prompt(String),
+}
+
+/// Normalize line endings to `\n` and drop NUL and other control characters
+/// that terminals pass through from binary or badly encoded clipboards.
+/// Tabs and newlines are kept.
+pub fn sanitize(text: &str) -> String {
+    let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
+    normalized
+        .chars()
+        .filter(|&c| c == '\n' || c == '\t' || !c.is_control())
+        .collect()
+}
+
+/// Sanitize a paste and decide whether it can be inserted directly.
+pub fn classify(text: &str, max_bytes: usize) -> PasteAction {
+    let text = sanitize(text);
+    if text.len() > max_bytes {
+        PasteAction::Prompt(text)
+    } else {
+        PasteAction::Insert(text)
+    }
+}
+
+/// The largest prefix of `text` that fits in `max_bytes` without splitting a
+/// character.
+fn prefix_within(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Wrap a paste in a fenced block, keeping at most `max_bytes` of it, with a
+/// trailer saying how many lines were dropped. The fence is longer than any
+/// backtick run in the text so the block can't be closed early.
+pub fn attachment_block(text: &str, max_bytes: usize) -> String {
+    let kept = prefix_within(text, max_bytes);
+    // Cut back to the last full line so the block doesn't end mid-line.
+    let kept = match kept.rfind('\n') {
+        Some(i) if kept.len() < text.len() => &kept[..i],
+        _ => kept,
+    };
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+
+    let mut block = format!(
+        "[pasted text: {} lines, {}]\n{fence}\n{kept}\n{fence}",
+        text.lines().count(),
+        crate::tools::summary::format_size(text.len()),
+    );
+    let dropped = text.lines().count().saturating_sub(kept.lines().count());
+    if dropped > 0 {
+        block.push_str(&format!("\n[\u{2026} truncated {} more lines]", dropped));
+    }
+    block
+}
+
+/// The first `n` lines of a paste.
+pub fn first_lines(text: &str, n: usize) -> String {
+    text.lines().take(n).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_normalizes_line_endings() {
+        assert_eq!(sanitize("a\r\nb\rc\nd"), "a\nb\nc\nd");
+    }
+
+    #[test]
+    fn sanitize_strips_nul_and_control_chars() {
+        assert_eq!(sanitize("a\0b\u{1b}[31mc\u{7f}\td"), "ab[31mc\td");
+        assert_eq!(sanitize("h\u{e9}llo \u{1f600}"), "h\u{e9}llo \u{1f600}");
+    }
+
+    #[test]
+    fn classify_inserts_up_to_the_limit() {
+        let text = "x".repeat(100);
+        assert_eq!(classify(&text, 100), PasteAction::Insert(text.clone()));
+        let over = "x".repeat(101);
+        assert_eq!(classify(&over, 100), PasteAction::Prompt(over.clone()));
+    }
+
+    #[test]
+    fn classify_measures_the_sanitized_text() {
+        // Stripping the NULs brings it under the limit.
+        let text = format!("{}{}", "x".repeat(90), "\0".repeat(20));
+        assert_eq!(classify(&text, 100), PasteAction::Insert("x".repeat(90)));
+    }
+
+    #[test]
+    fn attachment_block_truncates_at_a_line() {
+        let text = (1..=100)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let block = attachment_block(&text, 40);
+        assert!(block.starts_with("[pasted text: 100 lines,"));
+        assert!(block.contains("line 1\n"));
+        assert!(!block.contains("line 99"));
+        assert!(block.ends_with("more lines]"));
+    }
+
+    #[test]
+    fn attachment_block_never_splits_a_char() {
+        let text = "\u{e9}".repeat(50);
+        let block = attachment_block(&text, 51);
+        assert!(block.contains(&"\u{e9}".repeat(25)));
+    }
+
+    #[test]
+    fn attachment_fence_outlasts_backticks_in_the_text() {
+        let block = attachment_block("```rust\nfn main() {}\n```", 1000);
+        assert!(block.contains("\n````\n```rust"));
+        assert!(!block.contains("truncated"));
+    }
+
+    #[test]
+    fn first_lines_takes_n() {
+        assert_eq!(first_lines("a\nb\nc\nd", 2), "a\nb");
+        assert_eq!(first_lines("a", 5), "a");
+    }
+}
@@ -0,0 +1,157 @@
+// ABOUTME: Prompt snippets — canned text bound to function keys from [[snippets]] config.
+// ABOUTME: Looks up the snippet for a key press and fills in {selection}/{clipboard} placeholders.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::SnippetConfig;
+
+/// Parse a snippet key name like "f2" (case-insensitive) into a key code.
+/// Only function keys F1–F12 can be bound.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let name = name.trim().to_ascii_lowercase();
+    let n: u8 = name.strip_prefix('f')?.parse().ok()?;
+    (1..=12).contains(&n).then_some(KeyCode::F(n))
+}
+
+/// The snippet bound to this key press, if any. The first match wins.
+pub fn snippet_for_key<'a>(
+    snippets: &'a [SnippetConfig],
+    key: &KeyEvent,
+) -> Option<&'a SnippetConfig> {
+    if key.modifiers != KeyModifiers::NONE {
+        return None;
+    }
+    snippets
+        .iter()
+        .find(|s| parse_key(&s.key) == Some(key.code))
+}
+
+/// Whether the snippet text uses the current draft as `{selection}`.
+pub fn uses_selection(text: &str) -> bool {
+    text.contains("{selection}")
+}
+
+/// Whether the snippet text uses the system clipboard as `{clipboard}`.
+pub fn uses_clipboard(text: &str) -> bool {
+    text.contains("{clipboard}")
+}
+
+/// Fill in `{selection}` (the current input draft) and `{clipboard}` in one
+/// pass, so placeholders inside the filled-in values are left as they are.
+/// Unavailable values are replaced with an empty string.
+pub fn expand(text: &str, selection: &str, clipboard: Option<&str>) -> String {
+    let mut out = String::with_capacity(text.len() + selection.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{selection}") {
+            out.push_str(selection);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{clipboard}") {
+            out.push_str(clipboard.unwrap_or(""));
+            rest = after;
+        } else {
+            out.push('{');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Read the system clipboard via the platform's command-line tool, without
+/// blocking the TUI while it runs.
+pub async fn read_clipboard() -> Option<String> {
+    let candidates: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("pbpaste", &[])]
+    } else {
+        &[
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    };
+    for (cmd, args) in candidates {
+        let Ok(output) = tokio::process::Command::new(cmd).args(*args).output().await else {
+            continue;
+        };
+        if output.status.success() {
+            return Some(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(key: &str, text: &str) -> SnippetConfig {
+        SnippetConfig {
+            key: key.to_string(),
+            text: text.to_string(),
+            submit: false,
+        }
+    }
+
+    #[test]
+    fn parses_function_keys() {
+        assert_eq!(parse_key("f2"), Some(KeyCode::F(2)));
+        assert_eq!(parse_key("F12"), Some(KeyCode::F(12)));
+        assert_eq!(parse_key("f0"), None);
+        assert_eq!(parse_key("f13"), None);
+        assert_eq!(parse_key("ctrl+s"), None);
+    }
+
+    #[test]
+    fn finds_snippet_by_key() {
+        let snippets = vec![
+            snippet("f2", "run the tests and fix failures"),
+            snippet("F3", "explain"),
+            snippet("f2", "shadowed"),
+        ];
+        let f2 = KeyEvent::new(KeyCode::F(2), KeyModifiers::NONE);
+        let f3 = KeyEvent::new(KeyCode::F(3), KeyModifiers::NONE);
+        let f4 = KeyEvent::new(KeyCode::F(4), KeyModifiers::NONE);
+        let shift_f2 = KeyEvent::new(KeyCode::F(2), KeyModifiers::SHIFT);
+
+        assert_eq!(
+            snippet_for_key(&snippets, &f2).unwrap().text,
+            "run the tests and fix failures"
+        );
+        assert_eq!(snippet_for_key(&snippets, &f3).unwrap().text, "explain");
+        assert!(snippet_for_key(&snippets, &f4).is_none());
+        assert!(snippet_for_key(&snippets, &shift_f2).is_none());
+    }
+
+    #[test]
+    fn substitutes_placeholders() {
+        assert_eq!(
+            expand("Explain: {selection}", "fn main() {}", None),
+            "Explain: fn main() {}"
+        );
+        assert_eq!(
+            expand("Review this diff:\n{clipboard}", "", Some("+ added")),
+            "Review this diff:\n+ added"
+        );
+        assert_eq!(expand("paste: {clipboard}", "", None), "paste: ");
+        assert_eq!(expand("no placeholders", "draft", None), "no placeholders");
+        assert_eq!(expand("{a} {selection}}", "x", None), "{a} x}");
+        assert!(uses_selection("fix {selection}"));
+        assert!(!uses_selection("fix it"));
+        assert!(uses_clipboard("diff: {clipboard}"));
+    }
+
+    #[test]
+    fn placeholders_in_the_values_are_left_alone() {
+        assert_eq!(
+            expand(
+                "{selection} / {clipboard}",
+                "see {clipboard}",
+                Some("{selection}")
+            ),
+            "see {clipboard} / {selection}"
+        );
+    }
+}
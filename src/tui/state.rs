@@ -3,7 +3,12 @@
 
 use tokio::sync::oneshot;
 
-use crate::approval::ApprovalDecision;
+use crate::agent::compaction::SummaryDecision;
+use crate::approval::{ApprovalDecision, ApprovalReason, PathScope};
+use crate::config::Effort;
+use crate::prompt::SkillEntry;
+use crate::tools::ask_user::QuestionStyle;
+use crate::tui::consent::Feature;
 
 /// The kind of a single chat message displayed in the TUI.
 #[derive(Debug, PartialEq)]
@@ -11,8 +16,14 @@ pub enum ChatMessageKind {
     User,
     Assistant,
     ToolCall {
+        /// The provider's id for the call; status updates and the result
+        /// are matched to the message by it.
+        tool_call_id: String,
         tool_name: String,
         status: ToolCallStatus,
+        /// What auto-approved the call, shown as a dim suffix when
+        /// `[tui] show_approval_reason` is on.
+        approval_reason: Option<ApprovalReason>,
     },
     ToolResult {
         is_error: bool,
@@ -34,6 +45,37 @@ pub enum ToolCallStatus {
 pub struct ChatMessage {
     pub kind: ChatMessageKind,
     pub content: String,
+    /// Shown in the chat only, with no counterpart in the conversation the
+    /// model sees: slash-command output, notices, errors.
+    pub display_only: bool,
+    /// Pinned with /pin, and marked so in the chat.
+    pub pinned: bool,
+}
+
+impl ChatMessage {
+    /// A message that mirrors part of the conversation.
+    pub fn new(kind: ChatMessageKind, content: String) -> Self {
+        Self {
+            kind,
+            content,
+            display_only: false,
+            pinned: false,
+        }
+    }
+
+    /// A system note shown in the chat only.
+    pub fn note(content: String) -> Self {
+        Self {
+            display_only: true,
+            ..Self::new(ChatMessageKind::System, content)
+        }
+    }
+}
+
+/// The chat messages that stand for the model's conversation, in order:
+/// everything but the display-only ones.
+pub fn conversation(messages: &[ChatMessage]) -> impl Iterator<Item = &ChatMessage> {
+    messages.iter().filter(|m| !m.display_only)
 }
 
 /// Events sent from the agent loop to the TUI via an mpsc channel.
@@ -44,15 +86,27 @@ pub enum AgentEvent {
     TextDone,
     /// A tool call has started execution.
     ToolCallStarted {
+        tool_call_id: String,
         tool_name: String,
         params_summary: String,
     },
     /// A tool call was approved (auto or by user).
-    ToolCallApproved { tool_name: String },
+    ToolCallApproved {
+        tool_call_id: String,
+        tool_name: String,
+        /// What auto-approved the call; None when the user approved it.
+        reason: Option<ApprovalReason>,
+    },
     /// A tool call needs user approval via the TUI.
     ToolCallNeedsApproval {
+        tool_call_id: String,
         description: String,
         pattern: Option<String>,
+        /// Up to three existing allowlist patterns close to `pattern`.
+        similar: Vec<String>,
+        /// Directory choices for "Always Allow" on a file write, answered
+        /// with `AllowScope(index)`; empty for other tools.
+        scopes: Vec<PathScope>,
         tool_name: String,
         responder: oneshot::Sender<ApprovalDecision>,
     },
@@ -62,35 +116,112 @@ pub enum AgentEvent {
         tool_call_id: String,
         /// Multiple-choice options. Empty means free-text mode.
         options: Vec<String>,
+        /// A yes/no confirmation, or an open question.
+        style: QuestionStyle,
         responder: oneshot::Sender<String>,
     },
+    /// The LLM wants the user to edit a file via the open_in_editor tool.
+    /// Answered with the file's contents after editing, or why it failed.
+    OpenInEditor {
+        tool_call_id: String,
+        /// As the LLM gave it, relative to the workspace.
+        path: String,
+        responder: oneshot::Sender<Result<String, String>>,
+    },
     /// A tool call was denied.
-    ToolCallDenied { tool_name: String, reason: String },
+    ToolCallDenied {
+        tool_call_id: String,
+        tool_name: String,
+        reason: String,
+    },
+    /// A pending approval expired before the user answered it. The TUI
+    /// should drop any prompt still on screen, since its responder is dead.
+    ApprovalExpired {
+        tool_call_id: String,
+        tool_name: String,
+    },
     /// A tool call completed with a result.
     ToolResult {
+        tool_call_id: String,
         tool_name: String,
         content: String,
         is_error: bool,
     },
-    /// Token usage update from a completed API response.
+    /// Token usage update from a completed API response. `input_tokens`
+    /// excludes prompt tokens read from or written to the provider's cache.
     Usage {
         input_tokens: u32,
         output_tokens: u32,
+        cache_read_tokens: u32,
+        cache_write_tokens: u32,
     },
     /// An error occurred in the agent loop.
     Error(String),
+    /// An informational note from the agent loop, shown as a system message.
+    Notice(String),
+    /// The system prompt was rebuilt after /reload; carries the new skill report.
+    Reloaded { skills: Vec<SkillEntry> },
+    /// An MCP server started in the background (`[mcp] lazy`) came up with
+    /// `tools` new tools, making `tool_count` in all.
+    McpConnected {
+        server: String,
+        tools: usize,
+        tool_count: usize,
+    },
+    /// /forget redacted the `nth` most recent message the user typed, so
+    /// the chat can blank its copy.
+    Forgotten { nth: usize },
+    /// /pin pinned or unpinned the `nth` most recent message the user typed
+    /// (or with `reply`, the `nth` most recent reply), so the chat can mark it.
+    Pinned {
+        nth: usize,
+        reply: bool,
+        pinned: bool,
+    },
     /// The agent loop finished processing.
     Done,
     /// Compaction has started.
     CompactionStarted,
+    /// The compaction summary is streaming in; about `tokens` so far.
+    CompactionProgress { tokens: usize },
+    /// The compaction summary is ready for review (`[compaction] review = "ask"`).
+    /// History is only replaced once the user accepts or edits it.
+    CompactionSummaryReady {
+        summary: String,
+        responder: oneshot::Sender<SummaryDecision>,
+    },
     /// Compaction is complete.
     CompactionDone { old_count: usize, new_count: usize },
+    /// Compacting a resumed session before the first message finished,
+    /// however it went. Not a turn, so not followed by `Done`.
+    ResumeCompacted,
+    /// The in-flight turn was cancelled at the user's request. A `Done`
+    /// event follows once the loop is idle again.
+    TurnCancelled,
 }
 
 /// Events sent from the TUI to the agent loop.
 pub enum UserEvent {
     /// User submitted a chat message.
     Message(String),
+    /// User asked to abort the turn currently in progress.
+    Cancel,
+    /// User asked to re-read config, context files, and skills (/reload).
+    Reload,
+    /// User asked to discard the last answer and generate a new one (/regen),
+    /// optionally steered by a hint.
+    Regenerate { hint: Option<String> },
+    /// User changed the reasoning effort with /effort; None turns it off.
+    SetEffort(Option<Effort>),
+    /// User turned observe mode on or off with /observe.
+    SetObserve(bool),
+    /// User asked to redact the `nth` most recent message they typed (1 is
+    /// the last) from history and the saved session (/forget).
+    Forget { nth: usize },
+    /// User asked to pin the `nth` most recent message they typed, or with
+    /// `reply` the `nth` most recent reply, or to unpin it if it already is
+    /// (/pin). Pinned messages survive compaction.
+    Pin { nth: usize, reply: bool },
     /// User requested to quit.
     Quit,
 }
@@ -99,6 +230,12 @@ pub enum UserEvent {
 pub struct PendingApproval {
     pub description: String,
     pub pattern: Option<String>,
+    /// Already-allowlisted patterns similar to this call, shown as related approvals.
+    pub similar: Vec<String>,
+    /// Directory choices offered after "Always Allow" on a file write.
+    pub scopes: Vec<PathScope>,
+    /// The highlighted scope while choosing one; None on the main options.
+    pub scope_selected: Option<usize>,
     pub tool_name: String,
     /// Index of the currently selected option (0=AllowOnce, 1=AllowAlways, 2=Deny).
     pub selected: usize,
@@ -112,9 +249,51 @@ pub struct PendingQuestion {
     pub tool_call_id: String,
     /// Multiple-choice options. Empty means free-text mode.
     pub options: Vec<String>,
+    /// A confirm question takes y/n keys; an open one uses `options`.
+    pub style: QuestionStyle,
     /// Index of the currently selected option (for multiple choice).
     pub selected: usize,
     /// One-shot channel to send the user's answer back to the agent loop.
     pub responder: Option<oneshot::Sender<String>>,
 }
 
+impl PendingQuestion {
+    /// Whether the answer is typed into the input, rather than picked.
+    pub fn takes_text(&self) -> bool {
+        self.style == QuestionStyle::Open && self.options.is_empty()
+    }
+}
+
+/// A quit confirmation shown when quitting while a turn is still running.
+pub struct PendingQuit {
+    /// Index of the currently selected option (0=quit, 1=quit after this turn, 2=cancel).
+    pub selected: usize,
+}
+
+/// A compaction summary under review. The prompt itself is shown through the
+/// question UI; this holds what the answer is for.
+pub struct PendingSummaryReview {
+    pub summary: String,
+    /// Set once the user chose "edit" and the summary is in the input.
+    pub editing: bool,
+    pub responder: oneshot::Sender<SummaryDecision>,
+}
+
+/// A gated feature waiting for the user's consent, with what it was about to do.
+pub struct PendingConsent {
+    pub feature: Feature,
+    /// The text to copy or send once allowed.
+    pub payload: String,
+    /// What the payload is, e.g. "the last reply", for the note afterwards.
+    pub what: String,
+    /// Index of the currently selected option (0=yes, 1=always, 2=no).
+    pub selected: usize,
+}
+
+/// A paste over the size limit, waiting for the user to pick how to include it.
+pub struct PendingPaste {
+    /// The sanitized pasted text.
+    pub text: String,
+    /// Index of the currently selected option (0=attach, 1=first lines, 2=cancel).
+    pub selected: usize,
+}
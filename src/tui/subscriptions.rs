@@ -1,7 +1,8 @@
-// ABOUTME: Custom boba SubscriptionSource for agent loop events.
+// ABOUTME: Custom boba SubscriptionSources: agent loop events and the streaming cursor's blink.
 // ABOUTME: Wraps the mpsc::Receiver<AgentEvent> so boba's runtime manages it.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use boba::{SubscriptionId, SubscriptionSource};
 use futures::stream::BoxStream;
@@ -38,6 +39,28 @@ impl SubscriptionSource for AgentEventSource {
     }
 }
 
+/// How long the streaming cursor stays on, then off.
+pub const BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Ticks every [`BLINK_INTERVAL`] to blink the cursor at the end of a
+/// streaming reply. Only subscribed while a turn runs, so it stops with it.
+pub struct CursorBlink;
+
+impl SubscriptionSource for CursorBlink {
+    type Output = ();
+
+    fn id(&self) -> SubscriptionId {
+        SubscriptionId::of::<Self>()
+    }
+
+    fn stream(self) -> BoxStream<'static, ()> {
+        Box::pin(futures::stream::unfold((), |()| async {
+            tokio::time::sleep(BLINK_INTERVAL).await;
+            Some(((), ()))
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
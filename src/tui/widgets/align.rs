@@ -0,0 +1,58 @@
+// ABOUTME: Display-width layout helpers for single-row bars — measure spans in terminal cells, right-align.
+// ABOUTME: Emoji and other wide characters count as the cells they occupy, not as one char each.
+
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthStr;
+
+/// Terminal cells `spans` occupy.
+pub fn display_width(spans: &[Span]) -> usize {
+    spans
+        .iter()
+        .map(|span| UnicodeWidthStr::width(span.content.as_ref()))
+        .sum()
+}
+
+/// Lay out `left` and `right` on one row of `width` cells, with `right`
+/// pushed against the right edge. None when they don't both fit.
+pub fn right_align(
+    left: Vec<Span<'static>>,
+    right: Vec<Span<'static>>,
+    width: usize,
+) -> Option<Line<'static>> {
+    let used = display_width(&left) + display_width(&right);
+    if used > width {
+        return None;
+    }
+    let mut spans = left;
+    if !right.is_empty() {
+        spans.push(Span::raw(" ".repeat(width - used)));
+        spans.extend(right);
+    }
+    Some(Line::from(spans))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_characters_count_double() {
+        assert_eq!(display_width(&[Span::raw("ab")]), 2);
+        assert_eq!(display_width(&[Span::raw(" \u{1F4C1} ")]), 4);
+        assert_eq!(display_width(&[Span::raw("\u{23F1}\u{FE0F}")]), 2);
+    }
+
+    #[test]
+    fn right_align_pads_to_the_full_width() {
+        let line = right_align(vec![Span::raw("left")], vec![Span::raw("\u{1F43E}")], 10).unwrap();
+        let text: String = line.spans.iter().map(|s| s.content.to_string()).collect();
+        assert_eq!(text, "left    \u{1F43E}");
+        assert_eq!(display_width(&line.spans), 10);
+    }
+
+    #[test]
+    fn right_align_refuses_what_does_not_fit() {
+        assert!(right_align(vec![Span::raw("left")], vec![Span::raw("\u{1F43E}")], 5).is_none());
+        assert!(right_align(vec![Span::raw("left")], vec![], 4).is_some());
+    }
+}
@@ -1,14 +1,26 @@
 // ABOUTME: Approval prompt widget — inline TUI prompt for tool call approval.
-// ABOUTME: Shows description and three selectable options: Allow Once, Always Allow, Deny.
+// ABOUTME: Shows description (with any diff preview) and the options: Allow Once, Always Allow, Deny, Trust Tool.
 
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 
-/// The three approval options presented to the user.
-pub const APPROVAL_OPTIONS: &[&str] = &["✅ Allow Once", "🔓 Always Allow", "🚫 Deny"];
+use crate::approval::PathScope;
+use crate::tui::consent::CONSENT_OPTIONS;
 
-/// Render the approval prompt as two Lines: description + selectable options.
-pub fn approval_line(description: &str, selected: usize) -> Vec<Line<'static>> {
+/// The approval options presented to the user. Deny keeps its `3` key; the
+/// broadest option comes last and is worded to make its reach clear.
+pub const APPROVAL_OPTIONS: &[&str] = &[
+    "✅ Allow Once",
+    "🔓 Always Allow",
+    "🚫 Deny",
+    "⚠️ Trust Tool (all calls)",
+];
+
+/// Render the approval prompt: description, the diff preview under it (for
+/// a file write), related approvals (when there are any), and the
+/// selectable options.
+pub fn approval_line(description: &str, similar: &[String], selected: usize) -> Vec<Line<'static>> {
+    let (summary, preview) = description.split_once('\n').unwrap_or((description, ""));
     let header = Line::from(vec![
         Span::styled(
             "🔐 APPROVE? ",
@@ -16,11 +28,87 @@ pub fn approval_line(description: &str, selected: usize) -> Vec<Line<'static>> {
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
         ),
+        Span::styled(summary.to_string(), Style::default().fg(Color::White)),
+    ]);
+
+    let mut lines = vec![header];
+    lines.extend(preview.lines().map(diff_line));
+    if !similar.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("   similar approved: {}", similar.join(", ")),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines.push(option_line(APPROVAL_OPTIONS.iter().copied(), selected));
+    lines
+}
+
+/// One line of a diff preview, indented under the header and colored by
+/// whether it adds, removes, or just gives context.
+fn diff_line(line: &str) -> Line<'static> {
+    let color = match line.chars().next() {
+        Some('+') => Color::Green,
+        Some('-') => Color::Red,
+        Some('@') => Color::Cyan,
+        _ => Color::DarkGray,
+    };
+    Line::from(Span::styled(
+        format!("   {}", line),
+        Style::default().fg(color),
+    ))
+}
+
+/// The last scope choice: the whole tool, as plain "Always Allow" would be.
+pub const ANY_PATH_OPTION: &str = "anywhere";
+
+/// Render the follow-up to "Always Allow" on a file write: how far the
+/// approval reaches, narrowest first, then the whole tool. The diff preview
+/// was already seen, so only the description's first line is repeated.
+pub fn scope_lines(description: &str, scopes: &[PathScope], selected: usize) -> Vec<Line<'static>> {
+    let description = description.lines().next().unwrap_or_default();
+    let header = Line::from(vec![
+        Span::styled(
+            "🔓 ALWAYS ALLOW ",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
         Span::styled(description.to_string(), Style::default().fg(Color::White)),
+        Span::styled(" for writes to…", Style::default().fg(Color::DarkGray)),
     ]);
+    let labels = scopes
+        .iter()
+        .map(|scope| scope.label.as_str())
+        .chain([ANY_PATH_OPTION]);
+    let hint = Line::from(Span::styled(
+        "   Esc: back",
+        Style::default().fg(Color::DarkGray),
+    ));
+    vec![header, option_line(labels, selected), hint]
+}
 
+/// Render a consent prompt for a gated terminal feature, with the same
+/// numbered options as a tool approval.
+pub fn consent_lines(question: &str, selected: usize) -> Vec<Line<'static>> {
+    let header = Line::from(vec![
+        Span::styled(
+            "\u{1f512} PRIVACY ",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(question.to_string(), Style::default().fg(Color::White)),
+    ]);
+    vec![
+        header,
+        option_line(CONSENT_OPTIONS.iter().copied(), selected),
+    ]
+}
+
+/// Numbered options on one line, the selected one highlighted.
+fn option_line<'a>(options: impl Iterator<Item = &'a str>, selected: usize) -> Line<'static> {
     let mut option_spans = Vec::new();
-    for (i, option) in APPROVAL_OPTIONS.iter().enumerate() {
+    for (i, option) in options.enumerate() {
         if i > 0 {
             option_spans.push(Span::raw("  "));
         }
@@ -38,10 +126,7 @@ pub fn approval_line(description: &str, selected: usize) -> Vec<Line<'static>> {
             option_spans.push(Span::styled(label, Style::default().fg(Color::DarkGray)));
         }
     }
-
-    let options = Line::from(option_spans);
-
-    vec![header, options]
+    Line::from(option_spans)
 }
 
 #[cfg(test)]
@@ -50,7 +135,7 @@ mod tests {
 
     #[test]
     fn approval_line_has_all_options() {
-        let lines = approval_line("run bash command", 0);
+        let lines = approval_line("run bash command", &[], 0);
         assert_eq!(lines.len(), 2);
 
         // Header line should contain APPROVE? and description
@@ -71,18 +156,19 @@ mod tests {
         assert!(options_text.contains("✅ Allow Once"));
         assert!(options_text.contains("🔓 Always Allow"));
         assert!(options_text.contains("🚫 Deny"));
+        assert!(options_text.contains("[4] ⚠️ Trust Tool (all calls)"));
     }
 
     #[test]
     fn selected_index_is_valid() {
         // Test each valid selection index renders without panic
         for i in 0..APPROVAL_OPTIONS.len() {
-            let lines = approval_line("test", i);
+            let lines = approval_line("test", &[], i);
             assert_eq!(lines.len(), 2);
         }
 
         // Verify the selected option is highlighted (black on yellow)
-        let lines = approval_line("test", 1);
+        let lines = approval_line("test", &[], 1);
         let option_spans = &lines[1].spans;
         // Find the span for "Always Allow" (the selected one)
         let selected_span = option_spans
@@ -92,4 +178,59 @@ mod tests {
         assert_eq!(selected_span.style.fg, Some(Color::Black));
         assert_eq!(selected_span.style.bg, Some(Color::Yellow));
     }
+
+    #[test]
+    fn related_approvals_line_shown_when_similar() {
+        let similar = vec!["/usr/bin/git".to_string(), "/usr/local/bin/git".to_string()];
+        let lines = approval_line("bash(git push)", &similar, 0);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1].to_string(),
+            "   similar approved: /usr/bin/git, /usr/local/bin/git"
+        );
+        assert!(lines[2].to_string().contains("Allow Once"));
+    }
+
+    #[test]
+    fn diff_previews_sit_between_the_description_and_options() {
+        let description = "write_file(a.rs, 9 B) \u{2014} +1 -1 lines\n@@ -1,1 +1,1 @@\n-old\n+new";
+        let lines = approval_line(description, &[], 0);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            text[0],
+            "🔐 APPROVE? write_file(a.rs, 9 B) \u{2014} +1 -1 lines"
+        );
+        assert_eq!(text[1..4], ["   @@ -1,1 +1,1 @@", "   -old", "   +new"]);
+        assert!(text[4].contains("Allow Once"));
+        assert_eq!(lines[2].spans[0].style.fg, Some(Color::Red));
+        assert_eq!(lines[3].spans[0].style.fg, Some(Color::Green));
+
+        let scopes = scope_lines(description, &[], 0);
+        assert!(!scopes[0].to_string().contains("+new"));
+    }
+
+    #[test]
+    fn scope_chooser_lists_scopes_then_anywhere() {
+        let scopes = vec![
+            PathScope {
+                label: "src/main.rs".to_string(),
+                pattern: "/ws/src/main.rs".to_string(),
+            },
+            PathScope {
+                label: "src/**".to_string(),
+                pattern: "/ws/src/**".to_string(),
+            },
+        ];
+        let lines = scope_lines("write_file(src/main.rs)", &scopes, 1);
+        assert_eq!(
+            lines[1].to_string(),
+            " [1] src/main.rs    [2] src/**    [3] anywhere "
+        );
+        let selected = lines[1]
+            .spans
+            .iter()
+            .find(|s| s.content.contains("src/**"))
+            .unwrap();
+        assert_eq!(selected.style.bg, Some(Color::Yellow));
+    }
 }
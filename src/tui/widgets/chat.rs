@@ -1,107 +1,555 @@
 // ABOUTME: Chat widget — renders chat messages into styled ratatui Lines.
-// ABOUTME: Each message kind (user, assistant, tool, system) has distinct visual styling.
+// ABOUTME: Each message kind has distinct styling; a turn's tool calls render as a tree with results nested,
+// ABOUTME: folded into one line per call (compact) or one tally per turn (hidden) at quieter verbosities.
 
-use ratatui::style::{Color, Modifier, Style};
+use std::borrow::Cow;
+
+use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Paragraph, Wrap};
 
+use crate::config::ToolVerbosity;
 use crate::tui::state::{ChatMessage, ChatMessageKind, ToolCallStatus};
+use crate::tui::widgets::theme::Theme;
+
+/// Maximum lines of a tool result shown before truncating.
+const MAX_RESULT_LINES: usize = 10;
+
+/// Characters of a result's first line kept in a compact summary.
+const SUMMARY_CHARS: usize = 60;
+
+/// Block drawn at the end of a reply while it streams.
+pub const STREAMING_CURSOR: &str = "\u{258d}";
+
+/// Shown in place of a reply that hasn't produced its first token yet.
+pub const THINKING: &str = "\u{258d} thinking\u{2026}";
+
+/// [`THINKING`] in labeled output, without the block glyph.
+pub const WORKING: &str = "working\u{2026}";
+
+/// Display settings for the chat transcript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatOptions {
+    /// Columns per tab stop. Tabs in answers and tool output are expanded
+    /// to spaces so columns line up whatever the terminal's tab width.
+    pub tab_width: usize,
+    /// Draw [`STREAMING_CURSOR`] after the last message when it's a reply
+    /// still streaming. Toggled while streaming so the cursor blinks.
+    pub cursor: bool,
+    /// Label every message with its role ("USER:", "TOOL bash [denied]:")
+    /// instead of an emoji, drop the tree connectors, and use the
+    /// high-contrast theme (`[tui] accessibility`).
+    pub accessible: bool,
+    /// How much of each tool call and result to show.
+    pub tool_verbosity: ToolVerbosity,
+}
+
+impl Default for ChatOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            cursor: false,
+            accessible: false,
+            tool_verbosity: ToolVerbosity::Full,
+        }
+    }
+}
+
+/// Whether `message` is the placeholder for a reply with no text yet.
+pub fn is_thinking(message: &ChatMessage) -> bool {
+    message.kind == ChatMessageKind::Assistant && message.content.is_empty()
+}
+
+/// Expand tabs to spaces, padding to the next multiple of `tab_width`
+/// display columns. Only the rendered copy changes; the message itself, and
+/// what the model sees, keeps its tabs.
+pub fn expand_tabs(text: &str, tab_width: usize) -> Cow<'_, str> {
+    if !text.contains('\t') {
+        return Cow::Borrowed(text);
+    }
+    let tab_width = tab_width.max(1);
+    let mut out = String::with_capacity(text.len() + tab_width);
+    let mut column = 0;
+    for c in text.chars() {
+        if c == '\t' {
+            let pad = tab_width - column % tab_width;
+            out.extend(std::iter::repeat_n(' ', pad));
+            column += pad;
+        } else {
+            out.push(c);
+            column += unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Where a message sits in the turn tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreePosition {
+    /// Top-level message: user, assistant, system, or a result with no call.
+    Root,
+    /// A tool call in a run of consecutive calls; `first` and `last` mark
+    /// the ends of the run so the connectors can be drawn.
+    Call { first: bool, last: bool },
+    /// A tool result nested under its call. `call_last` mirrors the call's
+    /// `last` so the rail to later siblings continues past the result.
+    Result { call_last: bool },
+}
+
+/// Place each message in the turn tree. A run of consecutive tool calls and
+/// results forms one group; results are paired with calls by order, since the
+/// TUI places each result directly after the call with the same id.
+pub fn tree_positions(messages: &[ChatMessage]) -> Vec<TreePosition> {
+    let mut positions = vec![TreePosition::Root; messages.len()];
+    let mut idx = 0;
+    while idx < messages.len() {
+        if !matches!(messages[idx].kind, ChatMessageKind::ToolCall { .. }) {
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx < messages.len()
+            && matches!(
+                messages[idx].kind,
+                ChatMessageKind::ToolCall { .. } | ChatMessageKind::ToolResult { .. }
+            )
+        {
+            idx += 1;
+        }
+        let last_call = (start..idx)
+            .rev()
+            .find(|&i| matches!(messages[i].kind, ChatMessageKind::ToolCall { .. }))
+            .unwrap_or(start);
+        let mut call_last = false;
+        for (i, position) in positions.iter_mut().enumerate().take(idx).skip(start) {
+            *position = match messages[i].kind {
+                ChatMessageKind::ToolCall { .. } => {
+                    call_last = i == last_call;
+                    TreePosition::Call {
+                        first: i == start,
+                        last: call_last,
+                    }
+                }
+                _ => TreePosition::Result { call_last },
+            };
+        }
+    }
+    positions
+}
+
+/// A tool result in one line: its first non-blank line, cut to
+/// [`SUMMARY_CHARS`], and how many lines follow it.
+pub fn result_summary(content: &str) -> String {
+    let mut lines = content.lines().skip_while(|l| l.trim().is_empty());
+    let Some(first) = lines.next() else {
+        return "(no output)".to_string();
+    };
+    let first = first.trim();
+    let mut summary: String = first.chars().take(SUMMARY_CHARS).collect();
+    if first.chars().count() > SUMMARY_CHARS {
+        summary.push('\u{2026}');
+    }
+    let rest = lines.count();
+    if rest > 0 {
+        summary.push_str(&format!(
+            " (+{} line{})",
+            rest,
+            if rest == 1 { "" } else { "s" }
+        ));
+    }
+    summary
+}
+
+/// A turn's tool calls, counted for the tally line hidden tool output shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ToolTally {
+    calls: usize,
+    /// Calls that ran and returned an error.
+    failed: usize,
+    /// Calls denied or whose approval timed out.
+    not_run: usize,
+}
+
+impl ToolTally {
+    fn describe(&self) -> String {
+        let mut text = format!(
+            "{} tool call{}",
+            self.calls,
+            if self.calls == 1 { "" } else { "s" }
+        );
+        let mut notes = Vec::new();
+        if self.failed > 0 {
+            notes.push(format!("{} failed", self.failed));
+        }
+        if self.not_run > 0 {
+            notes.push(format!("{} not run", self.not_run));
+        }
+        if !notes.is_empty() {
+            text.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        text
+    }
+}
+
+/// The tally of each turn's tool calls, at the index of the turn's first
+/// call. A turn starts at each user message.
+fn turn_tallies(messages: &[ChatMessage]) -> Vec<Option<ToolTally>> {
+    let mut tallies = vec![None; messages.len()];
+    let mut first_call = None;
+    // Whether the latest call ran; a refused call's error result isn't a failure.
+    let mut ran = false;
+    for (idx, msg) in messages.iter().enumerate() {
+        match &msg.kind {
+            ChatMessageKind::User => first_call = None,
+            ChatMessageKind::ToolCall { status, .. } => {
+                let at = *first_call.get_or_insert(idx);
+                let tally: &mut ToolTally = tallies[at].get_or_insert_default();
+                tally.calls += 1;
+                ran = !matches!(status, ToolCallStatus::Denied | ToolCallStatus::TimedOut);
+                if !ran {
+                    tally.not_run += 1;
+                }
+            }
+            ChatMessageKind::ToolResult { is_error: true } if ran => {
+                if let Some(tally) = first_call.and_then(|at| tallies[at].as_mut()) {
+                    tally.failed += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tallies
+}
 
 /// Render a slice of chat messages into styled Lines for display.
-pub fn render_chat_lines(messages: &[ChatMessage]) -> Vec<Line<'static>> {
+pub fn render_chat_lines(messages: &[ChatMessage], options: &ChatOptions) -> Vec<Line<'static>> {
+    render_chat(messages, options).0
+}
+
+/// Render chat messages, along with the index of each message's first line
+/// (after its separator), for navigating by message or turn.
+pub fn render_chat(
+    messages: &[ChatMessage],
+    options: &ChatOptions,
+) -> (Vec<Line<'static>>, Vec<usize>) {
     let mut lines = Vec::new();
+    let mut starts = Vec::with_capacity(messages.len());
+    let positions = tree_positions(messages);
+    let theme = Theme::new(options.accessible);
+    let connector_style = theme.connector;
+    // Screen readers read box drawing aloud, so labeled output drops the tree.
+    let tree = !options.accessible;
+    let verbosity = options.tool_verbosity;
+    let tallies = match verbosity {
+        ToolVerbosity::Hidden => turn_tallies(messages),
+        _ => Vec::new(),
+    };
 
     for (idx, msg) in messages.iter().enumerate() {
-        // Add a blank separator line between message groups.
-        // ToolResult is part of the preceding ToolCall group, so no separator before it.
-        if idx > 0 && !matches!(msg.kind, ChatMessageKind::ToolResult { .. }) {
+        // Quieter output folds a result into its call's line, or a turn's
+        // calls and results into one tally; the messages themselves stay.
+        let tool = matches!(
+            msg.kind,
+            ChatMessageKind::ToolCall { .. } | ChatMessageKind::ToolResult { .. }
+        );
+        let folded = match verbosity {
+            ToolVerbosity::Full => false,
+            ToolVerbosity::Compact => matches!(positions[idx], TreePosition::Result { .. }),
+            ToolVerbosity::Hidden => tool && tallies[idx].is_none(),
+        };
+        if folded {
+            starts.push(lines.len());
+            continue;
+        }
+
+        // Add a blank separator line between message groups. Calls and
+        // results inside a tool group are joined by the tree connectors.
+        let grouped = matches!(
+            positions[idx],
+            TreePosition::Call { first: false, .. } | TreePosition::Result { .. }
+        );
+        if idx > 0 && !grouped && !matches!(msg.kind, ChatMessageKind::ToolResult { .. }) {
             lines.push(Line::from(""));
         }
+        starts.push(lines.len());
+
+        if let Some(tally) = tallies.get(idx).copied().flatten() {
+            let prefix = if options.accessible {
+                "TOOLS: "
+            } else {
+                "🔧 "
+            };
+            lines.push(Line::from(Span::styled(
+                format!("{}{}", prefix, tally.describe()),
+                theme.tool,
+            )));
+            continue;
+        }
 
         match &msg.kind {
             ChatMessageKind::User => {
+                let prefix = match (options.accessible, msg.pinned) {
+                    (true, false) => "USER: ",
+                    (true, true) => "USER [pinned]: ",
+                    (false, false) => "💬 ",
+                    (false, true) => "💬 📌 ",
+                };
                 lines.push(Line::from(vec![
-                    Span::styled(
-                        "💬 ",
-                        Style::default()
-                            .fg(Color::Green)
-                            .add_modifier(Modifier::BOLD),
-                    ),
+                    Span::styled(prefix, theme.user),
                     Span::raw(msg.content.clone()),
                 ]));
             }
+            ChatMessageKind::Assistant if msg.content.is_empty() => {
+                let (prefix, placeholder) = if options.accessible {
+                    ("ASSISTANT: ", WORKING)
+                } else {
+                    ("🤖 ", THINKING)
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(prefix, theme.assistant),
+                    Span::styled(placeholder, theme.muted),
+                ]));
+            }
             ChatMessageKind::Assistant => {
+                let prefix = match (options.accessible, msg.pinned) {
+                    (true, false) => "ASSISTANT: ",
+                    (true, true) => "ASSISTANT [pinned]: ",
+                    (false, false) => "🤖 ",
+                    (false, true) => "🤖 📌 ",
+                };
                 // First line gets the prefix, subsequent lines are plain.
                 let content_lines: Vec<&str> = msg.content.split('\n').collect();
                 for (i, text) in content_lines.iter().enumerate() {
+                    let text = expand_tabs(text, options.tab_width);
                     if i == 0 {
                         lines.push(Line::from(vec![
-                            Span::styled(
-                                "🤖 ",
-                                Style::default()
-                                    .fg(Color::Cyan)
-                                    .add_modifier(Modifier::BOLD),
-                            ),
+                            Span::styled(prefix, theme.assistant),
                             Span::raw(text.to_string()),
                         ]));
                     } else {
                         lines.push(Line::from(Span::raw(text.to_string())));
                     }
                 }
+                if options.cursor
+                    && idx + 1 == messages.len()
+                    && let Some(last) = lines.last_mut()
+                {
+                    last.spans.push(Span::styled(
+                        STREAMING_CURSOR,
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
             }
-            ChatMessageKind::ToolCall { tool_name, status } => {
-                let status_str = match status {
-                    ToolCallStatus::Allowed => "✅",
-                    ToolCallStatus::Denied => "🚫",
-                    ToolCallStatus::Pending => "⏳",
-                    ToolCallStatus::TimedOut => "⏰",
-                };
-                lines.push(Line::from(Span::styled(
-                    format!("🔧 {}({}) {}", tool_name, msg.content, status_str),
-                    Style::default().fg(Color::Yellow),
-                )));
+            ChatMessageKind::ToolCall {
+                tool_name,
+                status,
+                approval_reason,
+                ..
+            } => {
+                let mut spans = Vec::new();
+                if tree && let TreePosition::Call { last, .. } = positions[idx] {
+                    let connector = if last { "└─ " } else { "├─ " };
+                    spans.push(Span::styled(connector, connector_style));
+                }
+                if verbosity == ToolVerbosity::Compact {
+                    // The result, when there is one, is placed right after its call.
+                    let result = match (positions.get(idx + 1), messages.get(idx + 1)) {
+                        (Some(TreePosition::Result { .. }), Some(next)) => match next.kind {
+                            ChatMessageKind::ToolResult { is_error } => {
+                                Some((is_error, next.content.as_str()))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    spans.extend(compact_call(
+                        tool_name,
+                        &msg.content,
+                        status,
+                        result,
+                        options,
+                        &theme,
+                    ));
+                } else if options.accessible {
+                    let style = match status {
+                        ToolCallStatus::Denied => theme.denied,
+                        ToolCallStatus::TimedOut => theme.timed_out,
+                        _ => theme.tool,
+                    };
+                    spans.push(Span::styled(
+                        format!(
+                            "TOOL {} [{}]: {}",
+                            tool_name,
+                            status_label(status),
+                            msg.content
+                        ),
+                        style,
+                    ));
+                } else {
+                    // Calls that never ran say why in their own color, so a
+                    // denial reads differently from an approval nobody answered.
+                    let (glyph, outcome) = match status {
+                        ToolCallStatus::Allowed => (" ✅", None),
+                        ToolCallStatus::Pending => (" ⏳", None),
+                        ToolCallStatus::Denied => ("", Some((" ✗ denied", theme.denied))),
+                        ToolCallStatus::TimedOut => ("", Some((" ⏱ timed out", theme.timed_out))),
+                    };
+                    spans.push(Span::styled(
+                        format!("🔧 {}({}){}", tool_name, msg.content, glyph),
+                        theme.tool,
+                    ));
+                    if let Some((label, style)) = outcome {
+                        spans.push(Span::styled(label, style));
+                    }
+                }
+                if let Some(reason) = approval_reason {
+                    spans.push(Span::styled(format!(" \u{b7} {}", reason), theme.muted));
+                }
+                lines.push(Line::from(spans));
             }
             ChatMessageKind::ToolResult { is_error } => {
-                let prefix = if *is_error { "❌ " } else { "   " };
-                let style = if *is_error {
-                    Style::default().fg(Color::Red)
-                } else {
-                    Style::default().fg(Color::DarkGray)
+                let style = if *is_error { theme.error } else { theme.result };
+                // Nested results hang off their call: the rail continues when
+                // more calls follow, and the first line gets the branch.
+                let (rail, first_prefix, rest_prefix) = match positions[idx] {
+                    _ if options.accessible => {
+                        let label = if *is_error {
+                            "RESULT [error]: "
+                        } else {
+                            "RESULT: "
+                        };
+                        ("", label, "   ")
+                    }
+                    TreePosition::Result { call_last } => {
+                        let rail = if call_last { "   " } else { "│  " };
+                        let branch = if *is_error { "└─ ❌ " } else { "└─ " };
+                        (rail, branch, "   ")
+                    }
+                    _ => ("", if *is_error { "❌ " } else { "   " }, "   "),
                 };
                 let content_lines: Vec<&str> = msg.content.split('\n').collect();
-                let max_lines = 10;
-                let truncated = content_lines.len() > max_lines;
-                for (i, text) in content_lines.iter().take(max_lines).enumerate() {
-                    let line_prefix = if i == 0 { prefix } else { "   " };
-                    lines.push(Line::from(Span::styled(
-                        format!("{}{}", line_prefix, text),
+                let truncated = content_lines.len() > MAX_RESULT_LINES;
+                for (i, text) in content_lines.iter().take(MAX_RESULT_LINES).enumerate() {
+                    let line_prefix = if i == 0 { first_prefix } else { rest_prefix };
+                    let text = expand_tabs(text, options.tab_width);
+                    lines.push(result_line(
+                        rail,
+                        line_prefix,
+                        &text,
                         style,
-                    )));
+                        connector_style,
+                    ));
                 }
                 if truncated {
-                    lines.push(Line::from(Span::styled(
-                        format!("   ... ({} more lines)", content_lines.len() - max_lines),
+                    let text = format!(
+                        "... ({} more lines)",
+                        content_lines.len() - MAX_RESULT_LINES
+                    );
+                    lines.push(result_line(
+                        rail,
+                        rest_prefix,
+                        &text,
                         style,
-                    )));
+                        connector_style,
+                    ));
                 }
             }
             ChatMessageKind::System => {
+                let prefix = if options.accessible {
+                    "SYSTEM: "
+                } else {
+                    "💡 "
+                };
                 lines.push(Line::from(Span::styled(
-                    format!("💡 {}", msg.content),
-                    Style::default()
-                        .fg(Color::DarkGray)
-                        .add_modifier(Modifier::ITALIC),
+                    format!("{}{}", prefix, msg.content),
+                    theme.system,
                 )));
             }
         }
     }
 
-    lines
+    (lines, starts)
+}
+
+/// A tool call and its result on one line: "✓ bash(ls) → a.txt (+1 line)".
+fn compact_call(
+    tool_name: &str,
+    args: &str,
+    status: &ToolCallStatus,
+    result: Option<(bool, &str)>,
+    options: &ChatOptions,
+    theme: &Theme,
+) -> Vec<Span<'static>> {
+    let failed = matches!(result, Some((true, _)));
+    let call = if options.accessible {
+        // Denied and timed-out calls get an error result too; say why.
+        let label = match status {
+            ToolCallStatus::Allowed if failed => "error",
+            status => status_label(status),
+        };
+        format!("TOOL {} [{}]: {}", tool_name, label, args)
+    } else {
+        let glyph = match status {
+            ToolCallStatus::Denied => "✗",
+            ToolCallStatus::TimedOut => "⏱",
+            ToolCallStatus::Pending => "⏳",
+            ToolCallStatus::Allowed if result.is_none() => "⏳",
+            ToolCallStatus::Allowed if failed => "✗",
+            ToolCallStatus::Allowed => "✓",
+        };
+        format!("{} {}({})", glyph, tool_name, args)
+    };
+    let mut spans = vec![Span::styled(call, theme.tool)];
+    match status {
+        ToolCallStatus::Denied if !options.accessible => {
+            spans.push(Span::styled(" denied", theme.denied));
+        }
+        ToolCallStatus::TimedOut if !options.accessible => {
+            spans.push(Span::styled(" timed out", theme.timed_out));
+        }
+        _ => {}
+    }
+    if let Some((is_error, content)) = result {
+        let style = if is_error { theme.error } else { theme.result };
+        let summary = result_summary(&expand_tabs(content, options.tab_width));
+        spans.push(Span::styled(format!(" → {}", summary), style));
+    }
+    spans
+}
+
+/// A tool call's status spelled out, for labeled output.
+fn status_label(status: &ToolCallStatus) -> &'static str {
+    match status {
+        ToolCallStatus::Allowed => "allowed",
+        ToolCallStatus::Pending => "pending",
+        ToolCallStatus::Denied => "denied",
+        ToolCallStatus::TimedOut => "timed out",
+    }
+}
+
+/// One line of tool result output, with the tree rail in the connector style.
+fn result_line(
+    rail: &str,
+    prefix: &str,
+    text: &str,
+    style: Style,
+    connector_style: Style,
+) -> Line<'static> {
+    let body = Span::styled(format!("{}{}", prefix, text), style);
+    if rail.is_empty() {
+        Line::from(body)
+    } else {
+        Line::from(vec![Span::styled(rail.to_string(), connector_style), body])
+    }
 }
 
 /// Create a scrollable Paragraph widget from chat messages.
-pub fn chat_widget(messages: &[ChatMessage], scroll_offset: u16) -> Paragraph<'static> {
-    let lines = render_chat_lines(messages);
+pub fn chat_widget(
+    messages: &[ChatMessage],
+    options: &ChatOptions,
+    scroll_offset: u16,
+) -> Paragraph<'static> {
+    let lines = render_chat_lines(messages, options);
     Paragraph::new(lines)
         .wrap(Wrap { trim: false })
         .scroll((scroll_offset, 0))
@@ -110,14 +558,29 @@ pub fn chat_widget(messages: &[ChatMessage], scroll_offset: u16) -> Paragraph<'s
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::approval::ApprovalReason;
+    use ratatui::style::Modifier;
+
+    #[test]
+    fn message_starts_skip_separators() {
+        let messages = vec![
+            ChatMessage::new(ChatMessageKind::User, "hello".to_string()),
+            ChatMessage::new(ChatMessageKind::Assistant, "one\ntwo".to_string()),
+            ChatMessage::new(ChatMessageKind::User, "again".to_string()),
+        ];
+        let (lines, starts) = render_chat(&messages, &ChatOptions::default());
+        assert_eq!(starts.len(), 3);
+        assert_eq!(starts[0], 0);
+        for &start in &starts[1..] {
+            assert!(lines[start - 1].spans.iter().all(|s| s.content.is_empty()));
+            assert!(!lines[start].spans.is_empty());
+        }
+    }
 
     #[test]
     fn user_message_has_green_prefix() {
-        let messages = vec![ChatMessage {
-            kind: ChatMessageKind::User,
-            content: "hello".to_string(),
-        }];
-        let lines = render_chat_lines(&messages);
+        let messages = vec![ChatMessage::new(ChatMessageKind::User, "hello".to_string())];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         assert_eq!(lines.len(), 1);
         let spans = &lines[0].spans;
         assert!(spans.len() >= 2);
@@ -127,43 +590,122 @@ mod tests {
 
     #[test]
     fn assistant_message_has_cyan_prefix() {
-        let messages = vec![ChatMessage {
-            kind: ChatMessageKind::Assistant,
-            content: "hi there".to_string(),
-        }];
-        let lines = render_chat_lines(&messages);
+        let messages = vec![ChatMessage::new(
+            ChatMessageKind::Assistant,
+            "hi there".to_string(),
+        )];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         assert_eq!(lines.len(), 1);
         let spans = &lines[0].spans;
         assert_eq!(spans[0].content, "🤖 ");
         assert_eq!(spans[0].style.fg, Some(Color::Cyan));
     }
 
+    #[test]
+    fn empty_reply_renders_as_thinking() {
+        let messages = vec![ChatMessage::new(ChatMessageKind::Assistant, String::new())];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
+        assert_eq!(lines.len(), 1);
+        assert_eq!(line_text(&lines[0]), format!("🤖 {}", THINKING));
+        assert!(lines[0].spans[1].style.add_modifier.contains(Modifier::DIM));
+    }
+
+    #[test]
+    fn cursor_follows_only_the_streaming_reply() {
+        let messages = vec![
+            ChatMessage::new(ChatMessageKind::Assistant, "earlier".to_string()),
+            ChatMessage::new(ChatMessageKind::Assistant, "one\ntw".to_string()),
+        ];
+        let on = ChatOptions {
+            cursor: true,
+            ..ChatOptions::default()
+        };
+        let lines = render_chat_lines(&messages, &on);
+        assert_eq!(line_text(&lines[0]), "🤖 earlier");
+        assert_eq!(
+            line_text(lines.last().unwrap()),
+            format!("tw{}", STREAMING_CURSOR)
+        );
+
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
+        assert_eq!(line_text(lines.last().unwrap()), "tw");
+    }
+
     #[test]
     fn multiline_assistant_message() {
-        let messages = vec![ChatMessage {
-            kind: ChatMessageKind::Assistant,
-            content: "line1\nline2\nline3".to_string(),
-        }];
-        let lines = render_chat_lines(&messages);
+        let messages = vec![ChatMessage::new(
+            ChatMessageKind::Assistant,
+            "line1\nline2\nline3".to_string(),
+        )];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         assert_eq!(lines.len(), 3);
     }
 
     #[test]
     fn tool_call_has_gear_prefix() {
-        let messages = vec![ChatMessage {
-            kind: ChatMessageKind::ToolCall {
+        let messages = vec![ChatMessage::new(
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
                 tool_name: "bash".to_string(),
                 status: ToolCallStatus::Allowed,
+                approval_reason: None,
             },
-            content: "ls -la".to_string(),
-        }];
-        let lines = render_chat_lines(&messages);
+            "ls -la".to_string(),
+        )];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         assert_eq!(lines.len(), 1);
         let spans = &lines[0].spans;
-        assert_eq!(spans[0].style.fg, Some(Color::Yellow));
-        assert!(spans[0].content.contains("🔧"));
-        assert!(spans[0].content.contains("bash"));
-        assert!(spans[0].content.contains("✅"));
+        assert_eq!(spans[0].content, "└─ ");
+        assert_eq!(spans[1].style.fg, Some(Color::Yellow));
+        assert!(spans[1].content.contains("🔧"));
+        assert!(spans[1].content.contains("bash"));
+        assert!(spans[1].content.contains("✅"));
+    }
+
+    #[test]
+    fn denied_and_timed_out_calls_are_labeled_apart() {
+        let rendered = |status| {
+            let messages = vec![ChatMessage::new(
+                ChatMessageKind::ToolCall {
+                    tool_call_id: "c1".to_string(),
+                    tool_name: "bash".to_string(),
+                    status,
+                    approval_reason: None,
+                },
+                "rm -rf build".to_string(),
+            )];
+            let lines = render_chat_lines(&messages, &ChatOptions::default());
+            let label = lines[0].spans.last().unwrap().clone();
+            (label.content.to_string(), label.style.fg)
+        };
+        assert_eq!(
+            rendered(ToolCallStatus::Denied),
+            (" ✗ denied".to_string(), Some(Color::Red))
+        );
+        assert_eq!(
+            rendered(ToolCallStatus::TimedOut),
+            (" ⏱ timed out".to_string(), Some(Color::Magenta))
+        );
+    }
+
+    #[test]
+    fn auto_approved_tool_call_shows_a_dim_reason() {
+        let messages = vec![ChatMessage::new(
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "bash".to_string(),
+                status: ToolCallStatus::Allowed,
+                approval_reason: Some(ApprovalReason::AllowlistPattern(
+                    "/usr/bin/cargo".to_string(),
+                )),
+            },
+            "cargo build".to_string(),
+        )];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
+        let spans = &lines[0].spans;
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[2].content, " \u{b7} allowlist:/usr/bin/cargo");
+        assert!(spans[2].style.add_modifier.contains(Modifier::DIM));
     }
 
     #[test]
@@ -172,11 +714,11 @@ mod tests {
             .map(|i| format!("line {}", i))
             .collect::<Vec<_>>()
             .join("\n");
-        let messages = vec![ChatMessage {
-            kind: ChatMessageKind::ToolResult { is_error: false },
-            content: long_content,
-        }];
-        let lines = render_chat_lines(&messages);
+        let messages = vec![ChatMessage::new(
+            ChatMessageKind::ToolResult { is_error: false },
+            long_content,
+        )];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         // 10 visible lines + 1 truncation indicator
         assert_eq!(lines.len(), 11);
         let last_line = &lines[10].spans[0].content;
@@ -185,11 +727,8 @@ mod tests {
 
     #[test]
     fn system_message_is_italic_gray() {
-        let messages = vec![ChatMessage {
-            kind: ChatMessageKind::System,
-            content: "connected".to_string(),
-        }];
-        let lines = render_chat_lines(&messages);
+        let messages = vec![ChatMessage::note("connected".to_string())];
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         assert_eq!(lines.len(), 1);
         let spans = &lines[0].spans;
         assert_eq!(spans[0].style.fg, Some(Color::DarkGray));
@@ -199,16 +738,10 @@ mod tests {
     #[test]
     fn blank_separator_between_message_groups() {
         let messages = vec![
-            ChatMessage {
-                kind: ChatMessageKind::User,
-                content: "hi".to_string(),
-            },
-            ChatMessage {
-                kind: ChatMessageKind::Assistant,
-                content: "hello".to_string(),
-            },
+            ChatMessage::new(ChatMessageKind::User, "hi".to_string()),
+            ChatMessage::new(ChatMessageKind::Assistant, "hello".to_string()),
         ];
-        let lines = render_chat_lines(&messages);
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         // user line, blank separator, assistant line
         assert_eq!(lines.len(), 3);
         assert_eq!(lines[1].spans.len(), 0);
@@ -217,20 +750,436 @@ mod tests {
     #[test]
     fn no_separator_between_tool_call_and_result() {
         let messages = vec![
-            ChatMessage {
-                kind: ChatMessageKind::ToolCall {
+            ChatMessage::new(
+                ChatMessageKind::ToolCall {
+                    tool_call_id: "c1".to_string(),
                     tool_name: "bash".to_string(),
                     status: ToolCallStatus::Allowed,
+                    approval_reason: None,
                 },
-                content: "ls".to_string(),
-            },
-            ChatMessage {
-                kind: ChatMessageKind::ToolResult { is_error: false },
-                content: "file.txt".to_string(),
-            },
+                "ls".to_string(),
+            ),
+            ChatMessage::new(
+                ChatMessageKind::ToolResult { is_error: false },
+                "file.txt".to_string(),
+            ),
         ];
-        let lines = render_chat_lines(&messages);
+        let lines = render_chat_lines(&messages, &ChatOptions::default());
         // tool call line, tool result line (no separator)
         assert_eq!(lines.len(), 2);
     }
+
+    fn call(content: &str) -> ChatMessage {
+        ChatMessage::new(
+            ChatMessageKind::ToolCall {
+                tool_call_id: "c1".to_string(),
+                tool_name: "bash".to_string(),
+                status: ToolCallStatus::Allowed,
+                approval_reason: None,
+            },
+            content.to_string(),
+        )
+    }
+
+    fn result(content: &str) -> ChatMessage {
+        ChatMessage::new(
+            ChatMessageKind::ToolResult { is_error: false },
+            content.to_string(),
+        )
+    }
+
+    fn line_text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    fn message(kind: ChatMessageKind, content: &str) -> ChatMessage {
+        ChatMessage::new(kind, content.to_string())
+    }
+
+    fn call_kind(status: ToolCallStatus) -> ChatMessageKind {
+        ChatMessageKind::ToolCall {
+            tool_call_id: "c1".to_string(),
+            tool_name: "bash".to_string(),
+            status,
+            approval_reason: None,
+        }
+    }
+
+    const ACCESSIBLE: ChatOptions = ChatOptions {
+        tab_width: 4,
+        cursor: false,
+        accessible: true,
+        tool_verbosity: ToolVerbosity::Full,
+    };
+
+    #[test]
+    fn accessible_mode_labels_every_kind_of_message() {
+        let cases = [
+            (message(ChatMessageKind::User, "hi"), "USER: hi"),
+            (
+                message(ChatMessageKind::Assistant, "hello"),
+                "ASSISTANT: hello",
+            ),
+            (
+                message(ChatMessageKind::Assistant, ""),
+                "ASSISTANT: working\u{2026}",
+            ),
+            (
+                message(call_kind(ToolCallStatus::Allowed), "ls"),
+                "TOOL bash [allowed]: ls",
+            ),
+            (
+                message(call_kind(ToolCallStatus::Pending), "ls"),
+                "TOOL bash [pending]: ls",
+            ),
+            (
+                message(call_kind(ToolCallStatus::Denied), "ls"),
+                "TOOL bash [denied]: ls",
+            ),
+            (
+                message(call_kind(ToolCallStatus::TimedOut), "ls"),
+                "TOOL bash [timed out]: ls",
+            ),
+            (
+                message(ChatMessageKind::ToolResult { is_error: false }, "out"),
+                "RESULT: out",
+            ),
+            (
+                message(ChatMessageKind::ToolResult { is_error: true }, "boom"),
+                "RESULT [error]: boom",
+            ),
+            (message(ChatMessageKind::System, "note"), "SYSTEM: note"),
+        ];
+        for (msg, expected) in cases {
+            let lines = render_chat_lines(std::slice::from_ref(&msg), &ACCESSIBLE);
+            assert_eq!(line_text(&lines[0]), expected);
+        }
+    }
+
+    #[test]
+    fn accessible_mode_has_no_dim_grays_or_glyph_only_markers() {
+        let messages = vec![
+            message(ChatMessageKind::User, "run it"),
+            message(ChatMessageKind::Assistant, "Running."),
+            message(call_kind(ToolCallStatus::Allowed), "ls"),
+            message(ChatMessageKind::ToolResult { is_error: false }, "a\nb"),
+            message(call_kind(ToolCallStatus::Denied), "rm -rf /"),
+            message(
+                ChatMessageKind::ToolResult { is_error: true },
+                "Denied by user",
+            ),
+            message(ChatMessageKind::System, "note"),
+            message(ChatMessageKind::Assistant, ""),
+        ];
+        let lines = render_chat_lines(&messages, &ACCESSIBLE);
+        for line in &lines {
+            for span in &line.spans {
+                assert_ne!(span.style.fg, Some(Color::DarkGray), "{:?}", span);
+                assert!(
+                    !span.style.add_modifier.contains(Modifier::DIM),
+                    "{:?}",
+                    span
+                );
+            }
+            let text = line_text(line);
+            for glyph in ["💬", "🤖", "🔧", "✅", "❌", "💡", "├─", "└─", "│"] {
+                assert!(!text.contains(glyph), "{:?}", text);
+            }
+        }
+        // A denial and an error read differently in text, not only in color.
+        let text: Vec<String> = lines.iter().map(line_text).collect();
+        assert!(text.contains(&"TOOL bash [denied]: rm -rf /".to_string()));
+        assert!(text.contains(&"RESULT [error]: Denied by user".to_string()));
+    }
+
+    #[test]
+    fn pinned_messages_are_marked() {
+        let mut messages = vec![
+            message(ChatMessageKind::User, "which port?"),
+            message(ChatMessageKind::Assistant, "8443"),
+        ];
+        for m in &mut messages {
+            m.pinned = true;
+        }
+        let text: Vec<String> = render_chat_lines(&messages, &ChatOptions::default())
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(text, ["💬 📌 which port?", "", "🤖 📌 8443"]);
+        let text: Vec<String> = render_chat_lines(&messages, &ACCESSIBLE)
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(
+            text,
+            ["USER [pinned]: which port?", "", "ASSISTANT [pinned]: 8443"]
+        );
+    }
+
+    #[test]
+    fn tree_positions_group_calls_with_their_results() {
+        let messages = vec![
+            ChatMessage::new(ChatMessageKind::Assistant, "checking".to_string()),
+            call("ls"),
+            result("a.txt"),
+            call("pwd"),
+            result("/tmp"),
+            call("whoami"),
+            result("me"),
+        ];
+        assert_eq!(
+            tree_positions(&messages),
+            vec![
+                TreePosition::Root,
+                TreePosition::Call {
+                    first: true,
+                    last: false
+                },
+                TreePosition::Result { call_last: false },
+                TreePosition::Call {
+                    first: false,
+                    last: false
+                },
+                TreePosition::Result { call_last: false },
+                TreePosition::Call {
+                    first: false,
+                    last: true
+                },
+                TreePosition::Result { call_last: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_positions_split_runs_at_other_messages() {
+        let messages = vec![
+            call("ls"),
+            ChatMessage::note("denied".to_string()),
+            call("pwd"),
+            result("/tmp"),
+        ];
+        assert_eq!(
+            tree_positions(&messages),
+            vec![
+                TreePosition::Call {
+                    first: true,
+                    last: true
+                },
+                TreePosition::Root,
+                TreePosition::Call {
+                    first: true,
+                    last: true
+                },
+                TreePosition::Result { call_last: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_positions_leave_orphan_results_at_root() {
+        let messages = vec![result("stray"), call("ls"), call("pwd"), result("/tmp")];
+        assert_eq!(
+            tree_positions(&messages),
+            vec![
+                TreePosition::Root,
+                TreePosition::Call {
+                    first: true,
+                    last: false
+                },
+                TreePosition::Call {
+                    first: false,
+                    last: true
+                },
+                TreePosition::Result { call_last: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_tool_turn_renders_as_tree() {
+        let messages = vec![
+            call("ls"),
+            result("a.txt\nb.txt"),
+            call("pwd"),
+            result("/tmp"),
+        ];
+        let rendered: Vec<String> = render_chat_lines(&messages, &ChatOptions::default())
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "├─ 🔧 bash(ls) ✅",
+                "│  └─ a.txt",
+                "│     b.txt",
+                "└─ 🔧 bash(pwd) ✅",
+                "   └─ /tmp",
+            ]
+        );
+    }
+
+    fn verbosity(tool_verbosity: ToolVerbosity) -> ChatOptions {
+        ChatOptions {
+            tool_verbosity,
+            ..ChatOptions::default()
+        }
+    }
+
+    fn tool_turn() -> Vec<ChatMessage> {
+        vec![
+            message(ChatMessageKind::User, "run the tests"),
+            call("cargo test"),
+            result("\nrunning 114 tests\ntest a ... ok\ntest b ... ok"),
+            message(call_kind(ToolCallStatus::Denied), "rm -rf target"),
+            message(
+                ChatMessageKind::ToolResult { is_error: true },
+                "Denied by user",
+            ),
+            call("cat missing.txt"),
+            message(
+                ChatMessageKind::ToolResult { is_error: true },
+                "No such file",
+            ),
+            message(ChatMessageKind::Assistant, "114 passed."),
+        ]
+    }
+
+    #[test]
+    fn results_summarize_to_their_first_line() {
+        assert_eq!(result_summary("\n  ok  \n"), "ok");
+        assert_eq!(result_summary("a\nb\nc"), "a (+2 lines)");
+        assert_eq!(result_summary("a\nb"), "a (+1 line)");
+        assert_eq!(result_summary(""), "(no output)");
+        let long = "x".repeat(SUMMARY_CHARS + 5);
+        assert_eq!(
+            result_summary(&long),
+            format!("{}\u{2026}", "x".repeat(SUMMARY_CHARS))
+        );
+    }
+
+    #[test]
+    fn compact_verbosity_puts_each_call_and_result_on_one_line() {
+        let rendered: Vec<String> =
+            render_chat_lines(&tool_turn(), &verbosity(ToolVerbosity::Compact))
+                .iter()
+                .map(line_text)
+                .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "💬 run the tests",
+                "",
+                "├─ ✓ bash(cargo test) → running 114 tests (+2 lines)",
+                "├─ ✗ bash(rm -rf target) denied → Denied by user",
+                "└─ ✗ bash(cat missing.txt) → No such file",
+                "",
+                "🤖 114 passed.",
+            ]
+        );
+
+        let (lines, starts) = render_chat(&tool_turn(), &verbosity(ToolVerbosity::Compact));
+        assert_eq!(starts.len(), tool_turn().len());
+        assert!(starts.iter().all(|&start| start <= lines.len()));
+    }
+
+    #[test]
+    fn compact_calls_still_running_have_no_result_yet() {
+        let messages = vec![call("sleep 5")];
+        let lines = render_chat_lines(&messages, &verbosity(ToolVerbosity::Compact));
+        assert_eq!(line_text(&lines[0]), "└─ ⏳ bash(sleep 5)");
+
+        let labeled = ChatOptions {
+            tool_verbosity: ToolVerbosity::Compact,
+            ..ACCESSIBLE
+        };
+        let messages = vec![
+            call("cat x"),
+            message(ChatMessageKind::ToolResult { is_error: true }, "gone"),
+        ];
+        let lines = render_chat_lines(&messages, &labeled);
+        assert_eq!(line_text(&lines[0]), "TOOL bash [error]: cat x → gone");
+    }
+
+    #[test]
+    fn accessible_compact_calls_say_denied_and_timed_out() {
+        let labeled = ChatOptions {
+            tool_verbosity: ToolVerbosity::Compact,
+            ..ACCESSIBLE
+        };
+        for (status, label) in [
+            (ToolCallStatus::Denied, "denied"),
+            (ToolCallStatus::TimedOut, "timed out"),
+        ] {
+            let messages = vec![
+                message(call_kind(status), "rm -rf build"),
+                message(ChatMessageKind::ToolResult { is_error: true }, "not run"),
+            ];
+            let lines = render_chat_lines(&messages, &labeled);
+            assert_eq!(
+                line_text(&lines[0]),
+                format!("TOOL bash [{}]: rm -rf build → not run", label)
+            );
+        }
+    }
+
+    #[test]
+    fn hidden_verbosity_shows_one_tally_per_turn() {
+        let mut messages = tool_turn();
+        messages.push(message(ChatMessageKind::User, "again"));
+        messages.push(call("ls"));
+        messages.push(result("a.txt"));
+        let rendered: Vec<String> = render_chat_lines(&messages, &verbosity(ToolVerbosity::Hidden))
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "💬 run the tests",
+                "",
+                "🔧 3 tool calls (1 failed, 1 not run)",
+                "",
+                "🤖 114 passed.",
+                "",
+                "💬 again",
+                "",
+                "🔧 1 tool call",
+            ]
+        );
+    }
+
+    #[test]
+    fn tabs_expand_to_the_next_tab_stop() {
+        assert_eq!(expand_tabs("\tx", 4), "    x");
+        assert_eq!(expand_tabs("ab\tx", 4), "ab  x");
+        assert_eq!(expand_tabs("abcd\tx", 4), "abcd    x");
+        assert_eq!(expand_tabs("\tx", 8), "        x");
+        // Wide characters take two columns.
+        assert_eq!(expand_tabs("\u{65e5}\tx", 4), "\u{65e5}  x");
+        assert!(matches!(expand_tabs("no tabs", 4), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn tabs_in_answers_and_results_use_the_configured_width() {
+        let messages = vec![
+            ChatMessage::new(
+                ChatMessageKind::Assistant,
+                "fn main() {\n\tprintln!();\n}".to_string(),
+            ),
+            result("a\tb"),
+        ];
+        let options = ChatOptions {
+            tab_width: 2,
+            ..ChatOptions::default()
+        };
+        let rendered: Vec<String> = render_chat_lines(&messages, &options)
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(rendered[1], "  println!();");
+        assert_eq!(rendered[3], "   a b");
+        // The message itself keeps its tab.
+        assert!(messages[0].content.contains('\t'));
+    }
 }
@@ -0,0 +1,102 @@
+// ABOUTME: Slash command completion popup — the commands matching what's typed, drawn just above the input.
+// ABOUTME: One row per command: its usage in cyan, then its help text dimmed.
+
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// One command in the popup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    /// `/name <args>`.
+    pub usage: String,
+    pub help: String,
+}
+
+/// The popup's rows, with help text lined up after the longest usage.
+/// `more` is how many matches didn't fit.
+pub fn completion_lines(items: &[CompletionItem], more: usize) -> Vec<Line<'static>> {
+    let width = items
+        .iter()
+        .map(|i| i.usage.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines: Vec<Line<'static>> = items
+        .iter()
+        .map(|item| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", item.usage, width = width),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    item.help.clone(),
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::DIM),
+                ),
+            ])
+        })
+        .collect();
+    if more > 0 {
+        lines.push(Line::from(Span::styled(
+            format!("\u{2026} {} more", more),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines
+}
+
+/// Where the popup goes: directly above `input`, `rows` tall plus its
+/// border, clipped to the `above` area it covers.
+pub fn popup_area(input: Rect, above: Rect, rows: usize) -> Rect {
+    let height = (rows as u16 + 2).min(above.height);
+    Rect {
+        x: input.x,
+        y: input.y.saturating_sub(height).max(above.y),
+        width: input.width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(usage: &str, help: &str) -> CompletionItem {
+        CompletionItem {
+            usage: usage.to_string(),
+            help: help.to_string(),
+        }
+    }
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn help_lines_up_and_overflow_is_counted() {
+        let lines = completion_lines(
+            &[
+                item("/edit <path>", "Open a file"),
+                item("/effort", "Change effort"),
+            ],
+            3,
+        );
+        assert_eq!(lines.len(), 3);
+        assert_eq!(text(&lines[0]), "/edit <path>  Open a file");
+        assert_eq!(text(&lines[1]), "/effort       Change effort");
+        assert_eq!(text(&lines[2]), "\u{2026} 3 more");
+    }
+
+    #[test]
+    fn popup_sits_above_the_input_within_the_chat() {
+        let chat = Rect::new(0, 1, 80, 20);
+        let input = Rect::new(0, 21, 80, 3);
+        assert_eq!(popup_area(input, chat, 4), Rect::new(0, 15, 80, 6));
+        // A short chat area clips the popup rather than overlapping the header.
+        let chat = Rect::new(0, 1, 80, 4);
+        let input = Rect::new(0, 5, 80, 3);
+        assert_eq!(popup_area(input, chat, 8), Rect::new(0, 1, 80, 4));
+    }
+}
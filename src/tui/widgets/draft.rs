@@ -0,0 +1,116 @@
+// ABOUTME: Draft token preview — estimates what sending the current input will cost.
+// ABOUTME: Colors the estimate by its share of remaining context and the compaction limit.
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+use crate::agent::compaction::approx_token_count;
+use crate::tui::widgets::status::format_tokens;
+
+/// Share of the remaining context a draft can take before it's flagged.
+const LARGE_DRAFT_RATIO: f64 = 0.1;
+
+/// How much of the context budget sending the draft would use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DraftCost {
+    Normal,
+    /// The draft alone takes more than 10% of the remaining context.
+    Large,
+    /// Sending the draft would push the context past the compaction limit.
+    TriggersCompaction,
+}
+
+/// Approximate tokens in `draft`. The estimate goes by length alone, so
+/// it's cheap enough to take on every frame.
+pub fn draft_tokens(draft: &str) -> u64 {
+    approx_token_count(draft) as u64
+}
+
+/// Classify a draft's cost, using the same `current > limit` test as the
+/// agent loop's compaction check.
+pub fn draft_cost(
+    draft_tokens: u64,
+    context_used: u64,
+    context_window: u64,
+    compaction_limit: Option<u64>,
+) -> DraftCost {
+    if let Some(limit) = compaction_limit
+        && context_used + draft_tokens > limit
+    {
+        return DraftCost::TriggersCompaction;
+    }
+    let remaining = context_window.saturating_sub(context_used);
+    if draft_tokens as f64 > remaining as f64 * LARGE_DRAFT_RATIO {
+        DraftCost::Large
+    } else {
+        DraftCost::Normal
+    }
+}
+
+/// Input border title like " ≈ 3.4k tokens ", or None for an empty draft.
+pub fn draft_title(draft_tokens: u64, cost: DraftCost) -> Option<Line<'static>> {
+    if draft_tokens == 0 {
+        return None;
+    }
+    let color = match cost {
+        DraftCost::Normal => Color::DarkGray,
+        DraftCost::Large => Color::Yellow,
+        DraftCost::TriggersCompaction => Color::Red,
+    };
+    let text = format!(" \u{2248} {} tokens ", format_tokens(draft_tokens));
+    Some(Line::from(Span::styled(text, Style::default().fg(color))).right_aligned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_draft_is_normal() {
+        assert_eq!(
+            draft_cost(500, 10_000, 200_000, Some(180_000)),
+            DraftCost::Normal
+        );
+    }
+
+    #[test]
+    fn draft_over_tenth_of_remaining_is_large() {
+        // 100k remaining: 10k is the line.
+        assert_eq!(
+            draft_cost(10_000, 100_000, 200_000, Some(180_000)),
+            DraftCost::Normal
+        );
+        assert_eq!(
+            draft_cost(10_001, 100_000, 200_000, Some(180_000)),
+            DraftCost::Large
+        );
+    }
+
+    #[test]
+    fn draft_past_compaction_limit_triggers_compaction() {
+        assert_eq!(
+            draft_cost(10_001, 170_000, 200_000, Some(180_000)),
+            DraftCost::TriggersCompaction
+        );
+        // Exactly at the limit doesn't compact.
+        assert_eq!(
+            draft_cost(10_000, 170_000, 200_000, Some(180_000)),
+            DraftCost::Large
+        );
+    }
+
+    #[test]
+    fn no_compaction_color_when_disabled() {
+        assert_eq!(draft_cost(50_000, 170_000, 200_000, None), DraftCost::Large);
+    }
+
+    #[test]
+    fn title_formats_and_colors_estimate() {
+        assert!(draft_title(0, DraftCost::Normal).is_none());
+        let title = draft_title(3_400, DraftCost::Large).unwrap();
+        assert_eq!(title.to_string(), " \u{2248} 3.4k tokens ");
+        assert_eq!(title.spans[0].style.fg, Some(Color::Yellow));
+        let title = draft_title(3_400, DraftCost::TriggersCompaction).unwrap();
+        assert_eq!(title.spans[0].style.fg, Some(Color::Red));
+    }
+}
@@ -0,0 +1,80 @@
+// ABOUTME: Header widget — the app name on the top row, with its paw print unless ASCII mode is on, and a mode badge.
+// ABOUTME: Measured in display cells so the emoji's two columns never push the row past the terminal.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::tui::widgets::align::right_align;
+
+/// The header row for a terminal `width` cells wide, with `mode` (e.g.
+/// "observe") as a badge on the right. The paw print is dropped in ASCII
+/// mode or when it would overflow the row; the badge only when even the
+/// plain title doesn't leave room for it.
+pub fn header_line(width: u16, ascii: bool, mode: Option<&str>) -> Line<'static> {
+    let style = Style::default()
+        .fg(Color::White)
+        .add_modifier(Modifier::BOLD);
+    let titles: &[&str] = if ascii {
+        &[" claw"]
+    } else {
+        &[" \u{1F43E} claw", " claw"]
+    };
+    let badge: Vec<Span<'static>> = mode
+        .map(|mode| {
+            let style = Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD);
+            vec![Span::styled(format!("{} ", mode), style)]
+        })
+        .unwrap_or_default();
+    [badge, Vec::new()]
+        .iter()
+        .flat_map(|right| titles.iter().map(move |title| (*title, right)))
+        .find_map(|(title, right)| {
+            right_align(
+                vec![Span::styled(title, style)],
+                right.clone(),
+                width as usize,
+            )
+        })
+        .unwrap_or_else(|| Line::from(Span::styled(" claw", style)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::widgets::align::display_width;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.to_string()).collect()
+    }
+
+    #[test]
+    fn the_paw_print_shows_when_it_fits() {
+        let line = header_line(80, false, None);
+        assert_eq!(text(&line), " \u{1F43E} claw");
+        assert_eq!(display_width(&line.spans), 8);
+    }
+
+    #[test]
+    fn ascii_mode_and_tiny_widths_drop_the_emoji() {
+        assert_eq!(text(&header_line(80, true, None)), " claw");
+        assert_eq!(text(&header_line(6, false, None)), " claw");
+    }
+
+    #[test]
+    fn the_mode_badge_sits_on_the_right_edge() {
+        let line = header_line(20, true, Some("observe"));
+        assert_eq!(text(&line), " claw       observe ");
+        assert_eq!(display_width(&line.spans), 20);
+        // The paw print goes before the badge does.
+        assert_eq!(
+            text(&header_line(15, false, Some("observe"))),
+            " claw  observe "
+        );
+        assert_eq!(
+            text(&header_line(10, false, Some("observe"))),
+            " \u{1F43E} claw"
+        );
+    }
+}
@@ -1,7 +1,14 @@
-// ABOUTME: TUI widget sub-modules for chat, status bar, approval prompt, and question prompt.
+// ABOUTME: TUI widget sub-modules for the header, chat and its theme, status bar, draft preview, command completions, and approval, question, quit, and paste prompts.
 // ABOUTME: Each widget is a pure rendering function that takes its own display parameters.
 
+pub mod align;
 pub mod approval;
 pub mod chat;
+pub mod completion;
+pub mod draft;
+pub mod header;
+pub mod paste;
 pub mod question;
+pub mod quit;
 pub mod status;
+pub mod theme;
@@ -0,0 +1,71 @@
+// ABOUTME: Large paste widget — shown when a paste is over [tui] max_paste_bytes.
+// ABOUTME: Offers attaching it truncated, inserting its first lines, or cancelling.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::tools::summary::format_size;
+
+/// The keys that pick each option directly: attach, first lines, cancel.
+pub const PASTE_KEYS: [char; 3] = ['a', 'f', 'c'];
+
+/// Render the large paste prompt: a header with the paste's size and the
+/// selectable options.
+pub fn paste_prompt_lines(
+    bytes: usize,
+    lines: usize,
+    insert_lines: usize,
+    selected: usize,
+) -> Vec<Line<'static>> {
+    let header = Line::from(Span::styled(
+        format!(
+            "\u{1f4cb} Large paste ({}, {} lines) \u{2014} how should it go in?",
+            format_size(bytes),
+            lines
+        ),
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    ));
+
+    let labels = [
+        "attach (truncated)".to_string(),
+        format!("first {} lines", insert_lines),
+        "cancel".to_string(),
+    ];
+    let mut option_spans = Vec::new();
+    for (i, (key, label)) in PASTE_KEYS.iter().zip(labels).enumerate() {
+        if i > 0 {
+            option_spans.push(Span::raw("  "));
+        }
+        let label = format!(" [{}] {} ", key, label);
+        if i == selected {
+            option_spans.push(Span::styled(
+                label,
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        } else {
+            option_spans.push(Span::styled(label, Style::default().fg(Color::DarkGray)));
+        }
+    }
+
+    vec![header, Line::from(option_spans)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paste_prompt_shows_size_and_options() {
+        let lines = paste_prompt_lines(200 * 1024, 4000, 100, 0);
+        assert!(lines[0].to_string().contains("200.0 KB, 4000 lines"));
+        let options = lines[1].to_string();
+        assert!(options.contains("[a] attach (truncated)"));
+        assert!(options.contains("[f] first 100 lines"));
+        assert!(options.contains("[c] cancel"));
+    }
+}
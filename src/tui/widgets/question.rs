@@ -1,11 +1,12 @@
 // ABOUTME: Question prompt widget — inline TUI prompt for LLM-initiated questions.
-// ABOUTME: Supports both multiple-choice (horizontal options) and free-text question modes.
+// ABOUTME: Supports multiple-choice (horizontal options), yes/no confirm, and free-text question modes.
 
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 
-/// Render a free-text question prompt as two Lines: header with question + usage hint.
-pub fn question_lines(question: &str) -> Vec<Line<'static>> {
+/// Render a free-text question prompt as two Lines: header with question +
+/// usage hint naming the keys that submit and insert a newline.
+pub fn question_lines(question: &str, submit_key: &str, newline_key: &str) -> Vec<Line<'static>> {
     let header = Line::from(vec![
         Span::styled(
             "❓ QUESTION: ",
@@ -17,7 +18,10 @@ pub fn question_lines(question: &str) -> Vec<Line<'static>> {
     ]);
 
     let hint = Line::from(Span::styled(
-        "(Type your answer and press Enter, or Esc to skip)",
+        format!(
+            "(Type your answer and press {}, {} for a new line, Esc to skip)",
+            submit_key, newline_key
+        ),
         Style::default().fg(Color::DarkGray),
     ));
 
@@ -66,19 +70,59 @@ pub fn multichoice_lines(question: &str, options: &[String], selected: usize) ->
     vec![header, options_line, hint]
 }
 
+/// Render a yes/no confirmation: header + "[y]es / [n]o" with the Enter
+/// default highlighted + hint.
+pub fn confirm_lines(question: &str, default_yes: bool) -> Vec<Line<'static>> {
+    let header = Line::from(vec![
+        Span::styled(
+            "❓ QUESTION: ",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(question.to_string(), Style::default().fg(Color::White)),
+    ]);
+
+    let style_for = |is_default: bool| {
+        if is_default {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default().fg(Color::White)
+        }
+    };
+    let choice_line = Line::from(vec![
+        Span::styled("[y]es", style_for(default_yes)),
+        Span::raw(" / "),
+        Span::styled("[n]o", style_for(!default_yes)),
+    ]);
+
+    let default_label = if default_yes { "yes" } else { "no" };
+    let hint = Line::from(Span::styled(
+        format!(
+            "(y or n to answer, Enter for {}, Esc for no)",
+            default_label
+        ),
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    vec![header, choice_line, hint]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn question_lines_has_two_lines() {
-        let lines = question_lines("What is your name?");
+        let lines = question_lines("What is your name?", "Enter", "Shift+Enter");
         assert_eq!(lines.len(), 2);
     }
 
     #[test]
     fn header_contains_question_prefix_and_text() {
-        let lines = question_lines("What color do you prefer?");
+        let lines = question_lines("What color do you prefer?", "Enter", "Shift+Enter");
         let header_text: String = lines[0]
             .spans
             .iter()
@@ -90,26 +134,27 @@ mod tests {
 
     #[test]
     fn header_uses_cyan_color() {
-        let lines = question_lines("test");
+        let lines = question_lines("test", "Enter", "Shift+Enter");
         let question_label = &lines[0].spans[0];
         assert_eq!(question_label.style.fg, Some(Color::Cyan));
     }
 
     #[test]
     fn hint_line_mentions_enter_and_esc() {
-        let lines = question_lines("test");
+        let lines = question_lines("test", "Enter", "Shift+Enter");
         let hint_text: String = lines[1]
             .spans
             .iter()
             .map(|s| s.content.to_string())
             .collect();
         assert!(hint_text.contains("Enter"));
+        assert!(hint_text.contains("Shift+Enter"));
         assert!(hint_text.contains("Esc"));
     }
 
     #[test]
     fn empty_question_still_renders() {
-        let lines = question_lines("");
+        let lines = question_lines("", "Enter", "Shift+Enter");
         assert_eq!(lines.len(), 2);
         let header_text: String = lines[0]
             .spans
@@ -167,6 +212,34 @@ mod tests {
         assert!(selected_span.style.add_modifier.contains(Modifier::REVERSED));
     }
 
+    // --- Confirm tests ---
+
+    #[test]
+    fn confirm_shows_yes_no_with_the_default_highlighted() {
+        let lines = confirm_lines("Delete the branch?", false);
+        assert_eq!(lines.len(), 3);
+        let choice_text: String = lines[1]
+            .spans
+            .iter()
+            .map(|s| s.content.to_string())
+            .collect();
+        assert_eq!(choice_text, "[y]es / [n]o");
+        let no = lines[1].spans.iter().find(|s| s.content == "[n]o").unwrap();
+        assert!(no.style.add_modifier.contains(Modifier::REVERSED));
+        let yes = lines[1]
+            .spans
+            .iter()
+            .find(|s| s.content == "[y]es")
+            .unwrap();
+        assert!(!yes.style.add_modifier.contains(Modifier::REVERSED));
+        let hint_text: String = lines[2]
+            .spans
+            .iter()
+            .map(|s| s.content.to_string())
+            .collect();
+        assert!(hint_text.contains("Enter for no"));
+    }
+
     #[test]
     fn multichoice_hint_mentions_navigation() {
         let options = vec!["x".to_string()];
@@ -0,0 +1,76 @@
+// ABOUTME: Quit confirmation widget — shown when quitting while the agent is still working.
+// ABOUTME: Offers quit now, quit after the current turn, or cancel, styled like the approval prompt.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+/// The quit options, each with the key that picks it directly.
+pub const QUIT_OPTIONS: &[(char, &str)] = &[
+    ('y', "quit"),
+    ('s', "quit after this turn"),
+    ('n', "cancel"),
+];
+
+/// Render the quit confirmation: a warning header and the selectable options.
+pub fn quit_confirm_lines(selected: usize) -> Vec<Line<'static>> {
+    let header = Line::from(Span::styled(
+        "\u{26a0}\u{fe0f} Agent is still working \u{2014} quit anyway?",
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    ));
+
+    let mut option_spans = Vec::new();
+    for (i, (key, label)) in QUIT_OPTIONS.iter().enumerate() {
+        if i > 0 {
+            option_spans.push(Span::raw("  "));
+        }
+        let label = format!(" [{}] {} ", key, label);
+        if i == selected {
+            option_spans.push(Span::styled(
+                label,
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        } else {
+            option_spans.push(Span::styled(label, Style::default().fg(Color::DarkGray)));
+        }
+    }
+
+    vec![header, Line::from(option_spans)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quit_confirm_shows_all_options() {
+        let lines = quit_confirm_lines(0);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].to_string().contains("quit anyway?"));
+        let options = lines[1].to_string();
+        assert!(options.contains("[y] quit"));
+        assert!(options.contains("[s] quit after this turn"));
+        assert!(options.contains("[n] cancel"));
+    }
+
+    #[test]
+    fn quit_confirm_highlights_selection() {
+        let lines = quit_confirm_lines(2);
+        let selected = lines[1]
+            .spans
+            .iter()
+            .find(|s| s.content.contains("cancel"))
+            .unwrap();
+        assert_eq!(selected.style.bg, Some(Color::Yellow));
+        let other = lines[1]
+            .spans
+            .iter()
+            .find(|s| s.content.contains("[y]"))
+            .unwrap();
+        assert_eq!(other.style.bg, None);
+    }
+}
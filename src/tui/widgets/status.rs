@@ -1,109 +1,245 @@
 // ABOUTME: Status bar widget — renders directory, context usage bar, and elapsed session time.
-// ABOUTME: Displayed at the bottom of the TUI as a single-line summary.
+// ABOUTME: Displayed at the bottom of the TUI as a single-line summary, measured in display cells.
 
 use std::time::Instant;
 
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 
+use crate::agent::cost::format_cost;
+use crate::tui::widgets::align::right_align;
+
 /// Parameters for rendering the status bar.
 pub struct StatusBarParams<'a> {
     pub workspace_dir: &'a str,
     pub context_used: u64,
     pub context_window: u64,
+    /// Estimated turns until auto-compaction, if there's enough history to tell.
+    pub compaction_forecast: Option<u64>,
+    /// Estimated session cost in dollars; `None` for models without known pricing.
+    pub cost: Option<f64>,
     pub session_start: Instant,
     pub streaming: bool,
+    /// Available width in columns; lower-priority parts are dropped to fit.
+    pub width: u16,
+    /// Plain ASCII instead of emoji and box-drawing characters.
+    pub ascii: bool,
+    /// The vim keymap's mode, or the `:` command being typed.
+    pub mode: Option<&'a str>,
 }
 
-/// Render the status bar: directory │ context bar percentage │ elapsed time.
-pub fn status_line(params: &StatusBarParams) -> Line<'static> {
-    let dim = Style::default().fg(Color::DarkGray);
+/// Number of cells in the context gauge.
+const GAUGE_CELLS: usize = 10;
+
+/// The characters the bar is drawn with.
+struct Glyphs {
+    folder: &'static str,
+    timer: &'static str,
+    separator: &'static str,
+    filled: char,
+    empty: char,
+}
+
+/// Emoji and box drawing. The timer carries VS16 so it's measured, and
+/// drawn by most terminals, two cells wide.
+const FANCY: Glyphs = Glyphs {
+    folder: "\u{1F4C1} ",
+    timer: "\u{23F1}\u{FE0F} ",
+    separator: "\u{2502} ",
+    filled: '\u{25B0}',
+    empty: '\u{25B1}',
+};
 
-    // Directory name (last component of path).
-    let dir_name = params
-        .workspace_dir
-        .rsplit('/')
-        .next()
-        .unwrap_or(params.workspace_dir);
+/// For terminals and fonts that get emoji widths wrong.
+const ASCII: Glyphs = Glyphs {
+    folder: "",
+    timer: "",
+    separator: "| ",
+    filled: '#',
+    empty: '-',
+};
+
+/// Which optional parts of the bar to draw.
+#[derive(Clone, Copy)]
+struct Parts {
+    gauge: bool,
+    forecast: bool,
+    elapsed: bool,
+    dir: bool,
+}
 
+/// Render the status bar: directory │ context gauge, percentage and
+/// compaction forecast │ session cost, with the elapsed time against the
+/// right edge. Measured in display cells; when the line doesn't fit, the
+/// elapsed time goes first, then the path, the forecast, and the gauge and
+/// cost, leaving the context percentage.
+pub fn status_line(params: &StatusBarParams) -> Line<'static> {
     let context_pct = if params.context_window > 0 {
         ((params.context_used as f64 / params.context_window as f64) * 100.0).min(100.0)
     } else {
         0.0
     };
+    let glyphs = if params.ascii { &ASCII } else { &FANCY };
 
-    let bar = render_context_bar(context_pct, 12);
-
-    let bar_color = if context_pct >= 90.0 {
-        Color::Red
-    } else if context_pct >= 70.0 {
-        Color::Yellow
-    } else {
-        Color::Green
-    };
+    let candidates = [
+        (true, true, true, true),
+        (true, true, false, true),
+        (true, true, false, false),
+        (true, false, false, false),
+        (false, false, false, false),
+    ];
+    let mut last = None;
+    for (gauge, forecast, elapsed, dir) in candidates {
+        let parts = Parts {
+            gauge,
+            forecast,
+            elapsed,
+            dir,
+        };
+        let (left, right) = build_segments(params, glyphs, context_pct, parts);
+        if let Some(line) = right_align(left.clone(), right.clone(), params.width as usize) {
+            return line;
+        }
+        last = Some(left);
+    }
+    // Not even the percentage fits; the terminal clips what's left.
+    Line::from(last.unwrap_or_default())
+}
 
-    let elapsed = format_elapsed(params.session_start);
+/// The left-hand and right-aligned spans of the bar.
+fn build_segments(
+    params: &StatusBarParams,
+    glyphs: &Glyphs,
+    context_pct: f64,
+    parts: Parts,
+) -> (Vec<Span<'static>>, Vec<Span<'static>>) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut spans = Vec::new();
 
-    let mut spans = vec![
-        Span::styled(" \u{1F4C1} ", dim),
-        Span::styled(
+    if let Some(mode) = params.mode {
+        spans.push(Span::styled(
+            format!(" {} ", mode),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if parts.dir {
+        // Directory name (last component of path).
+        let dir_name = params
+            .workspace_dir
+            .rsplit('/')
+            .next()
+            .unwrap_or(params.workspace_dir);
+        spans.push(Span::styled(format!(" {}", glyphs.folder), dim));
+        spans.push(Span::styled(
             format!("{} ", dir_name),
             Style::default().fg(Color::Cyan),
-        ),
-        Span::styled("\u{2502} ", dim),
-        Span::styled(bar, Style::default().fg(bar_color)),
-        Span::styled(
-            format!(" {:.0}% ", context_pct),
-            Style::default().fg(Color::White),
-        ),
-        Span::styled("\u{2502} ", dim),
-        Span::styled(
-            format!("\u{23F1} {} ", elapsed),
+        ));
+        spans.push(Span::styled(glyphs.separator, dim));
+    } else {
+        spans.push(Span::raw(" "));
+    }
+
+    let gauge_color = gauge_color(context_pct);
+    if parts.gauge {
+        spans.push(Span::styled(
+            render_context_bar(context_pct, GAUGE_CELLS, glyphs),
+            Style::default().fg(gauge_color),
+        ));
+        spans.push(Span::raw(" "));
+    }
+    spans.push(Span::styled(
+        format!("{:.0}% ", context_pct),
+        Style::default().fg(gauge_color),
+    ));
+    if parts.forecast
+        && let Some(turns) = params.compaction_forecast
+    {
+        spans.push(Span::styled(format!("{} ", format_forecast(turns)), dim));
+    }
+
+    if parts.gauge
+        && let Some(cost) = params.cost
+    {
+        spans.push(Span::styled(glyphs.separator, dim));
+        spans.push(Span::styled(
+            format!("{} ", format_cost(cost)),
             Style::default().fg(Color::White),
-        ),
-    ];
+        ));
+    }
 
     if params.streaming {
-        spans.push(Span::styled("\u{2502} ", dim));
+        spans.push(Span::styled(glyphs.separator, dim));
         spans.push(Span::styled(
             "streaming... ",
             Style::default().fg(Color::Yellow),
         ));
     }
 
-    Line::from(spans)
-}
+    let mut right = Vec::new();
+    if parts.elapsed {
+        right.push(Span::styled(glyphs.separator, dim));
+        right.push(Span::styled(
+            format!("{}{} ", glyphs.timer, format_elapsed(params.session_start)),
+            Style::default().fg(Color::White),
+        ));
+    }
 
-/// Render a context usage bar using block characters.
-/// `filled_chars` total width, proportional fill with block elements.
-fn render_context_bar(pct: f64, width: usize) -> String {
-    let filled = (pct / 100.0) * width as f64;
-    let full_blocks = filled.floor() as usize;
-    let remainder = filled - filled.floor();
+    (spans, right)
+}
 
-    let mut bar = String::with_capacity(width * 4);
-    for _ in 0..full_blocks.min(width) {
-        bar.push('\u{2588}'); // Full block
+/// Gauge color: green, yellow above 75%, red above 90%.
+fn gauge_color(pct: f64) -> Color {
+    if pct > 90.0 {
+        Color::Red
+    } else if pct > 75.0 {
+        Color::Yellow
+    } else {
+        Color::Green
     }
+}
 
-    if full_blocks < width {
-        // Use braille-style partial fill for the fractional block.
-        let partial = if remainder >= 0.5 {
-            '\u{2593}' // Dark shade
-        } else if remainder >= 0.25 {
-            '\u{2592}' // Medium shade
+/// Render a context usage gauge like "▰▰▰▱▱" with `width` cells, rounding
+/// to the nearest cell.
+fn render_context_bar(pct: f64, width: usize, glyphs: &Glyphs) -> String {
+    let filled = ((pct / 100.0) * width as f64).round() as usize;
+    let filled = filled.min(width);
+    let mut bar = String::with_capacity(width * 3);
+    for i in 0..width {
+        bar.push(if i < filled {
+            glyphs.filled
         } else {
-            '\u{2591}' // Light shade
-        };
-        bar.push(partial);
+            glyphs.empty
+        });
+    }
+    bar
+}
 
-        // Fill remaining with light dots.
-        for _ in (full_blocks + 1)..width {
-            bar.push('\u{2591}');
-        }
+/// Describe a compaction forecast in turns.
+fn format_forecast(turns: u64) -> String {
+    match turns {
+        0 | 1 => "compaction next turn".to_string(),
+        n => format!("compaction in ~{} turns", n),
     }
+}
 
-    bar
+/// Estimate how many more turns fit before context usage reaches `limit`,
+/// from the average growth between consecutive per-turn samples.
+///
+/// Returns None with fewer than two samples or when usage isn't growing.
+pub fn forecast_turns_until(samples: &[u64], limit: u64) -> Option<u64> {
+    let (&first, &last) = (samples.first()?, samples.last()?);
+    if samples.len() < 2 || last <= first {
+        return None;
+    }
+    if last >= limit {
+        return Some(0);
+    }
+    let steps = (samples.len() - 1) as u64;
+    let avg_growth = ((last - first) / steps).max(1);
+    Some((limit - last).div_ceil(avg_growth))
 }
 
 /// Format elapsed time as human-readable "Xh Ym" or "Xm Ys".
@@ -134,6 +270,7 @@ pub fn format_tokens(tokens: u64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tui::widgets::align::display_width;
 
     #[test]
     fn format_tokens_small() {
@@ -163,8 +300,13 @@ mod tests {
             workspace_dir: "/home/user/my-project",
             context_used: 120_000,
             context_window: 200_000,
+            compaction_forecast: None,
+            cost: None,
             session_start: Instant::now(),
             streaming: true,
+            width: 120,
+            ascii: false,
+            mode: None,
         };
         let line = status_line(&params);
         let text: String = line.spans.iter().map(|s| s.content.to_string()).collect();
@@ -173,14 +315,37 @@ mod tests {
         assert!(text.contains("streaming..."));
     }
 
+    #[test]
+    fn status_line_leads_with_the_vim_mode() {
+        let params = StatusBarParams {
+            workspace_dir: "/home/user/my-project",
+            context_used: 0,
+            context_window: 200_000,
+            compaction_forecast: None,
+            cost: None,
+            session_start: Instant::now(),
+            streaming: false,
+            width: 30,
+            ascii: true,
+            mode: Some("NORMAL"),
+        };
+        let line = status_line(&params);
+        assert_eq!(line.spans[0].content, " NORMAL ");
+    }
+
     #[test]
     fn status_line_no_streaming() {
         let params = StatusBarParams {
             workspace_dir: "/tmp/test-dir",
             context_used: 0,
             context_window: 128_000,
+            compaction_forecast: None,
+            cost: None,
             session_start: Instant::now(),
             streaming: false,
+            width: 120,
+            ascii: false,
+            mode: None,
         };
         let line = status_line(&params);
         let text: String = line.spans.iter().map(|s| s.content.to_string()).collect();
@@ -191,24 +356,68 @@ mod tests {
 
     #[test]
     fn context_bar_empty() {
-        let bar = render_context_bar(0.0, 10);
-        assert_eq!(bar.chars().count(), 10);
-        assert!(!bar.contains('\u{2588}')); // No full blocks
+        let bar = render_context_bar(0.0, 10, &FANCY);
+        assert_eq!(bar, "\u{25B1}".repeat(10));
     }
 
     #[test]
     fn context_bar_full() {
-        let bar = render_context_bar(100.0, 10);
-        assert_eq!(bar.chars().count(), 10);
-        assert!(bar.chars().all(|c| c == '\u{2588}'));
+        let bar = render_context_bar(100.0, 10, &FANCY);
+        assert_eq!(bar, "\u{25B0}".repeat(10));
     }
 
     #[test]
-    fn context_bar_half() {
-        let bar = render_context_bar(50.0, 10);
-        assert_eq!(bar.chars().count(), 10);
-        let full_count = bar.chars().filter(|&c| c == '\u{2588}').count();
-        assert_eq!(full_count, 5);
+    fn context_bar_partial() {
+        assert_eq!(
+            render_context_bar(50.0, 10, &FANCY)
+                .chars()
+                .filter(|&c| c == '\u{25B0}')
+                .count(),
+            5
+        );
+        assert_eq!(
+            render_context_bar(62.0, 5, &FANCY),
+            "\u{25B0}\u{25B0}\u{25B0}\u{25B1}\u{25B1}"
+        );
+    }
+
+    #[test]
+    fn gauge_color_thresholds() {
+        assert_eq!(gauge_color(75.0), Color::Green);
+        assert_eq!(gauge_color(76.0), Color::Yellow);
+        assert_eq!(gauge_color(90.0), Color::Yellow);
+        assert_eq!(gauge_color(91.0), Color::Red);
+    }
+
+    #[test]
+    fn forecast_needs_two_growing_samples() {
+        assert_eq!(forecast_turns_until(&[], 1000), None);
+        assert_eq!(forecast_turns_until(&[500], 1000), None);
+        assert_eq!(forecast_turns_until(&[500, 500], 1000), None);
+        assert_eq!(forecast_turns_until(&[600, 400], 1000), None);
+    }
+
+    #[test]
+    fn forecast_uses_average_growth() {
+        // Grows 100, 200, 300 per turn: average 200, 1000 tokens left → 5 turns.
+        assert_eq!(
+            forecast_turns_until(&[1000, 1100, 1300, 1600], 2600),
+            Some(5)
+        );
+        // Partial turns round up.
+        assert_eq!(forecast_turns_until(&[0, 300], 1000), Some(3));
+    }
+
+    #[test]
+    fn forecast_at_or_over_limit_is_zero() {
+        assert_eq!(forecast_turns_until(&[800, 1200], 1000), Some(0));
+    }
+
+    #[test]
+    fn forecast_text() {
+        assert_eq!(format_forecast(0), "compaction next turn");
+        assert_eq!(format_forecast(1), "compaction next turn");
+        assert_eq!(format_forecast(3), "compaction in ~3 turns");
     }
 
     #[test]
@@ -219,22 +428,157 @@ mod tests {
             workspace_dir: "/tmp/test",
             context_used: 0,
             context_window: 100_000,
+            compaction_forecast: None,
+            cost: None,
             session_start: Instant::now(),
             streaming: false,
+            width: 120,
+            ascii: false,
+            mode: None,
         };
         let line = status_line(&params);
         let text: String = line.spans.iter().map(|s| s.content.to_string()).collect();
         assert!(text.contains("0m"));
     }
 
+    /// Render the status line into a one-row TestBackend of `width` columns.
+    fn render_row(params: &StatusBarParams) -> String {
+        use ratatui::Terminal;
+        use ratatui::backend::TestBackend;
+        use ratatui::widgets::Paragraph;
+
+        let mut terminal = Terminal::new(TestBackend::new(params.width, 1)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(Paragraph::new(status_line(params)), frame.area()))
+            .unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    fn gauge_params(width: u16) -> StatusBarParams<'static> {
+        StatusBarParams {
+            workspace_dir: "/home/user/my-project",
+            context_used: 124_000,
+            context_window: 200_000,
+            compaction_forecast: Some(3),
+            cost: Some(0.42),
+            session_start: Instant::now(),
+            streaming: false,
+            width,
+            ascii: false,
+            mode: None,
+        }
+    }
+
+    #[test]
+    fn renders_gauge_and_forecast_at_80_cols() {
+        let row = render_row(&gauge_params(80));
+        assert!(row.contains("my-project"));
+        assert!(row.contains("\u{25B0}\u{25B0}\u{25B0}\u{25B0}\u{25B0}\u{25B0}\u{25B1}"));
+        assert!(row.contains("62%"));
+        assert!(row.contains("compaction in ~3 turns"));
+        assert!(row.contains("$0.42"));
+    }
+
+    #[test]
+    fn narrow_terminal_drops_path_before_gauge() {
+        let params = gauge_params(40);
+        let row = render_row(&params);
+        assert!(!row.contains("my-project"));
+        assert!(!row.contains("compaction"));
+        assert!(row.contains('\u{25B0}'));
+        assert!(row.contains("62%"));
+        assert!(row.contains("$0.42"));
+        assert!(status_line(&params).width() <= 40);
+    }
+
+    /// The bar's display width and the text of its rendered row.
+    fn fit(params: &StatusBarParams) -> (usize, String) {
+        let line = status_line(params);
+        (display_width(&line.spans), render_row(params))
+    }
+
+    #[test]
+    fn the_bar_never_overflows_at_common_widths() {
+        for width in [80, 60, 38] {
+            for ascii in [false, true] {
+                let params = StatusBarParams {
+                    ascii,
+                    mode: None,
+                    streaming: true,
+                    ..gauge_params(width)
+                };
+                let (used, row) = fit(&params);
+                assert!(used <= width as usize, "{} cells at width {}", used, width);
+                assert!(row.contains("62%"), "width {}: {:?}", width, row);
+            }
+        }
+    }
+
+    #[test]
+    fn elapsed_time_is_right_aligned() {
+        let (used, row) = fit(&gauge_params(80));
+        assert_eq!(used, 80);
+        assert!(row.trim_end().ends_with("0m 00s"), "{:?}", row);
+    }
+
+    #[test]
+    fn segments_drop_in_priority_order() {
+        let row = |width| fit(&gauge_params(width)).1;
+
+        let at_80 = row(80);
+        assert!(at_80.contains("0m 00s") && at_80.contains("my-project"));
+        assert!(at_80.contains("compaction") && at_80.contains('\u{25B0}'));
+
+        // The elapsed time goes first, then the path.
+        let at_70 = row(70);
+        assert!(!at_70.contains("0m 00s"));
+        assert!(at_70.contains("my-project") && at_70.contains("compaction"));
+
+        let at_50 = row(50);
+        assert!(!at_50.contains("my-project"));
+        assert!(at_50.contains("compaction") && at_50.contains('\u{25B0}'));
+
+        // Then the context details, leaving the percentage.
+        let at_40 = row(40);
+        assert!(!at_40.contains("compaction"));
+        assert!(at_40.contains('\u{25B0}') && at_40.contains("$0.42"));
+
+        let at_12 = row(12);
+        assert!(!at_12.contains('\u{25B0}') && !at_12.contains('$'));
+        assert!(at_12.contains("62%"));
+    }
+
+    #[test]
+    fn ascii_mode_uses_no_emoji_or_box_drawing() {
+        let params = StatusBarParams {
+            ascii: true,
+            mode: None,
+            ..gauge_params(80)
+        };
+        let (_, row) = fit(&params);
+        assert!(row.is_ascii(), "{:?}", row);
+        assert!(row.contains("######----") && row.contains("| $0.42"));
+    }
+
     #[test]
     fn context_percentage_capped_at_100() {
         let params = StatusBarParams {
             workspace_dir: "/tmp",
             context_used: 300_000,
             context_window: 200_000,
+            compaction_forecast: None,
+            cost: None,
             session_start: Instant::now(),
             streaming: false,
+            width: 120,
+            ascii: false,
+            mode: None,
         };
         let line = status_line(&params);
         let text: String = line.spans.iter().map(|s| s.content.to_string()).collect();
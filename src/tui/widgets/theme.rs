@@ -0,0 +1,74 @@
+// ABOUTME: Chat transcript colors — the standard palette and a high-contrast one for `[tui] accessibility`.
+// ABOUTME: High contrast swaps dim grays for bright colors and bold, so nothing depends on a subtle shade.
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// Styles for each part of the chat transcript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub user: Style,
+    pub assistant: Style,
+    pub tool: Style,
+    pub result: Style,
+    pub error: Style,
+    /// Calls the user denied.
+    pub denied: Style,
+    /// Calls whose approval nobody answered in time.
+    pub timed_out: Style,
+    pub system: Style,
+    /// Secondary details: approval reasons, the thinking placeholder.
+    pub muted: Style,
+    /// Tree connectors between a turn's tool calls and results.
+    pub connector: Style,
+}
+
+impl Theme {
+    pub fn standard() -> Self {
+        let gray = Style::default().fg(Color::DarkGray);
+        Self {
+            user: Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+            assistant: Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+            tool: Style::default().fg(Color::Yellow),
+            result: gray,
+            error: Style::default().fg(Color::Red),
+            denied: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            timed_out: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            system: gray.add_modifier(Modifier::ITALIC),
+            muted: gray.add_modifier(Modifier::DIM),
+            connector: gray,
+        }
+    }
+
+    /// Bright colors only, no dimming; every distinction the colors draw is
+    /// also spelled out in the text.
+    pub fn high_contrast() -> Self {
+        let bold = |color| Style::default().fg(color).add_modifier(Modifier::BOLD);
+        Self {
+            user: bold(Color::LightGreen),
+            assistant: bold(Color::LightCyan),
+            tool: bold(Color::LightYellow),
+            result: Style::default().fg(Color::White),
+            error: bold(Color::LightRed),
+            denied: bold(Color::LightRed),
+            timed_out: bold(Color::LightMagenta),
+            system: Style::default().fg(Color::White),
+            muted: Style::default().fg(Color::White),
+            connector: Style::default().fg(Color::White),
+        }
+    }
+
+    /// The high-contrast theme when `accessible`, else the standard one.
+    pub fn new(accessible: bool) -> Self {
+        if accessible {
+            Self::high_contrast()
+        } else {
+            Self::standard()
+        }
+    }
+}
@@ -0,0 +1,640 @@
+// ABOUTME: End-to-end tests of the agent loop: user message → LLM → approval → tool → history → session log.
+// ABOUTME: Each scenario runs the real loop on a scripted client and checks golden event sequences and history.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use mux::prelude::*;
+use soloclaw::agent::compaction::SUMMARY_PREFIX;
+use soloclaw::agent::r#loop::{DECLINED_PENDING_RESULT, INTERRUPTED_RESULT, PENDING_QUESTION_ID};
+use soloclaw::config::{CompactionConfig, PendingToolCalls};
+use soloclaw::events::EventRecord;
+use soloclaw::testing::{
+    Autopilot, FakeMcpClient, FakeTool, LoopHarness, MockLlmClient, ScriptedResponse, event_names,
+};
+use soloclaw::tools::mcp::McpServer;
+use soloclaw::tui::state::AgentEvent;
+
+fn no_compaction() -> CompactionConfig {
+    CompactionConfig {
+        enabled: false,
+        ..CompactionConfig::default()
+    }
+}
+
+fn text_of(message: &Message) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn tool_result_of(message: &Message) -> (String, bool) {
+    match &message.content[0] {
+        ContentBlock::ToolResult {
+            content, is_error, ..
+        } => (content.clone(), *is_error),
+        other => panic!("expected a tool result, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn plain_answer() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("Hello ").text("there."),
+    ]));
+    let mut harness = LoopHarness::start(client.clone(), vec![], dir.path(), no_compaction())
+        .await
+        .unwrap();
+
+    let events = harness.turn("hi", &mut Autopilot::approving()).await;
+    assert_eq!(
+        event_names(&events),
+        ["text_delta", "text_delta", "text_done", "done"]
+    );
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(text_of(&messages[0]), "hi");
+    assert!(matches!(messages[1].role, Role::Assistant));
+    assert_eq!(text_of(&messages[1]), "Hello there.");
+    assert_eq!(client.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn approved_tool_call_runs_and_its_result_goes_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = FakeTool::new("notes", "buy milk");
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("Checking.").tool_use(
+            "c1",
+            "notes",
+            serde_json::json!({"topic": "shopping"}),
+        ),
+        ScriptedResponse::new().text("You need milk."),
+    ]));
+    let mut harness = LoopHarness::start(
+        client.clone(),
+        vec![notes.clone()],
+        dir.path(),
+        no_compaction(),
+    )
+    .await
+    .unwrap();
+
+    let events = harness
+        .turn("what do I need?", &mut Autopilot::approving())
+        .await;
+    assert_eq!(
+        event_names(&events),
+        [
+            "text_delta",
+            "text_done",
+            "tool_call_started",
+            "tool_call_needs_approval",
+            "tool_call_approved",
+            "tool_result",
+            "text_delta",
+            "text_done",
+            "done",
+        ]
+    );
+    assert!(matches!(
+        &events[5],
+        EventRecord::ToolResult { content, is_error: false, .. } if content == "buy milk"
+    ));
+    assert_eq!(
+        notes.calls(),
+        vec![serde_json::json!({"topic": "shopping"})]
+    );
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(
+        tool_result_of(&messages[2]),
+        ("buy milk".to_string(), false)
+    );
+    assert_eq!(text_of(&messages[3]), "You need milk.");
+    // The second request carried the tool result back to the model.
+    assert_eq!(client.requests()[1].messages.len(), 3);
+}
+
+#[tokio::test]
+async fn denied_tool_call_never_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = FakeTool::new("notes", "buy milk");
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().tool_use("c1", "notes", serde_json::json!({})),
+        ScriptedResponse::new().text("Okay, I won't."),
+    ]));
+    let mut harness = LoopHarness::start(client, vec![notes.clone()], dir.path(), no_compaction())
+        .await
+        .unwrap();
+
+    let events = harness
+        .turn("read my notes", &mut Autopilot::denying())
+        .await;
+    assert_eq!(
+        event_names(&events),
+        [
+            "tool_call_started",
+            "tool_call_needs_approval",
+            "tool_call_denied",
+            "text_delta",
+            "text_done",
+            "done",
+        ]
+    );
+    assert!(notes.calls().is_empty());
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(
+        tool_result_of(&messages[2]),
+        ("Denied by user".to_string(), true)
+    );
+}
+
+#[tokio::test]
+async fn open_in_editor_needs_approval_before_the_editor_opens() {
+    let dir = tempfile::tempdir().unwrap();
+    let editor = FakeTool::new("open_in_editor", "never run");
+    let script = || {
+        vec![
+            ScriptedResponse::new().tool_use(
+                "c1",
+                "open_in_editor",
+                serde_json::json!({"path": "notes.md"}),
+            ),
+            ScriptedResponse::new().text("Okay."),
+        ]
+    };
+
+    let client = Arc::new(MockLlmClient::new(script()));
+    let mut harness = LoopHarness::start(client, vec![editor.clone()], dir.path(), no_compaction())
+        .await
+        .unwrap();
+    let events = harness
+        .turn("let me edit the notes", &mut Autopilot::denying())
+        .await;
+    assert_eq!(
+        event_names(&events)[..3],
+        [
+            "tool_call_started",
+            "tool_call_needs_approval",
+            "tool_call_denied"
+        ]
+    );
+    harness.finish().await.unwrap();
+
+    // Approved, the TUI gets the file; the tool itself never runs.
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(script()));
+    let mut harness = LoopHarness::start(client, vec![editor.clone()], dir.path(), no_compaction())
+        .await
+        .unwrap();
+    let events = harness
+        .turn("let me edit the notes", &mut Autopilot::approving())
+        .await;
+    assert_eq!(
+        event_names(&events)[..5],
+        [
+            "tool_call_started",
+            "tool_call_needs_approval",
+            "tool_call_approved",
+            "open_in_editor",
+            "tool_result",
+        ]
+    );
+    assert!(editor.calls().is_empty());
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(
+        tool_result_of(&messages[2]),
+        (
+            "Editing notes.md failed: no editor in tests".to_string(),
+            true
+        )
+    );
+}
+
+#[tokio::test]
+async fn ask_user_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().tool_use(
+            "q1",
+            "ask_user",
+            serde_json::json!({"question": "Which color?", "options": ["red", "blue"]}),
+        ),
+        ScriptedResponse::new().text("Blue it is."),
+    ]));
+    let mut harness = LoopHarness::start(client, vec![], dir.path(), no_compaction())
+        .await
+        .unwrap();
+
+    let mut autopilot = Autopilot::approving().answering("blue");
+    let events = harness.turn("paint the fence", &mut autopilot).await;
+    assert_eq!(
+        event_names(&events),
+        ["ask_user", "text_delta", "text_done", "done"]
+    );
+    assert!(matches!(
+        &events[0],
+        EventRecord::AskUser { question, tool_call_id, options, .. }
+            if question == "Which color?" && tool_call_id == "q1" && options == &["red", "blue"]
+    ));
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(tool_result_of(&messages[2]), ("blue".to_string(), false));
+    assert!(matches!(
+        &messages[2].content[0],
+        ContentBlock::ToolResult { tool_use_id, .. } if tool_use_id == "q1"
+    ));
+    assert_eq!(text_of(&messages[3]), "Blue it is.");
+}
+
+#[tokio::test]
+async fn compaction_triggers_after_the_turn() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("Noted, I will remember the launch date."),
+        // The summarization request.
+        ScriptedResponse::new().text("The launch is on Friday."),
+    ]));
+    let compaction = CompactionConfig {
+        enabled: true,
+        threshold_token_limit: Some(5),
+        ..CompactionConfig::default()
+    };
+    let mut harness = LoopHarness::start(client.clone(), vec![], dir.path(), compaction)
+        .await
+        .unwrap();
+
+    let events = harness
+        .turn(
+            "remember that we launch on Friday",
+            &mut Autopilot::approving(),
+        )
+        .await;
+    assert_eq!(
+        event_names(&events),
+        [
+            "text_delta",
+            "text_done",
+            "compaction_started",
+            "compaction_done",
+            "done"
+        ]
+    );
+    assert!(matches!(
+        events[3],
+        EventRecord::CompactionDone {
+            old_count: 2,
+            new_count: 2
+        }
+    ));
+
+    // The log carries the compacted history forward.
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(text_of(&messages[0]), "remember that we launch on Friday");
+    let summary = text_of(&messages[1]);
+    assert!(summary.starts_with(SUMMARY_PREFIX));
+    assert!(summary.ends_with("The launch is on Friday."));
+    assert_eq!(client.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn stream_error_mid_turn_reports_and_keeps_only_the_user_message() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new()
+            .text("Partial ans")
+            .stream_error("connection reset"),
+    ]));
+    let mut harness = LoopHarness::start(client, vec![], dir.path(), no_compaction())
+        .await
+        .unwrap();
+
+    let events = harness.turn("hi", &mut Autopilot::approving()).await;
+    // The stream reports the failure as it happens, then the loop reports the
+    // failed turn.
+    assert_eq!(
+        event_names(&events),
+        ["text_delta", "error", "error", "done"]
+    );
+    assert!(matches!(
+        &events[1],
+        EventRecord::Error { message } if message.contains("connection reset")
+    ));
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(text_of(&messages[0]), "hi");
+}
+
+#[tokio::test]
+async fn a_blank_reply_is_explained_instead_of_shown_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![ScriptedResponse::new()]));
+    let mut harness = LoopHarness::start(client, vec![], dir.path(), no_compaction())
+        .await
+        .unwrap();
+
+    let events = harness.turn("hi", &mut Autopilot::approving()).await;
+    assert_eq!(event_names(&events), ["notice", "done"]);
+    assert!(matches!(
+        &events[0],
+        EventRecord::Notice { message } if message.contains("without replying")
+    ));
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 1);
+}
+
+#[tokio::test]
+async fn a_content_filtered_reply_is_explained() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new()
+            .text("Here is how")
+            .stop(StopReason::ContentFilter),
+    ]));
+    let mut harness = LoopHarness::start(client, vec![], dir.path(), no_compaction())
+        .await
+        .unwrap();
+
+    let events = harness.turn("hi", &mut Autopilot::approving()).await;
+    assert!(events.iter().any(|event| matches!(
+        event,
+        EventRecord::Notice { message } if message.contains("content filter")
+    )));
+    assert_eq!(
+        event_names(&events).last().map(String::as_str),
+        Some("done")
+    );
+}
+
+#[tokio::test]
+async fn oversized_resume_is_compacted_before_the_first_message() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("We were refactoring the parser."),
+    ]));
+    // Three 40-token turns against a 50-token budget: only the last fits.
+    let history: Vec<Message> = (0..3)
+        .flat_map(|i| {
+            [
+                Message::user(format!("{}{}", i, "u".repeat(79))),
+                Message::assistant("a".repeat(80)),
+            ]
+        })
+        .collect();
+    let compaction = CompactionConfig {
+        threshold_token_limit: Some(50),
+        ..CompactionConfig::default()
+    };
+    let mut harness = LoopHarness::resume(client.clone(), vec![], dir.path(), compaction, history)
+        .await
+        .unwrap();
+
+    let events = harness.startup(&mut Autopilot::approving()).await;
+    assert_eq!(
+        event_names(&events),
+        ["compaction_started", "compaction_done", "resume_compacted"]
+    );
+    assert!(matches!(
+        events[1],
+        EventRecord::CompactionDone { old_count: 6, .. }
+    ));
+
+    // The summarization request itself had to fit, so it carried only the
+    // last turn and the summarization prompt.
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].messages.len(), 3);
+    assert!(text_of(&requests[0].messages[0]).starts_with('2'));
+
+    let messages = harness.finish().await.unwrap();
+    let summary = text_of(messages.last().unwrap());
+    assert!(summary.starts_with(SUMMARY_PREFIX));
+    assert!(summary.ends_with("We were refactoring the parser."));
+}
+
+#[tokio::test]
+async fn oversized_resume_is_left_alone_with_compaction_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("Hi."),
+    ]));
+    let history = vec![
+        Message::user("u".repeat(400)),
+        Message::assistant("a".repeat(400)),
+    ];
+    let compaction = CompactionConfig {
+        threshold_token_limit: Some(50),
+        ..no_compaction()
+    };
+    let mut harness = LoopHarness::resume(client.clone(), vec![], dir.path(), compaction, history)
+        .await
+        .unwrap();
+
+    let events = harness.turn("hi", &mut Autopilot::approving()).await;
+    assert!(
+        !event_names(&events)
+            .iter()
+            .any(|name| name.starts_with("compaction"))
+    );
+    let requests = client.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].messages.len(), 3);
+    harness.finish().await.unwrap();
+}
+
+/// A resumed history whose last reply asked for a tool and never got its result.
+fn interrupted_history() -> Vec<Message> {
+    vec![
+        Message::user("what do I need?"),
+        Message {
+            role: Role::Assistant,
+            content: vec![
+                ContentBlock::text("Checking."),
+                ContentBlock::ToolUse {
+                    id: "c1".to_string(),
+                    name: "notes".to_string(),
+                    input: serde_json::json!({"topic": "shopping"}),
+                },
+            ],
+        },
+    ]
+}
+
+#[tokio::test]
+async fn pending_tool_call_runs_on_resume_when_the_user_says_yes() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = FakeTool::new("notes", "buy milk");
+    let client = Arc::new(MockLlmClient::new(vec![]));
+    let mut harness = LoopHarness::resume_settling(
+        client.clone(),
+        vec![notes.clone()],
+        dir.path(),
+        no_compaction(),
+        interrupted_history(),
+        PendingToolCalls::Ask,
+    )
+    .await
+    .unwrap();
+
+    let events = harness
+        .startup(&mut Autopilot::approving().answering("yes"))
+        .await;
+    assert_eq!(
+        event_names(&events),
+        [
+            "ask_user",
+            "tool_call_started",
+            "tool_call_needs_approval",
+            "tool_call_approved",
+            "tool_result",
+            "done",
+        ]
+    );
+    assert!(matches!(
+        &events[0],
+        EventRecord::AskUser { question, tool_call_id, .. }
+            if question.contains("notes(") && tool_call_id == PENDING_QUESTION_ID
+    ));
+    assert_eq!(
+        notes.calls(),
+        vec![serde_json::json!({"topic": "shopping"})]
+    );
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        tool_result_of(&messages[0]),
+        ("buy milk".to_string(), false)
+    );
+    // Nothing goes to the model until the user writes again.
+    assert!(client.requests().is_empty());
+}
+
+#[tokio::test]
+async fn pending_tool_call_is_closed_when_declined() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = FakeTool::new("notes", "buy milk");
+    let client = Arc::new(MockLlmClient::new(vec![]));
+    let mut harness = LoopHarness::resume_settling(
+        client.clone(),
+        vec![notes.clone()],
+        dir.path(),
+        no_compaction(),
+        interrupted_history(),
+        PendingToolCalls::Ask,
+    )
+    .await
+    .unwrap();
+
+    let events = harness
+        .startup(&mut Autopilot::approving().answering("no"))
+        .await;
+    assert_eq!(event_names(&events), ["ask_user", "notice", "done"]);
+    assert!(notes.calls().is_empty());
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(
+        tool_result_of(&messages[0]),
+        (DECLINED_PENDING_RESULT.to_string(), true)
+    );
+}
+
+#[tokio::test]
+async fn pending_tool_call_fails_without_asking_in_fail_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = FakeTool::new("notes", "buy milk");
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("Let me check again."),
+    ]));
+    let mut harness = LoopHarness::resume_settling(
+        client.clone(),
+        vec![notes.clone()],
+        dir.path(),
+        no_compaction(),
+        interrupted_history(),
+        PendingToolCalls::Fail,
+    )
+    .await
+    .unwrap();
+
+    let events = harness.startup(&mut Autopilot::approving()).await;
+    assert_eq!(event_names(&events), ["notice", "done"]);
+    assert!(notes.calls().is_empty());
+
+    // The next request carries a history with every tool call answered.
+    harness.turn("try again", &mut Autopilot::approving()).await;
+    let sent = &client.requests()[0].messages;
+    assert_eq!(sent.len(), 4);
+    assert_eq!(
+        tool_result_of(&sent[2]),
+        (INTERRUPTED_RESULT.to_string(), true)
+    );
+
+    let messages = harness.finish().await.unwrap();
+    assert_eq!(
+        tool_result_of(&messages[0]),
+        (INTERRUPTED_RESULT.to_string(), true)
+    );
+}
+
+#[tokio::test]
+async fn mcp_tools_resync_on_the_timer_between_turns() {
+    let dir = tempfile::tempdir().unwrap();
+    let github = FakeMcpClient::new(&["search_issues"]);
+    let server = McpServer::with_link("github", Arc::new(github.clone()))
+        .await
+        .unwrap();
+    let client = Arc::new(MockLlmClient::new(vec![
+        ScriptedResponse::new().text("Before."),
+        ScriptedResponse::new().text("After."),
+    ]));
+    let mut harness = LoopHarness::with_mcp(
+        client.clone(),
+        vec![Arc::new(server)],
+        Duration::from_millis(50),
+        dir.path(),
+    )
+    .await
+    .unwrap();
+
+    harness.turn("hi", &mut Autopilot::approving()).await;
+    github.offer(&["create_pr", "merge_pr"]);
+    let notice = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match harness.next_event().await {
+                Some(AgentEvent::Notice(notice)) => break notice,
+                Some(_) => continue,
+                None => panic!("the loop exited before resyncing"),
+            }
+        }
+    })
+    .await
+    .expect("the resync timer never fired");
+    assert_eq!(notice, "MCP github: +2 tools, -1");
+
+    harness.turn("again", &mut Autopilot::approving()).await;
+    let tools_sent = |request: &Request| -> Vec<String> {
+        let mut names: Vec<String> = request.tools.iter().map(|t| t.name.clone()).collect();
+        names.sort();
+        names
+    };
+    let requests = client.requests();
+    assert_eq!(tools_sent(&requests[0]), ["search_issues"]);
+    assert_eq!(tools_sent(&requests[1]), ["create_pr", "merge_pr"]);
+    harness.finish().await.unwrap();
+}
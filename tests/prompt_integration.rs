@@ -3,6 +3,7 @@
 
 use std::collections::HashMap;
 
+use soloclaw::config::PrivacyConfig;
 use soloclaw::prompt::{ContextFile, SystemPromptParams, build_system_prompt, load_context_files};
 
 fn base_params() -> SystemPromptParams {
@@ -38,10 +39,14 @@ fn base_params() -> SystemPromptParams {
             );
             m
         },
+        tool_origins: HashMap::new(),
+        mcp_descriptions: HashMap::new(),
         workspace_dir: "/home/user/project".to_string(),
         os: "linux".to_string(),
         arch: "x86_64".to_string(),
         shell: "/bin/bash".to_string(),
+        hostname: String::new(),
+        privacy: PrivacyConfig::default(),
         model: "claude-sonnet-4-5-20250929".to_string(),
         context_files: vec![],
         skill_files: vec![],